@@ -0,0 +1,3 @@
+-- Skill-scoped memory: NULL namespace is general memory
+ALTER TABLE memory ADD COLUMN namespace TEXT;
+CREATE INDEX IF NOT EXISTS idx_memory_namespace ON memory(namespace);
@@ -0,0 +1,14 @@
+-- Deliveries: outcome of every outbound send/edit, after retries
+CREATE TABLE deliveries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    channel TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    kind TEXT NOT NULL,            -- send | edit
+    status TEXT NOT NULL,          -- sent | edited | failed | rate_limited
+    attempts INTEGER NOT NULL DEFAULT 1,
+    error TEXT,
+    preview TEXT,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX idx_deliveries_status ON deliveries(status, created_at);
+CREATE INDEX idx_deliveries_session ON deliveries(session_id);
@@ -0,0 +1,13 @@
+-- Background tasks: long prompts run outside the interactive turn
+CREATE TABLE background_tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    task TEXT NOT NULL,
+    status TEXT NOT NULL,          -- pending | running | done | failed
+    result TEXT,
+    created_at INTEGER NOT NULL,
+    started_at INTEGER,
+    finished_at INTEGER
+);
+CREATE INDEX idx_background_tasks_status ON background_tasks(status, id);
+CREATE INDEX idx_background_tasks_session ON background_tasks(session_id, created_at);
@@ -0,0 +1,2 @@
+-- When a memory was last kept or edited in a memory review
+ALTER TABLE memory ADD COLUMN reviewed_at INTEGER;
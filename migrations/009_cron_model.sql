@@ -0,0 +1,4 @@
+-- Per-job model overrides (NULL = use the main agent's)
+ALTER TABLE cron_jobs ADD COLUMN provider TEXT;
+ALTER TABLE cron_jobs ADD COLUMN model TEXT;
+ALTER TABLE cron_jobs ADD COLUMN max_tokens INTEGER;
@@ -0,0 +1,2 @@
+-- Structured context of failed queue entries (JSON, see db::queue::QueueFailure)
+ALTER TABLE queue ADD COLUMN failure TEXT;
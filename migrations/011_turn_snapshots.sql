@@ -0,0 +1,24 @@
+-- What the agent saw on each turn, for `yoclaw debug turn`
+CREATE TABLE turn_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    turn INTEGER NOT NULL,          -- 1-based, per session
+    model TEXT NOT NULL,
+    prompt_hash TEXT NOT NULL,      -- snapshot_blobs.hash of the system prompt
+    tools_hash TEXT NOT NULL,       -- snapshot_blobs.hash of the tool schemas
+    context TEXT NOT NULL,          -- JSON messages sent to the model
+    filters TEXT NOT NULL,          -- JSON: input filters, context limits, compaction, privacy
+    response TEXT,                  -- JSON assistant message
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL
+);
+CREATE UNIQUE INDEX idx_turn_snapshots_turn ON turn_snapshots(session_id, turn);
+CREATE INDEX idx_turn_snapshots_created ON turn_snapshots(created_at);
+
+-- System prompts and tool schemas, stored once per version
+CREATE TABLE snapshot_blobs (
+    hash TEXT PRIMARY KEY,
+    content TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,8 @@
+-- Browsers subscribed to Web Push notifications from the dashboard
+CREATE TABLE push_subscriptions (
+    endpoint TEXT PRIMARY KEY,
+    p256dh TEXT NOT NULL,           -- browser public key (base64url)
+    auth TEXT NOT NULL,             -- auth secret (base64url)
+    topics TEXT NOT NULL,           -- comma-separated: mentions, budget, approvals, jobs
+    created_at INTEGER NOT NULL
+);
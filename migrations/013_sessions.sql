@@ -0,0 +1,15 @@
+-- Sessions: where each session lives on its platform, for delivery routing
+CREATE TABLE sessions (
+    session_id TEXT PRIMARY KEY,
+    channel TEXT NOT NULL,          -- adapter: telegram, discord, slack, ...
+    chat_id TEXT,                   -- platform chat / channel ID
+    thread_id TEXT,                 -- thread within chat_id (Slack thread ts)
+    is_group INTEGER NOT NULL DEFAULT 0,
+    first_seen INTEGER NOT NULL,
+    last_seen INTEGER NOT NULL
+);
+
+-- Sessions seen before this table existed keep their queued channel
+INSERT OR IGNORE INTO sessions (session_id, channel, first_seen, last_seen)
+SELECT session_id, channel, MIN(created_at), MAX(created_at)
+FROM queue GROUP BY session_id;
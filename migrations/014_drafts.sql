@@ -0,0 +1,15 @@
+-- Drafts: replies in reviewed chats, held until a reviewer decides
+CREATE TABLE drafts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    request TEXT NOT NULL,          -- the message the draft answers
+    content TEXT NOT NULL,          -- the agent's reply
+    status TEXT NOT NULL DEFAULT 'pending',  -- pending, approved, edited, rejected
+    sent_content TEXT,              -- what was posted, for approved and edited
+    decided_by TEXT,
+    created_at INTEGER NOT NULL,
+    decided_at INTEGER,
+    applied_at INTEGER              -- when the decision was posted and noted on the tape
+);
+CREATE INDEX idx_drafts_status ON drafts(status);
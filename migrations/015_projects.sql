@@ -0,0 +1,15 @@
+-- Projects (working sets): named collections of memories, files and sessions
+CREATE TABLE projects (
+    name TEXT PRIMARY KEY,
+    description TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE project_items (
+    project TEXT NOT NULL REFERENCES projects(name) ON DELETE CASCADE,
+    kind TEXT NOT NULL,             -- memory, file, session
+    item TEXT NOT NULL,             -- memory ID, file path or session ID
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (project, kind, item)
+);
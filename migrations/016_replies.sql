@@ -0,0 +1,10 @@
+-- Replies: the agent's messages on each platform, for reaction commands
+CREATE TABLE replies (
+    channel TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,       -- platform message ID, unique within the chat
+    content TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (session_id, message_id)
+);
+CREATE INDEX idx_replies_session ON replies(session_id, created_at);
@@ -0,0 +1,7 @@
+-- Tool usage: calls per tool (config name) and UTC day, for daily quotas
+CREATE TABLE tool_usage (
+    tool TEXT NOT NULL,
+    day TEXT NOT NULL,              -- YYYY-MM-DD, UTC
+    calls INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (tool, day)
+);
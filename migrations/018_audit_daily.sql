@@ -0,0 +1,10 @@
+-- Audit rollup: counts of audit entries older than [audit] keep_days, per
+-- UTC day, event type and tool, kept after the entries are deleted
+CREATE TABLE audit_daily (
+    day TEXT NOT NULL,              -- YYYY-MM-DD, UTC
+    event_type TEXT NOT NULL,
+    tool_name TEXT NOT NULL DEFAULT '',
+    events INTEGER NOT NULL DEFAULT 0,
+    tokens_used INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, event_type, tool_name)
+);
@@ -0,0 +1,10 @@
+-- Sender usage: tokens spent answering each member of a group chat per UTC
+-- day, for [agent.budget] max_tokens_per_sender_per_day
+CREATE TABLE sender_usage (
+    day TEXT NOT NULL,              -- YYYY-MM-DD, UTC
+    session_id TEXT NOT NULL,
+    sender_id TEXT NOT NULL,
+    tokens INTEGER NOT NULL DEFAULT 0,
+    notified INTEGER NOT NULL DEFAULT 0, -- 1 once told they hit the cap
+    PRIMARY KEY (day, session_id, sender_id)
+);
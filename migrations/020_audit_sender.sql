@@ -0,0 +1,3 @@
+-- Who each message's model usage is charged to, for per-sender budgets
+ALTER TABLE audit ADD COLUMN sender_id TEXT;
+CREATE INDEX idx_audit_sender ON audit(sender_id);
@@ -0,0 +1,4 @@
+-- Failed messages queued for another attempt ('retry' status) are processed
+-- from retry_at on, so a bulk retry is spread out over time
+ALTER TABLE queue ADD COLUMN retry_at INTEGER;
+CREATE INDEX idx_queue_retry ON queue(status, retry_at);
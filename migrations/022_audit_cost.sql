@@ -0,0 +1,4 @@
+-- Estimated dollars of each turn's model usage, for cost reporting and
+-- [agent.budget] max_cost_per_day
+ALTER TABLE audit ADD COLUMN cost_usd REAL;
+ALTER TABLE audit_daily ADD COLUMN cost_usd REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Files saved for a queued message (JSON, see attachments::SavedAttachment),
+-- so a retried message still has them
+ALTER TABLE queue ADD COLUMN attachments TEXT;
@@ -0,0 +1,19 @@
+-- What worker sub-agents did: their full transcript, linked to the session
+-- and to the tool call that started them
+CREATE TABLE worker_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    tool_call_id TEXT NOT NULL,     -- the parent's call of the worker
+    parent_id INTEGER,              -- worker_runs.id of the worker that started this one
+    worker TEXT NOT NULL,
+    task TEXT NOT NULL,
+    status TEXT NOT NULL,           -- running | done | failed
+    result TEXT,
+    error TEXT,
+    messages TEXT,                  -- JSON messages of the sub-agent
+    tokens INTEGER NOT NULL DEFAULT 0,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER
+);
+CREATE INDEX idx_worker_runs_session ON worker_runs(session_id, started_at);
+CREATE INDEX idx_worker_runs_started ON worker_runs(started_at);
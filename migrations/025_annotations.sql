@@ -0,0 +1,11 @@
+-- Operator labels on tape messages, collected as an eval / tuning dataset
+CREATE TABLE annotations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    message_idx INTEGER NOT NULL,   -- position in the session's tape
+    note TEXT NOT NULL DEFAULT '',
+    tags TEXT NOT NULL DEFAULT '[]', -- JSON array of labels
+    message TEXT NOT NULL,          -- JSON copy of the message, kept across compaction
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX idx_annotations_session ON annotations(session_id, message_idx);
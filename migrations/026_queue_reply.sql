@@ -0,0 +1,4 @@
+-- Reply of a message whose turn is on the tape ('answered' status), saved in
+-- the same transaction as the tape, so a crash before the reply is delivered
+-- neither loses it nor runs the message again
+ALTER TABLE queue ADD COLUMN reply TEXT;
@@ -0,0 +1,120 @@
+-- Shared storage for yoclaw hosts using [persistence] backend = "postgres".
+-- Timestamps are milliseconds since the epoch, as in the SQLite database.
+
+-- Tape: serialized conversation state per session
+CREATE TABLE tape (
+    session_id TEXT PRIMARY KEY,
+    messages_json TEXT NOT NULL,
+    message_count BIGINT NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+-- Queue: inbound messages, owned by the host (node) that received them
+CREATE TABLE queue (
+    id BIGSERIAL PRIMARY KEY,
+    node TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    sender_id TEXT NOT NULL,
+    sender_name TEXT,
+    session_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    reply_to TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    error_msg TEXT,
+    failure TEXT,
+    created_at BIGINT NOT NULL,
+    processed_at BIGINT,
+    retry_at BIGINT,
+    attachments TEXT,
+    reply TEXT
+);
+CREATE INDEX idx_queue_status ON queue(status, node);
+CREATE INDEX idx_queue_created ON queue(created_at);
+
+-- Memory: long-term agent memory with full-text search
+CREATE TABLE memory (
+    id BIGSERIAL PRIMARY KEY,
+    namespace TEXT,
+    key TEXT,
+    content TEXT NOT NULL,
+    tags TEXT,
+    source TEXT,
+    category TEXT NOT NULL DEFAULT 'fact',
+    importance INTEGER NOT NULL DEFAULT 5,
+    last_accessed BIGINT,
+    access_count INTEGER NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    reviewed_at BIGINT,
+    search TSVECTOR GENERATED ALWAYS AS
+        (to_tsvector('simple', content || ' ' || COALESCE(tags, ''))) STORED
+);
+CREATE UNIQUE INDEX idx_memory_key ON memory((COALESCE(namespace, '')), key)
+    WHERE key IS NOT NULL;
+CREATE INDEX idx_memory_search ON memory USING GIN(search);
+CREATE INDEX idx_memory_updated ON memory(updated_at);
+
+-- Audit: security-relevant event log
+CREATE TABLE audit (
+    id BIGSERIAL PRIMARY KEY,
+    session_id TEXT,
+    sender_id TEXT,
+    event_type TEXT NOT NULL,
+    tool_name TEXT,
+    detail TEXT,
+    tokens_used BIGINT NOT NULL DEFAULT 0,
+    cost_usd DOUBLE PRECISION,
+    timestamp BIGINT NOT NULL
+);
+CREATE INDEX idx_audit_session ON audit(session_id);
+CREATE INDEX idx_audit_timestamp ON audit(timestamp);
+
+-- Audit entries rolled up per UTC day, event type and tool ('' for none)
+CREATE TABLE audit_daily (
+    day TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    events BIGINT NOT NULL,
+    tokens_used BIGINT NOT NULL,
+    cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, event_type, tool_name)
+);
+
+-- Cron jobs, their runs and when each last fired
+CREATE TABLE cron_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    schedule TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    target_channel TEXT,
+    session_mode TEXT NOT NULL DEFAULT 'isolated',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    provider TEXT,
+    model TEXT,
+    max_tokens BIGINT,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE TABLE cron_runs (
+    id BIGSERIAL PRIMARY KEY,
+    job_id BIGINT NOT NULL REFERENCES cron_jobs(id) ON DELETE CASCADE,
+    status TEXT NOT NULL,
+    result TEXT,
+    started_at BIGINT NOT NULL,
+    finished_at BIGINT
+);
+CREATE INDEX idx_cron_runs_job ON cron_runs(job_id);
+
+CREATE TABLE cron_fired (
+    name TEXT PRIMARY KEY,
+    fired_at BIGINT NOT NULL
+);
+
+-- Dynamic worker definitions saved for reuse
+CREATE TABLE saved_workers (
+    name TEXT PRIMARY KEY,
+    system_prompt TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,590 @@
+//! Chat-based administration.
+//!
+//! Senders listed in `security.admins` can change a few settings from chat
+//! with `/admin ...`. Budget and tool changes are stored as overrides in the
+//! database and applied on top of config.toml, at startup and after every
+//! reload, so they survive restarts and file edits. Cron jobs already carry
+//! an enabled flag in the database, so toggling them is a direct update.
+//! `/admin pause` and `/admin resume` switch read-only mode like
+//! `yoclaw pause --all`. `/admin skills` (or just `/skills`) lists skills
+//! and toggles them in the system prompt, stored as overrides of
+//! `agent.disabled_skills`; the web API sets the same overrides.
+
+use crate::config::{Config, ToolPermission};
+use crate::db::pause::PAUSE_ALL;
+use crate::db::{Db, DbError};
+use std::collections::HashMap;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdminError {
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Invalid amount '{0}' (expected e.g. 500k, 2M or off)")]
+    InvalidAmount(String),
+    #[error("Invalid tool name '{0}'")]
+    InvalidTool(String),
+    #[error("No cron job named '{0}'")]
+    UnknownJob(String),
+    #[error("No skill named '{0}'")]
+    UnknownSkill(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+const USAGE: &str = "/admin budget set <tokens|off> | /admin budget turns <n|off> \
+                     | /admin tool <enable|disable> <name> | /admin cron <enable|disable> <name> \
+                     | /admin skills [list] | /admin skills <enable|disable> <name> \
+                     | /admin pause [reason] | /admin resume | /admin status";
+
+const KEY_DAILY_TOKENS: &str = "agent.budget.max_tokens_per_day";
+const KEY_SESSION_TURNS: &str = "agent.budget.max_turns_per_session";
+
+/// A parsed `/admin` command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminCommand {
+    /// Daily token budget (None removes the limit).
+    BudgetTokens(Option<u64>),
+    /// Turns per session (None removes the limit).
+    BudgetTurns(Option<u64>),
+    Tool {
+        name: String,
+        enabled: bool,
+    },
+    Cron {
+        name: String,
+        enabled: bool,
+    },
+    /// List skills with their state.
+    Skills,
+    Skill {
+        name: String,
+        enabled: bool,
+    },
+    /// Read-only mode for the whole daemon, with an optional reason.
+    Pause(Option<String>),
+    Resume,
+    Status,
+}
+
+impl std::fmt::Display for AdminCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let limit = |v: &Option<u64>| v.map_or("off".to_string(), |n| n.to_string());
+        match self {
+            AdminCommand::BudgetTokens(v) => write!(f, "budget set {}", limit(v)),
+            AdminCommand::BudgetTurns(v) => write!(f, "budget turns {}", limit(v)),
+            AdminCommand::Tool { name, enabled } => {
+                write!(f, "tool {} {}", toggle_word(*enabled), name)
+            }
+            AdminCommand::Cron { name, enabled } => {
+                write!(f, "cron {} {}", toggle_word(*enabled), name)
+            }
+            AdminCommand::Skills => write!(f, "skills list"),
+            AdminCommand::Skill { name, enabled } => {
+                write!(f, "skills {} {}", toggle_word(*enabled), name)
+            }
+            AdminCommand::Pause(_) => write!(f, "pause"),
+            AdminCommand::Resume => write!(f, "resume"),
+            AdminCommand::Status => write!(f, "status"),
+        }
+    }
+}
+
+/// Result of running an admin command.
+pub struct AdminOutcome {
+    /// Confirmation to send back to the admin.
+    pub reply: String,
+    /// Updated config to hot-reload, if the command changed it.
+    pub config: Option<Config>,
+}
+
+/// Parse `/admin ...` or `/skills ...`. Returns None if `text` isn't an
+/// admin command.
+pub fn parse(text: &str) -> Option<Result<AdminCommand, AdminError>> {
+    let mut words = text.split_whitespace();
+    let mut args: Vec<&str> = match words.next() {
+        Some("/admin") => Vec::new(),
+        Some("/skills") => vec!["skills"],
+        _ => return None,
+    };
+    args.extend(words);
+    Some(parse_args(&args))
+}
+
+fn parse_args(args: &[&str]) -> Result<AdminCommand, AdminError> {
+    match args {
+        ["budget", "set", amount] => Ok(AdminCommand::BudgetTokens(parse_amount(amount)?)),
+        ["budget", "turns", amount] => Ok(AdminCommand::BudgetTurns(parse_amount(amount)?)),
+        ["tool", action, name] => Ok(AdminCommand::Tool {
+            name: parse_tool_name(name)?,
+            enabled: parse_toggle(action)?,
+        }),
+        ["cron", action, name] => Ok(AdminCommand::Cron {
+            name: name.to_string(),
+            enabled: parse_toggle(action)?,
+        }),
+        ["skills"] | ["skills", "list"] => Ok(AdminCommand::Skills),
+        ["skills", action, name] => Ok(AdminCommand::Skill {
+            name: name.to_string(),
+            enabled: parse_toggle(action)?,
+        }),
+        ["pause", reason @ ..] => Ok(AdminCommand::Pause(
+            Some(reason.join(" ")).filter(|r| !r.is_empty()),
+        )),
+        ["resume"] => Ok(AdminCommand::Resume),
+        ["status"] | [] => Ok(AdminCommand::Status),
+        _ => Err(AdminError::Usage(USAGE)),
+    }
+}
+
+fn parse_toggle(action: &str) -> Result<bool, AdminError> {
+    match action {
+        "enable" => Ok(true),
+        "disable" => Ok(false),
+        _ => Err(AdminError::Usage(USAGE)),
+    }
+}
+
+fn parse_tool_name(name: &str) -> Result<String, AdminError> {
+    if name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(name.to_string())
+    } else {
+        Err(AdminError::InvalidTool(name.to_string()))
+    }
+}
+
+/// Parse "2M", "500k", "1500" or "off".
+fn parse_amount(s: &str) -> Result<Option<u64>, AdminError> {
+    if s.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+    let (digits, multiplier) = match s.chars().last().map(|c| c.to_ascii_lowercase()) {
+        Some('k') => (&s[..s.len() - 1], 1_000.0),
+        Some('m') => (&s[..s.len() - 1], 1_000_000.0),
+        _ => (s, 1.0),
+    };
+    match digits.parse::<f64>() {
+        Ok(n) if n.is_finite() && n > 0.0 => Ok(Some((n * multiplier).round() as u64)),
+        _ => Err(AdminError::InvalidAmount(s.to_string())),
+    }
+}
+
+/// Whether `sender_id` on `channel` may run admin commands.
+pub fn is_admin(config: &Config, channel: &str, sender_id: &str) -> bool {
+    let id = format!("{}:{}", channel, sender_id);
+    config.security.admins.contains(&id)
+}
+
+/// Apply stored overrides on top of `config`. Unknown keys are skipped.
+pub fn apply_overrides(config: &mut Config, overrides: &[(String, String)]) {
+    for (key, value) in overrides {
+        if let Some(skill) = skill_key(key) {
+            let disabled = &mut config.agent.disabled_skills;
+            disabled.retain(|s| s != skill);
+            if value != "true" {
+                disabled.push(skill.to_string());
+            }
+            continue;
+        }
+        let limit = if value == "off" {
+            None
+        } else {
+            value.parse::<u64>().ok()
+        };
+        match key.as_str() {
+            KEY_DAILY_TOKENS => config.agent.budget.max_tokens_per_day = limit,
+            KEY_SESSION_TURNS => {
+                config.agent.budget.max_turns_per_session = limit.map(|n| n as usize)
+            }
+            _ => match tool_key(key) {
+                Some(tool) => {
+                    let enabled = value == "true";
+                    config
+                        .security
+                        .tools
+                        .entry(tool.to_string())
+                        .or_insert_with(|| ToolPermission {
+                            enabled,
+                            allowed_paths: Vec::new(),
+                            allowed_hosts: Vec::new(),
+                            requires_approval: false,
+                            profile: None,
+                            max_calls_per_day: None,
+                            env: None,
+                        })
+                        .enabled = enabled;
+                }
+                None => tracing::warn!("Ignoring unknown config override '{}'", key),
+            },
+        }
+    }
+}
+
+/// "security.tools.<name>.enabled" → name
+fn tool_key(key: &str) -> Option<&str> {
+    key.strip_prefix("security.tools.")?
+        .strip_suffix(".enabled")
+}
+
+/// "skills.<name>.enabled" → name
+fn skill_key(key: &str) -> Option<&str> {
+    key.strip_prefix("skills.")?.strip_suffix(".enabled")
+}
+
+/// Skills enabled or disabled by an override, from the stored overrides.
+pub fn skill_overrides(overrides: &[(String, String)]) -> HashMap<String, bool> {
+    overrides
+        .iter()
+        .filter_map(|(key, value)| Some((skill_key(key)?.to_string(), value == "true")))
+        .collect()
+}
+
+/// Skills of `config` with their state, overrides in `db` included.
+pub async fn skill_statuses(
+    db: &Db,
+    config: &Config,
+) -> Result<Vec<crate::skills::SkillStatus>, DbError> {
+    let overrides = skill_overrides(&db.override_list().await?);
+    Ok(crate::skills::statuses(
+        crate::skills::load_configured(config),
+        &config.agent.disabled_skills,
+        &overrides,
+    ))
+}
+
+/// Store an override enabling or disabling skill `name`. Returns the
+/// override as (key, value) for [`apply_overrides`].
+pub async fn set_skill(
+    db: &Db,
+    config: &Config,
+    name: &str,
+    enabled: bool,
+) -> Result<(String, String), AdminError> {
+    let known = crate::skills::load_configured(config)
+        .iter()
+        .any(|s| s.manifest.name == name);
+    if !known {
+        return Err(AdminError::UnknownSkill(name.to_string()));
+    }
+    let (key, value) = (format!("skills.{}.enabled", name), enabled.to_string());
+    db.override_set(&key, &value).await?;
+    Ok((key, value))
+}
+
+/// Run an admin command against the running `config`.
+pub async fn execute(
+    cmd: &AdminCommand,
+    db: &Db,
+    config: &Config,
+) -> Result<AdminOutcome, AdminError> {
+    let (key, value) = match cmd {
+        AdminCommand::BudgetTokens(v) => (
+            KEY_DAILY_TOKENS.to_string(),
+            v.map_or("off".to_string(), |n| n.to_string()),
+        ),
+        AdminCommand::BudgetTurns(v) => (
+            KEY_SESSION_TURNS.to_string(),
+            v.map_or("off".to_string(), |n| n.to_string()),
+        ),
+        AdminCommand::Tool { name, enabled } => (
+            format!("security.tools.{}.enabled", name),
+            enabled.to_string(),
+        ),
+        AdminCommand::Cron { name, enabled } => {
+            return match crate::scheduler::cron::toggle_job(db, name, *enabled).await? {
+                Some(_) => Ok(AdminOutcome {
+                    reply: format!("Cron job '{}' {}d.", name, toggle_word(*enabled)),
+                    config: None,
+                }),
+                None => Err(AdminError::UnknownJob(name.clone())),
+            };
+        }
+        AdminCommand::Skills => {
+            let skills = skill_statuses(db, config).await?;
+            return Ok(AdminOutcome {
+                reply: format!(
+                    "Skills ({}):\n{}",
+                    skills.len(),
+                    crate::skills::format_skills_info(&skills)
+                ),
+                config: None,
+            });
+        }
+        AdminCommand::Skill { name, enabled } => {
+            let (key, value) = set_skill(db, config, name, *enabled).await?;
+            let mut new_config = config.clone();
+            apply_overrides(&mut new_config, &[(key, value)]);
+            return Ok(AdminOutcome {
+                reply: format!(
+                    "Skill '{}' {}d. It takes effect from the next message.",
+                    name,
+                    toggle_word(*enabled)
+                ),
+                config: Some(new_config),
+            });
+        }
+        AdminCommand::Pause(reason) => {
+            db.pause_set(PAUSE_ALL, "admin", reason.as_deref()).await?;
+            return Ok(AdminOutcome {
+                reply: "Paused. Messages are queued until /admin resume.".to_string(),
+                config: None,
+            });
+        }
+        AdminCommand::Resume => {
+            let reply = if db.pause_clear(PAUSE_ALL).await? {
+                "Resumed. Queued messages are processed now."
+            } else {
+                "Not paused."
+            };
+            return Ok(AdminOutcome {
+                reply: reply.to_string(),
+                config: None,
+            });
+        }
+        AdminCommand::Status => {
+            let overrides = db.override_list().await?;
+            let mut reply = if overrides.is_empty() {
+                "No admin overrides set.".to_string()
+            } else {
+                let lines: Vec<String> = overrides
+                    .iter()
+                    .map(|(k, v)| format!("{} = {}", k, v))
+                    .collect();
+                format!("Admin overrides:\n{}", lines.join("\n"))
+            };
+            if let Some(pause) = db.pause_check(None).await? {
+                reply.push_str(&format!("\nPaused by {}", pause.by));
+                if let Some(reason) = pause.reason {
+                    reply.push_str(&format!(": {}", reason));
+                }
+            }
+            return Ok(AdminOutcome {
+                reply,
+                config: None,
+            });
+        }
+    };
+
+    db.override_set(&key, &value).await?;
+    let mut new_config = config.clone();
+    apply_overrides(&mut new_config, &[(key.clone(), value.clone())]);
+    Ok(AdminOutcome {
+        reply: format!("Set {} = {}", key, value),
+        config: Some(new_config),
+    })
+}
+
+fn toggle_word(enabled: bool) -> &'static str {
+    if enabled {
+        "enable"
+    } else {
+        "disable"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn config() -> Config {
+        parse_config(
+            r#"
+[agent]
+model = "test"
+api_key = "key"
+
+[agent.budget]
+max_tokens_per_day = 100000
+
+[security]
+admins = ["telegram:42"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert!(parse("hello").is_none());
+        assert!(parse("/administrator").is_none());
+        assert_eq!(
+            parse("/admin budget set 2M").unwrap().unwrap(),
+            AdminCommand::BudgetTokens(Some(2_000_000))
+        );
+        assert_eq!(
+            parse("/admin budget set 1.5k").unwrap().unwrap(),
+            AdminCommand::BudgetTokens(Some(1500))
+        );
+        assert_eq!(
+            parse("/admin budget turns off").unwrap().unwrap(),
+            AdminCommand::BudgetTurns(None)
+        );
+        assert_eq!(
+            parse("/admin tool disable shell").unwrap().unwrap(),
+            AdminCommand::Tool {
+                name: "shell".into(),
+                enabled: false
+            }
+        );
+        assert_eq!(
+            parse("/admin cron enable morning-briefing")
+                .unwrap()
+                .unwrap(),
+            AdminCommand::Cron {
+                name: "morning-briefing".into(),
+                enabled: true
+            }
+        );
+        assert_eq!(
+            parse("/skills disable weather").unwrap().unwrap(),
+            AdminCommand::Skill {
+                name: "weather".into(),
+                enabled: false
+            }
+        );
+        assert_eq!(
+            parse("/admin skills list").unwrap().unwrap(),
+            AdminCommand::Skills
+        );
+        assert_eq!(parse("/skills").unwrap().unwrap(), AdminCommand::Skills);
+        assert!(parse("/skillset").is_none());
+        assert_eq!(parse("/admin").unwrap().unwrap(), AdminCommand::Status);
+        assert_eq!(
+            parse("/admin pause provider outage").unwrap().unwrap(),
+            AdminCommand::Pause(Some("provider outage".into()))
+        );
+        assert_eq!(
+            parse("/admin pause").unwrap().unwrap(),
+            AdminCommand::Pause(None)
+        );
+        assert_eq!(
+            parse("/admin resume").unwrap().unwrap(),
+            AdminCommand::Resume
+        );
+
+        assert!(matches!(
+            parse("/admin budget set lots").unwrap(),
+            Err(AdminError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            parse("/admin budget set -5").unwrap(),
+            Err(AdminError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            parse("/admin tool remove shell").unwrap(),
+            Err(AdminError::Usage(_))
+        ));
+        assert!(matches!(
+            parse("/admin tool disable sh;ell").unwrap(),
+            Err(AdminError::InvalidTool(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_admin() {
+        let config = config();
+        assert!(is_admin(&config, "telegram", "42"));
+        assert!(!is_admin(&config, "discord", "42"));
+        assert!(!is_admin(&config, "telegram", "43"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_persists_overrides() {
+        let db = Db::open_memory().unwrap();
+        let config = config();
+
+        let cmd = parse("/admin budget set 2M").unwrap().unwrap();
+        let outcome = execute(&cmd, &db, &config).await.unwrap();
+        let new_config = outcome.config.unwrap();
+        assert_eq!(new_config.agent.budget.max_tokens_per_day, Some(2_000_000));
+
+        let cmd = parse("/admin tool disable shell").unwrap().unwrap();
+        let outcome = execute(&cmd, &db, &new_config).await.unwrap();
+        let new_config = outcome.config.unwrap();
+        assert!(!new_config.security.tools["shell"].enabled);
+
+        // A fresh config picks up both overrides from the database
+        let mut reloaded = self::config();
+        apply_overrides(&mut reloaded, &db.override_list().await.unwrap());
+        assert_eq!(reloaded, new_config);
+
+        let cmd = parse("/admin cron disable missing").unwrap().unwrap();
+        assert!(matches!(
+            execute(&cmd, &db, &config).await,
+            Err(AdminError::UnknownJob(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_skills() {
+        let db = Db::open_memory().unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        for name in ["weather", "coding"] {
+            let dir = tmp.path().join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                dir.join("SKILL.md"),
+                format!("---\nname: {}\ndescription: Does {}\n---\n", name, name),
+            )
+            .unwrap();
+        }
+        let mut config = config();
+        config.agent.skills_dirs = vec![tmp.path().display().to_string()];
+        config.agent.disabled_skills = vec!["coding".into()];
+
+        let cmd = parse("/skills enable coding").unwrap().unwrap();
+        let new_config = execute(&cmd, &db, &config).await.unwrap().config.unwrap();
+        assert!(new_config.agent.disabled_skills.is_empty());
+        let cmd = parse("/skills disable weather").unwrap().unwrap();
+        let new_config = execute(&cmd, &db, &new_config)
+            .await
+            .unwrap()
+            .config
+            .unwrap();
+        assert_eq!(
+            new_config.agent.disabled_skills,
+            vec!["weather".to_string()]
+        );
+
+        let list = execute(&AdminCommand::Skills, &db, &new_config)
+            .await
+            .unwrap()
+            .reply;
+        assert!(list.contains("coding — Does coding (tools: none) [enabled, override]"));
+        assert!(list.contains("weather — Does weather (tools: none) [disabled, override]"));
+
+        let mut reloaded = config.clone();
+        apply_overrides(&mut reloaded, &db.override_list().await.unwrap());
+        assert_eq!(reloaded, new_config);
+
+        let cmd = parse("/skills disable missing").unwrap().unwrap();
+        assert!(matches!(
+            execute(&cmd, &db, &config).await,
+            Err(AdminError::UnknownSkill(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute_pause_and_resume() {
+        let db = Db::open_memory().unwrap();
+        let config = config();
+
+        let cmd = parse("/admin pause suspected leak").unwrap().unwrap();
+        let outcome = execute(&cmd, &db, &config).await.unwrap();
+        assert!(outcome.config.is_none());
+        let pause = db.pause_check(Some("tg-1")).await.unwrap().unwrap();
+        assert_eq!(pause.reason.as_deref(), Some("suspected leak"));
+
+        let status = execute(&AdminCommand::Status, &db, &config).await.unwrap();
+        assert!(status.reply.ends_with("Paused by admin: suspected leak"));
+
+        let outcome = execute(&AdminCommand::Resume, &db, &config).await.unwrap();
+        assert!(outcome.reply.starts_with("Resumed"));
+        assert_eq!(db.pause_check(Some("tg-1")).await.unwrap(), None);
+        let outcome = execute(&AdminCommand::Resume, &db, &config).await.unwrap();
+        assert_eq!(outcome.reply, "Not paused.");
+    }
+}
@@ -0,0 +1,225 @@
+//! Images and files sent with incoming messages.
+//!
+//! Adapters download what users send into [`Attachment`]s. Before a message
+//! is queued, its attachments are saved under the session's scratch
+//! directory (`[attachments] dir`, one subdirectory per session), so the
+//! agent can read them with its file tools and a failed message can be
+//! retried with them. When the message is answered, images go to the model
+//! as image blocks and other files are listed in the text with their path.
+
+use crate::channels::Attachment;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use yoagent::Content;
+
+/// Media types sent to the model as image blocks.
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Whether files of `mime_type` can be shown to the model as images.
+pub fn is_image(mime_type: &str) -> bool {
+    IMAGE_TYPES.contains(&mime_type)
+}
+
+/// An attachment saved to the session's scratch directory. Recorded with the
+/// queue entry of its message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedAttachment {
+    pub name: String,
+    pub mime_type: String,
+    pub path: PathBuf,
+    pub size: u64,
+    #[serde(default)]
+    pub voice: bool,
+}
+
+/// Name for a file sent without one, from its media type.
+pub fn default_name(mime_type: &str) -> String {
+    let ext = match mime_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        _ => "bin",
+    };
+    format!("attachment.{}", ext)
+}
+
+/// Directory the files of `session_id` are saved in.
+pub fn session_dir(dir: &Path, session_id: &str) -> PathBuf {
+    dir.join(sanitize(session_id))
+}
+
+/// Save `attachments` to the scratch directory of `session_id`. A name
+/// already taken gets a numbered suffix, so earlier files are kept.
+pub fn save(
+    dir: &Path,
+    session_id: &str,
+    attachments: &[Attachment],
+) -> std::io::Result<Vec<SavedAttachment>> {
+    if attachments.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dir = session_dir(dir, session_id);
+    std::fs::create_dir_all(&dir)?;
+    let mut saved = Vec::with_capacity(attachments.len());
+    for attachment in attachments {
+        let path = unique_path(&dir, &sanitize(&attachment.name));
+        std::fs::write(&path, &attachment.data)?;
+        saved.push(SavedAttachment {
+            name: attachment.name.clone(),
+            mime_type: attachment.mime_type.clone(),
+            path,
+            size: attachment.data.len() as u64,
+            voice: attachment.voice,
+        });
+    }
+    Ok(saved)
+}
+
+/// What the model is given for `saved`: a note listing the files it can
+/// read, appended to the message text, and image blocks. With `vision` off,
+/// images are listed like other files.
+pub fn prompt_parts(saved: &[SavedAttachment], vision: bool) -> (Option<String>, Vec<Content>) {
+    let mut files = Vec::new();
+    let mut images = Vec::new();
+    for attachment in saved {
+        if vision && is_image(&attachment.mime_type) {
+            match std::fs::read(&attachment.path) {
+                Ok(data) => {
+                    images.push(Content::Image {
+                        data: STANDARD.encode(data),
+                        mime_type: attachment.mime_type.clone(),
+                    });
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to read {}: {}", attachment.path.display(), e);
+                }
+            }
+        }
+        files.push(format!(
+            "- {} ({}, {} bytes): {}",
+            attachment.name,
+            attachment.mime_type,
+            attachment.size,
+            attachment.path.display()
+        ));
+    }
+    let note = (!files.is_empty()).then(|| {
+        format!(
+            "[The user attached files, saved for you to read:\n{}]",
+            files.join("\n")
+        )
+    });
+    (note, images)
+}
+
+/// Keep a name to one path component of safe characters.
+fn sanitize(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let clean: String = base
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let clean = clean.trim_start_matches('.');
+    if clean.is_empty() {
+        "attachment".to_string()
+    } else {
+        clean.to_string()
+    }
+}
+
+/// `dir/name`, or `dir/stem-N.ext` for the first N not taken.
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{}-{}{}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(name: &str, mime_type: &str) -> Attachment {
+        Attachment {
+            name: name.into(),
+            mime_type: mime_type.into(),
+            data: b"data".to_vec(),
+            voice: false,
+        }
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("report.pdf"), "report.pdf");
+        assert_eq!(sanitize("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize("my file (1).txt"), "my_file__1_.txt");
+        assert_eq!(sanitize(".hidden"), "hidden");
+        assert_eq!(sanitize(""), "attachment");
+        assert_eq!(sanitize("slack:C1:123.45"), "slack_C1_123.45");
+    }
+
+    #[test]
+    fn test_save_keeps_earlier_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let files = [
+            attachment("notes.txt", "text/plain"),
+            attachment("notes.txt", "text/plain"),
+        ];
+        let saved = save(tmp.path(), "tg-1", &files).unwrap();
+        let dir = tmp.path().join("tg-1");
+        assert_eq!(saved[0].path, dir.join("notes.txt"));
+        assert_eq!(saved[1].path, dir.join("notes-1.txt"));
+        assert_eq!(saved[1].size, 4);
+        assert_eq!(std::fs::read(&saved[1].path).unwrap(), b"data");
+        assert!(save(tmp.path(), "tg-2", &[]).unwrap().is_empty());
+        assert!(!tmp.path().join("tg-2").exists());
+    }
+
+    #[test]
+    fn test_prompt_parts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let files = [
+            attachment("photo.png", "image/png"),
+            attachment("report.pdf", "application/pdf"),
+        ];
+        let saved = save(tmp.path(), "tg-1", &files).unwrap();
+
+        let (note, images) = prompt_parts(&saved, true);
+        let note = note.unwrap();
+        assert!(note.contains("report.pdf (application/pdf, 4 bytes)"));
+        assert!(!note.contains("photo.png"));
+        assert_eq!(
+            images,
+            vec![Content::Image {
+                data: STANDARD.encode(b"data"),
+                mime_type: "image/png".into(),
+            }]
+        );
+
+        let (note, images) = prompt_parts(&saved, false);
+        assert!(note.unwrap().contains("photo.png"));
+        assert!(images.is_empty());
+
+        assert_eq!(prompt_parts(&[], true), (None, Vec::new()));
+    }
+}
@@ -0,0 +1,755 @@
+//! Backup and restore of the full yoclaw state.
+//!
+//! A backup is a gzipped tar archive holding `manifest.json` followed by the
+//! config, a consistent copy of the database, the persona file, skill
+//! directories and saved uploads. The manifest records where each entry is
+//! restored: relative to the config directory when it lived there, so an
+//! archive can move between machines, or as an absolute path otherwise.
+//!
+//! Entry kinds unknown to this build are skipped on restore; a newer archive
+//! format or database schema is refused instead of half-restored.
+//!
+//! Database snapshots are plain SQLite files, written with the online backup
+//! API so the daemon keeps running. `[persistence.backup]` takes one every
+//! `interval_hours` into the backup directory as `yoclaw-<UTC time>.db`, and
+//! keeps the newest `keep`.
+
+use crate::config::{BackupConfig, Config};
+use crate::db::{now_ms, Db, DbError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Archive layout version written by this build.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Replaces secrets in a redacted config.
+pub const REDACTED: &str = "<redacted>";
+
+const MANIFEST: &str = "manifest.json";
+
+/// Name of snapshots in the backup directory, after `yoclaw-`.
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database error: {0}")]
+    Db(#[from] DbError),
+    #[error("Invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("Invalid config: {0}")]
+    Config(String),
+    #[error("Not a complete yoclaw backup: {0} is missing")]
+    Missing(PathBuf),
+    #[error("Backup format {0} is newer than this yoclaw supports ({FORMAT_VERSION}); upgrade to restore it")]
+    NewerFormat(u32),
+    #[error("Database schema {found} is newer than this yoclaw supports ({supported}); upgrade to restore it")]
+    NewerSchema { found: i64, supported: i64 },
+    #[error("{0} already exists; use --force to overwrite")]
+    Exists(PathBuf),
+    #[error("{0} is not an unencrypted SQLite database")]
+    NotPlain(PathBuf),
+}
+
+/// Describes a backup archive.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub yoclaw_version: String,
+    pub created_at: u64,
+    /// Database schema version at backup time.
+    pub schema_version: i64,
+    /// Whether secrets were removed from the config.
+    pub redacted: bool,
+    pub entries: Vec<Entry>,
+}
+
+/// One file or directory in the archive.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Entry {
+    /// `config`, `database`, `persona`, `skills` or `media`.
+    pub kind: String,
+    /// Path inside the archive.
+    pub archive_path: String,
+    /// Restore location: relative to the config directory, or absolute.
+    pub restore_to: String,
+}
+
+/// Write a backup of the installation configured by `config_path` to `output`.
+pub async fn create(
+    config_path: &Path,
+    config: &Config,
+    db: &Db,
+    output: &Path,
+    redact: bool,
+) -> Result<Manifest, BackupError> {
+    let base = base_dir(config_path);
+    let mut entries = vec![
+        Entry::new("config", "config.toml", config_path, &base),
+        Entry::new("database", "yoclaw.db", &config.db_path(), &base),
+    ];
+    let persona = config.persona_path();
+    if persona.is_file() {
+        entries.push(Entry::new("persona", "persona.md", &persona, &base));
+    }
+    for (i, dir) in config.skills_dirs().iter().enumerate() {
+        if dir.is_dir() {
+            let name = format!("skills/{}", i);
+            entries.push(Entry::new("skills", &name, dir, &base));
+        }
+    }
+    let uploads = config.uploads_dir();
+    if uploads.is_dir() {
+        entries.push(Entry::new("media", "uploads", &uploads, &base));
+    }
+
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        yoclaw_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: now_ms(),
+        schema_version: db.schema_version().await?,
+        redacted: redact,
+        entries,
+    };
+
+    // The live database may be in use; archive a consistent copy
+    let db_copy = std::env::temp_dir().join(format!("yoclaw-backup-{}.db", now_ms()));
+    db.backup_to(&db_copy).await?;
+    let result = write_archive(&manifest, config_path, &db_copy, &base, output, redact);
+    let _ = std::fs::remove_file(&db_copy);
+    result?;
+    Ok(manifest)
+}
+
+fn write_archive(
+    manifest: &Manifest,
+    config_path: &Path,
+    db_copy: &Path,
+    base: &Path,
+    output: &Path,
+    redact: bool,
+) -> Result<(), BackupError> {
+    let file = std::fs::File::create(output)?;
+    let gz = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut tar = tar::Builder::new(gz);
+
+    append_bytes(&mut tar, MANIFEST, &serde_json::to_vec_pretty(manifest)?)?;
+    for entry in &manifest.entries {
+        match entry.kind.as_str() {
+            "config" => {
+                let raw = std::fs::read_to_string(config_path)?;
+                let raw = if redact { redact_config(&raw)? } else { raw };
+                append_bytes(&mut tar, &entry.archive_path, raw.as_bytes())?;
+            }
+            "database" => tar.append_path_with_name(db_copy, &entry.archive_path)?,
+            _ => {
+                let source = entry.source(base);
+                if source.is_dir() {
+                    tar.append_dir_all(&entry.archive_path, &source)?;
+                } else {
+                    tar.append_path_with_name(&source, &entry.archive_path)?;
+                }
+            }
+        }
+    }
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Restore `archive` into the installation configured by `config_path`.
+/// Existing files are only replaced with `force`.
+pub fn restore(archive: &Path, config_path: &Path, force: bool) -> Result<Manifest, BackupError> {
+    let base = base_dir(config_path);
+    std::fs::create_dir_all(&base)?;
+    let staging = base.join(format!(".restore-{}", now_ms()));
+    let result = restore_staged(archive, config_path, &base, &staging, force);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn restore_staged(
+    archive: &Path,
+    config_path: &Path,
+    base: &Path,
+    staging: &Path,
+    force: bool,
+) -> Result<Manifest, BackupError> {
+    let file = std::fs::File::open(archive)?;
+    tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(staging)?;
+
+    let manifest_path = staging.join(MANIFEST);
+    if !manifest_path.is_file() {
+        return Err(BackupError::Missing(PathBuf::from(MANIFEST)));
+    }
+    let manifest: Manifest = serde_json::from_slice(&std::fs::read(&manifest_path)?)?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(BackupError::NewerFormat(manifest.format_version));
+    }
+    let supported = Db::latest_schema_version();
+    if manifest.schema_version > supported {
+        return Err(BackupError::NewerSchema {
+            found: manifest.schema_version,
+            supported,
+        });
+    }
+
+    // Where each known entry goes; the config follows --config, not the archive
+    let mut targets = Vec::new();
+    for entry in &manifest.entries {
+        let target = match entry.kind.as_str() {
+            "config" => config_path.to_path_buf(),
+            "database" | "persona" | "skills" | "media" => entry.source(base),
+            other => {
+                tracing::warn!(
+                    "Skipping unknown backup entry '{}' ({})",
+                    entry.archive_path,
+                    other
+                );
+                continue;
+            }
+        };
+        let source = staging.join(&entry.archive_path);
+        if !source.exists() {
+            return Err(BackupError::Missing(PathBuf::from(&entry.archive_path)));
+        }
+        if !force && matches!(entry.kind.as_str(), "config" | "database") && target.exists() {
+            return Err(BackupError::Exists(target));
+        }
+        // An encrypted database can't be read without its key; the
+        // manifest's schema version was checked above
+        if entry.kind == "database" && is_database(&source)? {
+            let found = Db::file_schema_version(&source, None)?;
+            if found > supported {
+                return Err(BackupError::NewerSchema { found, supported });
+            }
+        }
+        targets.push((entry, source, target));
+    }
+
+    for (entry, source, target) in targets {
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if entry.kind == "database" {
+            // Stale WAL files would be replayed over the restored database
+            for suffix in ["-wal", "-shm"] {
+                let mut sidecar = target.clone().into_os_string();
+                sidecar.push(suffix);
+                let _ = std::fs::remove_file(PathBuf::from(sidecar));
+            }
+        }
+        copy_all(&source, &target)?;
+    }
+    Ok(manifest)
+}
+
+impl Entry {
+    fn new(kind: &str, archive_path: &str, path: &Path, base: &Path) -> Self {
+        let restore_to = match path.strip_prefix(base) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => path.to_string_lossy().to_string(),
+        };
+        Self {
+            kind: kind.to_string(),
+            archive_path: archive_path.to_string(),
+            restore_to,
+        }
+    }
+
+    /// Location of this entry on disk, given the config directory.
+    fn source(&self, base: &Path) -> PathBuf {
+        let path = Path::new(&self.restore_to);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            base.join(path)
+        }
+    }
+}
+
+/// Directory holding the config file.
+fn base_dir(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn append_bytes<W: std::io::Write>(
+    tar: &mut tar::Builder<W>,
+    path: &str,
+    bytes: &[u8],
+) -> Result<(), BackupError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(now_ms() / 1000);
+    header.set_cksum();
+    tar.append_data(&mut header, path, bytes)?;
+    Ok(())
+}
+
+/// Copy a file, or a directory recursively, over `target`.
+fn copy_all(source: &Path, target: &Path) -> Result<(), std::io::Error> {
+    if source.is_dir() {
+        std::fs::create_dir_all(target)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(source, target).map(|_| ())
+    }
+}
+
+/// File name of a database snapshot taken at `ms`, e.g.
+/// `yoclaw-20261016-093000.db`.
+pub fn snapshot_name(ms: u64) -> String {
+    let time = chrono::DateTime::from_timestamp_millis(ms as i64).unwrap_or_default();
+    format!("yoclaw-{}.db", time.format(SNAPSHOT_TIME_FORMAT))
+}
+
+/// When the snapshot named `name` was taken, if it is one.
+fn snapshot_time(name: &str) -> Option<u64> {
+    let stamp = name.strip_prefix("yoclaw-")?.strip_suffix(".db")?;
+    let time = chrono::NaiveDateTime::parse_from_str(stamp, SNAPSHOT_TIME_FORMAT).ok()?;
+    Some(time.and_utc().timestamp_millis() as u64)
+}
+
+/// Write a consistent copy of the database to `output`, which must not
+/// exist. The copy is written beside it and renamed, so a file at `output`
+/// is always complete.
+pub async fn snapshot(db: &Db, output: &Path) -> Result<(), BackupError> {
+    if output.exists() {
+        return Err(BackupError::Exists(output.to_path_buf()));
+    }
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = partial_path(output);
+    if let Err(e) = db.backup_to(&partial).await {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    std::fs::rename(&partial, output)?;
+    Ok(())
+}
+
+/// `path` with `.partial` appended, where a file is written before it is
+/// renamed into place.
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+/// Whether `path` starts with `magic`.
+fn has_magic(path: &Path, magic: &[u8]) -> Result<bool, std::io::Error> {
+    use std::io::Read;
+    let mut header = vec![0u8; magic.len()];
+    let mut file = std::fs::File::open(path)?;
+    Ok(file.read_exact(&mut header).is_ok() && header == magic)
+}
+
+/// Whether `path` is an unencrypted SQLite database. Encrypted ones can't be
+/// told from random data.
+pub fn is_database(path: &Path) -> Result<bool, std::io::Error> {
+    has_magic(path, b"SQLite format 3\0")
+}
+
+/// Whether `path` is a gzipped backup archive rather than a database copy.
+pub fn is_archive(path: &Path) -> Result<bool, std::io::Error> {
+    has_magic(path, &[0x1f, 0x8b])
+}
+
+/// Replace the database at `db_path` with the snapshot at `source`, through
+/// the online backup API. Both are unlocked with `key` if the database is
+/// encrypted. An existing database is only replaced with `force`. Returns
+/// the snapshot's schema version.
+pub fn restore_database(
+    source: &Path,
+    db_path: &Path,
+    key: Option<&str>,
+    force: bool,
+) -> Result<i64, BackupError> {
+    let supported = Db::latest_schema_version();
+    let found = Db::file_schema_version(source, key)?;
+    if found > supported {
+        return Err(BackupError::NewerSchema { found, supported });
+    }
+    if !force && db_path.exists() {
+        return Err(BackupError::Exists(db_path.to_path_buf()));
+    }
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let src = crate::db::connect(source, rusqlite::OpenFlags::default(), key)?;
+    let mut dst = crate::db::connect(db_path, rusqlite::OpenFlags::default(), key)?;
+    rusqlite::backup::Backup::new(&src, &mut dst)
+        .and_then(|backup| backup.run_to_completion(100, std::time::Duration::ZERO, None))
+        .map_err(DbError::from)?;
+    Ok(found)
+}
+
+/// Encrypt the unencrypted database at `db_path` in place with `key`, using
+/// SQLCipher's `sqlcipher_export`. yoclaw must not be running.
+pub fn encrypt_database(db_path: &Path, key: &str) -> Result<(), BackupError> {
+    if !cfg!(feature = "encryption") {
+        return Err(DbError::EncryptionUnsupported.into());
+    }
+    if !is_database(db_path)? {
+        return Err(BackupError::NotPlain(db_path.to_path_buf()));
+    }
+    let partial = partial_path(db_path);
+    let _ = std::fs::remove_file(&partial);
+    let export = || -> Result<(), DbError> {
+        let conn = crate::db::connect(db_path, rusqlite::OpenFlags::default(), None)?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            rusqlite::params![partial.to_string_lossy(), key],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.execute_batch("DETACH DATABASE encrypted")?;
+        // Closing the last connection checkpoints and removes the WAL
+        conn.close().map_err(|(_, e)| e)?;
+        Db::file_schema_version(&partial, Some(key))?;
+        Ok(())
+    };
+    if let Err(e) = export() {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    std::fs::rename(&partial, db_path)?;
+    Ok(())
+}
+
+/// Take a scheduled snapshot into `dir` once the newest one there is
+/// `interval_hours` old, then delete all but the newest `keep`. Returns the
+/// new snapshot, if one was taken.
+pub async fn run_scheduled(
+    db: &Db,
+    dir: &Path,
+    config: &BackupConfig,
+) -> Result<Option<PathBuf>, BackupError> {
+    let now = now_ms();
+    let newest = list_snapshots(dir)?.last().map(|(time, _)| *time);
+    if newest.is_some_and(|t| now.saturating_sub(t) < config.interval_hours.max(1) * 3_600_000) {
+        return Ok(None);
+    }
+    let path = dir.join(snapshot_name(now));
+    snapshot(db, &path).await?;
+    let rotated = rotate(dir, config.keep.max(1))?;
+    if rotated > 0 {
+        tracing::info!("Deleted {} old backup(s) from {}", rotated, dir.display());
+    }
+    Ok(Some(path))
+}
+
+/// Snapshots in `dir` with the time they were taken, oldest first.
+fn list_snapshots(dir: &Path) -> Result<Vec<(u64, PathBuf)>, std::io::Error> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(time) = entry.file_name().to_str().and_then(snapshot_time) {
+            snapshots.push((time, entry.path()));
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Delete all but the newest `keep` snapshots in `dir`. Other files are left
+/// alone. Returns how many were deleted.
+pub fn rotate(dir: &Path, keep: usize) -> Result<usize, std::io::Error> {
+    let snapshots = list_snapshots(dir)?;
+    let excess = snapshots.len().saturating_sub(keep);
+    for (_, path) in &snapshots[..excess] {
+        std::fs::remove_file(path)?;
+    }
+    Ok(excess)
+}
+
+/// Config text with secrets replaced by [`REDACTED`]. `${VAR}` references
+/// hold no secret and are kept.
+pub fn redact_config(raw: &str) -> Result<String, BackupError> {
+    let mut table: toml::Table = raw
+        .parse()
+        .map_err(|e: toml::de::Error| BackupError::Config(e.to_string()))?;
+    redact_table(&mut table);
+    toml::to_string(&table).map_err(|e| BackupError::Config(e.to_string()))
+}
+
+fn redact_table(table: &mut toml::Table) {
+    for (key, value) in table.iter_mut() {
+        match value {
+            toml::Value::String(s) if is_secret_key(key) && !is_env_reference(s) => {
+                *s = REDACTED.to_string();
+            }
+            toml::Value::Table(t) => redact_table(t),
+            toml::Value::Array(items) => {
+                let secret = is_secret_key(key);
+                for item in items {
+                    match item {
+                        toml::Value::String(s) if secret && !is_env_reference(s) => {
+                            *s = REDACTED.to_string();
+                        }
+                        toml::Value::Table(t) => redact_table(t),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["token", "secret", "password", "api_key"].contains(&key.as_str())
+        || key.ends_with("_token")
+        || key.ends_with("_secret")
+        || key.ends_with("_password")
+        || key.ends_with("_key")
+        || key.ends_with("_keys")
+}
+
+fn is_env_reference(value: &str) -> bool {
+    value.starts_with("${") && value.ends_with('}') && !value[2..].contains("${")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_install(dir: &Path) -> PathBuf {
+        std::fs::create_dir_all(dir.join("skills/weather")).unwrap();
+        std::fs::write(dir.join("skills/weather/SKILL.md"), "# Weather").unwrap();
+        std::fs::write(dir.join("persona.md"), "You are Claw.").unwrap();
+        std::fs::create_dir_all(dir.join("uploads")).unwrap();
+        std::fs::write(dir.join("uploads/tg-1-1.txt"), "big log").unwrap();
+        let config_path = dir.join("config.toml");
+        std::fs::write(
+            &config_path,
+            format!(
+                r#"
+[agent]
+model = "m"
+api_key = "sk-live-secret"
+persona = "{dir}/persona.md"
+skills_dirs = ["{dir}/skills"]
+
+[channels.telegram]
+bot_token = "${{TG_TOKEN}}"
+
+[persistence]
+db_path = "{dir}/yoclaw.db"
+"#,
+                dir = dir.display()
+            ),
+        )
+        .unwrap();
+        config_path
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let source_dir = tmp.path().join("source");
+        let config_path = write_install(&source_dir);
+        std::env::set_var("TG_TOKEN", "123:ABC");
+        let config = crate::config::load_config(Some(&config_path)).unwrap();
+        let db = Db::open(&config.db_path()).unwrap();
+        db.memory_store(None, "User likes tea", None, None)
+            .await
+            .unwrap();
+
+        let archive = tmp.path().join("backup.tar.gz");
+        let manifest = create(&config_path, &config, &db, &archive, true)
+            .await
+            .unwrap();
+        let kinds: Vec<&str> = manifest.entries.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, ["config", "database", "persona", "skills", "media"]);
+        assert_eq!(manifest.entries[2].restore_to, "persona.md");
+
+        // Restore into a fresh directory: paths follow the new config dir
+        let target_dir = tmp.path().join("target");
+        let target_config = target_dir.join("config.toml");
+        let restored = restore(&archive, &target_config, false).unwrap();
+        assert_eq!(restored, manifest);
+        let config_text = std::fs::read_to_string(&target_config).unwrap();
+        assert!(config_text.contains(REDACTED));
+        assert!(!config_text.contains("sk-live-secret"));
+        assert!(config_text.contains("${TG_TOKEN}"));
+        assert_eq!(
+            std::fs::read_to_string(target_dir.join("persona.md")).unwrap(),
+            "You are Claw."
+        );
+        assert!(target_dir.join("skills/weather/SKILL.md").is_file());
+        assert!(target_dir.join("uploads/tg-1-1.txt").is_file());
+        let restored_db = Db::open(&target_dir.join("yoclaw.db")).unwrap();
+        assert_eq!(restored_db.memory_search("tea", 10).await.unwrap().len(), 1);
+        drop(restored_db);
+        assert!(std::fs::read_dir(&target_dir).unwrap().all(|e| !e
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(".restore")));
+
+        // Existing state is kept unless forced
+        assert!(matches!(
+            restore(&archive, &target_config, false),
+            Err(BackupError::Exists(_))
+        ));
+        restore(&archive, &target_config, true).unwrap();
+    }
+
+    #[test]
+    fn test_restore_refuses_newer_format() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let archive = tmp.path().join("future.tar.gz");
+        let manifest = Manifest {
+            format_version: FORMAT_VERSION + 1,
+            yoclaw_version: "99.0.0".into(),
+            created_at: 0,
+            schema_version: 1,
+            redacted: false,
+            entries: Vec::new(),
+        };
+        let file = std::fs::File::create(&archive).unwrap();
+        let gz = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        append_bytes(&mut tar, MANIFEST, &serde_json::to_vec(&manifest).unwrap()).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let result = restore(&archive, &tmp.path().join("cfg/config.toml"), false);
+        assert!(matches!(result, Err(BackupError::NewerFormat(_))));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_encrypt_and_restore_encrypted() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("live.db");
+        let db = Db::open(&path).unwrap();
+        db.memory_store(None, "Encrypted fact", None, None)
+            .await
+            .unwrap();
+        drop(db);
+
+        encrypt_database(&path, "s3cret").unwrap();
+        assert!(!is_database(&path).unwrap());
+        assert!(matches!(
+            encrypt_database(&path, "s3cret"),
+            Err(BackupError::NotPlain(_))
+        ));
+        let db = Db::open_with_key(&path, 0, Some("s3cret")).unwrap();
+        let copy = tmp.path().join("copy.db");
+        snapshot(&db, &copy).await.unwrap();
+        assert!(!is_archive(&copy).unwrap());
+
+        let target = tmp.path().join("restored.db");
+        restore_database(&copy, &target, Some("s3cret"), false).unwrap();
+        let restored = Db::open_with_key(&target, 0, Some("s3cret")).unwrap();
+        assert_eq!(
+            restored.memory_search("encrypted", 10).await.unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore_and_rotate() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let db = Db::open(&tmp.path().join("live.db")).unwrap();
+        db.memory_store(None, "Snapshotted fact", None, None)
+            .await
+            .unwrap();
+
+        let copy = tmp.path().join("backups/manual.db");
+        snapshot(&db, &copy).await.unwrap();
+        assert!(is_database(&copy).unwrap());
+        assert!(matches!(
+            snapshot(&db, &copy).await,
+            Err(BackupError::Exists(_))
+        ));
+
+        let target = tmp.path().join("restored/yoclaw.db");
+        assert_eq!(
+            restore_database(&copy, &target, None, false).unwrap(),
+            Db::latest_schema_version()
+        );
+        assert!(matches!(
+            restore_database(&copy, &target, None, false),
+            Err(BackupError::Exists(_))
+        ));
+        restore_database(&copy, &target, None, true).unwrap();
+        let restored = Db::open(&target).unwrap();
+        assert_eq!(
+            restored
+                .memory_search("snapshotted", 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Scheduled snapshots: one per interval, the newest `keep` kept
+        let dir = tmp.path().join("backups");
+        for day in 1..=3 {
+            std::fs::write(dir.join(format!("yoclaw-202601{:02}-030000.db", day)), "").unwrap();
+        }
+        let config = BackupConfig {
+            interval_hours: 24,
+            dir: None,
+            keep: 2,
+        };
+        let taken = run_scheduled(&db, &dir, &config).await.unwrap().unwrap();
+        assert!(is_database(&taken).unwrap());
+        assert_eq!(run_scheduled(&db, &dir, &config).await.unwrap(), None);
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], "manual.db");
+        assert_eq!(names[1], "yoclaw-20260103-030000.db");
+        assert!(snapshot_time(&names[2]).is_some());
+        assert_eq!(
+            snapshot_time("yoclaw-20261016-093000.db"),
+            Some(1_792_143_000_000)
+        );
+        assert!(!is_database(&dir.join("yoclaw-20260103-030000.db")).unwrap());
+    }
+
+    #[test]
+    fn test_redact_config() {
+        let raw = r#"
+[agent]
+model = "m"
+api_key = "sk-secret"
+api_keys = ["sk-spare", "${SPARE_KEY}"]
+
+[channels.slack]
+bot_token = "xoxb-1"
+app_token = "${SLACK_APP_TOKEN}"
+allowed_users = ["U1"]
+
+[webhooks.ci]
+url = "https://ci.example.com"
+secret = "s3cret"
+"#;
+        let redacted = redact_config(raw).unwrap();
+        for secret in ["sk-secret", "sk-spare", "xoxb-1", "s3cret"] {
+            assert!(!redacted.contains(secret));
+        }
+        assert!(redacted.contains("${SLACK_APP_TOKEN}"));
+        assert!(redacted.contains("${SPARE_KEY}"));
+        assert!(redacted.contains("https://ci.example.com"));
+        assert!(redacted.contains("U1"));
+    }
+}
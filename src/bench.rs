@@ -0,0 +1,478 @@
+//! Load test of the message pipeline (`yoclaw bench`).
+//!
+//! Simulated channels send messages down the daemon's path: the coalescer,
+//! the queue, a conductor pool and full agent turns with tool calls, whose
+//! tapes, audit events and memories land in a scratch database. The model is
+//! a `MockProvider` that answers instantly, so the numbers are yoclaw's own
+//! overhead:
+//!
+//! ```text
+//! throughput   messages done per second, first send to last reply
+//! queue        wait between queueing a message and a conductor taking it
+//! turn         time a conductor spent on the message
+//! end to end   first send of the message to its reply
+//! db writer    wait for the writer connection, per operation
+//! ```
+//!
+//! A report saved with `--output json` can be passed back as `--baseline`;
+//! the run then fails if throughput drops, or p99 latency or writer wait
+//! grows, by more than `--tolerance` percent.
+
+use crate::channels::coalesce::MessageCoalescer;
+use crate::channels::{IncomingMessage, MessageKind};
+use crate::conductor::pool::{self, ConductorPool};
+use crate::conductor::{Conductor, DynProvider};
+use crate::config::{parse_config, Config};
+use crate::db::queue::QueueEntry;
+use crate::db::Db;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use yoagent::provider::mock::{MockProvider, MockResponse, MockToolCall};
+
+/// Channel names messages are attributed to, in turn.
+const CHANNELS: &[&str] = &["telegram", "discord", "slack", "signal"];
+
+/// What to simulate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchOptions {
+    /// Concurrent channels (chats), each its own session.
+    pub channels: usize,
+    /// Messages each channel sends.
+    pub messages: usize,
+    /// Tool calls the model makes per message before answering; they
+    /// alternate between memory_store and memory_search.
+    pub tool_calls: usize,
+    /// Conductors, as with `[agent] parallel_sessions`.
+    pub parallel_sessions: usize,
+    /// Pause between a channel's messages.
+    #[serde(with = "millis")]
+    pub interval: Duration,
+    /// Coalescer debounce.
+    #[serde(with = "millis")]
+    pub debounce: Duration,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            channels: 8,
+            messages: 25,
+            tool_calls: 2,
+            parallel_sessions: 4,
+            interval: Duration::from_millis(10),
+            debounce: Duration::ZERO,
+        }
+    }
+}
+
+/// Latency distribution, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    pub fn of(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let at = |p: f64| {
+            let idx = ((sorted.len() as f64 * p).ceil() as usize).clamp(1, sorted.len()) - 1;
+            ms(sorted[idx])
+        };
+        Self {
+            p50: at(0.50),
+            p90: at(0.90),
+            p99: at(0.99),
+            max: ms(sorted[sorted.len() - 1]),
+        }
+    }
+}
+
+/// Writer connection waits during the run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WriterReport {
+    pub ops: u64,
+    pub wait_mean_ms: f64,
+    pub wait_max_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub options: BenchOptions,
+    /// Messages the channels sent.
+    pub sent: usize,
+    /// Messages processed, after coalescing.
+    pub messages: usize,
+    /// Messages whose turn failed.
+    pub failed: usize,
+    pub elapsed_ms: f64,
+    /// Messages per second.
+    pub throughput: f64,
+    pub queue: Percentiles,
+    pub turn: Percentiles,
+    pub end_to_end: Percentiles,
+    pub db_writer: WriterReport,
+}
+
+impl BenchReport {
+    pub fn format_text(&self) -> String {
+        let row = |name: &str, p: &Percentiles| {
+            format!(
+                "{:<12} p50 {:>8.2} ms  p90 {:>8.2} ms  p99 {:>8.2} ms  max {:>8.2} ms\n",
+                name, p.p50, p.p90, p.p99, p.max
+            )
+        };
+        let o = &self.options;
+        let mut out = format!(
+            "{} channel(s) x {} message(s), {} tool call(s) each, {} conductor(s)\n\n",
+            o.channels, o.messages, o.tool_calls, o.parallel_sessions
+        );
+        out.push_str(&format!(
+            "{:<12} {:.1} msg/s ({} sent, {} processed in {:.0} ms, {} failed)\n",
+            "throughput", self.throughput, self.sent, self.messages, self.elapsed_ms, self.failed
+        ));
+        out.push_str(&row("queue", &self.queue));
+        out.push_str(&row("turn", &self.turn));
+        out.push_str(&row("end to end", &self.end_to_end));
+        out.push_str(&format!(
+            "{:<12} {} ops, wait mean {:.3} ms, max {:.2} ms\n",
+            "db writer",
+            self.db_writer.ops,
+            self.db_writer.wait_mean_ms,
+            self.db_writer.wait_max_ms
+        ));
+        out
+    }
+
+    /// How this run is worse than `baseline` by more than `tolerance`
+    /// percent; empty if it isn't.
+    pub fn regressions(&self, baseline: &BenchReport, tolerance: f64) -> Vec<String> {
+        let factor = tolerance / 100.0;
+        let mut found = Vec::new();
+        if self.throughput < baseline.throughput * (1.0 - factor) {
+            found.push(format!(
+                "throughput {:.1} msg/s, was {:.1}",
+                self.throughput, baseline.throughput
+            ));
+        }
+        let slower = [
+            ("queue p99", self.queue.p99, baseline.queue.p99),
+            ("turn p99", self.turn.p99, baseline.turn.p99),
+            (
+                "end to end p99",
+                self.end_to_end.p99,
+                baseline.end_to_end.p99,
+            ),
+            (
+                "db writer mean wait",
+                self.db_writer.wait_mean_ms,
+                baseline.db_writer.wait_mean_ms,
+            ),
+        ];
+        for (name, now, was) in slower {
+            if now > was * (1.0 + factor) {
+                found.push(format!("{} {:.2} ms, was {:.2} ms", name, now, was));
+            }
+        }
+        found
+    }
+}
+
+/// A message handed to a conductor.
+struct Job {
+    incoming: IncomingMessage,
+    queue_id: i64,
+    sent: Instant,
+    queued: Instant,
+}
+
+/// Timings of one processed message.
+struct Sample {
+    queue: Duration,
+    turn: Duration,
+    end_to_end: Duration,
+    ok: bool,
+}
+
+/// Run the load test against a database at `db_path`.
+pub async fn run(options: &BenchOptions, db_path: PathBuf) -> anyhow::Result<BenchReport> {
+    let workers = options.parallel_sessions.max(1);
+    let sessions: Vec<String> = (0..options.channels)
+        .map(|i| format!("bench-{}", i))
+        .collect();
+
+    let config = bench_config(&db_path, workers)?;
+    let db = Db::open_with_readers(&db_path, config.persistence.read_pool_size)?;
+
+    // Each conductor gets the responses for the messages of its sessions
+    let mut per_worker = vec![0; workers];
+    for session in &sessions {
+        per_worker[pool::slot(session, workers)] += options.messages;
+    }
+    let providers = per_worker
+        .iter()
+        .map(|&messages| DynProvider::new(script(messages, options.tool_calls)))
+        .collect();
+    let conductors = pool::conductors_with_providers(&config, db.clone(), providers).await?;
+
+    let (samples_tx, mut samples_rx) = mpsc::unbounded_channel::<Sample>();
+    let handle_db = db.clone();
+    let pool = ConductorPool::start(conductors, move |conductor: &mut Conductor, job: Job| {
+        let db = handle_db.clone();
+        let samples = samples_tx.clone();
+        Box::pin(async move {
+            let started = Instant::now();
+            conductor.set_queue_entry(job.queue_id);
+            let result = conductor
+                .process_message(&job.incoming.session_id, &job.incoming.content, None, None)
+                .await;
+            let ok = match result {
+                Ok(_) => db.queue_mark_done(job.queue_id).await.is_ok(),
+                Err(e) => {
+                    tracing::warn!("Bench message failed: {}", e);
+                    let failure = conductor.failure_context(&e);
+                    let _ = db.queue_mark_failed(job.queue_id, &failure).await;
+                    false
+                }
+            };
+            let _ = samples.send(Sample {
+                queue: started - job.queued,
+                turn: started.elapsed(),
+                end_to_end: job.sent.elapsed(),
+                ok,
+            });
+        })
+    });
+
+    let (raw_tx, raw_rx) = mpsc::unbounded_channel();
+    let (coalesced_tx, mut coalesced_rx) = mpsc::unbounded_channel();
+    tokio::spawn(MessageCoalescer::new(options.debounce, raw_rx, coalesced_tx).run());
+
+    let writer_before = db.writer_stats();
+    let start = Instant::now();
+    let sent_at: Arc<Mutex<HashMap<String, Instant>>> = Arc::default();
+    for (i, session) in sessions.iter().enumerate() {
+        let (raw_tx, sent_at, session) = (raw_tx.clone(), sent_at.clone(), session.clone());
+        let (messages, interval) = (options.messages, options.interval);
+        tokio::spawn(async move {
+            for n in 0..messages {
+                let message_id = format!("{}-{}", session, n);
+                sent_at
+                    .lock()
+                    .unwrap()
+                    .insert(message_id.clone(), Instant::now());
+                let _ = raw_tx.send(message(i, &session, n, message_id));
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+    drop(raw_tx);
+
+    // The daemon's main loop: queue each coalesced message, then hand it
+    // to its session's conductor
+    let mut dispatched = 0;
+    while let Some(incoming) = coalesced_rx.recv().await {
+        let sent = incoming
+            .message_id
+            .as_ref()
+            .and_then(|id| sent_at.lock().unwrap().remove(id))
+            .unwrap_or_else(Instant::now);
+        let entry = QueueEntry::new(
+            &incoming.channel,
+            &incoming.sender_id,
+            &incoming.session_id,
+            &incoming.content,
+        );
+        let queue_id = db.queue_push(&entry).await?;
+        db.session_touch(
+            &incoming.channel,
+            &incoming.session_id,
+            incoming.chat_id.as_deref(),
+            None,
+            false,
+        )
+        .await?;
+        let session_id = incoming.session_id.clone();
+        pool.send(
+            &session_id,
+            Job {
+                incoming,
+                queue_id,
+                sent,
+                queued: Instant::now(),
+            },
+        );
+        dispatched += 1;
+    }
+    drop(pool);
+
+    let mut samples = Vec::with_capacity(dispatched);
+    while let Some(sample) = samples_rx.recv().await {
+        samples.push(sample);
+    }
+    let elapsed = start.elapsed();
+    let writer = db.writer_stats();
+    let writer_ops = writer.ops - writer_before.ops;
+    let writer_wait = writer.wait_total - writer_before.wait_total;
+
+    let collect = |f: fn(&Sample) -> Duration| samples.iter().map(f).collect::<Vec<_>>();
+    Ok(BenchReport {
+        options: options.clone(),
+        sent: options.channels * options.messages,
+        messages: samples.len(),
+        failed: samples.iter().filter(|s| !s.ok).count(),
+        elapsed_ms: ms(elapsed),
+        throughput: samples.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        queue: Percentiles::of(&collect(|s| s.queue)),
+        turn: Percentiles::of(&collect(|s| s.turn)),
+        end_to_end: Percentiles::of(&collect(|s| s.end_to_end)),
+        db_writer: WriterReport {
+            ops: writer_ops,
+            wait_mean_ms: if writer_ops == 0 {
+                0.0
+            } else {
+                ms(writer_wait) / writer_ops as f64
+            },
+            wait_max_ms: ms(writer.wait_max),
+        },
+    })
+}
+
+/// Default config with `workers` conductors and the database at `db_path`,
+/// leaving out the user's persona and skills.
+fn bench_config(db_path: &std::path::Path, workers: usize) -> anyhow::Result<Config> {
+    let mut config = parse_config("[agent]\nmodel = \"mock\"\napi_key = \"bench\"\n")?;
+    let scratch = db_path.with_extension("bench");
+    config.agent.persona = Some(scratch.join("persona.md").display().to_string());
+    config.agent.skills_dirs = vec![scratch.join("skills").display().to_string()];
+    config.agent.parallel_sessions = workers;
+    config.persistence.db_path = db_path.display().to_string();
+    Ok(config)
+}
+
+/// Model responses for `messages` messages: `tool_calls` tool calls each,
+/// then a short answer.
+fn script(messages: usize, tool_calls: usize) -> MockProvider {
+    let mut responses = Vec::with_capacity(messages * (tool_calls + 1));
+    for m in 0..messages {
+        for t in 0..tool_calls {
+            let call = if t % 2 == 0 {
+                MockToolCall {
+                    name: "memory_store".into(),
+                    arguments: serde_json::json!({
+                        "content": format!("Benchmark fact {} of message {}", t, m),
+                    }),
+                }
+            } else {
+                MockToolCall {
+                    name: "memory_search".into(),
+                    arguments: serde_json::json!({ "query": "benchmark fact" }),
+                }
+            };
+            responses.push(MockResponse::ToolCalls(vec![call]));
+        }
+        responses.push(MockResponse::Text(format!("Done with message {}.", m)));
+    }
+    MockProvider::new(responses)
+}
+
+fn message(channel: usize, session: &str, n: usize, message_id: String) -> IncomingMessage {
+    IncomingMessage {
+        channel: CHANNELS[channel % CHANNELS.len()].to_string(),
+        sender_id: format!("user-{}", channel),
+        sender_name: Some(format!("Bench user {}", channel)),
+        session_id: session.to_string(),
+        chat_id: Some(session.to_string()),
+        thread_id: None,
+        content: format!(
+            "Message {} from {}: remember this and look it up.",
+            n, session
+        ),
+        reply_to: None,
+        quote: None,
+        timestamp: crate::db::now_ms(),
+        worker_hint: None,
+        is_group: false,
+        message_id: Some(message_id),
+        kind: MessageKind::New,
+        attachments: Vec::new(),
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Durations as whole milliseconds in reports.
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(d)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        let p = Percentiles::of(&samples);
+        assert_eq!((p.p50, p.p90, p.p99, p.max), (50.0, 90.0, 99.0, 100.0));
+        assert_eq!(Percentiles::of(&[]), Percentiles::default());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_and_compare() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bench.db");
+        let options = BenchOptions {
+            channels: 3,
+            messages: 4,
+            tool_calls: 2,
+            parallel_sessions: 2,
+            interval: Duration::from_millis(1),
+            debounce: Duration::ZERO,
+        };
+        let report = run(&options, path.clone()).await.unwrap();
+        assert_eq!((report.sent, report.failed), (12, 0));
+        // Messages arriving within the debounce are coalesced
+        assert!((3..=12).contains(&report.messages), "{:?}", report);
+        assert!(report.throughput > 0.0);
+        assert!(report.db_writer.ops > 0);
+
+        let db = Db::open(&path).unwrap();
+        assert_eq!(db.queue_pending_count().await.unwrap(), 0);
+        assert_eq!(db.tape_list_sessions().await.unwrap().len(), 3);
+        // One memory_store per processed message
+        let memories = db
+            .memory_list_filtered(100, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(memories.len(), report.messages);
+
+        assert!(report.regressions(&report, 10.0).is_empty());
+        let mut faster = report.clone();
+        faster.throughput = report.throughput * 2.0;
+        faster.end_to_end.p99 = report.end_to_end.p99 / 2.0;
+        let found = report.regressions(&faster, 10.0);
+        assert!(found[0].starts_with("throughput"), "{:?}", found);
+    }
+}
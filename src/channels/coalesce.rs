@@ -1,5 +1,5 @@
-use super::IncomingMessage;
-use std::collections::HashMap;
+use super::{IncomingMessage, MessageKind};
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, RwLock};
 use tokio::sync::mpsc;
 use tokio::time::{Duration, Instant};
@@ -13,8 +13,16 @@ pub struct DebounceConfig {
     pub per_channel: HashMap<String, Duration>,
 }
 
+/// Flushed message IDs remembered per session for matching later edits.
+const RECENT_PER_SESSION: usize = 50;
+
 /// Batches rapid-fire messages from the same session into a single message.
 /// Supports per-channel debounce overrides.
+///
+/// Edits and deletions of a message still in the debounce window update or
+/// drop it. Those of a recently flushed message are forwarded, with the
+/// previous text filled in, so the main loop can note them on the tape;
+/// anything else (e.g. a deletion of someone else's message) is ignored.
 pub struct MessageCoalescer {
     debounce: SharedDebounce,
     input_rx: mpsc::UnboundedReceiver<IncomingMessage>,
@@ -61,6 +69,7 @@ impl MessageCoalescer {
     pub async fn run(mut self) {
         let mut pending: HashMap<String, Vec<IncomingMessage>> = HashMap::new();
         let mut deadlines: HashMap<String, Instant> = HashMap::new();
+        let mut recent: HashMap<String, VecDeque<(String, String)>> = HashMap::new();
 
         loop {
             // Calculate next deadline
@@ -80,12 +89,23 @@ impl MessageCoalescer {
             tokio::select! {
                 msg = self.input_rx.recv() => {
                     match msg {
-                        Some(msg) => {
+                        Some(msg) if msg.kind == MessageKind::New => {
                             let session = msg.session_id.clone();
                             let debounce = self.debounce_for(&msg.channel);
                             pending.entry(session.clone()).or_default().push(msg);
                             deadlines.insert(session, Instant::now() + debounce);
                         }
+                        // Reactions act on replies, not on pending messages
+                        Some(msg) if matches!(msg.kind, MessageKind::Reaction { .. }) => {
+                            let _ = self.output_tx.send(msg);
+                        }
+                        Some(msg) => {
+                            if let Some(forward) = apply_change(msg, &mut pending, &mut recent) {
+                                let _ = self.output_tx.send(forward);
+                            }
+                            pending.retain(|_, messages| !messages.is_empty());
+                            deadlines.retain(|session, _| pending.contains_key(session));
+                        }
                         None => {
                             // Channel closed — flush remaining
                             for (_session, messages) in pending.drain() {
@@ -107,6 +127,7 @@ impl MessageCoalescer {
                     for session in expired {
                         deadlines.remove(&session);
                         if let Some(messages) = pending.remove(&session) {
+                            remember(&mut recent, &messages);
                             let coalesced = coalesce_messages(messages);
                             let _ = self.output_tx.send(coalesced);
                         }
@@ -117,16 +138,87 @@ impl MessageCoalescer {
     }
 }
 
-/// Combine multiple messages into a single message with joined content.
+/// Apply an edit or deletion. A pending message is updated or dropped in
+/// place; for a recently flushed one the event is returned for forwarding,
+/// with the previous text filled in.
+fn apply_change(
+    mut msg: IncomingMessage,
+    pending: &mut HashMap<String, Vec<IncomingMessage>>,
+    recent: &mut HashMap<String, VecDeque<(String, String)>>,
+) -> Option<IncomingMessage> {
+    let id = msg.message_id.clone()?;
+    if let Some(messages) = pending.get_mut(&msg.session_id) {
+        if let Some(pos) = messages
+            .iter()
+            .position(|m| m.message_id.as_deref() == Some(id.as_str()))
+        {
+            match msg.kind {
+                MessageKind::Edited { .. } => messages[pos].content = msg.content,
+                _ => {
+                    messages.remove(pos);
+                }
+            }
+            return None;
+        }
+    }
+
+    let seen = recent.get_mut(&msg.session_id)?;
+    let pos = seen.iter().position(|(seen_id, _)| *seen_id == id)?;
+    match msg.kind {
+        MessageKind::Edited { .. } => {
+            let previous = std::mem::replace(&mut seen[pos].1, msg.content.clone());
+            if previous == msg.content {
+                // Discord also reports embed updates as edits
+                return None;
+            }
+            msg.kind = MessageKind::Edited {
+                previous: Some(previous),
+            };
+        }
+        _ => {
+            let (_, previous) = seen.remove(pos)?;
+            msg.kind = MessageKind::Deleted {
+                previous: Some(previous),
+            };
+        }
+    }
+    Some(msg)
+}
+
+/// Remember the IDs of flushed messages, keeping the most recent per session.
+fn remember(
+    recent: &mut HashMap<String, VecDeque<(String, String)>>,
+    messages: &[IncomingMessage],
+) {
+    for m in messages {
+        let Some(ref id) = m.message_id else {
+            continue;
+        };
+        let seen = recent.entry(m.session_id.clone()).or_default();
+        seen.push_back((id.clone(), m.content.clone()));
+        if seen.len() > RECENT_PER_SESSION {
+            seen.pop_front();
+        }
+    }
+}
+
+/// Combine multiple messages into a single message with joined content and
+/// all of their attachments.
 fn coalesce_messages(mut messages: Vec<IncomingMessage>) -> IncomingMessage {
     if messages.len() == 1 {
         return messages.remove(0);
     }
 
+    let attachments = messages
+        .iter_mut()
+        .flat_map(|m| std::mem::take(&mut m.attachments))
+        .collect();
     let first = &messages[0];
+    // Messages that are only a file have no text to join
     let combined = messages
         .iter()
         .map(|m| m.content.as_str())
+        .filter(|c| !c.is_empty())
         .collect::<Vec<_>>()
         .join("\n");
 
@@ -135,11 +227,17 @@ fn coalesce_messages(mut messages: Vec<IncomingMessage>) -> IncomingMessage {
         sender_id: first.sender_id.clone(),
         sender_name: first.sender_name.clone(),
         session_id: first.session_id.clone(),
+        chat_id: first.chat_id.clone(),
+        thread_id: first.thread_id.clone(),
         content: combined,
         reply_to: first.reply_to.clone(),
+        quote: first.quote.clone(),
         timestamp: first.timestamp,
         worker_hint: first.worker_hint.clone(),
         is_group: first.is_group,
+        message_id: first.message_id.clone(),
+        kind: MessageKind::New,
+        attachments,
     }
 }
 
@@ -158,11 +256,25 @@ mod tests {
             sender_id: "user1".into(),
             sender_name: Some("User".into()),
             session_id: session.into(),
+            chat_id: None,
+            thread_id: None,
             content: content.into(),
             reply_to: None,
+            quote: None,
             timestamp: now_ms(),
             worker_hint: None,
             is_group: false,
+            message_id: None,
+            kind: MessageKind::New,
+            attachments: Vec::new(),
+        }
+    }
+
+    fn change(session: &str, id: &str, content: &str, kind: MessageKind) -> IncomingMessage {
+        IncomingMessage {
+            message_id: Some(id.into()),
+            kind,
+            ..test_msg(session, content)
         }
     }
 
@@ -207,6 +319,43 @@ mod tests {
         assert_eq!(msg.session_id, "s1");
     }
 
+    #[tokio::test]
+    async fn test_coalesce_keeps_attachments() {
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        let (output_tx, mut output_rx) = mpsc::unbounded_channel();
+        let coalescer = MessageCoalescer::new(Duration::from_millis(100), input_rx, output_tx);
+
+        tokio::spawn(coalescer.run());
+
+        let photo = |name: &str| crate::channels::Attachment {
+            name: name.into(),
+            mime_type: "image/jpeg".into(),
+            data: vec![1, 2, 3],
+            voice: false,
+        };
+        // A caption, then a second photo without one
+        input_tx
+            .send(IncomingMessage {
+                attachments: vec![photo("a.jpg")],
+                ..test_msg("s1", "what are these?")
+            })
+            .unwrap();
+        input_tx
+            .send(IncomingMessage {
+                attachments: vec![photo("b.jpg")],
+                ..test_msg("s1", "")
+            })
+            .unwrap();
+
+        let msg = tokio::time::timeout(Duration::from_secs(1), output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(msg.content, "what are these?");
+        assert_eq!(msg.attachments, vec![photo("a.jpg"), photo("b.jpg")]);
+    }
+
     #[tokio::test]
     async fn test_separate_sessions() {
         let (input_tx, input_rx) = mpsc::unbounded_channel();
@@ -273,4 +422,122 @@ mod tests {
         assert_eq!(second.channel, "chan_b");
         assert_eq!(second.content, "msg_b");
     }
+
+    #[tokio::test]
+    async fn test_edit_and_delete_pending_messages() {
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        let (output_tx, mut output_rx) = mpsc::unbounded_channel();
+        let coalescer = MessageCoalescer::new(Duration::from_millis(100), input_rx, output_tx);
+
+        tokio::spawn(coalescer.run());
+
+        let new = MessageKind::New;
+        input_tx
+            .send(change("s1", "1", "frist", new.clone()))
+            .unwrap();
+        input_tx
+            .send(change("s1", "2", "oops", new.clone()))
+            .unwrap();
+        input_tx.send(change("s1", "3", "third", new)).unwrap();
+        let edited = MessageKind::Edited { previous: None };
+        input_tx.send(change("s1", "1", "first", edited)).unwrap();
+        let deleted = MessageKind::Deleted { previous: None };
+        input_tx.send(change("s1", "2", "", deleted)).unwrap();
+
+        let msg = tokio::time::timeout(Duration::from_secs(1), output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(msg.content, "first\nthird");
+
+        // Deleting every pending message drops the batch
+        let new = MessageKind::New;
+        input_tx.send(change("s2", "4", "never mind", new)).unwrap();
+        let deleted = MessageKind::Deleted { previous: None };
+        input_tx.send(change("s2", "4", "", deleted)).unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(250), output_rx.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forward_changes_to_flushed_messages() {
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        let (output_tx, mut output_rx) = mpsc::unbounded_channel();
+        let coalescer = MessageCoalescer::new(Duration::from_millis(50), input_rx, output_tx);
+
+        tokio::spawn(coalescer.run());
+
+        input_tx
+            .send(change("s1", "1", "meet at 5", MessageKind::New))
+            .unwrap();
+        let msg = tokio::time::timeout(Duration::from_secs(1), output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(msg.kind, MessageKind::New);
+
+        // Unknown messages (e.g. someone else's) are ignored
+        let deleted = MessageKind::Deleted { previous: None };
+        input_tx.send(change("s1", "99", "", deleted)).unwrap();
+        let edited = MessageKind::Edited { previous: None };
+        input_tx
+            .send(change("s1", "1", "meet at 6", edited))
+            .unwrap();
+        let msg = tokio::time::timeout(Duration::from_secs(1), output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            msg.kind,
+            MessageKind::Edited {
+                previous: Some("meet at 5".into())
+            }
+        );
+        assert_eq!(
+            msg.followup_note().unwrap(),
+            "[The user edited their earlier message \"meet at 5\". It now reads: \"meet at 6\"]"
+        );
+
+        let deleted = MessageKind::Deleted { previous: None };
+        input_tx.send(change("s1", "1", "", deleted)).unwrap();
+        let msg = tokio::time::timeout(Duration::from_secs(1), output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            msg.kind,
+            MessageKind::Deleted {
+                previous: Some("meet at 6".into())
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reactions_skip_debounce() {
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        let (output_tx, mut output_rx) = mpsc::unbounded_channel();
+        let coalescer = MessageCoalescer::new(Duration::from_secs(60), input_rx, output_tx);
+
+        tokio::spawn(coalescer.run());
+
+        input_tx
+            .send(change("s1", "1", "still typing", MessageKind::New))
+            .unwrap();
+        let reaction = MessageKind::Reaction {
+            emoji: "🔁".into()
+        };
+        input_tx
+            .send(change("s1", "42", "", reaction.clone()))
+            .unwrap();
+        let msg = tokio::time::timeout(Duration::from_millis(500), output_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(msg.kind, reaction);
+        assert_eq!(msg.message_id.as_deref(), Some("42"));
+        assert_eq!(msg.followup_note(), None);
+    }
 }
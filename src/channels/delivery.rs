@@ -0,0 +1,272 @@
+//! Tracked delivery of outbound messages.
+//!
+//! Sends and final edits go through [`Deliverer`], which retries transient
+//! failures (timeouts, 5xx, rate limits) with backoff, records the outcome in
+//! the `deliveries` table, and broadcasts failures to the web UI. Intermediate
+//! streaming edits are not tracked; the final edit supersedes them.
+
+use super::{ChannelAdapter, OutgoingMessage, SentMessage};
+use crate::db::deliveries::DeliveryStatus;
+use crate::db::Db;
+use crate::web::SseEvent;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Attempts per delivery, including the first.
+const MAX_ATTEMPTS: u32 = 3;
+/// Backoff before the first retry of a transient error, doubled each time.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Backoff before the first retry after a rate limit, doubled each time.
+const RATE_LIMIT_DELAY: Duration = Duration::from_secs(5);
+
+/// How a failed attempt should be handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureKind {
+    RateLimited,
+    Transient,
+    Permanent,
+}
+
+/// Classify an adapter error by its message. Adapters wrap errors from
+/// different client libraries, so the text is all they have in common.
+fn classify(error: &str) -> FailureKind {
+    let e = error.to_ascii_lowercase();
+    if [
+        "429",
+        "too many requests",
+        "rate limit",
+        "ratelimit",
+        "retry after",
+    ]
+    .iter()
+    .any(|p| e.contains(p))
+    {
+        FailureKind::RateLimited
+    } else if [
+        "timed out",
+        "timeout",
+        "connection",
+        "network",
+        "temporarily",
+        "502",
+        "503",
+        "504",
+    ]
+    .iter()
+    .any(|p| e.contains(p))
+    {
+        FailureKind::Transient
+    } else {
+        FailureKind::Permanent
+    }
+}
+
+/// Sends messages through adapters with retries and a delivery log.
+#[derive(Clone)]
+pub struct Deliverer {
+    db: Db,
+    events: Option<broadcast::Sender<SseEvent>>,
+}
+
+impl Deliverer {
+    pub fn new(db: Db) -> Self {
+        Self { db, events: None }
+    }
+
+    /// Broadcast `delivery_failed` events to web UI clients.
+    pub fn with_events(mut self, tx: broadcast::Sender<SseEvent>) -> Self {
+        self.events = Some(tx);
+        self
+    }
+
+    /// Send a new message.
+    pub async fn send(&self, adapter: &dyn ChannelAdapter, msg: OutgoingMessage) -> DeliveryStatus {
+        self.track(&msg.channel, &msg.session_id, "send", &msg.content, || {
+            adapter.send(msg.clone())
+        })
+        .await
+    }
+
+    /// Replace the text of a sent message (e.g. the final streaming edit).
+    /// Text too long for one message continues in numbered chunks threaded
+    /// under it.
+    pub async fn edit(
+        &self,
+        adapter: &dyn ChannelAdapter,
+        handle: &SentMessage,
+        text: &str,
+    ) -> DeliveryStatus {
+        self.track(&handle.channel, &handle.session_id, "edit", text, || {
+            super::edit_chunked(adapter, handle, text)
+        })
+        .await
+    }
+
+    /// Run `op` with retries and record the outcome. `kind` is "send" or "edit".
+    pub async fn track<F, Fut>(
+        &self,
+        channel: &str,
+        session_id: &str,
+        kind: &str,
+        content: &str,
+        op: F,
+    ) -> DeliveryStatus
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<(), anyhow::Error>>,
+    {
+        let mut attempts = 0;
+        let (status, error) = loop {
+            attempts += 1;
+            let e = match op().await {
+                Ok(()) => {
+                    let status = if kind == "edit" {
+                        DeliveryStatus::Edited
+                    } else {
+                        DeliveryStatus::Sent
+                    };
+                    break (status, None);
+                }
+                Err(e) => e.to_string(),
+            };
+            let failure = classify(&e);
+            let status = match failure {
+                FailureKind::RateLimited => DeliveryStatus::RateLimited,
+                _ => DeliveryStatus::Failed,
+            };
+            if failure == FailureKind::Permanent || attempts >= MAX_ATTEMPTS {
+                break (status, Some(e));
+            }
+            let base = match failure {
+                FailureKind::RateLimited => RATE_LIMIT_DELAY,
+                _ => RETRY_DELAY,
+            };
+            let delay = base * 2u32.pow(attempts - 1);
+            tracing::warn!(
+                "{} to {} failed (attempt {}/{}), retrying in {:?}: {}",
+                kind,
+                session_id,
+                attempts,
+                MAX_ATTEMPTS,
+                delay,
+                e
+            );
+            tokio::time::sleep(delay).await;
+        };
+
+        if let Some(ref e) = error {
+            tracing::error!(
+                "{} to {} {} after {} attempt(s): {}",
+                kind,
+                session_id,
+                status.as_str(),
+                attempts,
+                e
+            );
+            if let Some(ref tx) = self.events {
+                let _ = tx.send(SseEvent::DeliveryFailed {
+                    session_id: session_id.to_string(),
+                    channel: channel.to_string(),
+                    status: status.as_str().to_string(),
+                    error: e.clone(),
+                });
+            }
+        }
+        if let Err(e) = self
+            .db
+            .delivery_log(
+                channel,
+                session_id,
+                kind,
+                status,
+                attempts,
+                error.as_deref(),
+                content,
+            )
+            .await
+        {
+            tracing::warn!("Failed to record delivery: {}", e);
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify("Telegram: Retry after 12s"),
+            FailureKind::RateLimited
+        );
+        assert_eq!(
+            classify("HTTP 429 Too Many Requests"),
+            FailureKind::RateLimited
+        );
+        assert_eq!(classify("operation timed out"), FailureKind::Transient);
+        assert_eq!(classify("503 Service Unavailable"), FailureKind::Transient);
+        assert_eq!(classify("chat not found"), FailureKind::Permanent);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_transient_then_succeeds() {
+        let db = Db::open_memory().unwrap();
+        let deliverer = Deliverer::new(db.clone());
+        let calls = AtomicU32::new(0);
+
+        let status = deliverer
+            .track("telegram", "tg-1", "send", "hello", || async {
+                if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err(anyhow::anyhow!("connection reset"))
+                } else {
+                    Ok(())
+                }
+            })
+            .await;
+
+        assert_eq!(status, DeliveryStatus::Sent);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let log = db.delivery_query(false, 10).await.unwrap();
+        assert_eq!(log[0].status, "sent");
+        assert_eq!(log[0].attempts, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failure_recorded_and_broadcast() {
+        let db = Db::open_memory().unwrap();
+        let (tx, mut rx) = broadcast::channel(8);
+        let deliverer = Deliverer::new(db.clone()).with_events(tx);
+        let calls = AtomicU32::new(0);
+
+        // Permanent errors are not retried
+        let status = deliverer
+            .track("discord", "dc-1", "edit", "hello", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(anyhow::anyhow!("Unknown Message"))
+            })
+            .await;
+        assert_eq!(status, DeliveryStatus::Failed);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Rate limits are retried up to MAX_ATTEMPTS
+        let status = deliverer
+            .track("discord", "dc-1", "send", "hello", || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(anyhow::anyhow!("429 Too Many Requests"))
+            })
+            .await;
+        assert_eq!(status, DeliveryStatus::RateLimited);
+        assert_eq!(calls.load(Ordering::SeqCst), 1 + MAX_ATTEMPTS);
+
+        let failed = db.delivery_query(true, 10).await.unwrap();
+        assert_eq!(failed.len(), 2);
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            SseEvent::DeliveryFailed { ref status, .. } if status == "failed"
+        ));
+    }
+}
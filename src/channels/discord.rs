@@ -1,19 +1,32 @@
-use super::{split_message, ChannelAdapter, IncomingMessage, OutgoingMessage, SentMessage};
+use super::filter::IngestFilter;
+use super::{
+    send_chunked, Attachment, Capabilities, ChannelAdapter, IncomingMessage, MessageKind,
+    OutgoingMessage, Quote, SentMessage,
+};
 use crate::config::DiscordConfig;
 use crate::db::now_ms;
 use async_trait::async_trait;
 use serenity::all::{
-    ChannelId, Context, CreateMessage, EditMessage, EventHandler, GatewayIntents, Message,
-    MessageId, Ready,
+    ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateAttachment,
+    CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    EditMessage, EventHandler, GatewayIntents, GuildId, Interaction, Message, MessageId,
+    MessageUpdateEvent, Reaction, ReactionType, Ready, User,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 
+/// Custom ID prefix of buttons; the rest is the label.
+const BUTTON_PREFIX: &str = "button:";
+
 /// Discord channel adapter using serenity.
 pub struct DiscordAdapter {
     config: DiscordConfig,
     http: Arc<RwLock<Option<Arc<serenity::http::Http>>>>,
+    /// Largest attachment downloaded; None to ignore attachments.
+    max_attachment_bytes: Option<u64>,
+    /// The gateway connection, for [`stop`](ChannelAdapter::stop).
+    shard_manager: std::sync::Mutex<Option<Arc<serenity::gateway::ShardManager>>>,
 }
 
 impl DiscordAdapter {
@@ -21,8 +34,16 @@ impl DiscordAdapter {
         Self {
             config,
             http: Arc::new(RwLock::new(None)),
+            max_attachment_bytes: None,
+            shard_manager: std::sync::Mutex::new(None),
         }
     }
+
+    /// Download attachments of up to `max_bytes`.
+    pub fn with_attachments(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_attachment_bytes = max_bytes;
+        self
+    }
 }
 
 struct Handler {
@@ -31,13 +52,14 @@ struct Handler {
     allowed_users: Vec<u64>,
     routing: HashMap<String, String>, // channel_name → worker_name
     http_store: Arc<RwLock<Option<Arc<serenity::http::Http>>>>,
+    filter: IngestFilter,
+    max_attachment_bytes: Option<u64>,
 }
 
 #[async_trait]
 impl EventHandler for Handler {
     async fn message(&self, ctx: Context, msg: Message) {
-        // Ignore bot messages
-        if msg.author.bot {
+        if self.filtered(&ctx, &msg.author, msg.guild_id.is_some(), &msg.content) {
             return;
         }
 
@@ -54,7 +76,8 @@ impl EventHandler for Handler {
         }
 
         let content = msg.content.clone();
-        if content.is_empty() {
+        let attachments = self.download_attachments(&msg).await;
+        if content.is_empty() && attachments.is_empty() {
             return;
         }
 
@@ -68,19 +91,160 @@ impl EventHandler for Handler {
             sender_id: msg.author.id.get().to_string(),
             sender_name: Some(msg.author.name.clone()),
             session_id: format!("dc-{}", channel_id.get()),
+            chat_id: Some(channel_id.get().to_string()),
+            thread_id: None,
             content,
             reply_to: msg
                 .referenced_message
                 .as_ref()
                 .map(|m| m.id.get().to_string()),
+            quote: msg
+                .referenced_message
+                .as_ref()
+                .filter(|m| !m.content.is_empty())
+                .map(|m| Quote {
+                    sender: Some(m.author.name.clone()),
+                    text: m.content.clone(),
+                    timestamp: Some(m.timestamp.unix_timestamp() as u64 * 1000),
+                }),
             timestamp: now_ms(),
             worker_hint,
             is_group: msg.guild_id.is_some(),
+            message_id: Some(msg.id.get().to_string()),
+            kind: MessageKind::New,
+            attachments,
+        };
+
+        let _ = self.tx.send(incoming);
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        _new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // Updates without content are embed loads, not edits
+        let (Some(author), Some(content)) = (event.author, event.content) else {
+            return;
+        };
+        if content.is_empty()
+            || !self.allowed(event.guild_id, Some(author.id.get()))
+            || self.filtered(&ctx, &author, event.guild_id.is_some(), &content)
+        {
+            return;
+        }
+
+        let incoming = IncomingMessage {
+            channel: "discord".into(),
+            sender_id: author.id.get().to_string(),
+            sender_name: Some(author.name.clone()),
+            session_id: format!("dc-{}", event.channel_id.get()),
+            chat_id: Some(event.channel_id.get().to_string()),
+            thread_id: None,
+            content,
+            reply_to: None,
+            quote: None,
+            timestamp: now_ms(),
+            worker_hint: None,
+            is_group: event.guild_id.is_some(),
+            message_id: Some(event.id.get().to_string()),
+            kind: MessageKind::Edited { previous: None },
+            attachments: Vec::new(),
         };
+        let _ = self.tx.send(incoming);
+    }
+
+    /// Deletions don't say whose message it was; the coalescer only acts on
+    /// IDs of messages it has seen from allowed users.
+    async fn message_delete(
+        &self,
+        _ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if !self.allowed(guild_id, None) {
+            return;
+        }
 
+        let incoming = IncomingMessage {
+            channel: "discord".into(),
+            sender_id: String::new(),
+            sender_name: None,
+            session_id: format!("dc-{}", channel_id.get()),
+            chat_id: Some(channel_id.get().to_string()),
+            thread_id: None,
+            content: String::new(),
+            reply_to: None,
+            quote: None,
+            timestamp: now_ms(),
+            worker_hint: None,
+            is_group: guild_id.is_some(),
+            message_id: Some(deleted_message_id.get().to_string()),
+            kind: MessageKind::Deleted { previous: None },
+            attachments: Vec::new(),
+        };
         let _ = self.tx.send(incoming);
     }
 
+    /// Unicode reactions of allowed users, ours excluded. The main loop
+    /// ignores reactions on anything but the bot's replies.
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        if user_id == ctx.cache.current_user().id
+            || !self.allowed(reaction.guild_id, Some(user_id.get()))
+        {
+            return;
+        }
+        let ReactionType::Unicode(emoji) = reaction.emoji else {
+            return;
+        };
+
+        let incoming = IncomingMessage {
+            channel: "discord".into(),
+            sender_id: user_id.get().to_string(),
+            sender_name: reaction.member.as_ref().map(|m| m.user.name.clone()),
+            session_id: format!("dc-{}", reaction.channel_id.get()),
+            chat_id: Some(reaction.channel_id.get().to_string()),
+            thread_id: None,
+            content: String::new(),
+            reply_to: None,
+            quote: None,
+            timestamp: now_ms(),
+            worker_hint: None,
+            is_group: reaction.guild_id.is_some(),
+            message_id: Some(reaction.message_id.get().to_string()),
+            kind: MessageKind::Reaction { emoji },
+            attachments: Vec::new(),
+        };
+        let _ = self.tx.send(incoming);
+    }
+
+    /// A tapped button: its label becomes a message from the user,
+    /// and the buttons are taken off the reply.
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Interaction::Component(component) = interaction else {
+            return;
+        };
+        let Some(text) = component.data.custom_id.strip_prefix(BUTTON_PREFIX) else {
+            return;
+        };
+        if !self.allowed(component.guild_id, Some(component.user.id.get())) {
+            return;
+        }
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().components(Vec::new()),
+        );
+        if let Err(e) = component.create_response(&ctx.http, response).await {
+            tracing::warn!("Failed to answer Discord interaction: {}", e);
+        }
+        let _ = self.tx.send(button_to_incoming(&component, text));
+    }
+
     async fn ready(&self, ctx: Context, ready: Ready) {
         tracing::info!("Discord bot connected as {}", ready.user.name);
         let mut http = self.http_store.write().await;
@@ -89,6 +253,69 @@ impl EventHandler for Handler {
 }
 
 impl Handler {
+    /// Guild and user allowlists. `user` is `None` when the event doesn't say.
+    fn allowed(&self, guild_id: Option<GuildId>, user: Option<u64>) -> bool {
+        if let Some(guild_id) = guild_id {
+            if !self.allowed_guilds.is_empty() && !self.allowed_guilds.contains(&guild_id.get()) {
+                return false;
+            }
+        }
+        match user {
+            Some(user) => self.allowed_users.is_empty() || self.allowed_users.contains(&user),
+            None => true,
+        }
+    }
+
+    /// Our own messages, and messages the ingest filter drops. Other bots
+    /// get through only with `ignore_bots = false`.
+    fn filtered(&self, ctx: &Context, author: &User, is_group: bool, content: &str) -> bool {
+        if author.bot && (self.filter.ignores_bots() || author.id == ctx.cache.current_user().id) {
+            return true;
+        }
+        match self.filter.reject(is_group, author.bot, content) {
+            Some(reason) => {
+                tracing::debug!("Ignoring {} message from {}", reason, author.name);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Attachments of `msg` within the size limit. Ones that are too large
+    /// or fail to download are skipped.
+    async fn download_attachments(&self, msg: &Message) -> Vec<Attachment> {
+        let Some(max) = self.max_attachment_bytes else {
+            return Vec::new();
+        };
+        let mut attachments = Vec::new();
+        for file in &msg.attachments {
+            if u64::from(file.size) > max {
+                tracing::warn!(
+                    "Skipping {}: {} bytes, over the limit",
+                    file.filename,
+                    file.size
+                );
+                continue;
+            }
+            match file.download().await {
+                Ok(data) => attachments.push(Attachment {
+                    name: file.filename.clone(),
+                    mime_type: file
+                        .content_type
+                        .as_deref()
+                        .and_then(|t| t.split(';').next())
+                        .unwrap_or("application/octet-stream")
+                        .to_string(),
+                    data,
+                    // Only voice messages carry a duration
+                    voice: file.duration_secs.is_some(),
+                }),
+                Err(e) => tracing::warn!("Failed to download {}: {}", file.filename, e),
+            }
+        }
+        attachments
+    }
+
     async fn resolve_routing(&self, ctx: &Context, channel_id: ChannelId) -> Option<String> {
         if self.routing.is_empty() {
             return None;
@@ -107,12 +334,55 @@ impl Handler {
     }
 }
 
+/// The message a tapped button stands for.
+fn button_to_incoming(component: &ComponentInteraction, text: &str) -> IncomingMessage {
+    let channel_id = component.channel_id;
+    IncomingMessage {
+        channel: "discord".into(),
+        sender_id: component.user.id.get().to_string(),
+        sender_name: Some(component.user.name.clone()),
+        session_id: format!("dc-{}", channel_id.get()),
+        chat_id: Some(channel_id.get().to_string()),
+        thread_id: None,
+        content: text.to_string(),
+        reply_to: None,
+        quote: None,
+        timestamp: now_ms(),
+        worker_hint: None,
+        is_group: component.guild_id.is_some(),
+        message_id: None,
+        kind: MessageKind::New,
+        attachments: Vec::new(),
+    }
+}
+
+/// One row of up to five buttons per five labels.
+fn button_rows(labels: &[String]) -> Vec<CreateActionRow> {
+    labels
+        .chunks(5)
+        .take(5)
+        .map(|row| {
+            CreateActionRow::Buttons(
+                row.iter()
+                    .map(|s| {
+                        CreateButton::new(format!("{}{}", BUTTON_PREFIX, s))
+                            .label(s.as_str())
+                            .style(ButtonStyle::Secondary)
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
 #[async_trait]
 impl ChannelAdapter for DiscordAdapter {
     async fn start(&self, tx: mpsc::UnboundedSender<IncomingMessage>) -> Result<(), anyhow::Error> {
         let intents = GatewayIntents::GUILD_MESSAGES
             | GatewayIntents::MESSAGE_CONTENT
-            | GatewayIntents::DIRECT_MESSAGES;
+            | GatewayIntents::DIRECT_MESSAGES
+            | GatewayIntents::GUILD_MESSAGE_REACTIONS
+            | GatewayIntents::DIRECT_MESSAGE_REACTIONS;
 
         let routing: HashMap<String, String> = self
             .config
@@ -127,11 +397,14 @@ impl ChannelAdapter for DiscordAdapter {
             allowed_users: self.config.allowed_users.clone(),
             routing,
             http_store: self.http.clone(),
+            filter: IngestFilter::new(&self.config.filters)?,
+            max_attachment_bytes: self.max_attachment_bytes,
         };
 
         let mut client = serenity::Client::builder(&self.config.bot_token, intents)
             .event_handler(handler)
             .await?;
+        *self.shard_manager.lock().unwrap() = Some(client.shard_manager.clone());
 
         tokio::spawn(async move {
             if let Err(e) = client.start().await {
@@ -143,31 +416,67 @@ impl ChannelAdapter for DiscordAdapter {
         Ok(())
     }
 
+    async fn stop(&self) {
+        let shard_manager = self.shard_manager.lock().unwrap().take();
+        if let Some(shard_manager) = shard_manager {
+            shard_manager.shutdown_all().await;
+            tracing::info!("Discord adapter stopped");
+        }
+    }
+
     async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
-        let channel_id: u64 = msg
-            .session_id
-            .strip_prefix("dc-")
-            .and_then(|s| s.parse().ok())
-            .ok_or_else(|| anyhow::anyhow!("Invalid discord session_id: {}", msg.session_id))?;
+        send_chunked(self, &msg).await
+    }
+
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    fn max_message_len(&self) -> usize {
+        2000
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            edit: true,
+            delete: true,
+            reactions: true,
+            threads: true,
+            attachments: self.max_attachment_bytes.is_some(),
+            buttons: true,
+            voice: true,
+            ..Default::default()
+        }
+    }
+
+    /// Later chunks reply to the first one. Replies work in DMs too, unlike threads.
+    async fn send_chunk(
+        &self,
+        session_id: &str,
+        text: &str,
+        thread: Option<&SentMessage>,
+    ) -> Result<Option<SentMessage>, anyhow::Error> {
+        let channel_id = parse_discord_session(session_id)
+            .ok_or_else(|| anyhow::anyhow!("Invalid discord session_id: {}", session_id))?;
 
         let http = self.http.read().await;
         let http = http
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Discord HTTP client not ready"))?;
 
-        let chunks = split_message(&msg.content, 2000);
-        for chunk in chunks {
-            let builder = CreateMessage::new().content(&chunk);
-            ChannelId::new(channel_id)
-                .send_message(http.as_ref(), builder)
-                .await?;
+        let mut builder = CreateMessage::new().content(text);
+        if let Some(id) = thread.and_then(|t| t.message_id.parse::<u64>().ok()) {
+            builder = builder.reference_message((ChannelId::new(channel_id), MessageId::new(id)));
         }
+        let sent = ChannelId::new(channel_id)
+            .send_message(http.as_ref(), builder)
+            .await?;
 
-        Ok(())
-    }
-
-    fn name(&self) -> &str {
-        "discord"
+        Ok(Some(SentMessage {
+            channel: "discord".into(),
+            session_id: session_id.to_string(),
+            message_id: sent.id.get().to_string(),
+        }))
     }
 
     async fn send_placeholder(&self, session_id: &str, text: &str) -> Option<SentMessage> {
@@ -229,6 +538,78 @@ impl ChannelAdapter for DiscordAdapter {
             .await?;
         Ok(())
     }
+
+    async fn delete_message(&self, handle: &SentMessage) -> Result<(), anyhow::Error> {
+        let channel_id = parse_discord_session(&handle.session_id)
+            .ok_or_else(|| anyhow::anyhow!("Invalid discord session_id"))?;
+        let message_id: u64 = handle
+            .message_id
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid discord message_id"))?;
+        let http = self.http.read().await;
+        let http = http
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Discord HTTP client not ready"))?;
+        ChannelId::new(channel_id)
+            .delete_message(http.as_ref(), MessageId::new(message_id))
+            .await?;
+        Ok(())
+    }
+
+    /// Buttons on the reply, or under `text` in a message of their own.
+    async fn offer_buttons(
+        &self,
+        session_id: &str,
+        reply: Option<&SentMessage>,
+        text: &str,
+        labels: &[String],
+    ) -> Result<(), anyhow::Error> {
+        let channel_id = parse_discord_session(session_id)
+            .ok_or_else(|| anyhow::anyhow!("Invalid discord session_id: {}", session_id))?;
+        let http = self.http.read().await;
+        let http = http
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Discord HTTP client not ready"))?;
+        let rows = button_rows(labels);
+        match reply.and_then(|r| r.message_id.parse::<u64>().ok()) {
+            Some(message_id) => {
+                ChannelId::new(channel_id)
+                    .edit_message(
+                        http.as_ref(),
+                        MessageId::new(message_id),
+                        EditMessage::new().components(rows),
+                    )
+                    .await?;
+            }
+            None => {
+                let builder = CreateMessage::new().content(text).components(rows);
+                ChannelId::new(channel_id)
+                    .send_message(http.as_ref(), builder)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sent as an audio attachment; bots can't post Discord's voice messages.
+    async fn send_voice(
+        &self,
+        session_id: &str,
+        audio: Vec<u8>,
+        format: &str,
+    ) -> Result<(), anyhow::Error> {
+        let channel_id = parse_discord_session(session_id)
+            .ok_or_else(|| anyhow::anyhow!("Invalid discord session_id: {}", session_id))?;
+        let http = self.http.read().await;
+        let http = http
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Discord HTTP client not ready"))?;
+        let file = CreateAttachment::bytes(audio, super::tts::file_name(format));
+        ChannelId::new(channel_id)
+            .send_message(http.as_ref(), CreateMessage::new().add_file(file))
+            .await?;
+        Ok(())
+    }
 }
 
 /// Parse a Discord session_id back to a channel_id.
@@ -239,6 +620,7 @@ pub fn parse_discord_session(session_id: &str) -> Option<u64> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::channels::split_message;
 
     #[test]
     fn test_parse_discord_session() {
@@ -0,0 +1,177 @@
+//! Ingest filters for group chats.
+//!
+//! Group channels carry other bots' output, join/leave notices from bridges
+//! and reaction GIFs, none of which the agent should read. Each adapter
+//! builds an [`IngestFilter`] from its `[channels.<name>.filters]` table and
+//! drops matching group messages before they reach the queue, so they never
+//! land on the tape or cost tokens. Direct messages are never filtered.
+
+use crate::config::IngestFilterConfig;
+use regex::Regex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FilterError {
+    #[error("Invalid ignore pattern '{pattern}': {source}")]
+    InvalidPattern {
+        pattern: String,
+        source: regex::Error,
+    },
+}
+
+/// Hosts whose links are GIFs rather than content.
+const GIF_HOSTS: [&str; 3] = ["tenor.com", "giphy.com", "gfycat.com"];
+
+/// Compiled ingest filter of one channel.
+#[derive(Debug)]
+pub struct IngestFilter {
+    ignore_bots: bool,
+    patterns: Vec<Regex>,
+    ignore_media_only: bool,
+}
+
+impl IngestFilter {
+    pub fn new(config: &IngestFilterConfig) -> Result<Self, FilterError> {
+        let patterns = config
+            .ignore_patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p).map_err(|source| FilterError::InvalidPattern {
+                    pattern: p.clone(),
+                    source,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            ignore_bots: config.ignore_bots,
+            patterns,
+            ignore_media_only: config.ignore_media_only,
+        })
+    }
+
+    /// Whether messages from bots other than ourselves are dropped.
+    pub fn ignores_bots(&self) -> bool {
+        self.ignore_bots
+    }
+
+    /// Why a message should be dropped, or None to keep it.
+    pub fn reject(&self, is_group: bool, from_bot: bool, text: &str) -> Option<&'static str> {
+        if !is_group {
+            return None;
+        }
+        if from_bot && self.ignore_bots {
+            return Some("bot");
+        }
+        if self.patterns.iter().any(|p| p.is_match(text)) {
+            return Some("pattern");
+        }
+        if self.ignore_media_only && is_media_only(text) {
+            return Some("media");
+        }
+        None
+    }
+}
+
+/// Text made only of GIF links, custom emoji (`<:name:id>` on Discord,
+/// `:name:` on Slack) and emoji characters.
+fn is_media_only(text: &str) -> bool {
+    let mut tokens = text.split_whitespace().peekable();
+    tokens.peek().is_some() && tokens.all(is_media_token)
+}
+
+fn is_media_token(token: &str) -> bool {
+    if let Some(rest) = token
+        .strip_prefix("https://")
+        .or_else(|| token.strip_prefix("http://"))
+    {
+        let host = rest.split('/').next().unwrap_or_default();
+        return GIF_HOSTS
+            .iter()
+            .any(|h| host == *h || host.ends_with(&format!(".{}", h)));
+    }
+    let shortcode = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+    };
+    if let Some(inner) = token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+        // <:name:id> or <a:name:id>
+        let parts: Vec<&str> = inner.split(':').collect();
+        return matches!(parts.as_slice(), ["" | "a", name, id]
+            if shortcode(name) && id.chars().all(|c| c.is_ascii_digit()));
+    }
+    if token.len() > 2 && token.starts_with(':') && token.ends_with(':') {
+        // Slack shortcodes, possibly several in a row (":tada::tada:")
+        return token.split(':').filter(|s| !s.is_empty()).all(shortcode);
+    }
+    // Emoji and their joiners and modifiers are neither ASCII nor alphanumeric
+    token.chars().all(|c| !c.is_ascii() && !c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(patterns: &[&str], media: bool) -> IngestFilter {
+        IngestFilter::new(&IngestFilterConfig {
+            ignore_bots: true,
+            ignore_patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            ignore_media_only: media,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reject() {
+        let f = filter(&[r"(?i)^\S+ (joined|left) the (channel|room)$"], true);
+        assert_eq!(f.reject(true, true, "Build #42 passed"), Some("bot"));
+        assert_eq!(
+            f.reject(true, false, "alice joined the room"),
+            Some("pattern")
+        );
+        assert_eq!(
+            f.reject(true, false, "https://tenor.com/view/cat-123"),
+            Some("media")
+        );
+        assert_eq!(f.reject(true, false, "Can you summarize this?"), None);
+        // Direct messages always get through
+        assert_eq!(f.reject(false, true, "alice joined the room"), None);
+        // Media filtering is opt-in
+        assert_eq!(filter(&[], false).reject(true, false, "🎉"), None);
+    }
+
+    #[test]
+    fn test_is_media_only() {
+        for text in [
+            "🎉",
+            "👍🏽 ❤️",
+            "<:party:123456> <a:dance:789>",
+            ":tada::tada:",
+            ":+1:",
+            "https://media.giphy.com/media/abc/giphy.gif",
+        ] {
+            assert!(is_media_only(text), "{}", text);
+        }
+        for text in [
+            "",
+            "ok 👍",
+            "?",
+            "https://example.com/report.pdf",
+            "see :tada: later",
+            "<@123456>",
+        ] {
+            assert!(!is_media_only(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let config = IngestFilterConfig {
+            ignore_patterns: vec!["(unclosed".into()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            IngestFilter::new(&config),
+            Err(FilterError::InvalidPattern { .. })
+        ));
+    }
+}
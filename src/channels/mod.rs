@@ -1,7 +1,14 @@
 pub mod coalesce;
+pub mod delivery;
 pub mod discord;
+pub mod filter;
+pub mod registry;
+pub mod signal;
 pub mod slack;
 pub mod telegram;
+pub mod tts;
+pub mod web;
+pub mod webhook;
 
 use async_trait::async_trait;
 use tokio::sync::mpsc;
@@ -13,13 +20,100 @@ pub struct IncomingMessage {
     pub sender_id: String,
     pub sender_name: Option<String>,
     pub session_id: String,
+    /// Platform chat the message came from: Telegram chat, Discord channel
+    /// or Slack channel ID. Recorded in the `sessions` table for delivery.
+    pub chat_id: Option<String>,
+    /// Thread within `chat_id`, for platforms where a session is a thread
+    /// (Slack thread ts).
+    pub thread_id: Option<String>,
     pub content: String,
     pub reply_to: Option<String>,
+    /// The message `reply_to` points at, when the platform sends it along.
+    pub quote: Option<Quote>,
     pub timestamp: u64,
     /// If set, route this message directly to a named worker instead of the main conductor.
     pub worker_hint: Option<String>,
     /// Whether this message originates from a group chat (vs a 1-on-1 DM).
     pub is_group: bool,
+    /// Platform-specific ID of the user's message, for matching edits and deletions.
+    pub message_id: Option<String>,
+    /// A new message, or an edit or deletion of message `message_id`.
+    pub kind: MessageKind,
+    /// Images and files sent with the message, downloaded by the adapter.
+    pub attachments: Vec<Attachment>,
+}
+
+/// A message quoted by a reply, as the platform delivered it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    /// Name or ID of whoever wrote it, when known.
+    pub sender: Option<String>,
+    pub text: String,
+    /// When it was sent (ms since epoch), when known.
+    pub timestamp: Option<u64>,
+}
+
+/// A file sent with an incoming message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// File name as sent, or one made up from the media type.
+    pub name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+    /// A voice message recorded in the app, transcribed for the agent when
+    /// `[channels.transcription]` is set.
+    pub voice: bool,
+}
+
+impl Attachment {
+    /// Whether the model can be shown the file as an image block.
+    pub fn is_image(&self) -> bool {
+        crate::attachments::is_image(&self.mime_type)
+    }
+}
+
+/// What an incoming event does to the conversation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum MessageKind {
+    #[default]
+    New,
+    /// The user edited message `message_id`; `content` is the new text.
+    /// `previous` is the text before the edit, when known.
+    Edited { previous: Option<String> },
+    /// The user deleted message `message_id`; `content` is empty.
+    Deleted { previous: Option<String> },
+    /// The user reacted with `emoji` to message `message_id`, usually one of
+    /// the agent's replies; `content` is empty.
+    Reaction { emoji: String },
+}
+
+impl IncomingMessage {
+    /// Note recorded on the tape for an edit or deletion of a message the
+    /// agent has already seen. `None` for new messages and reactions.
+    pub fn followup_note(&self) -> Option<String> {
+        match &self.kind {
+            MessageKind::New | MessageKind::Reaction { .. } => None,
+            MessageKind::Edited {
+                previous: Some(prev),
+            } => Some(format!(
+                "[The user edited their earlier message \"{}\". It now reads: \"{}\"]",
+                prev, self.content
+            )),
+            MessageKind::Edited { previous: None } => Some(format!(
+                "[The user edited an earlier message. It now reads: \"{}\"]",
+                self.content
+            )),
+            MessageKind::Deleted {
+                previous: Some(prev),
+            } => Some(format!(
+                "[The user deleted their earlier message \"{}\"]",
+                prev
+            )),
+            MessageKind::Deleted { previous: None } => {
+                Some("[The user deleted an earlier message]".to_string())
+            }
+        }
+    }
 }
 
 /// An outgoing message to send back through a channel.
@@ -40,6 +134,30 @@ pub struct SentMessage {
     pub message_id: String,
 }
 
+/// What a channel can do besides sending text. The pipeline asks instead of
+/// checking channel names, and does without what a channel lacks: no
+/// streaming without `edit`, no reaction commands without `reactions`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Sent messages can be edited, so replies stream into a placeholder.
+    pub edit: bool,
+    /// Sent messages can be deleted.
+    pub delete: bool,
+    /// A typing indicator can be shown while the agent works.
+    pub typing: bool,
+    /// Reactions to the agent's messages are reported as
+    /// [`MessageKind::Reaction`].
+    pub reactions: bool,
+    /// Messages can be sent as a reply to, or in a thread on, an earlier one.
+    pub threads: bool,
+    /// Images and files users send are downloaded for the agent.
+    pub attachments: bool,
+    /// Messages can carry buttons to tap.
+    pub buttons: bool,
+    /// Audio can be sent, for spoken replies.
+    pub voice: bool,
+}
+
 /// Channel adapter trait. Implement for each messaging platform.
 #[async_trait]
 pub trait ChannelAdapter: Send + Sync {
@@ -47,12 +165,48 @@ pub trait ChannelAdapter: Send + Sync {
     /// This should spawn background tasks and return immediately.
     async fn start(&self, tx: mpsc::UnboundedSender<IncomingMessage>) -> Result<(), anyhow::Error>;
 
-    /// Send a message through this channel.
+    /// Stop listening for messages, before the adapter is replaced or
+    /// removed on a config reload. Sending keeps working, for replies still
+    /// being written. Default: nothing to stop.
+    async fn stop(&self) {}
+
+    /// Send a message through this channel. Adapters that implement
+    /// [`send_chunk`](Self::send_chunk) forward to [`send_chunked`].
     async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error>;
 
     /// Channel name (e.g. "telegram", "discord").
     fn name(&self) -> &str;
 
+    /// Longest message the platform accepts, in bytes.
+    fn max_message_len(&self) -> usize {
+        4000
+    }
+
+    /// What this channel supports. Default: text only.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Send one message of at most `max_message_len()` bytes. With `thread`,
+    /// attach it to that message: a reply or a thread, whatever the platform
+    /// offers. Returns a handle for threading later chunks, if the platform
+    /// supports it. Default: plain `send()`, no threading.
+    async fn send_chunk(
+        &self,
+        session_id: &str,
+        text: &str,
+        _thread: Option<&SentMessage>,
+    ) -> Result<Option<SentMessage>, anyhow::Error> {
+        self.send(OutgoingMessage {
+            channel: self.name().to_string(),
+            session_id: session_id.to_string(),
+            content: text.to_string(),
+            reply_to: None,
+        })
+        .await?;
+        Ok(None)
+    }
+
     /// Start a "typing" indicator for the given session. Returns a handle that,
     /// when aborted, stops the indicator. Default: no-op.
     fn start_typing(&self, _session_id: &str) -> Option<tokio::task::JoinHandle<()>> {
@@ -73,6 +227,93 @@ pub trait ChannelAdapter: Send + Sync {
     ) -> Result<(), anyhow::Error> {
         Ok(())
     }
+
+    /// Delete a message the bot sent. Default: not supported.
+    async fn delete_message(&self, _handle: &SentMessage) -> Result<(), anyhow::Error> {
+        anyhow::bail!("{} can't delete messages", self.name())
+    }
+
+    /// Offer `labels` as buttons on `reply` if it was sent as an editable
+    /// message, otherwise under `text`. Tapping one sends its label as the
+    /// user's next message. Default: not supported.
+    async fn offer_buttons(
+        &self,
+        _session_id: &str,
+        _reply: Option<&SentMessage>,
+        _text: &str,
+        _labels: &[String],
+    ) -> Result<(), anyhow::Error> {
+        anyhow::bail!("{} can't show buttons", self.name())
+    }
+
+    /// Send `audio`, a file in `format` (see [`tts::file_name`]), as a voice
+    /// message, or as an audio file where that's all the platform shows.
+    /// Default: not supported.
+    async fn send_voice(
+        &self,
+        _session_id: &str,
+        _audio: Vec<u8>,
+        _format: &str,
+    ) -> Result<(), anyhow::Error> {
+        anyhow::bail!("{} can't send audio", self.name())
+    }
+}
+
+/// Room left in each chunk for its "(i/n)" marker.
+const CHUNK_MARKER_RESERVE: usize = 16;
+
+/// Split a message that doesn't fit into one and number the parts: "(1/3)\n…".
+/// Short messages are returned unchanged.
+pub fn number_chunks(text: &str, max_len: usize) -> Vec<String> {
+    if text.len() <= max_len {
+        return vec![text.to_string()];
+    }
+    let chunks = split_message(text, max_len.saturating_sub(CHUNK_MARKER_RESERVE).max(1));
+    let total = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("({}/{})\n{}", i + 1, total, chunk))
+        .collect()
+}
+
+/// Send a message as numbered chunks, threading the rest under the first
+/// where the channel has threads.
+pub async fn send_chunked<A: ChannelAdapter + ?Sized>(
+    adapter: &A,
+    msg: &OutgoingMessage,
+) -> Result<(), anyhow::Error> {
+    let threads = adapter.capabilities().threads;
+    let mut thread: Option<SentMessage> = None;
+    for chunk in number_chunks(&msg.content, adapter.max_message_len()) {
+        let sent = adapter
+            .send_chunk(&msg.session_id, &chunk, thread.as_ref())
+            .await?;
+        if thread.is_none() && threads {
+            thread = sent;
+        }
+    }
+    Ok(())
+}
+
+/// Replace a placeholder with `text`. If it doesn't fit, the placeholder gets
+/// the first numbered chunk and the rest are threaded under it.
+pub async fn edit_chunked<A: ChannelAdapter + ?Sized>(
+    adapter: &A,
+    handle: &SentMessage,
+    text: &str,
+) -> Result<(), anyhow::Error> {
+    let mut chunks = number_chunks(text, adapter.max_message_len()).into_iter();
+    if let Some(first) = chunks.next() {
+        adapter.edit_message(handle, &first).await?;
+    }
+    let thread = Some(handle).filter(|_| adapter.capabilities().threads);
+    for chunk in chunks {
+        adapter
+            .send_chunk(&handle.session_id, &chunk, thread)
+            .await?;
+    }
+    Ok(())
 }
 
 /// Split a message into chunks at newline boundaries, respecting max length.
@@ -142,6 +383,102 @@ mod tests {
         assert_eq!(chunks[2].len(), 20);
     }
 
+    #[test]
+    fn test_number_chunks() {
+        assert_eq!(number_chunks("short", 100), vec!["short"]);
+
+        let text = "a".repeat(50);
+        let chunks = number_chunks(&text, 36);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], format!("(1/3)\n{}", "a".repeat(20)));
+        assert!(chunks[2].starts_with("(3/3)\n"));
+        assert!(chunks.iter().all(|c| c.len() <= 36));
+    }
+
+    /// Records each chunk and the message it was threaded under.
+    #[derive(Default)]
+    struct RecordingAdapter {
+        sent: std::sync::Mutex<Vec<(String, Option<String>)>>,
+        /// Pretend the channel has no threads.
+        flat: bool,
+    }
+
+    #[async_trait]
+    impl ChannelAdapter for RecordingAdapter {
+        async fn start(
+            &self,
+            _tx: mpsc::UnboundedSender<IncomingMessage>,
+        ) -> Result<(), anyhow::Error> {
+            Ok(())
+        }
+        async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
+            send_chunked(self, &msg).await
+        }
+        fn name(&self) -> &str {
+            "recording"
+        }
+        fn max_message_len(&self) -> usize {
+            30
+        }
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                threads: !self.flat,
+                ..Default::default()
+            }
+        }
+        async fn send_chunk(
+            &self,
+            session_id: &str,
+            text: &str,
+            thread: Option<&SentMessage>,
+        ) -> Result<Option<SentMessage>, anyhow::Error> {
+            let mut sent = self.sent.lock().unwrap();
+            sent.push((text.to_string(), thread.map(|t| t.message_id.clone())));
+            Ok(Some(SentMessage {
+                channel: "recording".into(),
+                session_id: session_id.to_string(),
+                message_id: format!("m{}", sent.len()),
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_chunked_threads_under_first() {
+        let adapter = RecordingAdapter::default();
+        let msg = OutgoingMessage {
+            channel: "recording".into(),
+            session_id: "r-1".into(),
+            content: "first line\nsecond line\nthird line\n".into(),
+            reply_to: None,
+        };
+        adapter.send(msg).await.unwrap();
+
+        let sent = adapter.sent.lock().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0], ("(1/3)\nfirst line\n".to_string(), None));
+        assert_eq!(sent[1].1.as_deref(), Some("m1"));
+        assert_eq!(sent[2].1.as_deref(), Some("m1"));
+    }
+
+    #[tokio::test]
+    async fn test_send_chunked_without_threads() {
+        let adapter = RecordingAdapter {
+            flat: true,
+            ..Default::default()
+        };
+        let msg = OutgoingMessage {
+            channel: "recording".into(),
+            session_id: "r-1".into(),
+            content: "first line\nsecond line\nthird line\n".into(),
+            reply_to: None,
+        };
+        adapter.send(msg).await.unwrap();
+
+        let sent = adapter.sent.lock().unwrap();
+        assert_eq!(sent.len(), 3);
+        assert!(sent.iter().all(|(_, thread)| thread.is_none()));
+    }
+
     // -- Typing indicator tests --
 
     struct NoopAdapter;
@@ -166,6 +503,7 @@ mod tests {
     fn test_default_start_typing_returns_none() {
         let adapter = NoopAdapter;
         assert!(adapter.start_typing("test-session").is_none());
+        assert_eq!(adapter.capabilities(), Capabilities::default());
     }
 
     struct TypingAdapter;
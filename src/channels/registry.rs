@@ -0,0 +1,170 @@
+//! The running channel adapters.
+//!
+//! Replies, scheduler deliveries and drafts look their adapter up by channel
+//! name in [`Adapters`] when they are sent, so an adapter replaced on a config
+//! reload is used from then on. Telegram, Discord, Slack and Signal are
+//! started, stopped and restarted by [`reload`]; the webhook and web chat
+//! adapters hand their inboxes to the web server and change only with a
+//! restart.
+
+use super::{ChannelAdapter, IncomingMessage};
+use crate::config::Config;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+
+/// Channels whose adapter can be started and stopped while running.
+pub const RELOADABLE: &[&str] = &["telegram", "discord", "slack", "signal"];
+
+/// The running adapters, shared by the main loop, the session workers and
+/// the delivery task.
+#[derive(Clone, Default)]
+pub struct Adapters {
+    inner: Arc<RwLock<Vec<Arc<dyn ChannelAdapter>>>>,
+}
+
+impl Adapters {
+    /// The adapter of `channel`, if it is running.
+    pub fn get(&self, channel: &str) -> Option<Arc<dyn ChannelAdapter>> {
+        let adapters = self.inner.read().unwrap();
+        adapters.iter().find(|a| a.name() == channel).cloned()
+    }
+
+    /// Names of the running channels.
+    pub fn names(&self) -> Vec<String> {
+        let adapters = self.inner.read().unwrap();
+        adapters.iter().map(|a| a.name().to_string()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.read().unwrap().is_empty()
+    }
+
+    /// Add `adapter`, in place of a running one of the same channel.
+    pub fn insert(&self, adapter: Arc<dyn ChannelAdapter>) {
+        let mut adapters = self.inner.write().unwrap();
+        adapters.retain(|a| a.name() != adapter.name());
+        adapters.push(adapter);
+    }
+
+    /// Take the adapter of `channel` out, if it is running.
+    pub fn remove(&self, channel: &str) -> Option<Arc<dyn ChannelAdapter>> {
+        let mut adapters = self.inner.write().unwrap();
+        let i = adapters.iter().position(|a| a.name() == channel)?;
+        Some(adapters.remove(i))
+    }
+}
+
+/// The adapter for `channel` as configured in `config`, not started yet.
+/// `None` if the channel isn't configured or isn't one of [`RELOADABLE`].
+pub fn build(config: &Config, channel: &str) -> Option<Arc<dyn ChannelAdapter>> {
+    let limit = config.attachments.download_limit();
+    let channels = &config.channels;
+    match channel {
+        "telegram" => channels.telegram.clone().map(|c| {
+            Arc::new(super::telegram::TelegramAdapter::new(c).with_attachments(limit))
+                as Arc<dyn ChannelAdapter>
+        }),
+        "discord" => channels.discord.clone().map(|c| {
+            Arc::new(super::discord::DiscordAdapter::new(c).with_attachments(limit))
+                as Arc<dyn ChannelAdapter>
+        }),
+        "slack" => channels.slack.clone().map(|c| {
+            Arc::new(super::slack::SlackAdapter::new(c).with_attachments(limit))
+                as Arc<dyn ChannelAdapter>
+        }),
+        "signal" => channels
+            .signal
+            .clone()
+            .map(|c| Arc::new(super::signal::SignalAdapter::new(c)) as Arc<dyn ChannelAdapter>),
+        _ => None,
+    }
+}
+
+/// Bring the adapters of `channels` in line with `config`: stop the running
+/// one, then start one from the new settings if the channel is still
+/// configured. The old adapter stops first, since platforms like Telegram
+/// take one connection per bot token. A channel that fails to start is left
+/// stopped, with the error logged; messages already coalescing for it are
+/// dropped by the main loop.
+pub async fn reload(
+    adapters: &Adapters,
+    channels: &[&str],
+    config: &Config,
+    tx: &mpsc::UnboundedSender<IncomingMessage>,
+) {
+    for &channel in channels {
+        let old = adapters.remove(channel);
+        if let Some(ref old) = old {
+            old.stop().await;
+        }
+        let Some(adapter) = build(config, channel) else {
+            tracing::info!("Channel {} removed", channel);
+            continue;
+        };
+        match adapter.start(tx.clone()).await {
+            Ok(()) => {
+                adapters.insert(adapter);
+                let action = if old.is_some() { "restarted" } else { "added" };
+                tracing::info!("Channel {} {}", channel, action);
+            }
+            Err(e) => tracing::error!("Channel {} failed to start: {}", channel, e),
+        }
+    }
+    if adapters.is_empty() {
+        tracing::warn!("No channels running; messages will only come from the scheduler");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::OutgoingMessage;
+    use crate::config::parse_config;
+    use async_trait::async_trait;
+
+    struct Named(&'static str, u8);
+
+    #[async_trait]
+    impl ChannelAdapter for Named {
+        async fn start(
+            &self,
+            _tx: mpsc::UnboundedSender<IncomingMessage>,
+        ) -> Result<(), anyhow::Error> {
+            Ok(())
+        }
+        async fn send(&self, _msg: OutgoingMessage) -> Result<(), anyhow::Error> {
+            Ok(())
+        }
+        fn name(&self) -> &str {
+            self.0
+        }
+        fn max_message_len(&self) -> usize {
+            self.1 as usize
+        }
+    }
+
+    #[test]
+    fn test_insert_replaces_by_name() {
+        let adapters = Adapters::default();
+        adapters.insert(Arc::new(Named("telegram", 1)));
+        adapters.insert(Arc::new(Named("slack", 1)));
+        adapters.insert(Arc::new(Named("telegram", 2)));
+        assert_eq!(adapters.names(), vec!["slack", "telegram"]);
+        assert_eq!(adapters.get("telegram").unwrap().max_message_len(), 2);
+        assert!(adapters.remove("slack").is_some());
+        assert!(adapters.remove("slack").is_none());
+        assert!(adapters.get("slack").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reload_removes_unconfigured_channels() {
+        let config = parse_config("[agent]\nmodel = \"m\"\napi_key = \"k\"").unwrap();
+        let adapters = Adapters::default();
+        adapters.insert(Arc::new(Named("telegram", 1)));
+        adapters.insert(Arc::new(Named("webhook", 1)));
+        let (tx, _rx) = mpsc::unbounded_channel();
+        reload(&adapters, &["telegram"], &config, &tx).await;
+        assert_eq!(adapters.names(), vec!["webhook"]);
+        assert!(build(&config, "webhook").is_none());
+    }
+}
@@ -0,0 +1,480 @@
+//! Signal, through a local `signal-cli` daemon.
+//!
+//! yoclaw doesn't speak the Signal protocol itself: run `signal-cli -a
+//! <number> daemon --tcp` (or `--socket <path>`) with a registered or linked
+//! account, and point `[channels.signal] endpoint` at it. The daemon speaks
+//! line-delimited JSON-RPC. One connection stays open for `receive`
+//! notifications and is reopened when it drops; sends, typing indicators and
+//! deletions each open a short connection of their own.
+//!
+//! Direct chats are session `sig-<number>` (the sender's UUID when their
+//! number is hidden), groups `sig-group-<groupId>`.
+
+use super::filter::IngestFilter;
+use super::{
+    send_chunked, Capabilities, ChannelAdapter, IncomingMessage, MessageKind, OutgoingMessage,
+    Quote, SentMessage,
+};
+use crate::config::SignalConfig;
+use crate::db::now_ms;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+/// Session ID prefix of Signal sessions.
+pub const SESSION_PREFIX: &str = "sig-";
+
+const GROUP_PREFIX: &str = "sig-group-";
+
+/// How long a request waits for the daemon's response.
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignalError {
+    #[error("signal-cli connection error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid JSON from signal-cli: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("signal-cli error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("signal-cli closed the connection")]
+    Closed,
+    #[error("signal-cli didn't respond in time")]
+    Timeout,
+    #[error("Invalid signal session_id: {0}")]
+    InvalidSession(String),
+    #[error("UNIX sockets aren't supported on this platform")]
+    Unsupported,
+}
+
+/// Who a session's messages go to.
+#[derive(Debug, PartialEq)]
+pub enum SignalTarget {
+    /// Phone number or UUID of a direct chat.
+    Direct(String),
+    Group(String),
+}
+
+impl SignalTarget {
+    /// JSON-RPC params addressing this target.
+    fn params(&self) -> serde_json::Map<String, Value> {
+        let mut params = serde_json::Map::new();
+        match self {
+            SignalTarget::Direct(number) => params.insert("recipient".into(), json!([number])),
+            SignalTarget::Group(id) => params.insert("groupId".into(), json!(id)),
+        };
+        params
+    }
+}
+
+/// Parse a Signal session_id back to its recipient.
+pub fn parse_signal_session(session_id: &str) -> Option<SignalTarget> {
+    if let Some(id) = session_id.strip_prefix(GROUP_PREFIX) {
+        return (!id.is_empty()).then(|| SignalTarget::Group(id.to_string()));
+    }
+    let number = session_id.strip_prefix(SESSION_PREFIX)?;
+    (!number.is_empty()).then(|| SignalTarget::Direct(number.to_string()))
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// Connection settings for the daemon, shared with spawned tasks.
+struct SignalClient {
+    endpoint: String,
+    account: Option<String>,
+}
+
+impl SignalClient {
+    async fn connect(&self) -> Result<Box<dyn Stream>, SignalError> {
+        // Paths are sockets, anything else is host:port
+        if self.endpoint.starts_with('/') || self.endpoint.starts_with('.') {
+            #[cfg(unix)]
+            return Ok(Box::new(
+                tokio::net::UnixStream::connect(&self.endpoint).await?,
+            ));
+            #[cfg(not(unix))]
+            return Err(SignalError::Unsupported);
+        }
+        Ok(Box::new(
+            tokio::net::TcpStream::connect(&self.endpoint).await?,
+        ))
+    }
+
+    /// Call `method` and return its result.
+    async fn call(
+        &self,
+        method: &str,
+        mut params: serde_json::Map<String, Value>,
+    ) -> Result<Value, SignalError> {
+        if let Some(ref account) = self.account {
+            params.insert("account".into(), json!(account));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": id});
+
+        let mut stream = self.connect().await?;
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        stream.write_all(line.as_bytes()).await?;
+
+        let mut lines = BufReader::new(stream).lines();
+        loop {
+            let line = tokio::time::timeout(RPC_TIMEOUT, lines.next_line())
+                .await
+                .map_err(|_| SignalError::Timeout)??
+                .ok_or(SignalError::Closed)?;
+            // The daemon may push notifications on any connection; skip them
+            let response: Value = serde_json::from_str(&line)?;
+            if response["id"] != json!(id) {
+                continue;
+            }
+            if let Some(error) = response.get("error") {
+                return Err(SignalError::Rpc {
+                    code: error["code"].as_i64().unwrap_or(0),
+                    message: error["message"].as_str().unwrap_or("").to_string(),
+                });
+            }
+            return Ok(response.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    /// Read `receive` notifications until the connection drops.
+    async fn receive(
+        &self,
+        tx: &mpsc::UnboundedSender<IncomingMessage>,
+        allowed: &[String],
+        filter: &IngestFilter,
+    ) -> Result<(), SignalError> {
+        let stream = self.connect().await?;
+        let mut lines = BufReader::new(stream).lines();
+        while let Some(line) = lines.next_line().await? {
+            let Ok(notification) = serde_json::from_str::<Value>(&line) else {
+                tracing::debug!("Ignoring unparseable line from signal-cli");
+                continue;
+            };
+            if notification["method"] != "receive" {
+                continue;
+            }
+            let envelope = &notification["params"]["envelope"];
+            if let Some(incoming) = to_incoming(envelope, allowed, filter) {
+                let _ = tx.send(incoming);
+            }
+        }
+        Err(SignalError::Closed)
+    }
+}
+
+/// Convert a received envelope. `None` for senders outside the allowlist,
+/// receipts, typing and sync messages, messages without text (attachments
+/// only, stickers) and group messages the ingest filter drops.
+fn to_incoming(
+    envelope: &Value,
+    allowed: &[String],
+    filter: &IngestFilter,
+) -> Option<IncomingMessage> {
+    let sender_id = envelope["sourceNumber"]
+        .as_str()
+        .or_else(|| envelope["sourceUuid"].as_str())
+        .or_else(|| envelope["source"].as_str())?
+        .to_string();
+    if !allowed.is_empty() && !allowed.contains(&sender_id) {
+        let uuid = envelope["sourceUuid"].as_str().unwrap_or("");
+        if !allowed.iter().any(|a| a == uuid) {
+            return None;
+        }
+    }
+
+    let data = envelope.get("dataMessage")?;
+    let text = data["message"].as_str().unwrap_or("").to_string();
+    if text.is_empty() {
+        return None;
+    }
+
+    let group_id = data["groupInfo"]["groupId"].as_str();
+    let is_group = group_id.is_some();
+    let session_id = match group_id {
+        Some(id) => format!("{}{}", GROUP_PREFIX, id),
+        None => format!("{}{}", SESSION_PREFIX, sender_id),
+    };
+    if let Some(reason) = filter.reject(is_group, false, &text) {
+        tracing::debug!("Ignoring {} message in {}", reason, session_id);
+        return None;
+    }
+    Some(IncomingMessage {
+        channel: "signal".into(),
+        sender_name: envelope["sourceName"]
+            .as_str()
+            .filter(|n| !n.is_empty())
+            .map(String::from),
+        chat_id: Some(group_id.unwrap_or(&sender_id).to_string()),
+        sender_id,
+        session_id,
+        thread_id: None,
+        content: text,
+        reply_to: data["quote"]["id"].as_u64().map(|ts| ts.to_string()),
+        quote: data["quote"]["text"]
+            .as_str()
+            .filter(|t| !t.is_empty())
+            .map(|text| Quote {
+                sender: data["quote"]["authorNumber"]
+                    .as_str()
+                    .or_else(|| data["quote"]["author"].as_str())
+                    .map(String::from),
+                text: text.to_string(),
+                // Signal identifies a message by its sent time
+                timestamp: data["quote"]["id"].as_u64(),
+            }),
+        timestamp: now_ms(),
+        worker_hint: None,
+        is_group,
+        message_id: data["timestamp"].as_u64().map(|ts| ts.to_string()),
+        kind: MessageKind::New,
+        attachments: Vec::new(),
+    })
+}
+
+/// Signal channel adapter using a signal-cli JSON-RPC daemon.
+pub struct SignalAdapter {
+    client: Arc<SignalClient>,
+    config: SignalConfig,
+    /// The receive loop, for [`stop`](ChannelAdapter::stop).
+    receiver: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl SignalAdapter {
+    pub fn new(config: SignalConfig) -> Self {
+        let client = Arc::new(SignalClient {
+            endpoint: config.endpoint.clone(),
+            account: config.account.clone(),
+        });
+        Self {
+            client,
+            config,
+            receiver: Mutex::new(None),
+        }
+    }
+
+    fn target(session_id: &str) -> Result<SignalTarget, SignalError> {
+        parse_signal_session(session_id)
+            .ok_or_else(|| SignalError::InvalidSession(session_id.to_string()))
+    }
+}
+
+#[async_trait]
+impl ChannelAdapter for SignalAdapter {
+    async fn start(&self, tx: mpsc::UnboundedSender<IncomingMessage>) -> Result<(), anyhow::Error> {
+        let client = self.client.clone();
+        let allowed = self.config.allowed_numbers.clone();
+        let filter = IngestFilter::new(&self.config.filters)?;
+
+        let handle = tokio::spawn(async move {
+            loop {
+                if let Err(e) = client.receive(&tx, &allowed, &filter).await {
+                    tracing::warn!("Signal receive loop: {}, reconnecting in 5s", e);
+                }
+                if tx.is_closed() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        });
+
+        *self.receiver.lock().unwrap() = Some(handle);
+
+        tracing::info!("Signal adapter started ({})", self.config.endpoint);
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        if let Some(handle) = self.receiver.lock().unwrap().take() {
+            handle.abort();
+            tracing::info!("Signal adapter stopped");
+        }
+    }
+
+    async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
+        send_chunked(self, &msg).await
+    }
+
+    fn name(&self) -> &str {
+        "signal"
+    }
+
+    /// Signal turns longer messages into attachments.
+    fn max_message_len(&self) -> usize {
+        2000
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            delete: true,
+            typing: true,
+            threads: true,
+            ..Default::default()
+        }
+    }
+
+    /// Later chunks quote the first one.
+    async fn send_chunk(
+        &self,
+        session_id: &str,
+        text: &str,
+        thread: Option<&SentMessage>,
+    ) -> Result<Option<SentMessage>, anyhow::Error> {
+        let target = Self::target(session_id)?;
+        let mut params = target.params();
+        params.insert("message".into(), json!(text));
+        if let Some(ts) = thread.and_then(|t| t.message_id.parse::<u64>().ok()) {
+            params.insert("quoteTimestamp".into(), json!(ts));
+            if let Some(ref account) = self.config.account {
+                params.insert("quoteAuthor".into(), json!(account));
+            }
+        }
+        let result = self.client.call("send", params).await?;
+
+        Ok(result["timestamp"].as_u64().map(|ts| SentMessage {
+            channel: "signal".into(),
+            session_id: session_id.to_string(),
+            message_id: ts.to_string(),
+        }))
+    }
+
+    fn start_typing(&self, session_id: &str) -> Option<tokio::task::JoinHandle<()>> {
+        let params = parse_signal_session(session_id)?.params();
+        let client = self.client.clone();
+        Some(tokio::spawn(async move {
+            loop {
+                let _ = client.call("sendTyping", params.clone()).await;
+                // Signal clients drop the indicator after ~15s
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+        }))
+    }
+
+    async fn delete_message(&self, handle: &SentMessage) -> Result<(), anyhow::Error> {
+        let mut params = Self::target(&handle.session_id)?.params();
+        let ts: u64 = handle.message_id.parse()?;
+        params.insert("targetTimestamp".into(), json!(ts));
+        self.client.call("remoteDelete", params).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IngestFilterConfig;
+
+    fn filter() -> IngestFilter {
+        IngestFilter::new(&IngestFilterConfig::default()).unwrap()
+    }
+
+    fn envelope(group: Option<&str>, text: &str) -> Value {
+        let mut data = json!({"timestamp": 1700000000123u64, "message": text});
+        if let Some(id) = group {
+            data["groupInfo"] = json!({"groupId": id, "type": "DELIVER"});
+        }
+        json!({
+            "source": "+15550002222",
+            "sourceNumber": "+15550002222",
+            "sourceUuid": "0c8a5e8e-1111-2222-3333-444455556666",
+            "sourceName": "Alice",
+            "timestamp": 1700000000123u64,
+            "dataMessage": data,
+        })
+    }
+
+    #[test]
+    fn test_parse_signal_session() {
+        assert_eq!(
+            parse_signal_session("sig-+15550002222"),
+            Some(SignalTarget::Direct("+15550002222".into()))
+        );
+        assert_eq!(
+            parse_signal_session("sig-group-aGVsbG8="),
+            Some(SignalTarget::Group("aGVsbG8=".into()))
+        );
+        assert_eq!(parse_signal_session("sig-"), None);
+        assert_eq!(parse_signal_session("tg-42"), None);
+    }
+
+    #[test]
+    fn test_to_incoming() {
+        let direct = to_incoming(&envelope(None, "hi"), &[], &filter()).unwrap();
+        assert_eq!(direct.channel, "signal");
+        assert_eq!(direct.session_id, "sig-+15550002222");
+        assert_eq!(direct.sender_name.as_deref(), Some("Alice"));
+        assert_eq!(direct.message_id.as_deref(), Some("1700000000123"));
+        assert!(!direct.is_group);
+
+        let group = to_incoming(&envelope(Some("aGVsbG8="), "hi"), &[], &filter()).unwrap();
+        assert_eq!(group.session_id, "sig-group-aGVsbG8=");
+        assert_eq!(group.chat_id.as_deref(), Some("aGVsbG8="));
+        assert!(group.is_group);
+
+        // Allowlist by number or UUID
+        let allowed = vec!["+15550009999".to_string()];
+        assert!(to_incoming(&envelope(None, "hi"), &allowed, &filter()).is_none());
+        let allowed = vec!["0c8a5e8e-1111-2222-3333-444455556666".to_string()];
+        assert!(to_incoming(&envelope(None, "hi"), &allowed, &filter()).is_some());
+
+        // Attachment-only messages and receipts carry no text
+        assert!(to_incoming(&envelope(None, ""), &[], &filter()).is_none());
+        let receipt = json!({"sourceNumber": "+15550002222", "receiptMessage": {}});
+        assert!(to_incoming(&receipt, &[], &filter()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_send_chunk() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let daemon = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = tokio::io::split(stream);
+            let line = BufReader::new(read)
+                .lines()
+                .next_line()
+                .await
+                .unwrap()
+                .unwrap();
+            let request: Value = serde_json::from_str(&line).unwrap();
+            // A notification first, which the caller must skip
+            let reply = format!(
+                "{}\n{}\n",
+                json!({"jsonrpc": "2.0", "method": "receive", "params": {}}),
+                json!({"jsonrpc": "2.0", "id": request["id"], "result": {"timestamp": 42}})
+            );
+            write.write_all(reply.as_bytes()).await.unwrap();
+            request
+        });
+
+        let adapter = SignalAdapter::new(SignalConfig {
+            account: Some("+15550001111".into()),
+            endpoint,
+            allowed_numbers: vec![],
+            debounce_ms: 0,
+            greeting: None,
+            citations: false,
+            latency: None,
+            filters: IngestFilterConfig::default(),
+        });
+        let sent = adapter
+            .send_chunk("sig-group-aGVsbG8=", "hello", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sent.message_id, "42");
+
+        let request = daemon.await.unwrap();
+        assert_eq!(request["method"], "send");
+        assert_eq!(request["params"]["groupId"], "aGVsbG8=");
+        assert_eq!(request["params"]["message"], "hello");
+        assert_eq!(request["params"]["account"], "+15550001111");
+
+        assert!(adapter.send_chunk("tg-42", "hello", None).await.is_err());
+    }
+}
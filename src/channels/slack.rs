@@ -1,10 +1,14 @@
-use super::{split_message, ChannelAdapter, IncomingMessage, OutgoingMessage, SentMessage};
+use super::filter::IngestFilter;
+use super::{
+    send_chunked, Attachment, Capabilities, ChannelAdapter, IncomingMessage, MessageKind,
+    OutgoingMessage, SentMessage,
+};
 use crate::config::SlackConfig;
 use crate::db::now_ms;
 use async_trait::async_trait;
 use slack_morphism::prelude::*;
 use slack_morphism_hyper::*;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 
 /// State stored in SlackClientEventsUserState for the push events callback.
@@ -12,6 +16,11 @@ struct SlackAdapterState {
     tx: mpsc::UnboundedSender<IncomingMessage>,
     allowed_channels: Vec<String>,
     allowed_users: Vec<String>,
+    filter: IngestFilter,
+    /// Bot token, for downloading shared files.
+    bot_token: String,
+    max_attachment_bytes: Option<u64>,
+    http: reqwest::Client,
 }
 
 /// Slack channel adapter using slack-morphism with Socket Mode.
@@ -19,8 +28,17 @@ pub struct SlackAdapter {
     config: SlackConfig,
     client: Arc<SlackClient<SlackClientHyperHttpsConnector>>,
     bot_token: SlackApiToken,
+    /// Largest attachment downloaded; None to ignore attachments.
+    max_attachment_bytes: Option<u64>,
+    /// The Socket Mode listener, for [`stop`](ChannelAdapter::stop).
+    listener: Mutex<Option<SlackListener>>,
 }
 
+type SlackListener = (
+    Arc<SlackClientSocketModeListener<SlackClientHyperHttpsConnector>>,
+    tokio::task::JoinHandle<()>,
+);
+
 impl SlackAdapter {
     pub fn new(config: SlackConfig) -> Self {
         let connector = SlackClientHyperConnector::new();
@@ -30,8 +48,16 @@ impl SlackAdapter {
             config,
             client,
             bot_token,
+            max_attachment_bytes: None,
+            listener: Mutex::new(None),
         }
     }
+
+    /// Download shared files of up to `max_bytes`.
+    pub fn with_attachments(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_attachment_bytes = max_bytes;
+        self
+    }
 }
 
 async fn push_events_handler(
@@ -44,12 +70,7 @@ async fn push_events_handler(
     drop(states_r);
 
     if let Some(state) = state {
-        handle_push_event(
-            event,
-            &state.tx,
-            &state.allowed_channels,
-            &state.allowed_users,
-        );
+        handle_push_event(event, &state).await;
     }
     Ok(())
 }
@@ -72,6 +93,10 @@ impl ChannelAdapter for SlackAdapter {
             tx,
             allowed_channels: self.config.allowed_channels.clone(),
             allowed_users: self.config.allowed_users.clone(),
+            filter: IngestFilter::new(&self.config.filters)?,
+            bot_token: self.config.bot_token.clone(),
+            max_attachment_bytes: self.max_attachment_bytes,
+            http: reqwest::Client::new(),
         });
 
         let socket_mode_config = SlackClientSocketModeConfig::new().with_max_connections_count(2);
@@ -85,42 +110,79 @@ impl ChannelAdapter for SlackAdapter {
         let callbacks =
             SlackSocketModeListenerCallbacks::new().with_push_events(push_events_handler);
 
-        let listener =
-            SlackClientSocketModeListener::new(&socket_mode_config, listener_env, callbacks);
+        let listener = Arc::new(SlackClientSocketModeListener::new(
+            &socket_mode_config,
+            listener_env,
+            callbacks,
+        ));
         listener.listen_for(&app_token).await?;
 
-        tokio::spawn(async move {
-            listener.serve().await;
+        let serving = listener.clone();
+        let handle = tokio::spawn(async move {
+            serving.serve().await;
         });
+        *self.listener.lock().unwrap() = Some((listener, handle));
 
         tracing::info!("Slack adapter started (Socket Mode)");
         Ok(())
     }
 
-    async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
-        let (channel_id, thread_ts) = parse_slack_session(&msg.session_id)
-            .ok_or_else(|| anyhow::anyhow!("Invalid slack session_id: {}", msg.session_id))?;
+    async fn stop(&self) {
+        let running = self.listener.lock().unwrap().take();
+        if let Some((listener, handle)) = running {
+            // `serve()` only returns on a signal
+            handle.abort();
+            listener.shutdown().await;
+            tracing::info!("Slack adapter stopped");
+        }
+    }
 
-        let session = self.client.open_session(&self.bot_token);
-        let chunks = split_message(&msg.content, 4000);
+    async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
+        send_chunked(self, &msg).await
+    }
 
-        for chunk in chunks {
-            let content = SlackMessageContent::new().with_text(chunk);
-            let mut request =
-                SlackApiChatPostMessageRequest::new(SlackChannelId(channel_id.clone()), content);
+    fn name(&self) -> &str {
+        "slack"
+    }
 
-            if let Some(ref ts) = thread_ts {
-                request = request.with_thread_ts(SlackTs(ts.clone()));
-            }
+    fn max_message_len(&self) -> usize {
+        4000
+    }
 
-            session.chat_post_message(&request).await?;
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            edit: true,
+            threads: true,
+            attachments: self.max_attachment_bytes.is_some(),
+            ..Default::default()
         }
-
-        Ok(())
     }
 
-    fn name(&self) -> &str {
-        "slack"
+    /// Later chunks go into a thread on the first one, unless the session is
+    /// already a thread.
+    async fn send_chunk(
+        &self,
+        session_id: &str,
+        text: &str,
+        thread: Option<&SentMessage>,
+    ) -> Result<Option<SentMessage>, anyhow::Error> {
+        let (channel_id, thread_ts) = parse_slack_session(session_id)
+            .ok_or_else(|| anyhow::anyhow!("Invalid slack session_id: {}", session_id))?;
+        let thread_ts = thread_ts.or_else(|| thread.map(|t| t.message_id.clone()));
+
+        let session = self.client.open_session(&self.bot_token);
+        let content = SlackMessageContent::new().with_text(text.to_string());
+        let mut request = SlackApiChatPostMessageRequest::new(SlackChannelId(channel_id), content);
+        if let Some(ts) = thread_ts {
+            request = request.with_thread_ts(SlackTs(ts));
+        }
+        let resp = session.chat_post_message(&request).await?;
+
+        Ok(Some(SentMessage {
+            channel: "slack".into(),
+            session_id: session_id.to_string(),
+            message_id: resp.ts.0,
+        }))
     }
 
     async fn send_placeholder(&self, session_id: &str, text: &str) -> Option<SentMessage> {
@@ -163,17 +225,21 @@ impl ChannelAdapter for SlackAdapter {
     }
 }
 
-fn handle_push_event(
-    event: SlackPushEventCallback,
-    tx: &mpsc::UnboundedSender<IncomingMessage>,
-    allowed_channels: &[String],
-    allowed_users: &[String],
-) {
+async fn handle_push_event(event: SlackPushEventCallback, state: &SlackAdapterState) {
+    let SlackAdapterState {
+        tx,
+        allowed_channels,
+        allowed_users,
+        filter,
+        ..
+    } = state;
     let SlackPushEventCallback { event: inner, .. } = event;
 
     if let SlackEventCallbackBody::Message(msg_event) = inner {
-        // Skip bot messages
-        if msg_event.subtype.is_some() {
+        // Skip bot messages, whatever the filter's ignore_bots says. Shared
+        // files come as their own subtype
+        let file_share = matches!(msg_event.subtype, Some(SlackMessageEventType::FileShare));
+        if msg_event.subtype.is_some() && !file_share {
             return;
         }
         if msg_event.sender.bot_id.is_some() {
@@ -200,15 +266,12 @@ fn handle_push_event(
             return;
         }
 
-        let text = match &msg_event.content {
-            Some(content) => match &content.text {
-                Some(t) => t.clone(),
-                None => return,
-            },
-            None => return,
+        let Some(ref content) = msg_event.content else {
+            return;
         };
-
-        if text.is_empty() {
+        let text = content.text.clone().unwrap_or_default();
+        let files = content.files.as_deref().unwrap_or_default();
+        if text.is_empty() && (files.is_empty() || state.max_attachment_bytes.is_none()) {
             return;
         }
 
@@ -221,22 +284,86 @@ fn handle_push_event(
 
         // Slack channels starting with "D" are DMs, others are group channels
         let is_group = !channel_id.starts_with('D');
+        if let Some(reason) = filter.reject(is_group, false, &text) {
+            tracing::debug!("Ignoring {} message in slack-{}", reason, channel_id);
+            return;
+        }
+        let attachments = download_files(state, files).await;
+        if text.is_empty() && attachments.is_empty() {
+            return;
+        }
         let incoming = IncomingMessage {
             channel: "slack".into(),
             sender_id,
             sender_name: None,
             session_id,
+            chat_id: Some(channel_id),
+            thread_id: thread_ts.clone(),
             content: text,
             reply_to: thread_ts,
+            quote: None,
             timestamp: now_ms(),
             worker_hint: None,
             is_group,
+            message_id: Some(msg_event.origin.ts.0.clone()),
+            kind: MessageKind::New,
+            attachments,
         };
 
         let _ = tx.send(incoming);
     }
 }
 
+/// Shared files within the size limit. Ones that are too large or fail to
+/// download are skipped.
+async fn download_files(state: &SlackAdapterState, files: &[SlackFile]) -> Vec<Attachment> {
+    let Some(max) = state.max_attachment_bytes else {
+        return Vec::new();
+    };
+    let mut attachments = Vec::new();
+    for file in files {
+        let Some(ref url) = file.url_private_download else {
+            continue;
+        };
+        match download_file(state, url.as_str(), max).await {
+            Ok(data) => attachments.push(Attachment {
+                name: file.name.clone(),
+                mime_type: file
+                    .mimetype
+                    .as_ref()
+                    .map(|m| m.0.clone())
+                    .unwrap_or_else(|| "application/octet-stream".into()),
+                data,
+                voice: false,
+            }),
+            Err(e) => tracing::warn!("Skipping Slack file {}: {}", file.name, e),
+        }
+    }
+    attachments
+}
+
+async fn download_file(
+    state: &SlackAdapterState,
+    url: &str,
+    max_bytes: u64,
+) -> anyhow::Result<Vec<u8>> {
+    let resp = state
+        .http
+        .get(url)
+        .bearer_auth(&state.bot_token)
+        .send()
+        .await?
+        .error_for_status()?;
+    if resp.content_length().is_some_and(|len| len > max_bytes) {
+        anyhow::bail!("over the size limit");
+    }
+    let data = resp.bytes().await?;
+    if data.len() as u64 > max_bytes {
+        anyhow::bail!("over the size limit");
+    }
+    Ok(data.to_vec())
+}
+
 /// Parse a Slack session_id back to (channel_id, optional thread_ts).
 pub fn parse_slack_session(session_id: &str) -> Option<(String, Option<String>)> {
     let rest = session_id.strip_prefix("slack-")?;
@@ -260,6 +387,7 @@ pub fn parse_slack_session(session_id: &str) -> Option<(String, Option<String>)>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::channels::split_message;
 
     #[test]
     fn test_parse_slack_session_channel() {
@@ -1,21 +1,208 @@
-use super::{split_message, ChannelAdapter, IncomingMessage, OutgoingMessage, SentMessage};
+use super::filter::IngestFilter;
+use super::{
+    send_chunked, Attachment, Capabilities, ChannelAdapter, IncomingMessage, MessageKind,
+    OutgoingMessage, Quote, SentMessage,
+};
 use crate::config::TelegramConfig;
 use crate::db::now_ms;
 use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use teloxide::dispatching::ShutdownToken;
+use teloxide::net::Download;
 use teloxide::prelude::*;
+use teloxide::types::{
+    InputFile, KeyboardButton, KeyboardMarkup, MessageId, MessageReactionUpdated, ReactionType,
+    ReplyParameters,
+};
 use tokio::sync::mpsc;
 
 /// Telegram channel adapter using teloxide.
 pub struct TelegramAdapter {
     bot: Bot,
     config: TelegramConfig,
+    /// Largest attachment downloaded; None to ignore attachments.
+    max_attachment_bytes: Option<u64>,
+    /// The running dispatcher, for [`stop`](ChannelAdapter::stop).
+    dispatcher: Mutex<Option<(ShutdownToken, tokio::task::JoinHandle<()>)>>,
 }
 
 impl TelegramAdapter {
     pub fn new(config: TelegramConfig) -> Self {
         let bot = Bot::new(&config.bot_token);
-        Self { bot, config }
+        Self {
+            bot,
+            config,
+            max_attachment_bytes: None,
+            dispatcher: Mutex::new(None),
+        }
+    }
+
+    /// Download photos and documents of up to `max_bytes`.
+    pub fn with_attachments(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_attachment_bytes = max_bytes;
+        self
+    }
+}
+
+/// A photo, voice message or document to download.
+struct TelegramFile {
+    id: String,
+    size: u32,
+    name: String,
+    mime_type: String,
+    voice: bool,
+}
+
+/// The file sent with `msg`: the largest size of a photo, a voice message,
+/// or a document.
+fn media(msg: &teloxide::types::Message) -> Option<TelegramFile> {
+    if let Some(sizes) = msg.photo() {
+        // Telegram re-encodes photos as JPEG
+        let photo = sizes.iter().max_by_key(|p| p.width * p.height)?;
+        return Some(TelegramFile {
+            id: photo.file.id.clone(),
+            size: photo.file.size,
+            name: "photo.jpg".into(),
+            mime_type: "image/jpeg".into(),
+            voice: false,
+        });
     }
+    if let Some(voice) = msg.voice() {
+        return Some(TelegramFile {
+            id: voice.file.id.clone(),
+            size: voice.file.size,
+            name: "voice.ogg".into(),
+            mime_type: voice
+                .mime_type
+                .as_ref()
+                .map(|m| m.essence_str().to_string())
+                .unwrap_or_else(|| "audio/ogg".into()),
+            voice: true,
+        });
+    }
+    let doc = msg.document()?;
+    let mime_type = doc
+        .mime_type
+        .as_ref()
+        .map(|m| m.essence_str().to_string())
+        .unwrap_or_else(|| "application/octet-stream".into());
+    Some(TelegramFile {
+        id: doc.file.id.clone(),
+        size: doc.file.size,
+        name: doc
+            .file_name
+            .clone()
+            .unwrap_or_else(|| crate::attachments::default_name(&mime_type)),
+        mime_type,
+        voice: false,
+    })
+}
+
+async fn download(bot: &Bot, file: TelegramFile, max_bytes: u64) -> anyhow::Result<Attachment> {
+    if u64::from(file.size) > max_bytes {
+        anyhow::bail!("{} is {} bytes, over the limit", file.name, file.size);
+    }
+    let meta = bot.get_file(file.id).await?;
+    let mut data = Vec::new();
+    bot.download_file(&meta.path, &mut data).await?;
+    Ok(Attachment {
+        name: file.name,
+        mime_type: file.mime_type,
+        data,
+        voice: file.voice,
+    })
+}
+
+/// Convert a new or edited Telegram message. `None` for senders outside the
+/// allowlist, messages without text or caption (stickers, GIFs, join
+/// notices) unless `has_file`, and group messages the ingest filter drops.
+fn to_incoming(
+    msg: &teloxide::types::Message,
+    allowed: &[i64],
+    filter: &IngestFilter,
+    kind: MessageKind,
+    has_file: bool,
+) -> Option<IncomingMessage> {
+    // Sender allowlist
+    let sender_id = msg.from.as_ref().map(|u| u.id.0 as i64).unwrap_or(0);
+    if !allowed.is_empty() && !allowed.contains(&sender_id) {
+        return None;
+    }
+
+    let text = msg.text().or(msg.caption()).unwrap_or("").to_string();
+    if text.is_empty() && !has_file {
+        return None;
+    }
+
+    let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+    let from_bot = msg.from.as_ref().is_some_and(|u| u.is_bot);
+    if let Some(reason) = filter.reject(is_group, from_bot, &text) {
+        tracing::debug!("Ignoring {} message in tg-{}", reason, msg.chat.id.0);
+        return None;
+    }
+    Some(IncomingMessage {
+        channel: "telegram".into(),
+        sender_id: sender_id.to_string(),
+        sender_name: msg.from.as_ref().map(|u| u.first_name.clone()),
+        session_id: format!("tg-{}", msg.chat.id.0),
+        chat_id: Some(msg.chat.id.0.to_string()),
+        thread_id: None,
+        content: text,
+        reply_to: msg.reply_to_message().map(|m| m.id.0.to_string()),
+        quote: msg.reply_to_message().and_then(|m| {
+            Some(Quote {
+                sender: m.from.as_ref().map(|u| u.first_name.clone()),
+                text: m.text().or(m.caption())?.to_string(),
+                timestamp: Some(m.date.timestamp_millis() as u64),
+            })
+        }),
+        timestamp: now_ms(),
+        worker_hint: None,
+        is_group,
+        message_id: Some(msg.id.0.to_string()),
+        kind,
+        attachments: Vec::new(),
+    })
+}
+
+/// Convert a reaction update: one event per emoji the user added. Removed
+/// reactions and custom emoji are ignored.
+fn reactions_to_incoming(update: &MessageReactionUpdated, allowed: &[i64]) -> Vec<IncomingMessage> {
+    // Anonymous group admins react as the chat, not as a user
+    let Some(ref user) = update.user else {
+        return Vec::new();
+    };
+    let sender_id = user.id.0 as i64;
+    if !allowed.is_empty() && !allowed.contains(&sender_id) {
+        return Vec::new();
+    }
+    let is_group = update.chat.is_group() || update.chat.is_supergroup();
+    update
+        .new_reaction
+        .iter()
+        .filter(|r| !update.old_reaction.contains(r))
+        .filter_map(|r| match r {
+            ReactionType::Emoji { emoji } => Some(emoji.clone()),
+            _ => None,
+        })
+        .map(|emoji| IncomingMessage {
+            channel: "telegram".into(),
+            sender_id: sender_id.to_string(),
+            sender_name: Some(user.first_name.clone()),
+            session_id: format!("tg-{}", update.chat.id.0),
+            chat_id: Some(update.chat.id.0.to_string()),
+            thread_id: None,
+            content: String::new(),
+            reply_to: None,
+            quote: None,
+            timestamp: now_ms(),
+            worker_hint: None,
+            is_group,
+            message_id: Some(update.message_id.0.to_string()),
+            kind: MessageKind::Reaction { emoji },
+            attachments: Vec::new(),
+        })
+        .collect()
 }
 
 #[async_trait]
@@ -23,69 +210,138 @@ impl ChannelAdapter for TelegramAdapter {
     async fn start(&self, tx: mpsc::UnboundedSender<IncomingMessage>) -> Result<(), anyhow::Error> {
         let bot = self.bot.clone();
         let allowed = self.config.allowed_senders.clone();
+        let filter = Arc::new(IngestFilter::new(&self.config.filters)?);
+        let max_bytes = self.max_attachment_bytes;
 
-        tokio::spawn(async move {
-            let handler = Update::filter_message().endpoint(
-                move |msg: teloxide::types::Message, _bot: Bot| {
+        let edit_tx = tx.clone();
+        let edit_allowed = allowed.clone();
+        let edit_filter = filter.clone();
+        let reaction_tx = tx.clone();
+        let reaction_allowed = allowed.clone();
+        // Bots are not told about deleted messages, only edits. Reactions
+        // in groups only arrive while the bot is an administrator
+        let handler = dptree::entry()
+            .branch(Update::filter_message().endpoint(
+                move |bot: Bot, msg: teloxide::types::Message| {
+                    let file = max_bytes.and_then(|_| media(&msg));
+                    let incoming =
+                        to_incoming(&msg, &allowed, &filter, MessageKind::New, file.is_some());
                     let tx = tx.clone();
-                    let allowed = allowed.clone();
                     async move {
-                        // Sender allowlist
-                        let sender_id = msg.from.as_ref().map(|u| u.id.0 as i64).unwrap_or(0);
-                        if !allowed.is_empty() && !allowed.contains(&sender_id) {
+                        let Some(mut incoming) = incoming else {
                             return respond(());
+                        };
+                        if let (Some(file), Some(max)) = (file, max_bytes) {
+                            match download(&bot, file, max).await {
+                                Ok(a) => incoming.attachments.push(a),
+                                Err(e) => tracing::warn!(
+                                    "Skipping attachment in {}: {}",
+                                    incoming.session_id,
+                                    e
+                                ),
+                            }
                         }
-
-                        let text = msg.text().unwrap_or("").to_string();
-                        if text.is_empty() {
-                            return respond(());
+                        if !incoming.content.is_empty() || !incoming.attachments.is_empty() {
+                            let _ = tx.send(incoming);
                         }
-
-                        let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
-                        let incoming = IncomingMessage {
-                            channel: "telegram".into(),
-                            sender_id: sender_id.to_string(),
-                            sender_name: msg.from.as_ref().map(|u| u.first_name.clone()),
-                            session_id: format!("tg-{}", msg.chat.id.0),
-                            content: text,
-                            reply_to: msg.reply_to_message().map(|m| m.id.0.to_string()),
-                            timestamp: now_ms(),
-                            worker_hint: None,
-                            is_group,
-                        };
-
-                        let _ = tx.send(incoming);
                         respond(())
                     }
                 },
-            );
+            ))
+            .branch(Update::filter_edited_message().endpoint(
+                move |msg: teloxide::types::Message| {
+                    let kind = MessageKind::Edited { previous: None };
+                    if let Some(incoming) =
+                        to_incoming(&msg, &edit_allowed, &edit_filter, kind, false)
+                    {
+                        let _ = edit_tx.send(incoming);
+                    }
+                    async { respond(()) }
+                },
+            ))
+            .branch(Update::filter_message_reaction_updated().endpoint(
+                move |update: MessageReactionUpdated| {
+                    for incoming in reactions_to_incoming(&update, &reaction_allowed) {
+                        let _ = reaction_tx.send(incoming);
+                    }
+                    async { respond(()) }
+                },
+            ));
 
-            Dispatcher::builder(bot, handler).build().dispatch().await;
-        });
+        let mut dispatcher = Dispatcher::builder(bot, handler).build();
+        let token = dispatcher.shutdown_token();
+        let handle = tokio::spawn(async move { dispatcher.dispatch().await });
+        *self.dispatcher.lock().unwrap() = Some((token, handle));
 
         tracing::info!("Telegram adapter started");
         Ok(())
     }
 
-    async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
-        let chat_id: i64 = msg
-            .session_id
-            .strip_prefix("tg-")
-            .and_then(|s| s.parse().ok())
-            .ok_or_else(|| anyhow::anyhow!("Invalid telegram session_id: {}", msg.session_id))?;
-
-        let chunks = split_message(&msg.content, 4096);
-        for chunk in chunks {
-            self.bot.send_message(ChatId(chat_id), &chunk).await?;
+    /// Waits for the dispatcher to confirm the updates it has handled, so the
+    /// next bot with the token isn't sent them again.
+    async fn stop(&self) {
+        let running = self.dispatcher.lock().unwrap().take();
+        let Some((token, handle)) = running else {
+            return;
+        };
+        match token.shutdown() {
+            Ok(stopped) => stopped.await,
+            // Not dispatching yet, or any more
+            Err(_) => handle.abort(),
         }
+        tracing::info!("Telegram adapter stopped");
+    }
 
-        Ok(())
+    async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
+        send_chunked(self, &msg).await
     }
 
     fn name(&self) -> &str {
         "telegram"
     }
 
+    fn max_message_len(&self) -> usize {
+        4096
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            edit: true,
+            delete: true,
+            typing: true,
+            reactions: true,
+            threads: true,
+            attachments: self.max_attachment_bytes.is_some(),
+            buttons: true,
+            voice: true,
+        }
+    }
+
+    /// Later chunks reply to the first one.
+    async fn send_chunk(
+        &self,
+        session_id: &str,
+        text: &str,
+        thread: Option<&SentMessage>,
+    ) -> Result<Option<SentMessage>, anyhow::Error> {
+        let chat_id: i64 = session_id
+            .strip_prefix("tg-")
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid telegram session_id: {}", session_id))?;
+
+        let mut request = self.bot.send_message(ChatId(chat_id), text);
+        if let Some(id) = thread.and_then(|t| t.message_id.parse().ok()) {
+            request = request.reply_parameters(ReplyParameters::new(MessageId(id)));
+        }
+        let sent = request.await?;
+
+        Ok(Some(SentMessage {
+            channel: "telegram".into(),
+            session_id: session_id.to_string(),
+            message_id: sent.id.0.to_string(),
+        }))
+    }
+
     fn start_typing(&self, session_id: &str) -> Option<tokio::task::JoinHandle<()>> {
         let chat_id: i64 = session_id
             .strip_prefix("tg-")
@@ -164,4 +420,64 @@ impl ChannelAdapter for TelegramAdapter {
             Err(e) => Err(e.into()),
         }
     }
+
+    async fn delete_message(&self, handle: &SentMessage) -> Result<(), anyhow::Error> {
+        let chat_id: i64 = handle
+            .session_id
+            .strip_prefix("tg-")
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid telegram session_id"))?;
+        let message_id: i32 = handle
+            .message_id
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid telegram message_id"))?;
+        self.bot
+            .delete_message(ChatId(chat_id), MessageId(message_id))
+            .await?;
+        Ok(())
+    }
+
+    /// A reply keyboard, hidden again once a button is tapped. Reply keyboards
+    /// come with a message, so the buttons are always sent under `text`.
+    async fn offer_buttons(
+        &self,
+        session_id: &str,
+        _reply: Option<&SentMessage>,
+        text: &str,
+        labels: &[String],
+    ) -> Result<(), anyhow::Error> {
+        let chat_id: i64 = session_id
+            .strip_prefix("tg-")
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid telegram session_id: {}", session_id))?;
+        let keyboard =
+            KeyboardMarkup::new(labels.iter().map(|s| vec![KeyboardButton::new(s.as_str())]))
+                .resize_keyboard()
+                .one_time_keyboard();
+        self.bot
+            .send_message(ChatId(chat_id), text)
+            .reply_markup(keyboard)
+            .await?;
+        Ok(())
+    }
+
+    /// Ogg Opus is shown as a voice message, other formats as audio files.
+    async fn send_voice(
+        &self,
+        session_id: &str,
+        audio: Vec<u8>,
+        format: &str,
+    ) -> Result<(), anyhow::Error> {
+        let chat_id: i64 = session_id
+            .strip_prefix("tg-")
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid telegram session_id: {}", session_id))?;
+        let file = InputFile::memory(audio).file_name(super::tts::file_name(format));
+        if format == "opus" {
+            self.bot.send_voice(ChatId(chat_id), file).await?;
+        } else {
+            self.bot.send_audio(ChatId(chat_id), file).await?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,302 @@
+//! Spoken replies.
+//!
+//! On Telegram and Discord with `voice_replies = true` and `[channels.tts]`
+//! set, a reply is also sent as audio when the user's message was a voice
+//! message, or always in a chat that sent `/voice on`. The text goes out as
+//! usual first; the audio follows once the `[channels.tts]` backend (the
+//! OpenAI speech API, a server offering the same endpoint, or a local
+//! command such as piper) has spoken it. Markdown is stripped and code
+//! blocks are left out, and replies over `max_chars` stay text only.
+//! Anything going wrong just means no audio.
+
+use crate::config::{AgentConfig, TtsConfig};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TtsError {
+    #[error("Unknown TTS provider '{0}' (expected openai or local)")]
+    UnknownProvider(String),
+    #[error("No API key for TTS: set [channels.tts] api_key")]
+    NoApiKey,
+    #[error("TTS timed out after {0}s")]
+    Timeout(u64),
+    #[error("TTS failed: {0}")]
+    Failed(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+/// `/voice on`, `/voice off`, or `/voice` to show the setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceCommand {
+    On,
+    Off,
+    Show,
+}
+
+/// The `/voice` command in `text`, if it is one. Unknown arguments show
+/// the setting, with its usage.
+pub fn parse_command(text: &str) -> Option<VoiceCommand> {
+    let mut words = text.split_whitespace();
+    if words.next() != Some("/voice") {
+        return None;
+    }
+    Some(match (words.next(), words.next()) {
+        (Some("on"), None) => VoiceCommand::On,
+        (Some("off"), None) => VoiceCommand::Off,
+        _ => VoiceCommand::Show,
+    })
+}
+
+/// Answer to a `/voice` command, `on` being the setting after it.
+pub fn command_reply(command: VoiceCommand, on: bool) -> String {
+    match (command, on) {
+        (VoiceCommand::On, _) => {
+            "Replies in this chat will be spoken too. Send /voice off to stop.".to_string()
+        }
+        (VoiceCommand::Off, _) => {
+            "Replies in this chat are text only again, except to voice messages.".to_string()
+        }
+        (VoiceCommand::Show, true) => {
+            "Replies in this chat are spoken too. Send /voice off to stop.".to_string()
+        }
+        (VoiceCommand::Show, false) => {
+            "Replies are spoken only when you send a voice message. Send /voice on to hear \
+             every reply."
+                .to_string()
+        }
+    }
+}
+
+/// Name of an audio file in `format`, as sent to the platform.
+pub fn file_name(format: &str) -> String {
+    match format {
+        "opus" => "reply.ogg".to_string(),
+        other => format!("reply.{}", other),
+    }
+}
+
+/// `text` as it should be read out: without Markdown markup, links read
+/// by their text, code blocks left out. `None` if nothing is left to say or
+/// it is longer than `max_chars`.
+pub fn speakable(text: &str, max_chars: usize) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            if !in_code {
+                lines.push("(code left out)".to_string());
+            }
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let line = line
+            .trim_start()
+            .trim_start_matches(['#', '>'])
+            .trim_start()
+            .trim_start_matches("- ");
+        lines.push(strip_inline(line));
+    }
+    let spoken = lines.join("\n").trim().to_string();
+    if spoken.is_empty() || spoken.chars().count() > max_chars {
+        return None;
+    }
+    Some(spoken)
+}
+
+/// Drop emphasis and inline code markers, and keep only the text of links.
+fn strip_inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let link = rest[start..].find("](").and_then(|mid| {
+            let close = rest[start + mid..].find(')')?;
+            Some((start + mid, start + mid + close))
+        });
+        match link {
+            Some((mid, close)) => {
+                out.push_str(&rest[..start]);
+                out.push_str(&rest[start + 1..mid]);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                out.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.replace(['*', '`'], "").replace("__", "")
+}
+
+/// Audio of `text` in `config.format`.
+pub async fn synthesize(
+    config: &TtsConfig,
+    agent: &AgentConfig,
+    text: &str,
+) -> Result<Vec<u8>, TtsError> {
+    let timeout = Duration::from_secs(config.timeout_secs);
+    match config.provider.as_str() {
+        "openai" => {
+            let api_key = match config.api_key {
+                Some(ref key) => key.as_str(),
+                None if agent.provider == "openai" => agent.api_key.as_str(),
+                None => return Err(TtsError::NoApiKey),
+            };
+            tokio::time::timeout(timeout, synthesize_api(config, api_key, text))
+                .await
+                .map_err(|_| TtsError::Timeout(config.timeout_secs))?
+        }
+        "local" => tokio::time::timeout(timeout, synthesize_local(config, text))
+            .await
+            .map_err(|_| TtsError::Timeout(config.timeout_secs))?,
+        other => Err(TtsError::UnknownProvider(other.to_string())),
+    }
+}
+
+/// `POST {base_url}/audio/speech`, the OpenAI speech API.
+async fn synthesize_api(
+    config: &TtsConfig,
+    api_key: &str,
+    text: &str,
+) -> Result<Vec<u8>, TtsError> {
+    let body = serde_json::json!({
+        "model": config.model.as_deref().unwrap_or("tts-1"),
+        "voice": config.voice.as_deref().unwrap_or("alloy"),
+        "input": text,
+        "response_format": config.format,
+    });
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/audio/speech",
+            config.base_url.trim_end_matches('/')
+        ))
+        .bearer_auth(api_key)
+        .json(&body)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(TtsError::Failed(format!("{}: {}", status, body)));
+    }
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Run the local command with the text on stdin and read the file it wrote.
+async fn synthesize_local(config: &TtsConfig, text: &str) -> Result<Vec<u8>, TtsError> {
+    use tokio::io::AsyncWriteExt;
+
+    let file = std::env::temp_dir().join(format!(
+        "yoclaw-tts-{}-{}",
+        uuid::Uuid::new_v4(),
+        file_name(&config.format)
+    ));
+    let mut child = tokio::process::Command::new(&config.command)
+        .args(local_args(config, &file.to_string_lossy()))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that stops reading early is judged by its exit status
+        let _ = stdin.write_all(text.as_bytes()).await;
+    }
+    let output = child.wait_with_output().await?;
+    let audio = tokio::fs::read(&file).await;
+    let _ = tokio::fs::remove_file(&file).await;
+    if !output.status.success() {
+        return Err(TtsError::Failed(format!(
+            "{} exited with {}: {}",
+            config.command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(audio?)
+}
+
+/// `args` with `{file}`, `{model}` and `{voice}` filled in.
+fn local_args(config: &TtsConfig, file: &str) -> Vec<String> {
+    config
+        .args
+        .iter()
+        .map(|arg| {
+            arg.replace("{file}", file)
+                .replace("{model}", config.model.as_deref().unwrap_or(""))
+                .replace("{voice}", config.voice.as_deref().unwrap_or(""))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_config, Config};
+
+    fn with_tts(tts: &str) -> Config {
+        parse_config(&format!(
+            "[agent]\nmodel = \"m\"\napi_key = \"k\"\n\n[channels.tts]\n{}",
+            tts
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/voice on"), Some(VoiceCommand::On));
+        assert_eq!(parse_command("/voice  off"), Some(VoiceCommand::Off));
+        assert_eq!(parse_command("/voice"), Some(VoiceCommand::Show));
+        assert_eq!(parse_command("/voice loud"), Some(VoiceCommand::Show));
+        assert_eq!(parse_command("/voicemail"), None);
+        assert_eq!(parse_command("turn /voice on"), None);
+    }
+
+    #[test]
+    fn test_speakable() {
+        let text = "## Plan\n**Call** the [landlord](https://example.com) at `5`.\n\
+                    ```sh\ncurl x\n```\n- done";
+        assert_eq!(
+            speakable(text, 100).unwrap(),
+            "Plan\nCall the landlord at 5.\n(code left out)\ndone"
+        );
+        assert_eq!(speakable("a [b] c", 100).unwrap(), "a [b] c");
+        assert!(speakable("   ", 100).is_none());
+        assert!(speakable("too long", 3).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_synthesize_local() {
+        // Writes the text it is given to the output file
+        let config = with_tts(
+            "provider = \"local\"\nformat = \"wav\"\ncommand = \"sh\"\n\
+             args = [\"-c\", \"cat > {file}\"]",
+        );
+        let tts = config.channels.tts.as_ref().unwrap();
+        let audio = synthesize(tts, &config.agent, "hello").await.unwrap();
+        assert_eq!(audio, b"hello");
+
+        let config = with_tts("provider = \"local\"\ncommand = \"false\"");
+        let tts = config.channels.tts.as_ref().unwrap();
+        assert!(matches!(
+            synthesize(tts, &config.agent, "hello").await,
+            Err(TtsError::Failed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_openai_needs_a_key() {
+        let config = with_tts("voice = \"nova\"");
+        let tts = config.channels.tts.as_ref().unwrap();
+        assert!(matches!(
+            synthesize(tts, &config.agent, "hello").await,
+            Err(TtsError::NoApiKey)
+        ));
+    }
+}
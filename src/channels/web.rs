@@ -0,0 +1,273 @@
+//! Chat from the web UI.
+//!
+//! With `[channels.web]`, the dashboard's chat box POSTs to `/api/chat` with
+//! `Authorization: Bearer <token>`:
+//!
+//! ```json
+//! {"text": "What's on my calendar today?", "session_id": "web-4f1c…"}
+//! ```
+//!
+//! Without `session_id` a new `web-<uuid>` session is started. The message
+//! goes through the queue like any other and the request returns at once
+//! with the session ID. The reply streams to the browser over `/api/events`:
+//! `stream_chunk` events carry the text so far, `stream_end` closes it, and
+//! messages sent outside a reply (`send_message`, cron results) arrive as
+//! `chat_message`.
+
+use super::{
+    Capabilities, ChannelAdapter, IncomingMessage, MessageKind, OutgoingMessage, SentMessage,
+};
+use crate::config::WebChatConfig;
+use crate::db::now_ms;
+use crate::web::SseEvent;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+
+/// Session ID prefix of web chat sessions.
+pub const SESSION_PREFIX: &str = "web-";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebChatError {
+    #[error("The web chat isn't running")]
+    NotStarted,
+    #[error("Message text is empty")]
+    Empty,
+    #[error("'{0}' is not a web chat session")]
+    NotWebSession(String),
+}
+
+/// Body of a request to `/api/chat`.
+#[derive(Debug, Deserialize)]
+pub struct ChatRequest {
+    pub text: String,
+    /// Conversation to continue; a new one is started without it.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub sender_name: Option<String>,
+}
+
+/// Shared between the adapter and the web server's handler.
+pub struct WebChatInbox {
+    config: WebChatConfig,
+    tx: Mutex<Option<mpsc::UnboundedSender<IncomingMessage>>>,
+}
+
+impl WebChatInbox {
+    /// Whether an `Authorization` header carries the channel token.
+    pub fn authorized(&self, header: Option<&str>) -> bool {
+        crate::web::console::authorized(&self.config.token, header)
+    }
+
+    /// Queue `req` for the agent. Returns its session ID.
+    pub fn submit(&self, req: ChatRequest) -> Result<String, WebChatError> {
+        if req.text.trim().is_empty() {
+            return Err(WebChatError::Empty);
+        }
+        let session_id = match req.session_id {
+            Some(id) if id.starts_with(SESSION_PREFIX) => id,
+            Some(id) => return Err(WebChatError::NotWebSession(id)),
+            None => format!("{}{}", SESSION_PREFIX, uuid::Uuid::new_v4()),
+        };
+        let tx = self
+            .tx
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(WebChatError::NotStarted)?;
+
+        let incoming = IncomingMessage {
+            channel: "web".to_string(),
+            sender_id: "web".to_string(),
+            sender_name: req.sender_name,
+            session_id: session_id.clone(),
+            chat_id: None,
+            thread_id: None,
+            content: req.text,
+            reply_to: None,
+            quote: None,
+            timestamp: now_ms(),
+            worker_hint: None,
+            is_group: false,
+            message_id: None,
+            kind: MessageKind::New,
+            attachments: Vec::new(),
+        };
+        tx.send(incoming).map_err(|_| WebChatError::NotStarted)?;
+        Ok(session_id)
+    }
+}
+
+/// Channel adapter for the web UI's chat.
+pub struct WebChatAdapter {
+    inbox: Arc<WebChatInbox>,
+    events: broadcast::Sender<SseEvent>,
+}
+
+impl WebChatAdapter {
+    pub fn new(config: WebChatConfig, events: broadcast::Sender<SseEvent>) -> Self {
+        Self {
+            inbox: Arc::new(WebChatInbox {
+                config,
+                tx: Mutex::new(None),
+            }),
+            events,
+        }
+    }
+
+    /// The handle the web server accepts messages through.
+    pub fn inbox(&self) -> Arc<WebChatInbox> {
+        self.inbox.clone()
+    }
+}
+
+#[async_trait]
+impl ChannelAdapter for WebChatAdapter {
+    async fn start(&self, tx: mpsc::UnboundedSender<IncomingMessage>) -> Result<(), anyhow::Error> {
+        *self.inbox.tx.lock().unwrap() = Some(tx);
+        tracing::info!("Web chat started at /api/chat");
+        Ok(())
+    }
+
+    /// Browsers that aren't open miss the message; it stays on the tape.
+    async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
+        let _ = self.events.send(SseEvent::ChatMessage {
+            session_id: msg.session_id,
+            text: msg.content,
+        });
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "web"
+    }
+
+    fn max_message_len(&self) -> usize {
+        usize::MAX
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            edit: true,
+            ..Default::default()
+        }
+    }
+
+    /// Replies stream as `stream_chunk` events, so there is nothing to send
+    /// up front; the handle only makes the main loop stream.
+    async fn send_placeholder(&self, session_id: &str, _text: &str) -> Option<SentMessage> {
+        Some(SentMessage {
+            channel: "web".to_string(),
+            session_id: session_id.to_string(),
+            message_id: uuid::Uuid::new_v4().to_string(),
+        })
+    }
+
+    /// The final edit carries the finished reply, citations included.
+    async fn edit_message(
+        &self,
+        handle: &SentMessage,
+        new_text: &str,
+    ) -> Result<(), anyhow::Error> {
+        let _ = self.events.send(SseEvent::StreamChunk {
+            session_id: handle.session_id.clone(),
+            channel: "web".to_string(),
+            text: new_text.to_string(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter() -> (WebChatAdapter, broadcast::Receiver<SseEvent>) {
+        let (events, rx) = broadcast::channel(16);
+        let adapter = WebChatAdapter::new(
+            WebChatConfig {
+                token: "s3cret".into(),
+                debounce_ms: 0,
+                citations: false,
+                latency: None,
+            },
+            events,
+        );
+        (adapter, rx)
+    }
+
+    fn request(text: &str, session_id: Option<&str>) -> ChatRequest {
+        ChatRequest {
+            text: text.into(),
+            session_id: session_id.map(String::from),
+            sender_name: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit() {
+        let (adapter, _events) = adapter();
+        let inbox = adapter.inbox();
+        assert!(matches!(
+            inbox.submit(request("hi", None)),
+            Err(WebChatError::NotStarted)
+        ));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        adapter.start(tx).await.unwrap();
+        let session_id = inbox.submit(request("hi", None)).unwrap();
+        assert!(session_id.starts_with("web-"));
+        assert_ne!(inbox.submit(request("hi", None)).unwrap(), session_id);
+        let incoming = rx.recv().await.unwrap();
+        assert_eq!(incoming.channel, "web");
+        assert_eq!(incoming.session_id, session_id);
+        assert_eq!(incoming.content, "hi");
+
+        // Continuing a conversation
+        let again = inbox.submit(request("more", Some(&session_id))).unwrap();
+        assert_eq!(again, session_id);
+
+        assert!(matches!(
+            inbox.submit(request(" ", None)),
+            Err(WebChatError::Empty)
+        ));
+        assert!(matches!(
+            inbox.submit(request("hi", Some("tg-1"))),
+            Err(WebChatError::NotWebSession(id)) if id == "tg-1"
+        ));
+        assert!(inbox.authorized(Some("Bearer s3cret")));
+        assert!(!inbox.authorized(None));
+    }
+
+    #[tokio::test]
+    async fn test_replies_become_events() {
+        let (adapter, mut events) = adapter();
+        let handle = adapter.send_placeholder("web-1", "...").await.unwrap();
+        adapter.edit_message(&handle, "Sunny, 21°C.").await.unwrap();
+        match events.recv().await.unwrap() {
+            SseEvent::StreamChunk {
+                session_id, text, ..
+            } => {
+                assert_eq!(session_id, "web-1");
+                assert_eq!(text, "Sunny, 21°C.");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        adapter
+            .send(OutgoingMessage {
+                channel: "web".into(),
+                session_id: "web-1".into(),
+                content: "Reminder: standup".into(),
+                reply_to: None,
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            SseEvent::ChatMessage { text, .. } if text == "Reminder: standup"
+        ));
+    }
+}
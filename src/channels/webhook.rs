@@ -0,0 +1,330 @@
+//! Generic HTTP channel.
+//!
+//! With `[channels.webhook]`, other systems talk to the agent by POSTing to
+//! `/api/channels/webhook` on the web server, with `Authorization: Bearer
+//! <token>`:
+//!
+//! ```json
+//! {"text": "Build 1234 failed, summarize the log", "sender_id": "ci", "session": "builds"}
+//! ```
+//!
+//! The message goes through the queue like any other, in session
+//! `wh-<session>` (`wh-<sender_id>` without one). The request waits up to
+//! `reply_timeout_secs` and returns the reply. With `"callback": "<name>"`
+//! it returns at once and the reply is POSTed to that `[webhooks.<name>]`
+//! target instead, signed like other outbound webhooks; only targets listed
+//! in `callbacks` can be named. Later messages in the session (cron results,
+//! `send_message`) go to the session's last callback.
+
+use super::{ChannelAdapter, IncomingMessage, MessageKind, OutgoingMessage};
+use crate::config::{WebhookChannelConfig, WebhookTargetConfig};
+use crate::db::now_ms;
+use crate::webhook::WebhookSender;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// Session ID prefix of webhook sessions.
+pub const SESSION_PREFIX: &str = "wh-";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookChannelError {
+    #[error("The webhook channel isn't running")]
+    NotStarted,
+    #[error("Message text is empty")]
+    Empty,
+    #[error("Unknown callback '{0}'")]
+    UnknownCallback(String),
+}
+
+/// Body of a request to `/api/channels/webhook`.
+#[derive(Debug, Deserialize)]
+pub struct InboundMessage {
+    pub text: String,
+    pub sender_id: String,
+    #[serde(default)]
+    pub sender_name: Option<String>,
+    /// Conversation to continue; defaults to one per sender.
+    #[serde(default)]
+    pub session: Option<String>,
+    /// `[webhooks.<name>]` target for the reply, instead of waiting for it.
+    #[serde(default)]
+    pub callback: Option<String>,
+}
+
+/// A queued message: its session, and the reply to wait for unless it goes
+/// to a callback.
+pub struct Submitted {
+    pub session_id: String,
+    pub reply: Option<oneshot::Receiver<String>>,
+}
+
+/// Shared between the adapter and the web server's handler.
+pub struct WebhookInbox {
+    config: WebhookChannelConfig,
+    tx: Mutex<Option<mpsc::UnboundedSender<IncomingMessage>>>,
+    /// Requests waiting for the next reply, by session.
+    waiting: Mutex<HashMap<String, Vec<oneshot::Sender<String>>>>,
+    /// Callback target of each session's latest message.
+    callbacks: Mutex<HashMap<String, String>>,
+    sender: WebhookSender,
+}
+
+impl WebhookInbox {
+    /// Whether an `Authorization` header carries the channel token.
+    pub fn authorized(&self, header: Option<&str>) -> bool {
+        crate::web::console::authorized(&self.config.token, header)
+    }
+
+    pub fn reply_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.reply_timeout_secs)
+    }
+
+    /// Queue `msg` for the agent.
+    pub fn submit(&self, msg: InboundMessage) -> Result<Submitted, WebhookChannelError> {
+        if msg.text.trim().is_empty() {
+            return Err(WebhookChannelError::Empty);
+        }
+        if let Some(ref name) = msg.callback {
+            if !self.config.callbacks.contains(name) {
+                return Err(WebhookChannelError::UnknownCallback(name.clone()));
+            }
+        }
+        let tx = self
+            .tx
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(WebhookChannelError::NotStarted)?;
+
+        let session_id = format!(
+            "{}{}",
+            SESSION_PREFIX,
+            msg.session.as_deref().unwrap_or(&msg.sender_id)
+        );
+        let reply = match msg.callback {
+            Some(name) => {
+                self.callbacks
+                    .lock()
+                    .unwrap()
+                    .insert(session_id.clone(), name);
+                None
+            }
+            None => {
+                self.callbacks.lock().unwrap().remove(&session_id);
+                let (reply_tx, reply_rx) = oneshot::channel();
+                self.waiting
+                    .lock()
+                    .unwrap()
+                    .entry(session_id.clone())
+                    .or_default()
+                    .push(reply_tx);
+                Some(reply_rx)
+            }
+        };
+
+        let incoming = IncomingMessage {
+            channel: "webhook".to_string(),
+            sender_id: msg.sender_id,
+            sender_name: msg.sender_name,
+            session_id: session_id.clone(),
+            chat_id: None,
+            thread_id: None,
+            content: msg.text,
+            reply_to: None,
+            quote: None,
+            timestamp: now_ms(),
+            worker_hint: None,
+            is_group: false,
+            message_id: None,
+            kind: MessageKind::New,
+            attachments: Vec::new(),
+        };
+        tx.send(incoming)
+            .map_err(|_| WebhookChannelError::NotStarted)?;
+        Ok(Submitted { session_id, reply })
+    }
+
+    /// Hand `content` to the requests waiting in `session_id`, or else to the
+    /// session's callback. Messages nobody waits for stay on the tape only.
+    async fn deliver(&self, session_id: &str, content: &str) -> Result<(), anyhow::Error> {
+        let waiting = self
+            .waiting
+            .lock()
+            .unwrap()
+            .remove(session_id)
+            .unwrap_or_default();
+        // Coalesced messages share one reply; timed-out requests are gone
+        let answered = waiting
+            .into_iter()
+            .filter_map(|w| w.send(content.to_string()).ok())
+            .count();
+        if answered > 0 {
+            return Ok(());
+        }
+
+        let callback = self.callbacks.lock().unwrap().get(session_id).cloned();
+        match callback {
+            Some(name) => Ok(self.sender.send_reply(&name, session_id, content).await?),
+            None => {
+                tracing::debug!("No one waiting for the reply in {}", session_id);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Channel adapter for messages posted over HTTP.
+pub struct WebhookAdapter {
+    inbox: Arc<WebhookInbox>,
+}
+
+impl WebhookAdapter {
+    pub fn new(
+        config: WebhookChannelConfig,
+        targets: HashMap<String, WebhookTargetConfig>,
+    ) -> Self {
+        Self {
+            inbox: Arc::new(WebhookInbox {
+                config,
+                tx: Mutex::new(None),
+                waiting: Mutex::new(HashMap::new()),
+                callbacks: Mutex::new(HashMap::new()),
+                sender: WebhookSender::new(targets),
+            }),
+        }
+    }
+
+    /// The handle the web server accepts messages through.
+    pub fn inbox(&self) -> Arc<WebhookInbox> {
+        self.inbox.clone()
+    }
+}
+
+#[async_trait]
+impl ChannelAdapter for WebhookAdapter {
+    async fn start(&self, tx: mpsc::UnboundedSender<IncomingMessage>) -> Result<(), anyhow::Error> {
+        *self.inbox.tx.lock().unwrap() = Some(tx);
+        tracing::info!("Webhook channel started at /api/channels/webhook");
+        Ok(())
+    }
+
+    async fn send(&self, msg: OutgoingMessage) -> Result<(), anyhow::Error> {
+        self.inbox.deliver(&msg.session_id, &msg.content).await
+    }
+
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    /// Replies go out in one piece; HTTP has no length limit worth splitting for.
+    fn max_message_len(&self) -> usize {
+        usize::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter(callbacks: &[&str]) -> WebhookAdapter {
+        WebhookAdapter::new(
+            WebhookChannelConfig {
+                token: "s3cret".into(),
+                callbacks: callbacks.iter().map(|c| c.to_string()).collect(),
+                reply_timeout_secs: 5,
+                debounce_ms: 0,
+                citations: false,
+            },
+            HashMap::new(),
+        )
+    }
+
+    fn message(text: &str) -> InboundMessage {
+        InboundMessage {
+            text: text.into(),
+            sender_id: "ci".into(),
+            sender_name: None,
+            session: None,
+            callback: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reply_reaches_waiting_request() {
+        let adapter = adapter(&[]);
+        let inbox = adapter.inbox();
+        assert!(matches!(
+            inbox.submit(message("hi")),
+            Err(WebhookChannelError::NotStarted)
+        ));
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        adapter.start(tx).await.unwrap();
+        let submitted = inbox.submit(message("Build 12 failed")).unwrap();
+        assert_eq!(submitted.session_id, "wh-ci");
+
+        let incoming = rx.recv().await.unwrap();
+        assert_eq!(incoming.channel, "webhook");
+        assert_eq!(incoming.content, "Build 12 failed");
+        adapter
+            .send(OutgoingMessage {
+                channel: "webhook".into(),
+                session_id: incoming.session_id,
+                content: "The linker ran out of memory.".into(),
+                reply_to: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            submitted.reply.unwrap().await.unwrap(),
+            "The linker ran out of memory."
+        );
+
+        // Nobody is waiting any more
+        adapter
+            .send(OutgoingMessage {
+                channel: "webhook".into(),
+                session_id: "wh-ci".into(),
+                content: "Later".into(),
+                reply_to: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_messages() {
+        let adapter = adapter(&["ci"]);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        adapter.start(tx).await.unwrap();
+        let inbox = adapter.inbox();
+
+        assert!(matches!(
+            inbox.submit(message("  ")),
+            Err(WebhookChannelError::Empty)
+        ));
+        let submitted = inbox
+            .submit(InboundMessage {
+                callback: Some("ci".into()),
+                session: Some("builds".into()),
+                ..message("hi")
+            })
+            .unwrap();
+        assert_eq!(submitted.session_id, "wh-builds");
+        assert!(submitted.reply.is_none());
+        assert!(matches!(
+            inbox.submit(InboundMessage {
+                callback: Some("elsewhere".into()),
+                ..message("hi")
+            }),
+            Err(WebhookChannelError::UnknownCallback(name)) if name == "elsewhere"
+        ));
+
+        assert!(inbox.authorized(Some("Bearer s3cret")));
+        assert!(!inbox.authorized(Some("Bearer nope")));
+    }
+}
@@ -0,0 +1,92 @@
+//! `yoclaw backup`: archives, database snapshots, restore and encryption.
+
+use super::open_db;
+
+fn config_file(config_path: Option<&std::path::Path>) -> std::path::PathBuf {
+    config_path
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| yoclaw::config::config_dir().join("config.toml"))
+}
+
+pub async fn run_backup_create(
+    config_path: Option<&std::path::Path>,
+    output: &std::path::Path,
+    redact: bool,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let manifest =
+        yoclaw::backup::create(&config_file(config_path), &config, &db, output, redact).await?;
+    for entry in &manifest.entries {
+        println!("  {:<9} {}", entry.kind, entry.restore_to);
+    }
+    println!(
+        "Backup written to {}{}",
+        output.display(),
+        if redact { " (config redacted)" } else { "" }
+    );
+    Ok(())
+}
+
+pub async fn run_backup_db(
+    config_path: Option<&std::path::Path>,
+    output: Option<std::path::PathBuf>,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let output = output.unwrap_or_else(|| {
+        config
+            .backup_dir()
+            .join(yoclaw::backup::snapshot_name(yoclaw::db::now_ms()))
+    });
+    yoclaw::backup::snapshot(&db, &output).await?;
+    println!("Database backed up to {}", output.display());
+    Ok(())
+}
+
+pub fn run_backup_restore(
+    config_path: Option<&std::path::Path>,
+    archive: &std::path::Path,
+    force: bool,
+) -> anyhow::Result<()> {
+    if !yoclaw::backup::is_archive(archive)? {
+        let config = yoclaw::config::load_config(config_path)?;
+        let db_path = config.db_path();
+        let key = config.db_key()?;
+        let schema = yoclaw::backup::restore_database(archive, &db_path, key.as_deref(), force)?;
+        println!(
+            "Restored database (schema {}) to {}",
+            schema,
+            db_path.display()
+        );
+        return Ok(());
+    }
+    let config_path = config_file(config_path);
+    let manifest = yoclaw::backup::restore(archive, &config_path, force)?;
+    println!(
+        "Restored backup from yoclaw {} ({} entries, schema {})",
+        manifest.yoclaw_version,
+        manifest.entries.len(),
+        manifest.schema_version
+    );
+    if manifest.redacted {
+        println!(
+            "The config was redacted: replace each \"{}\" in {} before starting yoclaw.",
+            yoclaw::backup::REDACTED,
+            config_path.display()
+        );
+    }
+    Ok(())
+}
+
+pub fn run_backup_encrypt(config_path: Option<&std::path::Path>) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let Some(key) = config.db_key()? else {
+        anyhow::bail!("Add [persistence.encryption] to the config first");
+    };
+    let db_path = config.db_path();
+    yoclaw::backup::encrypt_database(&db_path, &key)?;
+    println!("Encrypted {}", db_path.display());
+    println!("Older backups and database copies are still unencrypted; delete them if they hold private data.");
+    Ok(())
+}
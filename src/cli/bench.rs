@@ -0,0 +1,49 @@
+//! `yoclaw bench`: synthetic load against a mock provider.
+
+use crate::OutputFormat;
+
+pub async fn run_bench(
+    options: yoclaw::bench::BenchOptions,
+    db: Option<std::path::PathBuf>,
+    baseline: Option<&std::path::Path>,
+    tolerance: f64,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let baseline: Option<yoclaw::bench::BenchReport> = match baseline {
+        Some(path) => Some(serde_json::from_str(&std::fs::read_to_string(path)?)?),
+        None => None,
+    };
+
+    let report = match db {
+        Some(path) => yoclaw::bench::run(&options, path).await?,
+        None => {
+            let path =
+                std::env::temp_dir().join(format!("yoclaw-bench-{}.db", uuid::Uuid::new_v4()));
+            let result = yoclaw::bench::run(&options, path.clone()).await;
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+            }
+            result?
+        }
+    };
+
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Text => print!("{}", report.format_text()),
+    }
+
+    if let Some(baseline) = baseline {
+        let regressions = report.regressions(&baseline, tolerance);
+        if !regressions.is_empty() {
+            for regression in &regressions {
+                eprintln!("Regression: {}", regression);
+            }
+            anyhow::bail!(
+                "{} regression(s) beyond {}% of the baseline",
+                regressions.len(),
+                tolerance
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! `yoclaw chat`: a conversation with the agent in the terminal.
+
+use super::open_db;
+use std::sync::Arc;
+
+pub async fn run_chat(
+    config_path: Option<&std::path::Path>,
+    session: String,
+) -> anyhow::Result<()> {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::io::AsyncBufReadExt;
+    use yoclaw::repl::ReplCommand;
+
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let mut conductor = yoclaw::conductor::Conductor::new(&config, db.clone()).await?;
+
+    let mut session = session;
+    println!(
+        "Chatting in session {} as model {}. /help lists commands.",
+        session, config.agent.model
+    );
+    // Read stdin on its own task, so lines typed during a turn can answer
+    // `ask_user` questions and approval prompts
+    let (line_tx, mut lines) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut stdin = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(line)) = stdin.next_line().await {
+            if line_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let questions = conductor.questions();
+    let approvals = conductor.approvals();
+    loop {
+        print!("you> ");
+        std::io::stdout().flush()?;
+        let Some(line) = lines.recv().await else {
+            println!();
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match yoclaw::repl::parse(line) {
+            Some(Err(usage)) => println!("{}", usage),
+            Some(Ok(ReplCommand::Quit)) => break,
+            Some(Ok(ReplCommand::Help)) => println!("{}", yoclaw::repl::HELP),
+            Some(Ok(ReplCommand::New)) => match conductor.set_aside_session(&session).await? {
+                Some(archived) => println!("Started over. The old conversation is {}.", archived),
+                None => println!("Nothing to set aside yet."),
+            },
+            Some(Ok(ReplCommand::History(limit))) => {
+                // The tape is saved after every turn
+                let messages = db.tape_load_messages(&session).await?;
+                println!("{}", yoclaw::repl::format_history(&messages, limit));
+            }
+            Some(Ok(ReplCommand::Budget)) => {
+                let used = db.audit_token_usage_today().await?;
+                println!(
+                    "{}",
+                    yoclaw::repl::format_budget(
+                        used,
+                        config.agent.budget.max_tokens_per_day,
+                        config.agent.budget.max_turns_per_session
+                    )
+                );
+            }
+            Some(Ok(ReplCommand::Session(id))) => {
+                session = id;
+                println!("Now in session {}.", session);
+            }
+            None => {
+                db.session_touch("cli", &session, None, None, false).await?;
+                let streamed = Arc::new(AtomicBool::new(false));
+                let on_chunk: yoclaw::conductor::OnStreamChunk = {
+                    let streamed = streamed.clone();
+                    Box::new(move |delta: &str| {
+                        if !streamed.swap(true, Ordering::Relaxed) {
+                            print!("agent> ");
+                        }
+                        print!("{}", delta);
+                        let _ = std::io::stdout().flush();
+                    })
+                };
+                let on_progress: Box<dyn Fn(String) + Send + Sync> =
+                    Box::new(|text| println!("  [{}]", text));
+                let turn =
+                    conductor.process_message(&session, line, Some(on_chunk), Some(on_progress));
+                tokio::pin!(turn);
+                let result = loop {
+                    tokio::select! {
+                        result = &mut turn => break result,
+                        Some(typed) = lines.recv() => {
+                            if !approvals.reply(&session, "cli:local", &typed)
+                                && !questions.answer(&session, &typed)
+                            {
+                                println!("  [still working on the last message]");
+                            }
+                        }
+                    }
+                };
+                match result {
+                    // Command replies and cached answers arrive without streaming
+                    Ok(reply) if !streamed.load(Ordering::Relaxed) => {
+                        println!("agent> {}", reply)
+                    }
+                    Ok(_) => println!(),
+                    Err(e) => println!("\nError: {}", e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! `yoclaw cron`: scheduled jobs.
+
+use super::{open_db, print_json, truncate};
+use crate::OutputFormat;
+
+pub async fn run_cron_list(
+    config_path: Option<&std::path::Path>,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let jobs = yoclaw::scheduler::cron::list_jobs(&db).await?;
+
+    if output == OutputFormat::Json {
+        let listings: Vec<_> = jobs.iter().map(|job| job.listing()).collect();
+        return print_json(&listings);
+    }
+    println!("=== Cron jobs ({}) ===", jobs.len());
+    for job in &jobs {
+        println!(
+            "  {} [{}] {} ('{}') → {} ({}, model: {})",
+            job.name,
+            if job.enabled { "enabled" } else { "disabled" },
+            job.describe_schedule(),
+            job.schedule,
+            job.target_channel.as_deref().unwrap_or("none"),
+            job.session_mode,
+            job.model.as_deref().unwrap_or("default")
+        );
+        if let Some(next) = job.next_run().filter(|_| job.enabled) {
+            println!("    next run: {}", next.format("%Y-%m-%d %H:%M UTC"));
+        }
+        println!("    {}", truncate(&job.prompt, 80));
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! `yoclaw debug`: what the model saw in a turn.
+
+use super::{open_db, print_json};
+use crate::OutputFormat;
+
+pub async fn run_debug_turn(
+    config_path: Option<&std::path::Path>,
+    session_id: &str,
+    turn: Option<u32>,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+
+    let Some(turn) = turn else {
+        let turns = db.snapshot_list(session_id).await?;
+        if output == OutputFormat::Json {
+            return print_json(&turns);
+        }
+        println!("{}", yoclaw::debug::format_turn_list(session_id, &turns));
+        return Ok(());
+    };
+
+    let Some(snapshot) = db.snapshot_get(session_id, turn).await? else {
+        anyhow::bail!(
+            "No snapshot of turn {} in session '{}' (snapshots are kept for {} days; see `yoclaw debug turn --session {}`)",
+            turn,
+            session_id,
+            config.persistence.turn_snapshot_days,
+            session_id
+        );
+    };
+    if output == OutputFormat::Json {
+        return print_json(&snapshot);
+    }
+    print!("{}", yoclaw::debug::format_turn(&snapshot));
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! `yoclaw export`: a session's transcript as HTML, PDF, Markdown or JSON.
+
+use super::open_db;
+
+pub async fn run_export(
+    config_path: Option<&std::path::Path>,
+    session_id: &str,
+    format: &str,
+    output: Option<std::path::PathBuf>,
+) -> anyhow::Result<()> {
+    let format: yoclaw::transcript::TranscriptFormat = format.parse()?;
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+
+    let messages = db.tape_load_messages(session_id).await?;
+    if messages.is_empty() {
+        anyhow::bail!("No messages found for session '{}'", session_id);
+    }
+
+    let output = output.unwrap_or_else(|| {
+        std::path::PathBuf::from(format!("{}.{}", session_id, format.extension()))
+    });
+    let bytes = yoclaw::transcript::render(format, session_id, &messages);
+    std::fs::write(&output, bytes)?;
+    println!(
+        "Exported {} messages to {}",
+        messages.len(),
+        output.display()
+    );
+    Ok(())
+}
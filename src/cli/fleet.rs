@@ -0,0 +1,41 @@
+//! `yoclaw fleet`: several agents supervised from one process.
+
+use std::sync::Arc;
+
+pub async fn run_fleet(
+    dir: &std::path::Path,
+    bind: &str,
+    port: u16,
+    no_web: bool,
+) -> anyhow::Result<()> {
+    let fleet = Arc::new(yoclaw::fleet::Fleet::load(dir, run_fleet_agent)?);
+    tracing::info!(
+        "Fleet of {} agent(s) from {}",
+        fleet.agents().len(),
+        dir.display()
+    );
+    fleet.start_all();
+
+    if !no_web {
+        let web_fleet = fleet.clone();
+        let bind = bind.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = yoclaw::web::fleet::start_server(web_fleet, &bind, port).await {
+                tracing::error!("Fleet dashboard error: {}", e);
+            }
+        });
+    }
+
+    tokio::signal::ctrl_c().await?;
+    tracing::info!("Shutting down fleet...");
+    fleet.stop_all().await;
+    Ok(())
+}
+
+/// One fleet agent: the daemon loop, reporting to the fleet's dashboard.
+fn run_fleet_agent(
+    config_path: std::path::PathBuf,
+    events: tokio::sync::broadcast::Sender<yoclaw::web::SseEvent>,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>>>> {
+    Box::pin(async move { crate::run_main(Some(&config_path), Some(events)).await })
+}
@@ -0,0 +1,107 @@
+//! `yoclaw init`: a starter config and persona, and the onboarding questions.
+
+use super::open_db;
+
+pub async fn run_init(
+    config_override: Option<&std::path::Path>,
+    interview: bool,
+    permissive: bool,
+) -> anyhow::Result<()> {
+    let dir = match config_override {
+        Some(p) => p
+            .parent()
+            .map(|d| d.to_path_buf())
+            .unwrap_or_else(yoclaw::config::config_dir),
+        None => yoclaw::config::config_dir(),
+    };
+    std::fs::create_dir_all(&dir)?;
+    std::fs::create_dir_all(dir.join("skills"))?;
+
+    let config_path = match config_override {
+        Some(p) => p.to_path_buf(),
+        None => dir.join("config.toml"),
+    };
+    if !config_path.exists() {
+        let grants = if permissive {
+            "permissive = true\n"
+        } else {
+            r#"
+# shell, write_file and http are disabled until granted:
+# [security.tools.shell]
+# enabled = true
+#
+# [security.tools.write_file]
+# enabled = true
+# allowed_paths = ["~/projects/"]
+#
+# [security.tools.http]
+# enabled = true
+# allowed_hosts = ["api.github.com"]
+"#
+        };
+        let template = r#"[agent]
+provider = "anthropic"
+model = "claude-sonnet-4-20250514"
+api_key = "${ANTHROPIC_API_KEY}"
+
+[agent.budget]
+max_tokens_per_day = 1_000_000
+max_turns_per_session = 50
+
+[channels.telegram]
+bot_token = "${TELEGRAM_BOT_TOKEN}"
+allowed_senders = []
+debounce_ms = 2000
+
+[security]
+shell_deny_patterns = ["rm -rf", "sudo", "chmod 777"]
+"#;
+        std::fs::write(&config_path, format!("{}{}", template, grants))?;
+        println!("Created {}", config_path.display());
+    } else {
+        println!("Config already exists: {}", config_path.display());
+    }
+
+    let persona_path = dir.join("persona.md");
+    if !persona_path.exists() {
+        std::fs::write(
+            &persona_path,
+            "You are a helpful AI assistant. Be concise and clear in your responses.\n",
+        )?;
+        println!("Created {}", persona_path.display());
+    }
+
+    println!("yoclaw initialized at {}", dir.display());
+
+    if interview {
+        let (db, db_path) = match yoclaw::config::load_config(Some(&config_path)) {
+            Ok(config) => (open_db(&config)?, config.db_path()),
+            Err(_) => {
+                let db_path = dir.join("yoclaw.db");
+                (yoclaw::db::Db::open(&db_path)?, db_path)
+            }
+        };
+        run_interview(&db, &db_path).await?;
+    }
+    Ok(())
+}
+
+/// Ask the onboarding questions on the terminal and store the answers.
+async fn run_interview(db: &yoclaw::db::Db, db_path: &std::path::Path) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    println!();
+    println!("A few questions so the agent knows who it's working with. Leave blank to skip one.");
+    for question in &yoclaw::onboarding::QUESTIONS {
+        print!("{} ", question.prompt);
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            break;
+        }
+        yoclaw::onboarding::save_answer(db, question, &answer).await?;
+    }
+    yoclaw::onboarding::finish(db).await?;
+    println!("Saved to {}", db_path.display());
+    Ok(())
+}
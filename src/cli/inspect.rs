@@ -0,0 +1,230 @@
+//! `yoclaw inspect`: queue, sessions, budget and audit at a glance.
+
+use super::{open_db, print_json};
+use crate::OutputFormat;
+
+pub async fn run_inspect(
+    config_path: Option<&std::path::Path>,
+    session_filter: Option<String>,
+    show_skills: bool,
+    show_workers: bool,
+    show_stats: bool,
+    show_worker_runs: bool,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+
+    if show_worker_runs {
+        let runs = db
+            .worker_run_list(session_filter.as_deref(), false, 20)
+            .await?;
+        if output == OutputFormat::Json {
+            return print_json(&runs);
+        }
+        println!("{}", yoclaw::debug::format_worker_runs(&runs));
+        return Ok(());
+    }
+
+    // Session stats (clap guarantees --session is set)
+    if let (true, Some(session)) = (show_stats, session_filter.as_deref()) {
+        let stats = yoclaw::stats::session_stats(&db, session).await?;
+        if output == OutputFormat::Json {
+            let mut value = serde_json::to_value(&stats)?;
+            value["estimated_cost"] = serde_json::json!(config
+                .agent
+                .pricing
+                .as_ref()
+                .map(|p| stats.estimated_cost(p)));
+            return print_json(&value);
+        }
+        println!("=== Stats ===");
+        println!("{}", stats.format(config.agent.pricing.as_ref()));
+        return Ok(());
+    }
+
+    let skills = if show_skills {
+        Some(yoclaw::admin::skill_statuses(&db, &config).await?)
+    } else {
+        None
+    };
+    let workers = if show_workers {
+        let worker_tools: Vec<std::sync::Arc<dyn yoagent::AgentTool>> = Vec::new();
+        let workers = yoclaw::conductor::delegate::build_workers(
+            &config,
+            &worker_tools,
+            &std::collections::HashMap::new(),
+            None,
+        );
+        Some(
+            workers
+                .into_iter()
+                .map(|(_, info)| info)
+                .collect::<Vec<_>>(),
+        )
+    } else {
+        None
+    };
+    let pending = db.queue_pending_count().await?;
+    let pauses = db.pause_list().await?;
+    let failures = db.queue_failures(5).await?;
+    let sessions = db.tape_list_sessions().await?;
+    let tokens_today = db.audit_token_usage_today().await?;
+    let daily_limit = config.agent.budget.max_tokens_per_day;
+    let cost_today = db.audit_cost_today().await?;
+    let cost_week = db.audit_cost_days(7).await?;
+    let max_cost = config.agent.budget.max_cost_per_day;
+    let audit = db.audit_query(session_filter.as_deref(), 20).await?;
+
+    if output == OutputFormat::Json {
+        let mut value = serde_json::json!({
+            "queue": { "pending": pending, "failures": failures, "paused": pauses },
+            "sessions": sessions,
+            "budget": {
+                "tokens_today": tokens_today,
+                "daily_limit": daily_limit,
+                "remaining": daily_limit.map(|max| max.saturating_sub(tokens_today)),
+                "cost_today_usd": cost_today,
+                "cost_week_usd": cost_week,
+                "max_cost_per_day": max_cost,
+            },
+            "audit": audit,
+        });
+        if let Some(ref skills) = skills {
+            value["skills"] = serde_json::to_value(skills)?;
+        }
+        if let Some(ref workers) = workers {
+            value["workers"] = serde_json::to_value(workers)?;
+        }
+        return print_json(&value);
+    }
+
+    // Skills info
+    if let Some(ref statuses) = skills {
+        println!("=== Skills ({}) ===", statuses.len());
+        println!("{}", yoclaw::skills::format_skills_info(statuses));
+        println!();
+    }
+
+    // Workers info
+    if let Some(ref infos) = workers {
+        println!("=== Workers ({}) ===", infos.len());
+        println!(
+            "{}",
+            yoclaw::conductor::delegate::format_workers_info(infos)
+        );
+        println!();
+    }
+
+    // Always show queue, sessions, budget, audit
+    println!("=== Queue ===");
+    println!("Pending messages: {}", pending);
+    for pause in &pauses {
+        println!(
+            "Paused: {} (by {}{})",
+            if pause.scope == yoclaw::db::pause::PAUSE_ALL {
+                "everything"
+            } else {
+                pause.scope.as_str()
+            },
+            pause.by,
+            pause
+                .reason
+                .as_deref()
+                .map(|r| format!(": {}", r))
+                .unwrap_or_default()
+        );
+    }
+    if !failures.is_empty() {
+        println!("Recent failures:");
+        for entry in &failures {
+            let f = &entry.failure;
+            let ts = chrono::DateTime::from_timestamp_millis(
+                entry.failed_at.unwrap_or(entry.created_at) as i64,
+            )
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "?".to_string());
+            println!(
+                "  #{} [{}] {} {}: {}",
+                entry.id, ts, entry.session_id, f.class, f.message
+            );
+            let mut context = Vec::new();
+            if let (Some(provider), Some(model)) = (&f.provider, &f.model) {
+                context.push(format!("{}/{}", provider, model));
+            }
+            if let Some(ref tool) = f.tool {
+                context.push(format!("tool {}", tool));
+            }
+            context.push(format!("{} turns, {} tokens", f.turns, f.tokens));
+            context.push(
+                if f.retryable {
+                    "retryable"
+                } else {
+                    "not retryable"
+                }
+                .to_string(),
+            );
+            println!("      {}", context.join(", "));
+        }
+    }
+    println!();
+
+    // Sessions
+    println!("=== Sessions ({}) ===", sessions.len());
+    for s in &sessions {
+        let updated = chrono::DateTime::from_timestamp_millis(s.updated_at as i64)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!(
+            "  {} — {} messages, last updated {}",
+            s.session_id, s.message_count, updated
+        );
+    }
+    println!();
+
+    // Token usage
+    println!("=== Budget ===");
+    println!("Tokens used today: {}", tokens_today);
+    if let Some(max) = daily_limit {
+        println!("Daily limit: {}", max);
+        println!("Remaining: {}", max.saturating_sub(tokens_today));
+    }
+    println!(
+        "Cost today: {}, last 7 days: {}",
+        yoclaw::pricing::format_usd(cost_today),
+        yoclaw::pricing::format_usd(cost_week)
+    );
+    if let Some(max) = max_cost {
+        println!("Daily cost limit: {}", yoclaw::pricing::format_usd(max));
+    }
+    println!();
+
+    // Audit log (recent or filtered)
+    if !audit.is_empty() {
+        println!("=== Recent Audit ({}) ===", audit.len());
+        for entry in &audit {
+            let ts = chrono::DateTime::from_timestamp_millis(entry.timestamp as i64)
+                .map(|dt| dt.format("%H:%M:%S").to_string())
+                .unwrap_or_else(|| "?".to_string());
+            println!(
+                "  [{}] {} {} {}",
+                ts,
+                entry.event_type,
+                entry.tool_name.as_deref().unwrap_or(""),
+                entry
+                    .detail
+                    .as_ref()
+                    .map(|d| {
+                        if d.len() > 60 {
+                            format!("{}...", &d[..60])
+                        } else {
+                            d.clone()
+                        }
+                    })
+                    .unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(())
+}
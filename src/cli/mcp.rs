@@ -0,0 +1,13 @@
+//! `yoclaw mcp-serve`: memory, cron jobs and sessions as MCP tools on stdio.
+
+use super::open_db;
+
+pub async fn run_mcp_serve(config_path: Option<&std::path::Path>) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let server =
+        yoclaw::mcp_server::McpServer::new(db, yoclaw::scheduler::cron::JobRules::new(&config));
+    let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+    server.serve(stdin, tokio::io::stdout()).await?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! `yoclaw memory`: list, search and edit long-term memories.
+
+use super::{open_db, print_json, truncate};
+use crate::OutputFormat;
+
+pub async fn run_memory_list(
+    config_path: Option<&std::path::Path>,
+    limit: usize,
+    filter: yoclaw::db::memory::MemoryFilter,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let memories = db.memory_list_filtered(limit, &filter).await?;
+    print_memories(&memories, output)
+}
+
+pub async fn run_memory_search(
+    config_path: Option<&std::path::Path>,
+    query: &str,
+    limit: usize,
+    filter: yoclaw::db::memory::MemoryFilter,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    /// Extra matches fetched when filtering, so filters don't empty the results
+    const FILTER_OVERFETCH: usize = 5;
+
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let mut memories = db
+        .memory_search(query, limit.saturating_mul(FILTER_OVERFETCH))
+        .await?;
+    memories.retain(|m| filter.matches(m));
+    memories.truncate(limit);
+    print_memories(&memories, output)
+}
+
+pub async fn run_memory_add(
+    config_path: Option<&std::path::Path>,
+    content: &str,
+    key: Option<&str>,
+    category: &str,
+    importance: i32,
+    tags: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let id = db
+        .memory_store_with_meta(key, content, tags, Some("cli"), category, importance)
+        .await?;
+    println!("Stored memory #{}", id);
+    Ok(())
+}
+
+pub async fn run_memory_edit(
+    config_path: Option<&std::path::Path>,
+    id: i64,
+    edit: yoclaw::db::memory::MemoryEdit,
+) -> anyhow::Result<()> {
+    if edit.content.is_none()
+        && edit.category.is_none()
+        && edit.importance.is_none()
+        && edit.tags.is_none()
+    {
+        anyhow::bail!("Nothing to change: pass --content, --category, --importance or --tags");
+    }
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    if !db.memory_edit(id, &edit).await? {
+        anyhow::bail!("No memory #{}", id);
+    }
+    println!("Updated memory #{}", id);
+    Ok(())
+}
+
+pub async fn run_memory_delete(
+    config_path: Option<&std::path::Path>,
+    ids: &[i64],
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let mut missing = Vec::new();
+    for &id in ids {
+        if db.memory_get_by_id(id).await?.is_none() {
+            missing.push(id.to_string());
+            continue;
+        }
+        db.memory_delete(id).await?;
+        println!("Deleted memory #{}", id);
+    }
+    if !missing.is_empty() {
+        anyhow::bail!("No memory #{}", missing.join(", #"));
+    }
+    Ok(())
+}
+
+fn print_memories(
+    memories: &[yoclaw::db::memory::MemoryEntry],
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    if output == OutputFormat::Json {
+        return print_json(&memories);
+    }
+    println!("=== Memories ({}) ===", memories.len());
+    for m in memories {
+        let updated = chrono::DateTime::from_timestamp_millis(m.updated_at as i64)
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "?".to_string());
+        println!(
+            "  #{} [{}, importance {}{}] {} — {}",
+            m.id.unwrap_or_default(),
+            m.category,
+            m.importance,
+            m.namespace
+                .as_deref()
+                .map(|ns| format!(", {}", ns))
+                .unwrap_or_default(),
+            updated,
+            truncate(&m.content, 80)
+        );
+    }
+    Ok(())
+}
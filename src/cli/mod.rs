@@ -0,0 +1,60 @@
+//! Subcommands other than the daemon itself, one module per command.
+
+pub mod backup;
+pub mod bench;
+pub mod chat;
+pub mod cron;
+pub mod debug;
+pub mod export;
+pub mod fleet;
+pub mod init;
+pub mod inspect;
+pub mod mcp;
+pub mod memory;
+pub mod pause;
+pub mod persona;
+pub mod secrets;
+pub mod sessions;
+
+pub fn print_json<T: serde::Serialize>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// A millisecond timestamp as UTC date and time.
+pub fn format_ms(ms: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms as i64)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Open the configured database, unlocked with its `[persistence.encryption]`
+/// key if it has one, and with the Postgres storage of
+/// `[persistence] backend = "postgres"`.
+pub fn open_db(config: &yoclaw::config::Config) -> anyhow::Result<yoclaw::db::Db> {
+    let key = config.db_key()?;
+    let db = yoclaw::db::Db::open_with_key(
+        &config.db_path(),
+        config.persistence.read_pool_size,
+        key.as_deref(),
+    )?;
+    let persistence = &config.persistence;
+    match (persistence.backend.as_str(), &persistence.url) {
+        ("postgres", Some(url)) => {
+            let node = persistence
+                .node
+                .clone()
+                .unwrap_or_else(yoclaw::db::storage::default_node);
+            Ok(db.with_storage(yoclaw::db::storage::connect(url, &node)?))
+        }
+        _ => Ok(db),
+    }
+}
+
+/// First `max` characters of `s`, with "..." if anything was cut.
+pub fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}...", &s[..i]),
+        None => s.to_string(),
+    }
+}
@@ -0,0 +1,60 @@
+//! `yoclaw pause`, `yoclaw resume` and `yoclaw queue`: holding and replaying messages.
+
+use super::open_db;
+use std::time::Duration;
+
+pub async fn run_queue_retry_failed(
+    config_path: Option<&std::path::Path>,
+    since: Duration,
+    pace: Duration,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let count = yoclaw::retry::retry_failed(&db, since, pace).await?;
+    if count == 0 {
+        println!("No failed messages in that window.");
+    } else {
+        println!(
+            "Queued {} failed message(s) for another attempt, {}s apart. yoclaw starts on them within a few seconds.",
+            count,
+            pace.as_secs()
+        );
+    }
+    Ok(())
+}
+
+pub async fn run_pause(
+    config_path: Option<&std::path::Path>,
+    session: Option<&str>,
+    reason: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let scope = session.unwrap_or(yoclaw::db::pause::PAUSE_ALL);
+    db.pause_set(scope, "cli", reason).await?;
+    match session {
+        Some(session) => println!(
+            "Paused session {}. Its messages are queued until `yoclaw resume --session {}`.",
+            session, session
+        ),
+        None => println!(
+            "Paused. Messages are queued and no agent, cron job or background task runs until `yoclaw resume --all`."
+        ),
+    }
+    Ok(())
+}
+
+pub async fn run_resume(
+    config_path: Option<&std::path::Path>,
+    session: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let scope = session.unwrap_or(yoclaw::db::pause::PAUSE_ALL);
+    if db.pause_clear(scope).await? {
+        println!("Resumed. Queued messages are processed within a few seconds.");
+    } else {
+        println!("Not paused.");
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! `yoclaw persona`: the system prompt a chat would get.
+
+use super::open_db;
+
+pub async fn run_persona_render(
+    config_path: Option<&std::path::Path>,
+    channel: Option<String>,
+    session_id: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+
+    let channel = match session_id {
+        Some(session_id) => match db.session_get(session_id).await? {
+            Some(session) => Some(session.channel),
+            None => anyhow::bail!("Unknown session '{}'", session_id),
+        },
+        None => channel,
+    };
+    let mut prompt = yoclaw::conductor::persona::assemble(&config, channel.as_deref(), &db).await?;
+    if let Some(session_id) = session_id {
+        if let Some(name) = db.project_selected(session_id).await? {
+            let block = yoclaw::projects::context(&db, &name, session_id).await?;
+            prompt = format!("{}\n\n{}", prompt, block);
+        }
+    }
+    println!("{}", prompt);
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! `yoclaw secrets`: values kept in the system keyring.
+
+use crate::SecretsCommands;
+
+pub fn run_secrets(command: SecretsCommands) -> anyhow::Result<()> {
+    match command {
+        SecretsCommands::Set { name } => {
+            if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+                eprint!("Value for {}: ", name);
+            }
+            let mut value = String::new();
+            std::io::stdin().read_line(&mut value)?;
+            let value = value.trim_end_matches(['\r', '\n']);
+            if value.is_empty() {
+                anyhow::bail!("No value given");
+            }
+            yoclaw::secrets::set(&name, value)?;
+            println!(
+                "Stored '{}'; use \"{}{}\" in the config",
+                name,
+                yoclaw::secrets::PREFIX,
+                name
+            );
+        }
+        SecretsCommands::Get { name } => println!("{}", yoclaw::secrets::get(&name)?),
+        SecretsCommands::Rm { name } => {
+            yoclaw::secrets::remove(&name)?;
+            println!("Deleted '{}'", name);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! `yoclaw sessions`: conversation tapes.
+
+use super::{format_ms, open_db, print_json};
+use crate::OutputFormat;
+use std::time::Duration;
+
+/// Sessions with a tape, most recently active first; with `idle`, only
+/// those not updated for that long.
+async fn idle_sessions(
+    db: &yoclaw::db::Db,
+    idle: Option<Duration>,
+) -> anyhow::Result<Vec<yoclaw::db::tape::SessionInfo>> {
+    let mut sessions = db.tape_list_sessions().await?;
+    if let Some(idle) = idle {
+        let cutoff = yoclaw::db::now_ms().saturating_sub(idle.as_millis() as u64);
+        sessions.retain(|s| s.updated_at < cutoff);
+    }
+    Ok(sessions)
+}
+
+pub async fn run_sessions_list(
+    config_path: Option<&std::path::Path>,
+    idle: Option<Duration>,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let sessions = idle_sessions(&db, idle).await?;
+
+    if output == OutputFormat::Json {
+        return print_json(&sessions);
+    }
+    println!("=== Sessions ({}) ===", sessions.len());
+    for s in &sessions {
+        let private = db.privacy_is_private(&s.session_id).await?;
+        println!(
+            "  {} — {} messages, last active {}{}",
+            s.session_id,
+            s.message_count,
+            format_ms(s.updated_at),
+            if private { " (private)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+pub async fn run_sessions_show(
+    config_path: Option<&std::path::Path>,
+    session_id: &str,
+    limit: usize,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let Some(mut tape) = db.tape_export(session_id).await? else {
+        anyhow::bail!("No messages found for session '{}'", session_id);
+    };
+
+    let total = tape.messages.len();
+    if output == OutputFormat::Json {
+        tape.messages.drain(..total.saturating_sub(limit));
+        return print_json(&tape);
+    }
+    println!("=== Session {} ===", session_id);
+    println!(
+        "  {} messages, started {}, last active {}",
+        total,
+        format_ms(tape.created_at),
+        format_ms(tape.updated_at)
+    );
+    if let Some(info) = db.session_get(session_id).await? {
+        println!(
+            "  Channel: {}{}",
+            info.channel,
+            if info.is_group { " (group)" } else { "" }
+        );
+    }
+    if db.privacy_is_private(session_id).await? {
+        println!("  Private");
+    }
+    println!();
+    println!("{}", yoclaw::debug::format_tape(&tape.messages, limit));
+    Ok(())
+}
+
+pub async fn run_sessions_delete(
+    config_path: Option<&std::path::Path>,
+    session_id: Option<&str>,
+    idle: Option<Duration>,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    let ids: Vec<String> = match session_id {
+        Some(id) => vec![id.to_string()],
+        None => idle_sessions(&db, idle)
+            .await?
+            .into_iter()
+            .map(|s| s.session_id)
+            .collect(),
+    };
+
+    let mut deleted = 0;
+    for id in &ids {
+        if db.tape_delete_session(id).await? {
+            println!("Deleted {}", id);
+            deleted += 1;
+        } else if session_id.is_some() {
+            anyhow::bail!("No messages found for session '{}'", id);
+        }
+    }
+    println!("Deleted {} session(s)", deleted);
+    Ok(())
+}
+
+pub async fn run_sessions_rename(
+    config_path: Option<&std::path::Path>,
+    session_id: &str,
+    new_id: &str,
+) -> anyhow::Result<()> {
+    let config = yoclaw::config::load_config(config_path)?;
+    let db = open_db(&config)?;
+    if db.tape_export(new_id).await?.is_some() {
+        anyhow::bail!("Session '{}' already exists", new_id);
+    }
+    if !db.tape_rename_session(session_id, new_id).await? {
+        anyhow::bail!("No messages found for session '{}'", session_id);
+    }
+    println!("Renamed {} to {}", session_id, new_id);
+    Ok(())
+}
@@ -0,0 +1,368 @@
+//! Clarifying questions asked in the middle of a turn.
+//!
+//! Without this, an agent that needs more information ends its reply with a
+//! question, and the tool state it built up (files read, commands run) is only
+//! available again as tape history on the next message. [`AskUserTool`] sends
+//! the question and waits, inside the same run, for the user's next message in
+//! that session. The main loop is busy with the very turn that is waiting, so
+//! answers are taken out of the incoming stream by [`forward_answers`] before
+//! they reach it. If nobody answers in time the agent carries on with its best
+//! assumption. Decisions on tool calls waiting for approval
+//! ([`Approvals`]) are taken out of the stream the same way.
+
+use crate::channels::{IncomingMessage, MessageKind};
+use crate::security::approval::{self, Approvals};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use yoagent::types::*;
+
+/// Sessions with a question waiting for an answer.
+#[derive(Clone, Default)]
+pub struct Questions {
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<String>>>>,
+}
+
+impl Questions {
+    /// Wait for the next answer in `session_id`. Replaces any earlier question.
+    fn ask(&self, session_id: &str) -> oneshot::Receiver<String> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), tx);
+        rx
+    }
+
+    /// Stop waiting for an answer in `session_id`.
+    fn withdraw(&self, session_id: &str) {
+        self.pending.lock().unwrap().remove(session_id);
+    }
+
+    /// Hand `text` to the question waiting in `session_id`.
+    /// Returns false if there is none.
+    pub fn answer(&self, session_id: &str, text: &str) -> bool {
+        let Some(tx) = self.pending.lock().unwrap().remove(session_id) else {
+            return false;
+        };
+        tx.send(text.to_string()).is_ok()
+    }
+
+    pub fn is_waiting(&self, session_id: &str) -> bool {
+        self.pending.lock().unwrap().contains_key(session_id)
+    }
+}
+
+/// Pass incoming messages from `rx` to `tx`, except new messages in a session
+/// with a pending question, which become its answer, and replies and
+/// reactions deciding a pending approval. A reply that is neither yes nor no
+/// denies the approval and is passed on. Only the requester and admins decide
+/// approvals; messages from anyone else pass on untouched.
+pub async fn forward_answers(
+    mut rx: mpsc::UnboundedReceiver<IncomingMessage>,
+    tx: mpsc::UnboundedSender<IncomingMessage>,
+    questions: Questions,
+    approvals: Approvals,
+) {
+    while let Some(msg) = rx.recv().await {
+        if approvals.is_waiting(&msg.session_id) {
+            let sender = format!("{}:{}", msg.channel, msg.sender_id);
+            let decided = match msg.kind {
+                MessageKind::New => approvals.reply(&msg.session_id, &sender, &msg.content),
+                MessageKind::Reaction { ref emoji } => approval::parse_reaction(emoji)
+                    .is_some_and(|approved| approvals.decide(&msg.session_id, &sender, approved)),
+                _ => false,
+            };
+            if decided {
+                tracing::info!("Approval decided in {}", msg.session_id);
+                continue;
+            }
+        }
+        if msg.kind == MessageKind::New && questions.answer(&msg.session_id, &msg.content) {
+            tracing::info!("Answer received in {}", msg.session_id);
+            continue;
+        }
+        if tx.send(msg).is_err() {
+            break;
+        }
+    }
+}
+
+/// Tool that asks the user a question and waits for the answer.
+pub struct AskUserTool {
+    questions: Questions,
+    session_id: Arc<RwLock<String>>,
+    timeout: Duration,
+}
+
+impl AskUserTool {
+    pub fn new(questions: Questions, session_id: Arc<RwLock<String>>, timeout: Duration) -> Self {
+        Self {
+            questions,
+            session_id,
+            timeout,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentTool for AskUserTool {
+    fn name(&self) -> &str {
+        "ask_user"
+    }
+
+    fn label(&self) -> &str {
+        "Ask User"
+    }
+
+    fn description(&self) -> &str {
+        "Ask the user a clarifying question and wait for the answer before continuing. \
+         Use this instead of ending your reply with a question when you cannot finish the \
+         task without more information. Your work so far is kept. If the user does not \
+         answer in time, continue with your best assumption and say what you assumed."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question to ask. Keep it short and specific."
+                }
+            },
+            "required": ["question"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let question = params["question"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidArgs("Missing 'question' parameter".into()))?;
+
+        // Scheduled runs and delegations have nobody to ask
+        let Some(ref on_progress) = ctx.on_progress else {
+            return Ok(text_result(
+                "There is no user to ask here. Continue with your best assumption \
+                 and say what you assumed.",
+            ));
+        };
+
+        let session_id = self.session_id.read().unwrap().clone();
+        let answer = self.questions.ask(&session_id);
+        on_progress(question.to_string());
+
+        let text = tokio::select! {
+            result = tokio::time::timeout(self.timeout, answer) => match result {
+                Ok(Ok(answer)) => format!("The user answered: {}", answer),
+                Ok(Err(_)) => "The question was replaced by a newer one. \
+                               Continue with your best assumption."
+                    .to_string(),
+                Err(_) => {
+                    self.questions.withdraw(&session_id);
+                    format!(
+                        "The user did not answer within {} seconds. Continue with your best \
+                         assumption and say what you assumed.",
+                        self.timeout.as_secs()
+                    )
+                }
+            },
+            _ = ctx.cancel.cancelled() => {
+                self.questions.withdraw(&session_id);
+                return Err(ToolError::Failed("Cancelled while waiting for an answer".into()));
+            }
+        };
+
+        Ok(text_result(&text))
+    }
+}
+
+fn text_result(text: &str) -> ToolResult {
+    ToolResult {
+        content: vec![Content::Text {
+            text: text.to_string(),
+        }],
+        details: serde_json::json!({}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(asked: Arc<Mutex<Vec<String>>>) -> ToolContext {
+        ToolContext {
+            tool_call_id: "tc-1".to_string(),
+            tool_name: "ask_user".to_string(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: Some(Arc::new(move |text: String| {
+                asked.lock().unwrap().push(text);
+            })),
+        }
+    }
+
+    fn incoming(session_id: &str, content: &str) -> IncomingMessage {
+        IncomingMessage {
+            channel: "telegram".into(),
+            sender_id: "1".into(),
+            sender_name: None,
+            session_id: session_id.into(),
+            chat_id: None,
+            thread_id: None,
+            content: content.into(),
+            reply_to: None,
+            quote: None,
+            timestamp: 0,
+            worker_hint: None,
+            is_group: false,
+            message_id: None,
+            kind: MessageKind::New,
+            attachments: Vec::new(),
+        }
+    }
+
+    fn result_text(result: &ToolResult) -> &str {
+        match &result.content[0] {
+            Content::Text { text } => text,
+            _ => "",
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ask_user_waits_for_answer() {
+        let questions = Questions::default();
+        let session = Arc::new(RwLock::new("tg-1".to_string()));
+        let tool = AskUserTool::new(questions.clone(), session, Duration::from_secs(5));
+        let asked = Arc::new(Mutex::new(Vec::new()));
+
+        // Answers go to the waiting tool; other sessions pass through
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel();
+        tokio::spawn(forward_answers(
+            in_rx,
+            out_tx,
+            questions.clone(),
+            Approvals::default(),
+        ));
+
+        let run = tokio::spawn({
+            let asked = asked.clone();
+            async move {
+                tool.execute(serde_json::json!({"question": "Which branch?"}), ctx(asked))
+                    .await
+            }
+        });
+        while !questions.is_waiting("tg-1") {
+            tokio::task::yield_now().await;
+        }
+        in_tx.send(incoming("tg-2", "hello")).unwrap();
+        in_tx.send(incoming("tg-1", "main")).unwrap();
+
+        let result = run.await.unwrap().unwrap();
+        assert_eq!(result_text(&result), "The user answered: main");
+        assert_eq!(*asked.lock().unwrap(), vec!["Which branch?".to_string()]);
+        assert_eq!(out_rx.recv().await.unwrap().session_id, "tg-2");
+        assert!(!questions.is_waiting("tg-1"));
+
+        // With no question pending, messages are not intercepted
+        in_tx.send(incoming("tg-1", "thanks")).unwrap();
+        assert_eq!(out_rx.recv().await.unwrap().content, "thanks");
+    }
+
+    #[tokio::test]
+    async fn test_forward_approval_decisions() {
+        let approvals = Approvals::new(Duration::from_secs(5));
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel();
+        tokio::spawn(forward_answers(
+            in_rx,
+            out_tx,
+            Questions::default(),
+            approvals.clone(),
+        ));
+
+        let request = |approvals: Approvals| {
+            tokio::spawn(async move {
+                approvals
+                    .request("tg-1", "bash", &serde_json::json!({}), &ctx(Arc::default()))
+                    .await
+            })
+        };
+
+        // A thumbs-up reaction approves
+        let run = request(approvals.clone());
+        while !approvals.is_waiting("tg-1") {
+            tokio::task::yield_now().await;
+        }
+        let mut reaction = incoming("tg-1", "");
+        reaction.kind = MessageKind::Reaction {
+            emoji: "👍".into()
+        };
+        in_tx.send(reaction).unwrap();
+        assert_eq!(run.await.unwrap(), approval::Outcome::Approved);
+
+        // Anything but yes or no denies, and still reaches the main loop
+        let run = request(approvals.clone());
+        while !approvals.is_waiting("tg-1") {
+            tokio::task::yield_now().await;
+        }
+        in_tx.send(incoming("tg-1", "wait, what?")).unwrap();
+        assert_eq!(run.await.unwrap(), approval::Outcome::Denied);
+        assert_eq!(out_rx.recv().await.unwrap().content, "wait, what?");
+
+        // Only the sender of the message that led to the call decides
+        approvals.note_requester(
+            "tg-1",
+            approval::Requester {
+                sender: "telegram:1".into(),
+                ..Default::default()
+            },
+        );
+        let run = request(approvals.clone());
+        while !approvals.is_waiting("tg-1") {
+            tokio::task::yield_now().await;
+        }
+        let mut other = incoming("tg-1", "yes");
+        other.sender_id = "2".into();
+        in_tx.send(other).unwrap();
+        assert_eq!(out_rx.recv().await.unwrap().sender_id, "2");
+        assert!(approvals.is_waiting("tg-1"));
+        in_tx.send(incoming("tg-1", "Approve")).unwrap();
+        assert_eq!(run.await.unwrap(), approval::Outcome::Approved);
+    }
+
+    #[tokio::test]
+    async fn test_ask_user_timeout_and_no_channel() {
+        let questions = Questions::default();
+        let session = Arc::new(RwLock::new("tg-1".to_string()));
+        let tool = AskUserTool::new(questions.clone(), session, Duration::from_millis(20));
+
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let result = tool
+            .execute(serde_json::json!({"question": "Which branch?"}), ctx(asked))
+            .await
+            .unwrap();
+        assert!(result_text(&result).contains("did not answer"));
+        assert!(!questions.is_waiting("tg-1"));
+        assert!(!questions.answer("tg-1", "too late"));
+
+        let mut no_channel = ctx(Arc::default());
+        no_channel.on_progress = None;
+        let result = tool
+            .execute(serde_json::json!({"question": "Which branch?"}), no_channel)
+            .await
+            .unwrap();
+        assert!(result_text(&result).contains("no user to ask"));
+
+        assert!(tool
+            .execute(serde_json::json!({}), ctx(Arc::default()))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Source links for facts taken from web tools.
+//!
+//! After each turn, the results of `http` and `web_search` calls are matched
+//! against the reply: a source counts as used if the reply contains its URL or
+//! shares at least [`MIN_SHARED`] word sequences with the text it came with.
+//! Channels with `citations = true` get the used sources appended as links.
+
+use std::collections::{HashMap, HashSet};
+use yoagent::types::*;
+
+/// Tools whose results can be cited.
+const CITABLE_TOOLS: [&str; 2] = ["http", "web_search"];
+
+/// Words per sequence compared between a source and the reply.
+const SHINGLE_WORDS: usize = 4;
+
+/// Shared sequences needed before a source counts as used.
+const MIN_SHARED: usize = 2;
+
+/// Most links appended to one reply.
+const MAX_SOURCES: usize = 5;
+
+/// URLs of web sources the reply draws on, in the order they were fetched.
+/// `turn` is the messages of the turn that produced `response`.
+pub fn referenced_sources(turn: &[AgentMessage], response: &str) -> Vec<String> {
+    // Tool call arguments by call ID, for the URL an http result came from
+    let mut arguments: HashMap<&str, &serde_json::Value> = HashMap::new();
+    for msg in turn {
+        if let AgentMessage::Llm(Message::Assistant { content, .. }) = msg {
+            for c in content {
+                if let Content::ToolCall {
+                    id, arguments: a, ..
+                } = c
+                {
+                    arguments.insert(id.as_str(), a);
+                }
+            }
+        }
+    }
+
+    let reply = shingles(response);
+    let mut sources: Vec<String> = Vec::new();
+    for msg in turn {
+        let AgentMessage::Llm(Message::ToolResult {
+            tool_call_id,
+            tool_name,
+            content,
+            is_error: false,
+            ..
+        }) = msg
+        else {
+            continue;
+        };
+        if !CITABLE_TOOLS.contains(&tool_name.as_str()) {
+            continue;
+        }
+        let called_url = arguments
+            .get(tool_call_id.as_str())
+            .and_then(|a| a["url"].as_str());
+        let text = content
+            .iter()
+            .filter_map(|c| match c {
+                Content::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // Search results list several pages; credit the URL next to each passage
+        for block in text.split("\n\n") {
+            let found = urls(block);
+            let block_urls = if found.is_empty() {
+                called_url.into_iter().collect()
+            } else {
+                found
+            };
+            for url in block_urls {
+                if sources.iter().any(|s| s == url) {
+                    continue;
+                }
+                let shared = shingles(block).intersection(&reply).count();
+                if response.contains(url) || shared >= MIN_SHARED {
+                    sources.push(url.to_string());
+                }
+            }
+        }
+    }
+    sources.truncate(MAX_SOURCES);
+    sources
+}
+
+/// `response` with a compact list of `sources` appended.
+pub fn append(response: String, sources: &[String]) -> String {
+    if sources.is_empty() {
+        return response;
+    }
+    let list = sources
+        .iter()
+        .enumerate()
+        .map(|(i, url)| format!("[{}] {}", i + 1, url))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\nSources:\n{}", response, list)
+}
+
+/// http(s) URLs in `text`, without trailing punctuation.
+fn urls(text: &str) -> Vec<&str> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '(' | ')'))
+        .filter(|w| w.starts_with("https://") || w.starts_with("http://"))
+        .map(|w| w.trim_end_matches(['.', ',', ';', ':', ']', '\'']))
+        .collect()
+}
+
+/// Lowercased runs of [`SHINGLE_WORDS`] consecutive words.
+fn shingles(text: &str) -> HashSet<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words.windows(SHINGLE_WORDS).map(|w| w.join(" ")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(id: &str, name: &str, arguments: serde_json::Value) -> AgentMessage {
+        AgentMessage::Llm(Message::Assistant {
+            content: vec![Content::ToolCall {
+                id: id.into(),
+                name: name.into(),
+                arguments,
+            }],
+            stop_reason: StopReason::ToolUse,
+            model: "mock".into(),
+            provider: "mock".into(),
+            usage: Usage::default(),
+            timestamp: 0,
+            error_message: None,
+        })
+    }
+
+    fn result(id: &str, name: &str, text: &str) -> AgentMessage {
+        AgentMessage::Llm(Message::ToolResult {
+            tool_call_id: id.into(),
+            tool_name: name.into(),
+            content: vec![Content::Text { text: text.into() }],
+            is_error: false,
+            timestamp: 0,
+        })
+    }
+
+    #[test]
+    fn test_referenced_sources() {
+        let turn = vec![
+            call("c1", "http", serde_json::json!({"url": "https://example.com/release"})),
+            result(
+                "c1",
+                "http",
+                "Version 2.4 was released on March 3 and adds offline sync for all users.",
+            ),
+            call("c2", "web_search", serde_json::json!({"query": "rust 2024"})),
+            result(
+                "c2",
+                "web_search",
+                "The Rust 2024 edition shipped with Rust 1.85 in February.\nhttps://blog.rust-lang.org/2025/02/20.html\n\n\
+                 Unrelated page about gardening tips.\nhttps://garden.example.org/tips",
+            ),
+            call("c3", "bash", serde_json::json!({"command": "curl https://ignored.example"})),
+            result("c3", "bash", "the rust 2024 edition shipped with rust 1.85"),
+        ];
+        let response = "Version 2.4 was released on March 3 with offline sync. \
+                        Also, the Rust 2024 edition shipped with Rust 1.85.";
+
+        let sources = referenced_sources(&turn, response);
+        assert_eq!(
+            sources,
+            vec![
+                "https://example.com/release".to_string(),
+                "https://blog.rust-lang.org/2025/02/20.html".to_string(),
+            ]
+        );
+
+        // Nothing in common, nothing cited
+        assert!(referenced_sources(&turn, "I couldn't find anything useful.").is_empty());
+
+        let text = append("Answer.".into(), &sources);
+        assert_eq!(
+            text,
+            "Answer.\n\nSources:\n[1] https://example.com/release\n[2] https://blog.rust-lang.org/2025/02/20.html"
+        );
+        assert_eq!(append("Answer.".into(), &[]), "Answer.");
+    }
+}
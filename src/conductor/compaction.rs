@@ -1,18 +1,100 @@
+use super::snapshot::TurnRecorder;
 use crate::db::Db;
+use crate::scheduler::AgentRunConfig;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use yoagent::context::{compact_messages, total_tokens, CompactionStrategy, ContextConfig};
 use yoagent::types::*;
 
+/// Max characters of dropped conversation sent to the summarizer.
+const SUMMARY_INPUT_CHARS: usize = 16_000;
+/// Compaction blocks the turn, so give up on a slow summarizer quickly.
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SUMMARY_SYSTEM_PROMPT: &str =
+    "You compress conversation excerpts into summaries for long-term memory.";
+
 /// Compaction strategy that saves dropped conversation content to memory
 /// before removal, making it searchable via MemorySearchTool.
 pub struct MemoryAwareCompaction {
     db: Db,
     session_id: Arc<RwLock<String>>,
+    /// When set, dropped content is discarded instead of stored (private sessions).
+    private: Arc<AtomicBool>,
+    /// When set, dropped content is summarized by this model instead of stored raw.
+    summarizer: Option<AgentRunConfig>,
+    /// When set, the compacted context goes into the turn snapshot.
+    recorder: Option<Arc<TurnRecorder>>,
 }
 
 impl MemoryAwareCompaction {
     pub fn new(db: Db, session_id: Arc<RwLock<String>>) -> Self {
-        Self { db, session_id }
+        Self {
+            db,
+            session_id,
+            private: Arc::new(AtomicBool::new(false)),
+            summarizer: None,
+            recorder: None,
+        }
+    }
+
+    /// Share the conductor's private-session flag with this strategy.
+    pub fn with_private_flag(mut self, private: Arc<AtomicBool>) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Record compacted contexts in turn snapshots.
+    pub fn with_turn_recorder(mut self, recorder: Arc<TurnRecorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Summarize dropped messages with an LLM. The summary is stored to memory
+    /// and also kept in context as a `[Summary]` message.
+    pub fn with_summarizer(mut self, agent_config: AgentRunConfig) -> Self {
+        self.summarizer = Some(agent_config);
+        self
+    }
+
+    /// Ask the summarizer model for a dense summary. Returns None if no
+    /// summarizer is configured or the call fails, so callers fall back to raw text.
+    fn summarize(&self, text: &str) -> Option<String> {
+        let agent_config = self.summarizer.as_ref()?;
+        // compact() is sync; it needs a multi-threaded runtime to block on the call
+        let handle = tokio::runtime::Handle::try_current().ok()?;
+        let prompt = format!(
+            "Summarize this conversation excerpt in at most 200 words. Keep names, \
+             facts, decisions, dates and open tasks; drop greetings and filler. \
+             Reply with the summary only.\n\n{}",
+            truncate_chars(text, SUMMARY_INPUT_CHARS)
+        );
+        let result = tokio::task::block_in_place(|| {
+            handle.block_on(tokio::time::timeout(
+                SUMMARY_TIMEOUT,
+                crate::scheduler::run_ephemeral_prompt(
+                    agent_config,
+                    SUMMARY_SYSTEM_PROMPT,
+                    &prompt,
+                ),
+            ))
+        });
+        match result {
+            Ok(Ok(summary)) if !summary.trim().is_empty() => Some(summary.trim().to_string()),
+            Ok(Ok(_)) => {
+                tracing::warn!("Compaction summary was empty, storing raw text");
+                None
+            }
+            Ok(Err(e)) => {
+                tracing::warn!("Compaction summary failed, storing raw text: {}", e);
+                None
+            }
+            Err(_) => {
+                tracing::warn!("Compaction summary timed out, storing raw text");
+                None
+            }
+        }
     }
 }
 
@@ -32,27 +114,44 @@ impl CompactionStrategy for MemoryAwareCompaction {
             .min(messages.len().saturating_sub(keep_first));
         let drop_end = messages.len().saturating_sub(keep_recent);
 
-        let droppable_text = if drop_end > keep_first {
+        let droppable_text = if drop_end > keep_first && !self.private.load(Ordering::SeqCst) {
             extract_text_content(&messages[keep_first..drop_end])
         } else {
             String::new()
         };
 
         let original_len = messages.len();
-        let compacted = compact_messages(messages, config);
+        // Providers reject a tool call without its results right after it,
+        // and results without their call (Anthropic: 400 "tool_use ids were
+        // found without tool_result blocks immediately after")
+        let mut compacted = pair_tool_calls(compact_messages(messages, config));
+
+        if compacted.len() < original_len {
+            // Recorded for private sessions too; the detail holds no content
+            let session_id = self.session_id.read().unwrap().clone();
+            let detail = format!("dropped={}", original_len - compacted.len());
+            let logged = tokio::task::block_in_place(|| {
+                self.db
+                    .audit_log_sync(Some(&session_id), "compaction", None, Some(&detail), 0)
+            });
+            if let Err(e) = logged {
+                tracing::warn!("Failed to audit compaction: {}", e);
+            }
+        }
 
         // If messages were actually dropped, store extracted text to memory
         if compacted.len() < original_len && !droppable_text.is_empty() {
             let dropped_count = original_len - compacted.len();
-            // Truncate to ~4000 chars to avoid storing excessive content
-            let content = if droppable_text.len() > 4000 {
-                let mut boundary = 4000;
-                while boundary > 0 && !droppable_text.is_char_boundary(boundary) {
-                    boundary -= 1;
+            let content = match self.summarize(&droppable_text) {
+                Some(summary) => {
+                    compacted = insert_summary(compacted, keep_first, &summary);
+                    summary
                 }
-                format!("{}... [truncated]", &droppable_text[..boundary])
-            } else {
-                droppable_text
+                // Truncate to ~4000 chars to avoid storing excessive content
+                None if droppable_text.len() > 4000 => {
+                    format!("{}... [truncated]", truncate_chars(&droppable_text, 4000))
+                }
+                None => droppable_text,
             };
 
             let session_id = self.session_id.read().unwrap().clone();
@@ -71,10 +170,104 @@ impl CompactionStrategy for MemoryAwareCompaction {
             }
         }
 
+        if let (Some(ref recorder), true) = (&self.recorder, compacted.len() < original_len) {
+            recorder.compacted(&compacted, original_len - compacted.len());
+        }
         compacted
     }
 }
 
+/// Drop every tool call whose results don't all follow it directly, along
+/// with those results, and every tool result whose call is gone. An
+/// assistant message with tool calls and the results after it are kept or
+/// dropped as one.
+fn pair_tool_calls(messages: Vec<AgentMessage>) -> Vec<AgentMessage> {
+    let mut kept = Vec::with_capacity(messages.len());
+    let mut messages = messages.into_iter().peekable();
+    while let Some(msg) = messages.next() {
+        let calls = tool_call_ids(&msg);
+        if calls.is_empty() {
+            if tool_result_id(&msg).is_some() {
+                tracing::debug!("Compaction: dropping tool result without its call");
+            } else {
+                kept.push(msg);
+            }
+            continue;
+        }
+
+        let mut results = Vec::new();
+        while messages
+            .peek()
+            .is_some_and(|next| tool_result_id(next).is_some())
+        {
+            results.extend(messages.next());
+        }
+        let answered: Vec<&str> = results.iter().filter_map(tool_result_id).collect();
+        let complete = calls.iter().all(|id| answered.contains(&id.as_str()))
+            && answered.iter().all(|id| calls.iter().any(|c| c == id));
+        if complete {
+            kept.push(msg);
+            kept.extend(results);
+        } else {
+            tracing::debug!(
+                "Compaction: dropping {} tool call(s) with {} of their results",
+                calls.len(),
+                answered.len()
+            );
+        }
+    }
+    kept
+}
+
+/// IDs of the tool calls in an assistant message.
+fn tool_call_ids(msg: &AgentMessage) -> Vec<String> {
+    match msg {
+        AgentMessage::Llm(Message::Assistant { content, .. }) => content
+            .iter()
+            .filter_map(|c| match c {
+                Content::ToolCall { id, .. } => Some(id.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn tool_result_id(msg: &AgentMessage) -> Option<&str> {
+    match msg {
+        AgentMessage::Llm(Message::ToolResult { tool_call_id, .. }) => Some(tool_call_id),
+        _ => None,
+    }
+}
+
+/// Insert a `[Summary]` message where the dropped messages used to be, but
+/// never between a tool call and its results.
+fn insert_summary(
+    mut messages: Vec<AgentMessage>,
+    position: usize,
+    summary: &str,
+) -> Vec<AgentMessage> {
+    let mut position = position.min(messages.len());
+    while position < messages.len() && tool_result_id(&messages[position]).is_some() {
+        position += 1;
+    }
+    let text = format!("[Summary] Earlier in this conversation: {}", summary);
+    messages.insert(position, AgentMessage::Llm(Message::user(text.as_str())));
+    messages
+}
+
+/// Cut `text` to at most `max` bytes on a char boundary.
+fn truncate_chars(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut boundary = max;
+    while boundary > 0 && !text.is_char_boundary(boundary) {
+        boundary -= 1;
+    }
+    &text[..boundary]
+}
+
 /// Extract user and assistant text content from messages, skipping tool calls,
 /// tool results, and summary markers.
 fn extract_text_content(messages: &[AgentMessage]) -> String {
@@ -134,9 +327,13 @@ mod tests {
     }
 
     fn make_tool_call_msg(name: &str) -> AgentMessage {
+        make_tool_call_msg_with_id(name, "tc-1")
+    }
+
+    fn make_tool_call_msg_with_id(name: &str, id: &str) -> AgentMessage {
         AgentMessage::Llm(Message::Assistant {
             content: vec![Content::ToolCall {
-                id: "tc-1".to_string(),
+                id: id.to_string(),
                 name: name.to_string(),
                 arguments: serde_json::json!({}),
             }],
@@ -150,8 +347,12 @@ mod tests {
     }
 
     fn make_tool_result_msg(name: &str, text: &str) -> AgentMessage {
+        make_tool_result_msg_with_id(name, "tc-1", text)
+    }
+
+    fn make_tool_result_msg_with_id(name: &str, id: &str, text: &str) -> AgentMessage {
         AgentMessage::Llm(Message::ToolResult {
-            tool_call_id: "tc-1".to_string(),
+            tool_call_id: id.to_string(),
             tool_name: name.to_string(),
             content: vec![Content::Text {
                 text: text.to_string(),
@@ -248,6 +449,65 @@ mod tests {
         assert_eq!(category, "context");
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_compaction_is_audited() {
+        let db = Db::open_memory().unwrap();
+        let session_id = Arc::new(RwLock::new("tg-123".to_string()));
+        let strategy = MemoryAwareCompaction::new(db.clone(), session_id);
+
+        let mut messages = Vec::new();
+        for i in 0..20 {
+            messages.push(make_user_msg(&format!("Question number {}", i)));
+            messages.push(make_assistant_msg(&"x".repeat(200)));
+        }
+        let config = ContextConfig {
+            max_context_tokens: 100,
+            system_prompt_tokens: 10,
+            keep_recent: 2,
+            keep_first: 2,
+            tool_output_max_lines: 50,
+        };
+        strategy.compact(messages, &config);
+
+        let events = db.audit_session_events("tg-123").await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "compaction");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_private_session_compaction_stores_nothing() {
+        let db = Db::open_memory().unwrap();
+        let session_id = Arc::new(RwLock::new("tg-789".to_string()));
+        let strategy = MemoryAwareCompaction::new(db.clone(), session_id)
+            .with_private_flag(Arc::new(AtomicBool::new(true)));
+
+        let mut messages = Vec::new();
+        for i in 0..20 {
+            messages.push(make_user_msg(&format!("Private question {}", i)));
+            messages.push(make_assistant_msg(&"y".repeat(200)));
+        }
+
+        let config = ContextConfig {
+            max_context_tokens: 100,
+            system_prompt_tokens: 10,
+            keep_recent: 2,
+            keep_first: 2,
+            tool_output_max_lines: 50,
+        };
+
+        let original_len = messages.len();
+        let result = strategy.compact(messages, &config);
+        assert!(result.len() < original_len);
+
+        let count = db
+            .exec_sync(|conn| {
+                let c: i64 = conn.query_row("SELECT COUNT(*) FROM memory", [], |r| r.get(0))?;
+                Ok(c)
+            })
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
     #[test]
     fn test_extract_text_content_skips_tool_results() {
         let messages = vec![
@@ -272,6 +532,156 @@ mod tests {
         assert!(!text.contains("[Context compacted"));
     }
 
+    #[test]
+    fn test_insert_summary_after_kept_prefix() {
+        let messages = vec![
+            make_user_msg("first"),
+            make_assistant_msg("first reply"),
+            make_user_msg("recent"),
+        ];
+        let result = insert_summary(messages, 2, "User asked about otters.");
+        assert_eq!(result.len(), 4);
+        let AgentMessage::Llm(Message::User { ref content, .. }) = result[2] else {
+            panic!("expected summary user message");
+        };
+        let Content::Text { ref text } = content[0] else {
+            panic!("expected text");
+        };
+        assert!(text.starts_with("[Summary]"));
+        assert!(text.contains("otters"));
+        // Summaries are not re-summarized on the next compaction
+        assert!(!extract_text_content(&result[2..3]).contains("otters"));
+
+        // Out-of-range positions append
+        let result = insert_summary(vec![make_user_msg("only")], 5, "s");
+        assert_eq!(result.len(), 2);
+    }
+
+    /// What Anthropic checks before answering 400: every tool call is
+    /// answered by the results right after it, and every result answers the
+    /// call right before it.
+    fn provider_errors(messages: &[AgentMessage]) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut pending: Vec<String> = Vec::new();
+        for msg in messages {
+            if let Some(id) = tool_result_id(msg) {
+                if !pending.iter().any(|p| p == id) {
+                    errors.push(format!("unexpected tool_use_id {} in tool_result", id));
+                }
+                pending.retain(|p| p != id);
+                continue;
+            }
+            for id in pending.drain(..) {
+                errors.push(format!("tool_use id {} without tool_result", id));
+            }
+            pending = tool_call_ids(msg);
+        }
+        for id in pending {
+            errors.push(format!("tool_use id {} without tool_result", id));
+        }
+        errors
+    }
+
+    #[test]
+    fn test_pair_tool_calls() {
+        let messages = vec![
+            // Result whose call was compacted away
+            make_tool_result_msg_with_id("http", "tc-0", "<html>"),
+            make_user_msg("What's the weather?"),
+            make_tool_call_msg_with_id("get_weather", "tc-1"),
+            make_tool_result_msg_with_id("get_weather", "tc-1", "Sunny"),
+            make_assistant_msg("Sunny."),
+            // Call whose result was compacted away
+            make_tool_call_msg_with_id("read_file", "tc-2"),
+            make_user_msg("And tomorrow?"),
+        ];
+        assert_eq!(provider_errors(&messages).len(), 2);
+
+        let paired = pair_tool_calls(messages);
+        assert!(provider_errors(&paired).is_empty());
+        assert_eq!(paired.len(), 5);
+        assert_eq!(tool_call_ids(&paired[1]), vec!["tc-1"]);
+        assert_eq!(tool_result_id(&paired[2]), Some("tc-1"));
+    }
+
+    #[test]
+    fn test_pair_tool_calls_drops_partly_answered_calls() {
+        let mut both = make_tool_call_msg_with_id("a", "tc-1");
+        if let AgentMessage::Llm(Message::Assistant {
+            ref mut content, ..
+        }) = both
+        {
+            content.push(Content::ToolCall {
+                id: "tc-2".into(),
+                name: "b".into(),
+                arguments: serde_json::json!({}),
+            });
+        }
+        let messages = vec![
+            make_user_msg("Do a and b"),
+            both,
+            make_tool_result_msg_with_id("b", "tc-2", "done"),
+            make_assistant_msg("Done."),
+        ];
+        let paired = pair_tool_calls(messages);
+        assert!(provider_errors(&paired).is_empty());
+        assert_eq!(paired.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_compaction_keeps_tool_pairs_together() {
+        let db = Db::open_memory().unwrap();
+        let session_id = Arc::new(RwLock::new("tg-321".to_string()));
+        let strategy = MemoryAwareCompaction::new(db, session_id);
+
+        // Every boundary compaction could cut at splits a call from its result
+        let mut messages = vec![make_user_msg("Check these pages")];
+        for i in 0..15 {
+            let id = format!("tc-{}", i);
+            messages.push(make_tool_call_msg_with_id("http", &id));
+            messages.push(make_tool_result_msg_with_id("http", &id, &"z".repeat(300)));
+        }
+        messages.push(make_assistant_msg("All checked."));
+
+        for (keep_first, keep_recent) in [(1, 2), (2, 2), (2, 3), (3, 4)] {
+            let config = ContextConfig {
+                max_context_tokens: 400,
+                system_prompt_tokens: 10,
+                keep_recent,
+                keep_first,
+                tool_output_max_lines: 50,
+            };
+            let result = strategy.compact(messages.clone(), &config);
+            assert!(result.len() < messages.len());
+            assert_eq!(
+                provider_errors(&result),
+                Vec::<String>::new(),
+                "keep_first={} keep_recent={}",
+                keep_first,
+                keep_recent
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_summary_skips_tool_results() {
+        let messages = vec![
+            make_tool_call_msg("get_weather"),
+            make_tool_result_msg("get_weather", "Sunny"),
+            make_user_msg("recent"),
+        ];
+        let result = insert_summary(messages, 1, "s");
+        assert!(provider_errors(&result).is_empty());
+        assert!(extract_text_content(&result[3..]).contains("recent"));
+    }
+
+    #[test]
+    fn test_truncate_chars_respects_boundaries() {
+        assert_eq!(truncate_chars("hello", 10), "hello");
+        assert_eq!(truncate_chars("hello", 3), "hel");
+        assert_eq!(truncate_chars("héllo", 2), "h");
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_large_content_truncated() {
         let db = Db::open_memory().unwrap();
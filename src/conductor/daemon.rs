@@ -0,0 +1,1696 @@
+//! The daemon's message loop.
+//!
+//! `main.rs` starts the channels, the web server and the scheduler, then
+//! hands the coalesced messages to a [`Daemon`]. It reloads the config,
+//! answers the commands that don't need an agent (admin, drafts) and holds
+//! messages of paused sessions; the rest goes as a [`Job`] to the session's
+//! worker in the [`ConductorPool`], where a [`Handler`] answers it with that
+//! worker's conductor: the chat commands answered per session, routing, the
+//! agent's reply and its delivery, and reactions to earlier replies.
+
+use super::pool::ConductorPool;
+use super::Conductor;
+use crate::channels::coalesce::SharedDebounce;
+use crate::channels::delivery::Deliverer;
+use crate::channels::registry::Adapters;
+use crate::channels::{ChannelAdapter, IncomingMessage, OutgoingMessage};
+use crate::config::Config;
+use crate::db::Db;
+use crate::routing::{RouteAction, Router};
+use crate::watcher::{ConfigDiff, ConfigWatcher};
+use crate::web::SseEvent;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// Work for a session's worker, which owns the conductor its tape lives in.
+pub enum Job {
+    /// A queued message past the commands and pause checks of the main loop.
+    Message {
+        incoming: IncomingMessage,
+        queue_id: i64,
+        config: Arc<Config>,
+        router: Arc<Router>,
+    },
+    Reaction {
+        incoming: IncomingMessage,
+        emoji: String,
+        config: Arc<Config>,
+    },
+    /// A note for the session's tape.
+    Note { session_id: String, note: String },
+    Reload {
+        diff: Arc<ConfigDiff>,
+        config: Arc<Config>,
+    },
+}
+
+/// What the session workers share with the main loop: the database and
+/// where replies go.
+#[derive(Clone)]
+pub struct Handler {
+    pub db: Db,
+    pub adapters: Adapters,
+    pub deliverer: Deliverer,
+    pub events: broadcast::Sender<SseEvent>,
+}
+
+impl Handler {
+    /// Do a job on the worker that owns `conductor`.
+    pub async fn run(self, conductor: &mut Conductor, job: Job) {
+        match job {
+            Job::Message {
+                incoming,
+                queue_id,
+                config,
+                router,
+            } => {
+                if let Err(e) = self
+                    .process(conductor, incoming, queue_id, &config, &router)
+                    .await
+                {
+                    tracing::error!("Failed to process message: {}", e);
+                }
+            }
+            Job::Reaction {
+                incoming,
+                emoji,
+                config,
+            } => {
+                handle_reaction(
+                    &incoming,
+                    &emoji,
+                    &config,
+                    &self.db,
+                    conductor,
+                    &self.adapters,
+                    &self.deliverer,
+                )
+                .await
+            }
+            Job::Note { session_id, note } => {
+                if let Err(e) = conductor.record_note(&session_id, &note).await {
+                    tracing::error!("Failed to record note for {}: {}", session_id, e);
+                }
+            }
+            Job::Reload { diff, config } => {
+                crate::watcher::reload_conductor(&diff, &config, conductor);
+            }
+        }
+    }
+
+    /// Answer a queued message: session commands, routing, the agent's
+    /// reply and its delivery.
+    pub async fn process(
+        &self,
+        conductor: &mut Conductor,
+        mut incoming: IncomingMessage,
+        queue_id: i64,
+        config: &Config,
+        router: &Router,
+    ) -> anyhow::Result<()> {
+        let Handler {
+            db,
+            adapters,
+            deliverer,
+            events,
+        } = self;
+        let adapter = adapters.get(&incoming.channel);
+
+        // `/use` is answered here so it reaches the conductor even in pinned sessions
+        let use_reply = match conductor
+            .handle_use_command(&incoming.session_id, &incoming.content)
+            .await
+        {
+            Ok(reply) => reply,
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(reply) = use_reply {
+            if let Some(ref adapter) = adapter {
+                let outgoing = OutgoingMessage {
+                    channel: incoming.channel.clone(),
+                    session_id: incoming.session_id.clone(),
+                    content: reply,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // `/voice on|off` decides whether replies in the chat are spoken
+        if let Some(command) = crate::channels::tts::parse_command(&incoming.content) {
+            let reply = if config.channels.tts.is_none()
+                || !config.channels.voice_replies(&incoming.channel)
+            {
+                "Spoken replies aren't available in this chat.".to_string()
+            } else {
+                use crate::channels::tts::VoiceCommand;
+                match command {
+                    VoiceCommand::On => db.voice_set(&incoming.session_id, true).await?,
+                    VoiceCommand::Off => db.voice_set(&incoming.session_id, false).await?,
+                    VoiceCommand::Show => {}
+                }
+                let on = db.voice_get(&incoming.session_id).await?;
+                crate::channels::tts::command_reply(command, on)
+            };
+            if let Some(ref adapter) = adapter {
+                let outgoing = OutgoingMessage {
+                    channel: incoming.channel.clone(),
+                    session_id: incoming.session_id.clone(),
+                    content: reply,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // `/temp <value>` sets the sampling temperature of the chat
+        if let Some(command) = super::temperature::parse_command(&incoming.content) {
+            use super::temperature::TempCommand;
+            match command {
+                TempCommand::Set(t) => db.temperature_set(&incoming.session_id, Some(t)).await?,
+                TempCommand::Reset => db.temperature_set(&incoming.session_id, None).await?,
+                TempCommand::Show | TempCommand::Invalid => {}
+            }
+            let session = db.temperature_get(&incoming.session_id).await?;
+            let reply =
+                super::temperature::command_reply(command, session, config.agent.temperature);
+            if let Some(ref adapter) = adapter {
+                let outgoing = OutgoingMessage {
+                    channel: incoming.channel.clone(),
+                    session_id: incoming.session_id.clone(),
+                    content: reply,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // `/remember that`, in reply to a message, stores it as a memory
+        if crate::remember::is_command(&incoming.content) {
+            let quote =
+                match (&incoming.quote, &incoming.reply_to) {
+                    (Some(quote), _) => Some(quote.clone()),
+                    // The agent's own replies, when the platform doesn't quote them
+                    (None, Some(id)) => db.reply_get(&incoming.session_id, id).await?.map(|r| {
+                        crate::channels::Quote {
+                            sender: Some("agent".to_string()),
+                            text: r.content,
+                            timestamp: Some(r.created_at),
+                        }
+                    }),
+                    (None, None) => None,
+                };
+            let reply = match quote {
+                None if incoming.reply_to.is_none() => {
+                    "Reply to a message with /remember that to save it.".to_string()
+                }
+                None => "I can't see the message you replied to.".to_string(),
+                Some(quote) => {
+                    match conductor
+                        .remember_quote(&incoming.session_id, &incoming.channel, &quote)
+                        .await?
+                    {
+                        Some(id) => {
+                            let detail = format!("memory #{}", id);
+                            let _ = db
+                                .audit_log(
+                                    Some(&incoming.session_id),
+                                    "remember",
+                                    None,
+                                    Some(&detail),
+                                    0,
+                                )
+                                .await;
+                            crate::remember::stored_reply(&quote.text)
+                        }
+                        None => "Nothing is remembered in private sessions.".to_string(),
+                    }
+                }
+            };
+            if let Some(ref adapter) = adapter {
+                let outgoing = OutgoingMessage {
+                    channel: incoming.channel.clone(),
+                    session_id: incoming.session_id.clone(),
+                    content: reply,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // `/retry [model]` answers the previous message again, in place of its reply
+        if let Some(alias) = super::retry_command(&incoming.content) {
+            let note = if config
+                .review
+                .applies(&incoming.channel, &incoming.session_id)
+            {
+                Some("/retry isn't available in reviewed chats.".to_string())
+            } else {
+                match alias
+                    .map(|a| config.agent.models.get(a).ok_or(a))
+                    .transpose()
+                {
+                    Err(alias) => {
+                        let mut names: Vec<_> = config.agent.models.keys().cloned().collect();
+                        names.sort();
+                        Some(if names.is_empty() {
+                            format!(
+                                "Unknown model '{}'. Model names are set in [agent.models].",
+                                alias
+                            )
+                        } else {
+                            format!("Unknown model '{}'. Try: {}", alias, names.join(", "))
+                        })
+                    }
+                    Ok(model) => match adapter {
+                        Some(ref adapter) => {
+                            regenerate_reply(
+                                &incoming.session_id,
+                                incoming.is_group,
+                                model.map(|m| m.as_str()),
+                                config,
+                                db,
+                                conductor,
+                                adapter,
+                                deliverer,
+                            )
+                            .await
+                        }
+                        None => None,
+                    },
+                }
+            };
+            if let (Some(note), Some(ref adapter)) = (note, &adapter) {
+                let outgoing = OutgoingMessage {
+                    channel: incoming.channel.clone(),
+                    session_id: incoming.session_id.clone(),
+                    content: note,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // `/start <template>` stands in for the template's prompt from here on
+        if let Some(start) = crate::templates::parse(&config.templates, &incoming.content) {
+            let Some(template) = config.templates.get(start.name) else {
+                if let Some(ref adapter) = adapter {
+                    let outgoing = OutgoingMessage {
+                        channel: incoming.channel.clone(),
+                        session_id: incoming.session_id.clone(),
+                        content: crate::templates::unknown(&config.templates, start.name),
+                        reply_to: None,
+                    };
+                    deliverer.send(adapter.as_ref(), outgoing).await;
+                }
+                db.queue_mark_done(queue_id).await?;
+                return Ok(());
+            };
+            let _ = db
+                .audit_log(
+                    Some(&incoming.session_id),
+                    "template",
+                    None,
+                    Some(start.name),
+                    0,
+                )
+                .await;
+            if template.new_session {
+                match conductor.set_aside_session(&incoming.session_id).await {
+                    Ok(Some(archived)) => {
+                        tracing::info!("Set aside {} as {}", incoming.session_id, archived)
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::error!("Failed to set aside {}: {}", incoming.session_id, e),
+                }
+            }
+            incoming.content = crate::templates::render(template, start.input, &incoming);
+        }
+
+        // Group members past their own daily cap are told once and then ignored;
+        // commands still go through, they cost no tokens
+        let sender_cap = config
+            .agent
+            .budget
+            .max_tokens_per_sender_per_day
+            .filter(|_| incoming.is_group && !incoming.content.starts_with('/'));
+        if let Some(max) = sender_cap {
+            let used = db
+                .sender_usage_today(&incoming.session_id, &incoming.sender_id)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("Failed to load sender usage: {}", e);
+                    0
+                });
+            if used >= max {
+                tracing::info!(
+                    "Sender {} in {} is over their daily cap",
+                    incoming.sender_id,
+                    incoming.session_id
+                );
+                if db
+                    .sender_usage_notify(&incoming.session_id, &incoming.sender_id)
+                    .await
+                    .unwrap_or(false)
+                {
+                    let _ = db
+                        .audit_log(
+                            Some(&incoming.session_id),
+                            "sender_budget_exceeded",
+                            None,
+                            Some(&incoming.sender_id),
+                            0,
+                        )
+                        .await;
+                    if let Some(ref adapter) = adapter {
+                        let name = incoming
+                            .sender_name
+                            .as_deref()
+                            .unwrap_or(&incoming.sender_id);
+                        let outgoing = OutgoingMessage {
+                            channel: incoming.channel.clone(),
+                            session_id: incoming.session_id.clone(),
+                            content: crate::security::budget::sender_limit_notice(name, max),
+                            reply_to: None,
+                        };
+                        deliverer.send(adapter.as_ref(), outgoing).await;
+                    }
+                }
+                db.queue_mark_done(queue_id).await?;
+                return Ok(());
+            }
+        }
+
+        // Sessions and senders past their daily allowance are told once a day
+        // and then ignored; commands still go through
+        let sender_key = format!("{}:{}", incoming.channel, incoming.sender_id);
+        let message_budget = crate::security::budget::MessageBudget::load(
+            db,
+            &config.agent.budget,
+            &incoming.session_id,
+            &sender_key,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load budget usage: {}", e);
+            crate::security::budget::MessageBudget::unlimited(&sender_key)
+        });
+        if let Some(scope) = message_budget
+            .exhausted
+            .filter(|_| !incoming.content.starts_with('/'))
+        {
+            tracing::info!(
+                "{} in {} is over the daily {} budget",
+                sender_key,
+                incoming.session_id,
+                scope.as_str()
+            );
+            let detail = match scope {
+                crate::security::budget::Scope::Session => "session".to_string(),
+                crate::security::budget::Scope::Sender => format!("sender {}", sender_key),
+            };
+            let notified = db
+                .audit_logged_today(&incoming.session_id, "budget_exceeded", &detail)
+                .await
+                .unwrap_or(true);
+            if !notified {
+                let _ = db
+                    .audit_log(
+                        Some(&incoming.session_id),
+                        "budget_exceeded",
+                        None,
+                        Some(&detail),
+                        0,
+                    )
+                    .await;
+                if let Some(ref adapter) = adapter {
+                    let outgoing = OutgoingMessage {
+                        channel: incoming.channel.clone(),
+                        session_id: incoming.session_id.clone(),
+                        content: crate::security::budget::scope_limit_notice(scope).to_string(),
+                        reply_to: None,
+                    };
+                    deliverer.send(adapter.as_ref(), outgoing).await;
+                }
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // Routing rules take precedence over /use pins and adapter-provided worker hints
+        let pinned = match conductor.pinned_worker(&incoming.session_id).await {
+            Ok(pinned) => pinned,
+            Err(e) => {
+                tracing::error!("Failed to load worker pin: {}", e);
+                None
+            }
+        };
+        let route = match router.evaluate(&incoming) {
+            Some(m) => {
+                tracing::info!("Routing rule '{}' matched: {}", m.rule, m.action);
+                let detail = format!("{}: {}", m.rule, m.action);
+                let _ = db
+                    .audit_log(Some(&incoming.session_id), "route", None, Some(&detail), 0)
+                    .await;
+                Some(m.action.clone())
+            }
+            None => pinned
+                .or_else(|| incoming.worker_hint.clone())
+                .map(RouteAction::Worker),
+        };
+
+        // Auto-replies are answered here without involving any agent
+        if let Some(RouteAction::Reply(ref template)) = route {
+            let outgoing = OutgoingMessage {
+                channel: incoming.channel.clone(),
+                session_id: incoming.session_id.clone(),
+                content: crate::routing::render_reply(template, &incoming),
+                reply_to: None,
+            };
+            if let Some(ref adapter) = adapter {
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        let model_override = match route {
+            Some(RouteAction::Model(ref model)) => Some(model.as_str()),
+            _ => None,
+        };
+        conductor.set_model_override(model_override);
+        conductor.set_temperature(db.temperature_get(&incoming.session_id).await?);
+        let delegated = matches!(
+            route,
+            Some(RouteAction::Worker(_)) | Some(RouteAction::Pipeline(_))
+        );
+
+        // Replies in reviewed chats go to the reviewer as drafts, so nothing
+        // may reach the chat before the final response
+        let reviewed = config
+            .review
+            .applies(&incoming.channel, &incoming.session_id);
+
+        // Features the channel lacks are skipped rather than attempted
+        let caps = adapter
+            .as_ref()
+            .map(|a| a.capabilities())
+            .unwrap_or_default();
+        // Approval prompts go to the chat, with Approve/Deny buttons where
+        // the channel has them; only this sender or an admin decides
+        let buttons: Option<crate::security::approval::ButtonsFn> = match &adapter {
+            Some(adapter) if caps.buttons && !reviewed => {
+                let adapter = adapter.clone();
+                let session_id = incoming.session_id.clone();
+                Some(Arc::new(move |text: String, labels: Vec<String>| {
+                    let adapter = adapter.clone();
+                    let session_id = session_id.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = adapter
+                            .offer_buttons(&session_id, None, &text, &labels)
+                            .await
+                        {
+                            tracing::warn!("Failed to send approval prompt: {}", e);
+                        }
+                    });
+                }))
+            }
+            _ => None,
+        };
+        conductor.approvals().note_requester(
+            &incoming.session_id,
+            crate::security::approval::Requester {
+                sender: format!("{}:{}", incoming.channel, incoming.sender_id),
+                admins: config.security.admins.clone(),
+                reactions: caps.reactions,
+                buttons,
+            },
+        );
+
+        // Start typing indicator
+        let typing_handle = adapter
+            .as_ref()
+            .filter(|_| caps.typing)
+            .and_then(|a| a.start_typing(&incoming.session_id));
+
+        // Send a streaming placeholder message (skip for worker delegations — no
+        // streaming — and channels that can't edit it)
+        let placeholder = if !delegated && !reviewed && caps.edit {
+            if let Some(ref adapter) = adapter {
+                adapter.send_placeholder(&incoming.session_id, "...").await
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        // Build debounced on_chunk callback for streaming edits
+        let on_chunk: Option<super::OnStreamChunk> = {
+            if let (Some(ref ph), Some(ref adapter)) = (&placeholder, &adapter) {
+                let ph = ph.clone();
+                let adapter = adapter.clone();
+                let debounce =
+                    Duration::from_millis(config.channels.stream_debounce_ms(&incoming.channel));
+                let last_edit =
+                    Arc::new(std::sync::Mutex::new(std::time::Instant::now() - debounce));
+                // Also emit SSE events for web UI streaming
+                let sse_tx = events.clone();
+                let sse_session = incoming.session_id.clone();
+                let sse_channel = incoming.channel.clone();
+
+                Some(Box::new(move |accumulated: &str| {
+                    let mut last = last_edit.lock().unwrap();
+                    if last.elapsed() >= debounce {
+                        *last = std::time::Instant::now();
+                        let ph = ph.clone();
+                        let adapter = adapter.clone();
+                        let text = accumulated.to_string();
+                        tokio::spawn(async move {
+                            let _ = adapter.edit_message(&ph, &text).await;
+                        });
+                    }
+                    // Emit SSE stream chunk
+                    let _ = sse_tx.send(SseEvent::StreamChunk {
+                        session_id: sse_session.clone(),
+                        channel: sse_channel.clone(),
+                        text: accumulated.to_string(),
+                    });
+                }) as super::OnStreamChunk)
+            } else {
+                None
+            }
+        };
+
+        // Build progress callback to route send_message tool output to the channel
+        let on_progress: Option<Box<dyn Fn(String) + Send + Sync>> = {
+            if let (Some(ref adapter), false) = (&adapter, reviewed) {
+                let adapter = adapter.clone();
+                let channel = incoming.channel.clone();
+                let session_id = incoming.session_id.clone();
+                let deliverer = deliverer.clone();
+                Some(Box::new(move |text: String| {
+                    let outgoing = OutgoingMessage {
+                        channel: channel.clone(),
+                        session_id: session_id.clone(),
+                        content: text,
+                        reply_to: None,
+                    };
+                    let adapter = adapter.clone();
+                    let deliverer = deliverer.clone();
+                    tokio::spawn(async move {
+                        deliverer.send(adapter.as_ref(), outgoing).await;
+                    });
+                }))
+            } else {
+                None
+            }
+        };
+
+        // Images go to the model, other files are listed for it to read.
+        // Workers get text only
+        let attachments = db.queue_attachments(queue_id).await.unwrap_or_else(|e| {
+            tracing::error!("Failed to load attachments: {}", e);
+            Vec::new()
+        });
+        // Voice messages are read to the agent as text
+        let spoken = attachments.iter().any(|a| a.voice);
+        let (transcripts, attachments) =
+            crate::transcription::transcribe_all(config, attachments).await;
+        if !transcripts.is_empty() {
+            incoming.content =
+                crate::transcription::with_transcripts(&incoming.content, &transcripts);
+        }
+        if !attachments.is_empty() {
+            let vision = config.attachments.vision && !delegated;
+            let (note, images) = crate::attachments::prompt_parts(&attachments, vision);
+            if let Some(note) = note {
+                incoming.content = if incoming.content.is_empty() {
+                    note
+                } else {
+                    format!("{}\n\n{}", incoming.content, note)
+                };
+            }
+            conductor.attach_images(images);
+        }
+
+        // Slow replies say what the agent is doing, then move to a background task
+        let watchdog = config
+            .channels
+            .latency(&incoming.channel)
+            .filter(|_| !delegated && !reviewed)
+            .zip(adapter.clone())
+            .map(|(latency, adapter)| {
+                let notice = Duration::from_secs(latency.notice_secs);
+                let max = latency.max_secs.map(Duration::from_secs);
+                let watch = conductor.turn_watch();
+                let placeholder = placeholder.clone();
+                let deliverer = deliverer.clone();
+                let channel = incoming.channel.clone();
+                let session_id = incoming.session_id.clone();
+                tokio::spawn(async move {
+                    let started = std::time::Instant::now();
+                    if max.map_or(true, |max| notice < max) {
+                        tokio::time::sleep(notice).await;
+                        let status = super::latency::status_message(
+                            started.elapsed(),
+                            &watch.running_tools(),
+                        );
+                        match placeholder {
+                            Some(ref ph) => {
+                                let _ = adapter.edit_message(ph, &status).await;
+                            }
+                            None => {
+                                let outgoing = OutgoingMessage {
+                                    channel,
+                                    session_id: session_id.clone(),
+                                    content: status,
+                                    reply_to: None,
+                                };
+                                deliverer.send(adapter.as_ref(), outgoing).await;
+                            }
+                        }
+                    }
+                    if let Some(max) = max {
+                        tokio::time::sleep(max.saturating_sub(started.elapsed())).await;
+                        tracing::info!("Reply in {} is over its latency cap", session_id);
+                        watch.hand_off();
+                    }
+                })
+            });
+
+        // The reply is stored with the tape, so a crash before it is sent
+        // doesn't run the message again. Drafts are left to the reviewer
+        if !delegated && !reviewed {
+            conductor.set_queue_entry(queue_id);
+        }
+        conductor.budget().start_message(message_budget);
+        let result = match route {
+            Some(RouteAction::Worker(ref worker_name)) => {
+                conductor
+                    .delegate_to_worker(&incoming.session_id, worker_name, &incoming.content)
+                    .await
+            }
+            Some(RouteAction::Pipeline(ref workers)) => {
+                conductor
+                    .delegate_to_pipeline(&incoming.session_id, workers, &incoming.content)
+                    .await
+            }
+            _ if incoming.is_group => {
+                conductor
+                    .process_group_message(
+                        &incoming.session_id,
+                        &incoming.content,
+                        on_chunk,
+                        on_progress,
+                    )
+                    .await
+            }
+            _ => {
+                conductor
+                    .process_message(
+                        &incoming.session_id,
+                        &incoming.content,
+                        on_chunk,
+                        on_progress,
+                    )
+                    .await
+            }
+        };
+        conductor.budget().end_message();
+
+        // Stop typing indicator
+        if let Some(handle) = typing_handle {
+            handle.abort();
+        }
+        if let Some(handle) = watchdog {
+            handle.abort();
+        }
+
+        // Charge the reply to the group member who asked for it
+        if sender_cap.is_some() && !delegated {
+            let tokens = conductor.message_tokens();
+            if tokens > 0 {
+                if let Err(e) = db
+                    .sender_usage_add(&incoming.session_id, &incoming.sender_id, tokens)
+                    .await
+                {
+                    tracing::error!("Failed to record sender usage: {}", e);
+                }
+            }
+        }
+
+        match result {
+            Ok(response) => {
+                tracing::info!("Response: {}", truncate(&response, 80));
+                let mut reply_handle = None;
+
+                // Link the web sources the reply drew on, where the channel wants them
+                let sources = conductor.take_sources();
+                let response = if config.channels.citations(&incoming.channel) {
+                    super::citations::append(response, &sources)
+                } else {
+                    response
+                };
+
+                if reviewed {
+                    let draft = db
+                        .draft_create(
+                            &incoming.channel,
+                            &incoming.session_id,
+                            &incoming.content,
+                            &response,
+                        )
+                        .await?;
+                    tracing::info!("Reply held for review as draft #{}", draft);
+                    if let (Some(reviewer), Some(draft)) =
+                        (&config.review.reviewer, db.draft_get(draft).await?)
+                    {
+                        let channel = crate::scheduler::cron::resolve_channel(db, reviewer).await;
+                        match adapters.get(&channel) {
+                            Some(adapter) => {
+                                let outgoing = OutgoingMessage {
+                                    channel,
+                                    session_id: reviewer.clone(),
+                                    content: crate::drafts::format_for_reviewer(&draft),
+                                    reply_to: None,
+                                };
+                                deliverer.send(adapter.as_ref(), outgoing).await;
+                            }
+                            None => {
+                                tracing::warn!("No adapter '{}' for reviewer {}", channel, reviewer)
+                            }
+                        }
+                    }
+                } else if let Some(ref ph) = placeholder {
+                    // Final edit to ensure complete text if we had a placeholder
+                    if let Some(ref adapter) = adapter {
+                        let status = deliverer.edit(adapter.as_ref(), ph, &response).await;
+                        if status == crate::db::deliveries::DeliveryStatus::Edited {
+                            reply_handle = Some(ph);
+                        }
+                        // Remember the reply so reactions to it can be acted on
+                        if status == crate::db::deliveries::DeliveryStatus::Edited && caps.reactions
+                        {
+                            if let Err(e) = db
+                                .reply_record(
+                                    &ph.channel,
+                                    &ph.message_id,
+                                    &incoming.session_id,
+                                    &response,
+                                )
+                                .await
+                            {
+                                tracing::warn!("Failed to record reply: {}", e);
+                            }
+                        }
+                    }
+                } else {
+                    // No placeholder — send the full response as a new message
+                    let outgoing = OutgoingMessage {
+                        channel: incoming.channel.clone(),
+                        session_id: incoming.session_id.clone(),
+                        content: response.clone(),
+                        reply_to: None,
+                    };
+
+                    if let Some(ref adapter) = adapter {
+                        deliverer.send(adapter.as_ref(), outgoing).await;
+                    }
+                }
+
+                db.queue_mark_done(queue_id).await?;
+
+                // Emit SSE events for web UI
+                let _ = events.send(SseEvent::StreamEnd {
+                    session_id: incoming.session_id.clone(),
+                    channel: incoming.channel.clone(),
+                });
+                let _ = events.send(SseEvent::MessageProcessed {
+                    session_id: incoming.session_id.clone(),
+                    channel: incoming.channel.clone(),
+                });
+
+                // Spoken too, in answer to voice messages and in chats that asked
+                if let (Some(ref adapter), Some(ref tts)) = (&adapter, &config.channels.tts) {
+                    if !reviewed && caps.voice && config.channels.voice_replies(&incoming.channel) {
+                        send_voice_reply(
+                            db,
+                            adapter.as_ref(),
+                            &incoming.session_id,
+                            &response,
+                            spoken,
+                            tts,
+                            config,
+                        )
+                        .await;
+                    }
+                }
+
+                // Follow-up buttons, in direct messages on channels that want them
+                if let Some(ref adapter) = adapter {
+                    if !reviewed
+                        && !delegated
+                        && !incoming.is_group
+                        && caps.buttons
+                        && config.channels.suggestions(&incoming.channel)
+                    {
+                        offer_suggestions(
+                            conductor,
+                            adapter.as_ref(),
+                            &incoming,
+                            &response,
+                            reply_handle,
+                            config,
+                        )
+                        .await;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("Processing error: {}", e);
+                // Clean up streaming placeholder on error
+                if let Some(ref ph) = placeholder {
+                    if let Some(ref adapter) = adapter {
+                        deliverer
+                            .edit(
+                                adapter.as_ref(),
+                                ph,
+                                "An error occurred processing your message.",
+                            )
+                            .await;
+                    }
+                }
+                let failure = conductor.failure_context(&e);
+                db.queue_mark_failed(queue_id, &failure).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ask for follow-ups to `response`, offer them as buttons and note them on
+/// the tape. Nothing is shown if any step fails.
+async fn offer_suggestions(
+    conductor: &mut Conductor,
+    adapter: &dyn ChannelAdapter,
+    incoming: &IncomingMessage,
+    response: &str,
+    reply: Option<&crate::channels::SentMessage>,
+    config: &Config,
+) {
+    let suggestions = crate::suggestions::generate(
+        &crate::suggestions::agent_config(config),
+        &incoming.content,
+        response,
+        config.suggestions.count,
+    )
+    .await;
+    if suggestions.is_empty() {
+        return;
+    }
+    if let Err(e) = adapter
+        .offer_buttons(
+            &incoming.session_id,
+            reply,
+            crate::suggestions::HEADER,
+            &suggestions,
+        )
+        .await
+    {
+        tracing::warn!("Failed to offer follow-up suggestions: {}", e);
+        return;
+    }
+    let note = crate::suggestions::tape_note(&suggestions);
+    if let Err(e) = conductor.record_note(&incoming.session_id, &note).await {
+        tracing::warn!("Failed to record follow-up suggestions: {}", e);
+    }
+}
+
+/// Send `response` as audio if the user spoke or the session asked for it.
+/// Failures are logged; the text reply has gone out already.
+async fn send_voice_reply(
+    db: &Db,
+    adapter: &dyn ChannelAdapter,
+    session_id: &str,
+    response: &str,
+    spoken: bool,
+    tts: &crate::config::TtsConfig,
+    config: &Config,
+) {
+    let wanted = spoken
+        || db.voice_get(session_id).await.unwrap_or_else(|e| {
+            tracing::error!("Failed to read voice setting: {}", e);
+            false
+        });
+    if !wanted {
+        return;
+    }
+    let Some(text) = crate::channels::tts::speakable(response, tts.max_chars) else {
+        return;
+    };
+    let audio = match crate::channels::tts::synthesize(tts, &config.agent, &text).await {
+        Ok(audio) => audio,
+        Err(e) => {
+            tracing::warn!("Failed to speak reply in {}: {}", session_id, e);
+            return;
+        }
+    };
+    if let Err(e) = adapter.send_voice(session_id, audio, &tts.format).await {
+        tracing::warn!("Failed to send spoken reply in {}: {}", session_id, e);
+    }
+}
+
+/// The main loop, with the state it keeps between messages.
+pub struct Daemon {
+    handler: Handler,
+    pool: ConductorPool<Job>,
+    config: Arc<Config>,
+    router: Arc<Router>,
+    debounce: SharedDebounce,
+    watcher: ConfigWatcher,
+    /// Where restarted adapters send their messages
+    raw_tx: mpsc::UnboundedSender<IncomingMessage>,
+    /// Messages that arrived while their session was paused...
+    held: VecDeque<(i64, IncomingMessage)>,
+    /// ...and those released by a resume
+    ready: VecDeque<(i64, IncomingMessage)>,
+}
+
+impl Daemon {
+    /// Start a worker for each of `conductors`; sessions are answered there,
+    /// while the loop takes care of everything that doesn't need a conductor.
+    pub fn new(
+        handler: Handler,
+        conductors: Vec<Conductor>,
+        config: Config,
+        router: Router,
+        debounce: SharedDebounce,
+        watcher: ConfigWatcher,
+        raw_tx: mpsc::UnboundedSender<IncomingMessage>,
+    ) -> Self {
+        let worker = handler.clone();
+        let pool = ConductorPool::<Job>::start(conductors, move |conductor, job| {
+            Box::pin(worker.clone().run(conductor, job))
+        });
+        if pool.len() > 1 {
+            tracing::info!("Processing up to {} sessions in parallel", pool.len());
+        }
+        Self {
+            handler,
+            pool,
+            config: Arc::new(config),
+            router: Arc::new(router),
+            debounce,
+            watcher,
+            raw_tx,
+            held: VecDeque::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Handle messages from `rx` until it closes, polling the config file
+    /// every 5 seconds.
+    pub async fn run(
+        mut self,
+        mut rx: mpsc::UnboundedReceiver<IncomingMessage>,
+    ) -> anyhow::Result<()> {
+        let mut reload_interval = tokio::time::interval(Duration::from_secs(5));
+        reload_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        tracing::info!("yoclaw running. Waiting for messages...");
+
+        loop {
+            tokio::select! {
+                // Config hot-reload poll
+                _ = reload_interval.tick() => self.tick().await,
+                // Incoming message, or one released from a pause
+                msg = next_message(&mut self.ready, &mut rx) => {
+                    let Some((incoming, held_id)) = msg else {
+                        break; // channel closed
+                    };
+                    self.dispatch(incoming, held_id).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reload the config if it changed, release messages whose pause has
+    /// been lifted and post decided drafts.
+    async fn tick(&mut self) {
+        if let Some(mut new_config) = self.watcher.check() {
+            match self.handler.db.override_list().await {
+                Ok(overrides) => crate::admin::apply_overrides(&mut new_config, &overrides),
+                Err(e) => tracing::error!("Failed to load admin overrides: {}", e),
+            }
+            self.reload(new_config).await;
+        } else {
+            // Overrides stored from the web API
+            match self.handler.db.override_list().await {
+                Ok(overrides) => {
+                    let mut new_config = (*self.config).clone();
+                    crate::admin::apply_overrides(&mut new_config, &overrides);
+                    if new_config != *self.config {
+                        self.reload(new_config).await;
+                    }
+                }
+                Err(e) => tracing::error!("Failed to load admin overrides: {}", e),
+            }
+        }
+        // Release messages whose pause has been lifted
+        let mut still_held = VecDeque::new();
+        for (id, m) in self.held.drain(..) {
+            match self.handler.db.pause_check(Some(&m.session_id)).await {
+                Ok(None) => self.ready.push_back((id, m)),
+                Ok(Some(_)) => still_held.push_back((id, m)),
+                Err(e) => {
+                    tracing::error!("Failed to read pause state: {}", e);
+                    still_held.push_back((id, m));
+                }
+            }
+        }
+        self.held = still_held;
+        if !self.ready.is_empty() {
+            tracing::info!("Resumed, processing {} queued message(s)", self.ready.len());
+        }
+        // Failed messages queued again, as their turn comes
+        release_retries(&self.handler.db, &mut self.ready).await;
+        // Drafts approved, edited or rejected from the dashboard
+        let Handler {
+            db,
+            adapters,
+            deliverer,
+            ..
+        } = &self.handler;
+        apply_draft_decisions(db, &self.pool, adapters, deliverer).await;
+    }
+
+    /// Apply a changed config: here what the main loop owns, on each worker
+    /// what its conductor does.
+    async fn reload(&mut self, new_config: Config) {
+        let diff = Arc::new(crate::watcher::diff_configs(&self.config, &new_config));
+        crate::watcher::apply_hot_reload(&diff, &new_config, &mut self.router, &self.debounce);
+        crate::channels::registry::reload(
+            &self.handler.adapters,
+            &diff.channels_changed,
+            &new_config,
+            &self.raw_tx,
+        )
+        .await;
+        let new_config = Arc::new(new_config);
+        self.pool.broadcast(|| Job::Reload {
+            diff: diff.clone(),
+            config: new_config.clone(),
+        });
+        self.config = new_config;
+    }
+
+    /// Queue an incoming message and answer what the main loop answers
+    /// itself; hand the rest to the handler unless the session is paused.
+    async fn dispatch(
+        &mut self,
+        incoming: IncomingMessage,
+        held_id: Option<i64>,
+    ) -> anyhow::Result<()> {
+        let Handler {
+            db,
+            adapters,
+            deliverer,
+            ..
+        } = self.handler.clone();
+
+        if let crate::channels::MessageKind::Reaction { ref emoji } = incoming.kind {
+            let (session_id, emoji) = (incoming.session_id.clone(), emoji.clone());
+            self.pool.send(
+                &session_id,
+                Job::Reaction {
+                    incoming,
+                    emoji,
+                    config: self.config.clone(),
+                },
+            );
+            return Ok(());
+        }
+
+        // Edits and deletions of processed messages only leave a note on the tape
+        if let Some(note) = incoming.followup_note() {
+            tracing::info!(
+                "[{}] {}: {}",
+                incoming.channel,
+                incoming.session_id,
+                truncate(&note, 80)
+            );
+            self.pool.send(
+                &incoming.session_id,
+                Job::Note {
+                    session_id: incoming.session_id.clone(),
+                    note,
+                },
+            );
+            return Ok(());
+        }
+
+        // Coalesced before their channel was removed or failed to restart
+        if held_id.is_none() && adapters.get(&incoming.channel).is_none() {
+            tracing::warn!(
+                "[{}] Channel stopped, dropping message from {}",
+                incoming.channel,
+                incoming.session_id
+            );
+            return Ok(());
+        }
+
+        let mut queue_entry = crate::db::queue::QueueEntry::new(
+            &incoming.channel,
+            &incoming.sender_id,
+            &incoming.session_id,
+            &incoming.content,
+        );
+        let queue_id = match held_id {
+            Some(id) => id,
+            None => {
+                // Files are saved before queueing, so a retry still has them
+                queue_entry.attachments = crate::attachments::save(
+                    &self.config.attachments_dir(),
+                    &incoming.session_id,
+                    &incoming.attachments,
+                )
+                .unwrap_or_else(|e| {
+                    tracing::error!(
+                        "Failed to save attachments for {}: {}",
+                        incoming.session_id,
+                        e
+                    );
+                    Vec::new()
+                });
+                let id = db.queue_push(&queue_entry).await?;
+                // Remember where the session lives, for cron and other deliveries
+                if let Err(e) = db
+                    .session_touch(
+                        &incoming.channel,
+                        &incoming.session_id,
+                        incoming.chat_id.as_deref(),
+                        incoming.thread_id.as_deref(),
+                        incoming.is_group,
+                    )
+                    .await
+                {
+                    tracing::warn!("Failed to record session {}: {}", incoming.session_id, e);
+                }
+                id
+            }
+        };
+
+        tracing::info!(
+            "[{}] {} ({}): {}",
+            incoming.channel,
+            incoming.sender_name.as_deref().unwrap_or("unknown"),
+            incoming.session_id,
+            truncate(&incoming.content, 80)
+        );
+
+        // Find the adapter for this channel
+        let adapter = adapters.get(&incoming.channel);
+
+        // Greet senders on their first direct message, before the reply
+        if let (Some(greeting), Some(ref adapter), false) = (
+            self.config.channels.greeting(&incoming.channel),
+            &adapter,
+            incoming.is_group,
+        ) {
+            match db
+                .greeting_first_contact(
+                    &incoming.channel,
+                    &incoming.sender_id,
+                    &incoming.session_id,
+                )
+                .await
+            {
+                Ok(true) => {
+                    let name = incoming.sender_name.as_deref().unwrap_or("there");
+                    let outgoing = OutgoingMessage {
+                        channel: incoming.channel.clone(),
+                        session_id: incoming.session_id.clone(),
+                        content: greeting.replace("{name}", name),
+                        reply_to: None,
+                    };
+                    deliverer.send(adapter.as_ref(), outgoing).await;
+                }
+                Ok(false) => {}
+                Err(e) => tracing::error!("Failed to check first contact: {}", e),
+            }
+        }
+
+        // Admin commands are answered here and never reach the agent
+        if let Some(parsed) = crate::admin::parse(&incoming.content) {
+            let actor = format!("{}:{}", incoming.channel, incoming.sender_id);
+            let reply =
+                if !crate::admin::is_admin(&self.config, &incoming.channel, &incoming.sender_id) {
+                    tracing::warn!("Rejected admin command from {}", actor);
+                    let _ = db
+                        .audit_log(
+                            Some(&incoming.session_id),
+                            "admin_denied",
+                            None,
+                            Some(&actor),
+                            0,
+                        )
+                        .await;
+                    "Admin commands are restricted.".to_string()
+                } else {
+                    match parsed {
+                        Ok(cmd) => {
+                            let result = crate::admin::execute(&cmd, &db, &self.config).await;
+                            let detail = match result {
+                                Ok(_) => format!("{} {}", actor, cmd),
+                                Err(ref e) => format!("{} {} (failed: {})", actor, cmd, e),
+                            };
+                            tracing::info!("Admin command: {}", detail);
+                            let _ = db
+                                .audit_log(
+                                    Some(&incoming.session_id),
+                                    "admin",
+                                    None,
+                                    Some(&detail),
+                                    0,
+                                )
+                                .await;
+                            match result {
+                                Ok(outcome) => {
+                                    if let Some(new_config) = outcome.config {
+                                        self.reload(new_config).await;
+                                    }
+                                    outcome.reply
+                                }
+                                Err(e) => e.to_string(),
+                            }
+                        }
+                        Err(e) => e.to_string(),
+                    }
+                };
+            if let Some(ref adapter) = adapter {
+                let outgoing = OutgoingMessage {
+                    channel: incoming.channel.clone(),
+                    session_id: incoming.session_id.clone(),
+                    content: reply,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // Draft review commands, restricted like admin commands
+        if let Some(parsed) = crate::drafts::parse(&incoming.content) {
+            let actor = format!("{}:{}", incoming.channel, incoming.sender_id);
+            let reply =
+                if !crate::admin::is_admin(&self.config, &incoming.channel, &incoming.sender_id) {
+                    tracing::warn!("Rejected draft command from {}", actor);
+                    "Draft review is restricted to admins.".to_string()
+                } else {
+                    match parsed {
+                        Ok(cmd) => match crate::drafts::execute(&cmd, &db, &actor).await {
+                            Ok(reply) => reply,
+                            Err(e) => e.to_string(),
+                        },
+                        Err(e) => e.to_string(),
+                    }
+                };
+            if let Some(ref adapter) = adapter {
+                let outgoing = OutgoingMessage {
+                    channel: incoming.channel.clone(),
+                    session_id: incoming.session_id.clone(),
+                    content: reply,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            apply_draft_decisions(&db, &self.pool, &adapters, &deliverer).await;
+            db.queue_mark_done(queue_id).await?;
+            return Ok(());
+        }
+
+        // In read-only mode messages wait in the queue. Later messages of a
+        // session wait behind its held ones
+        let paused = match db.pause_check(Some(&incoming.session_id)).await {
+            Ok(pause) => pause,
+            Err(e) => {
+                tracing::error!("Failed to read pause state: {}", e);
+                None
+            }
+        };
+        if paused.is_some()
+            || self
+                .held
+                .iter()
+                .any(|(_, m)| m.session_id == incoming.session_id)
+        {
+            tracing::info!(
+                "[{}] {} is paused, message queued",
+                incoming.channel,
+                incoming.session_id
+            );
+            self.held.push_back((queue_id, incoming));
+            return Ok(());
+        }
+
+        // The rest happens on the session's worker, in parallel with other sessions
+        let session_id = incoming.session_id.clone();
+        self.pool.send(
+            &session_id,
+            Job::Message {
+                incoming,
+                queue_id,
+                config: self.config.clone(),
+                router: self.router.clone(),
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Next message to process: one released from a pause, else the next
+/// incoming one, with its queue ID if it is already queued.
+async fn next_message(
+    ready: &mut VecDeque<(i64, IncomingMessage)>,
+    rx: &mut mpsc::UnboundedReceiver<IncomingMessage>,
+) -> Option<(IncomingMessage, Option<i64>)> {
+    if let Some((id, msg)) = ready.pop_front() {
+        return Some((msg, Some(id)));
+    }
+    rx.recv().await.map(|msg| (msg, None))
+}
+
+/// Queue the failed messages whose retry is due (`yoclaw queue
+/// retry-failed`) for dispatch.
+async fn release_retries(db: &Db, ready: &mut VecDeque<(i64, IncomingMessage)>) {
+    let entries = match db.queue_take_retries().await {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!("Failed to load retries: {}", e);
+            return;
+        }
+    };
+    for entry in entries {
+        let Some(id) = entry.id else { continue };
+        let session = db.session_get(&entry.session_id).await.ok().flatten();
+        tracing::info!("Retrying failed message {} in {}", id, entry.session_id);
+        ready.push_back((id, crate::retry::to_incoming(&entry, session.as_ref())));
+    }
+}
+
+/// Send the replies of messages answered before a crash but not marked done.
+/// A reply may reach the chat twice, but the message isn't run again.
+pub async fn deliver_answered(db: &Db, adapters: &Adapters, deliverer: &Deliverer) {
+    let entries = match db.queue_answered().await {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!("Failed to load answered messages: {}", e);
+            return;
+        }
+    };
+    for entry in entries {
+        tracing::info!(
+            "Delivering reply to message {} in {} from before a crash",
+            entry.id,
+            entry.session_id
+        );
+        match adapters.get(&entry.channel) {
+            Some(adapter) => {
+                let outgoing = OutgoingMessage {
+                    channel: entry.channel,
+                    session_id: entry.session_id,
+                    content: entry.reply,
+                    reply_to: None,
+                };
+                deliverer.send(adapter.as_ref(), outgoing).await;
+            }
+            None => tracing::warn!("No adapter '{}' for message {}", entry.channel, entry.id),
+        }
+        if let Err(e) = db.queue_mark_done(entry.id).await {
+            tracing::error!("Failed to mark message {} done: {}", entry.id, e);
+        }
+    }
+}
+
+/// Post reviewed drafts that have been decided in chat or the dashboard,
+/// and note edits and rejections on their session's tape.
+async fn apply_draft_decisions(
+    db: &Db,
+    pool: &ConductorPool<Job>,
+    adapters: &crate::channels::registry::Adapters,
+    deliverer: &Deliverer,
+) {
+    let drafts = match db.draft_unapplied().await {
+        Ok(drafts) => drafts,
+        Err(e) => {
+            tracing::error!("Failed to load draft decisions: {}", e);
+            return;
+        }
+    };
+    for draft in drafts {
+        tracing::info!(
+            "Draft #{} for {} {}",
+            draft.id,
+            draft.session_id,
+            draft.status
+        );
+        if let Some(outgoing) = crate::drafts::outgoing(&draft) {
+            match adapters.get(&draft.channel) {
+                Some(adapter) => {
+                    deliverer.send(adapter.as_ref(), outgoing).await;
+                }
+                None => tracing::warn!("No adapter '{}' for draft #{}", draft.channel, draft.id),
+            }
+        }
+        if let Some(note) = crate::drafts::tape_note(&draft) {
+            pool.send(
+                &draft.session_id,
+                Job::Note {
+                    session_id: draft.session_id.clone(),
+                    note,
+                },
+            );
+        }
+        if let Err(e) = db.draft_mark_applied(draft.id).await {
+            tracing::error!("Failed to mark draft #{} applied: {}", draft.id, e);
+        }
+    }
+}
+
+/// Act on a reaction to one of the agent's replies: regenerate it, pin it to
+/// memory or delete it. Reactions to other messages are ignored.
+async fn handle_reaction(
+    incoming: &IncomingMessage,
+    emoji: &str,
+    config: &Config,
+    db: &Db,
+    conductor: &mut Conductor,
+    adapters: &crate::channels::registry::Adapters,
+    deliverer: &Deliverer,
+) {
+    use crate::reactions::ReactionCommand;
+
+    let Some(command) = crate::reactions::parse(&config.reactions, emoji) else {
+        return;
+    };
+    let Some(ref message_id) = incoming.message_id else {
+        return;
+    };
+    let reply = match db.reply_get(&incoming.session_id, message_id).await {
+        Ok(Some(reply)) => reply,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to load reply: {}", e);
+            return;
+        }
+    };
+    let Some(adapter) = adapters.get(&reply.channel) else {
+        return;
+    };
+    let handle = crate::channels::SentMessage {
+        channel: reply.channel.clone(),
+        session_id: reply.session_id.clone(),
+        message_id: reply.message_id.clone(),
+    };
+    tracing::info!(
+        "[{}] {} reaction on {}",
+        incoming.channel,
+        command,
+        reply.session_id
+    );
+    let detail = format!("{} {}", command, reply.message_id);
+    let _ = db
+        .audit_log(Some(&reply.session_id), "reaction", None, Some(&detail), 0)
+        .await;
+
+    match command {
+        ReactionCommand::Regenerate => {
+            // Earlier replies have been answered since, and reviewed or paused
+            // sessions must not get a reply past the reviewer or the pause
+            let latest = db.reply_latest(&reply.session_id).await.ok().flatten();
+            if latest.as_ref().map(|r| r.message_id.as_str()) != Some(reply.message_id.as_str())
+                || config.review.applies(&reply.channel, &reply.session_id)
+                || !matches!(db.pause_check(Some(&reply.session_id)).await, Ok(None))
+            {
+                tracing::info!("Not regenerating reply in {}", reply.session_id);
+                return;
+            }
+            if let Some(note) = regenerate_reply(
+                &reply.session_id,
+                incoming.is_group,
+                None,
+                config,
+                db,
+                conductor,
+                &adapter,
+                deliverer,
+            )
+            .await
+            {
+                tracing::info!("Reply in {} not regenerated: {}", reply.session_id, note);
+            }
+        }
+        ReactionCommand::Pin => {
+            match conductor.pin_reply(&reply.session_id, &reply.content).await {
+                Ok(Some(id)) => tracing::info!("Pinned reply as memory #{}", id),
+                Ok(None) => {
+                    tracing::info!("Not pinning reply in private session {}", reply.session_id)
+                }
+                Err(e) => tracing::error!("Failed to pin reply: {}", e),
+            }
+        }
+        ReactionCommand::Delete => {
+            if !adapter.capabilities().delete {
+                tracing::info!("{} can't delete messages", reply.channel);
+                return;
+            }
+            if let Err(e) = adapter.delete_message(&handle).await {
+                tracing::warn!("Failed to delete reply: {}", e);
+                return;
+            }
+            if let Err(e) = conductor
+                .forget_reply(&reply.session_id, &reply.content)
+                .await
+            {
+                tracing::error!("Failed to remove reply from tape: {}", e);
+            }
+            if let Err(e) = db.reply_delete(&reply.session_id, &reply.message_id).await {
+                tracing::warn!("Failed to forget reply: {}", e);
+            }
+        }
+    }
+}
+
+/// Answer the latest message in `session_id` again, with `model` if given.
+/// The new answer replaces the latest reply when it was recorded, and is
+/// sent as a new message otherwise. Returns what to tell the user when
+/// there was nothing to regenerate or it failed.
+#[allow(clippy::too_many_arguments)]
+async fn regenerate_reply(
+    session_id: &str,
+    is_group: bool,
+    model: Option<&str>,
+    config: &Config,
+    db: &Db,
+    conductor: &mut Conductor,
+    adapter: &Arc<dyn ChannelAdapter>,
+    deliverer: &Deliverer,
+) -> Option<String> {
+    // Edit the recorded reply only if it is the one being replaced
+    let caps = adapter.capabilities();
+    let messages = db.tape_load_messages(session_id).await.unwrap_or_default();
+    let latest = crate::reactions::find_exchange(&messages, None);
+    let handle = match db.reply_latest(session_id).await {
+        Ok(Some(reply))
+            if caps.edit
+                && reply.channel == adapter.name()
+                && latest.is_some()
+                && crate::reactions::find_exchange(&messages, Some(&reply.content)) == latest =>
+        {
+            Some(crate::channels::SentMessage {
+                channel: reply.channel,
+                session_id: reply.session_id,
+                message_id: reply.message_id,
+            })
+        }
+        _ => None,
+    };
+
+    let typing_handle = Some(adapter)
+        .filter(|_| caps.typing)
+        .and_then(|a| a.start_typing(session_id));
+    let result = conductor.regenerate(session_id, is_group, model).await;
+    if let Some(handle) = typing_handle {
+        handle.abort();
+    }
+    let response = match result {
+        Ok(Some(response)) => response,
+        Ok(None) => return Some("There's no reply to retry yet.".to_string()),
+        Err(e) => {
+            tracing::error!("Failed to regenerate reply: {}", e);
+            return Some("An error occurred processing your message.".to_string());
+        }
+    };
+    let _ = db
+        .audit_log(
+            Some(session_id),
+            "retry",
+            None,
+            Some(model.unwrap_or(&config.agent.model)),
+            0,
+        )
+        .await;
+
+    let sources = conductor.take_sources();
+    let response = if config.channels.citations(adapter.name()) {
+        super::citations::append(response, &sources)
+    } else {
+        response
+    };
+    match handle {
+        Some(handle) => {
+            let status = deliverer.edit(adapter.as_ref(), &handle, &response).await;
+            if status == crate::db::deliveries::DeliveryStatus::Edited {
+                if let Err(e) = db
+                    .reply_record(&handle.channel, &handle.message_id, session_id, &response)
+                    .await
+                {
+                    tracing::warn!("Failed to record reply: {}", e);
+                }
+            }
+        }
+        None => {
+            let outgoing = OutgoingMessage {
+                channel: adapter.name().to_string(),
+                session_id: session_id.to_string(),
+                content: response,
+                reply_to: None,
+            };
+            deliverer.send(adapter.as_ref(), outgoing).await;
+        }
+    }
+    None
+}
+
+/// First `max` characters of `s`, with "..." if anything was cut.
+fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}...", &s[..i]),
+        None => s.to_string(),
+    }
+}
@@ -1,27 +1,37 @@
 use crate::config::Config;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::Arc;
 use yoagent::provider::StreamProvider;
 use yoagent::sub_agent::SubAgentTool;
 use yoagent::types::AgentTool;
 
 /// Summary of a configured worker (for inspect output).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct WorkerInfo {
     pub name: String,
     pub provider: String,
     pub model: String,
     pub max_turns: usize,
     pub system_prompt: Option<String>,
+    /// Execution profile of the worker's shell, if it has one.
+    pub profile: Option<String>,
 }
 
 /// Build SubAgentTools from the `[agent.workers.*]` config sections.
 ///
 /// Returns a list of (SubAgentTool, WorkerInfo) pairs. Each SubAgentTool should
 /// be wrapped with `SecureToolWrapper` and added to the agent's tool list so
-/// that worker delegations are audit-logged and security-checked.
+/// that worker delegations are audit-logged and security-checked. Workers with
+/// a `profile` also get its shell from `shells` (profile name → tool). With
+/// `limits`, the workers' token usage counts toward the per-message budget.
+/// Providers record the transcripts of runs made through
+/// [`RunLog::record`](super::worker_runs::RunLog::record).
 pub fn build_workers(
     config: &Config,
     tools: &[Arc<dyn AgentTool>],
+    shells: &HashMap<String, Arc<dyn AgentTool>>,
+    limits: Option<&Arc<super::limits::WorkerLimits>>,
 ) -> Vec<(SubAgentTool, WorkerInfo)> {
     let workers_config = &config.agent.workers;
     let mut result = Vec::new();
@@ -43,7 +53,32 @@ pub fn build_workers(
         let api_key = worker.api_key.as_deref().unwrap_or(&config.agent.api_key);
         let max_turns = worker.max_turns.unwrap_or(10);
 
-        let provider = resolve_arc_provider(provider_name);
+        let mut endpoint = super::endpoint::Endpoint::for_provider(&config.agent, provider_name);
+        if let Some(ref base_url) = worker.base_url {
+            endpoint.base_url = Some(base_url.clone());
+        }
+        if let Some(ref options) = worker.provider_options {
+            endpoint.options = options.clone();
+        }
+        let mut provider = resolve_arc_provider(provider_name, &endpoint);
+        if let Some(limits) = limits {
+            provider = Arc::new(super::limits::MeteredProvider {
+                inner: provider,
+                limits: limits.clone(),
+            });
+        }
+        let provider = Arc::new(super::worker_runs::RecordingProvider { inner: provider });
+        let mut worker_tools = tools.to_vec();
+        if let Some(ref profile) = worker.profile {
+            match shells.get(profile) {
+                Some(shell) => worker_tools.push(shell.clone()),
+                None => tracing::warn!(
+                    "Worker '{}' requests profile '{}', but no shell was built for it",
+                    name,
+                    profile
+                ),
+            }
+        }
 
         let description = match &worker.system_prompt {
             Some(prompt) => {
@@ -65,7 +100,7 @@ pub fn build_workers(
             .with_model(model)
             .with_api_key(api_key)
             .with_max_turns(max_turns)
-            .with_tools(tools.to_vec());
+            .with_tools(worker_tools);
 
         if let Some(ref prompt) = worker.system_prompt {
             sub = sub.with_system_prompt(prompt);
@@ -81,6 +116,7 @@ pub fn build_workers(
             model: model.to_string(),
             max_turns,
             system_prompt: worker.system_prompt.clone(),
+            profile: worker.profile.clone(),
         };
 
         result.push((sub, info));
@@ -91,25 +127,37 @@ pub fn build_workers(
     result
 }
 
-/// Resolve a provider name to an Arc<dyn StreamProvider>.
-pub(crate) fn resolve_arc_provider(name: &str) -> Arc<dyn StreamProvider> {
+/// Resolve a provider name to an Arc<dyn StreamProvider>, using the keys
+/// of [`keys::ring`](super::keys::ring).
+pub(crate) fn resolve_arc_provider(
+    name: &str,
+    endpoint: &super::endpoint::Endpoint,
+) -> Arc<dyn StreamProvider> {
     use yoagent::provider::*;
-    match name {
-        "anthropic" => Arc::new(AnthropicProvider),
-        "openai" => Arc::new(OpenAiCompatProvider),
-        "google" => Arc::new(GoogleProvider),
-        "vertex" => Arc::new(GoogleVertexProvider),
-        "azure" => Arc::new(AzureOpenAiProvider),
-        "bedrock" => Arc::new(BedrockProvider),
-        "openai_responses" => Arc::new(OpenAiResponsesProvider),
+    let inner: Box<dyn StreamProvider> = match name {
+        "anthropic" => Box::new(AnthropicProvider),
+        "openai" => Box::new(OpenAiCompatProvider),
+        "google" => Box::new(GoogleProvider),
+        "vertex" => Box::new(GoogleVertexProvider),
+        "azure" => Box::new(AzureOpenAiProvider),
+        "bedrock" => Box::new(BedrockProvider),
+        "openai_responses" => Box::new(OpenAiResponsesProvider),
         other => {
             tracing::warn!(
                 "Unknown provider '{}' for worker, defaulting to anthropic",
                 other
             );
-            Arc::new(AnthropicProvider)
+            Box::new(AnthropicProvider)
         }
-    }
+    };
+    Arc::new(super::keys::KeyRotatingProvider {
+        inner: Box::new(super::endpoint::EndpointProvider {
+            inner,
+            provider: name.to_string(),
+            endpoint: endpoint.clone(),
+        }),
+        ring: super::keys::ring(),
+    })
 }
 
 /// Format worker info for display (inspect command).
@@ -133,9 +181,14 @@ pub fn format_workers_info(workers: &[WorkerInfo]) -> String {
                     format!(" \"{}\"", snippet)
                 })
                 .unwrap_or_default();
+            let profile_hint = w
+                .profile
+                .as_ref()
+                .map(|p| format!(", profile: {}", p))
+                .unwrap_or_default();
             format!(
-                "  {} — {} / {} (max_turns: {}{})",
-                w.name, w.provider, w.model, w.max_turns, prompt_hint
+                "  {} — {} / {} (max_turns: {}{}){}",
+                w.name, w.provider, w.model, w.max_turns, profile_hint, prompt_hint
             )
         })
         .collect::<Vec<_>>()
@@ -165,10 +218,11 @@ max_turns = 20
 
 [agent.workers.research]
 max_turns = 15
+profile = "sandbox"
 "#;
         let config = parse_config(toml).unwrap();
         let tools: Vec<Arc<dyn AgentTool>> = Vec::new();
-        let workers = build_workers(&config, &tools);
+        let workers = build_workers(&config, &tools, &HashMap::new(), None);
 
         assert_eq!(workers.len(), 2);
 
@@ -182,6 +236,7 @@ max_turns = 15
         // Falls back to workers default model
         assert_eq!(workers[1].1.model, "claude-haiku-4-5-20251001");
         assert_eq!(workers[1].1.max_turns, 15);
+        assert_eq!(workers[1].1.profile.as_deref(), Some("sandbox"));
     }
 
     #[test]
@@ -193,7 +248,7 @@ api_key = "key"
 "#;
         let config = parse_config(toml).unwrap();
         let tools: Vec<Arc<dyn AgentTool>> = Vec::new();
-        let workers = build_workers(&config, &tools);
+        let workers = build_workers(&config, &tools, &HashMap::new(), None);
         assert!(workers.is_empty());
     }
 
@@ -206,6 +261,7 @@ api_key = "key"
                 model: "claude-sonnet-4-20250514".into(),
                 max_turns: 20,
                 system_prompt: Some("You are a coding assistant.".into()),
+                profile: Some("docker-python".into()),
             },
             WorkerInfo {
                 name: "research".into(),
@@ -213,11 +269,12 @@ api_key = "key"
                 model: "claude-haiku-4-5-20251001".into(),
                 max_turns: 15,
                 system_prompt: None,
+                profile: None,
             },
         ];
         let info = format_workers_info(&workers);
         assert!(info.contains("coding"));
         assert!(info.contains("research"));
-        assert!(info.contains("max_turns: 20"));
+        assert!(info.contains("max_turns: 20, profile: docker-python"));
     }
 }
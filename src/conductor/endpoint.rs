@@ -0,0 +1,174 @@
+//! Model configs for providers that need one.
+//!
+//! yoagent's providers other than Anthropic read the API URL, extra headers
+//! and protocol quirks from `StreamConfig::model_config`, which the agent
+//! loop leaves empty. [`EndpointProvider`] fills it in from the provider
+//! name, the call's model and an [`Endpoint`]: `base_url` and
+//! `provider_options` of `[agent]`, a worker or a fallback provider. That
+//! is how OpenAI-compatible servers such as Ollama, vLLM or OpenRouter are
+//! reached. Calls that already carry a model config are passed on
+//! unchanged.
+
+use crate::config::{AgentConfig, ProviderOptions};
+use yoagent::provider::{
+    ApiProtocol, ModelConfig, OpenAiCompat, ProviderError, StreamConfig, StreamEvent,
+    StreamProvider,
+};
+use yoagent::types::*;
+
+/// Where a provider's requests go.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Endpoint {
+    /// API base URL; the provider's public API if unset.
+    pub base_url: Option<String>,
+    pub options: ProviderOptions,
+}
+
+impl Endpoint {
+    pub fn new(base_url: Option<&str>, options: &ProviderOptions) -> Self {
+        Self {
+            base_url: base_url.map(str::to_string),
+            options: options.clone(),
+        }
+    }
+
+    /// The endpoint of `[agent]`.
+    pub fn agent(agent: &AgentConfig) -> Self {
+        Self::new(agent.base_url.as_deref(), &agent.provider_options)
+    }
+
+    /// The agent's endpoint if `provider` is the agent's provider, the
+    /// provider's default one otherwise.
+    pub fn for_provider(agent: &AgentConfig, provider: &str) -> Self {
+        if provider == agent.provider {
+            Self::agent(agent)
+        } else {
+            Self::default()
+        }
+    }
+}
+
+/// Compat flags of a `provider_options.compat` preset.
+pub fn compat(name: &str) -> Option<OpenAiCompat> {
+    Some(match name {
+        "openai" => OpenAiCompat::openai(),
+        "generic" => OpenAiCompat::default(),
+        "xai" => OpenAiCompat::xai(),
+        "groq" => OpenAiCompat::groq(),
+        "cerebras" => OpenAiCompat::cerebras(),
+        "openrouter" => OpenAiCompat::openrouter(),
+        "mistral" => OpenAiCompat::mistral(),
+        "deepseek" => OpenAiCompat::deepseek(),
+        _ => return None,
+    })
+}
+
+/// The model config for `model` on `provider` at `endpoint`, if the
+/// provider needs one. Vertex, Azure and Bedrock have no public default
+/// and need a `base_url`.
+pub fn model_config(provider: &str, model: &str, endpoint: &Endpoint) -> Option<ModelConfig> {
+    let with_api = |api: ApiProtocol, base: ModelConfig| ModelConfig {
+        api,
+        provider: provider.to_string(),
+        ..base
+    };
+    let mut config = match provider {
+        "openai" => ModelConfig::openai(model, model),
+        "openai_responses" => with_api(
+            ApiProtocol::OpenAiResponses,
+            ModelConfig::openai(model, model),
+        ),
+        "google" => ModelConfig::google(model, model),
+        "vertex" if endpoint.base_url.is_some() => {
+            with_api(ApiProtocol::GoogleVertex, ModelConfig::google(model, model))
+        }
+        "azure" if endpoint.base_url.is_some() => with_api(
+            ApiProtocol::AzureOpenAiResponses,
+            ModelConfig::openai(model, model),
+        ),
+        "bedrock" if endpoint.base_url.is_some() => with_api(
+            ApiProtocol::BedrockConverseStream,
+            ModelConfig::anthropic(model, model),
+        ),
+        _ => return None,
+    };
+    if let Some(ref base_url) = endpoint.base_url {
+        config.base_url = base_url.trim_end_matches('/').to_string();
+    }
+    config.headers.extend(endpoint.options.headers.clone());
+    if let Some(flags) = endpoint.options.compat.as_deref().and_then(compat) {
+        config.compat = Some(flags);
+    }
+    Some(config)
+}
+
+/// Adds the provider's model config to each call.
+pub struct EndpointProvider {
+    pub inner: Box<dyn StreamProvider>,
+    /// Provider name, as in `[agent] provider`.
+    pub provider: String,
+    pub endpoint: Endpoint,
+}
+
+#[async_trait::async_trait]
+impl StreamProvider for EndpointProvider {
+    async fn stream(
+        &self,
+        mut config: StreamConfig,
+        tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Message, ProviderError> {
+        if config.model_config.is_none() {
+            config.model_config = model_config(&self.provider, &config.model, &self.endpoint);
+        }
+        self.inner.stream(config, tx, cancel).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn test_model_config() {
+        let agent = parse_config(
+            r#"
+[agent]
+provider = "openai"
+model = "llama3.1"
+api_key = ""
+base_url = "http://localhost:11434/v1/"
+
+[agent.provider_options]
+compat = "generic"
+headers = { "X-Title" = "yoclaw" }
+"#,
+        )
+        .unwrap()
+        .agent;
+
+        let endpoint = Endpoint::agent(&agent);
+        let config = model_config("openai", "llama3.1", &endpoint).unwrap();
+        assert_eq!(config.base_url, "http://localhost:11434/v1");
+        assert_eq!(config.headers["X-Title"], "yoclaw");
+        // Plain OpenAI-compatible server, not OpenAI's own flags
+        assert!(!config.compat.unwrap().supports_store);
+
+        // Another provider doesn't get the agent's endpoint
+        let google = model_config(
+            "google",
+            "gemini",
+            &Endpoint::for_provider(&agent, "google"),
+        );
+        assert_eq!(
+            google.unwrap().base_url,
+            ModelConfig::google("gemini", "gemini").base_url
+        );
+        assert!(model_config("anthropic", "claude", &endpoint).is_none());
+        // No default URL to fall back on
+        assert!(model_config("azure", "gpt-4o", &Endpoint::default()).is_none());
+        let azure = model_config("azure", "gpt-4o", &endpoint).unwrap();
+        assert_eq!(azure.api, ApiProtocol::AzureOpenAiResponses);
+    }
+}
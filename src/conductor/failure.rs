@@ -0,0 +1,136 @@
+//! Failure context for messages the conductor couldn't process.
+//!
+//! Errors raised by the conductor itself are `ProcessError`s, so the main loop
+//! can record what failed (`QueueFailure`) instead of only the message: the
+//! error class, the provider/model or worker involved, how far the agent got
+//! and whether reprocessing the message could help.
+
+use crate::db::queue::QueueFailure;
+use crate::db::DbError;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessError {
+    #[error("Provider overloaded after {attempts} attempt(s): {message}")]
+    Overloaded { attempts: u32, message: String },
+    #[error("Worker '{0}' not found")]
+    UnknownWorker(String),
+    #[error("Worker '{worker}' failed: {message}")]
+    Worker { worker: String, message: String },
+}
+
+/// Progress of the message in flight, updated after every agent turn.
+#[derive(Debug, Clone, Default)]
+pub struct RunStats {
+    pub turns: u32,
+    pub tokens: u64,
+    /// Last tool the agent called.
+    pub tool: Option<String>,
+}
+
+/// Shared between the conductor and the agent's after-turn callback.
+pub type RunStatsRef = Arc<Mutex<RunStats>>;
+
+/// Describe `error` for the queue. `provider` and `model` are the main agent's;
+/// worker failures name the worker's instead when it is known.
+pub fn describe(
+    error: &anyhow::Error,
+    stats: &RunStats,
+    provider: &str,
+    model: &str,
+    worker: Option<(&str, &str)>,
+) -> QueueFailure {
+    let (class, retryable) = classify(error);
+    let (provider, model) = worker.unwrap_or((provider, model));
+    let tool = match error.downcast_ref::<ProcessError>() {
+        Some(ProcessError::UnknownWorker(name))
+        | Some(ProcessError::Worker { worker: name, .. }) => Some(name.clone()),
+        _ => stats.tool.clone(),
+    };
+    QueueFailure {
+        class: class.to_string(),
+        message: error.to_string(),
+        provider: Some(provider.to_string()),
+        model: Some(model.to_string()),
+        tool,
+        turns: stats.turns,
+        tokens: stats.tokens,
+        retryable,
+    }
+}
+
+/// Error class and whether the message is worth processing again.
+fn classify(error: &anyhow::Error) -> (&'static str, bool) {
+    if let Some(e) = error.downcast_ref::<ProcessError>() {
+        return match e {
+            ProcessError::Overloaded { .. } => ("provider_overloaded", true),
+            ProcessError::UnknownWorker(_) => ("unknown_worker", false),
+            ProcessError::Worker { .. } => ("worker", true),
+        };
+    }
+    if let Some(e) = error.downcast_ref::<DbError>() {
+        // A busy or locked database clears up; a poisoned lock doesn't
+        return ("storage", !matches!(e, DbError::LockPoisoned));
+    }
+    if error.downcast_ref::<serde_json::Error>().is_some() {
+        return ("serialization", false);
+    }
+    ("internal", false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_failures() {
+        let stats = RunStats {
+            turns: 2,
+            tokens: 1500,
+            tool: Some("web_fetch".into()),
+        };
+
+        let error = anyhow::Error::from(ProcessError::Overloaded {
+            attempts: 3,
+            message: "HTTP 529".into(),
+        });
+        let failure = describe(&error, &stats, "anthropic", "claude", None);
+        assert_eq!(failure.class, "provider_overloaded");
+        assert_eq!(
+            failure.message,
+            "Provider overloaded after 3 attempt(s): HTTP 529"
+        );
+        assert_eq!(failure.provider.as_deref(), Some("anthropic"));
+        assert_eq!(failure.tool.as_deref(), Some("web_fetch"));
+        assert_eq!((failure.turns, failure.tokens), (2, 1500));
+        assert!(failure.retryable);
+
+        let error = anyhow::Error::from(ProcessError::UnknownWorker("coding".into()));
+        let failure = describe(&error, &RunStats::default(), "anthropic", "claude", None);
+        assert_eq!(failure.class, "unknown_worker");
+        assert_eq!(failure.tool.as_deref(), Some("coding"));
+        assert!(!failure.retryable);
+
+        let error = anyhow::Error::from(DbError::JoinError("cancelled".into()));
+        let failure = describe(
+            &error,
+            &stats,
+            "anthropic",
+            "claude",
+            Some(("openai", "gpt-4o")),
+        );
+        assert_eq!(failure.class, "storage");
+        assert_eq!(failure.model.as_deref(), Some("gpt-4o"));
+        assert!(failure.retryable);
+
+        let failure = describe(
+            &anyhow::anyhow!("boom"),
+            &stats,
+            "anthropic",
+            "claude",
+            None,
+        );
+        assert_eq!(failure.class, "internal");
+        assert!(!failure.retryable);
+    }
+}
@@ -0,0 +1,202 @@
+//! Fallback providers for the agent.
+//!
+//! `[[agent.fallback_providers]]` lists providers, each with its own model
+//! and API key, to use while the configured one is rate limited or
+//! overloaded. The agent's provider is wrapped in [`FallbackProvider`],
+//! which sends each call to the provider the shared [`FallbackChain`]
+//! points at. The conductor moves the chain along when a turn fails with an
+//! overload error, goes on with the turn (from the results of any tools that
+//! already ran) and audits the switch as `provider_fallback`; every new
+//! message starts with the configured provider again.
+
+use super::DynProvider;
+use crate::config::AgentConfig;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use yoagent::provider::{ProviderError, StreamConfig, StreamEvent, StreamProvider};
+use yoagent::types::*;
+
+/// A provider to fall back to, with the model and key to use on it.
+pub(crate) struct Fallback {
+    /// `"<provider>/<model>"`, for logs and the audit log.
+    pub label: String,
+    pub provider: DynProvider,
+    pub model: String,
+    pub api_key: String,
+}
+
+/// Which provider the agent uses: 0 for the configured one, then the
+/// fallbacks in order.
+#[derive(Clone)]
+pub struct FallbackChain {
+    active: Arc<AtomicUsize>,
+    /// Label of each provider, the configured one first.
+    labels: Arc<Vec<String>>,
+}
+
+impl FallbackChain {
+    /// Go back to the configured provider.
+    pub fn reset(&self) {
+        self.active.store(0, Ordering::SeqCst);
+    }
+
+    /// Switch to the next provider. Returns the labels of the provider
+    /// switched from and to, or `None` if there is no provider left.
+    pub fn advance(&self) -> Option<(&str, &str)> {
+        let from = self.active.load(Ordering::SeqCst);
+        let to = self.labels.get(from + 1)?;
+        self.active.store(from + 1, Ordering::SeqCst);
+        Some((&self.labels[from], to))
+    }
+}
+
+/// Sends calls to the configured provider or, once the chain has moved on,
+/// to a fallback with its own model and key.
+pub struct FallbackProvider {
+    inner: Box<dyn StreamProvider>,
+    fallbacks: Vec<Fallback>,
+    active: Arc<AtomicUsize>,
+}
+
+/// Wrap `provider` with the fallbacks of `agent`, if it has any.
+pub fn wrap(provider: DynProvider, agent: &AgentConfig) -> (DynProvider, Option<FallbackChain>) {
+    if agent.fallback_providers.is_empty() {
+        return (provider, None);
+    }
+    let fallbacks = agent
+        .fallback_providers
+        .iter()
+        .map(|f| Fallback {
+            label: format!("{}/{}", f.provider, f.model),
+            provider: super::resolve_provider(
+                &f.provider,
+                &super::endpoint::Endpoint::new(f.base_url.as_deref(), &f.provider_options),
+            ),
+            model: f.model.clone(),
+            api_key: f.api_key.clone(),
+        })
+        .collect();
+    let primary = format!("{}/{}", agent.provider, agent.model);
+    let (provider, chain) = chain(provider, primary, fallbacks);
+    (provider, Some(chain))
+}
+
+/// Wrap `provider`, labelled `primary`, with `fallbacks`.
+pub(crate) fn chain(
+    provider: DynProvider,
+    primary: String,
+    fallbacks: Vec<Fallback>,
+) -> (DynProvider, FallbackChain) {
+    let labels = std::iter::once(primary)
+        .chain(fallbacks.iter().map(|f| f.label.clone()))
+        .collect();
+    let active = Arc::new(AtomicUsize::new(0));
+    let chain = FallbackChain {
+        active: active.clone(),
+        labels: Arc::new(labels),
+    };
+    let provider = DynProvider(Box::new(FallbackProvider {
+        inner: Box::new(provider),
+        fallbacks,
+        active,
+    }));
+    (provider, chain)
+}
+
+#[async_trait::async_trait]
+impl StreamProvider for FallbackProvider {
+    async fn stream(
+        &self,
+        mut config: StreamConfig,
+        tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Message, ProviderError> {
+        let Some(fallback) = self
+            .active
+            .load(Ordering::SeqCst)
+            .checked_sub(1)
+            .and_then(|i| self.fallbacks.get(i))
+        else {
+            return self.inner.stream(config, tx, cancel).await;
+        };
+        config.model = fallback.model.clone();
+        config.api_key = fallback.api_key.clone();
+        config.model_config = None;
+        fallback.provider.stream(config, tx, cancel).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers with the model and key it was called with.
+    struct EchoProvider;
+
+    #[async_trait::async_trait]
+    impl StreamProvider for EchoProvider {
+        async fn stream(
+            &self,
+            config: StreamConfig,
+            _tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+            _cancel: tokio_util::sync::CancellationToken,
+        ) -> Result<Message, ProviderError> {
+            Ok(Message::user(format!(
+                "{} {}",
+                config.model, config.api_key
+            )))
+        }
+    }
+
+    async fn call(provider: &DynProvider) -> String {
+        let config = StreamConfig {
+            model: "primary-model".into(),
+            system_prompt: String::new(),
+            messages: Vec::new(),
+            tools: Vec::new(),
+            thinking_level: ThinkingLevel::Off,
+            api_key: "primary-key".into(),
+            max_tokens: None,
+            temperature: None,
+            model_config: None,
+            cache_config: CacheConfig::default(),
+        };
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let message = provider
+            .stream(config, tx, tokio_util::sync::CancellationToken::new())
+            .await
+            .unwrap();
+        match message {
+            Message::User { content, .. } => match &content[0] {
+                Content::Text { text } => text.clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain_switches_model_and_key() {
+        let (provider, chain) = chain(
+            DynProvider::new(EchoProvider),
+            "anthropic/primary-model".into(),
+            vec![Fallback {
+                label: "openai/gpt-4o".into(),
+                provider: DynProvider::new(EchoProvider),
+                model: "gpt-4o".into(),
+                api_key: "sk-fallback".into(),
+            }],
+        );
+        assert_eq!(call(&provider).await, "primary-model primary-key");
+
+        assert_eq!(
+            chain.advance(),
+            Some(("anthropic/primary-model", "openai/gpt-4o"))
+        );
+        assert_eq!(call(&provider).await, "gpt-4o sk-fallback");
+        assert_eq!(chain.advance(), None);
+
+        chain.reset();
+        assert_eq!(call(&provider).await, "primary-model primary-key");
+    }
+}
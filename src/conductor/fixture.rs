@@ -0,0 +1,266 @@
+//! Replaying recorded provider responses, for tests.
+//!
+//! A transcript fixture is a JSON file with what the provider streams for
+//! each request, in order:
+//!
+//! ```json
+//! {"responses": [
+//!   {"events": [
+//!     {"thinking": "They want the weather where they live."},
+//!     {"text": "Let me check. "},
+//!     {"tool_call": {"id": "tc-1", "name": "memory_search", "arguments": {"query": "city"}}}
+//!   ], "usage": {"input": 120, "output": 30}},
+//!   {"events": [{"text": "Sunny, "}, {"delay_ms": 50}, {"text": "21°C."}]},
+//!   {"error": "Invalid status code: 529 <unknown status code>"}
+//! ]}
+//! ```
+//!
+//! Each `text` or `thinking` event is one streamed delta; consecutive ones
+//! make up one block of the final message. A response with tool calls stops
+//! with `ToolUse`, so the agent runs the tools and asks for the next
+//! response. An `error` response fails the request the way a provider
+//! error does. Requests past the last response fail too.
+//!
+//! [`FixtureProvider`] stands in for `MockProvider` wherever a test needs
+//! tool calls, thinking or chunked text in a fixed order. Fixtures live in
+//! `tests/fixtures/transcripts/`.
+
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use yoagent::provider::{ProviderError, StreamConfig, StreamEvent, StreamProvider};
+use yoagent::types::*;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FixtureError {
+    #[error("Failed to read fixture {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("Invalid fixture: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// The responses of a fixture file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Transcript {
+    pub responses: Vec<FixtureResponse>,
+}
+
+/// What the provider streams for one request.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FixtureResponse {
+    #[serde(default)]
+    pub events: Vec<FixtureEvent>,
+    #[serde(default)]
+    pub usage: Option<FixtureUsage>,
+    /// Fail the request with this message instead.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixtureEvent {
+    Text(String),
+    Thinking(String),
+    ToolCall {
+        id: String,
+        name: String,
+        #[serde(default)]
+        arguments: serde_json::Value,
+    },
+    /// Pause the stream, to interleave it with something else.
+    DelayMs(u64),
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct FixtureUsage {
+    #[serde(default)]
+    pub input: u64,
+    #[serde(default)]
+    pub output: u64,
+}
+
+/// Provider that plays back a [`Transcript`], one response per request.
+pub struct FixtureProvider {
+    responses: Mutex<VecDeque<FixtureResponse>>,
+}
+
+impl FixtureProvider {
+    pub fn new(transcript: Transcript) -> Self {
+        Self {
+            responses: Mutex::new(transcript.responses.into()),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, FixtureError> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+
+    /// Load a fixture file.
+    pub fn load(path: &Path) -> Result<Self, FixtureError> {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| FixtureError::Read(path.to_path_buf(), e))?;
+        Self::from_json(&json)
+    }
+
+    /// Responses not played yet.
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+}
+
+#[async_trait::async_trait]
+impl StreamProvider for FixtureProvider {
+    async fn stream(
+        &self,
+        _config: StreamConfig,
+        tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Message, ProviderError> {
+        let response = self.responses.lock().unwrap().pop_front();
+        let _ = tx.send(StreamEvent::Start);
+        let response = match response {
+            Some(FixtureResponse {
+                error: Some(error), ..
+            }) => return Ok(failed(&tx, error)),
+            Some(response) => response,
+            None => {
+                return Ok(failed(
+                    &tx,
+                    "Transcript fixture has no responses left".to_string(),
+                ))
+            }
+        };
+
+        let mut content: Vec<Content> = Vec::new();
+        for event in response.events {
+            if cancel.is_cancelled() {
+                break;
+            }
+            match event {
+                FixtureEvent::Text(delta) => {
+                    if let Some(Content::Text { text }) = content.last_mut() {
+                        text.push_str(&delta);
+                    } else {
+                        content.push(Content::Text {
+                            text: delta.clone(),
+                        });
+                    }
+                    let _ = tx.send(StreamEvent::TextDelta {
+                        content_index: content.len() - 1,
+                        delta,
+                    });
+                }
+                FixtureEvent::Thinking(delta) => {
+                    if let Some(Content::Thinking { thinking, .. }) = content.last_mut() {
+                        thinking.push_str(&delta);
+                    } else {
+                        content.push(Content::Thinking {
+                            thinking: delta.clone(),
+                            signature: None,
+                        });
+                    }
+                    let _ = tx.send(StreamEvent::ThinkingDelta {
+                        content_index: content.len() - 1,
+                        delta,
+                    });
+                }
+                FixtureEvent::ToolCall {
+                    id,
+                    name,
+                    arguments,
+                } => {
+                    let content_index = content.len();
+                    let _ = tx.send(StreamEvent::ToolCallStart {
+                        content_index,
+                        id: id.clone(),
+                        name: name.clone(),
+                    });
+                    let _ = tx.send(StreamEvent::ToolCallDelta {
+                        content_index,
+                        delta: arguments.to_string(),
+                    });
+                    let _ = tx.send(StreamEvent::ToolCallEnd { content_index });
+                    content.push(Content::ToolCall {
+                        id,
+                        name,
+                        arguments,
+                    });
+                }
+                FixtureEvent::DelayMs(ms) => tokio::time::sleep(Duration::from_millis(ms)).await,
+            }
+        }
+
+        let stop_reason = if content
+            .iter()
+            .any(|c| matches!(c, Content::ToolCall { .. }))
+        {
+            StopReason::ToolUse
+        } else {
+            StopReason::Stop
+        };
+        let usage = response.usage.unwrap_or_default();
+        let message = Message::Assistant {
+            content,
+            stop_reason,
+            model: "fixture".to_string(),
+            provider: "fixture".to_string(),
+            usage: Usage {
+                input: usage.input,
+                output: usage.output,
+                ..Default::default()
+            },
+            timestamp: crate::db::now_ms(),
+            error_message: None,
+        };
+        let _ = tx.send(StreamEvent::Done {
+            message: message.clone(),
+        });
+        Ok(message)
+    }
+}
+
+/// A failed request, as providers report it.
+fn failed(tx: &tokio::sync::mpsc::UnboundedSender<StreamEvent>, error: String) -> Message {
+    let message = Message::Assistant {
+        content: Vec::new(),
+        stop_reason: StopReason::Error,
+        model: "fixture".to_string(),
+        provider: "fixture".to_string(),
+        usage: Usage::default(),
+        timestamp: crate::db::now_ms(),
+        error_message: Some(error),
+    };
+    let _ = tx.send(StreamEvent::Error {
+        message: message.clone(),
+    });
+    message
+}
+
+/// Path of a fixture in `tests/fixtures/transcripts/`.
+pub fn path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/transcripts")
+        .join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let provider = FixtureProvider::load(&path("tool_call.json")).unwrap();
+        assert_eq!(provider.remaining(), 2);
+        assert!(matches!(
+            FixtureProvider::from_json(r#"{"responses": [{"events": [{"shout": "hi"}]}]}"#),
+            Err(FixtureError::Parse(_))
+        ));
+        assert!(matches!(
+            FixtureProvider::load(&path("missing.json")),
+            Err(FixtureError::Read(..))
+        ));
+    }
+}
@@ -0,0 +1,298 @@
+//! Rotation over several API keys for the agent's provider.
+//!
+//! `[agent] api_keys` lists keys to use besides `api_key`. Providers from
+//! [`resolve_provider`](super::resolve_provider) and workers' providers are
+//! wrapped in [`KeyRotatingProvider`], which replaces `api_key` in each call
+//! with a key from the shared [`KeyRing`]: the first usable one with
+//! `key_rotation = "failover"`, the next in turn with `"round_robin"`. A key
+//! answered with 401/403 is set aside for an hour, one answered with 429 for
+//! the time the provider asks (a minute if it doesn't say), and the call is
+//! made again with the next key. The ring is reconfigured on every config
+//! reload, so keys can be added or revoked without a restart. Calls with any
+//! other key (e.g. a worker's own) are passed through unchanged.
+
+use crate::config::AgentConfig;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use yoagent::provider::{ProviderError, StreamConfig, StreamEvent, StreamProvider};
+use yoagent::types::*;
+
+/// How long a rejected key is left out.
+const AUTH_COOLDOWN: Duration = Duration::from_secs(3600);
+/// How long a rate-limited key is left out, unless the provider says.
+const RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct State {
+    keys: Vec<String>,
+    /// `api_key` values agents were built with. Calls with these get a key
+    /// from the ring, so agents pick up keys changed since.
+    aliases: HashSet<String>,
+    round_robin: bool,
+    next: usize,
+    /// Keys left out until the given time.
+    benched: HashMap<String, Instant>,
+}
+
+/// The agent provider's API keys, shared by every provider in the process.
+#[derive(Default)]
+pub struct KeyRing {
+    state: Mutex<State>,
+}
+
+/// The process-wide ring, configured by [`configure`].
+pub fn ring() -> &'static KeyRing {
+    static RING: OnceLock<KeyRing> = OnceLock::new();
+    RING.get_or_init(KeyRing::default)
+}
+
+/// Load the keys of `agent` into the process-wide ring.
+pub fn configure(agent: &AgentConfig) {
+    ring().configure(agent);
+}
+
+impl KeyRing {
+    /// Use `api_key` and `api_keys` of `agent`. Keys still configured stay
+    /// set aside if they were.
+    pub fn configure(&self, agent: &AgentConfig) {
+        let mut keys: Vec<String> = Vec::new();
+        for key in std::iter::once(&agent.api_key).chain(&agent.api_keys) {
+            if !key.is_empty() && !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        let round_robin = match agent.key_rotation.as_str() {
+            "failover" => false,
+            "round_robin" => true,
+            other => {
+                tracing::warn!("Unknown key_rotation '{}', using failover", other);
+                false
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if !state.keys.is_empty() && state.keys != keys {
+            tracing::info!("API keys reloaded ({} keys)", keys.len());
+        }
+        state.benched.retain(|key, _| keys.contains(key));
+        // Calls without a key (local servers, fallbacks and workers that
+        // need none) never get the agent's keys
+        if !agent.api_key.is_empty() {
+            state.aliases.insert(agent.api_key.clone());
+        }
+        state.keys = keys;
+        state.round_robin = round_robin;
+    }
+
+    /// Keys to try, in order, for a call made with `api_key`: the usable
+    /// ones, or the one back soonest if all are set aside. `None` if the
+    /// ring doesn't stand in for `api_key`.
+    fn candidates(&self, api_key: &str) -> Option<Vec<String>> {
+        let mut state = self.state.lock().unwrap();
+        if state.keys.is_empty() || !state.aliases.contains(api_key) {
+            return None;
+        }
+        let now = Instant::now();
+        state.benched.retain(|_, until| *until > now);
+
+        let start = if state.round_robin {
+            let start = state.next % state.keys.len();
+            state.next = start + 1;
+            start
+        } else {
+            0
+        };
+        let mut keys = state.keys.clone();
+        keys.rotate_left(start);
+
+        let usable: Vec<String> = keys
+            .iter()
+            .filter(|key| !state.benched.contains_key(*key))
+            .cloned()
+            .collect();
+        if !usable.is_empty() {
+            return Some(usable);
+        }
+        let soonest = keys.into_iter().min_by_key(|key| state.benched[key])?;
+        Some(vec![soonest])
+    }
+
+    /// Leave `key` out for `duration`.
+    fn bench(&self, key: &str, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .benched
+            .insert(key.to_string(), Instant::now() + duration);
+    }
+}
+
+/// Last four characters of `key`, for logs.
+fn masked(key: &str) -> String {
+    let tail: String = key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("…{}", tail)
+}
+
+/// Swaps the agent's API key for keys from a [`KeyRing`].
+pub struct KeyRotatingProvider {
+    pub inner: Box<dyn StreamProvider>,
+    pub ring: &'static KeyRing,
+}
+
+#[async_trait::async_trait]
+impl StreamProvider for KeyRotatingProvider {
+    async fn stream(
+        &self,
+        config: StreamConfig,
+        tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Message, ProviderError> {
+        let Some(candidates) = self.ring.candidates(&config.api_key) else {
+            return self.inner.stream(config, tx, cancel).await;
+        };
+        let mut last_error = None;
+        for key in candidates {
+            let mut attempt = config.clone();
+            attempt.api_key = key.clone();
+            let cooldown = match self.inner.stream(attempt, tx.clone(), cancel.clone()).await {
+                Err(e @ ProviderError::Auth(_)) => {
+                    tracing::warn!("API key {} rejected: {}", masked(&key), e);
+                    last_error = Some(e);
+                    AUTH_COOLDOWN
+                }
+                Err(ProviderError::RateLimited { retry_after_ms }) => {
+                    tracing::warn!("API key {} rate limited", masked(&key));
+                    last_error = Some(ProviderError::RateLimited { retry_after_ms });
+                    retry_after_ms
+                        .map(Duration::from_millis)
+                        .unwrap_or(RATE_LIMIT_COOLDOWN)
+                }
+                result => return result,
+            };
+            self.ring.bench(&key, cooldown);
+        }
+        Err(last_error.unwrap_or(ProviderError::Other("No API key available".into())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn agent(keys: &str) -> AgentConfig {
+        parse_config(&format!("[agent]\nmodel = \"m\"\n{}", keys))
+            .unwrap()
+            .agent
+    }
+
+    fn ring(keys: &str) -> &'static KeyRing {
+        let ring: &'static KeyRing = Box::leak(Box::default());
+        ring.configure(&agent(keys));
+        ring
+    }
+
+    /// Answers with the key it was called with; 401 for "bad", 429 for "busy".
+    struct EchoProvider;
+
+    #[async_trait::async_trait]
+    impl StreamProvider for EchoProvider {
+        async fn stream(
+            &self,
+            config: StreamConfig,
+            _tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+            _cancel: tokio_util::sync::CancellationToken,
+        ) -> Result<Message, ProviderError> {
+            match config.api_key.as_str() {
+                "bad" => Err(ProviderError::Auth("invalid x-api-key".into())),
+                "busy" => Err(ProviderError::RateLimited {
+                    retry_after_ms: None,
+                }),
+                key => Ok(Message::user(key)),
+            }
+        }
+    }
+
+    async fn call(ring: &'static KeyRing, api_key: &str) -> Result<String, ProviderError> {
+        let provider = KeyRotatingProvider {
+            inner: Box::new(EchoProvider),
+            ring,
+        };
+        let config = StreamConfig {
+            model: "m".into(),
+            system_prompt: String::new(),
+            messages: Vec::new(),
+            tools: Vec::new(),
+            thinking_level: ThinkingLevel::Off,
+            api_key: api_key.into(),
+            max_tokens: None,
+            temperature: None,
+            model_config: None,
+            cache_config: CacheConfig::default(),
+        };
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let message = provider
+            .stream(config, tx, tokio_util::sync::CancellationToken::new())
+            .await?;
+        match message {
+            Message::User { content, .. } => match &content[0] {
+                Content::Text { text } => Ok(text.clone()),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover_sets_aside_failed_keys() {
+        let ring = ring("api_key = \"bad\"\napi_keys = [\"busy\", \"good\"]");
+        assert_eq!(call(ring, "bad").await.unwrap(), "good");
+        assert_eq!(ring.candidates("bad").unwrap(), vec!["good"]);
+        // Keys other than the configured one are left alone
+        assert_eq!(call(ring, "worker-key").await.unwrap(), "worker-key");
+    }
+
+    #[tokio::test]
+    async fn test_empty_key_is_not_rotated() {
+        let ring = ring("api_key = \"\"\napi_keys = [\"good\"]");
+        assert_eq!(ring.candidates(""), None);
+        assert_eq!(call(ring, "").await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let ring =
+            ring("api_key = \"a\"\napi_keys = [\"b\", \"c\"]\nkey_rotation = \"round_robin\"");
+        let mut used = Vec::new();
+        for _ in 0..4 {
+            used.push(call(ring, "a").await.unwrap());
+        }
+        assert_eq!(used, vec!["a", "b", "c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_all_keys_failing() {
+        let ring = ring("api_key = \"bad\"\napi_keys = [\"busy\"]");
+        assert!(matches!(
+            call(ring, "bad").await,
+            Err(ProviderError::RateLimited { .. })
+        ));
+        // Only the key back soonest is tried while all are set aside
+        assert_eq!(ring.candidates("bad").unwrap(), vec!["busy"]);
+    }
+
+    #[tokio::test]
+    async fn test_reload_replaces_keys() {
+        let ring = ring("api_key = \"old\"");
+        ring.configure(&agent("api_key = \"new\""));
+        // Agents built with the old key use the new one
+        assert_eq!(call(ring, "old").await.unwrap(), "new");
+    }
+}
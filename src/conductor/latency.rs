@@ -0,0 +1,150 @@
+//! Latency budget of an interactive turn (`[channels.<name>.latency]`).
+//!
+//! Users otherwise watch "..." for as long as a turn takes. The message
+//! handler starts a watchdog next to each turn: once `notice_secs` pass it
+//! posts a status naming the tools that are running, and once `max_secs`
+//! pass it calls [`TurnWatch::hand_off`]. The agent then stops before its
+//! next model call, the conductor drops the unfinished turn and queues the
+//! message as a background task, which delivers the answer later.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use yoagent::types::*;
+
+/// What the message in flight is doing. Shared between the conductor, the
+/// agent's turn callbacks and the message handler's watchdog.
+#[derive(Default)]
+pub struct TurnWatch {
+    /// Tools called by the last model response, until the next model call.
+    running: Mutex<Vec<String>>,
+    handoff_allowed: AtomicBool,
+    handoff_requested: AtomicBool,
+}
+
+impl TurnWatch {
+    /// Start watching a new message. It can't be handed off until
+    /// `allow_handoff`.
+    pub fn reset(&self) {
+        self.running.lock().unwrap().clear();
+        self.handoff_allowed.store(false, Ordering::SeqCst);
+        self.handoff_requested.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether the message may be handed off: background tasks are enabled
+    /// and the session isn't private.
+    pub fn allow_handoff(&self, allowed: bool) {
+        self.handoff_allowed.store(allowed, Ordering::SeqCst);
+    }
+
+    /// Tools the agent is waiting on, empty while the model is responding.
+    pub fn running_tools(&self) -> Vec<String> {
+        self.running.lock().unwrap().clone()
+    }
+
+    /// Stop the turn before its next model call and move the message to a
+    /// background task, if allowed.
+    pub fn hand_off(&self) {
+        self.handoff_requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn handed_off(&self) -> bool {
+        self.handoff_requested.load(Ordering::SeqCst) && self.handoff_allowed.load(Ordering::SeqCst)
+    }
+
+    /// From the agent's before-turn callback: a model call is starting.
+    pub(crate) fn model_call(&self) {
+        self.running.lock().unwrap().clear();
+    }
+
+    /// From the agent's after-turn callback: the tools the response called.
+    pub(crate) fn tools_called(&self, messages: &[AgentMessage]) {
+        *self.running.lock().unwrap() = tool_calls(messages);
+    }
+}
+
+/// Names of the tools called by the last assistant message, once each.
+fn tool_calls(messages: &[AgentMessage]) -> Vec<String> {
+    let content = messages.iter().rev().find_map(|msg| match msg {
+        AgentMessage::Llm(Message::Assistant { content, .. }) => Some(content),
+        _ => None,
+    });
+    let mut names: Vec<String> = Vec::new();
+    for c in content.into_iter().flatten() {
+        if let Content::ToolCall { name, .. } = c {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    names
+}
+
+/// Status posted once a turn passes `notice_secs`.
+pub fn status_message(elapsed: Duration, tools: &[String]) -> String {
+    let secs = elapsed.as_secs();
+    if tools.is_empty() {
+        format!("Still working on it ({}s so far)…", secs)
+    } else {
+        format!(
+            "Still working on it ({}s so far), running {}…",
+            secs,
+            tools.join(", ")
+        )
+    }
+}
+
+/// Reply to a message handed off to background task `id`.
+pub fn handoff_reply(id: i64) -> String {
+    format!(
+        "This is taking longer than expected, so I've moved it to background task #{}. \
+         I'll send the result here when it's done; check on it with /jobs.",
+        id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str) -> Content {
+        Content::ToolCall {
+            id: format!("tc-{}", name),
+            name: name.to_string(),
+            arguments: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn test_turn_watch() {
+        let watch = TurnWatch::default();
+        let response = AgentMessage::Llm(Message::Assistant {
+            content: vec![call("shell"), call("web_fetch"), call("shell")],
+            stop_reason: StopReason::ToolUse,
+            model: "mock".to_string(),
+            provider: "mock".to_string(),
+            usage: Usage::default(),
+            timestamp: 0,
+            error_message: None,
+        });
+        let messages = vec![AgentMessage::Llm(Message::user("build it")), response];
+        watch.reset();
+        watch.tools_called(&messages);
+        assert_eq!(watch.running_tools(), vec!["shell", "web_fetch"]);
+        assert_eq!(
+            status_message(Duration::from_secs(20), &watch.running_tools()),
+            "Still working on it (20s so far), running shell, web_fetch…"
+        );
+        watch.model_call();
+        assert!(watch.running_tools().is_empty());
+
+        // Requested before the conductor knows whether it may
+        watch.hand_off();
+        assert!(!watch.handed_off());
+        watch.allow_handoff(true);
+        assert!(watch.handed_off());
+        watch.reset();
+        watch.allow_handoff(true);
+        assert!(!watch.handed_off());
+    }
+}
@@ -0,0 +1,276 @@
+//! Per-message limits on worker sub-agents.
+//!
+//! `max_concurrent` only bounds how many dynamic workers run at once; a model
+//! could still start them one after another, or chain them, for as long as
+//! the turn lasts. [`WorkerLimits`] is shared by every worker entry point
+//! (configured workers through [`LimitedWorker`], `spawn_worker` directly) and
+//! reset for each message. It caps nesting depth, the number of workers
+//! started, and the tokens they use, which [`MeteredProvider`] counts. Each
+//! refusal is written to the audit log as a `worker_limit` event.
+
+use crate::config::WorkersConfig;
+use crate::db::Db;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use yoagent::provider::{ProviderError, StreamConfig, StreamEvent, StreamProvider};
+use yoagent::types::*;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum LimitHit {
+    #[error("Worker depth limit reached ({max}); workers at this level can't start more workers")]
+    Depth { max: usize },
+    #[error("Worker limit for this message reached ({max} started)")]
+    Spawns { max: usize },
+    #[error("Worker token budget for this message used up ({used}/{max} tokens)")]
+    Tokens { used: u64, max: u64 },
+}
+
+/// Worker usage while answering the current message.
+pub struct WorkerLimits {
+    max_depth: usize,
+    max_spawns: usize,
+    max_tokens: Option<u64>,
+    spawns: AtomicUsize,
+    tokens: AtomicU64,
+    db: Db,
+    session_id: Arc<RwLock<String>>,
+}
+
+impl WorkerLimits {
+    pub fn new(config: &WorkersConfig, db: Db, session_id: Arc<RwLock<String>>) -> Self {
+        Self {
+            max_depth: config.max_depth,
+            max_spawns: config.max_spawns_per_message,
+            max_tokens: config.max_tokens_per_message,
+            spawns: AtomicUsize::new(0),
+            tokens: AtomicU64::new(0),
+            db,
+            session_id,
+        }
+    }
+
+    /// Start counting for a new message.
+    pub fn reset(&self) {
+        self.spawns.store(0, Ordering::SeqCst);
+        self.tokens.store(0, Ordering::SeqCst);
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Tokens used by workers for the current message.
+    pub fn tokens_used(&self) -> u64 {
+        self.tokens.load(Ordering::SeqCst)
+    }
+
+    pub fn record_tokens(&self, tokens: u64) {
+        self.tokens.fetch_add(tokens, Ordering::SeqCst);
+    }
+
+    /// Count a worker starting at `depth` (1 when started by the main agent),
+    /// or refuse it and audit why.
+    pub async fn admit(&self, worker: &str, depth: usize) -> Result<(), LimitHit> {
+        let result = self.check(depth);
+        if let Err(ref hit) = result {
+            tracing::warn!("Refused worker '{}': {}", worker, hit);
+            let session = self.session_id.read().unwrap().clone();
+            let _ = self
+                .db
+                .audit_log(
+                    Some(&session),
+                    "worker_limit",
+                    Some(worker),
+                    Some(&hit.to_string()),
+                    0,
+                )
+                .await;
+        }
+        result
+    }
+
+    fn check(&self, depth: usize) -> Result<(), LimitHit> {
+        if depth > self.max_depth {
+            return Err(LimitHit::Depth {
+                max: self.max_depth,
+            });
+        }
+        if let Some(max) = self.max_tokens {
+            let used = self.tokens_used();
+            if used >= max {
+                return Err(LimitHit::Tokens { used, max });
+            }
+        }
+        if self.spawns.fetch_add(1, Ordering::SeqCst) >= self.max_spawns {
+            self.spawns.fetch_sub(1, Ordering::SeqCst);
+            return Err(LimitHit::Spawns {
+                max: self.max_spawns,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Configured worker that counts against [`WorkerLimits`] before running,
+/// and whose runs are recorded in the [`RunLog`](super::worker_runs::RunLog).
+pub struct LimitedWorker {
+    pub inner: Box<dyn AgentTool>,
+    pub limits: Arc<WorkerLimits>,
+    pub runs: Arc<super::worker_runs::RunLog>,
+}
+
+#[async_trait::async_trait]
+impl AgentTool for LimitedWorker {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn label(&self) -> &str {
+        self.inner.label()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.inner.parameters_schema()
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.limits
+            .admit(self.inner.name(), 1)
+            .await
+            .map_err(|hit| ToolError::Failed(hit.to_string()))?;
+        let task = params["task"].as_str().unwrap_or_default().to_string();
+        let tool_call_id = ctx.tool_call_id.clone();
+        self.runs
+            .record(
+                self.inner.name(),
+                &task,
+                &tool_call_id,
+                self.inner.execute(params, ctx),
+            )
+            .await
+    }
+}
+
+/// Worker provider that adds each response's token usage to [`WorkerLimits`].
+pub struct MeteredProvider {
+    pub inner: Arc<dyn StreamProvider>,
+    pub limits: Arc<WorkerLimits>,
+}
+
+#[async_trait::async_trait]
+impl StreamProvider for MeteredProvider {
+    async fn stream(
+        &self,
+        config: StreamConfig,
+        tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Message, ProviderError> {
+        let message = self.inner.stream(config, tx, cancel).await?;
+        if let Message::Assistant { ref usage, .. } = message {
+            self.limits.record_tokens(usage.input + usage.output);
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(config: WorkersConfig, db: &Db) -> WorkerLimits {
+        WorkerLimits::new(&config, db.clone(), Arc::new(RwLock::new("tg-1".into())))
+    }
+
+    #[tokio::test]
+    async fn test_worker_limits() {
+        let db = Db::open_memory().unwrap();
+        let limits = limits(
+            WorkersConfig {
+                max_depth: 2,
+                max_spawns_per_message: 2,
+                max_tokens_per_message: Some(1_000),
+                ..Default::default()
+            },
+            &db,
+        );
+
+        assert_eq!(limits.admit("a", 1).await, Ok(()));
+        assert_eq!(
+            limits.admit("deep", 3).await,
+            Err(LimitHit::Depth { max: 2 })
+        );
+        assert_eq!(limits.admit("b", 2).await, Ok(()));
+        assert_eq!(limits.admit("c", 1).await, Err(LimitHit::Spawns { max: 2 }));
+
+        // A new message starts from zero
+        limits.reset();
+        limits.record_tokens(1_200);
+        assert_eq!(
+            limits.admit("d", 1).await,
+            Err(LimitHit::Tokens {
+                used: 1_200,
+                max: 1_000
+            })
+        );
+
+        let refused: Vec<_> = db
+            .audit_session_events("tg-1")
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|e| e.event_type == "worker_limit")
+            .collect();
+        assert_eq!(refused.len(), 3);
+        assert_eq!(refused[0].tool_name.as_deref(), Some("deep"));
+    }
+
+    #[tokio::test]
+    async fn test_limited_worker_runs_through_metered_provider() {
+        use yoagent::provider::MockProvider;
+
+        let db = Db::open_memory().unwrap();
+        let limits = Arc::new(limits(WorkersConfig::default(), &db));
+        let worker = LimitedWorker {
+            inner: Box::new(
+                yoagent::sub_agent::SubAgentTool::new(
+                    "research",
+                    Arc::new(MeteredProvider {
+                        inner: Arc::new(MockProvider::text("done")),
+                        limits: limits.clone(),
+                    }),
+                )
+                .with_model("mock")
+                .with_api_key("test"),
+            ),
+            limits: limits.clone(),
+            runs: Arc::new(crate::conductor::worker_runs::RunLog::new(
+                db.clone(),
+                Arc::new(RwLock::new("tg-1".into())),
+                Arc::default(),
+                &Default::default(),
+            )),
+        };
+        let ctx = ToolContext {
+            tool_call_id: "tc-1".into(),
+            tool_name: "research".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        };
+        worker
+            .execute(serde_json::json!({"task": "look it up"}), ctx)
+            .await
+            .unwrap();
+        assert_eq!(limits.spawns.load(Ordering::SeqCst), 1);
+        let runs = db.worker_run_list(Some("tg-1"), false, 10).await.unwrap();
+        assert_eq!(runs[0].task, "look it up");
+    }
+}
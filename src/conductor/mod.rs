@@ -1,6 +1,27 @@
+pub mod ask;
+pub mod citations;
 pub mod compaction;
+pub mod daemon;
 pub mod delegate;
+pub mod endpoint;
+pub mod failure;
+pub mod fallback;
+pub mod fixture;
+pub mod keys;
+pub mod latency;
+pub mod limits;
+pub mod native_tools;
+pub mod oversize;
+pub mod persona;
+pub mod pool;
+pub mod preamble;
+pub mod progress;
+pub mod snapshot;
+pub mod temperature;
+pub mod thinking;
+pub mod tool_summary;
 pub mod tools;
+pub mod worker_runs;
 
 use crate::config::Config;
 use crate::db::Db;
@@ -8,7 +29,9 @@ use crate::security::budget::BudgetTracker;
 use crate::security::{self, SecurityPolicy};
 use crate::skills::LoadedSkill;
 use delegate::WorkerInfo;
+use failure::ProcessError;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use yoagent::provider;
 use yoagent::types::*;
@@ -17,6 +40,12 @@ use yoagent::Agent;
 /// Callback type for streaming text chunks to the client.
 pub type OnStreamChunk = Box<dyn Fn(&str) + Send + Sync>;
 
+/// Replaces tool output in the tape of private sessions once the turn is over.
+const PRIVATE_TOOL_OUTPUT: &str = "[tool output not retained in private session]";
+
+/// Shown in place of the streamed response while waiting out provider overload.
+const RETRY_NOTICE: &str = "The model is busy, retrying…";
+
 /// The Conductor owns the yoagent Agent and mediates all interactions.
 pub struct Conductor {
     agent: Agent,
@@ -40,83 +69,290 @@ pub struct Conductor {
     injection_heuristic_threshold: f64,
     injection_llm_judge_threshold: f64,
     injection_extra_patterns: Vec<String>,
+    heuristic_scorer: crate::security::heuristics::HeuristicScorer,
+    /// Whether the current session is in private mode. Shared with memory
+    /// tools and compaction so they skip memory writes.
+    private_ref: Arc<AtomicBool>,
+    /// Model from `agent.model`, restored after a routing override.
+    default_model: String,
+    /// Model the agent is currently using.
+    active_model: String,
+    /// Temperature from `agent.temperature`, used without a session override.
+    default_temperature: Option<f32>,
+    /// Thinking level per message, from `agent.thinking` and `[agent.thinking_rules]`.
+    thinking: thinking::ThinkingRules,
+    /// Thinking level the agent is currently using.
+    active_thinking: ThinkingLevel,
+    /// Backoff policy for overloaded provider responses.
+    retry: crate::config::RetryConfig,
+    /// Providers to switch to while the configured one is overloaded.
+    fallbacks: Option<fallback::FallbackChain>,
+    /// Token prices for `/stats` cost estimates.
+    pricing: Option<crate::config::PricingConfig>,
+    /// Run the first-run interview on the first DM of a fresh database.
+    onboarding: bool,
+    /// Stream callback of the message in flight, for partial tool output.
+    progress_sink: progress::ProgressSink,
+    /// Accept `/background` and `/jobs`.
+    background: bool,
+    /// Security denials already explained to the agent for this message.
+    denial_hints: security::DenialHints,
+    /// Skill the agent follows in the message in flight.
+    active_skill: tools::ActiveSkill,
+    /// Memory namespaces of the enabled skills, shared with the memory tools.
+    skill_namespaces: tools::SkillNamespaces,
+    /// Files of the enabled skills, shared with `read_file`.
+    skill_files: tools::SkillFiles,
+    /// Provider from `agent.provider`, for failure records.
+    provider_name: String,
+    /// Turns, tokens and last tool of the message in flight.
+    run_stats: failure::RunStatsRef,
+    /// Size limit for a single incoming message.
+    oversize: oversize::OversizePolicy,
+    /// `ask_user` questions waiting for the user's answer.
+    questions: ask::Questions,
+    /// Tool calls waiting for the user's approval.
+    approvals: security::approval::Approvals,
+    /// Web sources the last reply drew on.
+    sources: Vec<String>,
+    /// Worker depth, spawn and token limits for the message in flight.
+    worker_limits: Arc<limits::WorkerLimits>,
+    /// Transcripts of worker runs, for direct delegation.
+    worker_runs: Arc<worker_runs::RunLog>,
+    /// Persona file, used without persona fragments.
+    persona: String,
+    /// `[[agent.persona_fragments]]`, rendered for every message.
+    persona_fragments: Vec<crate::config::PersonaFragmentConfig>,
+    skills_prompt: String,
+    /// System prompt last given to the agent: persona, skills and the
+    /// selected project's context.
+    system_prompt: String,
+    /// Facts for the per-turn preamble; None with `[agent.preamble]` disabled.
+    turn_facts: Option<preamble::TurnFactsRef>,
+    /// `[agent.preamble] timezone`, ahead of the onboarding answer.
+    timezone: Option<chrono_tz::Tz>,
+    /// Images sent with the next message, shown to the model with its text.
+    images: Vec<Content>,
+    /// Running tools and latency handoff of the message in flight.
+    turn_watch: Arc<latency::TurnWatch>,
+    /// Queue entry of the next message, marked answered along with its tape.
+    queue_entry: Option<i64>,
 }
 
 impl Conductor {
     /// Create a new Conductor from config.
     pub async fn new(config: &Config, db: Db) -> Result<Self, anyhow::Error> {
+        Self::build(config, db, None, None).await
+    }
+
+    /// Create a Conductor, sharing questions, approvals and the daily token
+    /// count with the other conductors of a pool if `shared` is given, and
+    /// talking to `provider` instead of `[agent] provider` if given.
+    async fn build(
+        config: &Config,
+        db: Db,
+        shared: Option<&pool::Shared>,
+        provider: Option<DynProvider>,
+    ) -> Result<Self, anyhow::Error> {
         // 1. Load persona
-        let persona_path = config.persona_path();
-        let persona = if persona_path.exists() {
-            std::fs::read_to_string(&persona_path)?
-        } else {
-            "You are a helpful AI assistant.".to_string()
-        };
+        let persona_file = persona::load_file(config)?;
 
         // 2. Load skills with capability filtering
         let skills_dirs = config.skills_dirs();
         let skills_refs: Vec<&std::path::Path> = skills_dirs.iter().map(|p| p.as_path()).collect();
-        let policy = SecurityPolicy::from_config(&config.security);
-        let (skills_prompt, loaded_skills) =
-            crate::skills::load_filtered_skills(&skills_refs, &policy);
+        let policy =
+            SecurityPolicy::from_config(&config.security).with_readable_dir(config.uploads_dir());
+        let (_, loaded_skills) = crate::skills::load_filtered_skills(&skills_refs, &policy);
+        let skills_prompt = crate::skills::prompt(&loaded_skills, &config.agent.disabled_skills);
         let policy_ref = Arc::new(std::sync::RwLock::new(policy));
 
         if !loaded_skills.is_empty() {
             tracing::info!("Loaded {} skill(s)", loaded_skills.len());
         }
 
-        // Append skills to persona
-        let persona = if skills_prompt.is_empty() {
-            persona
+        // Append skills to persona. Fragments are rendered again for every
+        // message; this is the prompt until the first one
+        let initial_prompt = if config.agent.persona_fragments.is_empty() {
+            persona::with_skills(&persona_file, &skills_prompt)
         } else {
-            format!("{}\n\n{}", persona, skills_prompt)
+            let rendered = persona::render(&config.agent.persona_fragments, None, &db).await?;
+            persona::with_skills(&rendered, &skills_prompt)
         };
 
         // 3. Build tools
         let session_id_ref = Arc::new(std::sync::RwLock::new(String::new()));
-        let mut tool_list: Vec<Box<dyn AgentTool>> = yoagent::tools::default_tools();
-        tool_list.push(Box::new(tools::MemorySearchTool::new(db.clone())));
-        tool_list.push(Box::new(tools::MemoryStoreTool::new(db.clone())));
-        tool_list.push(Box::new(crate::scheduler::tools::CronScheduleTool::new(
+        let private_ref = Arc::new(AtomicBool::new(false));
+        let enabled_skills = crate::skills::enabled(&loaded_skills, &config.agent.disabled_skills);
+        let skill_namespaces: tools::SkillNamespaces = Arc::new(std::sync::RwLock::new(
+            crate::skills::memory_namespaces(&enabled_skills),
+        ));
+        let skill_files: tools::SkillFiles = Arc::new(std::sync::RwLock::new(
+            crate::skills::skill_files(&enabled_skills),
+        ));
+        let active_skill = tools::ActiveSkill::default();
+        let profiles: security::sandbox::Profiles = Arc::new(config.profiles.clone());
+        let shell = config.security.tools.get("shell");
+        let tool_list: Vec<Box<dyn AgentTool>> = security::sandbox::apply_profiles(
+            security::shell_env::apply_env(
+                yoagent::tools::default_tools(),
+                shell.and_then(|perm| perm.env.as_ref()),
+            ),
+            &profiles,
+            shell.and_then(|perm| perm.profile.clone()),
+            crate::skills::skill_profiles(&loaded_skills),
+            active_skill.clone(),
+        );
+        let mut tool_list =
+            tools::track_skill_reads(tool_list, skill_files.clone(), active_skill.clone());
+        tool_list.push(Box::new(
+            tools::MemorySearchTool::new(db.clone())
+                .with_skill_namespaces(skill_namespaces.clone())
+                .with_active_skill(active_skill.clone()),
+        ));
+        tool_list.push(Box::new(
+            tools::MemoryStoreTool::new(db.clone())
+                .with_private_flag(private_ref.clone())
+                .with_skill_namespaces(skill_namespaces.clone())
+                .with_active_skill(active_skill.clone())
+                .with_session_ref(session_id_ref.clone()),
+        ));
+        tool_list.push(Box::new(
+            crate::scheduler::tools::CronScheduleTool::new(db.clone(), session_id_ref.clone())
+                .with_job_rules(crate::scheduler::cron::JobRules::new(config)),
+        ));
+        tool_list.push(Box::new(tools::SendMessageTool));
+        let questions = shared.map(|s| s.questions.clone()).unwrap_or_default();
+        if config.agent.ask_user.enabled {
+            tool_list.push(Box::new(ask::AskUserTool::new(
+                questions.clone(),
+                session_id_ref.clone(),
+                std::time::Duration::from_secs(config.agent.ask_user.timeout_secs),
+            )));
+        }
+        if config.background.enabled {
+            tool_list.push(Box::new(crate::scheduler::tools::BackgroundTaskTool::new(
+                db.clone(),
+                session_id_ref.clone(),
+                private_ref.clone(),
+            )));
+        }
+        tool_list.extend(native_tools::build_native_tools(
+            &config.agent.native_tools,
+            &config.agent.provider,
+            &config.agent.model,
+            &config.agent.api_key,
             db.clone(),
             session_id_ref.clone(),
-        )));
-        tool_list.push(Box::new(tools::SendMessageTool));
+        ));
 
-        // 4. Wrap with security
-        let mut wrapped_tools = security::wrap_tools(
+        // 4. Wrap with security, then output summaries, then progress streaming
+        let progress_sink: progress::ProgressSink = Arc::new(std::sync::RwLock::new(None));
+        let denial_hints = security::DenialHints::default();
+        let approvals = match shared {
+            Some(s) => s.approvals.clone(),
+            None => security::approval::Approvals::new(std::time::Duration::from_secs(
+                config.security.approval.timeout_secs,
+            )),
+        };
+        let run_stats = failure::RunStatsRef::default();
+        let wrapped_tools = security::wrap_tools(
             tool_list,
             policy_ref.clone(),
             db.clone(),
             session_id_ref.clone(),
+            denial_hints.clone(),
+            approvals.clone(),
+        );
+        let wrapped_tools = tool_summary::wrap_tools(
+            wrapped_tools,
+            tool_summary::ToolSummaryPolicy::from_config(config),
+        );
+        let mut wrapped_tools = progress::wrap_tools(
+            wrapped_tools,
+            &config.agent.tool_progress,
+            progress_sink.clone(),
         );
 
         // 5. Build budget tracker
-        let budget = BudgetTracker::new(
-            config.agent.budget.max_tokens_per_day,
-            config.agent.budget.max_turns_per_session,
-            db.clone(),
-        );
-        budget.load_from_db().await?;
+        let budget = match shared {
+            Some(s) => s.budget.sharing_tokens(),
+            None => {
+                let budget = BudgetTracker::new(
+                    config.agent.budget.max_tokens_per_day,
+                    config.agent.budget.max_turns_per_session,
+                    db.clone(),
+                )
+                .with_max_cost_per_day(config.agent.budget.max_cost_per_day);
+                budget.load_from_db().await?;
+                budget
+            }
+        };
 
         // 6. Build worker sub-agents from config
         // Workers get security-wrapped tools so their internal tool calls are
         // audit-logged and policy-checked (Gap 2 fix)
         let worker_tools: Vec<Arc<dyn AgentTool>> = vec![
             Arc::new(security::SecureToolWrapper {
-                inner: Box::new(tools::MemorySearchTool::new(db.clone())),
+                inner: Box::new(
+                    tools::MemorySearchTool::new(db.clone())
+                        .with_skill_namespaces(skill_namespaces.clone())
+                        .with_active_skill(active_skill.clone()),
+                ),
                 policy: policy_ref.clone(),
                 db: db.clone(),
                 session_id: session_id_ref.clone(),
+                hints: denial_hints.clone(),
+                approvals: approvals.clone(),
             }),
             Arc::new(security::SecureToolWrapper {
-                inner: Box::new(tools::MemoryStoreTool::new(db.clone())),
+                inner: Box::new(
+                    tools::MemoryStoreTool::new(db.clone())
+                        .with_private_flag(private_ref.clone())
+                        .with_skill_namespaces(skill_namespaces.clone())
+                        .with_active_skill(active_skill.clone()),
+                ),
                 policy: policy_ref.clone(),
                 db: db.clone(),
                 session_id: session_id_ref.clone(),
+                hints: denial_hints.clone(),
+                approvals: approvals.clone(),
             }),
         ];
-        let workers = delegate::build_workers(config, &worker_tools);
+        // Workers with a profile get a shell confined to it
+        let worker_shells: HashMap<String, Arc<dyn AgentTool>> = config
+            .agent
+            .workers
+            .named
+            .values()
+            .filter_map(|worker| worker.profile.clone())
+            .map(|profile| {
+                let tool: Arc<dyn AgentTool> = Arc::new(security::SecureToolWrapper {
+                    inner: Box::new(security::sandbox::ProfiledShellTool::confined(
+                        &profile,
+                        profiles.clone(),
+                    )),
+                    policy: policy_ref.clone(),
+                    db: db.clone(),
+                    session_id: session_id_ref.clone(),
+                    hints: denial_hints.clone(),
+                    approvals: approvals.clone(),
+                });
+                (profile, tool)
+            })
+            .collect();
+        let worker_limits = Arc::new(limits::WorkerLimits::new(
+            &config.agent.workers,
+            db.clone(),
+            session_id_ref.clone(),
+        ));
+        let worker_runs = Arc::new(worker_runs::RunLog::new(
+            db.clone(),
+            session_id_ref.clone(),
+            private_ref.clone(),
+            &config.persistence,
+        ));
+        let workers =
+            delegate::build_workers(config, &worker_tools, &worker_shells, Some(&worker_limits));
         let worker_infos: Vec<WorkerInfo> = workers.iter().map(|(_, info)| info.clone()).collect();
 
         if !worker_infos.is_empty() {
@@ -127,7 +363,8 @@ impl Conductor {
         // No outer SecureToolWrapper here — the SubAgentTool's inner tools are already
         // security-wrapped via worker_tools, and wrapping the SubAgentTool itself would
         // produce misleading audit entries under the worker name (e.g., "coding").
-        let direct_workers_raw = delegate::build_workers(config, &worker_tools);
+        let direct_workers_raw =
+            delegate::build_workers(config, &worker_tools, &worker_shells, None);
         let mut direct_workers: HashMap<String, Box<dyn AgentTool>> = HashMap::new();
         for (sub_agent, info) in direct_workers_raw {
             direct_workers.insert(info.name.clone(), Box::new(sub_agent));
@@ -137,16 +374,31 @@ impl Conductor {
         // are audit-logged and security-checked (Gap 1 fix)
         for (sub_agent, _info) in workers {
             wrapped_tools.push(Box::new(security::SecureToolWrapper {
-                inner: Box::new(sub_agent),
+                inner: Box::new(limits::LimitedWorker {
+                    inner: Box::new(sub_agent),
+                    limits: worker_limits.clone(),
+                    runs: worker_runs.clone(),
+                }),
                 policy: policy_ref.clone(),
                 db: db.clone(),
                 session_id: session_id_ref.clone(),
+                hints: denial_hints.clone(),
+                approvals: approvals.clone(),
             }));
         }
 
         // 6b. Add dynamic worker tools (spawn_worker, list_workers, remove_worker)
         let dynamic_worker_active = Arc::new(std::sync::atomic::AtomicUsize::new(0));
-        let dynamic_provider = delegate::resolve_arc_provider(&config.agent.provider);
+        let dynamic_provider: Arc<dyn provider::StreamProvider> =
+            Arc::new(worker_runs::RecordingProvider {
+                inner: Arc::new(limits::MeteredProvider {
+                    inner: delegate::resolve_arc_provider(
+                        &config.agent.provider,
+                        &endpoint::Endpoint::agent(&config.agent),
+                    ),
+                    limits: worker_limits.clone(),
+                }),
+            });
         let spawn_tool = tools::SpawnWorkerTool::new(tools::SpawnWorkerConfig {
             db: db.clone(),
             provider: dynamic_provider,
@@ -156,57 +408,130 @@ impl Conductor {
             active_count: dynamic_worker_active,
             max_concurrent: config.agent.workers.max_concurrent,
             max_turns: config.agent.workers.max_worker_turns,
+            limits: worker_limits.clone(),
+            runs: worker_runs.clone(),
         });
         wrapped_tools.push(Box::new(security::SecureToolWrapper {
             inner: Box::new(spawn_tool),
             policy: policy_ref.clone(),
             db: db.clone(),
             session_id: session_id_ref.clone(),
+            hints: denial_hints.clone(),
+            approvals: approvals.clone(),
         }));
         wrapped_tools.push(Box::new(security::SecureToolWrapper {
             inner: Box::new(tools::ListWorkersTool::new(db.clone())),
             policy: policy_ref.clone(),
             db: db.clone(),
             session_id: session_id_ref.clone(),
+            hints: denial_hints.clone(),
+            approvals: approvals.clone(),
         }));
         wrapped_tools.push(Box::new(security::SecureToolWrapper {
             inner: Box::new(tools::RemoveWorkerTool::new(db.clone())),
             policy: policy_ref.clone(),
             db: db.clone(),
             session_id: session_id_ref.clone(),
+            hints: denial_hints.clone(),
+            approvals: approvals.clone(),
         }));
 
-        // 7. Resolve provider
-        let provider = resolve_provider(&config.agent.provider);
+        // 7. Resolve provider and its fallbacks, with current time and channel
+        // facts in front of each turn's user message. Its API keys come from
+        // the key ring
+        keys::configure(&config.agent);
+        let provider = provider.unwrap_or_else(|| {
+            resolve_provider(
+                &config.agent.provider,
+                &endpoint::Endpoint::agent(&config.agent),
+            )
+        });
+        let (mut provider, fallbacks) = fallback::wrap(provider, &config.agent);
+        let turn_facts = if config.agent.preamble.enabled {
+            let facts: preamble::TurnFactsRef = Default::default();
+            provider = DynProvider(Box::new(preamble::PreambleProvider {
+                inner: Box::new(provider),
+                facts: facts.clone(),
+            }));
+            Some(facts)
+        } else {
+            None
+        };
+
+        // 7a. Per-turn snapshots for `yoclaw debug turn`
+        let recorder = if config.persistence.turn_snapshots {
+            Some(Arc::new(snapshot::TurnRecorder::new(
+                db.clone(),
+                session_id_ref.clone(),
+                private_ref.clone(),
+                &initial_prompt,
+                &wrapped_tools,
+                snapshot_filters(config),
+                config.persistence.turn_snapshot_days,
+            )))
+        } else {
+            None
+        };
 
         // 8. Build agent — workers are included in wrapped_tools, no with_sub_agent needed
         let budget_check = budget.clone();
         let budget_record = budget.clone();
         let db_usage = db.clone();
         let session_id_usage = session_id_ref.clone();
+        let prices = crate::pricing::Prices::from_config(&config.agent);
+        let (provider_usage, model_usage) =
+            (config.agent.provider.clone(), config.agent.model.clone());
+        let run_stats_turn = run_stats.clone();
+        let recorder_before = recorder.clone();
+        let recorder_after = recorder.clone();
+        let turn_watch = Arc::new(latency::TurnWatch::default());
+        let watch_before = turn_watch.clone();
+        let watch_after = turn_watch.clone();
         let mut agent = Agent::new(provider)
-            .with_system_prompt(&persona)
+            .with_system_prompt(&initial_prompt)
             .with_model(&config.agent.model)
             .with_api_key(&config.agent.api_key)
             .with_tools(wrapped_tools)
-            .on_before_turn(move |_messages, _turn| budget_check.can_continue())
-            .on_after_turn(move |_messages, usage| {
+            .on_before_turn(move |messages, _turn| {
+                // A handed-off message continues as a background task
+                let proceed = budget_check.can_continue() && !watch_before.handed_off();
+                if proceed {
+                    watch_before.model_call();
+                }
+                if let (true, Some(ref recorder)) = (proceed, &recorder_before) {
+                    recorder.before_turn(messages);
+                }
+                proceed
+            })
+            .on_after_turn(move |messages, usage| {
+                watch_after.tools_called(messages);
                 budget_record.record_usage(usage.input, usage.output);
                 budget_record.record_turn();
+                if let Some(ref recorder) = recorder_after {
+                    recorder.after_turn(messages, usage);
+                }
+                {
+                    let mut stats = run_stats_turn.lock().unwrap();
+                    stats.turns += 1;
+                    stats.tokens += usage.input + usage.output;
+                    if let Some(tool) = last_tool_call(messages) {
+                        stats.tool = Some(tool);
+                    }
+                }
                 // Persist token usage to audit table so budget survives restarts
                 let total = usage.input + usage.output;
                 if total > 0 {
                     let sid = session_id_usage.read().unwrap().clone();
-                    let ts = crate::db::now_ms() as i64;
+                    let sender = budget_record.sender();
+                    let detail = format!("in={} out={}", usage.input, usage.output);
+                    let (provider, model) = last_model(messages)
+                        .unwrap_or((provider_usage.as_str(), model_usage.as_str()));
+                    let cost = prices.cost(provider, model, usage.input, usage.output);
+                    if let Some(cost) = cost {
+                        budget_record.record_cost(cost);
+                    }
                     let _ = tokio::task::block_in_place(|| {
-                        db_usage.exec_sync(|conn| {
-                            conn.execute(
-                                "INSERT INTO audit (session_id, event_type, tokens_used, timestamp) \
-                                 VALUES (?1, ?2, ?3, ?4)",
-                                rusqlite::params![sid, "llm_usage", total as i64, ts],
-                            )?;
-                            Ok(())
-                        })
+                        db_usage.audit_usage_sync(&sid, sender.as_deref(), &detail, total, cost)
                     });
                 }
             });
@@ -228,10 +553,28 @@ impl Conductor {
                 ctx_config.tool_output_max_lines = max_lines;
             }
             agent = agent.with_context_config(ctx_config);
-            agent = agent.with_compaction_strategy(compaction::MemoryAwareCompaction::new(
-                db.clone(),
-                session_id_ref.clone(),
-            ));
+            let mut strategy =
+                compaction::MemoryAwareCompaction::new(db.clone(), session_id_ref.clone())
+                    .with_private_flag(private_ref.clone());
+            if let Some(ref recorder) = recorder {
+                strategy = strategy.with_turn_recorder(recorder.clone());
+            }
+            if ctx.summarize {
+                strategy = strategy.with_summarizer(crate::scheduler::AgentRunConfig {
+                    provider: config.agent.provider.clone(),
+                    model: ctx
+                        .summary_model
+                        .clone()
+                        .unwrap_or_else(|| config.scheduler.cortex.model.clone()),
+                    api_key: config.agent.api_key.clone(),
+                    endpoint: endpoint::Endpoint::agent(&config.agent),
+                    context: Default::default(),
+                    max_tokens: None,
+                    stream_provider: None,
+                    fallback_providers: Vec::new(),
+                });
+            }
+            agent = agent.with_compaction_strategy(strategy);
             tracing::info!("Context management enabled");
         }
 
@@ -248,7 +591,8 @@ impl Conductor {
                 &inj.extra_patterns,
                 inj.heuristic_threshold,
                 llm_judge_threshold,
-            );
+            )
+            .with_heuristics(&inj.heuristics);
             agent = agent.with_input_filter(detector);
             tracing::info!(
                 "Injection detection enabled (action: {}, heuristic_threshold: {:.2}, llm_judge: {})",
@@ -261,16 +605,14 @@ impl Conductor {
         if let Some(max_tokens) = config.agent.max_tokens {
             agent = agent.with_max_tokens(max_tokens);
         }
-
-        if let Some(ref thinking) = config.agent.thinking {
-            let level = match thinking.as_str() {
-                "off" => ThinkingLevel::Off,
-                "low" => ThinkingLevel::Low,
-                "medium" => ThinkingLevel::Medium,
-                "high" => ThinkingLevel::High,
-                _ => ThinkingLevel::Off,
-            };
-            agent = agent.with_thinking(level);
+        agent.temperature = config.agent.temperature;
+
+        let thinking_rules = thinking::ThinkingRules::compile(
+            config.agent.thinking.as_deref(),
+            config.agent.thinking_rules.as_ref(),
+        )?;
+        if config.agent.thinking.is_some() {
+            agent = agent.with_thinking(thinking_rules.default_level());
         }
 
         // 9. Build optional LLM judge for borderline injection cases
@@ -285,7 +627,10 @@ impl Conductor {
                 .llm_judge_model
                 .as_deref()
                 .unwrap_or("claude-haiku-4-5-20251001");
-            let judge_provider = delegate::resolve_arc_provider(judge_provider_name);
+            let judge_provider = delegate::resolve_arc_provider(
+                judge_provider_name,
+                &endpoint::Endpoint::for_provider(&config.agent, judge_provider_name),
+            );
             tracing::info!("LLM injection judge enabled (model: {})", judge_model);
             Some(crate::security::llm_judge::LlmJudge::new(
                 judge_provider,
@@ -296,6 +641,15 @@ impl Conductor {
             None
         };
 
+        // 10. Size limit for incoming messages: half the context window by default
+        let ctx = &config.agent.context;
+        let max_message_tokens = match (ctx.max_message_tokens, ctx.max_context_tokens) {
+            (Some(max), _) => max as usize,
+            (None, Some(window)) => window as usize / 2,
+            (None, None) => yoagent::context::ContextConfig::default().max_context_tokens / 2,
+        };
+        let oversize = oversize::OversizePolicy::new(max_message_tokens, config.uploads_dir());
+
         Ok(Self {
             agent,
             db,
@@ -318,9 +672,92 @@ impl Conductor {
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
+            heuristic_scorer: crate::security::heuristics::HeuristicScorer::new(
+                &config.security.injection.heuristics,
+            ),
+            private_ref,
+            default_model: config.agent.model.clone(),
+            active_model: config.agent.model.clone(),
+            default_temperature: config.agent.temperature,
+            active_thinking: thinking_rules.default_level(),
+            thinking: thinking_rules,
+            retry: config.agent.retry.clone(),
+            fallbacks,
+            pricing: config.agent.pricing.clone(),
+            onboarding: config.onboarding.enabled,
+            progress_sink,
+            background: config.background.enabled,
+            denial_hints,
+            active_skill,
+            skill_namespaces,
+            skill_files,
+            provider_name: config.agent.provider.clone(),
+            run_stats,
+            oversize,
+            questions,
+            approvals,
+            sources: Vec::new(),
+            worker_limits,
+            worker_runs,
+            persona: persona_file,
+            persona_fragments: config.agent.persona_fragments.clone(),
+            skills_prompt,
+            system_prompt: initial_prompt,
+            turn_facts,
+            timezone: config
+                .agent
+                .preamble
+                .timezone
+                .as_deref()
+                .and_then(|tz| tz.parse().ok()),
+            images: Vec::new(),
+            turn_watch,
+            queue_entry: None,
         })
     }
 
+    /// Running tools and latency handoff of the message in flight, for the
+    /// message handler's latency watchdog.
+    pub fn turn_watch(&self) -> Arc<latency::TurnWatch> {
+        self.turn_watch.clone()
+    }
+
+    /// Show `images` to the model with the next message, as image blocks.
+    /// Dropped if that message is a command answered without the agent.
+    pub fn attach_images(&mut self, images: Vec<Content>) {
+        self.images = images;
+    }
+
+    /// The next message is queue entry `id`: its reply is stored and the
+    /// entry marked answered in the transaction that saves the tape.
+    pub fn set_queue_entry(&mut self, id: i64) {
+        self.queue_entry = Some(id);
+    }
+
+    /// Tokens the last processed message used, its workers' included.
+    pub fn message_tokens(&self) -> u64 {
+        self.run_stats.lock().unwrap().tokens + self.worker_limits.tokens_used()
+    }
+
+    /// Failure context for an error returned while processing a message.
+    pub fn failure_context(&self, error: &anyhow::Error) -> crate::db::queue::QueueFailure {
+        let worker = match error.downcast_ref::<ProcessError>() {
+            Some(ProcessError::Worker { worker, .. }) => self
+                .worker_infos
+                .iter()
+                .find(|w| &w.name == worker)
+                .map(|w| (w.provider.as_str(), w.model.as_str())),
+            _ => None,
+        };
+        failure::describe(
+            error,
+            &self.run_stats.lock().unwrap(),
+            &self.provider_name,
+            &self.active_model,
+            worker,
+        )
+    }
+
     /// Get loaded skills info.
     pub fn loaded_skills(&self) -> &[LoadedSkill] {
         &self.loaded_skills
@@ -332,12 +769,23 @@ impl Conductor {
     }
 
     /// Update budget limits at runtime (hot-reload).
-    pub fn update_budget(&mut self, max_tokens: Option<u64>, max_turns: Option<usize>) {
-        self.budget.update_limits(max_tokens, max_turns);
+    /// The budget the agent's turns count against.
+    pub fn budget(&self) -> &BudgetTracker {
+        &self.budget
+    }
+
+    pub fn update_budget(
+        &mut self,
+        max_tokens: Option<u64>,
+        max_turns: Option<usize>,
+        max_cost: Option<f64>,
+    ) {
+        self.budget.update_limits(max_tokens, max_turns, max_cost);
         tracing::info!(
-            "Budget updated: max_tokens={:?}, max_turns={:?}",
+            "Budget updated: max_tokens={:?}, max_turns={:?}, max_cost={:?}",
             max_tokens,
-            max_turns
+            max_turns,
+            max_cost
         );
     }
 
@@ -353,6 +801,129 @@ impl Conductor {
         self.max_group_catchup = max;
     }
 
+    /// Use `model` for subsequent messages, or go back to the configured model
+    /// when `None`. Used by routing rules with a model override.
+    pub fn set_model_override(&mut self, model: Option<&str>) {
+        let model = model.unwrap_or(&self.default_model).to_string();
+        if model == self.active_model {
+            return;
+        }
+        // Agent's builder consumes self, so swap in a placeholder while rebuilding
+        let agent = std::mem::replace(&mut self.agent, Agent::new(provider::AnthropicProvider));
+        self.agent = agent.with_model(&model);
+        tracing::info!("Model switched to {}", model);
+        self.active_model = model;
+    }
+
+    /// Sample with the session's `/temp` override for subsequent messages, or
+    /// with `agent.temperature` when `None`.
+    pub fn set_temperature(&mut self, temperature: Option<f32>) {
+        self.agent.temperature = temperature.or(self.default_temperature);
+    }
+
+    /// Use a new `agent.temperature` from the next message on (hot-reload).
+    pub fn update_default_temperature(&mut self, temperature: Option<f32>) {
+        if self.default_temperature != temperature {
+            self.default_temperature = temperature;
+            tracing::info!("Default temperature set to {:?}", temperature);
+        }
+    }
+
+    /// Use new persona fragments from the next message on (hot-reload).
+    pub fn update_persona_fragments(&mut self, fragments: &[crate::config::PersonaFragmentConfig]) {
+        if self.persona_fragments != fragments {
+            self.persona_fragments = fragments.to_vec();
+            tracing::info!("Persona fragments reloaded");
+        }
+    }
+
+    /// Leave the `disabled` skills out of the prompt, and their files and
+    /// memory namespaces out of the tools, from the next message on
+    /// (hot-reload). Skills are the ones found at startup.
+    pub fn update_skills(&mut self, disabled: &[String]) {
+        let enabled = crate::skills::enabled(&self.loaded_skills, disabled);
+        *self.skill_namespaces.write().unwrap() = crate::skills::memory_namespaces(&enabled);
+        *self.skill_files.write().unwrap() = crate::skills::skill_files(&enabled);
+        let prompt = crate::skills::prompt(&self.loaded_skills, disabled);
+        if prompt != self.skills_prompt {
+            self.skills_prompt = prompt;
+            tracing::info!("Skills reloaded");
+        }
+    }
+
+    /// Rebuild the system prompt for `session_id`: the persona fragments for
+    /// its channel, or the persona file, then skills and the selected
+    /// project's context. Rebuilt every message so new memories and file
+    /// edits show up. If a fragment can't be read the prompt stays as it is.
+    async fn refresh_system_prompt(&mut self, session_id: &str) -> Result<(), anyhow::Error> {
+        let base = if self.persona_fragments.is_empty() {
+            persona::with_skills(&self.persona, &self.skills_prompt)
+        } else {
+            let channel = self.db.session_get(session_id).await?.map(|s| s.channel);
+            match persona::render(&self.persona_fragments, channel.as_deref(), &self.db).await {
+                Ok(rendered) => persona::with_skills(&rendered, &self.skills_prompt),
+                Err(e) => {
+                    tracing::warn!("System prompt not rebuilt: {}", e);
+                    return Ok(());
+                }
+            }
+        };
+        let prompt = match self.db.project_selected(session_id).await? {
+            Some(name) => {
+                let block = crate::projects::context(&self.db, &name, session_id).await?;
+                format!("{}\n\n{}", base, block)
+            }
+            None => base,
+        };
+        if prompt == self.system_prompt {
+            return Ok(());
+        }
+        let agent = std::mem::replace(&mut self.agent, Agent::new(provider::AnthropicProvider));
+        self.agent = agent.with_system_prompt(&prompt);
+        self.system_prompt = prompt;
+        Ok(())
+    }
+
+    /// Update what the preamble says about `session_id`. The onboarding
+    /// timezone is looked up each time, as the interview may have just set it.
+    async fn refresh_turn_facts(
+        &mut self,
+        session_id: &str,
+        is_group: bool,
+    ) -> Result<(), anyhow::Error> {
+        let Some(ref facts) = self.turn_facts else {
+            return Ok(());
+        };
+        let timezone = match self.timezone {
+            Some(tz) => Some(tz),
+            None => self
+                .db
+                .memory_get(preamble::TIMEZONE_MEMORY)
+                .await?
+                .and_then(|m| preamble::parse_timezone(&m.content)),
+        };
+        let channel = self.db.session_get(session_id).await?.map(|s| s.channel);
+        let participants = if is_group {
+            self.db.session_participants(session_id).await?
+        } else {
+            1
+        };
+        *facts.write().unwrap() = preamble::TurnFacts {
+            channel,
+            is_group,
+            participants,
+            timezone,
+        };
+        Ok(())
+    }
+
+    /// Use `level` for subsequent turns.
+    fn set_thinking(&mut self, level: ThinkingLevel) {
+        let agent = std::mem::replace(&mut self.agent, Agent::new(provider::AnthropicProvider));
+        self.agent = agent.with_thinking(level);
+        self.active_thinking = level;
+    }
+
     /// Process a user message and return the assistant's text response.
     /// If `on_chunk` is provided, streaming text deltas are forwarded in real-time.
     /// If `on_progress` is provided, ProgressMessage events (from send_message tool)
@@ -389,13 +960,51 @@ impl Conductor {
         on_chunk: Option<OnStreamChunk>,
         on_progress: Option<Box<dyn Fn(String) + Send + Sync>>,
     ) -> Result<String, anyhow::Error> {
+        self.sources.clear();
+        self.worker_limits.reset();
+        self.turn_watch.reset();
+        *self.run_stats.lock().unwrap() = Default::default();
+        let images = std::mem::take(&mut self.images);
+        let queue_entry = self.queue_entry.take();
+
+        // Session commands are answered directly, without involving the agent
+        if let Some(reply) = self.handle_privacy_command(session_id, text).await? {
+            self.group_catchup_prefix.clear();
+            return Ok(reply);
+        }
+        if let Some(reply) = self.handle_background_command(session_id, text).await? {
+            self.group_catchup_prefix.clear();
+            return Ok(reply);
+        }
+        if let Some(reply) = crate::projects::handle(&self.db, session_id, text).await? {
+            self.group_catchup_prefix.clear();
+            return Ok(reply);
+        }
+        if text.split_whitespace().next() == Some("/stats") {
+            self.group_catchup_prefix.clear();
+            let stats = crate::stats::session_stats(&self.db, session_id).await?;
+            return Ok(stats.format(self.pricing.as_ref()));
+        }
+        if self.onboarding && !is_group {
+            if let Some(reply) = crate::onboarding::handle(&self.db, session_id, text).await? {
+                self.group_catchup_prefix.clear();
+                return Ok(reply);
+            }
+        }
+        if !is_group {
+            if let Some(reply) = crate::memory_review::handle(&self.db, session_id, text).await? {
+                self.group_catchup_prefix.clear();
+                return Ok(reply);
+            }
+        }
+
         // LLM judge pre-check: if the sync filter will flag for LLM judge,
         // run the judge asynchronously before prompting the agent.
         if let Some(ref judge) = self.llm_judge {
             use crate::security::injection::InjectionDetector;
             // Check if the text would produce the judge marker
             // by looking at the heuristic score directly
-            let heuristic = crate::security::heuristics::HeuristicScorer::analyze(text);
+            let heuristic = self.heuristic_scorer.score(text);
             let detector_check = InjectionDetector::new("warn", &self.injection_extra_patterns);
             let has_pattern = detector_check.analyze_patterns(text).is_some();
 
@@ -440,12 +1049,173 @@ impl Conductor {
         if self.current_session != session_id {
             self.switch_session(session_id, is_group).await?;
         }
+        self.refresh_system_prompt(session_id).await?;
+        self.refresh_turn_facts(session_id, is_group).await?;
+
+        let (level, rule) = self.thinking.level_for(text, is_group);
+        if level != self.active_thinking {
+            tracing::debug!(
+                "Thinking level {} ({} rule)",
+                thinking::level_name(level),
+                rule
+            );
+            self.set_thinking(level);
+        }
+
+        // A message too large for the context is replaced by an excerpt, and
+        // the user offered to save it to a file
+        let private = self.private_ref.load(Ordering::SeqCst);
+        self.turn_watch.allow_handoff(self.background && !private);
+        let replacement = self.oversize.check(session_id, text, private)?;
+        let text = replacement.as_ref().map_or(text, |r| r.prompt.as_str());
+
+        // Callbacks are shared across retry attempts
+        let on_chunk: Option<progress::ChunkFn> = on_chunk.map(Arc::from);
+        let on_progress: Option<Arc<dyn Fn(String) + Send + Sync>> = on_progress.map(Arc::from);
+        // Partial tool output edits the placeholder like streamed text
+        *self.progress_sink.write().unwrap() = on_chunk.clone();
+        // Each message gets one explanation per kind of denial
+        self.denial_hints.lock().unwrap().clear();
+        // No skill is followed until the agent reads one
+        *self.active_skill.write().unwrap() = None;
+        let checkpoint = self.agent.messages().to_vec();
+        if let Some(ref fallbacks) = self.fallbacks {
+            fallbacks.reset();
+        }
+        let mut attempt = 1;
+        let mut waited = std::time::Duration::ZERO;
+        let mut notified = false;
+        // Whether the next attempt continues from the tool results of a failed one
+        let mut resume = false;
+
+        // Run the agent, retrying with backoff while the provider is overloaded
+        let started = std::time::Instant::now();
+        let result = loop {
+            let rx = if resume {
+                self.agent.continue_loop().await
+            } else if images.is_empty() {
+                self.agent.prompt(text).await
+            } else {
+                // A message that is only an image has no text block
+                let mut content: Vec<Content> = (!text.is_empty())
+                    .then(|| Content::Text {
+                        text: text.to_string(),
+                    })
+                    .into_iter()
+                    .collect();
+                content.extend(images.iter().cloned());
+                let msg = Message::User {
+                    content,
+                    timestamp: crate::db::now_ms(),
+                };
+                self.agent
+                    .prompt_messages(vec![AgentMessage::Llm(msg)])
+                    .await
+            };
+            let chunk_cb = on_chunk
+                .clone()
+                .map(|cb| Box::new(move |s: &str| cb(s)) as OnStreamChunk);
+            let progress_cb = on_progress
+                .clone()
+                .map(|cb| Box::new(move |s: String| cb(s)) as Box<dyn Fn(String) + Send + Sync>);
+            let result = stream_response(rx, chunk_cb, progress_cb).await;
+
+            let error = match result.error {
+                Some(ref e) if is_overloaded(e) => e.clone(),
+                _ => break result,
+            };
+
+            // Tools that already ran aren't run again: the next attempt, on a
+            // fallback or after backoff, continues from their results.
+            // Otherwise the failed turn is dropped, so neither a retry nor
+            // the next message sees it
+            let resumed = resumable(self.agent.messages());
+            resume = resumed.is_some();
+            let json = serde_json::to_string(resumed.as_ref().unwrap_or(&checkpoint))?;
+            self.agent.restore_messages(&json)?;
+
+            // Go on with the turn on the next fallback provider, if any
+            if let Some((from, to)) = self.fallbacks.as_ref().and_then(|f| f.advance()) {
+                tracing::warn!(
+                    "Provider {} overloaded, falling back to {}: {}",
+                    from,
+                    to,
+                    error
+                );
+                let detail = format!("{} -> {}: {}", from, to, error);
+                let _ = self
+                    .db
+                    .audit_log(
+                        Some(session_id),
+                        "provider_fallback",
+                        None,
+                        Some(&detail),
+                        0,
+                    )
+                    .await;
+                continue;
+            }
+
+            if attempt >= self.retry.max_attempts {
+                // Giving up drops the whole turn, tool results included
+                let json = serde_json::to_string(&checkpoint)?;
+                self.agent.restore_messages(&json)?;
+                *self.progress_sink.write().unwrap() = None;
+                return Err(ProcessError::Overloaded {
+                    attempts: attempt,
+                    message: error,
+                }
+                .into());
+            }
+
+            let delay = backoff_delay(&self.retry, attempt, jitter());
+            waited += delay;
+            tracing::warn!(
+                "Provider overloaded (attempt {}/{}), retrying in {}ms: {}",
+                attempt,
+                self.retry.max_attempts,
+                delay.as_millis(),
+                error
+            );
+            if !notified && waited.as_millis() >= u128::from(self.retry.notice_after_ms) {
+                if let Some(ref cb) = on_chunk {
+                    cb(RETRY_NOTICE);
+                }
+                notified = true;
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+        *self.progress_sink.write().unwrap() = None;
 
-        // Run the agent
-        let rx = self.agent.prompt(text).await;
+        // Response latency feeds the /stats percentiles. Saved with the tape
+        // when the turn is kept
+        let latency = format!("latency_ms={}", started.elapsed().as_millis());
+        if self.turn_watch.handed_off() || result.input_rejected.is_some() {
+            let _ = self
+                .db
+                .audit_log(Some(session_id), "response", None, Some(&latency), 0)
+                .await;
+        }
 
-        // Stream events and collect response
-        let result = stream_response(rx, on_chunk, on_progress).await;
+        // Past the channel's latency cap the message starts over as a
+        // background task; the unfinished turn is dropped
+        if self.turn_watch.handed_off() {
+            let json = serde_json::to_string(&checkpoint)?;
+            self.agent.restore_messages(&json)?;
+            let id = self.db.background_enqueue(session_id, text).await?;
+            tracing::info!(
+                "Handed off message in {} as background task #{}",
+                session_id,
+                id
+            );
+            let detail = format!("background task #{}", id);
+            let _ = self
+                .db
+                .audit_log(Some(session_id), "latency_handoff", None, Some(&detail), 0)
+                .await;
+            return Ok(latency::handoff_reply(id));
+        }
 
         // Audit log if input was rejected (e.g. by injection detector)
         if let Some(ref reason) = result.input_rejected {
@@ -458,19 +1228,48 @@ impl Conductor {
             return Ok("I can't process that message.".to_string());
         }
 
+        // Attribute the reply to the web results it used, while tool output is still there
+        let messages = self.agent.messages();
+        let turn_start = messages
+            .iter()
+            .rposition(|m| matches!(m, AgentMessage::Llm(Message::User { .. })))
+            .unwrap_or(0);
+        self.sources = citations::referenced_sources(&messages[turn_start..], &result.response);
+
+        // Keep what send_message told the user on the tape, next to the call
+        if let Some(recorded) = tools::record_interim_messages(messages, turn_start) {
+            let json = serde_json::to_string(&recorded)?;
+            self.agent.restore_messages(&json)?;
+        }
+
+        // Private sessions keep tool output only for the duration of the turn
+        if self.private_ref.load(Ordering::SeqCst) {
+            let redacted = redact_tool_outputs(self.agent.messages());
+            let json = serde_json::to_string(&redacted)?;
+            self.agent.restore_messages(&json)?;
+        }
+
+        let reply = match replacement {
+            Some(r) => format!("{}\n\n{}", r.notice, result.response),
+            None => result.response,
+        };
+
         // Persist conversation state — reconstruct full tape if group catchup trimmed a prefix
         let prefix = std::mem::take(&mut self.group_catchup_prefix);
+        let answered = queue_entry.map(|id| (id, reply.as_str()));
         if prefix.is_empty() {
             self.db
-                .tape_save_messages(session_id, self.agent.messages())
+                .tape_save_turn(session_id, self.agent.messages(), &latency, answered)
                 .await?;
         } else {
             let mut full_tape = prefix;
             full_tape.extend_from_slice(self.agent.messages());
-            self.db.tape_save_messages(session_id, &full_tape).await?;
+            self.db
+                .tape_save_turn(session_id, &full_tape, &latency, answered)
+                .await?;
         }
 
-        Ok(result.response)
+        Ok(reply)
     }
 
     async fn switch_session(
@@ -518,6 +1317,8 @@ impl Conductor {
 
         self.current_session = new_session.to_string();
         *self.session_id_ref.write().unwrap() = new_session.to_string();
+        let private = self.db.privacy_is_private(new_session).await?;
+        self.private_ref.store(private, Ordering::SeqCst);
         self.budget.reset_turns();
 
         tracing::info!(
@@ -533,6 +1334,177 @@ impl Conductor {
         &self.current_session
     }
 
+    /// Questions the agent is waiting on, for routing the user's answers.
+    pub fn questions(&self) -> ask::Questions {
+        self.questions.clone()
+    }
+
+    /// Tool calls waiting for approval, for routing the user's decisions.
+    pub fn approvals(&self) -> security::approval::Approvals {
+        self.approvals.clone()
+    }
+
+    /// Web sources the last reply drew on, emptied by the call.
+    pub fn take_sources(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.sources)
+    }
+
+    /// Handle `/private on|off|status`. Returns the reply if `text` was a privacy command.
+    async fn handle_privacy_command(
+        &mut self,
+        session_id: &str,
+        text: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let mut words = text.split_whitespace();
+        if words.next() != Some("/private") {
+            return Ok(None);
+        }
+
+        let reply = match words.next().unwrap_or("status") {
+            "on" => {
+                self.db.privacy_set(session_id, true).await?;
+                self.db
+                    .audit_log(Some(session_id), "privacy", None, Some("on"), 0)
+                    .await?;
+                "Private mode on. I won't store memories from this conversation, tool output \
+                 is discarded after each turn, and the transcript is purged after a period of \
+                 inactivity."
+            }
+            "off" => {
+                self.db.privacy_set(session_id, false).await?;
+                self.db
+                    .audit_log(Some(session_id), "privacy", None, Some("off"), 0)
+                    .await?;
+                "Private mode off."
+            }
+            "status" => {
+                if self.db.privacy_is_private(session_id).await? {
+                    "Private mode is on."
+                } else {
+                    "Private mode is off."
+                }
+            }
+            _ => "Usage: /private on | off | status",
+        };
+
+        if self.current_session == session_id {
+            let private = self.db.privacy_is_private(session_id).await?;
+            self.private_ref.store(private, Ordering::SeqCst);
+        }
+
+        Ok(Some(reply.to_string()))
+    }
+
+    /// Handle `/use worker <name>`, `/use main` and `/use`. Returns the reply if
+    /// `text` was a `/use` command. Called before routing, so the command reaches
+    /// the conductor even in a session pinned to a worker.
+    pub async fn handle_use_command(
+        &self,
+        session_id: &str,
+        text: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let mut words = text.split_whitespace();
+        if words.next() != Some("/use") {
+            return Ok(None);
+        }
+
+        let reply = match (words.next(), words.next()) {
+            (None, _) => match self.db.pin_get(session_id).await? {
+                Some(worker) => format!(
+                    "This chat is pinned to the '{}' worker. Send /use main to switch back.",
+                    worker
+                ),
+                None => "This chat uses the main agent.".to_string(),
+            },
+            (Some("main"), None) => {
+                self.db.pin_set(session_id, None).await?;
+                self.db
+                    .audit_log(Some(session_id), "pin", None, Some("main"), 0)
+                    .await?;
+                "Back to the main agent.".to_string()
+            }
+            (Some("worker"), Some(worker)) if self.direct_workers.contains_key(worker) => {
+                self.db.pin_set(session_id, Some(worker)).await?;
+                self.db
+                    .audit_log(Some(session_id), "pin", None, Some(worker), 0)
+                    .await?;
+                format!(
+                    "Messages in this chat now go to the '{}' worker. Send /use main to switch back.",
+                    worker
+                )
+            }
+            (Some("worker"), Some(worker)) => {
+                let mut names: Vec<&str> = self.direct_workers.keys().map(|k| k.as_str()).collect();
+                names.sort_unstable();
+                if names.is_empty() {
+                    format!("Unknown worker '{}'. No workers are configured.", worker)
+                } else {
+                    format!(
+                        "Unknown worker '{}'. Available: {}",
+                        worker,
+                        names.join(", ")
+                    )
+                }
+            }
+            _ => "Usage: /use worker <name> | /use main".to_string(),
+        };
+        Ok(Some(reply))
+    }
+
+    /// Worker the session is pinned to, if it still exists.
+    pub async fn pinned_worker(&self, session_id: &str) -> Result<Option<String>, anyhow::Error> {
+        match self.db.pin_get(session_id).await? {
+            Some(worker) if self.direct_workers.contains_key(&worker) => Ok(Some(worker)),
+            Some(worker) => {
+                tracing::warn!(
+                    "Session {} is pinned to unknown worker '{}'; using the main agent",
+                    session_id,
+                    worker
+                );
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Handle `/background <task>` and `/jobs`. Returns the reply if `text` was one of them.
+    async fn handle_background_command(
+        &mut self,
+        session_id: &str,
+        text: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        let text = text.trim();
+        let (command, task) = match text.split_once(char::is_whitespace) {
+            Some((command, task)) => (command, task.trim()),
+            None => (text, ""),
+        };
+        if command != "/background" && command != "/jobs" {
+            return Ok(None);
+        }
+        if !self.background {
+            return Ok(Some("Background tasks are disabled.".to_string()));
+        }
+
+        if command == "/jobs" {
+            let reply = crate::scheduler::background::list_jobs(&self.db, session_id).await?;
+            return Ok(Some(reply));
+        }
+        if task.is_empty() {
+            return Ok(Some("Usage: /background <task>".to_string()));
+        }
+        if self.db.privacy_is_private(session_id).await? {
+            return Ok(Some(
+                "Background tasks are not available in private mode.".to_string(),
+            ));
+        }
+        let id = self.db.background_enqueue(session_id, task).await?;
+        Ok(Some(format!(
+            "Queued as background task #{}. I'll send the result here when it's done; \
+             check on it with /jobs.",
+            id
+        )))
+    }
+
     /// Delegate a message directly to a named worker's sub-agent, bypassing the main conductor.
     /// Used for channel routing (e.g., Discord channel → specific worker).
     pub async fn delegate_to_worker(
@@ -542,7 +1514,7 @@ impl Conductor {
         text: &str,
     ) -> Result<String, anyhow::Error> {
         if !self.direct_workers.contains_key(worker_name) {
-            anyhow::bail!("Worker '{}' not found", worker_name);
+            return Err(ProcessError::UnknownWorker(worker_name.to_string()).into());
         }
 
         tracing::info!(
@@ -554,31 +1526,277 @@ impl Conductor {
         // Update session_id reference for audit logging
         *self.session_id_ref.write().unwrap() = session_id.to_string();
 
-        // Execute the worker's sub-agent directly
-        let params = serde_json::json!({"task": text});
-        let ctx = ToolContext {
-            tool_call_id: "direct-delegate".to_string(),
-            tool_name: worker_name.to_string(),
-            cancel: tokio_util::sync::CancellationToken::new(),
-            on_update: None,
-            on_progress: None,
-        };
-        let worker_tool = self.direct_workers.get(worker_name).unwrap();
-        let result = worker_tool
-            .execute(params, ctx)
-            .await
-            .map_err(|e| anyhow::anyhow!("Worker '{}' failed: {:?}", worker_name, e))?;
+        let response = self.run_worker(worker_name, text).await?;
+        self.record_delegation(
+            session_id,
+            text,
+            &response,
+            &format!("worker:{}", worker_name),
+        )
+        .await?;
+        Ok(response)
+    }
 
-        let response = result
-            .content
+    /// Run workers in sequence, passing each worker's output to the next.
+    /// Only the original message and the final output are added to the tape.
+    pub async fn delegate_to_pipeline(
+        &mut self,
+        session_id: &str,
+        workers: &[String],
+        text: &str,
+    ) -> Result<String, anyhow::Error> {
+        if let Some(missing) = workers
             .iter()
-            .filter_map(|c| match c {
-                Content::Text { text } => Some(text.as_str()),
-                _ => None,
+            .find(|w| !self.direct_workers.contains_key(w.as_str()))
+        {
+            return Err(ProcessError::UnknownWorker(missing.clone()).into());
+        }
+
+        tracing::info!(
+            "Running pipeline {} for session {}",
+            workers.join(" > "),
+            session_id
+        );
+
+        *self.session_id_ref.write().unwrap() = session_id.to_string();
+
+        let mut output = text.to_string();
+        for worker_name in workers {
+            output = self.run_worker(worker_name, &output).await?;
+        }
+
+        self.record_delegation(
+            session_id,
+            text,
+            &output,
+            &format!("pipeline:{}", workers.join(">")),
+        )
+        .await?;
+        Ok(output)
+    }
+
+    /// Execute a worker's sub-agent directly and return its text output.
+    async fn run_worker(&self, worker_name: &str, text: &str) -> Result<String, anyhow::Error> {
+        let params = serde_json::json!({"task": text});
+        let tool_call_id = "direct-delegate";
+        let ctx = ToolContext {
+            tool_call_id: tool_call_id.to_string(),
+            tool_name: worker_name.to_string(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        };
+        let worker_tool = self
+            .direct_workers
+            .get(worker_name)
+            .ok_or_else(|| ProcessError::UnknownWorker(worker_name.to_string()))?;
+        let result = self
+            .worker_runs
+            .record(
+                worker_name,
+                text,
+                tool_call_id,
+                worker_tool.execute(params, ctx),
+            )
+            .await
+            .map_err(|e| ProcessError::Worker {
+                worker: worker_name.to_string(),
+                message: format!("{:?}", e),
+            })?;
+
+        Ok(result
+            .content
+            .iter()
+            .filter_map(|c| match c {
+                Content::Text { text } => Some(text.as_str()),
+                _ => None,
             })
             .collect::<Vec<_>>()
-            .join("\n");
+            .join("\n"))
+    }
+
+    /// Append a note about an already-processed message (e.g. the user
+    /// edited or deleted it) to the session tape. The agent sees it with the
+    /// next message; nothing is answered now.
+    pub async fn record_note(&mut self, session_id: &str, note: &str) -> Result<(), anyhow::Error> {
+        if self.current_session == session_id {
+            let messages = self.agent.messages();
+            self.db.tape_save_messages(session_id, messages).await?;
+        }
+
+        let mut messages = self.db.tape_load_messages(session_id).await?;
+        messages.push(AgentMessage::Llm(Message::user(note)));
+        self.db.tape_save_messages(session_id, &messages).await?;
+
+        // Invalidate current session so next process_message reloads from tape
+        self.current_session = String::new();
+        Ok(())
+    }
+
+    /// Answer the message behind the latest reply in `session_id` again,
+    /// with `model` if given. The old exchange, and anything after it, is
+    /// taken off the tape first and put back if the new attempt fails; a note
+    /// after the new reply records that it was regenerated and by which
+    /// model. `None` if the tape has no reply.
+    pub async fn regenerate(
+        &mut self,
+        session_id: &str,
+        is_group: bool,
+        model: Option<&str>,
+    ) -> Result<Option<String>, anyhow::Error> {
+        if self.current_session == session_id {
+            let messages = self.agent.messages();
+            self.db.tape_save_messages(session_id, messages).await?;
+        }
+
+        let original = self.db.tape_load_messages(session_id).await?;
+        let Some(exchange) = crate::reactions::find_exchange(&original, None) else {
+            return Ok(None);
+        };
+        let AgentMessage::Llm(Message::User { ref content, .. }) = original[exchange.start] else {
+            return Ok(None);
+        };
+        let text = crate::reactions::message_text(content);
+        self.db
+            .tape_save_messages(session_id, &original[..exchange.start])
+            .await?;
+        self.current_session = String::new();
+
+        self.set_temperature(self.db.temperature_get(session_id).await?);
+        self.set_model_override(model);
+        let result = self
+            .process_message_inner(session_id, &text, is_group, None, None)
+            .await;
+        let used = self.active_model.clone();
+        self.set_model_override(None);
+        match result {
+            Ok(response) => {
+                let note = format!(
+                    "[The user asked for a new answer. The reply above was regenerated by {} and replaces an earlier one]",
+                    used
+                );
+                self.record_note(session_id, &note).await?;
+                Ok(Some(response))
+            }
+            Err(e) => {
+                self.db.tape_save_messages(session_id, &original).await?;
+                self.current_session = String::new();
+                Err(e)
+            }
+        }
+    }
+
+    /// Store a reply the user pinned as a memory. `None` in private
+    /// sessions, which keep nothing.
+    pub async fn pin_reply(
+        &self,
+        session_id: &str,
+        content: &str,
+    ) -> Result<Option<i64>, anyhow::Error> {
+        if self.db.privacy_is_private(session_id).await? {
+            return Ok(None);
+        }
+        let id = self
+            .db
+            .memory_store_with_meta(
+                None,
+                content,
+                Some("pinned"),
+                Some(&format!("reaction:{}", session_id)),
+                "fact",
+                7,
+            )
+            .await?;
+        Ok(Some(id))
+    }
+
+    /// Store a message the user asked to `/remember`, with where it came
+    /// from. `None` in private sessions.
+    pub async fn remember_quote(
+        &self,
+        session_id: &str,
+        channel: &str,
+        quote: &crate::channels::Quote,
+    ) -> Result<Option<i64>, anyhow::Error> {
+        if self.db.privacy_is_private(session_id).await? {
+            return Ok(None);
+        }
+        let source = crate::remember::source(channel, quote.sender.as_deref(), quote.timestamp);
+        let id = self
+            .db
+            .memory_store_with_meta(
+                None,
+                &quote.text,
+                Some(crate::remember::TAG),
+                Some(&source),
+                "fact",
+                crate::remember::IMPORTANCE,
+            )
+            .await?;
+        Ok(Some(id))
+    }
+
+    /// Take the exchange ending in the reply `sent` off the tape: the user's
+    /// message, any tool calls and the reply. Returns whether it was found.
+    pub async fn forget_reply(
+        &mut self,
+        session_id: &str,
+        sent: &str,
+    ) -> Result<bool, anyhow::Error> {
+        if self.current_session == session_id {
+            let messages = self.agent.messages();
+            self.db.tape_save_messages(session_id, messages).await?;
+        }
+
+        let mut messages = self.db.tape_load_messages(session_id).await?;
+        let Some(exchange) = crate::reactions::find_exchange(&messages, Some(sent)) else {
+            return Ok(false);
+        };
+        messages.drain(exchange);
+        self.db.tape_save_messages(session_id, &messages).await?;
+
+        // Invalidate current session so next process_message reloads from tape
+        self.current_session = String::new();
+        Ok(true)
+    }
+
+    /// Move the session's conversation to a tape of its own, named
+    /// `<session_id>~<timestamp>`, so the next message starts from an empty
+    /// one. Returns that name, or None when there was nothing to move.
+    pub async fn set_aside_session(
+        &mut self,
+        session_id: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        if self.current_session == session_id {
+            let messages = self.agent.messages();
+            self.db.tape_save_messages(session_id, messages).await?;
+        }
+
+        let messages = self.db.tape_load_messages(session_id).await?;
+        if messages.is_empty() {
+            return Ok(None);
+        }
+        let archived = format!(
+            "{}~{}",
+            session_id,
+            chrono::Utc::now().format("%Y%m%d-%H%M%S")
+        );
+        self.db.tape_save_messages(&archived, &messages).await?;
+        self.db.tape_save_messages(session_id, &[]).await?;
 
+        // Invalidate current session so next process_message reloads from tape
+        self.current_session = String::new();
+        Ok(Some(archived))
+    }
+
+    /// Append a delegated exchange to the session tape.
+    async fn record_delegation(
+        &mut self,
+        session_id: &str,
+        text: &str,
+        response: &str,
+        model: &str,
+    ) -> Result<(), anyhow::Error> {
         // Save current agent state if we're in this session
         if self.current_session == session_id {
             let messages = self.agent.messages();
@@ -590,10 +1808,10 @@ impl Conductor {
         messages.push(AgentMessage::Llm(Message::user(text)));
         messages.push(AgentMessage::Llm(Message::Assistant {
             content: vec![Content::Text {
-                text: response.clone(),
+                text: response.to_string(),
             }],
             stop_reason: StopReason::Stop,
-            model: format!("worker:{}", worker_name),
+            model: model.to_string(),
             provider: "worker".to_string(),
             usage: Usage::default(),
             timestamp: crate::db::now_ms(),
@@ -603,14 +1821,23 @@ impl Conductor {
 
         // Invalidate current session so next process_message reloads from tape
         self.current_session = String::new();
-
-        Ok(response)
+        Ok(())
     }
 }
 
 /// For group chats, slice the message tape from the last assistant message onward,
 /// capped at `max_messages`. This gives the agent context of what happened since it
 /// last spoke, without loading the entire conversation history.
+/// The model alias of a `/retry` command: `Some(None)` for a bare `/retry`,
+/// `Some(Some("strong"))` for `/retry strong`, `None` for other messages.
+pub fn retry_command(text: &str) -> Option<Option<&str>> {
+    let mut words = text.split_whitespace();
+    if words.next() != Some("/retry") {
+        return None;
+    }
+    Some(words.next())
+}
+
 fn catchup_messages(messages: Vec<AgentMessage>, max_messages: usize) -> Vec<AgentMessage> {
     let last_assistant_idx = messages
         .iter()
@@ -627,11 +1854,162 @@ fn catchup_messages(messages: Vec<AgentMessage>, max_messages: usize) -> Vec<Age
     }
 }
 
+/// Replace the content of every tool result with a placeholder, keeping the
+/// tool call/result pairing intact.
+fn redact_tool_outputs(messages: &[AgentMessage]) -> Vec<AgentMessage> {
+    messages
+        .iter()
+        .cloned()
+        .map(|mut msg| {
+            if let AgentMessage::Llm(Message::ToolResult {
+                ref mut content, ..
+            }) = msg
+            {
+                *content = vec![Content::Text {
+                    text: PRIVATE_TOOL_OUTPUT.to_string(),
+                }];
+            }
+            msg
+        })
+        .collect()
+}
+
+/// Whether a provider error means "try again later": yoagent's rate-limit
+/// error, HTTP 429, 503 or 529, or an overload or rate-limit error event.
+fn is_overloaded(error: &str) -> bool {
+    // ProviderError::RateLimited
+    error.starts_with("Rate limited")
+        || matches!(error_status(error), Some(429 | 503 | 529))
+        || matches!(
+            error_kind(error).as_deref(),
+            Some("overloaded_error" | "rate_limit_error" | "rate_limit_exceeded")
+        )
+}
+
+/// HTTP status of a failed provider request, as yoagent's providers word it.
+fn error_status(error: &str) -> Option<u16> {
+    // reqwest-eventsource (Anthropic, OpenAI-compatible), then Google and Bedrock
+    [
+        "Invalid status code: ",
+        "Google API error ",
+        "Bedrock error ",
+    ]
+    .iter()
+    .find_map(|prefix| {
+        let status = &error[error.find(prefix)? + prefix.len()..];
+        status.get(..3)?.parse().ok()
+    })
+}
+
+/// Type of the error in a provider's error event, e.g. Anthropic's
+/// `{"type":"error","error":{"type":"overloaded_error",...}}`.
+fn error_kind(error: &str) -> Option<String> {
+    let event: serde_json::Value = serde_json::from_str(&error[error.find('{')?..]).ok()?;
+    let error = event.get("error").unwrap_or(&event);
+    let kind = error.get("type").or_else(|| error.get("code"))?;
+    kind.as_str().map(String::from)
+}
+
+/// The messages of an attempt that failed after running tools, without the
+/// failed reply, to continue from. `None` if no tool ran before the failure.
+fn resumable(messages: &[AgentMessage]) -> Option<Vec<AgentMessage>> {
+    let (last, rest) = messages.split_last()?;
+    let failed = matches!(
+        last,
+        AgentMessage::Llm(Message::Assistant {
+            error_message: Some(_),
+            ..
+        })
+    );
+    let after_tools = matches!(
+        rest.last(),
+        Some(AgentMessage::Llm(Message::ToolResult { .. }))
+    );
+    (failed && after_tools).then(|| rest.to_vec())
+}
+
+/// Filters fixed by config, recorded with every turn snapshot.
+fn snapshot_filters(config: &Config) -> serde_json::Value {
+    let inj = &config.security.injection;
+    let ctx = &config.agent.context;
+    serde_json::json!({
+        "injection": if inj.enabled {
+            serde_json::json!({
+                "action": inj.action,
+                "heuristic_threshold": inj.heuristic_threshold,
+                "llm_judge": inj.llm_judge,
+                "extra_patterns": inj.extra_patterns.len(),
+            })
+        } else {
+            serde_json::Value::Null
+        },
+        "context": {
+            "max_context_tokens": ctx.max_context_tokens,
+            "keep_recent": ctx.keep_recent,
+            "tool_output_max_lines": ctx.tool_output_max_lines,
+            "summarize": ctx.summarize,
+        },
+        "max_tokens": config.agent.max_tokens,
+        "thinking": config.agent.thinking,
+        "thinking_rules": config.agent.thinking_rules.is_some(),
+        "shell_deny_patterns": config.security.shell_deny_patterns,
+    })
+}
+
+/// Name of the last tool called in `messages`, if the last assistant message called one.
+fn last_tool_call(messages: &[AgentMessage]) -> Option<String> {
+    messages.iter().rev().find_map(|msg| match msg {
+        AgentMessage::Llm(Message::Assistant { content, .. }) => {
+            content.iter().rev().find_map(|c| match c {
+                Content::ToolCall { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+        }
+        _ => None,
+    })
+}
+
+/// Provider and model that wrote the last assistant message.
+fn last_model(messages: &[AgentMessage]) -> Option<(&str, &str)> {
+    messages.iter().rev().find_map(|msg| match msg {
+        AgentMessage::Llm(Message::Assistant {
+            provider, model, ..
+        }) if !model.is_empty() => Some((provider.as_str(), model.as_str())),
+        _ => None,
+    })
+}
+
+/// Exponential backoff for retry `attempt` (1-based), capped at `max_delay_ms`.
+/// `jitter` in [0, 1) spreads the delay over the upper half of the window.
+fn backoff_delay(
+    config: &crate::config::RetryConfig,
+    attempt: u32,
+    jitter: f64,
+) -> std::time::Duration {
+    let exp = config
+        .base_delay_ms
+        .saturating_mul(1u64 << attempt.saturating_sub(1).min(20))
+        .min(config.max_delay_ms);
+    let half = exp / 2;
+    std::time::Duration::from_millis(half + (half as f64 * jitter) as u64)
+}
+
+/// Cheap jitter source; doesn't need to be cryptographically random.
+fn jitter() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    f64::from(nanos % 1000) / 1000.0
+}
+
 /// Result of streaming/draining an agent event stream.
 struct StreamResult {
     response: String,
     /// If input was rejected by a filter (e.g. injection detection).
     input_rejected: Option<String>,
+    /// Provider error reported on the final assistant message.
+    error: Option<String>,
 }
 
 /// Stream agent events: forwards text deltas via `on_chunk` and progress via `on_progress`.
@@ -643,6 +2021,7 @@ async fn stream_response(
 ) -> StreamResult {
     let mut response = String::new();
     let mut input_rejected = None;
+    let mut error = None;
     let mut accumulated = String::new();
     while let Some(event) = rx.recv().await {
         match event {
@@ -668,12 +2047,20 @@ async fn stream_response(
                 input_rejected = Some(reason);
             }
             AgentEvent::AgentEnd { ref messages } => {
-                // Extract text from the last assistant message
+                if let Some(AgentMessage::Llm(Message::Assistant {
+                    error_message: Some(ref e),
+                    ..
+                })) = messages.last()
+                {
+                    error = Some(e.clone());
+                }
+                // Extract text from the last assistant message, skipping interim
+                // send_message text recorded in earlier turns
                 for msg in messages.iter().rev() {
                     if let AgentMessage::Llm(Message::Assistant { ref content, .. }) = msg {
                         for c in content {
                             if let Content::Text { ref text } = c {
-                                if response.is_empty() {
+                                if response.is_empty() && !tools::is_interim(text) {
                                     response = text.clone();
                                 }
                             }
@@ -690,6 +2077,7 @@ async fn stream_response(
     StreamResult {
         response,
         input_rejected,
+        error,
     }
 }
 
@@ -697,6 +2085,12 @@ async fn stream_response(
 /// as a single concrete type that implements `StreamProvider`.
 pub struct DynProvider(Box<dyn provider::StreamProvider>);
 
+impl DynProvider {
+    pub fn new(provider: impl provider::StreamProvider + 'static) -> Self {
+        Self(Box::new(provider))
+    }
+}
+
 #[async_trait::async_trait]
 impl provider::StreamProvider for DynProvider {
     async fn stream(
@@ -709,9 +2103,10 @@ impl provider::StreamProvider for DynProvider {
     }
 }
 
-/// Resolve a provider name to a StreamProvider implementation.
-pub fn resolve_provider(name: &str) -> DynProvider {
-    DynProvider(match name {
+/// Resolve a provider name to a StreamProvider implementation, using the
+/// keys of [`keys::ring`] and the model config of `endpoint`.
+pub fn resolve_provider(name: &str, endpoint: &endpoint::Endpoint) -> DynProvider {
+    let inner: Box<dyn provider::StreamProvider> = match name {
         "anthropic" => Box::new(provider::AnthropicProvider),
         "openai" => Box::new(provider::OpenAiCompatProvider),
         "google" => Box::new(provider::GoogleProvider),
@@ -723,7 +2118,15 @@ pub fn resolve_provider(name: &str) -> DynProvider {
             tracing::warn!("Unknown provider '{}', defaulting to anthropic", name);
             Box::new(provider::AnthropicProvider)
         }
-    })
+    };
+    DynProvider(Box::new(keys::KeyRotatingProvider {
+        inner: Box::new(endpoint::EndpointProvider {
+            inner,
+            provider: name.to_string(),
+            endpoint: endpoint.clone(),
+        }),
+        ring: keys::ring(),
+    }))
 }
 
 #[cfg(test)]
@@ -744,9 +2147,10 @@ api_key = "test-key"
 
         // Build conductor manually with MockProvider
         let provider = MockProvider::text(mock_response);
-        let mut tools: Vec<Box<dyn AgentTool>> = Vec::new();
-        tools.push(Box::new(tools::MemorySearchTool::new(db.clone())));
-        tools.push(Box::new(tools::MemoryStoreTool::new(db.clone())));
+        let tools: Vec<Box<dyn AgentTool>> = vec![
+            Box::new(tools::MemorySearchTool::new(db.clone())),
+            Box::new(tools::MemoryStoreTool::new(db.clone())),
+        ];
 
         let budget = BudgetTracker::new(None, None, db.clone());
         let session_id_ref = Arc::new(std::sync::RwLock::new(String::new()));
@@ -761,6 +2165,8 @@ api_key = "test-key"
         let policy_ref = Arc::new(std::sync::RwLock::new(SecurityPolicy {
             shell_deny_patterns: vec![],
             tool_permissions: HashMap::new(),
+            permissive: true,
+            readable_dirs: vec![],
         }));
         let conductor = Conductor {
             agent,
@@ -778,6 +2184,49 @@ api_key = "test-key"
             injection_heuristic_threshold: 0.6,
             injection_llm_judge_threshold: 0.4,
             injection_extra_patterns: vec![],
+            heuristic_scorer: Default::default(),
+            private_ref: Arc::new(AtomicBool::new(false)),
+            default_model: "mock".to_string(),
+            active_model: "mock".to_string(),
+            default_temperature: None,
+            thinking: Default::default(),
+            active_thinking: ThinkingLevel::Off,
+            retry: crate::config::RetryConfig::default(),
+            fallbacks: None,
+            pricing: None,
+            onboarding: false,
+            progress_sink: Arc::new(std::sync::RwLock::new(None)),
+            background: false,
+            denial_hints: Default::default(),
+            active_skill: Default::default(),
+            skill_namespaces: Default::default(),
+            skill_files: Default::default(),
+            provider_name: "mock".to_string(),
+            run_stats: Default::default(),
+            oversize: Default::default(),
+            questions: Default::default(),
+            approvals: Default::default(),
+            sources: Vec::new(),
+            worker_limits: Arc::new(limits::WorkerLimits::new(
+                &Default::default(),
+                db.clone(),
+                Arc::default(),
+            )),
+            worker_runs: Arc::new(worker_runs::RunLog::new(
+                db.clone(),
+                Arc::default(),
+                Arc::default(),
+                &Default::default(),
+            )),
+            persona: String::new(),
+            persona_fragments: Vec::new(),
+            skills_prompt: String::new(),
+            system_prompt: String::new(),
+            turn_facts: None,
+            timezone: None,
+            images: Vec::new(),
+            turn_watch: Arc::new(latency::TurnWatch::default()),
+            queue_entry: None,
         };
 
         (conductor, db)
@@ -793,6 +2242,104 @@ api_key = "test-key"
         assert_eq!(response, "Hello! How can I help?");
     }
 
+    #[tokio::test]
+    async fn test_toggling_a_skill_rebuilds_its_files_and_namespace() {
+        let (mut conductor, db) = test_conductor("Done.").await;
+        let dir = tempfile::tempdir().unwrap();
+        let skill_file = dir.path().join("SKILL.md");
+        std::fs::write(&skill_file, "---\nname: inbox-triage\n---\nSort mail.").unwrap();
+        conductor.loaded_skills = vec![LoadedSkill {
+            manifest: crate::skills::manifest::SkillManifest {
+                name: "inbox-triage".into(),
+                description: "Sort mail".into(),
+                tools: Vec::new(),
+                memory_namespace: Some("inbox".into()),
+                profile: None,
+            },
+            dir_name: "inbox-triage".into(),
+            file_path: skill_file.clone(),
+        }];
+        let read = tools::track_skill_reads(
+            vec![Box::new(yoagent::tools::ReadFileTool::new())],
+            conductor.skill_files.clone(),
+            conductor.active_skill.clone(),
+        )
+        .remove(0);
+        let store = tools::MemoryStoreTool::new(db.clone())
+            .with_skill_namespaces(conductor.skill_namespaces.clone())
+            .with_active_skill(conductor.active_skill.clone());
+        let ctx = || ToolContext {
+            tool_call_id: "t1".into(),
+            tool_name: "read_file".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        };
+        let path = serde_json::json!({"path": skill_file.to_str().unwrap()});
+
+        conductor.update_skills(&[]);
+        read.execute(path.clone(), ctx()).await.unwrap();
+        assert_eq!(
+            conductor.active_skill.read().unwrap().as_deref(),
+            Some("inbox-triage")
+        );
+        store
+            .execute(
+                serde_json::json!({"content": "Receipts go to finance"}),
+                ctx(),
+            )
+            .await
+            .unwrap();
+        assert!(db.memory_search("receipts", 10).await.unwrap().is_empty());
+
+        // Disabled: reading its file no longer scopes memory to it
+        conductor.update_skills(&["inbox-triage".into()]);
+        *conductor.active_skill.write().unwrap() = None;
+        read.execute(path, ctx()).await.unwrap();
+        assert_eq!(*conductor.active_skill.read().unwrap(), None);
+        store
+            .execute(
+                serde_json::json!({"content": "Invoices go to finance", "skill": "inbox-triage"}),
+                ctx(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(db.memory_search("invoices", 10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_attached_images_reach_the_model() {
+        let (mut conductor, _db) = test_conductor("A cat.").await;
+        let image = Content::Image {
+            data: "aGVsbG8=".into(),
+            mime_type: "image/png".into(),
+        };
+        conductor.attach_images(vec![image.clone()]);
+        conductor
+            .process_message("test-session", "What is this?", None, None)
+            .await
+            .unwrap();
+        let user = conductor
+            .agent
+            .messages()
+            .iter()
+            .find_map(|m| match m {
+                AgentMessage::Llm(Message::User { content, .. }) => Some(content.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            user,
+            vec![
+                Content::Text {
+                    text: "What is this?".into()
+                },
+                image
+            ]
+        );
+        assert!(conductor.images.is_empty());
+    }
+
     #[tokio::test]
     async fn test_session_persistence() {
         let db = Db::open_memory().unwrap();
@@ -802,6 +2349,8 @@ api_key = "test-key"
         let policy_ref = Arc::new(std::sync::RwLock::new(SecurityPolicy {
             shell_deny_patterns: vec![],
             tool_permissions: HashMap::new(),
+            permissive: true,
+            readable_dirs: vec![],
         }));
 
         let agent = Agent::new(provider)
@@ -826,6 +2375,49 @@ api_key = "test-key"
             injection_heuristic_threshold: 0.6,
             injection_llm_judge_threshold: 0.4,
             injection_extra_patterns: vec![],
+            heuristic_scorer: Default::default(),
+            private_ref: Arc::new(AtomicBool::new(false)),
+            default_model: "mock".to_string(),
+            active_model: "mock".to_string(),
+            default_temperature: None,
+            thinking: Default::default(),
+            active_thinking: ThinkingLevel::Off,
+            retry: crate::config::RetryConfig::default(),
+            fallbacks: None,
+            pricing: None,
+            onboarding: false,
+            progress_sink: Arc::new(std::sync::RwLock::new(None)),
+            background: false,
+            denial_hints: Default::default(),
+            active_skill: Default::default(),
+            skill_namespaces: Default::default(),
+            skill_files: Default::default(),
+            provider_name: "mock".to_string(),
+            run_stats: Default::default(),
+            oversize: Default::default(),
+            questions: Default::default(),
+            approvals: Default::default(),
+            sources: Vec::new(),
+            worker_limits: Arc::new(limits::WorkerLimits::new(
+                &Default::default(),
+                db.clone(),
+                Arc::default(),
+            )),
+            worker_runs: Arc::new(worker_runs::RunLog::new(
+                db.clone(),
+                Arc::default(),
+                Arc::default(),
+                &Default::default(),
+            )),
+            persona: String::new(),
+            persona_fragments: Vec::new(),
+            skills_prompt: String::new(),
+            system_prompt: String::new(),
+            turn_facts: None,
+            timezone: None,
+            images: Vec::new(),
+            turn_watch: Arc::new(latency::TurnWatch::default()),
+            queue_entry: None,
         };
 
         // Send a message
@@ -933,6 +2525,8 @@ api_key = "test-key"
         let policy_ref = Arc::new(std::sync::RwLock::new(SecurityPolicy {
             shell_deny_patterns: vec![],
             tool_permissions: HashMap::new(),
+            permissive: true,
+            readable_dirs: vec![],
         }));
 
         let agent = Agent::new(provider)
@@ -957,6 +2551,49 @@ api_key = "test-key"
             injection_heuristic_threshold: 0.6,
             injection_llm_judge_threshold: 0.4,
             injection_extra_patterns: vec![],
+            heuristic_scorer: Default::default(),
+            private_ref: Arc::new(AtomicBool::new(false)),
+            default_model: "mock".to_string(),
+            active_model: "mock".to_string(),
+            default_temperature: None,
+            thinking: Default::default(),
+            active_thinking: ThinkingLevel::Off,
+            retry: crate::config::RetryConfig::default(),
+            fallbacks: None,
+            pricing: None,
+            onboarding: false,
+            progress_sink: Arc::new(std::sync::RwLock::new(None)),
+            background: false,
+            denial_hints: Default::default(),
+            active_skill: Default::default(),
+            skill_namespaces: Default::default(),
+            skill_files: Default::default(),
+            provider_name: "mock".to_string(),
+            run_stats: Default::default(),
+            oversize: Default::default(),
+            questions: Default::default(),
+            approvals: Default::default(),
+            sources: Vec::new(),
+            worker_limits: Arc::new(limits::WorkerLimits::new(
+                &Default::default(),
+                db.clone(),
+                Arc::default(),
+            )),
+            worker_runs: Arc::new(worker_runs::RunLog::new(
+                db.clone(),
+                Arc::default(),
+                Arc::default(),
+                &Default::default(),
+            )),
+            persona: String::new(),
+            persona_fragments: Vec::new(),
+            skills_prompt: String::new(),
+            system_prompt: String::new(),
+            turn_facts: None,
+            timezone: None,
+            images: Vec::new(),
+            turn_watch: Arc::new(latency::TurnWatch::default()),
+            queue_entry: None,
         };
 
         let response = conductor
@@ -1148,6 +2785,74 @@ api_key = "test-key"
         assert_eq!(captured[1], "Part2"); // After reset, starts fresh
     }
 
+    #[tokio::test]
+    async fn test_stream_response_captures_provider_error() {
+        use tokio::sync::mpsc;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(AgentEvent::AgentEnd {
+            messages: vec![AgentMessage::Llm(Message::Assistant {
+                content: vec![],
+                stop_reason: StopReason::Error,
+                model: "mock".to_string(),
+                provider: "mock".to_string(),
+                usage: Usage::default(),
+                timestamp: 0,
+                error_message: Some("Invalid status code: 529 <unknown status code>".to_string()),
+            })],
+        })
+        .unwrap();
+        drop(tx);
+
+        let result = stream_response(rx, None, None).await;
+        assert!(result.response.is_empty());
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Invalid status code: 529 <unknown status code>")
+        );
+    }
+
+    #[test]
+    fn test_is_overloaded() {
+        assert!(is_overloaded("Rate limited, retry after Some(1000)ms"));
+        assert!(is_overloaded(
+            "Invalid status code: 529 <unknown status code>"
+        ));
+        assert!(is_overloaded(
+            "API error: Google API error 503 Service Unavailable: {}"
+        ));
+        assert!(is_overloaded(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+        ));
+        assert!(!is_overloaded("invalid x-api-key"));
+        assert!(!is_overloaded("Invalid status code: 400 Bad Request"));
+        // Numbers and words in other errors don't count
+        assert!(!is_overloaded(
+            "API error: prompt is 4290 tokens, rate limit unaffected"
+        ));
+        assert!(!is_overloaded(
+            "Tool output mentions an overloaded_error from the upstream service"
+        ));
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_and_caps() {
+        let config = crate::config::RetryConfig {
+            max_attempts: 10,
+            base_delay_ms: 1000,
+            max_delay_ms: 5000,
+            notice_after_ms: 3000,
+        };
+        let ms = |attempt, jitter| backoff_delay(&config, attempt, jitter).as_millis();
+        assert_eq!(ms(1, 0.0), 500);
+        assert_eq!(ms(2, 0.0), 1000);
+        assert_eq!(ms(2, 0.5), 1500);
+        assert_eq!(ms(3, 0.0), 2000);
+        // Capped at max_delay_ms
+        assert_eq!(ms(4, 0.0), 2500);
+        assert_eq!(ms(30, 0.999), 4997);
+    }
+
     #[tokio::test]
     async fn test_group_catchup_preserves_full_tape() {
         let db = Db::open_memory().unwrap();
@@ -1190,6 +2895,8 @@ api_key = "test-key"
         let policy_ref = Arc::new(std::sync::RwLock::new(SecurityPolicy {
             shell_deny_patterns: vec![],
             tool_permissions: HashMap::new(),
+            permissive: true,
+            readable_dirs: vec![],
         }));
 
         let agent = Agent::new(provider)
@@ -1214,6 +2921,49 @@ api_key = "test-key"
             injection_heuristic_threshold: 0.6,
             injection_llm_judge_threshold: 0.4,
             injection_extra_patterns: vec![],
+            heuristic_scorer: Default::default(),
+            private_ref: Arc::new(AtomicBool::new(false)),
+            default_model: "mock".to_string(),
+            active_model: "mock".to_string(),
+            default_temperature: None,
+            thinking: Default::default(),
+            active_thinking: ThinkingLevel::Off,
+            retry: crate::config::RetryConfig::default(),
+            fallbacks: None,
+            pricing: None,
+            onboarding: false,
+            progress_sink: Arc::new(std::sync::RwLock::new(None)),
+            background: false,
+            denial_hints: Default::default(),
+            active_skill: Default::default(),
+            skill_namespaces: Default::default(),
+            skill_files: Default::default(),
+            provider_name: "mock".to_string(),
+            run_stats: Default::default(),
+            oversize: Default::default(),
+            questions: Default::default(),
+            approvals: Default::default(),
+            sources: Vec::new(),
+            worker_limits: Arc::new(limits::WorkerLimits::new(
+                &Default::default(),
+                db.clone(),
+                Arc::default(),
+            )),
+            worker_runs: Arc::new(worker_runs::RunLog::new(
+                db.clone(),
+                Arc::default(),
+                Arc::default(),
+                &Default::default(),
+            )),
+            persona: String::new(),
+            persona_fragments: Vec::new(),
+            skills_prompt: String::new(),
+            system_prompt: String::new(),
+            turn_facts: None,
+            timezone: None,
+            images: Vec::new(),
+            turn_watch: Arc::new(latency::TurnWatch::default()),
+            queue_entry: None,
         };
 
         // Process a group message — should use catchup slicing
@@ -1244,19 +2994,564 @@ api_key = "test-key"
         }
     }
 
+    #[tokio::test]
+    async fn test_private_command_bypasses_agent() {
+        let (mut conductor, db) = test_conductor("agent reply").await;
+
+        let reply = conductor
+            .process_message("tg-1", "/private on", None, None)
+            .await
+            .unwrap();
+        assert!(reply.starts_with("Private mode on"));
+        assert!(db.privacy_is_private("tg-1").await.unwrap());
+        // The command never reached the agent, so nothing was written to tape
+        assert!(db.tape_load_messages("tg-1").await.unwrap().is_empty());
+
+        // The next real message picks up the private flag on session switch
+        conductor
+            .process_message("tg-1", "hello", None, None)
+            .await
+            .unwrap();
+        assert!(conductor.private_ref.load(Ordering::SeqCst));
+
+        let reply = conductor
+            .process_message("tg-1", "/private off", None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Private mode off.");
+        assert!(!conductor.private_ref.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_record_note_appends_to_tape() {
+        let (mut conductor, db) = test_conductor("agent reply").await;
+
+        conductor
+            .process_message("tg-1", "meet at 5", None, None)
+            .await
+            .unwrap();
+        let before = db.tape_load_messages("tg-1").await.unwrap().len();
+        conductor
+            .record_note(
+                "tg-1",
+                "[The user edited an earlier message. It now reads: \"meet at 6\"]",
+            )
+            .await
+            .unwrap();
+
+        let messages = db.tape_load_messages("tg-1").await.unwrap();
+        assert_eq!(messages.len(), before + 1);
+        assert!(matches!(
+            messages.last(),
+            Some(AgentMessage::Llm(Message::User { .. }))
+        ));
+        assert!(conductor.current_session.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_regenerate_replaces_last_exchange() {
+        let (mut conductor, db) = test_conductor("unused").await;
+        conductor.agent = Agent::new(MockProvider::texts(vec!["Paris.", "It's Paris."]))
+            .with_system_prompt("test")
+            .with_model("mock")
+            .with_api_key("test")
+            .without_context_management();
+
+        conductor
+            .process_message("tg-1", "capital of France?", None, None)
+            .await
+            .unwrap();
+        let before = db.tape_load_messages("tg-1").await.unwrap().len();
+
+        let response = conductor
+            .regenerate("tg-1", false, Some("mock-strong"))
+            .await
+            .unwrap();
+        assert_eq!(response.as_deref(), Some("It's Paris."));
+        assert_eq!(conductor.active_model, "mock");
+        let messages = db.tape_load_messages("tg-1").await.unwrap();
+        // Same exchange with the new reply, plus the provenance note
+        assert_eq!(messages.len(), before + 1);
+        assert_eq!(
+            crate::reactions::find_exchange(&messages, Some("Paris.")),
+            None
+        );
+        let AgentMessage::Llm(Message::User { ref content, .. }) = messages[before] else {
+            panic!("expected a note");
+        };
+        assert!(crate::reactions::message_text(content).contains("regenerated by mock-strong"));
+
+        assert_eq!(
+            conductor.regenerate("tg-empty", false, None).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_persona_fragments_rebuild_prompt() {
+        let (mut conductor, db) = test_conductor("unused").await;
+        conductor.agent = Agent::new(MockProvider::texts(vec!["Hi", "Hi"]))
+            .with_model("mock")
+            .with_api_key("test")
+            .without_context_management();
+        let fragment =
+            |name: &str, text: &str, channels: Vec<String>| crate::config::PersonaFragmentConfig {
+                name: name.to_string(),
+                file: None,
+                text: Some(text.to_string()),
+                memories: None,
+                category: None,
+                channels,
+            };
+        conductor.update_persona_fragments(&[
+            fragment("identity", "You are Claw.", vec![]),
+            fragment("telegram", "Keep it short.", vec!["telegram".into()]),
+        ]);
+        db.session_touch("telegram", "tg-1", Some("1"), None, false)
+            .await
+            .unwrap();
+
+        conductor
+            .process_message("tg-1", "hello", None, None)
+            .await
+            .unwrap();
+        assert_eq!(conductor.system_prompt, "You are Claw.\n\nKeep it short.");
+
+        conductor
+            .process_message("dc-2", "hello", None, None)
+            .await
+            .unwrap();
+        assert_eq!(conductor.system_prompt, "You are Claw.");
+    }
+
+    #[test]
+    fn test_retry_command() {
+        assert_eq!(retry_command("/retry"), Some(None));
+        assert_eq!(retry_command("  /retry strong "), Some(Some("strong")));
+        assert_eq!(retry_command("/retrying"), None);
+        assert_eq!(retry_command("please /retry"), None);
+    }
+
+    #[tokio::test]
+    async fn test_pin_and_forget_reply() {
+        let (mut conductor, db) = test_conductor("The meeting moved to 6pm.").await;
+        conductor
+            .process_message("tg-1", "when is the meeting?", None, None)
+            .await
+            .unwrap();
+
+        let id = conductor
+            .pin_reply("tg-1", "The meeting moved to 6pm.")
+            .await
+            .unwrap()
+            .unwrap();
+        let memory = db.memory_get_by_id(id).await.unwrap().unwrap();
+        assert_eq!(memory.content, "The meeting moved to 6pm.");
+        db.privacy_set("tg-2", true).await.unwrap();
+        assert_eq!(conductor.pin_reply("tg-2", "secret").await.unwrap(), None);
+
+        assert!(!conductor
+            .forget_reply("tg-1", "Something else")
+            .await
+            .unwrap());
+        assert!(conductor
+            .forget_reply("tg-1", "The meeting moved to 6pm.")
+            .await
+            .unwrap());
+        assert!(db.tape_load_messages("tg-1").await.unwrap().is_empty());
+        assert!(conductor.current_session.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remember_quote() {
+        let (conductor, db) = test_conductor("unused").await;
+        let quote = crate::channels::Quote {
+            sender: Some("Alice".into()),
+            text: "The office wifi password is hunter2.".into(),
+            timestamp: Some(1_700_000_000_000),
+        };
+        let id = conductor
+            .remember_quote("tg-1", "telegram", &quote)
+            .await
+            .unwrap()
+            .unwrap();
+        let memory = db.memory_get_by_id(id).await.unwrap().unwrap();
+        assert_eq!(memory.content, "The office wifi password is hunter2.");
+        assert_eq!(memory.importance, crate::remember::IMPORTANCE);
+        assert_eq!(memory.tags.as_deref(), Some("remembered"));
+        assert_eq!(
+            memory.source.as_deref(),
+            Some("remember:telegram:Alice:2023-11-14T22:13:20Z")
+        );
+
+        db.privacy_set("tg-2", true).await.unwrap();
+        assert_eq!(
+            conductor
+                .remember_quote("tg-2", "telegram", &quote)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_aside_session() {
+        let (mut conductor, db) = test_conductor("Noted.").await;
+        assert_eq!(conductor.set_aside_session("tg-1").await.unwrap(), None);
+        conductor
+            .process_message("tg-1", "remember the milk", None, None)
+            .await
+            .unwrap();
+
+        let archived = conductor.set_aside_session("tg-1").await.unwrap().unwrap();
+        assert!(archived.starts_with("tg-1~"));
+        assert!(!db.tape_load_messages(&archived).await.unwrap().is_empty());
+        assert!(db.tape_load_messages("tg-1").await.unwrap().is_empty());
+        assert!(conductor.current_session.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_background_commands() {
+        let (mut conductor, db) = test_conductor("agent reply").await;
+
+        let reply = conductor
+            .process_message("tg-1", "/background research flights", None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Background tasks are disabled.");
+
+        conductor.background = true;
+        let reply = conductor
+            .process_message("tg-1", "/background research flights", None, None)
+            .await
+            .unwrap();
+        assert!(reply.starts_with("Queued as background task #"));
+        let reply = conductor
+            .process_message("tg-1", "/jobs", None, None)
+            .await
+            .unwrap();
+        assert!(reply.contains("[pending] research flights"));
+        assert!(db.tape_load_messages("tg-1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_use_command_pins_session() {
+        let (mut conductor, db) = test_conductor("agent reply").await;
+        conductor
+            .direct_workers
+            .insert("coding".into(), Box::new(tools::SendMessageTool));
+
+        let reply = conductor
+            .handle_use_command("dc-1-2", "/use worker coding")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(reply.starts_with("Messages in this chat now go to the 'coding' worker"));
+        assert_eq!(
+            conductor.pinned_worker("dc-1-2").await.unwrap().as_deref(),
+            Some("coding")
+        );
+        assert_eq!(conductor.pinned_worker("tg-1").await.unwrap(), None);
+
+        let reply = conductor
+            .handle_use_command("dc-1-2", "/use worker research")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply, "Unknown worker 'research'. Available: coding");
+        assert!(conductor
+            .handle_use_command("dc-1-2", "/usefully")
+            .await
+            .unwrap()
+            .is_none());
+
+        conductor
+            .handle_use_command("dc-1-2", "/use main")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(conductor.pinned_worker("dc-1-2").await.unwrap(), None);
+
+        // A pin to a worker that was removed from config is ignored
+        db.pin_set("dc-1-2", Some("gone")).await.unwrap();
+        assert_eq!(conductor.pinned_worker("dc-1-2").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_fixture_tool_call_and_chunks() {
+        use fixture::{path, FixtureProvider};
+
+        let (mut conductor, db) = test_conductor("unused").await;
+        conductor.agent = Agent::new(FixtureProvider::load(&path("tool_call.json")).unwrap())
+            .with_model("mock")
+            .with_api_key("test")
+            .with_tools(vec![Box::new(tools::MemorySearchTool::new(db.clone()))])
+            .without_context_management();
+        let chunks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let on_chunk: OnStreamChunk = Box::new({
+            let chunks = chunks.clone();
+            move |text: &str| chunks.lock().unwrap().push(text.to_string())
+        });
+
+        let reply = conductor
+            .process_message("tg-1", "weather?", Some(on_chunk), None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Sunny, 21°C.");
+        let chunks = chunks.lock().unwrap().clone();
+        assert!(chunks.contains(&"Sunny, ".to_string()));
+        assert_eq!(chunks.last().map(String::as_str), Some("Sunny, 21°C."));
+
+        // The tool ran between the two responses
+        let messages = db.tape_load_messages("tg-1").await.unwrap();
+        assert_eq!(last_tool_call(&messages).as_deref(), Some("memory_search"));
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, AgentMessage::Llm(Message::ToolResult { .. }))));
+    }
+
+    #[tokio::test]
+    async fn test_fixture_overloaded_then_retried() {
+        use fixture::{path, FixtureProvider};
+
+        let (mut conductor, _db) = test_conductor("unused").await;
+        conductor.agent = Agent::new(FixtureProvider::load(&path("overloaded.json")).unwrap())
+            .with_model("mock")
+            .with_api_key("test")
+            .without_context_management();
+        conductor.retry = crate::config::RetryConfig {
+            max_attempts: 2,
+            base_delay_ms: 1,
+            max_delay_ms: 1,
+            notice_after_ms: 60_000,
+        };
+
+        let reply = conductor
+            .process_message("tg-1", "hi", None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Back now.");
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_after_tools_continues() {
+        use fixture::FixtureProvider;
+
+        let (mut conductor, db) = test_conductor("unused").await;
+        let provider = FixtureProvider::from_json(
+            r#"{"responses": [
+                {"events": [{"tool_call": {"id": "tc-1", "name": "memory_search", "arguments": {"query": "city"}}}]},
+                {"error": "Invalid status code: 529 <unknown status code>"},
+                {"events": [{"text": "Sunny."}]}
+            ]}"#,
+        )
+        .unwrap();
+        conductor.agent = Agent::new(provider)
+            .with_model("mock")
+            .with_api_key("test")
+            .with_tools(vec![Box::new(tools::MemorySearchTool::new(db.clone()))])
+            .without_context_management();
+        conductor.retry = crate::config::RetryConfig {
+            max_attempts: 2,
+            base_delay_ms: 1,
+            max_delay_ms: 1,
+            notice_after_ms: 60_000,
+        };
+
+        let reply = conductor
+            .process_message("tg-1", "weather?", None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Sunny.");
+        // The retry picked up after the tool instead of running the turn again
+        let messages = db.tape_load_messages("tg-1").await.unwrap();
+        let count = |f: fn(&AgentMessage) -> bool| messages.iter().filter(|m| f(m)).count();
+        assert_eq!(
+            count(|m| matches!(m, AgentMessage::Llm(Message::User { .. }))),
+            1
+        );
+        assert_eq!(
+            count(|m| matches!(m, AgentMessage::Llm(Message::ToolResult { .. }))),
+            1
+        );
+        assert_eq!(
+            count(|m| matches!(
+                m,
+                AgentMessage::Llm(Message::Assistant {
+                    error_message: Some(_),
+                    ..
+                })
+            )),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_falls_back() {
+        let (mut conductor, db) = test_conductor("unused").await;
+        let primary = fixture::FixtureProvider::from_json(
+            r#"{"responses": [{"error": "Invalid status code: 529 <unknown status code>"}]}"#,
+        )
+        .unwrap();
+        let (provider, chain) = fallback::chain(
+            DynProvider::new(primary),
+            "anthropic/mock".into(),
+            vec![fallback::Fallback {
+                label: "openai/local".into(),
+                provider: DynProvider::new(MockProvider::text("From the fallback.")),
+                model: "local".into(),
+                api_key: String::new(),
+            }],
+        );
+        conductor.agent = Agent::new(provider)
+            .with_model("mock")
+            .with_api_key("test")
+            .without_context_management();
+        conductor.fallbacks = Some(chain);
+        // No backoff retries: only the fallback can answer
+        conductor.retry.max_attempts = 1;
+
+        let reply = conductor
+            .process_message("tg-1", "hi", None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "From the fallback.");
+        let audit = db.audit_range(0, crate::db::now_ms() + 1).await.unwrap();
+        let switch = audit
+            .iter()
+            .find(|e| e.event_type == "provider_fallback")
+            .unwrap();
+        assert_eq!(
+            switch.detail.as_deref(),
+            Some("anthropic/mock -> openai/local: Invalid status code: 529 <unknown status code>")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fallback_keeps_tool_results() {
+        let (mut conductor, db) = test_conductor("unused").await;
+        let primary = fixture::FixtureProvider::from_json(
+            r#"{"responses": [
+                {"events": [{"tool_call": {"id": "tc-1", "name": "memory_search", "arguments": {"query": "city"}}}]},
+                {"error": "Invalid status code: 529 <unknown status code>"}
+            ]}"#,
+        )
+        .unwrap();
+        let (provider, chain) = fallback::chain(
+            DynProvider::new(primary),
+            "anthropic/mock".into(),
+            vec![fallback::Fallback {
+                label: "openai/local".into(),
+                provider: DynProvider::new(MockProvider::text("From the fallback.")),
+                model: "local".into(),
+                api_key: String::new(),
+            }],
+        );
+        conductor.agent = Agent::new(provider)
+            .with_model("mock")
+            .with_api_key("test")
+            .with_tools(vec![Box::new(tools::MemorySearchTool::new(db.clone()))])
+            .without_context_management();
+        conductor.fallbacks = Some(chain);
+        conductor.retry.max_attempts = 1;
+
+        let reply = conductor
+            .process_message("tg-1", "weather?", None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "From the fallback.");
+        // The fallback answered from the tool result the primary got
+        let messages = db.tape_load_messages("tg-1").await.unwrap();
+        assert_eq!(last_tool_call(&messages).as_deref(), Some("memory_search"));
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|m| matches!(m, AgentMessage::Llm(Message::User { .. })))
+                .count(),
+            1
+        );
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, AgentMessage::Llm(Message::ToolResult { .. }))));
+    }
+
+    #[tokio::test]
+    async fn test_model_override_and_restore() {
+        let (mut conductor, _db) = test_conductor("ok").await;
+
+        conductor.set_model_override(Some("fast-model"));
+        assert_eq!(conductor.active_model, "fast-model");
+        // Conversation still works after the agent is rebuilt
+        let reply = conductor
+            .process_message("tg-1", "hi", None, None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "ok");
+
+        conductor.set_model_override(None);
+        assert_eq!(conductor.active_model, "mock");
+    }
+
+    #[tokio::test]
+    async fn test_session_temperature() {
+        let (mut conductor, _db) = test_conductor("ok").await;
+        conductor.update_default_temperature(Some(0.7));
+
+        conductor.set_temperature(Some(0.1));
+        // Survives the agent being rebuilt for another model
+        conductor.set_model_override(Some("fast-model"));
+        assert_eq!(conductor.agent.temperature, Some(0.1));
+
+        conductor.set_temperature(None);
+        assert_eq!(conductor.agent.temperature, Some(0.7));
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_unknown_worker() {
+        let (mut conductor, _db) = test_conductor("ok").await;
+        let err = conductor
+            .delegate_to_pipeline("tg-1", &["missing".to_string()], "task")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("'missing' not found"));
+    }
+
+    #[test]
+    fn test_redact_tool_outputs() {
+        let messages = vec![
+            AgentMessage::Llm(Message::user("read my file")),
+            AgentMessage::Llm(Message::ToolResult {
+                tool_call_id: "tc-1".to_string(),
+                tool_name: "read_file".to_string(),
+                content: vec![Content::Text {
+                    text: "top secret".to_string(),
+                }],
+                is_error: false,
+                timestamp: 0,
+            }),
+        ];
+
+        let redacted = redact_tool_outputs(&messages);
+        assert_eq!(redacted.len(), 2);
+        let json = serde_json::to_string(&redacted).unwrap();
+        assert!(!json.contains("top secret"));
+        assert!(json.contains(PRIVATE_TOOL_OUTPUT));
+        assert!(json.contains("tc-1"));
+    }
+
     #[test]
     fn test_resolve_provider_anthropic() {
-        let _p = resolve_provider("anthropic");
+        let _p = resolve_provider("anthropic", &endpoint::Endpoint::default());
     }
 
     #[test]
     fn test_resolve_provider_openai() {
-        let _p = resolve_provider("openai");
+        let _p = resolve_provider("openai", &endpoint::Endpoint::default());
     }
 
     #[test]
     fn test_resolve_provider_unknown_defaults() {
         // Unknown name should not panic — falls back to anthropic
-        let _p = resolve_provider("some-unknown-provider");
+        let _p = resolve_provider("some-unknown-provider", &endpoint::Endpoint::default());
     }
 }
@@ -0,0 +1,471 @@
+//! Provider-hosted tools (web search, code interpreter).
+//!
+//! These run on the provider's side, so yoclaw never sees the individual
+//! searches or executions unless the provider reports them. Each tool makes a
+//! dedicated request to the provider with the hosted tool enabled, returns the
+//! final text to the agent, and writes every provider-side action it finds in
+//! the response to the audit log as a `native_tool` event.
+
+use crate::config::NativeToolsConfig;
+use crate::db::Db;
+use std::sync::{Arc, RwLock};
+use yoagent::types::*;
+
+const ANTHROPIC_URL: &str = "https://api.anthropic.com/v1/messages";
+const OPENAI_URL: &str = "https://api.openai.com/v1/responses";
+const MAX_TOKENS: u32 = 4096;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NativeToolError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Provider returned {status}: {body}")]
+    Api { status: u16, body: String },
+}
+
+/// Which hosted tool to enable for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NativeToolKind {
+    WebSearch,
+    CodeInterpreter,
+}
+
+impl NativeToolKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::WebSearch => "web_search",
+            Self::CodeInterpreter => "code_interpreter",
+        }
+    }
+}
+
+/// Provider API family that hosts the tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NativeProvider {
+    Anthropic,
+    OpenAi,
+}
+
+impl NativeProvider {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "anthropic" => Some(Self::Anthropic),
+            "openai" | "openai_responses" => Some(Self::OpenAi),
+            _ => None,
+        }
+    }
+}
+
+/// Final text plus the provider-side actions reported in the response.
+#[derive(Debug, Default, PartialEq)]
+pub struct NativeToolOutput {
+    pub text: String,
+    pub actions: Vec<String>,
+}
+
+/// HTTP client for a provider's hosted tools.
+pub struct NativeToolClient {
+    provider: NativeProvider,
+    model: String,
+    api_key: String,
+    max_uses: u32,
+    http: reqwest::Client,
+}
+
+impl NativeToolClient {
+    /// Returns None if the provider has no hosted tools.
+    pub fn new(provider: &str, model: &str, api_key: &str, max_uses: u32) -> Option<Self> {
+        Some(Self {
+            provider: NativeProvider::from_name(provider)?,
+            model: model.to_string(),
+            api_key: api_key.to_string(),
+            max_uses,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    pub async fn run(
+        &self,
+        kind: NativeToolKind,
+        input: &str,
+    ) -> Result<NativeToolOutput, NativeToolError> {
+        let request = match self.provider {
+            NativeProvider::Anthropic => {
+                let mut req = self
+                    .http
+                    .post(ANTHROPIC_URL)
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", "2023-06-01");
+                if kind == NativeToolKind::CodeInterpreter {
+                    req = req.header("anthropic-beta", "code-execution-2025-05-22");
+                }
+                req.json(&anthropic_request(&self.model, kind, input, self.max_uses))
+            }
+            NativeProvider::OpenAi => self
+                .http
+                .post(OPENAI_URL)
+                .bearer_auth(&self.api_key)
+                .json(&openai_request(&self.model, kind, input)),
+        };
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(NativeToolError::Api {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(match self.provider {
+            NativeProvider::Anthropic => parse_anthropic(&body),
+            NativeProvider::OpenAi => parse_openai(&body),
+        })
+    }
+}
+
+/// Build the hosted tools enabled in config. Providers without hosted tools
+/// get none, with a warning.
+pub fn build_native_tools(
+    config: &NativeToolsConfig,
+    provider: &str,
+    model: &str,
+    api_key: &str,
+    db: Db,
+    session_id: Arc<RwLock<String>>,
+) -> Vec<Box<dyn AgentTool>> {
+    let mut kinds = Vec::new();
+    if config.web_search {
+        kinds.push(NativeToolKind::WebSearch);
+    }
+    if config.code_interpreter {
+        kinds.push(NativeToolKind::CodeInterpreter);
+    }
+    if kinds.is_empty() {
+        return Vec::new();
+    }
+
+    let model = config.model.as_deref().unwrap_or(model);
+    let Some(client) = NativeToolClient::new(provider, model, api_key, config.max_uses) else {
+        tracing::warn!(
+            "Provider '{}' has no hosted tools; agent.native_tools ignored",
+            provider
+        );
+        return Vec::new();
+    };
+    let client = Arc::new(client);
+
+    kinds
+        .into_iter()
+        .map(|kind| {
+            Box::new(NativeTool {
+                kind,
+                client: client.clone(),
+                db: db.clone(),
+                session_id: session_id.clone(),
+            }) as Box<dyn AgentTool>
+        })
+        .collect()
+}
+
+/// Agent-facing wrapper around one hosted tool.
+pub struct NativeTool {
+    kind: NativeToolKind,
+    client: Arc<NativeToolClient>,
+    db: Db,
+    session_id: Arc<RwLock<String>>,
+}
+
+#[async_trait::async_trait]
+impl AgentTool for NativeTool {
+    fn name(&self) -> &str {
+        self.kind.name()
+    }
+
+    fn label(&self) -> &str {
+        match self.kind {
+            NativeToolKind::WebSearch => "Web Search",
+            NativeToolKind::CodeInterpreter => "Code Interpreter",
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self.kind {
+            NativeToolKind::WebSearch => {
+                "Search the web using the model provider's hosted search. Describe what you \
+                 want to find; returns a summary with source URLs."
+            }
+            NativeToolKind::CodeInterpreter => {
+                "Run code in the model provider's hosted sandbox. Describe the computation or \
+                 include the code to run; returns the result and output."
+            }
+        }
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "request": {
+                    "type": "string",
+                    "description": "What to search for or compute"
+                }
+            },
+            "required": ["request"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        _ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let request = params["request"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidArgs("Missing 'request' parameter".into()))?;
+
+        let output = self
+            .client
+            .run(self.kind, request)
+            .await
+            .map_err(|e| ToolError::Failed(e.to_string()))?;
+
+        // Make provider-side actions visible alongside yoclaw's own tool calls
+        let session_id = self.session_id.read().unwrap().clone();
+        for action in &output.actions {
+            let _ = self
+                .db
+                .audit_log(
+                    Some(&session_id),
+                    "native_tool",
+                    Some(self.kind.name()),
+                    Some(action),
+                    0,
+                )
+                .await;
+        }
+
+        Ok(ToolResult {
+            content: vec![Content::Text { text: output.text }],
+            details: serde_json::json!({ "actions": output.actions }),
+        })
+    }
+}
+
+fn anthropic_request(
+    model: &str,
+    kind: NativeToolKind,
+    input: &str,
+    max_uses: u32,
+) -> serde_json::Value {
+    let tool = match kind {
+        NativeToolKind::WebSearch => serde_json::json!({
+            "type": "web_search_20250305",
+            "name": "web_search",
+            "max_uses": max_uses,
+        }),
+        NativeToolKind::CodeInterpreter => serde_json::json!({
+            "type": "code_execution_20250522",
+            "name": "code_execution",
+        }),
+    };
+    serde_json::json!({
+        "model": model,
+        "max_tokens": MAX_TOKENS,
+        "messages": [{ "role": "user", "content": input }],
+        "tools": [tool],
+    })
+}
+
+fn openai_request(model: &str, kind: NativeToolKind, input: &str) -> serde_json::Value {
+    let tool = match kind {
+        NativeToolKind::WebSearch => serde_json::json!({ "type": "web_search_preview" }),
+        NativeToolKind::CodeInterpreter => serde_json::json!({
+            "type": "code_interpreter",
+            "container": { "type": "auto" },
+        }),
+    };
+    serde_json::json!({
+        "model": model,
+        "input": input,
+        "tools": [tool],
+    })
+}
+
+/// Extract text and hosted tool activity from an Anthropic Messages response.
+fn parse_anthropic(body: &serde_json::Value) -> NativeToolOutput {
+    let mut out = NativeToolOutput::default();
+    let mut texts = Vec::new();
+    for block in body["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => {
+                if let Some(t) = block["text"].as_str() {
+                    texts.push(t.to_string());
+                }
+            }
+            Some("server_tool_use") => {
+                if let Some(q) = block["input"]["query"].as_str() {
+                    out.actions.push(format!("search: {}", q));
+                } else if let Some(code) = block["input"]["code"].as_str() {
+                    out.actions.push(format!("run: {}", first_line(code)));
+                }
+            }
+            Some("web_search_tool_result") => {
+                for result in block["content"].as_array().into_iter().flatten() {
+                    if let Some(url) = result["url"].as_str() {
+                        out.actions.push(format!("result: {}", url));
+                    }
+                }
+            }
+            Some("code_execution_tool_result") => {
+                if let Some(code) = block["content"]["return_code"].as_i64() {
+                    out.actions.push(format!("exit code: {}", code));
+                }
+                if let Some(stdout) = block["content"]["stdout"].as_str() {
+                    if !stdout.is_empty() {
+                        texts.push(format!("Output:\n{}", stdout));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out.text = texts.join("\n");
+    out
+}
+
+/// Extract text and hosted tool activity from an OpenAI Responses response.
+fn parse_openai(body: &serde_json::Value) -> NativeToolOutput {
+    let mut out = NativeToolOutput::default();
+    let mut texts = Vec::new();
+    for item in body["output"].as_array().into_iter().flatten() {
+        match item["type"].as_str() {
+            Some("web_search_call") => {
+                if let Some(q) = item["action"]["query"].as_str() {
+                    out.actions.push(format!("search: {}", q));
+                }
+            }
+            Some("code_interpreter_call") => {
+                if let Some(code) = item["code"].as_str() {
+                    out.actions.push(format!("run: {}", first_line(code)));
+                }
+                for output in item["outputs"].as_array().into_iter().flatten() {
+                    if let Some(logs) = output["logs"].as_str() {
+                        texts.push(format!("Output:\n{}", logs));
+                    }
+                }
+            }
+            Some("message") => {
+                for part in item["content"].as_array().into_iter().flatten() {
+                    if let Some(t) = part["text"].as_str() {
+                        texts.push(t.to_string());
+                    }
+                    for ann in part["annotations"].as_array().into_iter().flatten() {
+                        if let Some(url) = ann["url"].as_str() {
+                            out.actions.push(format!("result: {}", url));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out.text = texts.join("\n");
+    out
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_support() {
+        assert!(NativeToolClient::new("anthropic", "m", "k", 5).is_some());
+        assert!(NativeToolClient::new("openai", "m", "k", 5).is_some());
+        assert!(NativeToolClient::new("bedrock", "m", "k", 5).is_none());
+    }
+
+    #[test]
+    fn test_request_bodies() {
+        let req = anthropic_request("claude", NativeToolKind::WebSearch, "rust news", 3);
+        assert_eq!(req["tools"][0]["type"], "web_search_20250305");
+        assert_eq!(req["tools"][0]["max_uses"], 3);
+        assert_eq!(req["messages"][0]["content"], "rust news");
+
+        let req = openai_request("gpt", NativeToolKind::CodeInterpreter, "2+2");
+        assert_eq!(req["tools"][0]["type"], "code_interpreter");
+        assert_eq!(req["input"], "2+2");
+    }
+
+    #[test]
+    fn test_parse_anthropic_web_search() {
+        let body = serde_json::json!({
+            "content": [
+                {"type": "server_tool_use", "id": "s1", "name": "web_search",
+                 "input": {"query": "rust 2024 edition"}},
+                {"type": "web_search_tool_result", "tool_use_id": "s1", "content": [
+                    {"type": "web_search_result", "url": "https://blog.rust-lang.org/", "title": "Rust Blog"}
+                ]},
+                {"type": "text", "text": "Rust 2024 shipped in 1.85."}
+            ]
+        });
+        let out = parse_anthropic(&body);
+        assert_eq!(out.text, "Rust 2024 shipped in 1.85.");
+        assert_eq!(
+            out.actions,
+            vec![
+                "search: rust 2024 edition".to_string(),
+                "result: https://blog.rust-lang.org/".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_openai_code_interpreter() {
+        let body = serde_json::json!({
+            "output": [
+                {"type": "code_interpreter_call", "code": "print(2+2)\n",
+                 "outputs": [{"type": "logs", "logs": "4"}]},
+                {"type": "message", "content": [
+                    {"type": "output_text", "text": "The answer is 4.", "annotations": []}
+                ]}
+            ]
+        });
+        let out = parse_openai(&body);
+        assert_eq!(out.text, "Output:\n4\nThe answer is 4.");
+        assert_eq!(out.actions, vec!["run: print(2+2)".to_string()]);
+    }
+
+    #[test]
+    fn test_build_native_tools() {
+        let db = Db::open_memory().unwrap();
+        let sid = Arc::new(RwLock::new(String::new()));
+        let config = NativeToolsConfig {
+            web_search: true,
+            ..NativeToolsConfig::default()
+        };
+
+        let tools = build_native_tools(&config, "anthropic", "m", "k", db.clone(), sid.clone());
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name(), "web_search");
+
+        // Unsupported provider: no tools
+        let tools = build_native_tools(&config, "bedrock", "m", "k", db.clone(), sid.clone());
+        assert!(tools.is_empty());
+
+        // Nothing enabled: no tools
+        let tools = build_native_tools(
+            &NativeToolsConfig::default(),
+            "anthropic",
+            "m",
+            "k",
+            db,
+            sid,
+        );
+        assert!(tools.is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//! Incoming messages too large for the context window.
+//!
+//! A message estimated above `max_tokens` (e.g. a pasted log) is not sent as
+//! is, where it would fail at the provider with an opaque error. The agent
+//! gets an excerpt that fits, and the user is warned and offered to save the
+//! full text to a file: if their next message in the session says yes, the
+//! file is written and the agent gets its path, so it can read the parts it
+//! needs. In private sessions nothing is written to disk, so there is no
+//! offer, only the warning.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use yoagent::context::total_tokens;
+use yoagent::types::*;
+
+/// Characters of the original message kept as an excerpt, at most.
+const EXCERPT_CHARS: usize = 8_000;
+
+#[derive(Debug, Clone)]
+pub struct OversizePolicy {
+    /// Largest message, in estimated tokens, sent to the agent unchanged.
+    max_tokens: usize,
+    /// Where oversized messages are saved.
+    dir: PathBuf,
+    /// Oversized messages the user was offered to save, by session.
+    offered: HashMap<String, String>,
+}
+
+impl Default for OversizePolicy {
+    fn default() -> Self {
+        Self::new(
+            yoagent::context::ContextConfig::default().max_context_tokens / 2,
+            crate::config::config_dir().join("uploads"),
+        )
+    }
+}
+
+/// What to send instead of an oversized message.
+#[derive(Debug, PartialEq)]
+pub struct Replacement {
+    /// Prompt for the agent: excerpt, or where the full text is.
+    pub prompt: String,
+    /// Notice for the user, prepended to the reply.
+    pub notice: String,
+}
+
+/// Estimated tokens of `text` as a user message.
+pub fn estimate_tokens(text: &str) -> usize {
+    total_tokens(&[AgentMessage::Llm(Message::user(text))])
+}
+
+/// The start of `text`: at most `EXCERPT_CHARS` characters and half of
+/// `max_tokens`, so the excerpt and its note always fit.
+fn excerpt(text: &str, max_tokens: usize) -> &str {
+    // yoagent estimates 4 bytes per token
+    let max_bytes = max_tokens / 2 * 4;
+    let end = text
+        .char_indices()
+        .take(EXCERPT_CHARS)
+        .map(|(i, c)| i + c.len_utf8())
+        .take_while(|&end| end <= max_bytes)
+        .last()
+        .unwrap_or(0);
+    &text[..end]
+}
+
+impl OversizePolicy {
+    pub fn new(max_tokens: usize, dir: PathBuf) -> Self {
+        Self {
+            max_tokens,
+            dir,
+            offered: HashMap::new(),
+        }
+    }
+
+    /// Replacement for `text` if it is too large or accepts the offer to save
+    /// the previous one, `None` if it goes to the agent as is.
+    pub fn check(
+        &mut self,
+        session_id: &str,
+        text: &str,
+        private: bool,
+    ) -> Result<Option<Replacement>, std::io::Error> {
+        // The offer stands until the next message in the session
+        if let Some(original) = self.offered.remove(session_id) {
+            if !private && crate::security::approval::parse_reply(text) == Some(true) {
+                let path = self.save(session_id, &original)?;
+                return Ok(Some(Replacement {
+                    prompt: format!(
+                        "[As the user asked, their message that was too large for the context \
+                         window (about {} tokens) is saved in full at {}. Read or search the \
+                         parts you need from that file and carry on with their request.]",
+                        estimate_tokens(&original),
+                        path.display()
+                    ),
+                    notice: format!("Saved your message to {}.", path.display()),
+                }));
+            }
+        }
+
+        let tokens = estimate_tokens(text);
+        if tokens <= self.max_tokens {
+            return Ok(None);
+        }
+        let excerpt = excerpt(text, self.max_tokens);
+        let size_kb = text.len().div_ceil(1024);
+        tracing::info!(
+            "Message of ~{} tokens in {} cut to {} bytes",
+            tokens,
+            session_id,
+            excerpt.len()
+        );
+
+        if private {
+            return Ok(Some(Replacement {
+                prompt: format!(
+                    "{}\n\n[The user's message was cut off here: it was about {} tokens ({} KB), \
+                     too large for the context window. Only the beginning is shown.]",
+                    excerpt, tokens, size_kb
+                ),
+                notice: format!(
+                    "Your message was too large (about {} tokens), so I only read the first part of it.",
+                    tokens
+                ),
+            }));
+        }
+
+        self.offered
+            .insert(session_id.to_string(), text.to_string());
+        Ok(Some(Replacement {
+            prompt: format!(
+                "{}\n\n[The user's message was cut off here: it was about {} tokens ({} KB), too \
+                 large for the context window. Only the beginning is shown. The user has been \
+                 offered to save the full text to a file you can read.]",
+                excerpt, tokens, size_kb
+            ),
+            notice: format!(
+                "Your message was too large to read at once (about {} tokens), so I only read the first part of it. \
+                 Reply \"yes\" to save the whole message to a file I can work from.",
+                tokens
+            ),
+        }))
+    }
+
+    /// Write `text` under `dir`, returning the file's path.
+    fn save(&self, session_id: &str, text: &str) -> Result<PathBuf, std::io::Error> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!(
+            "{}-{}.txt",
+            sanitize(session_id),
+            crate::db::now_ms()
+        ));
+        std::fs::write(&path, text)?;
+        tracing::info!("Message in {} saved to {}", session_id, path.display());
+        Ok(path)
+    }
+}
+
+/// File-name-safe form of a session ID.
+fn sanitize(session_id: &str) -> String {
+    session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oversized_messages() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let uploads = tmp.path().join("uploads");
+        let mut policy = OversizePolicy::new(1_000, uploads.clone());
+        assert!(policy.check("tg-1", "hello", false).unwrap().is_none());
+
+        let log = "ERROR connection reset\n".repeat(2_000);
+        assert!(estimate_tokens(&log) > 1_000);
+        let trimmed = policy.check("slack-C1-1.2", &log, false).unwrap().unwrap();
+        assert!(trimmed.prompt.starts_with("ERROR connection reset"));
+        // The excerpt fits the budget, whatever EXCERPT_CHARS allows
+        assert!(estimate_tokens(&trimmed.prompt) <= 1_000);
+        assert!(trimmed.notice.contains("Reply \"yes\""));
+        // Nothing is written before the user accepts
+        assert!(!uploads.exists());
+
+        let saved = policy.check("slack-C1-1.2", "yes", false).unwrap().unwrap();
+        let files: Vec<_> = std::fs::read_dir(&uploads)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert!(files[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("slack-C1-1_2-"));
+        assert_eq!(std::fs::read_to_string(&files[0]).unwrap(), log);
+        assert!(saved.prompt.contains(&files[0].display().to_string()));
+        assert!(saved.notice.starts_with("Saved your message to"));
+        // The offer was used up
+        assert!(policy
+            .check("slack-C1-1.2", "yes", false)
+            .unwrap()
+            .is_none());
+
+        // Any other reply drops the offer
+        policy.check("tg-1", &log, false).unwrap().unwrap();
+        assert!(policy.check("tg-1", "never mind", false).unwrap().is_none());
+        assert!(policy.check("tg-1", "yes", false).unwrap().is_none());
+
+        // Private sessions are trimmed with no offer, and nothing is stored
+        let private = policy.check("tg-2", &log, true).unwrap().unwrap();
+        assert!(private.prompt.contains("was cut off here"));
+        assert!(private.notice.contains("only read the first part"));
+        assert!(!private.notice.contains("yes"));
+        assert!(policy.check("tg-2", "yes", true).unwrap().is_none());
+        assert_eq!(std::fs::read_dir(&uploads).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let text = "é".repeat(10_000);
+        assert_eq!(excerpt(&text, 100_000).chars().count(), EXCERPT_CHARS);
+        // 400 tokens: half of them, 800 bytes, 400 two-byte characters
+        assert_eq!(excerpt(&text, 400).chars().count(), 400);
+        assert_eq!(excerpt(&text, 0), "");
+    }
+}
@@ -0,0 +1,210 @@
+//! System prompt composed from fragments.
+//!
+//! Instead of one persona file, `[[agent.persona_fragments]]` lists the
+//! parts of the system prompt in order: identity, style, safety rules,
+//! notes for one channel, a digest of recent memories. Fragments are
+//! rendered again for every message, so edits to their files, memory
+//! changes and config reloads show up without a restart. Fragment files
+//! that can't be read keep the previous prompt in place.
+
+use crate::config::{Config, PersonaFragmentConfig};
+use crate::db::{Db, DbError};
+use std::path::PathBuf;
+
+/// Used when there is neither a persona file nor fragments.
+pub const DEFAULT_PERSONA: &str = "You are a helpful AI assistant.";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PersonaError {
+    #[error("Can't read persona file {}: {source}", path.display())]
+    File {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Persona fragment '{name}': can't read {}: {source}", path.display())]
+    Read {
+        name: String,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+/// The persona file, or the default persona when there is none.
+pub fn load_file(config: &Config) -> Result<String, std::io::Error> {
+    let path = config.persona_path();
+    if path.exists() {
+        std::fs::read_to_string(&path)
+    } else {
+        Ok(DEFAULT_PERSONA.to_string())
+    }
+}
+
+/// The fragments that apply on `channel`, rendered and joined in order.
+/// Channel-specific fragments are left out when `channel` is None, and so
+/// are fragments that render empty.
+pub async fn render(
+    fragments: &[PersonaFragmentConfig],
+    channel: Option<&str>,
+    db: &Db,
+) -> Result<String, PersonaError> {
+    let mut parts = Vec::new();
+    for fragment in fragments {
+        if !fragment.channels.is_empty()
+            && !channel.is_some_and(|c| fragment.channels.iter().any(|f| f == c))
+        {
+            continue;
+        }
+        let text = render_fragment(fragment, db).await?;
+        let text = text.trim();
+        if !text.is_empty() {
+            parts.push(text.to_string());
+        }
+    }
+    Ok(parts.join("\n\n"))
+}
+
+async fn render_fragment(
+    fragment: &PersonaFragmentConfig,
+    db: &Db,
+) -> Result<String, PersonaError> {
+    if let Some(ref file) = fragment.file {
+        let path = crate::config::config_relative(file);
+        return std::fs::read_to_string(&path).map_err(|source| PersonaError::Read {
+            name: fragment.name.clone(),
+            path,
+            source,
+        });
+    }
+    if let Some(limit) = fragment.memories {
+        // Skill namespaces are the skill's own notes, not about the user
+        let memories: Vec<_> = db
+            .memory_list(limit, fragment.category.as_deref())
+            .await?
+            .into_iter()
+            .filter(|m| m.namespace.is_none())
+            .collect();
+        if memories.is_empty() {
+            return Ok(String::new());
+        }
+        let lines: Vec<String> = memories
+            .iter()
+            .map(|m| format!("- {}", m.content.replace('\n', " ")))
+            .collect();
+        return Ok(format!("Things you remember:\n{}", lines.join("\n")));
+    }
+    Ok(fragment.text.clone().unwrap_or_default())
+}
+
+/// The system prompt as the conductor builds it for a message on
+/// `channel`, before any project context. Used by `yoclaw persona render`.
+pub async fn assemble(
+    config: &Config,
+    channel: Option<&str>,
+    db: &Db,
+) -> Result<String, PersonaError> {
+    let skills_dirs = config.skills_dirs();
+    let skills_refs: Vec<&std::path::Path> = skills_dirs.iter().map(|p| p.as_path()).collect();
+    let policy = crate::security::SecurityPolicy::from_config(&config.security);
+    let (_, skills) = crate::skills::load_filtered_skills(&skills_refs, &policy);
+    let skills_prompt = crate::skills::prompt(&skills, &config.agent.disabled_skills);
+    let persona = if config.agent.persona_fragments.is_empty() {
+        load_file(config).map_err(|source| PersonaError::File {
+            path: config.persona_path(),
+            source,
+        })?
+    } else {
+        render(&config.agent.persona_fragments, channel, db).await?
+    };
+    Ok(with_skills(&persona, &skills_prompt))
+}
+
+/// `persona` followed by the skills section, if any.
+pub fn with_skills(persona: &str, skills_prompt: &str) -> String {
+    if skills_prompt.is_empty() {
+        persona.to_string()
+    } else {
+        format!("{}\n\n{}", persona, skills_prompt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(name: &str) -> PersonaFragmentConfig {
+        PersonaFragmentConfig {
+            name: name.to_string(),
+            file: None,
+            text: None,
+            memories: None,
+            category: None,
+            channels: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render() {
+        let db = Db::open_memory().unwrap();
+        db.memory_store_with_meta(None, "Prefers metric units", None, None, "preference", 5)
+            .await
+            .unwrap();
+        db.memory_store_with_meta(None, "Deploy on Friday", None, None, "task", 5)
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let identity = dir.path().join("identity.md");
+        std::fs::write(&identity, "You are Claw.\n").unwrap();
+
+        let fragments = vec![
+            PersonaFragmentConfig {
+                file: Some(identity.to_string_lossy().into_owned()),
+                ..fragment("identity")
+            },
+            PersonaFragmentConfig {
+                text: Some("Keep replies short.".into()),
+                channels: vec!["telegram".into()],
+                ..fragment("telegram")
+            },
+            PersonaFragmentConfig {
+                memories: Some(5),
+                category: Some("preference".into()),
+                ..fragment("preferences")
+            },
+        ];
+
+        let prompt = render(&fragments, Some("telegram"), &db).await.unwrap();
+        assert_eq!(
+            prompt,
+            "You are Claw.\n\nKeep replies short.\n\nThings you remember:\n- Prefers metric units"
+        );
+        let prompt = render(&fragments, Some("discord"), &db).await.unwrap();
+        assert!(!prompt.contains("Keep replies short."));
+        assert!(!render(&fragments, None, &db)
+            .await
+            .unwrap()
+            .contains("short"));
+
+        // Edits show up on the next render
+        std::fs::write(&identity, "You are Claw, a butler.").unwrap();
+        assert!(render(&fragments, None, &db)
+            .await
+            .unwrap()
+            .starts_with("You are Claw, a butler."));
+
+        std::fs::remove_file(&identity).unwrap();
+        let err = render(&fragments, None, &db).await.unwrap_err();
+        assert!(matches!(err, PersonaError::Read { ref name, .. } if name == "identity"));
+    }
+
+    #[test]
+    fn test_with_skills() {
+        assert_eq!(with_skills("You are Claw.", ""), "You are Claw.");
+        assert_eq!(
+            with_skills("You are Claw.", "## Skills"),
+            "You are Claw.\n\n## Skills"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Processing sessions in parallel.
+//!
+//! A [`Conductor`] handles one message at a time and keeps the session it
+//! last worked on in memory, so messages from different chats used to wait
+//! for each other. With `[agent] parallel_sessions = N` the daemon builds N
+//! conductors ([`conductors`]) and runs each as a worker of a
+//! [`ConductorPool`]. Every session belongs to one worker, picked by a hash
+//! of its ID ([`slot`]): its messages are handled one after another, in the
+//! order they were sent to the pool, and its tape is only ever touched by
+//! that worker's conductor. Sessions of different workers run at once;
+//! sessions that hash to the same worker still take turns.
+//!
+//! The conductors share `ask_user` questions and tool approvals, so answers
+//! reach whichever turn asked, and the daily token count, so the budget
+//! holds across all of them. Turn limits stay per conductor.
+
+use super::{ask, Conductor, DynProvider};
+use crate::config::Config;
+use crate::db::Db;
+use crate::security::approval::Approvals;
+use crate::security::budget::BudgetTracker;
+use futures::future::BoxFuture;
+use std::hash::{Hash, Hasher};
+use tokio::sync::mpsc;
+
+/// State the conductors of a pool have in common.
+#[derive(Clone)]
+pub struct Shared {
+    pub questions: ask::Questions,
+    pub approvals: Approvals,
+    pub budget: BudgetTracker,
+}
+
+impl Conductor {
+    /// Handles for other conductors to share this one's state.
+    fn shared(&self) -> Shared {
+        Shared {
+            questions: self.questions.clone(),
+            approvals: self.approvals.clone(),
+            budget: self.budget.clone(),
+        }
+    }
+}
+
+/// Build `[agent] parallel_sessions` conductors (at least one) sharing
+/// questions, approvals and the daily token count.
+pub async fn conductors(config: &Config, db: Db) -> Result<Vec<Conductor>, anyhow::Error> {
+    let providers = (0..config.agent.parallel_sessions.max(1)).map(|_| None);
+    build_all(config, db, providers.collect()).await
+}
+
+/// Build one conductor per provider, like [`conductors`], each talking to
+/// its provider instead of `[agent] provider`. Used by `yoclaw bench`.
+pub async fn conductors_with_providers(
+    config: &Config,
+    db: Db,
+    providers: Vec<DynProvider>,
+) -> Result<Vec<Conductor>, anyhow::Error> {
+    build_all(config, db, providers.into_iter().map(Some).collect()).await
+}
+
+async fn build_all(
+    config: &Config,
+    db: Db,
+    providers: Vec<Option<DynProvider>>,
+) -> Result<Vec<Conductor>, anyhow::Error> {
+    let mut conductors: Vec<Conductor> = Vec::with_capacity(providers.len());
+    for provider in providers {
+        let shared = conductors.first().map(|first| first.shared());
+        conductors.push(Conductor::build(config, db.clone(), shared.as_ref(), provider).await?);
+    }
+    Ok(conductors)
+}
+
+/// The worker, out of `workers`, that handles `session_id`.
+pub fn slot(session_id: &str, workers: usize) -> usize {
+    // FNV-1a: stable across runs and builds, unlike the std hasher's seed
+    struct Fnv(u64);
+    impl Hasher for Fnv {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+        fn write(&mut self, bytes: &[u8]) {
+            for b in bytes {
+                self.0 = (self.0 ^ *b as u64).wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+    let mut hasher = Fnv(0xcbf29ce484222325);
+    session_id.hash(&mut hasher);
+    (hasher.finish() % workers.max(1) as u64) as usize
+}
+
+/// Workers that each own a conductor (or any other state `S`) and handle the
+/// jobs of their sessions in order.
+pub struct ConductorPool<J> {
+    queues: Vec<mpsc::UnboundedSender<J>>,
+}
+
+impl<J: Send + 'static> ConductorPool<J> {
+    /// Spawn one worker per item of `workers`, each running `handle` on its
+    /// jobs one at a time.
+    pub fn start<S, F>(workers: Vec<S>, handle: F) -> Self
+    where
+        S: Send + 'static,
+        F: for<'a> Fn(&'a mut S, J) -> BoxFuture<'a, ()> + Clone + Send + Sync + 'static,
+    {
+        let queues = workers
+            .into_iter()
+            .map(|mut state| {
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                let handle = handle.clone();
+                tokio::spawn(async move {
+                    while let Some(job) = rx.recv().await {
+                        handle(&mut state, job).await;
+                    }
+                });
+                tx
+            })
+            .collect();
+        Self { queues }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.is_empty()
+    }
+
+    /// Queue `job` with the worker of `session_id`.
+    pub fn send(&self, session_id: &str, job: J) {
+        let queue = &self.queues[slot(session_id, self.queues.len())];
+        if queue.send(job).is_err() {
+            tracing::error!("Worker for {} has stopped", session_id);
+        }
+    }
+
+    /// Queue a job with every worker, behind the jobs they already have.
+    pub fn broadcast(&self, job: impl Fn() -> J) {
+        for queue in &self.queues {
+            let _ = queue.send(job());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn test_slot() {
+        assert_eq!(slot("tg-1", 1), 0);
+        assert_eq!(slot("tg-1", 0), 0);
+        assert_eq!(slot("tg-1", 4), slot("tg-1", 4));
+        assert!(slot("dc-98765", 3) < 3);
+        // Sessions spread over the workers
+        let used: std::collections::HashSet<_> =
+            (0..32).map(|i| slot(&format!("tg-{}", i), 4)).collect();
+        assert_eq!(used.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_run_in_parallel_and_in_order() {
+        // Two sessions on different workers
+        let a = "tg-a".to_string();
+        let b = (0..)
+            .map(|i| format!("tg-{}", i))
+            .find(|s| slot(s, 2) != slot(&a, 2))
+            .unwrap();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let pool = ConductorPool::start(
+            vec![log.clone(), log.clone()],
+            |log: &mut Arc<Mutex<Vec<String>>>, (session, n, delay): (String, u32, u64)| {
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    log.lock().unwrap().push(format!("{}#{}", session, n));
+                })
+            },
+        );
+        assert_eq!(pool.len(), 2);
+
+        // `a` is slow, but doesn't hold up `b`; each keeps its own order
+        pool.send(&a, (a.clone(), 1, 100));
+        pool.send(&a, (a.clone(), 2, 0));
+        pool.send(&b, (b.clone(), 1, 0));
+        pool.send(&b, (b.clone(), 2, 0));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let log = log.lock().unwrap().clone();
+        assert_eq!(
+            log,
+            vec![
+                format!("{}#1", b),
+                format!("{}#2", b),
+                format!("{}#1", a),
+                format!("{}#2", a),
+            ]
+        );
+    }
+}
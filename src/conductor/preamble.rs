@@ -0,0 +1,172 @@
+//! Per-turn context preamble.
+//!
+//! Models don't know what day it is, and the persona can't tell them. Before
+//! every LLM call [`PreambleProvider`] puts a short preamble in front of the
+//! latest user message of the request: the current time in the user's
+//! timezone, the channel and, in groups, how many people take part. The
+//! preamble only goes to the provider; the tape keeps the message as sent,
+//! and the system prompt stays the same from turn to turn, so prompt caching
+//! keeps working.
+
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
+use std::sync::{Arc, RwLock};
+use yoagent::provider::{ProviderError, StreamConfig, StreamEvent, StreamProvider};
+use yoagent::types::*;
+
+/// Memory written by the onboarding interview, e.g.
+/// "The user's timezone: Europe/Berlin".
+pub const TIMEZONE_MEMORY: &str = "profile:timezone";
+
+/// What the preamble says about the message in flight. Set by the conductor
+/// before each message, read by the hook on each turn.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TurnFacts {
+    pub channel: Option<String>,
+    pub is_group: bool,
+    /// Distinct senders seen in the session.
+    pub participants: usize,
+    /// None uses the server's local time.
+    pub timezone: Option<Tz>,
+}
+
+pub type TurnFactsRef = Arc<RwLock<TurnFacts>>;
+
+/// The timezone in an onboarding answer like "The user's timezone:
+/// Europe/Berlin", or in a bare name.
+pub fn parse_timezone(text: &str) -> Option<Tz> {
+    let name = text.rsplit(':').next()?.trim();
+    name.parse().ok()
+}
+
+/// The `[Context]` block for `now`.
+pub fn render(now: DateTime<Utc>, facts: &TurnFacts) -> String {
+    let time = match facts.timezone {
+        Some(tz) => {
+            let local = now.with_timezone(&tz);
+            format!(
+                "{} ({}, UTC{})",
+                local.format("%A, %-d %B %Y, %H:%M"),
+                tz.name(),
+                local.format("%:z")
+            )
+        }
+        None => {
+            let local = now.with_timezone(&Local);
+            format!(
+                "{} (server time, UTC{})",
+                local.format("%A, %-d %B %Y, %H:%M"),
+                local.format("%:z")
+            )
+        }
+    };
+    let mut lines = vec![format!("Current time: {}", time)];
+    if let Some(ref channel) = facts.channel {
+        lines.push(if facts.is_group {
+            format!(
+                "Channel: {} group chat, {} participant{}",
+                channel,
+                facts.participants,
+                if facts.participants == 1 { "" } else { "s" }
+            )
+        } else {
+            format!("Channel: {} direct message", channel)
+        });
+    }
+    lines.push(format!("Assistant: yoclaw {}", env!("CARGO_PKG_VERSION")));
+    format!("[Context]\n{}", lines.join("\n"))
+}
+
+/// `messages` with `preamble` as the first block of the latest user message.
+pub fn inject(mut messages: Vec<Message>, preamble: &str) -> Vec<Message> {
+    let latest = messages.iter_mut().rev().find_map(|m| match m {
+        Message::User { content, .. } => Some(content),
+        _ => None,
+    });
+    if let Some(content) = latest {
+        content.insert(
+            0,
+            Content::Text {
+                text: preamble.to_string(),
+            },
+        );
+    }
+    messages
+}
+
+/// Provider that adds the preamble for the current [`TurnFacts`] to each
+/// request before passing it on.
+pub struct PreambleProvider {
+    pub inner: Box<dyn StreamProvider>,
+    pub facts: TurnFactsRef,
+}
+
+#[async_trait::async_trait]
+impl StreamProvider for PreambleProvider {
+    async fn stream(
+        &self,
+        mut config: StreamConfig,
+        tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Message, ProviderError> {
+        let text = render(Utc::now(), &self.facts.read().unwrap());
+        config.messages = inject(std::mem::take(&mut config.messages), &text);
+        self.inner.stream(config, tx, cancel).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 5, 0).unwrap();
+        let facts = TurnFacts {
+            channel: Some("telegram".into()),
+            is_group: true,
+            participants: 5,
+            timezone: Some(chrono_tz::Europe::Berlin),
+        };
+        let preamble = render(now, &facts);
+        assert!(preamble
+            .contains("Current time: Friday, 16 October 2026, 14:05 (Europe/Berlin, UTC+02:00)"));
+        assert!(preamble.contains("Channel: telegram group chat, 5 participants"));
+        assert!(preamble.contains(env!("CARGO_PKG_VERSION")));
+
+        let dm = TurnFacts {
+            channel: Some("discord".into()),
+            is_group: false,
+            ..facts
+        };
+        assert!(render(now, &dm).contains("Channel: discord direct message"));
+        // Sessions no adapter has recorded
+        assert!(!render(now, &TurnFacts::default()).contains("Channel:"));
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(
+            parse_timezone("The user's timezone: Europe/Berlin"),
+            Some(chrono_tz::Europe::Berlin)
+        );
+        assert_eq!(parse_timezone("UTC"), Some(chrono_tz::UTC));
+        assert_eq!(parse_timezone("The user's timezone: somewhere warm"), None);
+    }
+
+    #[test]
+    fn test_inject() {
+        let messages = vec![Message::user("earlier"), Message::user("what day is it?")];
+        let injected = inject(messages, "[Context]");
+        let Message::User { ref content, .. } = injected[1] else {
+            panic!("expected a user message");
+        };
+        assert!(matches!(&content[0], Content::Text { text } if text == "[Context]"));
+        assert_eq!(content.len(), 2);
+        let Message::User { ref content, .. } = injected[0] else {
+            panic!("expected a user message");
+        };
+        assert_eq!(content.len(), 1);
+    }
+}
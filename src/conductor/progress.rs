@@ -0,0 +1,241 @@
+//! Streaming of partial tool output to the channel.
+//!
+//! Long-running tools (a shell build, a large download) report partial results
+//! through `on_update`, but nothing reached the user until they finished.
+//! [`ProgressToolWrapper`] keeps the last few lines of each update and, at most
+//! once per interval, passes "<tool> output: …" to the stream callback of the
+//! message being processed, which edits the placeholder. Streamed response text
+//! replaces it as soon as the model continues.
+
+use crate::config::ToolProgressConfig;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use yoagent::types::*;
+
+/// A stream callback shared across retry attempts.
+pub type ChunkFn = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Stream callback of the message currently being processed, if any.
+pub type ProgressSink = Arc<RwLock<Option<ChunkFn>>>;
+
+/// Forwards throttled partial output of the wrapped tool to a [`ProgressSink`].
+pub struct ProgressToolWrapper {
+    pub inner: Box<dyn AgentTool>,
+    pub sink: ProgressSink,
+    pub interval: Duration,
+    pub lines: usize,
+}
+
+#[async_trait::async_trait]
+impl AgentTool for ProgressToolWrapper {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn label(&self) -> &str {
+        self.inner.label()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.inner.parameters_schema()
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        mut ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let sink = self.sink.read().unwrap().clone();
+        let Some(sink) = sink else {
+            return self.inner.execute(params, ctx).await;
+        };
+
+        let name = self.inner.name().to_string();
+        let lines = self.lines;
+        let interval = self.interval;
+        let last_edit: Mutex<Option<Instant>> = Mutex::new(None);
+        let original = ctx.on_update.take();
+        ctx.on_update = Some(Arc::new(move |partial: ToolResult| {
+            if let Some(text) = render(&name, &partial, lines) {
+                let mut last = last_edit.lock().unwrap();
+                if last.map_or(true, |t| t.elapsed() >= interval) {
+                    *last = Some(Instant::now());
+                    sink(&text);
+                }
+            }
+            if let Some(ref cb) = original {
+                cb(partial);
+            }
+        }));
+        self.inner.execute(params, ctx).await
+    }
+}
+
+/// Wrap the tools that have progress streaming enabled.
+pub fn wrap_tools(
+    tools: Vec<Box<dyn AgentTool>>,
+    config: &ToolProgressConfig,
+    sink: ProgressSink,
+) -> Vec<Box<dyn AgentTool>> {
+    if !config.enabled {
+        return tools;
+    }
+    tools
+        .into_iter()
+        .map(|tool| {
+            let rule = config.tools.get(tool.name()).cloned().unwrap_or_default();
+            if !rule.enabled.unwrap_or(true) {
+                return tool;
+            }
+            Box::new(ProgressToolWrapper {
+                inner: tool,
+                sink: sink.clone(),
+                interval: Duration::from_millis(rule.interval_ms.unwrap_or(config.interval_ms)),
+                lines: rule.lines.unwrap_or(config.lines),
+            }) as Box<dyn AgentTool>
+        })
+        .collect()
+}
+
+/// Placeholder text for a partial result. Each update is taken as the output
+/// so far, so only its tail is shown. None if there is no text yet.
+fn render(tool: &str, partial: &ToolResult, lines: usize) -> Option<String> {
+    let text: String = partial
+        .content
+        .iter()
+        .filter_map(|c| match c {
+            Content::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let tail = tail_lines(&text, lines);
+    if tail.is_empty() {
+        return None;
+    }
+    Some(format!("{} output:\n```\n{}\n```", tool, tail))
+}
+
+/// Last `n` non-empty lines of `text`.
+fn tail_lines(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|l| l.trim_end())
+        .filter(|l| !l.is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Emits three partial results, then finishes.
+    struct ChattyTool;
+
+    #[async_trait::async_trait]
+    impl AgentTool for ChattyTool {
+        fn name(&self) -> &str {
+            "bash"
+        }
+
+        fn label(&self) -> &str {
+            "Chatty"
+        }
+
+        fn description(&self) -> &str {
+            "test"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({})
+        }
+
+        async fn execute(
+            &self,
+            _params: serde_json::Value,
+            ctx: ToolContext,
+        ) -> Result<ToolResult, ToolError> {
+            let mut output = String::new();
+            for i in 1..=3 {
+                output.push_str(&format!("step {}\n", i));
+                if let Some(ref cb) = ctx.on_update {
+                    cb(ToolResult {
+                        content: vec![Content::Text {
+                            text: output.clone(),
+                        }],
+                        details: serde_json::json!({}),
+                    });
+                }
+            }
+            Ok(ToolResult {
+                content: vec![Content::Text { text: output }],
+                details: serde_json::json!({}),
+            })
+        }
+    }
+
+    fn ctx(on_update: Option<Arc<dyn Fn(ToolResult) + Send + Sync>>) -> ToolContext {
+        ToolContext {
+            tool_call_id: "tc-1".to_string(),
+            tool_name: "bash".to_string(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update,
+            on_progress: None,
+        }
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\n\nc\nd\n", 2), "c\nd");
+        assert_eq!(tail_lines("a\nb", 5), "a\nb");
+        assert_eq!(tail_lines("\n\n", 5), "");
+    }
+
+    #[tokio::test]
+    async fn test_progress_is_throttled_and_forwarded() {
+        let edits = Arc::new(Mutex::new(Vec::<String>::new()));
+        let edits_clone = edits.clone();
+        let on_chunk: Arc<dyn Fn(&str) + Send + Sync> = Arc::new(move |s: &str| {
+            edits_clone.lock().unwrap().push(s.to_string());
+        });
+        let sink: ProgressSink = Arc::new(RwLock::new(Some(on_chunk)));
+        let updates = Arc::new(Mutex::new(0));
+        let updates_clone = updates.clone();
+
+        let config = ToolProgressConfig {
+            interval_ms: 60_000,
+            lines: 1,
+            ..Default::default()
+        };
+        let tools = wrap_tools(vec![Box::new(ChattyTool)], &config, sink.clone());
+        tools[0]
+            .execute(
+                serde_json::json!({}),
+                ctx(Some(Arc::new(move |_: ToolResult| {
+                    *updates_clone.lock().unwrap() += 1
+                }))),
+            )
+            .await
+            .unwrap();
+
+        // Only the first update fits in the interval; the tool's own callback sees all
+        assert_eq!(
+            *edits.lock().unwrap(),
+            vec!["bash output:\n```\nstep 1\n```"]
+        );
+        assert_eq!(*updates.lock().unwrap(), 3);
+
+        // No message in flight: nothing is forwarded
+        *sink.write().unwrap() = None;
+        tools[0]
+            .execute(serde_json::json!({}), ctx(None))
+            .await
+            .unwrap();
+        assert_eq!(edits.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,185 @@
+//! Records what the agent saw on every turn (`[persistence] turn_snapshots`).
+//!
+//! The context is taken in `on_before_turn`, or from the compaction strategy
+//! when it compacts the turn's context (which takes precedence); the snapshot
+//! is written in `on_after_turn` together with the response and token usage.
+//! Private sessions are not recorded.
+
+use crate::db::snapshots::TurnSnapshot;
+use crate::db::Db;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use yoagent::types::*;
+
+const DAY_MS: u64 = 86_400_000;
+
+#[derive(Default)]
+struct Pending {
+    context: Option<Vec<AgentMessage>>,
+    /// Messages dropped by compaction before this turn.
+    compacted: Option<usize>,
+}
+
+pub struct TurnRecorder {
+    db: Db,
+    session_id: Arc<RwLock<String>>,
+    private: Arc<AtomicBool>,
+    system_prompt: String,
+    tools: serde_json::Value,
+    /// Filters fixed at startup; per-turn entries are added on save.
+    filters: serde_json::Value,
+    retention_days: u64,
+    pending: Mutex<Pending>,
+}
+
+impl TurnRecorder {
+    pub fn new(
+        db: Db,
+        session_id: Arc<RwLock<String>>,
+        private: Arc<AtomicBool>,
+        system_prompt: &str,
+        tools: &[Box<dyn AgentTool>],
+        filters: serde_json::Value,
+        retention_days: u64,
+    ) -> Self {
+        let tools = tools
+            .iter()
+            .map(|t| {
+                serde_json::json!({
+                    "name": t.name(),
+                    "description": t.description(),
+                    "parameters": t.parameters_schema(),
+                })
+            })
+            .collect();
+        Self {
+            db,
+            session_id,
+            private,
+            system_prompt: system_prompt.to_string(),
+            tools: serde_json::Value::Array(tools),
+            filters,
+            retention_days,
+            pending: Mutex::new(Pending::default()),
+        }
+    }
+
+    /// Context about to be sent, from `on_before_turn`. Kept only if
+    /// compaction hasn't already provided this turn's context.
+    pub fn before_turn(&self, messages: &[AgentMessage]) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.compacted.is_none() {
+            pending.context = Some(messages.to_vec());
+        }
+    }
+
+    /// Context after compaction dropped `dropped` messages.
+    pub fn compacted(&self, messages: &[AgentMessage], dropped: usize) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.context = Some(messages.to_vec());
+        pending.compacted = Some(dropped);
+    }
+
+    /// Write the snapshot of the turn that just ended, from `on_after_turn`.
+    pub fn after_turn(&self, messages: &[AgentMessage], usage: &Usage) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if self.private.load(Ordering::SeqCst) {
+            return;
+        }
+        let Some(context) = pending.context else {
+            return;
+        };
+        let response = messages
+            .iter()
+            .rev()
+            .find(|m| matches!(m, AgentMessage::Llm(Message::Assistant { .. })))
+            .cloned();
+        let model = match response {
+            Some(AgentMessage::Llm(Message::Assistant { ref model, .. })) => model.clone(),
+            _ => String::new(),
+        };
+        let mut filters = self.filters.clone();
+        filters["compaction"] = match pending.compacted {
+            Some(dropped) => serde_json::json!({ "dropped": dropped }),
+            None => serde_json::Value::Null,
+        };
+
+        let snapshot = TurnSnapshot {
+            session_id: self.session_id.read().unwrap().clone(),
+            turn: 0,
+            model,
+            system_prompt: self.system_prompt.clone(),
+            tools: self.tools.clone(),
+            context,
+            filters,
+            response,
+            input_tokens: usage.input,
+            output_tokens: usage.output,
+            created_at: 0,
+        };
+        let cutoff = crate::db::now_ms().saturating_sub(self.retention_days * DAY_MS);
+        let saved = tokio::task::block_in_place(|| {
+            self.db.snapshot_save_sync(&snapshot)?;
+            self.db.snapshot_prune_sync(cutoff)
+        });
+        if let Err(e) = saved {
+            tracing::warn!("Failed to save turn snapshot: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assistant(text: &str) -> AgentMessage {
+        AgentMessage::Llm(Message::Assistant {
+            content: vec![Content::Text { text: text.into() }],
+            stop_reason: StopReason::Stop,
+            model: "mock".into(),
+            provider: "mock".into(),
+            usage: Usage::default(),
+            timestamp: 0,
+            error_message: None,
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_records_compacted_context() {
+        let db = Db::open_memory().unwrap();
+        let session = Arc::new(RwLock::new("tg-1".to_string()));
+        let private = Arc::new(AtomicBool::new(false));
+        let recorder = TurnRecorder::new(
+            db.clone(),
+            session,
+            private.clone(),
+            "You are a test assistant.",
+            &[],
+            serde_json::json!({ "private": false }),
+            7,
+        );
+
+        let full = vec![
+            AgentMessage::Llm(Message::user("old")),
+            assistant("old reply"),
+            AgentMessage::Llm(Message::user("new")),
+        ];
+        recorder.before_turn(&full);
+        recorder.compacted(&full[2..], 2);
+        let mut after = full.clone();
+        after.push(assistant("new reply"));
+        recorder.after_turn(&after, &Usage::default());
+
+        let turn = db.snapshot_get("tg-1", 1).await.unwrap().unwrap();
+        assert_eq!(turn.context.len(), 1);
+        assert_eq!(turn.model, "mock");
+        assert_eq!(turn.filters["compaction"]["dropped"], 2);
+        assert!(turn.response.is_some());
+
+        // Next turn starts fresh; private turns are not recorded
+        private.store(true, Ordering::SeqCst);
+        recorder.before_turn(&after);
+        recorder.after_turn(&after, &Usage::default());
+        assert_eq!(db.snapshot_list("tg-1").await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,99 @@
+//! Sampling temperature per session.
+//!
+//! `agent.temperature` sets it for every chat; `/temp 0.2` overrides it for
+//! one session until `/temp default`, so a coding chat can stay
+//! deterministic while a brainstorming chat runs hot. The override is kept
+//! in the state table and applied before each message. Without either, the
+//! provider's default is used.
+
+/// Highest temperature accepted; providers differ (Anthropic stops at 1.0,
+/// OpenAI at 2.0) and reject what they don't support themselves.
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// `/temp <value>`, `/temp default`, or `/temp` to show the setting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TempCommand {
+    Set(f32),
+    Reset,
+    Show,
+    /// An argument that isn't a temperature.
+    Invalid,
+}
+
+/// Whether `value` is a temperature the agent can be given.
+pub fn is_valid(value: f32) -> bool {
+    (0.0..=MAX_TEMPERATURE).contains(&value)
+}
+
+/// The `/temp` command in `text`, if it is one.
+pub fn parse_command(text: &str) -> Option<TempCommand> {
+    let mut words = text.split_whitespace();
+    if words.next() != Some("/temp") {
+        return None;
+    }
+    Some(match (words.next(), words.next()) {
+        (None, _) => TempCommand::Show,
+        (Some("default" | "off"), None) => TempCommand::Reset,
+        (Some(value), None) => match value.parse::<f32>() {
+            Ok(value) if is_valid(value) => TempCommand::Set(value),
+            _ => TempCommand::Invalid,
+        },
+        _ => TempCommand::Invalid,
+    })
+}
+
+/// Answer to a `/temp` command. `session` is the session's temperature
+/// after it, `default` the one from `agent.temperature`.
+pub fn command_reply(command: TempCommand, session: Option<f32>, default: Option<f32>) -> String {
+    let default = match default {
+        Some(t) => t.to_string(),
+        None => "the provider's default".to_string(),
+    };
+    match (command, session) {
+        (TempCommand::Invalid, _) => format!(
+            "Usage: /temp <0.0 to {:.1}>, or /temp default to go back to {}.",
+            MAX_TEMPERATURE, default
+        ),
+        (_, Some(t)) => format!(
+            "Temperature in this chat: {}. Send /temp default to go back to {}.",
+            t, default
+        ),
+        (_, None) => format!(
+            "Temperature in this chat: {}. Send /temp <0.0 to {:.1}> to change it.",
+            default, MAX_TEMPERATURE
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/temp 0.2"), Some(TempCommand::Set(0.2)));
+        assert_eq!(parse_command(" /temp  1 "), Some(TempCommand::Set(1.0)));
+        assert_eq!(parse_command("/temp default"), Some(TempCommand::Reset));
+        assert_eq!(parse_command("/temp"), Some(TempCommand::Show));
+        assert_eq!(parse_command("/temp 3"), Some(TempCommand::Invalid));
+        assert_eq!(parse_command("/temp -1"), Some(TempCommand::Invalid));
+        assert_eq!(parse_command("/temp hot"), Some(TempCommand::Invalid));
+        assert_eq!(parse_command("/temp NaN"), Some(TempCommand::Invalid));
+        assert_eq!(parse_command("/temperature 1"), None);
+        assert_eq!(parse_command("set /temp 1"), None);
+    }
+
+    #[test]
+    fn test_command_reply() {
+        assert_eq!(
+            command_reply(TempCommand::Set(0.2), Some(0.2), None),
+            "Temperature in this chat: 0.2. Send /temp default to go back to the provider's \
+             default."
+        );
+        assert_eq!(
+            command_reply(TempCommand::Reset, None, Some(0.7)),
+            "Temperature in this chat: 0.7. Send /temp <0.0 to 2.0> to change it."
+        );
+        assert!(command_reply(TempCommand::Invalid, None, None).starts_with("Usage: /temp"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Thinking level per message.
+//!
+//! One static `agent.thinking` level either wastes thinking tokens on
+//! "thanks!" in a busy group or skimps on a hard debugging question.
+//! [`ThinkingRules`] picks a level for each message from
+//! `[agent.thinking_rules]`: the `deep` level for messages matching
+//! `deep_patterns` (an explicit "think hard" wins even in a group), the
+//! `group` level for group chats, the `quick` level for short messages, and
+//! `agent.thinking` for everything else.
+
+use crate::config::ThinkingRulesConfig;
+use regex::Regex;
+use yoagent::types::ThinkingLevel;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThinkingError {
+    #[error("Invalid thinking level '{0}' (expected off, low, medium or high)")]
+    InvalidLevel(String),
+    #[error("Invalid thinking pattern '{pattern}': {source}")]
+    InvalidPattern {
+        pattern: String,
+        source: regex::Error,
+    },
+}
+
+/// Parse a config level name.
+pub fn parse_level(level: &str) -> Result<ThinkingLevel, ThinkingError> {
+    match level {
+        "off" => Ok(ThinkingLevel::Off),
+        "low" => Ok(ThinkingLevel::Low),
+        "medium" => Ok(ThinkingLevel::Medium),
+        "high" => Ok(ThinkingLevel::High),
+        other => Err(ThinkingError::InvalidLevel(other.to_string())),
+    }
+}
+
+/// Config name of a level.
+pub fn level_name(level: ThinkingLevel) -> &'static str {
+    match level {
+        ThinkingLevel::Off => "off",
+        ThinkingLevel::Low => "low",
+        ThinkingLevel::Medium => "medium",
+        ThinkingLevel::High => "high",
+        #[allow(unreachable_patterns)]
+        _ => "other",
+    }
+}
+
+struct Rules {
+    deep: ThinkingLevel,
+    deep_patterns: Vec<Regex>,
+    group: ThinkingLevel,
+    quick: ThinkingLevel,
+    quick_max_chars: usize,
+}
+
+/// Picks the thinking level for each message.
+pub struct ThinkingRules {
+    /// `agent.thinking`, used when no rule applies.
+    default: ThinkingLevel,
+    rules: Option<Rules>,
+}
+
+impl Default for ThinkingRules {
+    fn default() -> Self {
+        Self {
+            default: ThinkingLevel::Off,
+            rules: None,
+        }
+    }
+}
+
+impl ThinkingRules {
+    /// `thinking` is `agent.thinking`; an unknown name there means off, as it
+    /// always has. Levels and patterns in `rules` must be valid.
+    pub fn compile(
+        thinking: Option<&str>,
+        rules: Option<&ThinkingRulesConfig>,
+    ) -> Result<Self, ThinkingError> {
+        let default = thinking
+            .and_then(|t| parse_level(t).ok())
+            .unwrap_or(ThinkingLevel::Off);
+        let rules = match rules {
+            Some(cfg) => Some(Rules {
+                deep: parse_level(&cfg.deep)?,
+                deep_patterns: cfg
+                    .deep_patterns
+                    .iter()
+                    .map(|p| {
+                        Regex::new(p).map_err(|source| ThinkingError::InvalidPattern {
+                            pattern: p.clone(),
+                            source,
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+                group: parse_level(&cfg.group)?,
+                quick: parse_level(&cfg.quick)?,
+                quick_max_chars: cfg.quick_max_chars,
+            }),
+            None => None,
+        };
+        Ok(Self { default, rules })
+    }
+
+    /// Level from `agent.thinking`.
+    pub fn default_level(&self) -> ThinkingLevel {
+        self.default
+    }
+
+    /// Level for `text`, and the rule that chose it.
+    pub fn level_for(&self, text: &str, is_group: bool) -> (ThinkingLevel, &'static str) {
+        let Some(ref rules) = self.rules else {
+            return (self.default, "default");
+        };
+        if rules.deep_patterns.iter().any(|p| p.is_match(text)) {
+            (rules.deep, "deep")
+        } else if is_group {
+            (rules.group, "group")
+        } else if text.trim().chars().count() <= rules.quick_max_chars {
+            (rules.quick, "quick")
+        } else {
+            (self.default, "default")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_for() {
+        let rules =
+            ThinkingRules::compile(Some("medium"), Some(&ThinkingRulesConfig::default())).unwrap();
+        let level = |text: &str, group: bool| {
+            let (level, rule) = rules.level_for(text, group);
+            (level_name(level), rule)
+        };
+
+        assert_eq!(level("thanks!", false), ("low", "quick"));
+        assert_eq!(level("sounds good, see you at 5", true), ("low", "group"));
+        assert_eq!(
+            level("Can you think hard about why the cache misses?", true),
+            ("high", "deep")
+        );
+        assert_eq!(
+            level("```\nfn main() {}\n```\nwhy does this not compile", false),
+            ("high", "deep")
+        );
+        assert_eq!(
+            level("Please Analyze last month's sales numbers", false),
+            ("high", "deep")
+        );
+        let long = "Could you put together a packing list for a week of hiking in the Alps \
+                    in late September, including what to bring for rain and cold nights?";
+        assert_eq!(level(long, false), ("medium", "default"));
+
+        // Without rules every message gets agent.thinking
+        let rules = ThinkingRules::compile(Some("medium"), None).unwrap();
+        assert_eq!(level_name(rules.level_for("hi", true).0), "medium");
+        assert_eq!(level_name(rules.default_level()), "medium");
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let cfg = ThinkingRulesConfig {
+            group: "maximum".into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ThinkingRules::compile(None, Some(&cfg)),
+            Err(ThinkingError::InvalidLevel(l)) if l == "maximum"
+        ));
+        let cfg = ThinkingRulesConfig {
+            deep_patterns: vec!["(unclosed".into()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            ThinkingRules::compile(None, Some(&cfg)),
+            Err(ThinkingError::InvalidPattern { .. })
+        ));
+        // A bad agent.thinking still means off
+        let rules = ThinkingRules::compile(Some("lots"), None).unwrap();
+        assert_eq!(level_name(rules.default_level()), "off");
+    }
+}
@@ -0,0 +1,267 @@
+//! Summaries of oversized tool outputs.
+//!
+//! `tool_output_max_lines` keeps the start of a tool output and drops the
+//! rest, which often loses the error at the end of a build log or the one
+//! relevant row of a large listing. With `summarize_tool_output`,
+//! [`SummarizingToolWrapper`] sends the middle of any output above
+//! `tool_summary_min_tokens` to the summary model and returns the first and
+//! last lines verbatim around the summary. If summarizing fails the output is
+//! passed on unchanged, so truncation applies as before.
+
+use super::oversize::estimate_tokens;
+use crate::config::Config;
+use crate::scheduler::AgentRunConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use yoagent::types::*;
+
+/// Max characters of the middle section sent to the summarizer.
+const SUMMARY_INPUT_CHARS: usize = 32_000;
+/// The agent waits on the summary, so give up on a slow model quickly.
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SUMMARY_SYSTEM_PROMPT: &str =
+    "You condense tool output for an AI agent that needs its facts, not its bulk.";
+
+#[derive(Debug, Clone)]
+pub struct ToolSummaryPolicy {
+    /// Smallest output, in estimated tokens, that is summarized.
+    pub min_tokens: usize,
+    /// Lines kept verbatim at each end.
+    pub keep_lines: usize,
+    pub agent_config: AgentRunConfig,
+}
+
+impl ToolSummaryPolicy {
+    /// Policy from `[agent.context]`, `None` unless `summarize_tool_output` is set.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let ctx = &config.agent.context;
+        if !ctx.summarize_tool_output {
+            return None;
+        }
+        Some(Self {
+            min_tokens: ctx.tool_summary_min_tokens.unwrap_or(4_000),
+            keep_lines: ctx.tool_summary_keep_lines.unwrap_or(20),
+            agent_config: AgentRunConfig {
+                provider: config.agent.provider.clone(),
+                model: ctx
+                    .summary_model
+                    .clone()
+                    .unwrap_or_else(|| config.scheduler.cortex.model.clone()),
+                api_key: config.agent.api_key.clone(),
+                endpoint: super::endpoint::Endpoint::agent(&config.agent),
+                context: Default::default(),
+                max_tokens: None,
+                stream_provider: None,
+                fallback_providers: Vec::new(),
+            },
+        })
+    }
+}
+
+/// The parts of an oversized output: verbatim head and tail, and the middle
+/// that gets summarized.
+#[derive(Debug, PartialEq)]
+pub struct Split<'a> {
+    pub head: &'a str,
+    pub middle: &'a str,
+    pub tail: &'a str,
+    /// Lines in `middle`.
+    pub middle_lines: usize,
+}
+
+/// Split `text` for summarizing, `None` if it is below `min_tokens` or too
+/// few lines to leave anything between head and tail.
+pub fn split(text: &str, min_tokens: usize, keep_lines: usize) -> Option<Split<'_>> {
+    if estimate_tokens(text) < min_tokens {
+        return None;
+    }
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .filter(|&i| i < text.len())
+        .collect();
+    if starts.len() <= keep_lines * 2 {
+        return None;
+    }
+    let middle_start = starts[keep_lines];
+    let tail_start = starts[starts.len() - keep_lines];
+    Some(Split {
+        head: &text[..middle_start],
+        middle: &text[middle_start..tail_start],
+        tail: &text[tail_start..],
+        middle_lines: starts.len() - keep_lines * 2,
+    })
+}
+
+/// The output the agent sees: head, summary of the middle, tail.
+pub fn compose(split: &Split<'_>, summary: &str) -> String {
+    format!(
+        "{}[{} lines summarized:\n{}\n]\n{}",
+        split.head,
+        split.middle_lines,
+        summary.trim(),
+        split.tail
+    )
+}
+
+/// Summarizes oversized text output of the wrapped tool.
+pub struct SummarizingToolWrapper {
+    pub inner: Box<dyn AgentTool>,
+    pub policy: Arc<ToolSummaryPolicy>,
+}
+
+#[async_trait::async_trait]
+impl AgentTool for SummarizingToolWrapper {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn label(&self) -> &str {
+        self.inner.label()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.inner.parameters_schema()
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let mut result = self.inner.execute(params, ctx).await?;
+        // Only plain text output; images and mixed content are left alone
+        let [Content::Text { text }] = result.content.as_slice() else {
+            return Ok(result);
+        };
+        let Some(parts) = split(text, self.policy.min_tokens, self.policy.keep_lines) else {
+            return Ok(result);
+        };
+        if let Some(summary) = self.summarize(&parts).await {
+            tracing::info!(
+                "Summarized {} lines of {} output",
+                parts.middle_lines,
+                self.inner.name()
+            );
+            let text = compose(&parts, &summary);
+            result.content = vec![Content::Text { text }];
+        }
+        Ok(result)
+    }
+}
+
+impl SummarizingToolWrapper {
+    async fn summarize(&self, parts: &Split<'_>) -> Option<String> {
+        let prompt = format!(
+            "Below is the middle part of the output of the `{}` tool; the first and last \
+             lines are shown to the agent as is. Summarize it in at most 300 words. Quote \
+             every error and warning message, and keep file paths, numbers, identifiers and \
+             other key facts exactly. Reply with the summary only.\n\n{}",
+            self.inner.name(),
+            clip_middle(parts.middle, SUMMARY_INPUT_CHARS)
+        );
+        let result = tokio::time::timeout(
+            SUMMARY_TIMEOUT,
+            crate::scheduler::run_ephemeral_prompt(
+                &self.policy.agent_config,
+                SUMMARY_SYSTEM_PROMPT,
+                &prompt,
+            ),
+        )
+        .await;
+        match result {
+            Ok(Ok(summary)) if !summary.trim().is_empty() => Some(summary),
+            Ok(Ok(_)) => {
+                tracing::warn!("Tool output summary was empty, keeping raw output");
+                None
+            }
+            Ok(Err(e)) => {
+                tracing::warn!("Tool output summary failed, keeping raw output: {}", e);
+                None
+            }
+            Err(_) => {
+                tracing::warn!("Tool output summary timed out, keeping raw output");
+                None
+            }
+        }
+    }
+}
+
+/// `text` cut to about `max` characters by dropping its center.
+fn clip_middle(text: &str, max: usize) -> String {
+    let count = text.chars().count();
+    if count <= max {
+        return text.to_string();
+    }
+    let half = max / 2;
+    let head: String = text.chars().take(half).collect();
+    let tail: String = text.chars().skip(count - half).collect();
+    format!(
+        "{}\n[... {} characters omitted ...]\n{}",
+        head,
+        count - max,
+        tail
+    )
+}
+
+/// Wrap every tool when summarizing is enabled.
+pub fn wrap_tools(
+    tools: Vec<Box<dyn AgentTool>>,
+    policy: Option<ToolSummaryPolicy>,
+) -> Vec<Box<dyn AgentTool>> {
+    let Some(policy) = policy else {
+        return tools;
+    };
+    let policy = Arc::new(policy);
+    tools
+        .into_iter()
+        .map(|tool| {
+            Box::new(SummarizingToolWrapper {
+                inner: tool,
+                policy: policy.clone(),
+            }) as Box<dyn AgentTool>
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_compose() {
+        let log: String = (1..=500)
+            .map(|i| format!("compiling crate_{} v0.1.{}\n", i, i))
+            .collect::<String>()
+            + "error[E0308]: mismatched types at src/main.rs:42\n";
+        assert!(split("short output", 10, 2).is_none());
+        assert!(split(&log, 1_000_000, 2).is_none());
+        // Not enough lines to leave a middle
+        assert!(split("a\nb\nc\nd\n", 0, 2).is_none());
+
+        let parts = split(&log, 1_000, 3).unwrap();
+        assert_eq!(parts.head.lines().count(), 3);
+        assert!(parts.head.starts_with("compiling crate_1 "));
+        assert_eq!(parts.middle_lines, 495);
+        assert_eq!(format!("{}{}{}", parts.head, parts.middle, parts.tail), log);
+        assert!(parts.tail.ends_with("src/main.rs:42\n"));
+
+        let text = compose(&parts, "  Compiled 495 crates without errors.\n");
+        assert!(text.starts_with(parts.head));
+        assert!(text.contains("[495 lines summarized:\nCompiled 495 crates without errors.\n]\n"));
+        assert!(text.ends_with("error[E0308]: mismatched types at src/main.rs:42\n"));
+        assert!(estimate_tokens(&text) < estimate_tokens(&log) / 10);
+    }
+
+    #[test]
+    fn test_clip_middle() {
+        assert_eq!(clip_middle("short", 10), "short");
+        let clipped = clip_middle(&"ab".repeat(50), 20);
+        assert!(clipped.starts_with("ababababab\n[... 80 characters omitted ...]\n"));
+        assert!(clipped.ends_with("ababababab"));
+    }
+}
@@ -1,16 +1,136 @@
+use crate::db::projects::ItemKind;
 use crate::db::Db;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use yoagent::types::*;
 
+/// Skill name → memory namespace, from the manifests of enabled skills.
+/// Rebuilt when skills are enabled or disabled.
+pub type SkillNamespaces = Arc<std::sync::RwLock<HashMap<String, String>>>;
+
+/// Skill file → skill name, for enabled skills. Rebuilt like
+/// [`SkillNamespaces`].
+pub type SkillFiles = Arc<std::sync::RwLock<HashMap<std::path::PathBuf, String>>>;
+
+/// Skill the agent is following in the current message: the last one whose
+/// file it read. Cleared for every message.
+pub type ActiveSkill = Arc<std::sync::RwLock<Option<String>>>;
+
+/// Resolve the memory namespace of a memory tool call: that of the skill
+/// named in its `skill` parameter, else that of the skill being followed.
+/// Skills without a declared namespace use general memory.
+fn resolve_namespace(
+    namespaces: &SkillNamespaces,
+    active: &ActiveSkill,
+    params: &serde_json::Value,
+) -> Option<String> {
+    let active = active.read().unwrap();
+    params["skill"]
+        .as_str()
+        .or(active.as_deref())
+        .and_then(|skill| namespaces.read().unwrap().get(skill).cloned())
+}
+
+/// Wraps `read_file` to note which skill the agent follows: reading a
+/// skill's file makes it the [`ActiveSkill`].
+pub struct SkillReadTracker {
+    pub inner: Box<dyn AgentTool>,
+    pub files: SkillFiles,
+    pub active: ActiveSkill,
+}
+
+/// Wrap the `read_file` tool in `tools` with a [`SkillReadTracker`].
+pub fn track_skill_reads(
+    tools: Vec<Box<dyn AgentTool>>,
+    files: SkillFiles,
+    active: ActiveSkill,
+) -> Vec<Box<dyn AgentTool>> {
+    tools
+        .into_iter()
+        .map(|tool| -> Box<dyn AgentTool> {
+            if tool.name() == "read_file" {
+                Box::new(SkillReadTracker {
+                    inner: tool,
+                    files: files.clone(),
+                    active: active.clone(),
+                })
+            } else {
+                tool
+            }
+        })
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl AgentTool for SkillReadTracker {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn label(&self) -> &str {
+        self.inner.label()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.inner.parameters_schema()
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let skill = params["path"].as_str().and_then(|path| {
+            let path = std::path::Path::new(path);
+            let files = self.files.read().unwrap();
+            files
+                .get(path)
+                .or_else(|| {
+                    std::fs::canonicalize(path)
+                        .ok()
+                        .and_then(|path| files.get(&path))
+                })
+                .cloned()
+        });
+        let result = self.inner.execute(params, ctx).await;
+        if let (Some(skill), Ok(_)) = (skill, &result) {
+            *self.active.write().unwrap() = Some(skill);
+        }
+        result
+    }
+}
+
 /// Tool for searching the agent's long-term memory via FTS5 (with temporal decay).
 pub struct MemorySearchTool {
     db: Db,
+    namespaces: SkillNamespaces,
+    active: ActiveSkill,
 }
 
 impl MemorySearchTool {
     pub fn new(db: Db) -> Self {
-        Self { db }
+        Self {
+            db,
+            namespaces: Arc::default(),
+            active: Arc::default(),
+        }
+    }
+
+    /// Scope searches made on behalf of a skill to its memory namespace.
+    pub fn with_skill_namespaces(mut self, namespaces: SkillNamespaces) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// Scope searches to the namespace of the skill being followed.
+    pub fn with_active_skill(mut self, active: ActiveSkill) -> Self {
+        self.active = active;
+        self
     }
 }
 
@@ -26,7 +146,8 @@ impl AgentTool for MemorySearchTool {
 
     fn description(&self) -> &str {
         "Search the agent's long-term memory. Results are ranked by relevance with temporal decay \
-         (task memories fade faster than preferences/decisions). Returns category and importance metadata."
+         (task memories fade faster than preferences/decisions). Returns category and importance metadata. \
+         While following a skill, the search covers that skill's memories."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -40,6 +161,10 @@ impl AgentTool for MemorySearchTool {
                 "limit": {
                     "type": "integer",
                     "description": "Maximum number of results to return (default: 10)"
+                },
+                "skill": {
+                    "type": "string",
+                    "description": "Optional name of the skill being followed, if not the one whose file was read last; scopes the search to its memory namespace"
                 }
             },
             "required": ["query"]
@@ -55,10 +180,11 @@ impl AgentTool for MemorySearchTool {
             .as_str()
             .ok_or_else(|| ToolError::InvalidArgs("Missing 'query' parameter".into()))?;
         let limit = params["limit"].as_u64().unwrap_or(10) as usize;
+        let namespace = resolve_namespace(&self.namespaces, &self.active, &params);
 
         let results = self
             .db
-            .memory_search(query, limit)
+            .memory_search_scoped(query, limit, namespace.as_deref())
             .await
             .map_err(|e| ToolError::Failed(e.to_string()))?;
 
@@ -99,11 +225,47 @@ impl AgentTool for MemorySearchTool {
 /// Tool for storing information in the agent's long-term memory.
 pub struct MemoryStoreTool {
     db: Db,
+    /// Set while the current session is in private mode; writes are refused.
+    private: Arc<AtomicBool>,
+    namespaces: SkillNamespaces,
+    active: ActiveSkill,
+    /// Session in flight; new memories join the project it has selected.
+    session_id: Option<Arc<std::sync::RwLock<String>>>,
 }
 
 impl MemoryStoreTool {
     pub fn new(db: Db) -> Self {
-        Self { db }
+        Self {
+            db,
+            private: Arc::new(AtomicBool::new(false)),
+            namespaces: Arc::default(),
+            active: Arc::default(),
+            session_id: None,
+        }
+    }
+
+    /// Add stored memories to the project selected in the current session.
+    pub fn with_session_ref(mut self, session_id: Arc<std::sync::RwLock<String>>) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+
+    /// Store memories made on behalf of a skill in its memory namespace.
+    pub fn with_skill_namespaces(mut self, namespaces: SkillNamespaces) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// Store memories in the namespace of the skill being followed.
+    pub fn with_active_skill(mut self, active: ActiveSkill) -> Self {
+        self.active = active;
+        self
+    }
+
+    /// Share the conductor's private-session flag with this tool.
+    pub fn with_private_flag(mut self, private: Arc<AtomicBool>) -> Self {
+        self.private = private;
+        self
     }
 }
 
@@ -120,7 +282,8 @@ impl AgentTool for MemoryStoreTool {
     fn description(&self) -> &str {
         "Save information to long-term memory with optional category and importance. Categories: \
          fact, preference, decision, event, task, reflection. Importance: 1-10 (higher = more important, \
-         less likely to be pruned). Decisions never decay; tasks decay in ~7 days; preferences persist ~90 days."
+         less likely to be pruned). Decisions never decay; tasks decay in ~7 days; preferences persist ~90 days. \
+         While following a skill, the memory is kept with that skill."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -147,6 +310,10 @@ impl AgentTool for MemoryStoreTool {
                 "importance": {
                     "type": "integer",
                     "description": "Importance score 1-10 (default: 5). Higher = more important, less likely to be pruned."
+                },
+                "skill": {
+                    "type": "string",
+                    "description": "Optional name of the skill being followed, if not the one whose file was read last; stores into its memory namespace"
                 }
             },
             "required": ["content"]
@@ -158,6 +325,12 @@ impl AgentTool for MemoryStoreTool {
         params: serde_json::Value,
         _ctx: ToolContext,
     ) -> Result<ToolResult, ToolError> {
+        if self.private.load(Ordering::SeqCst) {
+            return Err(ToolError::Failed(
+                "This session is in private mode; memory writes are disabled.".into(),
+            ));
+        }
+
         let content = params["content"]
             .as_str()
             .ok_or_else(|| ToolError::InvalidArgs("Missing 'content' parameter".into()))?;
@@ -165,12 +338,37 @@ impl AgentTool for MemoryStoreTool {
         let tags = params["tags"].as_str();
         let category = params["category"].as_str().unwrap_or("fact");
         let importance = params["importance"].as_i64().unwrap_or(5) as i32;
+        let namespace = resolve_namespace(&self.namespaces, &self.active, &params);
 
-        self.db
-            .memory_store_with_meta(key, content, tags, Some("agent"), category, importance)
+        let id = self
+            .db
+            .memory_store_scoped(
+                namespace.as_deref(),
+                key,
+                content,
+                tags,
+                Some("agent"),
+                category,
+                importance,
+            )
             .await
             .map_err(|e| ToolError::Failed(e.to_string()))?;
 
+        if let Some(ref session_id) = self.session_id {
+            let session_id = session_id.read().unwrap().clone();
+            let project = self
+                .db
+                .project_selected(&session_id)
+                .await
+                .map_err(|e| ToolError::Failed(e.to_string()))?;
+            if let Some(project) = project {
+                self.db
+                    .project_add(&project, ItemKind::Memory, &id.to_string())
+                    .await
+                    .map_err(|e| ToolError::Failed(e.to_string()))?;
+            }
+        }
+
         let msg = match key {
             Some(k) => format!(
                 "Stored {} memory (importance: {}) with key '{}'.",
@@ -187,7 +385,8 @@ impl AgentTool for MemoryStoreTool {
 }
 
 /// Tool that lets the agent send a message to the user mid-task via progress events.
-/// The message is delivered immediately through the channel adapter, NOT stored in tape.
+/// The message is delivered immediately through the channel adapter; after the turn,
+/// [`record_interim_messages`] adds it to the tape as interim assistant text.
 pub struct SendMessageTool;
 
 #[async_trait::async_trait]
@@ -242,11 +441,77 @@ impl AgentTool for SendMessageTool {
     }
 }
 
+/// Marks assistant text recorded from a `send_message` call rather than written
+/// as a reply.
+pub const INTERIM_PREFIX: &str = "[Sent mid-task] ";
+
+/// Whether `text` is an interim message recorded by [`record_interim_messages`].
+pub fn is_interim(text: &str) -> bool {
+    text.starts_with(INTERIM_PREFIX)
+}
+
+/// Copy each successful `send_message` call in `messages[from..]` into the
+/// assistant message that made it, as interim text just before the call.
+/// Only the call's arguments held the text before, which compaction,
+/// transcripts and summaries don't read, so the agent lost track of what it had
+/// told the user. Returns None if there was nothing to record.
+pub fn record_interim_messages(
+    messages: &[AgentMessage],
+    from: usize,
+) -> Option<Vec<AgentMessage>> {
+    let failed: Vec<&str> = messages[from..]
+        .iter()
+        .filter_map(|m| match m {
+            AgentMessage::Llm(Message::ToolResult {
+                tool_call_id,
+                tool_name,
+                is_error: true,
+                ..
+            }) if tool_name == "send_message" => Some(tool_call_id.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut recorded = messages.to_vec();
+    let mut changed = false;
+    for msg in &mut recorded[from..] {
+        let AgentMessage::Llm(Message::Assistant { content, .. }) = msg else {
+            continue;
+        };
+        let mut i = 0;
+        while i < content.len() {
+            let interim = match &content[i] {
+                Content::ToolCall {
+                    id,
+                    name,
+                    arguments,
+                } if name == "send_message" && !failed.contains(&id.as_str()) => arguments
+                    ["message"]
+                    .as_str()
+                    .map(|m| format!("{}{}", INTERIM_PREFIX, m)),
+                _ => None,
+            };
+            if let Some(text) = interim {
+                let already =
+                    i > 0 && matches!(&content[i - 1], Content::Text { text: t } if *t == text);
+                if !already {
+                    content.insert(i, Content::Text { text });
+                    changed = true;
+                    i += 1;
+                }
+            }
+            i += 1;
+        }
+    }
+    changed.then_some(recorded)
+}
+
 // ---------------------------------------------------------------------------
 // Dynamic Worker Tools
 // ---------------------------------------------------------------------------
 
 /// Tool for spawning a dynamic worker sub-agent at runtime.
+#[derive(Clone)]
 pub struct SpawnWorkerTool {
     db: Db,
     provider: Arc<dyn yoagent::provider::StreamProvider>,
@@ -256,6 +521,10 @@ pub struct SpawnWorkerTool {
     active_count: Arc<AtomicUsize>,
     max_concurrent: usize,
     max_turns: usize,
+    limits: Arc<super::limits::WorkerLimits>,
+    runs: Arc<super::worker_runs::RunLog>,
+    /// Nesting level of the agent holding this tool (0 for the main agent).
+    depth: usize,
 }
 
 /// Config for creating a SpawnWorkerTool.
@@ -268,6 +537,8 @@ pub struct SpawnWorkerConfig {
     pub active_count: Arc<AtomicUsize>,
     pub max_concurrent: usize,
     pub max_turns: usize,
+    pub limits: Arc<super::limits::WorkerLimits>,
+    pub runs: Arc<super::worker_runs::RunLog>,
 }
 
 impl SpawnWorkerTool {
@@ -281,6 +552,9 @@ impl SpawnWorkerTool {
             active_count: config.active_count,
             max_concurrent: config.max_concurrent,
             max_turns: config.max_turns,
+            limits: config.limits,
+            runs: config.runs,
+            depth: 0,
         }
     }
 }
@@ -355,6 +629,13 @@ impl AgentTool for SpawnWorkerTool {
             }
         };
 
+        // Per-message depth, spawn and token limits
+        let depth = self.depth + 1;
+        self.limits
+            .admit(name, depth)
+            .await
+            .map_err(|hit| ToolError::Failed(hit.to_string()))?;
+
         // Check concurrent limit
         let current = self.active_count.fetch_add(1, Ordering::SeqCst);
         if current >= self.max_concurrent {
@@ -370,13 +651,22 @@ impl AgentTool for SpawnWorkerTool {
             on_progress(format!("Spawning worker '{}'...", name));
         }
 
+        // Workers may start workers of their own while below the depth limit
+        let mut tools = self.worker_tools.clone();
+        if depth < self.limits.max_depth() {
+            tools.push(Arc::new(Self {
+                depth,
+                ..self.clone()
+            }));
+        }
+
         // Build and run ephemeral sub-agent
         let sub = yoagent::sub_agent::SubAgentTool::new(name, self.provider.clone())
             .with_system_prompt(&system_prompt)
             .with_model(&self.model)
             .with_api_key(&self.api_key)
             .with_max_turns(self.max_turns)
-            .with_tools(self.worker_tools.clone());
+            .with_tools(tools);
 
         let sub_ctx = ToolContext {
             tool_call_id: ctx.tool_call_id.clone(),
@@ -386,8 +676,14 @@ impl AgentTool for SpawnWorkerTool {
             on_progress: ctx.on_progress.clone(),
         };
 
-        let result = sub
-            .execute(serde_json::json!({"task": task}), sub_ctx)
+        let result = self
+            .runs
+            .record(
+                name,
+                task,
+                &ctx.tool_call_id,
+                sub.execute(serde_json::json!({"task": task}), sub_ctx),
+            )
             .await;
 
         // Decrement active count
@@ -546,6 +842,23 @@ mod tests {
     use super::*;
     use crate::db::Db;
 
+    fn test_limits() -> Arc<crate::conductor::limits::WorkerLimits> {
+        Arc::new(crate::conductor::limits::WorkerLimits::new(
+            &Default::default(),
+            Db::open_memory().unwrap(),
+            Arc::default(),
+        ))
+    }
+
+    fn test_runs() -> Arc<crate::conductor::worker_runs::RunLog> {
+        Arc::new(crate::conductor::worker_runs::RunLog::new(
+            Db::open_memory().unwrap(),
+            Arc::default(),
+            Arc::default(),
+            &Default::default(),
+        ))
+    }
+
     fn test_ctx() -> ToolContext {
         ToolContext {
             tool_call_id: "test".to_string(),
@@ -580,6 +893,144 @@ mod tests {
         assert!(content_text(&result.content[0]).contains("dark mode"));
     }
 
+    #[tokio::test]
+    async fn test_memory_store_refused_in_private_session() {
+        let db = Db::open_memory().unwrap();
+        let private = Arc::new(AtomicBool::new(true));
+        let store = MemoryStoreTool::new(db.clone()).with_private_flag(private.clone());
+
+        let result = store
+            .execute(serde_json::json!({"content": "secret"}), test_ctx())
+            .await;
+        assert!(result.unwrap_err().to_string().contains("private mode"));
+        assert!(db.memory_search("secret", 10).await.unwrap().is_empty());
+
+        // Writes resume once the flag is cleared
+        private.store(false, Ordering::SeqCst);
+        store
+            .execute(serde_json::json!({"content": "public"}), test_ctx())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_joins_selected_project() {
+        let db = Db::open_memory().unwrap();
+        let session = Arc::new(std::sync::RwLock::new("tg-1".to_string()));
+        let store = MemoryStoreTool::new(db.clone()).with_session_ref(session.clone());
+        db.project_select("tg-1", Some("rewrite")).await.unwrap();
+
+        store
+            .execute(
+                serde_json::json!({"content": "Queue moves to SQLite"}),
+                test_ctx(),
+            )
+            .await
+            .unwrap();
+        *session.write().unwrap() = "tg-2".to_string();
+        store
+            .execute(serde_json::json!({"content": "Unrelated"}), test_ctx())
+            .await
+            .unwrap();
+
+        let memories: Vec<_> = db
+            .project_items("rewrite")
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|i| i.kind == ItemKind::Memory)
+            .collect();
+        assert_eq!(memories.len(), 1);
+        let id = memories[0].item.parse().unwrap();
+        let memory = db.memory_get_by_id(id).await.unwrap().unwrap();
+        assert_eq!(memory.content, "Queue moves to SQLite");
+    }
+
+    #[tokio::test]
+    async fn test_skill_memory_uses_namespace() {
+        let db = Db::open_memory().unwrap();
+        let namespaces: SkillNamespaces = Arc::new(std::sync::RwLock::new(HashMap::from([(
+            "inbox-triage".to_string(),
+            "inbox".to_string(),
+        )])));
+        let store = MemoryStoreTool::new(db.clone()).with_skill_namespaces(namespaces.clone());
+        let search = MemorySearchTool::new(db.clone()).with_skill_namespaces(namespaces);
+
+        store
+            .execute(
+                serde_json::json!({"content": "Newsletter senders go to archive", "skill": "inbox-triage"}),
+                test_ctx(),
+            )
+            .await
+            .unwrap();
+
+        // Not visible in general memory
+        assert!(db.memory_search("newsletter", 10).await.unwrap().is_empty());
+        let result = search
+            .execute(serde_json::json!({"query": "newsletter"}), test_ctx())
+            .await
+            .unwrap();
+        assert!(content_text(&result.content[0]).contains("No memories"));
+
+        // Visible when searching as the skill
+        let result = search
+            .execute(
+                serde_json::json!({"query": "newsletter", "skill": "inbox-triage"}),
+                test_ctx(),
+            )
+            .await
+            .unwrap();
+        assert!(content_text(&result.content[0]).contains("archive"));
+    }
+
+    #[tokio::test]
+    async fn test_reading_skill_file_scopes_memory() {
+        let db = Db::open_memory().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let skill_file = dir.path().join("SKILL.md");
+        std::fs::write(&skill_file, "---\nname: inbox-triage\n---\nSort mail.").unwrap();
+        let namespaces: SkillNamespaces = Arc::new(std::sync::RwLock::new(HashMap::from([(
+            "inbox-triage".to_string(),
+            "inbox".to_string(),
+        )])));
+        let active = ActiveSkill::default();
+        let tools = track_skill_reads(
+            vec![Box::new(yoagent::tools::ReadFileTool::new())],
+            Arc::new(std::sync::RwLock::new(HashMap::from([(
+                skill_file.clone(),
+                "inbox-triage".to_string(),
+            )]))),
+            active.clone(),
+        );
+        let store = MemoryStoreTool::new(db.clone())
+            .with_skill_namespaces(namespaces)
+            .with_active_skill(active.clone());
+
+        tools[0]
+            .execute(
+                serde_json::json!({"path": skill_file.to_str().unwrap()}),
+                test_ctx(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(active.read().unwrap().as_deref(), Some("inbox-triage"));
+
+        // Without a `skill` parameter the memory still goes to the skill
+        store
+            .execute(
+                serde_json::json!({"content": "Newsletter senders go to archive"}),
+                test_ctx(),
+            )
+            .await
+            .unwrap();
+        assert!(db.memory_search("newsletter", 10).await.unwrap().is_empty());
+        let scoped = db
+            .memory_search_scoped("newsletter", 10, Some("inbox"))
+            .await
+            .unwrap();
+        assert_eq!(scoped.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_send_message_tool_with_progress() {
         let tool = SendMessageTool;
@@ -607,6 +1058,68 @@ mod tests {
         assert_eq!(captured[0], "Processing step 1...");
     }
 
+    #[test]
+    fn test_record_interim_messages() {
+        let call = |id: &str, message: &str| Content::ToolCall {
+            id: id.into(),
+            name: "send_message".into(),
+            arguments: serde_json::json!({ "message": message }),
+        };
+        let assistant = |content: Vec<Content>| {
+            AgentMessage::Llm(Message::Assistant {
+                content,
+                stop_reason: StopReason::ToolUse,
+                model: "mock".into(),
+                provider: "mock".into(),
+                usage: Usage::default(),
+                timestamp: 0,
+                error_message: None,
+            })
+        };
+        let result = |id: &str, is_error: bool| {
+            AgentMessage::Llm(Message::ToolResult {
+                tool_call_id: id.into(),
+                tool_name: "send_message".into(),
+                content: vec![Content::Text {
+                    text: "Message sent.".into(),
+                }],
+                is_error,
+                timestamp: 0,
+            })
+        };
+        let messages = vec![
+            AgentMessage::Llm(Message::user("Deploy it")),
+            assistant(vec![
+                Content::Text {
+                    text: "Starting.".into(),
+                },
+                call("c1", "Building now, this takes a minute."),
+            ]),
+            result("c1", false),
+            assistant(vec![call("c2", "Never delivered")]),
+            result("c2", true),
+        ];
+
+        let recorded = record_interim_messages(&messages, 0).unwrap();
+        assert_eq!(recorded.len(), messages.len());
+        let AgentMessage::Llm(Message::Assistant { content, .. }) = &recorded[1] else {
+            panic!("expected assistant message");
+        };
+        assert_eq!(content.len(), 3);
+        assert!(matches!(&content[1], Content::Text { text }
+            if text == "[Sent mid-task] Building now, this takes a minute."));
+        assert!(matches!(&content[2], Content::ToolCall { .. }));
+        // Failed sends are not recorded
+        let AgentMessage::Llm(Message::Assistant { content, .. }) = &recorded[3] else {
+            panic!("expected assistant message");
+        };
+        assert_eq!(content.len(), 1);
+
+        // Recording again, or outside the turn, changes nothing
+        assert!(record_interim_messages(&recorded, 0).is_none());
+        assert!(record_interim_messages(&messages, 3).is_none());
+    }
+
     #[tokio::test]
     async fn test_send_message_tool_without_progress() {
         let tool = SendMessageTool;
@@ -664,6 +1177,8 @@ mod tests {
             active_count: active_count.clone(),
             max_concurrent: 3,
             max_turns: 10,
+            limits: test_limits(),
+            runs: test_runs(),
         });
 
         let result = tool
@@ -700,6 +1215,8 @@ mod tests {
             active_count,
             max_concurrent: 3,
             max_turns: 10,
+            limits: test_limits(),
+            runs: test_runs(),
         });
 
         let result = tool
@@ -796,6 +1313,8 @@ mod tests {
             active_count,
             max_concurrent: 3,
             max_turns: 10,
+            limits: test_limits(),
+            runs: test_runs(),
         });
 
         // Spawn without system_prompt — should use saved definition
@@ -830,6 +1349,8 @@ mod tests {
             active_count,
             max_concurrent: 3,
             max_turns: 10,
+            limits: test_limits(),
+            runs: test_runs(),
         });
 
         let result = tool
@@ -845,6 +1366,52 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("No system_prompt"));
     }
+
+    #[tokio::test]
+    async fn test_spawn_worker_message_limits() {
+        use yoagent::provider::MockProvider;
+
+        let db = Db::open_memory().unwrap();
+        let limits = Arc::new(crate::conductor::limits::WorkerLimits::new(
+            &crate::config::WorkersConfig {
+                max_spawns_per_message: 1,
+                ..Default::default()
+            },
+            db.clone(),
+            Arc::default(),
+        ));
+        let tool = SpawnWorkerTool::new(SpawnWorkerConfig {
+            db,
+            provider: Arc::new(MockProvider::text("ok")),
+            model: "mock".into(),
+            api_key: "test".into(),
+            worker_tools: vec![],
+            active_count: Arc::new(AtomicUsize::new(0)),
+            max_concurrent: 3,
+            max_turns: 10,
+            limits: limits.clone(),
+            runs: test_runs(),
+        });
+        let params = serde_json::json!({"name": "w", "system_prompt": "test", "task": "do stuff"});
+
+        // A worker's own spawn_worker is one level deeper than the default max_depth of 1
+        let nested = SpawnWorkerTool {
+            depth: 1,
+            ..tool.clone()
+        };
+        let err = nested
+            .execute(params.clone(), test_ctx())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("depth limit"));
+
+        tool.execute(params.clone(), test_ctx()).await.unwrap();
+        let err = tool.execute(params.clone(), test_ctx()).await.unwrap_err();
+        assert!(err.to_string().contains("Worker limit for this message"));
+
+        limits.reset();
+        tool.execute(params, test_ctx()).await.unwrap();
+    }
 }
 
 /// Helper: extract text from Content (test-only).
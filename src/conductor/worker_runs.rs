@@ -0,0 +1,279 @@
+//! Transcripts of worker runs (`[persistence] worker_runs`).
+//!
+//! A worker's sub-agent hands only its final text back to the agent that
+//! called it; its tool calls, their results and any provider error would be
+//! lost. [`RunLog::record`] wraps each run (configured workers, dynamic
+//! workers and direct delegation) and stores it in the `worker_runs` table
+//! with the session and the tool call that started it. The transcript is
+//! collected by [`RecordingProvider`], which sees every request the
+//! sub-agent makes: the run in progress is found through a task-local, so
+//! concurrent and nested runs each keep their own. Private sessions are not
+//! recorded.
+
+use crate::config::PersistenceConfig;
+use crate::db::worker_runs::RunStatus;
+use crate::db::Db;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use yoagent::provider::{ProviderError, StreamConfig, StreamEvent, StreamProvider};
+use yoagent::types::*;
+
+const DAY_MS: u64 = 86_400_000;
+
+tokio::task_local! {
+    static RUN: Arc<Mutex<Transcript>>;
+}
+
+/// What the provider has seen of the run in progress.
+#[derive(Default)]
+struct Transcript {
+    id: i64,
+    messages: Vec<Message>,
+    error: Option<String>,
+    tokens: u64,
+}
+
+/// Records worker runs of the current session.
+pub struct RunLog {
+    db: Db,
+    session_id: Arc<RwLock<String>>,
+    private: Arc<AtomicBool>,
+    enabled: bool,
+    retention_days: u64,
+}
+
+impl RunLog {
+    pub fn new(
+        db: Db,
+        session_id: Arc<RwLock<String>>,
+        private: Arc<AtomicBool>,
+        config: &PersistenceConfig,
+    ) -> Self {
+        Self {
+            db,
+            session_id,
+            private,
+            enabled: config.worker_runs,
+            retention_days: config.worker_run_days,
+        }
+    }
+
+    /// Run `worker` on `task` by awaiting `run`, storing its transcript.
+    /// Recording failures are logged; the run's result is returned as is.
+    pub async fn record<F>(
+        &self,
+        worker: &str,
+        task: &str,
+        tool_call_id: &str,
+        run: F,
+    ) -> Result<ToolResult, ToolError>
+    where
+        F: Future<Output = Result<ToolResult, ToolError>>,
+    {
+        if !self.enabled || self.private.load(Ordering::SeqCst) {
+            return run.await;
+        }
+        let session = self.session_id.read().unwrap().clone();
+        let parent = RUN.try_with(|run| run.lock().unwrap().id).ok();
+        let id = match self
+            .db
+            .worker_run_start(&session, tool_call_id, parent, worker, task)
+            .await
+        {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::warn!("Failed to record worker run of '{}': {}", worker, e);
+                return run.await;
+            }
+        };
+
+        let transcript = Arc::new(Mutex::new(Transcript {
+            id,
+            ..Default::default()
+        }));
+        let result = RUN.scope(transcript.clone(), run).await;
+        let transcript = std::mem::take(&mut *transcript.lock().unwrap());
+
+        let (status, output, error) = outcome(&result, &transcript);
+        let messages: Vec<AgentMessage> = transcript
+            .messages
+            .into_iter()
+            .map(AgentMessage::Llm)
+            .collect();
+        let saved = self
+            .db
+            .worker_run_finish(
+                id,
+                status,
+                output.as_deref(),
+                error.as_deref(),
+                &messages,
+                transcript.tokens,
+            )
+            .await;
+        if let Err(e) = saved {
+            tracing::warn!("Failed to record worker run of '{}': {}", worker, e);
+        }
+        let cutoff = crate::db::now_ms().saturating_sub(self.retention_days * DAY_MS);
+        if let Err(e) = self.db.worker_run_prune(cutoff).await {
+            tracing::warn!("Failed to prune worker runs: {}", e);
+        }
+        result
+    }
+}
+
+/// Status, final text and error of a finished run. A run fails when the
+/// worker returned an error, or when its last request to the provider did.
+fn outcome(
+    result: &Result<ToolResult, ToolError>,
+    transcript: &Transcript,
+) -> (RunStatus, Option<String>, Option<String>) {
+    let last_error = transcript.messages.last().and_then(|m| match m {
+        Message::Assistant {
+            stop_reason: StopReason::Error,
+            error_message,
+            ..
+        } => Some(error_message.clone().unwrap_or_default()),
+        _ => None,
+    });
+    match result {
+        Err(e) => (RunStatus::Failed, None, Some(e.to_string())),
+        Ok(result) => {
+            let text = result
+                .content
+                .iter()
+                .filter_map(|c| match c {
+                    Content::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            match transcript.error.clone().or(last_error) {
+                Some(error) => (RunStatus::Failed, Some(text), Some(error)),
+                None => (RunStatus::Done, Some(text), None),
+            }
+        }
+    }
+}
+
+/// Worker provider that keeps the transcript of the run it is called for.
+pub struct RecordingProvider {
+    pub inner: Arc<dyn StreamProvider>,
+}
+
+#[async_trait::async_trait]
+impl StreamProvider for RecordingProvider {
+    async fn stream(
+        &self,
+        config: StreamConfig,
+        tx: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+        cancel: tokio_util::sync::CancellationToken,
+    ) -> Result<Message, ProviderError> {
+        let Ok(run) = RUN.try_with(|run| run.clone()) else {
+            return self.inner.stream(config, tx, cancel).await;
+        };
+        let context = config.messages.clone();
+        let result = self.inner.stream(config, tx, cancel).await;
+        let mut run = run.lock().unwrap();
+        // Each request carries the whole conversation so far
+        run.messages = context;
+        match result {
+            Ok(ref message) => {
+                if let Message::Assistant { ref usage, .. } = message {
+                    run.tokens += usage.input + usage.output;
+                }
+                run.messages.push(message.clone());
+                run.error = None;
+            }
+            Err(ref e) => run.error = Some(e.to_string()),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::provider::MockProvider;
+    use yoagent::sub_agent::SubAgentTool;
+
+    fn run_log(db: &Db, private: bool) -> RunLog {
+        RunLog::new(
+            db.clone(),
+            Arc::new(RwLock::new("tg-1".into())),
+            Arc::new(AtomicBool::new(private)),
+            &PersistenceConfig::default(),
+        )
+    }
+
+    fn ctx() -> ToolContext {
+        ToolContext {
+            tool_call_id: "call-1".into(),
+            tool_name: "research".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_transcript() {
+        let db = Db::open_memory().unwrap();
+        let runs = run_log(&db, false);
+        let worker = SubAgentTool::new(
+            "research",
+            Arc::new(RecordingProvider {
+                inner: Arc::new(MockProvider::text("found it")),
+            }),
+        )
+        .with_model("mock")
+        .with_api_key("test");
+
+        let result = runs
+            .record(
+                "research",
+                "find the docs",
+                "call-1",
+                worker.execute(serde_json::json!({"task": "find the docs"}), ctx()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.content.len(), 1);
+
+        let recorded = db.worker_run_list(Some("tg-1"), false, 10).await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        let run = &recorded[0];
+        assert_eq!(run.status, "done");
+        assert_eq!(run.tool_call_id, "call-1");
+        assert_eq!(run.result.as_deref(), Some("found it"));
+        // The task and the reply
+        assert_eq!(run.messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_and_private_runs() {
+        let db = Db::open_memory().unwrap();
+        let runs = run_log(&db, false);
+        let err = runs
+            .record("research", "x", "call-1", async {
+                Err(ToolError::Failed("boom".into()))
+            })
+            .await;
+        assert!(err.is_err());
+        let failed = db.worker_run_list(None, true, 10).await.unwrap();
+        assert_eq!(failed[0].error.as_deref(), Some("boom"));
+
+        let private = run_log(&db, true);
+        private
+            .record("research", "secret", "call-2", async {
+                Ok(ToolResult {
+                    content: Vec::new(),
+                    details: serde_json::Value::Null,
+                })
+            })
+            .await
+            .unwrap();
+        assert_eq!(db.worker_run_list(None, false, 10).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,134 @@
+use super::{Db, DbError};
+use std::collections::BTreeMap;
+
+/// Activity on one channel on one UTC day.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DailyActivity {
+    /// UTC date, e.g. "2026-10-16".
+    pub date: String,
+    pub channel: String,
+    /// Inbound messages.
+    pub messages: u64,
+    /// Distinct senders of those messages.
+    pub senders: u64,
+    /// LLM tokens used in the channel's sessions.
+    pub tokens: u64,
+}
+
+impl Db {
+    /// Messages, senders and tokens per UTC day and channel since
+    /// `since_ms`, ordered by date then channel. Tokens are attributed to a
+    /// channel through the sessions table.
+    pub async fn daily_activity(&self, since_ms: u64) -> Result<Vec<DailyActivity>, DbError> {
+        self.exec_read(move |conn| {
+            let mut days: BTreeMap<(String, String), DailyActivity> = BTreeMap::new();
+
+            let mut stmt = conn.prepare(
+                "SELECT date(created_at / 1000, 'unixepoch') AS day, channel,
+                        COUNT(*), COUNT(DISTINCT sender_id)
+                 FROM queue WHERE created_at >= ?1
+                 GROUP BY day, channel",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![since_ms as i64], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, i64>(3)? as u64,
+                ))
+            })?;
+            for row in rows {
+                let (date, channel, messages, senders) = row?;
+                let day = days.entry((date, channel)).or_default();
+                day.messages = messages;
+                day.senders = senders;
+            }
+
+            let mut stmt = conn.prepare(
+                "SELECT date(a.timestamp / 1000, 'unixepoch') AS day, s.channel,
+                        COALESCE(SUM(a.tokens_used), 0)
+                 FROM audit a JOIN sessions s ON s.session_id = a.session_id
+                 WHERE a.timestamp >= ?1 AND a.tokens_used > 0
+                 GROUP BY day, s.channel",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![since_ms as i64], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)? as u64,
+                ))
+            })?;
+            for row in rows {
+                let (date, channel, tokens) = row?;
+                days.entry((date, channel)).or_default().tokens = tokens;
+            }
+
+            Ok(days
+                .into_iter()
+                .map(|((date, channel), day)| DailyActivity {
+                    date,
+                    channel,
+                    ..day
+                })
+                .collect())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queue::QueueEntry;
+
+    #[tokio::test]
+    async fn test_daily_activity() {
+        let db = Db::open_memory().unwrap();
+        for sender in ["alice", "bob", "alice"] {
+            db.queue_push(&QueueEntry::new("telegram", sender, "tg-1", "hi"))
+                .await
+                .unwrap();
+        }
+        db.queue_push(&QueueEntry::new("discord", "carol", "dc-1", "hi"))
+            .await
+            .unwrap();
+        db.session_touch("telegram", "tg-1", None, None, true)
+            .await
+            .unwrap();
+        db.audit_log(Some("tg-1"), "llm_usage", None, None, 1200)
+            .await
+            .unwrap();
+        db.audit_log(Some("tg-1"), "llm_usage", None, None, 300)
+            .await
+            .unwrap();
+        // Not in the sessions table, so no channel to count it under
+        db.audit_log(Some("unknown"), "llm_usage", None, None, 50)
+            .await
+            .unwrap();
+
+        let days = db.daily_activity(0).await.unwrap();
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        assert_eq!(
+            days,
+            vec![
+                DailyActivity {
+                    date: today.clone(),
+                    channel: "discord".into(),
+                    messages: 1,
+                    senders: 1,
+                    tokens: 0,
+                },
+                DailyActivity {
+                    date: today,
+                    channel: "telegram".into(),
+                    messages: 3,
+                    senders: 2,
+                    tokens: 1500,
+                },
+            ]
+        );
+
+        let later = crate::db::now_ms() + 1000;
+        assert!(db.daily_activity(later).await.unwrap().is_empty());
+    }
+}
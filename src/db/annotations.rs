@@ -0,0 +1,147 @@
+use super::{now_ms, Db, DbError};
+use serde::Serialize;
+use yoagent::types::AgentMessage;
+
+/// An operator's label on one tape message, e.g. "hallucination" or
+/// "great answer", for building eval and tuning datasets.
+#[derive(Debug, Clone, Serialize)]
+pub struct Annotation {
+    pub id: i64,
+    pub session_id: String,
+    /// Position of the message in the session's tape when it was annotated.
+    pub message_idx: usize,
+    pub note: String,
+    pub tags: Vec<String>,
+    /// Copy of the annotated message, so the label keeps its context after
+    /// compaction rewrites the tape.
+    pub message: serde_json::Value,
+    pub created_at: u64,
+}
+
+impl Db {
+    /// Annotate message `message_idx` of a session's tape. Returns the new
+    /// annotation's id.
+    pub async fn annotation_add(
+        &self,
+        session_id: &str,
+        message_idx: usize,
+        note: &str,
+        tags: &[String],
+        message: &AgentMessage,
+    ) -> Result<i64, DbError> {
+        let session_id = session_id.to_string();
+        let note = note.to_string();
+        let tags = serde_json::to_string(tags)?;
+        let message = serde_json::to_string(message)?;
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO annotations (session_id, message_idx, note, tags, message, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![session_id, message_idx as i64, note, tags, message, ts],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await
+    }
+
+    /// Annotations in order of session, message and creation, optionally of
+    /// one session and with one tag.
+    pub async fn annotation_list(
+        &self,
+        session_id: Option<&str>,
+        tag: Option<&str>,
+    ) -> Result<Vec<Annotation>, DbError> {
+        let session_id = session_id.map(|s| s.to_string());
+        let tag = tag.map(|t| t.to_string());
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, session_id, message_idx, note, tags, message, created_at
+                 FROM annotations
+                 WHERE ?1 IS NULL OR session_id = ?1
+                 ORDER BY session_id, message_idx, id",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![session_id], |row| {
+                    let tags: String = row.get(4)?;
+                    let message: String = row.get(5)?;
+                    Ok(Annotation {
+                        id: row.get(0)?,
+                        session_id: row.get(1)?,
+                        message_idx: row.get::<_, i64>(2)? as usize,
+                        note: row.get(3)?,
+                        tags: serde_json::from_str(&tags).unwrap_or_default(),
+                        message: serde_json::from_str(&message).unwrap_or_default(),
+                        created_at: row.get::<_, i64>(6)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows
+                .into_iter()
+                .filter(|a| tag.as_ref().map_or(true, |t| a.tags.contains(t)))
+                .collect())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::types::Message;
+
+    #[tokio::test]
+    async fn test_annotations() {
+        let db = Db::open_memory().unwrap();
+        let message = AgentMessage::Llm(Message::user("what's the capital of Australia?"));
+        db.annotation_add("tg-1", 3, "", &["great answer".into()], &message)
+            .await
+            .unwrap();
+        let id = db
+            .annotation_add(
+                "tg-1",
+                1,
+                "made up a citation",
+                &["hallucination".into()],
+                &message,
+            )
+            .await
+            .unwrap();
+        db.annotation_add("tg-2", 0, "", &["hallucination".into()], &message)
+            .await
+            .unwrap();
+
+        let all = db.annotation_list(Some("tg-1"), None).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].id, id);
+        assert_eq!(all[0].note, "made up a citation");
+        assert_eq!(
+            serde_json::from_value::<AgentMessage>(all[0].message.clone())
+                .unwrap()
+                .role(),
+            "user"
+        );
+
+        let tagged = db
+            .annotation_list(None, Some("hallucination"))
+            .await
+            .unwrap();
+        assert_eq!(tagged.len(), 2);
+
+        db.tape_save_messages("tg-1", &[message]).await.unwrap();
+        db.tape_rename_session("tg-1", "tg-1-old").await.unwrap();
+        assert_eq!(
+            db.annotation_list(Some("tg-1-old"), None)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        db.tape_delete_session("tg-1-old").await.unwrap();
+        assert!(db
+            .annotation_list(Some("tg-1-old"), None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 use super::{now_ms, Db, DbError};
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AuditEntry {
     pub id: Option<i64>,
     pub session_id: Option<String>,
@@ -11,6 +12,17 @@ pub struct AuditEntry {
     pub timestamp: u64,
 }
 
+/// Audit entries of one UTC day, event type and tool, rolled up.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditDay {
+    pub day: String,
+    pub event_type: String,
+    pub tool_name: Option<String>,
+    pub events: u64,
+    pub tokens_used: u64,
+    pub cost_usd: f64,
+}
+
 impl Db {
     /// Log an audit event.
     pub async fn audit_log(
@@ -21,25 +33,124 @@ impl Db {
         detail: Option<&str>,
         tokens_used: u64,
     ) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage
+                .audit_log(
+                    session_id,
+                    None,
+                    event_type,
+                    tool_name,
+                    detail,
+                    tokens_used,
+                    None,
+                )
+                .await;
+        }
         let session_id = session_id.map(|s| s.to_string());
         let event_type = event_type.to_string();
         let tool_name = tool_name.map(|s| s.to_string());
         let detail = detail.map(|s| s.to_string());
-        let ts = now_ms();
         self.exec(move |conn| {
-            conn.execute(
-                "INSERT INTO audit (session_id, event_type, tool_name, detail, tokens_used, timestamp)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                rusqlite::params![
-                    session_id,
-                    event_type,
-                    tool_name,
-                    detail,
-                    tokens_used as i64,
-                    ts as i64,
-                ],
+            insert_audit(
+                conn,
+                session_id.as_deref(),
+                None,
+                &event_type,
+                tool_name.as_deref(),
+                detail.as_deref(),
+                tokens_used,
+                None,
+            )
+        })
+        .await
+    }
+
+    /// Log an audit event from sync code (agent callbacks, compaction).
+    /// Call inside `block_in_place` when on a tokio worker thread.
+    pub fn audit_log_sync(
+        &self,
+        session_id: Option<&str>,
+        event_type: &str,
+        tool_name: Option<&str>,
+        detail: Option<&str>,
+        tokens_used: u64,
+    ) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return Self::block_on(storage.audit_log(
+                session_id,
+                None,
+                event_type,
+                tool_name,
+                detail,
+                tokens_used,
+                None,
+            ));
+        }
+        self.exec_sync(|conn| {
+            insert_audit(
+                conn,
+                session_id,
+                None,
+                event_type,
+                tool_name,
+                detail,
+                tokens_used,
+                None,
+            )
+        })
+    }
+
+    /// Log a turn's model usage and its estimated cost, charged to
+    /// `sender_id` if given, from sync code like
+    /// [`audit_log_sync`](Self::audit_log_sync).
+    pub fn audit_usage_sync(
+        &self,
+        session_id: &str,
+        sender_id: Option<&str>,
+        detail: &str,
+        tokens_used: u64,
+        cost_usd: Option<f64>,
+    ) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return Self::block_on(storage.audit_log(
+                Some(session_id),
+                sender_id,
+                "llm_usage",
+                None,
+                Some(detail),
+                tokens_used,
+                cost_usd,
+            ));
+        }
+        self.exec_sync(|conn| {
+            insert_audit(
+                conn,
+                Some(session_id),
+                sender_id,
+                "llm_usage",
+                None,
+                Some(detail),
+                tokens_used,
+                cost_usd,
+            )
+        })
+    }
+
+    /// All audit entries for a session, oldest first.
+    pub async fn audit_session_events(&self, session_id: &str) -> Result<Vec<AuditEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.audit_session_events(session_id).await;
+        }
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, session_id, event_type, tool_name, detail, tokens_used, timestamp
+                 FROM audit WHERE session_id = ?1 ORDER BY timestamp, id",
             )?;
-            Ok(())
+            let rows = stmt
+                .query_map(rusqlite::params![session_id], row_to_entry)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
         })
         .await
     }
@@ -50,8 +161,11 @@ impl Db {
         session_id: Option<&str>,
         limit: usize,
     ) -> Result<Vec<AuditEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.audit_query(session_id, limit).await;
+        }
         let session_id = session_id.map(|s| s.to_string());
-        self.exec(move |conn| {
+        self.exec_read(move |conn| {
             let (sql, params): (&str, Vec<Box<dyn rusqlite::types::ToSql>>) = match &session_id {
                 Some(sid) => (
                     "SELECT id, session_id, event_type, tool_name, detail, tokens_used, timestamp
@@ -71,15 +185,89 @@ impl Db {
             let params_refs: Vec<&dyn rusqlite::types::ToSql> =
                 params.iter().map(|p| p.as_ref()).collect();
             let rows = stmt
-                .query_map(params_refs.as_slice(), |row| {
-                    Ok(AuditEntry {
-                        id: Some(row.get(0)?),
-                        session_id: row.get(1)?,
-                        event_type: row.get(2)?,
-                        tool_name: row.get(3)?,
-                        detail: row.get(4)?,
-                        tokens_used: row.get::<_, i64>(5)? as u64,
-                        timestamp: row.get::<_, i64>(6)? as u64,
+                .query_map(params_refs.as_slice(), row_to_entry)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// All audit entries in `[since_ms, until_ms)`, oldest first.
+    pub async fn audit_range(
+        &self,
+        since_ms: u64,
+        until_ms: u64,
+    ) -> Result<Vec<AuditEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.audit_range(since_ms, until_ms).await;
+        }
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, session_id, event_type, tool_name, detail, tokens_used, timestamp
+                 FROM audit WHERE timestamp >= ?1 AND timestamp < ?2 ORDER BY timestamp, id",
+            )?;
+            let rows = stmt
+                .query_map(
+                    rusqlite::params![since_ms as i64, until_ms as i64],
+                    row_to_entry,
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Fold entries older than `before_ms` into the daily counts in
+    /// `audit_daily` and delete them. Returns how many were deleted.
+    pub async fn audit_rollup(&self, before_ms: u64) -> Result<usize, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.audit_rollup(before_ms).await;
+        }
+        self.exec(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "INSERT INTO audit_daily (day, event_type, tool_name, events, tokens_used, cost_usd)
+                 SELECT date(timestamp / 1000, 'unixepoch'), event_type, COALESCE(tool_name, ''),
+                        COUNT(*), COALESCE(SUM(tokens_used), 0), COALESCE(SUM(cost_usd), 0)
+                 FROM audit WHERE timestamp < ?1
+                 GROUP BY 1, 2, 3
+                 ON CONFLICT(day, event_type, tool_name) DO UPDATE SET
+                     events = events + excluded.events,
+                     tokens_used = tokens_used + excluded.tokens_used,
+                     cost_usd = cost_usd + excluded.cost_usd",
+                rusqlite::params![before_ms as i64],
+            )?;
+            let deleted = tx.execute(
+                "DELETE FROM audit WHERE timestamp < ?1",
+                rusqlite::params![before_ms as i64],
+            )?;
+            tx.commit()?;
+            Ok(deleted)
+        })
+        .await
+    }
+
+    /// Rolled-up counts for days from `since_day` ("YYYY-MM-DD") on, oldest first.
+    pub async fn audit_daily(&self, since_day: &str) -> Result<Vec<AuditDay>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.audit_daily(since_day).await;
+        }
+        let since_day = since_day.to_string();
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT day, event_type, tool_name, events, tokens_used, cost_usd FROM audit_daily
+                 WHERE day >= ?1 ORDER BY day, event_type, tool_name",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![since_day], |row| {
+                    let tool_name: String = row.get(2)?;
+                    Ok(AuditDay {
+                        day: row.get(0)?,
+                        event_type: row.get(1)?,
+                        tool_name: (!tool_name.is_empty()).then_some(tool_name),
+                        events: row.get::<_, i64>(3)? as u64,
+                        tokens_used: row.get::<_, i64>(4)? as u64,
+                        cost_usd: row.get(5)?,
                     })
                 })?
                 .collect::<Result<Vec<_>, _>>()?;
@@ -90,7 +278,10 @@ impl Db {
 
     /// Sum token usage for today (since midnight UTC).
     pub async fn audit_token_usage_today(&self) -> Result<u64, DbError> {
-        self.exec(|conn| {
+        if let Some(storage) = &self.storage {
+            return storage.audit_token_usage_today().await;
+        }
+        self.exec_read(|conn| {
             let today_start = today_start_ms();
             let total: i64 = conn.query_row(
                 "SELECT COALESCE(SUM(tokens_used), 0) FROM audit WHERE timestamp >= ?1",
@@ -101,10 +292,136 @@ impl Db {
         })
         .await
     }
+
+    /// Estimated dollars spent on model calls since `since_ms`, including
+    /// days already rolled up into `audit_daily`. Rolled-up days count
+    /// whole, so `since_ms` should be the start of a UTC day.
+    pub async fn audit_cost_since(&self, since_ms: u64) -> Result<f64, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.audit_cost_since(since_ms).await;
+        }
+        self.exec_read(move |conn| {
+            let cost: f64 = conn.query_row(
+                "SELECT (SELECT COALESCE(SUM(cost_usd), 0) FROM audit WHERE timestamp >= ?1)
+                      + (SELECT COALESCE(SUM(cost_usd), 0) FROM audit_daily
+                         WHERE day >= date(?1 / 1000, 'unixepoch'))",
+                rusqlite::params![since_ms as i64],
+                |r| r.get(0),
+            )?;
+            Ok(cost)
+        })
+        .await
+    }
+
+    /// Estimated dollars spent on model calls today (UTC).
+    pub async fn audit_cost_today(&self) -> Result<f64, DbError> {
+        self.audit_cost_since(today_start_ms()).await
+    }
+
+    /// Estimated dollars spent on model calls over the last `days` UTC
+    /// days, today included.
+    pub async fn audit_cost_days(&self, days: u64) -> Result<f64, DbError> {
+        let day_ms = 24 * 60 * 60 * 1000;
+        self.audit_cost_since(today_start_ms().saturating_sub(days.saturating_sub(1) * day_ms))
+            .await
+    }
+
+    /// Tokens and turns used today (UTC) in `session_id` and by `sender_id`,
+    /// whichever are given.
+    pub async fn audit_usage_today(
+        &self,
+        session_id: Option<&str>,
+        sender_id: Option<&str>,
+    ) -> Result<(u64, u64), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.audit_usage_today(session_id, sender_id).await;
+        }
+        let session_id = session_id.map(|s| s.to_string());
+        let sender_id = sender_id.map(|s| s.to_string());
+        self.exec_read(move |conn| {
+            let (tokens, turns): (i64, i64) = conn.query_row(
+                "SELECT COALESCE(SUM(tokens_used), 0), COUNT(*) FROM audit
+                 WHERE event_type = 'llm_usage' AND timestamp >= ?1
+                   AND (?2 IS NULL OR session_id = ?2) AND (?3 IS NULL OR sender_id = ?3)",
+                rusqlite::params![today_start_ms() as i64, session_id, sender_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )?;
+            Ok((tokens as u64, turns as u64))
+        })
+        .await
+    }
+
+    /// Whether `event_type` was logged today (UTC) in `session_id` with
+    /// `detail`.
+    pub async fn audit_logged_today(
+        &self,
+        session_id: &str,
+        event_type: &str,
+        detail: &str,
+    ) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage
+                .audit_logged_today(session_id, event_type, detail)
+                .await;
+        }
+        let session_id = session_id.to_string();
+        let event_type = event_type.to_string();
+        let detail = detail.to_string();
+        self.exec_read(move |conn| {
+            let found: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM audit
+                 WHERE session_id = ?1 AND event_type = ?2 AND detail = ?3 AND timestamp >= ?4)",
+                rusqlite::params![session_id, event_type, detail, today_start_ms() as i64],
+                |r| r.get(0),
+            )?;
+            Ok(found)
+        })
+        .await
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn insert_audit(
+    conn: &rusqlite::Connection,
+    session_id: Option<&str>,
+    sender_id: Option<&str>,
+    event_type: &str,
+    tool_name: Option<&str>,
+    detail: Option<&str>,
+    tokens_used: u64,
+    cost_usd: Option<f64>,
+) -> Result<(), DbError> {
+    conn.execute(
+        "INSERT INTO audit (session_id, sender_id, event_type, tool_name, detail, tokens_used, cost_usd, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            session_id,
+            sender_id,
+            event_type,
+            tool_name,
+            detail,
+            tokens_used as i64,
+            cost_usd,
+            now_ms() as i64,
+        ],
+    )?;
+    Ok(())
+}
+
+fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<AuditEntry> {
+    Ok(AuditEntry {
+        id: Some(row.get(0)?),
+        session_id: row.get(1)?,
+        event_type: row.get(2)?,
+        tool_name: row.get(3)?,
+        detail: row.get(4)?,
+        tokens_used: row.get::<_, i64>(5)? as u64,
+        timestamp: row.get::<_, i64>(6)? as u64,
+    })
 }
 
 /// Milliseconds since epoch at start of today (UTC).
-fn today_start_ms() -> u64 {
+pub(super) fn today_start_ms() -> u64 {
     let now = chrono::Utc::now();
     let today = now.date_naive().and_hms_opt(0, 0, 0).unwrap();
     today.and_utc().timestamp_millis() as u64
@@ -134,6 +451,23 @@ mod tests {
         assert_eq!(s1.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_session_events() {
+        let db = Db::open_memory().unwrap();
+        db.audit_log(Some("s1"), "tool_call", Some("bash"), Some("ls -la"), 100)
+            .await
+            .unwrap();
+        db.audit_log(Some("s2"), "denied", Some("shell"), Some("rm -rf /"), 0)
+            .await
+            .unwrap();
+        db.audit_log_sync(Some("s1"), "compaction", None, Some("dropped=4"), 0)
+            .unwrap();
+
+        let events = db.audit_session_events("s1").await.unwrap();
+        let types: Vec<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, ["tool_call", "compaction"]);
+    }
+
     #[tokio::test]
     async fn test_token_usage_today() {
         let db = Db::open_memory().unwrap();
@@ -147,4 +481,141 @@ mod tests {
         let total = db.audit_token_usage_today().await.unwrap();
         assert_eq!(total, 1500);
     }
+
+    #[tokio::test]
+    async fn test_usage_today_by_session_and_sender() {
+        let db = Db::open_memory().unwrap();
+        db.audit_usage_sync("tg-group-1", Some("telegram:42"), "in=90 out=10", 100, None)
+            .unwrap();
+        db.audit_usage_sync("tg-group-1", Some("telegram:7"), "in=40 out=10", 50, None)
+            .unwrap();
+        db.audit_usage_sync("tg-42", Some("telegram:42"), "in=20 out=5", 25, None)
+            .unwrap();
+        db.audit_usage_sync("cron-daily", None, "in=5 out=5", 10, None)
+            .unwrap();
+        db.audit_log(Some("tg-42"), "tool_call", Some("bash"), None, 0)
+            .await
+            .unwrap();
+
+        let usage = |session, sender| db.audit_usage_today(session, sender);
+        assert_eq!(usage(Some("tg-group-1"), None).await.unwrap(), (150, 2));
+        assert_eq!(usage(None, Some("telegram:42")).await.unwrap(), (125, 2));
+        assert_eq!(
+            usage(Some("tg-group-1"), Some("telegram:7")).await.unwrap(),
+            (50, 1)
+        );
+        assert_eq!(usage(None, None).await.unwrap(), (185, 4));
+        assert_eq!(db.audit_token_usage_today().await.unwrap(), 185);
+
+        assert!(!db
+            .audit_logged_today("tg-42", "budget_exceeded", "sender")
+            .await
+            .unwrap());
+        db.audit_log(Some("tg-42"), "budget_exceeded", None, Some("sender"), 0)
+            .await
+            .unwrap();
+        assert!(db
+            .audit_logged_today("tg-42", "budget_exceeded", "sender")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_audit_range() {
+        let db = Db::open_memory().unwrap();
+        db.audit_log(Some("s1"), "denied", Some("shell"), None, 0)
+            .await
+            .unwrap();
+        let now = now_ms();
+        assert_eq!(db.audit_range(0, now + 1).await.unwrap().len(), 1);
+        assert!(db.audit_range(now + 1, now + 2).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_audit_rollup() {
+        let db = Db::open_memory().unwrap();
+        let day_ms = 86_400_000;
+        let old = 1_700_000_000_000u64; // 2023-11-14
+        db.exec(move |conn| {
+            for (ts, event, tool, tokens, cost) in [
+                (old, "tool_call", Some("shell"), 0, None),
+                (old + 1, "tool_call", Some("shell"), 0, None),
+                (old + 2, "llm_usage", None, 1200, Some(0.5)),
+                (old + day_ms, "llm_usage", None, 300, None),
+            ] {
+                conn.execute(
+                    "INSERT INTO audit (session_id, event_type, tool_name, tokens_used, cost_usd, timestamp)
+                     VALUES ('s1', ?1, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![event, tool, tokens, cost, ts as i64],
+                )?;
+            }
+            Ok(())
+        })
+        .await
+        .unwrap();
+        db.audit_log(Some("s1"), "tool_call", Some("shell"), None, 0)
+            .await
+            .unwrap();
+
+        let cutoff = now_ms() - day_ms;
+        assert_eq!(db.audit_rollup(cutoff).await.unwrap(), 4);
+        assert_eq!(db.audit_query(None, 100).await.unwrap().len(), 1);
+        // Running again finds nothing more to fold in
+        assert_eq!(db.audit_rollup(cutoff).await.unwrap(), 0);
+
+        let days = db.audit_daily("2023-11-01").await.unwrap();
+        assert_eq!(
+            days,
+            vec![
+                AuditDay {
+                    day: "2023-11-14".into(),
+                    event_type: "llm_usage".into(),
+                    tool_name: None,
+                    events: 1,
+                    tokens_used: 1200,
+                    cost_usd: 0.5,
+                },
+                AuditDay {
+                    day: "2023-11-14".into(),
+                    event_type: "tool_call".into(),
+                    tool_name: Some("shell".into()),
+                    events: 2,
+                    tokens_used: 0,
+                    cost_usd: 0.0,
+                },
+                AuditDay {
+                    day: "2023-11-15".into(),
+                    event_type: "llm_usage".into(),
+                    tool_name: None,
+                    events: 1,
+                    tokens_used: 300,
+                    cost_usd: 0.0,
+                },
+            ]
+        );
+        assert_eq!(db.audit_daily("2023-11-15").await.unwrap().len(), 1);
+        // Rolled-up days still count toward spend
+        assert_eq!(db.audit_cost_since(old - old % day_ms).await.unwrap(), 0.5);
+        assert_eq!(
+            db.audit_cost_since(old - old % day_ms + day_ms)
+                .await
+                .unwrap(),
+            0.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cost_today() {
+        let db = Db::open_memory().unwrap();
+        db.audit_usage_sync("s1", None, "in=1000 out=100", 1100, Some(0.25))
+            .unwrap();
+        db.audit_usage_sync("s1", None, "in=500 out=50", 550, Some(0.125))
+            .unwrap();
+        db.audit_usage_sync("s2", None, "in=10 out=10", 20, None)
+            .unwrap();
+
+        assert_eq!(db.audit_cost_today().await.unwrap(), 0.375);
+        assert_eq!(db.audit_cost_days(7).await.unwrap(), 0.375);
+        assert_eq!(db.audit_cost_since(now_ms() + 1).await.unwrap(), 0.0);
+    }
 }
@@ -0,0 +1,161 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackgroundTask {
+    pub id: i64,
+    pub session_id: String,
+    pub task: String,
+    /// "pending", "running", "done" or "failed"
+    pub status: String,
+    pub result: Option<String>,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+fn row_to_task(row: &rusqlite::Row) -> rusqlite::Result<BackgroundTask> {
+    Ok(BackgroundTask {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        task: row.get(2)?,
+        status: row.get(3)?,
+        result: row.get(4)?,
+        created_at: row.get::<_, i64>(5)? as u64,
+        started_at: row.get::<_, Option<i64>>(6)?.map(|t| t as u64),
+        finished_at: row.get::<_, Option<i64>>(7)?.map(|t| t as u64),
+    })
+}
+
+const COLUMNS: &str = "id, session_id, task, status, result, created_at, started_at, finished_at";
+
+impl Db {
+    /// Queue a task for the background workers. Returns its ID.
+    pub async fn background_enqueue(&self, session_id: &str, task: &str) -> Result<i64, DbError> {
+        let session_id = session_id.to_string();
+        let task = task.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO background_tasks (session_id, task, status, created_at)
+                 VALUES (?1, ?2, 'pending', ?3)",
+                rusqlite::params![session_id, task, ts],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await
+    }
+
+    /// Mark the oldest pending task as running and return it.
+    pub async fn background_claim(&self) -> Result<Option<BackgroundTask>, DbError> {
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            let sql = format!(
+                "SELECT {} FROM background_tasks WHERE status = 'pending' ORDER BY id LIMIT 1",
+                COLUMNS
+            );
+            let Some(mut task) = conn.query_row(&sql, [], row_to_task).optional()? else {
+                return Ok(None);
+            };
+            conn.execute(
+                "UPDATE background_tasks SET status = 'running', started_at = ?1 WHERE id = ?2",
+                rusqlite::params![ts, task.id],
+            )?;
+            task.status = "running".to_string();
+            task.started_at = Some(ts as u64);
+            Ok(Some(task))
+        })
+        .await
+    }
+
+    /// Record the outcome of a running task.
+    pub async fn background_finish(&self, id: i64, ok: bool, result: &str) -> Result<(), DbError> {
+        let status = if ok { "done" } else { "failed" };
+        let result = result.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "UPDATE background_tasks SET status = ?1, result = ?2, finished_at = ?3 WHERE id = ?4",
+                rusqlite::params![status, result, ts, id],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Put tasks left running by a previous process back in the queue.
+    pub async fn background_requeue_running(&self) -> Result<usize, DbError> {
+        self.exec(|conn| {
+            Ok(conn.execute(
+                "UPDATE background_tasks SET status = 'pending', started_at = NULL
+                 WHERE status = 'running'",
+                [],
+            )?)
+        })
+        .await
+    }
+
+    /// A session's most recent tasks, newest first.
+    pub async fn background_list(
+        &self,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<BackgroundTask>, DbError> {
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            let sql = format!(
+                "SELECT {} FROM background_tasks WHERE session_id = ?1
+                 ORDER BY id DESC LIMIT ?2",
+                COLUMNS
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt
+                .query_map(rusqlite::params![session_id, limit as i64], row_to_task)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_background_task_lifecycle() {
+        let db = Db::open_memory().unwrap();
+        let first = db
+            .background_enqueue("tg-1", "write a report")
+            .await
+            .unwrap();
+        let second = db
+            .background_enqueue("tg-1", "summarize logs")
+            .await
+            .unwrap();
+        db.background_enqueue("dc-2", "other session")
+            .await
+            .unwrap();
+
+        // Claimed oldest first, and only once
+        let task = db.background_claim().await.unwrap().unwrap();
+        assert_eq!(task.id, first);
+        assert_eq!(task.status, "running");
+        assert_eq!(db.background_claim().await.unwrap().unwrap().id, second);
+
+        db.background_finish(first, true, "the report")
+            .await
+            .unwrap();
+
+        // A restart puts the interrupted task back in the queue
+        assert_eq!(db.background_requeue_running().await.unwrap(), 1);
+        assert_eq!(db.background_claim().await.unwrap().unwrap().id, second);
+
+        let tasks = db.background_list("tg-1", 10).await.unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].id, second);
+        assert_eq!(tasks[1].status, "done");
+        assert_eq!(tasks[1].result.as_deref(), Some("the report"));
+    }
+}
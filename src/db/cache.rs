@@ -0,0 +1,228 @@
+//! In-process cache for memory search results.
+//!
+//! A memory search runs FTS, the optional vector lookup and the decay sort on
+//! every call, and long agent loops tend to repeat the same query. Results are
+//! cached per (query, limit, namespace) for a short TTL, evicting the least
+//! recently used entry when full. Any memory write clears the cache.
+
+use super::memory::MemoryEntry;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// (query, limit, namespace)
+pub(crate) type CacheKey = (String, usize, Option<String>);
+
+/// Hit/miss counters since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Results currently cached.
+    pub entries: usize,
+}
+
+impl MemoryCacheStats {
+    /// Fraction of lookups served from the cache (0.0 with no lookups yet).
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+struct Cached {
+    stored_at: Instant,
+    last_used: u64,
+    entries: Vec<MemoryEntry>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    results: HashMap<CacheKey, Cached>,
+    /// Monotonic use counter for LRU ordering.
+    tick: u64,
+}
+
+pub(crate) struct MemoryCache {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<CacheState>,
+    /// Bumped on every invalidation so results computed across a write are
+    /// never stored.
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl MemoryCache {
+    /// A cache that stores nothing.
+    pub(crate) fn disabled() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            state: Mutex::new(CacheState::default()),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.capacity > 0 && !self.ttl.is_zero()
+    }
+
+    /// Current generation; pass it back to `insert` after computing a result.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<Vec<MemoryEntry>> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        let fresh = match state.results.get_mut(key) {
+            Some(cached) if cached.stored_at.elapsed() < self.ttl => {
+                cached.last_used = tick;
+                Some(cached.entries.clone())
+            }
+            _ => None,
+        };
+        if fresh.is_none() {
+            // Drop an expired result, if any
+            state.results.remove(key);
+        }
+        let counter = if fresh.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        fresh
+    }
+
+    /// Store a result computed at `generation`. Dropped if memory was written since.
+    pub(crate) fn insert(&self, key: CacheKey, generation: u64, entries: Vec<MemoryEntry>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if generation != self.generation() {
+            return;
+        }
+        if state.results.len() >= self.capacity && !state.results.contains_key(&key) {
+            let oldest = state
+                .results
+                .iter()
+                .min_by_key(|(_, c)| c.last_used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                state.results.remove(&oldest);
+            }
+        }
+        state.tick += 1;
+        let tick = state.tick;
+        state.results.insert(
+            key,
+            Cached {
+                stored_at: Instant::now(),
+                last_used: tick,
+                entries,
+            },
+        );
+    }
+
+    pub(crate) fn invalidate(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        state.results.clear();
+    }
+
+    pub(crate) fn stats(&self) -> MemoryCacheStats {
+        MemoryCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.state.lock().unwrap().results.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str) -> MemoryEntry {
+        MemoryEntry {
+            id: Some(1),
+            key: None,
+            content: content.to_string(),
+            tags: None,
+            source: None,
+            category: "fact".to_string(),
+            importance: 5,
+            last_accessed: None,
+            access_count: 0,
+            created_at: 0,
+            updated_at: 0,
+            namespace: None,
+        }
+    }
+
+    fn key(q: &str) -> CacheKey {
+        (q.to_string(), 5, None)
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = MemoryCache::new(2, Duration::from_secs(60));
+        let gen = cache.generation();
+        cache.insert(key("a"), gen, vec![entry("a")]);
+        cache.insert(key("b"), gen, vec![entry("b")]);
+        assert!(cache.get(&key("a")).is_some()); // a is now more recent than b
+        cache.insert(key("c"), gen, vec![entry("c")]);
+
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("c")).is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 2);
+        assert!((stats.hit_rate() - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stale_generation_not_stored() {
+        let cache = MemoryCache::new(8, Duration::from_secs(60));
+        let gen = cache.generation();
+        cache.invalidate();
+        cache.insert(key("a"), gen, vec![entry("a")]);
+        assert!(cache.get(&key("a")).is_none());
+    }
+
+    #[test]
+    fn test_expired_and_disabled() {
+        let cache = MemoryCache::new(8, Duration::from_millis(1));
+        cache.insert(key("a"), cache.generation(), vec![entry("a")]);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(cache.get(&key("a")).is_none());
+        assert_eq!(cache.stats().entries, 0);
+
+        let cache = MemoryCache::disabled();
+        cache.insert(key("a"), cache.generation(), vec![entry("a")]);
+        assert!(cache.get(&key("a")).is_none());
+        assert_eq!(cache.stats(), MemoryCacheStats::default());
+    }
+}
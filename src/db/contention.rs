@@ -0,0 +1,55 @@
+//! Time spent waiting for the writer connection.
+//!
+//! Every `exec` queues on a blocking thread, then on the writer mutex. How
+//! long that takes is the cost of serializing writes through one connection,
+//! and the first thing to grow when the pipeline gets busier. `yoclaw bench`
+//! reports it.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Writer waits since startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WriterStats {
+    /// Operations run on the writer connection.
+    pub ops: u64,
+    /// Total time they waited for it.
+    pub wait_total: Duration,
+    /// Longest single wait.
+    pub wait_max: Duration,
+}
+
+impl WriterStats {
+    /// Mean wait per operation (zero with no operations yet).
+    pub fn wait_mean(&self) -> Duration {
+        if self.ops == 0 {
+            Duration::ZERO
+        } else {
+            self.wait_total / self.ops as u32
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct WriterWait {
+    ops: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl WriterWait {
+    pub(crate) fn record(&self, wait: Duration) {
+        let us = wait.as_micros() as u64;
+        self.ops.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> WriterStats {
+        WriterStats {
+            ops: self.ops.load(Ordering::Relaxed),
+            wait_total: Duration::from_micros(self.total_us.load(Ordering::Relaxed)),
+            wait_max: Duration::from_micros(self.max_us.load(Ordering::Relaxed)),
+        }
+    }
+}
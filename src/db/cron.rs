@@ -0,0 +1,229 @@
+use super::schedule::CRON_FIRED_PREFIX;
+use super::{now_ms, Db, DbError};
+use serde::Serialize;
+
+/// A loaded cron job from the database.
+#[derive(Debug, Clone, Serialize)]
+pub struct CronJob {
+    pub id: i64,
+    pub name: String,
+    pub schedule: String,
+    pub prompt: String,
+    pub target_channel: Option<String>,
+    pub session_mode: String,
+    pub enabled: bool,
+    /// Overrides of the main agent's provider, model and max_tokens.
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+}
+
+impl Db {
+    /// Create or update cron job `name`, keeping its model overrides.
+    /// A changed schedule counts as a change for when the job is next due.
+    /// Returns the job ID.
+    pub async fn cron_job_upsert(
+        &self,
+        name: &str,
+        schedule: &str,
+        prompt: &str,
+        target: Option<&str>,
+        session: &str,
+    ) -> Result<i64, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage
+                .cron_job_upsert(name, schedule, prompt, target, session)
+                .await;
+        }
+        let name = name.to_string();
+        let schedule = schedule.to_string();
+        let prompt = prompt.to_string();
+        let target = target.map(|s| s.to_string());
+        let session = session.to_string();
+        self.exec(move |conn| {
+            let ts = now_ms() as i64;
+            conn.execute(
+                "INSERT INTO cron_jobs (name, schedule, prompt, target_channel, session_mode, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT(name) DO UPDATE SET
+                    schedule = excluded.schedule,
+                    prompt = excluded.prompt,
+                    target_channel = excluded.target_channel,
+                    session_mode = excluded.session_mode,
+                    updated_at = CASE WHEN cron_jobs.schedule = excluded.schedule
+                                      THEN cron_jobs.updated_at ELSE excluded.updated_at END",
+                rusqlite::params![name, schedule, prompt, target, session, ts],
+            )?;
+            Ok(conn.query_row(
+                "SELECT id FROM cron_jobs WHERE name = ?1",
+                rusqlite::params![name],
+                |r| r.get(0),
+            )?)
+        })
+        .await
+    }
+
+    /// Set or clear a job's provider, model and max_tokens overrides.
+    /// Returns false if there is no job with that name.
+    pub async fn cron_job_set_model(
+        &self,
+        name: &str,
+        provider: Option<&str>,
+        model: Option<&str>,
+        max_tokens: Option<u32>,
+    ) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage
+                .cron_job_set_model(name, provider, model, max_tokens)
+                .await;
+        }
+        let name = name.to_string();
+        let provider = provider.map(|s| s.to_string());
+        let model = model.map(|s| s.to_string());
+        self.exec(move |conn| {
+            let updated = conn.execute(
+                "UPDATE cron_jobs SET provider = ?1, model = ?2, max_tokens = ?3 WHERE name = ?4",
+                rusqlite::params![provider, model, max_tokens, name],
+            )?;
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    /// All cron jobs, by name.
+    pub async fn cron_jobs_list(&self) -> Result<Vec<CronJob>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_jobs_list().await;
+        }
+        self.exec_read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, schedule, prompt, target_channel, session_mode, enabled, provider, model, max_tokens
+                 FROM cron_jobs ORDER BY name",
+            )?;
+            let jobs = stmt
+                .query_map([], row_to_job)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(jobs)
+        })
+        .await
+    }
+
+    /// Enabled cron jobs, each with when (ms) it last fired or its schedule
+    /// last changed or it was re-enabled, whichever is later.
+    pub async fn cron_jobs_enabled(&self) -> Result<Vec<(CronJob, u64)>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_jobs_enabled().await;
+        }
+        self.exec_read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT j.id, j.name, j.schedule, j.prompt, j.target_channel, j.session_mode, j.enabled,
+                        j.provider, j.model, j.max_tokens,
+                        MAX(j.updated_at, COALESCE(CAST(s.value AS INTEGER), 0))
+                 FROM cron_jobs j LEFT JOIN state s ON s.key = ?1 || j.name
+                 WHERE j.enabled = 1",
+            )?;
+            let jobs = stmt
+                .query_map(rusqlite::params![CRON_FIRED_PREFIX], |row| {
+                    Ok((row_to_job(row)?, row.get::<_, i64>(10)? as u64))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(jobs)
+        })
+        .await
+    }
+
+    /// Delete cron job `name` with its runs. Returns true if a job was deleted.
+    pub async fn cron_job_delete(&self, name: &str) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_job_delete(name).await;
+        }
+        let name = name.to_string();
+        self.exec(move |conn| {
+            conn.execute(
+                "DELETE FROM cron_runs WHERE job_id IN (SELECT id FROM cron_jobs WHERE name = ?1)",
+                rusqlite::params![name],
+            )?;
+            let deleted = conn.execute(
+                "DELETE FROM cron_jobs WHERE name = ?1",
+                rusqlite::params![name],
+            )?;
+            conn.execute(
+                "DELETE FROM state WHERE key = ?1 || ?2",
+                rusqlite::params![CRON_FIRED_PREFIX, name],
+            )?;
+            Ok(deleted > 0)
+        })
+        .await
+    }
+
+    /// Enable or disable cron job `name`. Returns false if not found.
+    pub async fn cron_job_set_enabled(&self, name: &str, enabled: bool) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_job_set_enabled(name, enabled).await;
+        }
+        let name = name.to_string();
+        self.exec(move |conn| {
+            let updated = conn.execute(
+                "UPDATE cron_jobs SET enabled = ?1, updated_at = ?2 WHERE name = ?3",
+                rusqlite::params![enabled as i64, now_ms() as i64, name],
+            )?;
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    /// Record that job `job_id` started running at `started_at`. Returns the run ID.
+    pub async fn cron_run_start(&self, job_id: i64, started_at: u64) -> Result<i64, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_run_start(job_id, started_at).await;
+        }
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO cron_runs (job_id, status, started_at) VALUES (?1, 'running', ?2)",
+                rusqlite::params![job_id, started_at as i64],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await
+    }
+
+    /// Record how run `run_id` ended: its response, or its error if `ok` is false.
+    pub async fn cron_run_finish(
+        &self,
+        run_id: i64,
+        ok: bool,
+        result: &str,
+    ) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_run_finish(run_id, ok, result).await;
+        }
+        let status = if ok { "ok" } else { "error" };
+        let result = result.to_string();
+        let finished_at = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "UPDATE cron_runs SET status = ?1, result = ?2, finished_at = ?3 WHERE id = ?4",
+                rusqlite::params![status, result, finished_at, run_id],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn row_to_job(row: &rusqlite::Row<'_>) -> rusqlite::Result<CronJob> {
+    Ok(CronJob {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        schedule: row.get(2)?,
+        prompt: row.get(3)?,
+        target_channel: row.get(4)?,
+        session_mode: row
+            .get::<_, Option<String>>(5)?
+            .unwrap_or_else(|| "isolated".to_string()),
+        enabled: row.get::<_, i64>(6)? == 1,
+        provider: row.get(7)?,
+        model: row.get(8)?,
+        max_tokens: row.get(9)?,
+    })
+}
@@ -0,0 +1,175 @@
+use super::{now_ms, Db, DbError};
+use serde::Serialize;
+
+/// Characters of the message kept in the log.
+const PREVIEW_CHARS: usize = 120;
+
+/// Final outcome of an outbound send or edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Sent,
+    Edited,
+    Failed,
+    RateLimited,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sent => "sent",
+            Self::Edited => "edited",
+            Self::Failed => "failed",
+            Self::RateLimited => "rate_limited",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryEntry {
+    pub id: i64,
+    pub channel: String,
+    pub session_id: String,
+    /// "send" or "edit"
+    pub kind: String,
+    pub status: String,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub preview: Option<String>,
+    pub created_at: u64,
+}
+
+impl Db {
+    /// Record the outcome of a delivery.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn delivery_log(
+        &self,
+        channel: &str,
+        session_id: &str,
+        kind: &str,
+        status: DeliveryStatus,
+        attempts: u32,
+        error: Option<&str>,
+        content: &str,
+    ) -> Result<(), DbError> {
+        let channel = channel.to_string();
+        let session_id = session_id.to_string();
+        let kind = kind.to_string();
+        let error = error.map(|s| s.to_string());
+        let preview: String = content.chars().take(PREVIEW_CHARS).collect();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO deliveries (channel, session_id, kind, status, attempts, error, preview, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                rusqlite::params![
+                    channel,
+                    session_id,
+                    kind,
+                    status.as_str(),
+                    attempts,
+                    error,
+                    preview,
+                    ts
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Most recent deliveries, newest first. With `failed_only`, only
+    /// `failed` and `rate_limited` entries.
+    pub async fn delivery_query(
+        &self,
+        failed_only: bool,
+        limit: usize,
+    ) -> Result<Vec<DeliveryEntry>, DbError> {
+        self.exec_read(move |conn| {
+            let sql = if failed_only {
+                "SELECT id, channel, session_id, kind, status, attempts, error, preview, created_at
+                 FROM deliveries WHERE status IN ('failed', 'rate_limited')
+                 ORDER BY created_at DESC, id DESC LIMIT ?1"
+            } else {
+                "SELECT id, channel, session_id, kind, status, attempts, error, preview, created_at
+                 FROM deliveries ORDER BY created_at DESC, id DESC LIMIT ?1"
+            };
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt
+                .query_map(rusqlite::params![limit as i64], |row| {
+                    Ok(DeliveryEntry {
+                        id: row.get(0)?,
+                        channel: row.get(1)?,
+                        session_id: row.get(2)?,
+                        kind: row.get(3)?,
+                        status: row.get(4)?,
+                        attempts: row.get(5)?,
+                        error: row.get(6)?,
+                        preview: row.get(7)?,
+                        created_at: row.get::<_, i64>(8)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Failed deliveries since `since_ms`.
+    pub async fn delivery_failed_count(&self, since_ms: u64) -> Result<u64, DbError> {
+        self.exec_read(move |conn| {
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM deliveries
+                 WHERE status IN ('failed', 'rate_limited') AND created_at >= ?1",
+                rusqlite::params![since_ms as i64],
+                |r| r.get(0),
+            )?;
+            Ok(count as u64)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_delivery_log_and_query() {
+        let db = Db::open_memory().unwrap();
+        db.delivery_log(
+            "telegram",
+            "tg-1",
+            "send",
+            DeliveryStatus::Sent,
+            1,
+            None,
+            "hi",
+        )
+        .await
+        .unwrap();
+        db.delivery_log(
+            "discord",
+            "dc-1",
+            "edit",
+            DeliveryStatus::RateLimited,
+            3,
+            Some("429 Too Many Requests"),
+            &"x".repeat(500),
+        )
+        .await
+        .unwrap();
+
+        let all = db.delivery_query(false, 10).await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        let failed = db.delivery_query(true, 10).await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, "rate_limited");
+        assert_eq!(failed[0].attempts, 3);
+        assert_eq!(failed[0].preview.as_ref().unwrap().len(), PREVIEW_CHARS);
+
+        assert_eq!(db.delivery_failed_count(0).await.unwrap(), 1);
+        assert_eq!(db.delivery_failed_count(now_ms() + 1000).await.unwrap(), 0);
+    }
+}
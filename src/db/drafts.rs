@@ -0,0 +1,254 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// A reply held for review.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Draft {
+    pub id: i64,
+    pub session_id: String,
+    pub channel: String,
+    /// The message the draft answers.
+    pub request: String,
+    /// The agent's reply.
+    pub content: String,
+    /// "pending", "approved", "edited" or "rejected"
+    pub status: String,
+    /// What was posted to the session, for approved and edited drafts.
+    pub sent_content: Option<String>,
+    pub decided_by: Option<String>,
+    pub created_at: u64,
+    pub decided_at: Option<u64>,
+}
+
+/// A reviewer's decision on a draft.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DraftDecision {
+    Approve,
+    /// Post this text instead of the draft.
+    Edit(String),
+    Reject,
+}
+
+impl DraftDecision {
+    fn status(&self) -> &'static str {
+        match self {
+            DraftDecision::Approve => "approved",
+            DraftDecision::Edit(_) => "edited",
+            DraftDecision::Reject => "rejected",
+        }
+    }
+}
+
+fn row_to_draft(row: &rusqlite::Row) -> rusqlite::Result<Draft> {
+    Ok(Draft {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        channel: row.get(2)?,
+        request: row.get(3)?,
+        content: row.get(4)?,
+        status: row.get(5)?,
+        sent_content: row.get(6)?,
+        decided_by: row.get(7)?,
+        created_at: row.get::<_, i64>(8)? as u64,
+        decided_at: row.get::<_, Option<i64>>(9)?.map(|t| t as u64),
+    })
+}
+
+const COLUMNS: &str = "id, session_id, channel, request, content, status, sent_content, \
+                       decided_by, created_at, decided_at";
+
+impl Db {
+    /// Hold a reply for review. Returns the draft ID.
+    pub async fn draft_create(
+        &self,
+        channel: &str,
+        session_id: &str,
+        request: &str,
+        content: &str,
+    ) -> Result<i64, DbError> {
+        let channel = channel.to_string();
+        let session_id = session_id.to_string();
+        let request = request.to_string();
+        let content = content.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO drafts (session_id, channel, request, content, status, created_at)
+                 VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
+                rusqlite::params![session_id, channel, request, content, ts],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await
+    }
+
+    pub async fn draft_get(&self, id: i64) -> Result<Option<Draft>, DbError> {
+        self.exec_read(move |conn| {
+            let sql = format!("SELECT {} FROM drafts WHERE id = ?1", COLUMNS);
+            Ok(conn
+                .query_row(&sql, rusqlite::params![id], row_to_draft)
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Drafts waiting for a decision, oldest first.
+    pub async fn draft_pending(&self) -> Result<Vec<Draft>, DbError> {
+        self.exec_read(|conn| {
+            let sql = format!(
+                "SELECT {} FROM drafts WHERE status = 'pending' ORDER BY id",
+                COLUMNS
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let drafts = stmt
+                .query_map([], row_to_draft)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(drafts)
+        })
+        .await
+    }
+
+    /// Record a decision on a pending draft. Returns the decided draft, or
+    /// None if it doesn't exist or was already decided.
+    pub async fn draft_decide(
+        &self,
+        id: i64,
+        decision: &DraftDecision,
+        by: &str,
+    ) -> Result<Option<Draft>, DbError> {
+        let status = decision.status();
+        let edited = match decision {
+            DraftDecision::Edit(text) => Some(text.clone()),
+            _ => None,
+        };
+        let by = by.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            // Approved drafts are sent as written, rejected ones not at all
+            let updated = conn.execute(
+                "UPDATE drafts SET status = ?1,
+                     sent_content = CASE ?1 WHEN 'approved' THEN content WHEN 'edited' THEN ?2 END,
+                     decided_by = ?3, decided_at = ?4
+                 WHERE id = ?5 AND status = 'pending'",
+                rusqlite::params![status, edited, by, ts, id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            let sql = format!("SELECT {} FROM drafts WHERE id = ?1", COLUMNS);
+            Ok(Some(conn.query_row(
+                &sql,
+                rusqlite::params![id],
+                row_to_draft,
+            )?))
+        })
+        .await
+    }
+
+    /// Decided drafts whose decision the main loop hasn't acted on yet.
+    pub async fn draft_unapplied(&self) -> Result<Vec<Draft>, DbError> {
+        self.exec_read(|conn| {
+            let sql = format!(
+                "SELECT {} FROM drafts WHERE status != 'pending' AND applied_at IS NULL ORDER BY id",
+                COLUMNS
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let drafts = stmt
+                .query_map([], row_to_draft)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(drafts)
+        })
+        .await
+    }
+
+    /// Record that a decision was posted to the session.
+    pub async fn draft_mark_applied(&self, id: i64) -> Result<(), DbError> {
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "UPDATE drafts SET applied_at = ?1 WHERE id = ?2",
+                rusqlite::params![ts, id],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_draft_lifecycle() {
+        let db = Db::open_memory().unwrap();
+        let a = db
+            .draft_create(
+                "slack",
+                "slack-C1",
+                "Can I get a refund?",
+                "Yes, within 30 days.",
+            )
+            .await
+            .unwrap();
+        let b = db
+            .draft_create(
+                "slack",
+                "slack-C1",
+                "Where is my order?",
+                "It shipped today.",
+            )
+            .await
+            .unwrap();
+        let pending: Vec<i64> = db
+            .draft_pending()
+            .await
+            .unwrap()
+            .iter()
+            .map(|d| d.id)
+            .collect();
+        assert_eq!(pending, vec![a, b]);
+
+        let approved = db
+            .draft_decide(a, &DraftDecision::Approve, "telegram:42")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(approved.status, "approved");
+        assert_eq!(
+            approved.sent_content.as_deref(),
+            Some("Yes, within 30 days.")
+        );
+        assert_eq!(approved.decided_by.as_deref(), Some("telegram:42"));
+        // A decided draft can't be decided again
+        assert_eq!(
+            db.draft_decide(a, &DraftDecision::Reject, "web")
+                .await
+                .unwrap(),
+            None
+        );
+
+        let edited = db
+            .draft_decide(b, &DraftDecision::Edit("It ships tomorrow.".into()), "web")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(edited.status, "edited");
+        assert_eq!(edited.sent_content.as_deref(), Some("It ships tomorrow."));
+        assert_eq!(edited.content, "It shipped today.");
+        assert!(db.draft_pending().await.unwrap().is_empty());
+        assert_eq!(db.draft_get(b).await.unwrap().unwrap().status, "edited");
+
+        let unapplied: Vec<i64> = db
+            .draft_unapplied()
+            .await
+            .unwrap()
+            .iter()
+            .map(|d| d.id)
+            .collect();
+        assert_eq!(unapplied, vec![a, b]);
+        db.draft_mark_applied(a).await.unwrap();
+        assert_eq!(db.draft_unapplied().await.unwrap()[0].id, b);
+    }
+}
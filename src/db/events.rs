@@ -0,0 +1,225 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// Prefix of the state-table keys holding the last budget alert sent to
+/// each webhook target, followed by the target name.
+const BUDGET_ALERT_PREFIX: &str = "webhook_budget_alert:";
+
+/// A queued message the agent has finished with.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessedMessage {
+    pub queue_id: i64,
+    pub session_id: String,
+    pub channel: String,
+    pub sender_id: String,
+    pub processed_at: u64,
+}
+
+/// A session seen for the first time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NewSession {
+    pub session_id: String,
+    pub channel: String,
+    pub is_group: bool,
+    pub first_seen: u64,
+}
+
+/// A cron run that ended in an error.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FailedCronRun {
+    pub job: String,
+    pub error: String,
+    pub finished_at: u64,
+}
+
+impl Db {
+    /// Messages marked done in `[since_ms, until_ms)`, oldest first.
+    pub async fn events_processed_messages(
+        &self,
+        since_ms: u64,
+        until_ms: u64,
+    ) -> Result<Vec<ProcessedMessage>, DbError> {
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, session_id, channel, sender_id, processed_at FROM queue
+                 WHERE status = 'done' AND processed_at >= ?1 AND processed_at < ?2
+                 ORDER BY processed_at, id",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![since_ms as i64, until_ms as i64], |row| {
+                    Ok(ProcessedMessage {
+                        queue_id: row.get(0)?,
+                        session_id: row.get(1)?,
+                        channel: row.get(2)?,
+                        sender_id: row.get(3)?,
+                        processed_at: row.get::<_, i64>(4)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Sessions first seen in `[since_ms, until_ms)`, oldest first.
+    pub async fn events_new_sessions(
+        &self,
+        since_ms: u64,
+        until_ms: u64,
+    ) -> Result<Vec<NewSession>, DbError> {
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT session_id, channel, is_group, first_seen FROM sessions
+                 WHERE first_seen >= ?1 AND first_seen < ?2 ORDER BY first_seen",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![since_ms as i64, until_ms as i64], |row| {
+                    Ok(NewSession {
+                        session_id: row.get(0)?,
+                        channel: row.get(1)?,
+                        is_group: row.get(2)?,
+                        first_seen: row.get::<_, i64>(3)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Cron runs that failed in `[since_ms, until_ms)`, oldest first.
+    pub async fn events_failed_cron_runs(
+        &self,
+        since_ms: u64,
+        until_ms: u64,
+    ) -> Result<Vec<FailedCronRun>, DbError> {
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT j.name, COALESCE(r.result, ''), r.finished_at
+                 FROM cron_runs r JOIN cron_jobs j ON j.id = r.job_id
+                 WHERE r.status = 'error' AND r.finished_at >= ?1 AND r.finished_at < ?2
+                 ORDER BY r.finished_at",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![since_ms as i64, until_ms as i64], |row| {
+                    Ok(FailedCronRun {
+                        job: row.get(0)?,
+                        error: row.get(1)?,
+                        finished_at: row.get::<_, i64>(2)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Highest budget alert sent to webhook `target` on `day` (percent of
+    /// the daily limit).
+    pub async fn events_budget_alerted(
+        &self,
+        target: &str,
+        day: &str,
+    ) -> Result<Option<u64>, DbError> {
+        let key = format!("{}{}", BUDGET_ALERT_PREFIX, target);
+        let day = day.to_string();
+        self.exec_read(move |conn| {
+            let value: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(value.and_then(|v| {
+                let (alerted_day, percent) = v.split_once(':')?;
+                if alerted_day != day {
+                    return None;
+                }
+                percent.parse().ok()
+            }))
+        })
+        .await
+    }
+
+    pub async fn events_set_budget_alerted(
+        &self,
+        target: &str,
+        day: &str,
+        percent: u64,
+    ) -> Result<(), DbError> {
+        let key = format!("{}{}", BUDGET_ALERT_PREFIX, target);
+        let value = format!("{}:{}", day, percent);
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, value, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queue::QueueEntry;
+
+    #[tokio::test]
+    async fn test_event_queries() {
+        let db = Db::open_memory().unwrap();
+        let since = now_ms() - 1;
+        let id = db
+            .queue_push(&QueueEntry::new("telegram", "7", "tg-7", "hi"))
+            .await
+            .unwrap();
+        db.queue_push(&QueueEntry::new("telegram", "7", "tg-7", "still pending"))
+            .await
+            .unwrap();
+        db.queue_mark_done(id).await.unwrap();
+        db.session_touch("telegram", "tg-7", Some("7"), None, false)
+            .await
+            .unwrap();
+        let until = now_ms() + 1;
+
+        let processed = db.events_processed_messages(since, until).await.unwrap();
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0].queue_id, id);
+        assert_eq!(processed[0].sender_id, "7");
+        let sessions = db.events_new_sessions(since, until).await.unwrap();
+        assert_eq!(sessions[0].session_id, "tg-7");
+        assert!(db
+            .events_new_sessions(until, until + 1000)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db
+            .events_failed_cron_runs(since, until)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            db.events_budget_alerted("ci", "2026-01-01").await.unwrap(),
+            None
+        );
+        db.events_set_budget_alerted("ci", "2026-01-01", 80)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.events_budget_alerted("ci", "2026-01-01").await.unwrap(),
+            Some(80)
+        );
+        assert_eq!(
+            db.events_budget_alerted("ci", "2026-01-02").await.unwrap(),
+            None
+        );
+        assert_eq!(
+            db.events_budget_alerted("log", "2026-01-01").await.unwrap(),
+            None
+        );
+    }
+}
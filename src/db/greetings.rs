@@ -0,0 +1,74 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key marking that a sender has been seen on a channel.
+fn greeted_key(channel: &str, sender_id: &str) -> String {
+    format!("greeted:{}:{}", channel, sender_id)
+}
+
+impl Db {
+    /// Record first contact from a sender. Returns true only the first time,
+    /// and only if `session_id` has no history yet, so senders who talked to
+    /// the bot before greetings were configured aren't greeted.
+    pub async fn greeting_first_contact(
+        &self,
+        channel: &str,
+        sender_id: &str,
+        session_id: &str,
+    ) -> Result<bool, DbError> {
+        let key = greeted_key(channel, sender_id);
+        let session_id = session_id.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            let inserted = conn.execute(
+                "INSERT OR IGNORE INTO state (key, value, updated_at) VALUES (?1, '1', ?2)",
+                rusqlite::params![key, ts],
+            )?;
+            if inserted == 0 {
+                return Ok(false);
+            }
+            let history: Option<i64> = conn
+                .query_row(
+                    "SELECT message_count FROM tape WHERE session_id = ?1",
+                    rusqlite::params![session_id],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(history.unwrap_or(0) == 0)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::types::{AgentMessage, Message};
+
+    #[tokio::test]
+    async fn test_first_contact_once() {
+        let db = Db::open_memory().unwrap();
+        assert!(db
+            .greeting_first_contact("telegram", "42", "tg-42")
+            .await
+            .unwrap());
+        assert!(!db
+            .greeting_first_contact("telegram", "42", "tg-42")
+            .await
+            .unwrap());
+        // Same ID on another channel is another sender
+        assert!(db
+            .greeting_first_contact("discord", "42", "dc-7")
+            .await
+            .unwrap());
+
+        // Senders with history predate the greeting
+        db.tape_save_messages("tg-43", &[AgentMessage::Llm(Message::user("hi"))])
+            .await
+            .unwrap();
+        assert!(!db
+            .greeting_first_contact("telegram", "43", "tg-43")
+            .await
+            .unwrap());
+    }
+}
@@ -1,8 +1,9 @@
 use super::{now_ms, Db, DbError};
 use rusqlite::Connection;
+use serde::Serialize;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MemoryEntry {
     pub id: Option<i64>,
     pub key: Option<String>,
@@ -15,6 +16,44 @@ pub struct MemoryEntry {
     pub access_count: i32,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Skill namespace, or None for general memory.
+    pub namespace: Option<String>,
+}
+
+/// Which memories [`Db::memory_list_filtered`] returns. Unset fields match
+/// every memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFilter {
+    pub category: Option<String>,
+    pub min_importance: Option<i32>,
+    /// One of the memory's comma-separated tags.
+    pub tag: Option<String>,
+}
+
+impl MemoryFilter {
+    pub fn matches(&self, entry: &MemoryEntry) -> bool {
+        self.category
+            .as_ref()
+            .map_or(true, |c| *c == entry.category)
+            && self.min_importance.map_or(true, |i| entry.importance >= i)
+            && self.tag.as_ref().map_or(true, |tag| {
+                entry
+                    .tags
+                    .as_deref()
+                    .unwrap_or("")
+                    .split(',')
+                    .any(|t| t.trim() == tag)
+            })
+    }
+}
+
+/// Changes made by [`Db::memory_edit`]. Unset fields are left as they are.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryEdit {
+    pub content: Option<String>,
+    pub category: Option<String>,
+    pub importance: Option<i32>,
+    pub tags: Option<String>,
 }
 
 /// Memory categories and their temporal decay half-lives in days.
@@ -65,46 +104,344 @@ impl Db {
         category: &str,
         importance: i32,
     ) -> Result<i64, DbError> {
+        self.memory_store_scoped(None, key, content, tags, source, category, importance)
+            .await
+    }
+
+    /// Store a memory entry in a skill namespace (None = general memory).
+    /// Keys are unique per namespace.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn memory_store_scoped(
+        &self,
+        namespace: Option<&str>,
+        key: Option<&str>,
+        content: &str,
+        tags: Option<&str>,
+        source: Option<&str>,
+        category: &str,
+        importance: i32,
+    ) -> Result<i64, DbError> {
+        if let Some(storage) = &self.storage {
+            let id = storage
+                .memory_store(namespace, key, content, tags, source, category, importance)
+                .await?;
+            self.memory_cache.invalidate();
+            return Ok(id);
+        }
+        let namespace = namespace.map(|s| s.to_string());
         let key = key.map(|s| s.to_string());
         let content = content.to_string();
         let tags = tags.map(|s| s.to_string());
         let source = source.map(|s| s.to_string());
         let category = category.to_string();
         let ts = now_ms();
-        self.exec(move |conn| {
-            memory_store_sync(
-                conn,
-                key.as_deref(),
-                &content,
-                tags.as_deref(),
-                source.as_deref(),
-                &category,
-                importance,
-                ts,
-            )
-        })
-        .await
+        let id = self
+            .exec(move |conn| {
+                memory_store_sync(
+                    conn,
+                    namespace.as_deref(),
+                    key.as_deref(),
+                    &content,
+                    tags.as_deref(),
+                    source.as_deref(),
+                    &category,
+                    importance,
+                    ts,
+                )
+            })
+            .await?;
+        self.memory_cache.invalidate();
+        Ok(id)
     }
 
-    /// Full-text search over memory with temporal decay applied.
+    /// Full-text search over general memory with temporal decay applied.
     pub async fn memory_search(
         &self,
         query: &str,
         limit: usize,
     ) -> Result<Vec<MemoryEntry>, DbError> {
-        let query = query.to_string();
-        self.exec(move |conn| memory_search_sync(conn, &query, limit))
-            .await
+        self.memory_search_scoped(query, limit, None).await
+    }
+
+    /// Full-text search within a skill namespace (None = general memory).
+    pub async fn memory_search_scoped(
+        &self,
+        query: &str,
+        limit: usize,
+        namespace: Option<&str>,
+    ) -> Result<Vec<MemoryEntry>, DbError> {
+        let cache_key = (query.to_string(), limit, namespace.map(|s| s.to_string()));
+        if let Some(entries) = self.memory_cache.get(&cache_key) {
+            // Keep access tracking accurate even when the search itself is skipped
+            let ids: Vec<i64> = entries.iter().filter_map(|e| e.id).collect();
+            if !ids.is_empty() {
+                self.memory_touch(ids).await?;
+            }
+            return Ok(entries);
+        }
+
+        let generation = self.memory_cache.generation();
+        let (query, _, namespace) = cache_key.clone();
+        let entries = match &self.storage {
+            Some(storage) => {
+                storage
+                    .memory_search(&query, limit, namespace.as_deref())
+                    .await?
+            }
+            None => {
+                // Search on a reader, then record the access on the writer
+                let entries = self
+                    .exec_read(move |conn| {
+                        memory_search_sync(conn, &query, limit, namespace.as_deref())
+                    })
+                    .await?;
+                let ids: Vec<i64> = entries.iter().filter_map(|e| e.id).collect();
+                if !ids.is_empty() {
+                    self.memory_touch(ids).await?;
+                }
+                entries
+            }
+        };
+        self.memory_cache
+            .insert(cache_key, generation, entries.clone());
+        Ok(entries)
     }
 
-    /// Get a memory entry by key.
+    /// Get a general memory entry by key.
     pub async fn memory_get(&self, key: &str) -> Result<Option<MemoryEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.memory_get(key).await;
+        }
         let key = key.to_string();
-        self.exec(move |conn| memory_get_sync(conn, &key)).await
+        self.exec_read(move |conn| memory_get_sync(conn, &key))
+            .await
+    }
+
+    /// Most recently updated memories across all namespaces, optionally of one category.
+    pub async fn memory_list(
+        &self,
+        limit: usize,
+        category: Option<&str>,
+    ) -> Result<Vec<MemoryEntry>, DbError> {
+        let filter = MemoryFilter {
+            category: category.map(|c| c.to_string()),
+            ..Default::default()
+        };
+        self.memory_list_filtered(limit, &filter).await
+    }
+
+    /// Most recently updated memories across all namespaces that match `filter`.
+    pub async fn memory_list_filtered(
+        &self,
+        limit: usize,
+        filter: &MemoryFilter,
+    ) -> Result<Vec<MemoryEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.memory_list_filtered(limit, filter).await;
+        }
+        let MemoryFilter {
+            category,
+            min_importance,
+            tag,
+        } = filter.clone();
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, key, content, tags, source, category, importance, last_accessed, access_count, created_at, updated_at, namespace
+                 FROM memory
+                 WHERE (?2 IS NULL OR category = ?2)
+                   AND (?3 IS NULL OR COALESCE(importance, 5) >= ?3)
+                   AND (?4 IS NULL OR instr(',' || REPLACE(COALESCE(tags, ''), ' ', '') || ',', ',' || ?4 || ',') > 0)
+                 ORDER BY updated_at DESC, id DESC LIMIT ?1",
+            )?;
+            let rows = stmt
+                .query_map(
+                    rusqlite::params![limit as i64, category, min_importance, tag],
+                    |row| {
+                    Ok(MemoryEntry {
+                        id: Some(row.get(0)?),
+                        key: row.get(1)?,
+                        content: row.get(2)?,
+                        tags: row.get(3)?,
+                        source: row.get(4)?,
+                        category: row
+                            .get::<_, Option<String>>(5)?
+                            .unwrap_or_else(|| "fact".to_string()),
+                        importance: row.get::<_, Option<i32>>(6)?.unwrap_or(5),
+                        last_accessed: row.get::<_, Option<i64>>(7)?.map(|v| v as u64),
+                        access_count: row.get::<_, Option<i32>>(8)?.unwrap_or(0),
+                        created_at: row.get::<_, i64>(9)? as u64,
+                        updated_at: row.get::<_, i64>(10)? as u64,
+                        namespace: row.get(11)?,
+                    })
+                    },
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Get a memory entry by ID.
+    pub async fn memory_get_by_id(&self, id: i64) -> Result<Option<MemoryEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.memory_get_by_id(id).await;
+        }
+        self.exec_read(move |conn| memory_get_by_id_sync(conn, id))
+            .await
+    }
+
+    /// Memories due for review, newest first, and how many there are in total:
+    /// those created since `recent_since` and low-importance ones unused since
+    /// `expiring_before` (candidates for stale cleanup). Memories kept or
+    /// edited in a review are skipped until they change again.
+    pub async fn memory_review_candidates(
+        &self,
+        recent_since: u64,
+        expiring_before: u64,
+        limit: usize,
+    ) -> Result<(Vec<MemoryEntry>, usize), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage
+                .memory_review_candidates(recent_since, expiring_before, limit)
+                .await;
+        }
+        self.exec_read(move |conn| {
+            const FILTER: &str = "(reviewed_at IS NULL OR reviewed_at < updated_at)
+                 AND category != 'context'
+                 AND (created_at >= ?1
+                      OR (importance <= 3 AND category != 'decision'
+                          AND last_accessed IS NOT NULL AND last_accessed < ?2))";
+            let params = rusqlite::params![recent_since as i64, expiring_before as i64];
+            let total: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM memory WHERE {}", FILTER),
+                params,
+                |r| r.get(0),
+            )?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT id FROM memory WHERE {} ORDER BY created_at DESC, id DESC LIMIT {}",
+                FILTER, limit
+            ))?;
+            let ids: Vec<i64> = stmt
+                .query_map(params, |r| r.get(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut entries = Vec::with_capacity(ids.len());
+            for id in ids {
+                entries.extend(memory_get_by_id_sync(conn, id)?);
+            }
+            Ok((entries, total as usize))
+        })
+        .await
+    }
+
+    /// Record that memories were kept in a review. Also counts as an access,
+    /// so stale cleanup starts over.
+    pub async fn memory_mark_reviewed(&self, ids: Vec<i64>) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.memory_mark_reviewed(ids).await;
+        }
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            let mut stmt = conn
+                .prepare("UPDATE memory SET reviewed_at = ?1, last_accessed = ?1 WHERE id = ?2")?;
+            for id in ids {
+                stmt.execute(rusqlite::params![ts, id])?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Replace the content of a memory after a review edit.
+    pub async fn memory_update_content(&self, id: i64, content: &str) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            storage.memory_update_content(id, content).await?;
+            self.memory_cache.invalidate();
+            return Ok(());
+        }
+        let content = content.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "UPDATE memory SET content = ?1, updated_at = ?2, reviewed_at = ?2, last_accessed = ?2
+                 WHERE id = ?3",
+                rusqlite::params![content, ts, id],
+            )?;
+
+            // Update embedding on content change
+            #[cfg(feature = "semantic")]
+            {
+                if super::vector::vec_table_exists(conn) {
+                    if let Ok(engine) = super::vector::EmbeddingEngine::global() {
+                        if let Ok(embeddings) = engine.embed(&[content.as_str()]) {
+                            if let Some(embedding) = embeddings.first() {
+                                super::vector::vec_insert(conn, id, embedding).ok();
+                            }
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
+        self.memory_cache.invalidate();
+        Ok(())
+    }
+
+    /// Change a memory by hand. Returns whether it exists.
+    pub async fn memory_edit(&self, id: i64, edit: &MemoryEdit) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            let found = storage.memory_edit(id, edit).await?;
+            self.memory_cache.invalidate();
+            return Ok(found);
+        }
+        let MemoryEdit {
+            content,
+            category,
+            importance,
+            tags,
+        } = edit.clone();
+        let ts = now_ms() as i64;
+        let found = self
+            .exec(move |conn| {
+                let updated = conn.execute(
+                    "UPDATE memory SET content = COALESCE(?1, content),
+                     category = COALESCE(?2, category), importance = COALESCE(?3, importance),
+                     tags = COALESCE(?4, tags), updated_at = ?5
+                     WHERE id = ?6",
+                    rusqlite::params![content, category, importance, tags, ts, id],
+                )?;
+
+                // Update embedding on content change
+                #[cfg(feature = "semantic")]
+                {
+                    if let Some(ref content) = content {
+                        if updated > 0 && super::vector::vec_table_exists(conn) {
+                            if let Ok(engine) = super::vector::EmbeddingEngine::global() {
+                                if let Ok(embeddings) = engine.embed(&[content.as_str()]) {
+                                    if let Some(embedding) = embeddings.first() {
+                                        super::vector::vec_insert(conn, id, embedding).ok();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                Ok(updated > 0)
+            })
+            .await?;
+        self.memory_cache.invalidate();
+        Ok(found)
     }
 
     /// Delete a memory entry by ID.
     pub async fn memory_delete(&self, id: i64) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            storage.memory_delete(id).await?;
+            self.memory_cache.invalidate();
+            return Ok(());
+        }
         self.exec(move |conn| {
             conn.execute("DELETE FROM memory WHERE id = ?1", rusqlite::params![id])?;
 
@@ -118,7 +455,9 @@ impl Db {
 
             Ok(())
         })
-        .await
+        .await?;
+        self.memory_cache.invalidate();
+        Ok(())
     }
 
     /// Store compacted conversation context as a memory entry (sync, for compaction).
@@ -132,10 +471,22 @@ impl Db {
     ) -> Result<i64, DbError> {
         let ts = now_ms();
         let tags = format!("compaction,dropped:{}", dropped_count);
-        tokio::task::block_in_place(|| {
+        let id = tokio::task::block_in_place(|| {
+            if let Some(storage) = &self.storage {
+                return Self::block_on(storage.memory_store(
+                    None,
+                    Some(source),
+                    content,
+                    Some(&tags),
+                    Some(source),
+                    "context",
+                    3,
+                ));
+            }
             self.exec_sync(|conn| {
                 memory_store_sync(
                     conn,
+                    None,
                     Some(source),
                     content,
                     Some(&tags),
@@ -145,11 +496,16 @@ impl Db {
                     ts,
                 )
             })
-        })
+        })?;
+        self.memory_cache.invalidate();
+        Ok(id)
     }
 
     /// Update access tracking for a set of memory IDs (called after search results are returned).
     pub async fn memory_touch(&self, ids: Vec<i64>) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.memory_touch(ids).await;
+        }
         let ts = now_ms();
         self.exec(move |conn| {
             let mut stmt = conn.prepare(
@@ -167,6 +523,7 @@ impl Db {
 #[allow(clippy::too_many_arguments)]
 fn memory_store_sync(
     conn: &Connection,
+    namespace: Option<&str>,
     key: Option<&str>,
     content: &str,
     tags: Option<&str>,
@@ -179,8 +536,8 @@ fn memory_store_sync(
     if let Some(key) = key {
         let existing: Option<i64> = conn
             .query_row(
-                "SELECT id FROM memory WHERE key = ?1",
-                rusqlite::params![key],
+                "SELECT id FROM memory WHERE key = ?1 AND namespace IS ?2",
+                rusqlite::params![key, namespace],
                 |row| row.get(0),
             )
             .ok();
@@ -210,9 +567,9 @@ fn memory_store_sync(
     }
     // Insert new
     conn.execute(
-        "INSERT INTO memory (key, content, tags, source, category, importance, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
-        rusqlite::params![key, content, tags, source, category, importance, ts as i64],
+        "INSERT INTO memory (key, content, tags, source, category, importance, created_at, updated_at, namespace)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?8)",
+        rusqlite::params![key, content, tags, source, category, importance, ts as i64, namespace],
     )?;
     let id = conn.last_insert_rowid();
 
@@ -243,6 +600,7 @@ fn memory_search_sync(
     conn: &Connection,
     query: &str,
     limit: usize,
+    namespace: Option<&str>,
 ) -> Result<Vec<MemoryEntry>, DbError> {
     let fetch_limit = limit * 3; // over-fetch for re-ranking
 
@@ -254,9 +612,9 @@ fn memory_search_sync(
         .map(|w| format!("\"{}\"*", w.replace('"', "\"\"")))
         .collect::<Vec<_>>()
         .join(" OR ");
-    let fts_entries = match memory_search_fts(conn, &safe_query, fetch_limit) {
+    let fts_entries = match memory_search_fts(conn, &safe_query, fetch_limit, namespace) {
         Ok(entries) => entries,
-        Err(_) => memory_search_like(conn, query, fetch_limit)?,
+        Err(_) => memory_search_like(conn, query, fetch_limit, namespace)?,
     };
 
     // 2. Optionally run vector KNN search and merge with RRF
@@ -323,6 +681,9 @@ fn memory_search_sync(
     #[cfg(not(feature = "semantic"))]
     let mut entries = fts_entries;
 
+    // Vector hits aren't namespace-filtered, so drop any from other namespaces
+    entries.retain(|e| e.namespace.as_deref() == namespace);
+
     // 3. Apply temporal decay and re-rank (using RRF scores as base when available)
     let now = now_ms();
     entries.sort_by(|a, b| {
@@ -345,19 +706,6 @@ fn memory_search_sync(
     });
 
     entries.truncate(limit);
-
-    // Update access tracking for returned results
-    let ids: Vec<i64> = entries.iter().filter_map(|e| e.id).collect();
-    if !ids.is_empty() {
-        let ts = now as i64;
-        let mut stmt = conn.prepare(
-            "UPDATE memory SET last_accessed = ?1, access_count = access_count + 1 WHERE id = ?2",
-        )?;
-        for id in &ids {
-            stmt.execute(rusqlite::params![ts, id])?;
-        }
-    }
-
     Ok(entries)
 }
 
@@ -365,14 +713,15 @@ fn memory_search_like(
     conn: &Connection,
     query: &str,
     limit: usize,
+    namespace: Option<&str>,
 ) -> Result<Vec<MemoryEntry>, DbError> {
     let pattern = format!("%{}%", query);
     let mut stmt = conn.prepare(
-        "SELECT id, key, content, tags, source, category, importance, last_accessed, access_count, created_at, updated_at
-         FROM memory WHERE content LIKE ?1 ORDER BY updated_at DESC LIMIT ?2",
+        "SELECT id, key, content, tags, source, category, importance, last_accessed, access_count, created_at, updated_at, namespace
+         FROM memory WHERE content LIKE ?1 AND namespace IS ?3 ORDER BY updated_at DESC LIMIT ?2",
     )?;
     let rows = stmt
-        .query_map(rusqlite::params![pattern, limit as i64], |row| {
+        .query_map(rusqlite::params![pattern, limit as i64, namespace], |row| {
             Ok(MemoryEntry {
                 id: Some(row.get(0)?),
                 key: row.get(1)?,
@@ -387,6 +736,7 @@ fn memory_search_like(
                 access_count: row.get::<_, Option<i32>>(8)?.unwrap_or(0),
                 created_at: row.get::<_, i64>(9)? as u64,
                 updated_at: row.get::<_, i64>(10)? as u64,
+                namespace: row.get(11)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
@@ -397,17 +747,18 @@ fn memory_search_fts(
     conn: &Connection,
     query: &str,
     limit: usize,
+    namespace: Option<&str>,
 ) -> Result<Vec<MemoryEntry>, DbError> {
     let mut stmt = conn.prepare(
-        "SELECT m.id, m.key, m.content, m.tags, m.source, m.category, m.importance, m.last_accessed, m.access_count, m.created_at, m.updated_at
+        "SELECT m.id, m.key, m.content, m.tags, m.source, m.category, m.importance, m.last_accessed, m.access_count, m.created_at, m.updated_at, m.namespace
          FROM memory m
          JOIN memory_fts f ON m.id = f.rowid
-         WHERE memory_fts MATCH ?1
+         WHERE memory_fts MATCH ?1 AND m.namespace IS ?3
          ORDER BY rank
          LIMIT ?2",
     )?;
     let rows = stmt
-        .query_map(rusqlite::params![query, limit as i64], |row| {
+        .query_map(rusqlite::params![query, limit as i64, namespace], |row| {
             Ok(MemoryEntry {
                 id: Some(row.get(0)?),
                 key: row.get(1)?,
@@ -422,16 +773,16 @@ fn memory_search_fts(
                 access_count: row.get::<_, Option<i32>>(8)?.unwrap_or(0),
                 created_at: row.get::<_, i64>(9)? as u64,
                 updated_at: row.get::<_, i64>(10)? as u64,
+                namespace: row.get(11)?,
             })
         })?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(rows)
 }
 
-#[cfg(feature = "semantic")]
 fn memory_get_by_id_sync(conn: &Connection, id: i64) -> Result<Option<MemoryEntry>, DbError> {
     let result = conn.query_row(
-        "SELECT id, key, content, tags, source, category, importance, last_accessed, access_count, created_at, updated_at
+        "SELECT id, key, content, tags, source, category, importance, last_accessed, access_count, created_at, updated_at, namespace
          FROM memory WHERE id = ?1",
         rusqlite::params![id],
         |row| {
@@ -447,6 +798,7 @@ fn memory_get_by_id_sync(conn: &Connection, id: i64) -> Result<Option<MemoryEntr
                 access_count: row.get::<_, Option<i32>>(8)?.unwrap_or(0),
                 created_at: row.get::<_, i64>(9)? as u64,
                 updated_at: row.get::<_, i64>(10)? as u64,
+                namespace: row.get(11)?,
             })
         },
     );
@@ -479,8 +831,8 @@ pub fn rrf_merge(
 
 fn memory_get_sync(conn: &Connection, key: &str) -> Result<Option<MemoryEntry>, DbError> {
     let result = conn.query_row(
-        "SELECT id, key, content, tags, source, category, importance, last_accessed, access_count, created_at, updated_at
-         FROM memory WHERE key = ?1",
+        "SELECT id, key, content, tags, source, category, importance, last_accessed, access_count, created_at, updated_at, namespace
+         FROM memory WHERE key = ?1 AND namespace IS NULL",
         rusqlite::params![key],
         |row| {
             Ok(MemoryEntry {
@@ -495,6 +847,7 @@ fn memory_get_sync(conn: &Connection, key: &str) -> Result<Option<MemoryEntry>,
                 access_count: row.get::<_, Option<i32>>(8)?.unwrap_or(0),
                 created_at: row.get::<_, i64>(9)? as u64,
                 updated_at: row.get::<_, i64>(10)? as u64,
+                namespace: row.get(11)?,
             })
         },
     );
@@ -543,6 +896,123 @@ mod tests {
         assert_eq!(entry.importance, 8);
     }
 
+    #[tokio::test]
+    async fn test_memory_list() {
+        let db = Db::open_memory().unwrap();
+        db.memory_store_with_meta(None, "Deploy by Friday", None, None, "task", 8)
+            .await
+            .unwrap();
+        db.memory_store_with_meta(None, "Prefers tea", None, None, "preference", 6)
+            .await
+            .unwrap();
+
+        let all = db.memory_list(10, None).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].content, "Prefers tea");
+
+        let tasks = db.memory_list(10, Some("task")).await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].content, "Deploy by Friday");
+    }
+
+    #[tokio::test]
+    async fn test_memory_list_filtered_and_edit() {
+        let db = Db::open_memory().unwrap();
+        let tea = db
+            .memory_store_with_meta(None, "Prefers tea", Some("drinks, user"), None, "fact", 6)
+            .await
+            .unwrap();
+        db.memory_store_with_meta(None, "Likes userland", Some("users"), None, "fact", 8)
+            .await
+            .unwrap();
+
+        let filter = |tag: &str, min: i32| MemoryFilter {
+            tag: Some(tag.to_string()),
+            min_importance: Some(min),
+            ..Default::default()
+        };
+        let found = db
+            .memory_list_filtered(10, &filter("user", 1))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, Some(tea));
+        assert!(filter("user", 1).matches(&found[0]));
+        assert!(!filter("user", 7).matches(&found[0]));
+        assert!(db
+            .memory_list_filtered(10, &filter("user", 7))
+            .await
+            .unwrap()
+            .is_empty());
+
+        let edit = MemoryEdit {
+            content: Some("Prefers green tea".into()),
+            category: Some("preference".into()),
+            importance: Some(9),
+            tags: None,
+        };
+        assert!(db.memory_edit(tea, &edit).await.unwrap());
+        let entry = db.memory_get_by_id(tea).await.unwrap().unwrap();
+        assert_eq!(entry.content, "Prefers green tea");
+        assert_eq!(entry.category, "preference");
+        assert_eq!(entry.importance, 9);
+        assert_eq!(entry.tags.as_deref(), Some("drinks, user"));
+        assert_eq!(db.memory_search("green", 5).await.unwrap().len(), 1);
+        assert!(!db.memory_edit(9999, &edit).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_memory_review_candidates() {
+        let db = Db::open_memory().unwrap();
+        let now = now_ms();
+        let new = db
+            .memory_store_with_meta(None, "New fact", None, None, "fact", 5)
+            .await
+            .unwrap();
+        let stale = db
+            .memory_store_with_meta(None, "Old trivia", None, None, "fact", 2)
+            .await
+            .unwrap();
+        let decision = db
+            .memory_store_with_meta(None, "Use SQLite", None, None, "decision", 2)
+            .await
+            .unwrap();
+        // Backdate the stale and decision memories by 80 days
+        let old = (now - 80 * 86_400_000) as i64;
+        db.exec(move |conn| {
+            conn.execute(
+                "UPDATE memory SET created_at = ?1, updated_at = ?1, last_accessed = ?1 WHERE id IN (?2, ?3)",
+                rusqlite::params![old, stale, decision],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let recent_since = now - 7 * 86_400_000;
+        let expiring_before = now - 75 * 86_400_000;
+        let (batch, total) = db
+            .memory_review_candidates(recent_since, expiring_before, 10)
+            .await
+            .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(batch[0].id, Some(new));
+        assert_eq!(batch[1].id, Some(stale));
+
+        db.memory_mark_reviewed(vec![new]).await.unwrap();
+        db.memory_update_content(stale, "Old trivia, still true")
+            .await
+            .unwrap();
+        let (batch, total) = db
+            .memory_review_candidates(recent_since, expiring_before, 10)
+            .await
+            .unwrap();
+        assert!(batch.is_empty());
+        assert_eq!(total, 0);
+        let edited = db.memory_get_by_id(stale).await.unwrap().unwrap();
+        assert_eq!(edited.content, "Old trivia, still true");
+    }
+
     #[tokio::test]
     async fn test_upsert_by_key() {
         let db = Db::open_memory().unwrap();
@@ -615,6 +1085,75 @@ mod tests {
         assert_eq!(count_after, 1);
     }
 
+    #[tokio::test]
+    async fn test_search_cache_invalidated_on_write() {
+        let db = Db::open_memory()
+            .unwrap()
+            .with_memory_cache(16, std::time::Duration::from_secs(60));
+        db.memory_store(Some("a"), "otter facts", None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(db.memory_search("otter", 10).await.unwrap().len(), 1);
+        assert_eq!(db.memory_search("otter", 10).await.unwrap().len(), 1);
+        let stats = db.memory_cache_stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+
+        // A write must not leave the old result behind
+        db.memory_store(Some("b"), "more otter facts", None, None)
+            .await
+            .unwrap();
+        assert_eq!(db.memory_search("otter", 10).await.unwrap().len(), 2);
+        assert_eq!(db.memory_cache_stats().misses, 2);
+
+        // Two misses and one hit, all counted as accesses
+        let entry = db.memory_get("a").await.unwrap().unwrap();
+        assert_eq!(entry.access_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_memory_is_isolated() {
+        let db = Db::open_memory().unwrap();
+        db.memory_store(Some("k"), "general fox note", None, None)
+            .await
+            .unwrap();
+        db.memory_store_scoped(
+            Some("inbox"),
+            Some("k"),
+            "inbox fox note",
+            None,
+            None,
+            "fact",
+            5,
+        )
+        .await
+        .unwrap();
+
+        // Same key in different namespaces doesn't upsert across them
+        assert_eq!(
+            db.memory_get("k").await.unwrap().unwrap().content,
+            "general fox note"
+        );
+
+        let general = db.memory_search("fox", 10).await.unwrap();
+        assert_eq!(general.len(), 1);
+        assert!(general[0].namespace.is_none());
+
+        let inbox = db
+            .memory_search_scoped("fox", 10, Some("inbox"))
+            .await
+            .unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].content, "inbox fox note");
+        assert_eq!(inbox[0].namespace.as_deref(), Some("inbox"));
+
+        let other = db
+            .memory_search_scoped("fox", 10, Some("other"))
+            .await
+            .unwrap();
+        assert!(other.is_empty());
+    }
+
     #[tokio::test]
     async fn test_delete() {
         let db = Db::open_memory().unwrap();
@@ -1,14 +1,52 @@
+pub mod analytics;
+pub mod annotations;
 pub mod audit;
+pub mod background;
+mod backup;
+mod cache;
+mod contention;
+pub mod cron;
+pub mod deliveries;
+pub mod drafts;
+pub mod events;
+pub mod greetings;
 pub mod memory;
+pub mod onboarding;
+pub mod overrides;
+pub mod pause;
+pub mod pins;
+mod pool;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod privacy;
+pub mod projects;
+pub mod push;
 pub mod queue;
+pub mod replies;
+pub mod reports;
+pub mod review;
+pub mod schedule;
+pub mod sender_usage;
+pub mod sessions;
+pub mod snapshots;
+pub mod storage;
 pub mod tape;
+pub mod temperature;
+pub mod tool_usage;
 #[cfg(feature = "semantic")]
 pub mod vector;
+pub mod voice;
+pub mod worker_runs;
 
-use rusqlite::Connection;
 use rusqlite::OptionalExtension;
+use rusqlite::{Connection, OpenFlags};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub use cache::MemoryCacheStats;
+pub use contention::WriterStats;
+pub use storage::Storage;
 
 #[derive(Debug, thiserror::Error)]
 pub enum DbError {
@@ -20,22 +58,92 @@ pub enum DbError {
     JoinError(String),
     #[error("Serialization error: {0}")]
     Serde(#[from] serde_json::Error),
+    #[error("Database encryption needs yoclaw built with the `encryption` feature")]
+    EncryptionUnsupported,
+    #[error("Wrong database key, or the database isn't encrypted (encrypt it with `yoclaw backup encrypt`)")]
+    WrongKey,
+    #[error("Postgres error: {0}")]
+    Postgres(String),
+    #[error("Postgres storage needs yoclaw built with the `postgres` feature")]
+    PostgresUnsupported,
 }
 
-/// Database handle. Clone-safe (wraps Arc<Mutex<Connection>>).
+/// Default number of read-only connections for file-backed databases.
+pub const DEFAULT_READ_POOL_SIZE: usize = 4;
+
+/// Database handle. Clone-safe.
+///
+/// Writes are serialized through a single connection behind a mutex. Reads that
+/// go through `exec_read` use a pool of read-only WAL connections instead, so
+/// they never wait on in-flight writes.
 #[derive(Clone)]
 pub struct Db {
     conn: Arc<Mutex<Connection>>,
+    readers: Arc<pool::ReadPool>,
+    memory_cache: Arc<cache::MemoryCache>,
+    writer_wait: Arc<contention::WriterWait>,
+    /// SQLCipher key, also used for backup copies.
+    key: Option<Arc<str>>,
+    /// Backend of the shared data, if not this database.
+    storage: Option<Arc<dyn Storage>>,
+}
+
+/// Open a connection to the database file at `path`, unlocking it with `key`
+/// if given. A key needs SQLCipher: without the `encryption` feature,
+/// SQLite would silently ignore it and store everything in plain text.
+pub(crate) fn connect(
+    path: &Path,
+    flags: OpenFlags,
+    key: Option<&str>,
+) -> Result<Connection, DbError> {
+    let Some(key) = key else {
+        return Ok(Connection::open_with_flags(path, flags)?);
+    };
+    if !cfg!(feature = "encryption") {
+        return Err(DbError::EncryptionUnsupported);
+    }
+    let conn = Connection::open_with_flags(path, flags)?;
+    conn.pragma_update(None, "key", key)?;
+    // The key is only checked on the first read
+    match conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(())) {
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::NotADatabase =>
+        {
+            Err(DbError::WrongKey)
+        }
+        result => {
+            result?;
+            Ok(conn)
+        }
+    }
 }
 
 impl Db {
-    /// Open a file-backed database with WAL mode.
+    /// Open a file-backed database with WAL mode and the default read pool.
     pub fn open(path: &Path) -> Result<Self, DbError> {
+        Self::open_with_readers(path, DEFAULT_READ_POOL_SIZE)
+    }
+
+    /// Open a file-backed database with WAL mode and `readers` read-only connections.
+    /// With `readers == 0`, all reads share the writer connection.
+    pub fn open_with_readers(path: &Path, readers: usize) -> Result<Self, DbError> {
+        Self::open_with_key(path, readers, None)
+    }
+
+    /// Open a file-backed database like `open_with_readers`, encrypted with
+    /// SQLCipher under `key` if one is given.
+    pub fn open_with_key(path: &Path, readers: usize, key: Option<&str>) -> Result<Self, DbError> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent).ok();
         }
-        let conn = Connection::open(path)?;
-        Self::configure_and_migrate(conn)
+        let conn = connect(path, OpenFlags::default(), key)?;
+        let mut db = Self::configure_and_migrate(conn)?;
+        db.key = key.map(Arc::from);
+        // Readers are opened after migrations so they see the final schema
+        if readers > 0 {
+            db.readers = Arc::new(pool::ReadPool::open(path, readers, key)?);
+        }
+        Ok(db)
     }
 
     /// Open an in-memory database (for tests).
@@ -61,11 +169,50 @@ impl Db {
 
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
+            readers: Arc::new(pool::ReadPool::empty()),
+            memory_cache: Arc::new(cache::MemoryCache::disabled()),
+            writer_wait: Arc::default(),
+            key: None,
+            storage: None,
         };
         db.run_migrations()?;
         Ok(db)
     }
 
+    /// Cache up to `capacity` memory search results for `ttl`. Disabled by default.
+    pub fn with_memory_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.memory_cache = Arc::new(cache::MemoryCache::new(capacity, ttl));
+        self
+    }
+
+    /// Keep tapes, the queue, memory, the audit log, cron jobs and saved
+    /// workers in `storage` instead of this database.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Whether the shared data is in another backend.
+    pub fn has_storage(&self) -> bool {
+        self.storage.is_some()
+    }
+
+    /// Memory search cache hit/miss counters.
+    pub fn memory_cache_stats(&self) -> MemoryCacheStats {
+        self.memory_cache.stats()
+    }
+
+    /// How long operations waited for the writer connection.
+    pub fn writer_stats(&self) -> WriterStats {
+        self.writer_wait.stats()
+    }
+
+    /// Drop cached memory search results. Call after writing to the memory
+    /// table outside the `memory_*` methods.
+    pub fn memory_cache_invalidate(&self) {
+        self.memory_cache.invalidate();
+    }
+
     /// Execute a blocking DB operation on a spawn_blocking thread.
     pub async fn exec<F, T>(&self, f: F) -> Result<T, DbError>
     where
@@ -73,14 +220,33 @@ impl Db {
         T: Send + 'static,
     {
         let conn = self.conn.clone();
+        let writer_wait = self.writer_wait.clone();
+        let queued = std::time::Instant::now();
         tokio::task::spawn_blocking(move || {
             let conn = conn.lock().map_err(|_| DbError::LockPoisoned)?;
+            writer_wait.record(queued.elapsed());
             f(&conn)
         })
         .await
         .map_err(|e| DbError::JoinError(e.to_string()))?
     }
 
+    /// Execute a read-only DB operation on a pooled reader connection.
+    /// Falls back to the writer connection when no readers are open (in-memory databases).
+    pub async fn exec_read<F, T>(&self, f: F) -> Result<T, DbError>
+    where
+        F: FnOnce(&Connection) -> Result<T, DbError> + Send + 'static,
+        T: Send + 'static,
+    {
+        if self.readers.is_empty() {
+            return self.exec(f).await;
+        }
+        let readers = self.readers.clone();
+        tokio::task::spawn_blocking(move || readers.with_reader(f))
+            .await
+            .map_err(|e| DbError::JoinError(e.to_string()))?
+    }
+
     /// Execute a blocking DB operation synchronously (for non-async contexts like tests).
     pub fn exec_sync<F, T>(&self, f: F) -> Result<T, DbError>
     where
@@ -90,6 +256,13 @@ impl Db {
         f(&conn)
     }
 
+    /// Run a storage backend call from sync code, inside `block_in_place`
+    /// when on a tokio worker thread.
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let handle = tokio::runtime::Handle::current();
+        tokio::task::block_in_place(|| handle.block_on(future))
+    }
+
     // -- Migrations --
 
     const MIGRATIONS: &[(&str, &str)] = &[
@@ -109,6 +282,94 @@ impl Db {
             "004_saved_workers",
             include_str!("../../migrations/004_saved_workers.sql"),
         ),
+        (
+            "005_memory_namespace",
+            include_str!("../../migrations/005_memory_namespace.sql"),
+        ),
+        (
+            "006_deliveries",
+            include_str!("../../migrations/006_deliveries.sql"),
+        ),
+        (
+            "007_background_tasks",
+            include_str!("../../migrations/007_background_tasks.sql"),
+        ),
+        (
+            "008_memory_review",
+            include_str!("../../migrations/008_memory_review.sql"),
+        ),
+        (
+            "009_cron_model",
+            include_str!("../../migrations/009_cron_model.sql"),
+        ),
+        (
+            "010_queue_failure",
+            include_str!("../../migrations/010_queue_failure.sql"),
+        ),
+        (
+            "011_turn_snapshots",
+            include_str!("../../migrations/011_turn_snapshots.sql"),
+        ),
+        (
+            "012_push_subscriptions",
+            include_str!("../../migrations/012_push_subscriptions.sql"),
+        ),
+        (
+            "013_sessions",
+            include_str!("../../migrations/013_sessions.sql"),
+        ),
+        (
+            "014_drafts",
+            include_str!("../../migrations/014_drafts.sql"),
+        ),
+        (
+            "015_projects",
+            include_str!("../../migrations/015_projects.sql"),
+        ),
+        (
+            "016_replies",
+            include_str!("../../migrations/016_replies.sql"),
+        ),
+        (
+            "017_tool_usage",
+            include_str!("../../migrations/017_tool_usage.sql"),
+        ),
+        (
+            "018_audit_daily",
+            include_str!("../../migrations/018_audit_daily.sql"),
+        ),
+        (
+            "019_sender_usage",
+            include_str!("../../migrations/019_sender_usage.sql"),
+        ),
+        (
+            "020_audit_sender",
+            include_str!("../../migrations/020_audit_sender.sql"),
+        ),
+        (
+            "021_queue_retry",
+            include_str!("../../migrations/021_queue_retry.sql"),
+        ),
+        (
+            "022_audit_cost",
+            include_str!("../../migrations/022_audit_cost.sql"),
+        ),
+        (
+            "023_queue_attachments",
+            include_str!("../../migrations/023_queue_attachments.sql"),
+        ),
+        (
+            "024_worker_runs",
+            include_str!("../../migrations/024_worker_runs.sql"),
+        ),
+        (
+            "025_annotations",
+            include_str!("../../migrations/025_annotations.sql"),
+        ),
+        (
+            "026_queue_reply",
+            include_str!("../../migrations/026_queue_reply.sql"),
+        ),
     ];
 
     fn run_migrations(&self) -> Result<(), DbError> {
@@ -153,7 +414,10 @@ pub struct SavedWorker {
 impl Db {
     /// List all saved workers.
     pub async fn saved_workers_list(&self) -> Result<Vec<SavedWorker>, DbError> {
-        self.exec(|conn| {
+        if let Some(storage) = &self.storage {
+            return storage.saved_workers_list().await;
+        }
+        self.exec_read(|conn| {
             let mut stmt = conn.prepare(
                 "SELECT name, system_prompt, created_at FROM saved_workers ORDER BY name",
             )?;
@@ -173,8 +437,11 @@ impl Db {
 
     /// Get a saved worker by name.
     pub async fn saved_workers_get(&self, name: &str) -> Result<Option<SavedWorker>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.saved_workers_get(name).await;
+        }
         let name = name.to_string();
-        self.exec(move |conn| {
+        self.exec_read(move |conn| {
             let mut stmt = conn.prepare(
                 "SELECT name, system_prompt, created_at FROM saved_workers WHERE name = ?1",
             )?;
@@ -198,6 +465,9 @@ impl Db {
         name: &str,
         system_prompt: &str,
     ) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.saved_workers_upsert(name, system_prompt).await;
+        }
         let name = name.to_string();
         let system_prompt = system_prompt.to_string();
         let now = now_ms() as i64;
@@ -214,6 +484,9 @@ impl Db {
 
     /// Remove a saved worker by name. Returns true if deleted.
     pub async fn saved_workers_remove(&self, name: &str) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.saved_workers_remove(name).await;
+        }
         let name = name.to_string();
         self.exec(move |conn| {
             let rows = conn.execute(
@@ -266,7 +539,7 @@ mod tests {
         db.exec_sync(|conn| {
             let count: i64 =
                 conn.query_row("SELECT COUNT(*) FROM schema_version", [], |r| r.get(0))?;
-            assert_eq!(count, 4); // 001_initial + 002_vector_memory + 003_scheduler + 004_saved_workers
+            assert_eq!(count, 26); // 001_initial .. 026_queue_reply
             Ok(())
         })
         .unwrap();
@@ -284,4 +557,85 @@ mod tests {
             .unwrap();
         assert_eq!(result, 42);
     }
+
+    #[cfg(not(feature = "encryption"))]
+    #[test]
+    fn test_key_needs_encryption_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plain.db");
+        assert!(matches!(
+            Db::open_with_key(&path, 0, Some("s3cret")),
+            Err(DbError::EncryptionUnsupported)
+        ));
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_encrypted_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret.db");
+        let db = Db::open_with_key(&path, 2, Some("s3cret")).unwrap();
+        db.saved_workers_upsert("w", "private prompt")
+            .await
+            .unwrap();
+        assert!(db.saved_workers_get("w").await.unwrap().is_some());
+        let copy = dir.path().join("copy.db");
+        db.backup_to(&copy).await.unwrap();
+        drop(db);
+
+        for file in [&path, &copy] {
+            let bytes = std::fs::read(file).unwrap();
+            assert!(!bytes.starts_with(b"SQLite format 3"));
+            assert!(!bytes.windows(14).any(|w| w == b"private prompt"));
+            assert!(matches!(
+                Db::open_with_key(file, 0, Some("wrong")),
+                Err(DbError::WrongKey)
+            ));
+            let db = Db::open_with_key(file, 0, Some("s3cret")).unwrap();
+            assert!(db.saved_workers_get("w").await.unwrap().is_some());
+        }
+        assert!(Db::open(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reads_not_blocked_by_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::open_with_readers(&dir.path().join("pool.db"), 2).unwrap();
+        db.saved_workers_upsert("w", "prompt").await.unwrap();
+
+        // Hold the writer connection on another thread until the read completes
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel::<()>();
+        let writer_db = db.clone();
+        let holder = std::thread::spawn(move || {
+            writer_db
+                .exec_sync(|_conn| {
+                    locked_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    Ok(())
+                })
+                .unwrap();
+        });
+        locked_rx.recv().unwrap();
+
+        let workers = db.saved_workers_list().await.unwrap();
+        assert_eq!(workers.len(), 1);
+        // Memory search only writes when it has results to record
+        assert!(db.memory_search("anything", 5).await.unwrap().is_empty());
+        // The scheduler's reads don't queue behind the writer either
+        assert!(db.cron_jobs_enabled().await.unwrap().is_empty());
+        assert!(db.used_bytes().await.unwrap() > 0);
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_exec_read_falls_back_to_writer_in_memory() {
+        let db = Db::open_memory().unwrap();
+        db.saved_workers_upsert("w", "prompt").await.unwrap();
+        let worker = db.saved_workers_get("w").await.unwrap();
+        assert!(worker.is_some());
+    }
 }
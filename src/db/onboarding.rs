@@ -0,0 +1,69 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key holding the onboarding interview progress.
+const ONBOARDING_KEY: &str = "onboarding";
+
+impl Db {
+    /// Raw onboarding progress, or None if the interview never started.
+    pub async fn onboarding_get(&self) -> Result<Option<String>, DbError> {
+        self.exec_read(|conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![ONBOARDING_KEY],
+                    |r| r.get(0),
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    pub async fn onboarding_set(&self, value: &str) -> Result<(), DbError> {
+        let value = value.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![ONBOARDING_KEY, value, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// True if nothing has been remembered or said yet.
+    pub async fn is_fresh(&self) -> Result<bool, DbError> {
+        self.exec_read(|conn| {
+            let count: i64 = conn.query_row(
+                "SELECT (SELECT COUNT(*) FROM memory) + (SELECT COUNT(*) FROM tape)",
+                [],
+                |r| r.get(0),
+            )?;
+            Ok(count == 0)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::types::Message;
+    use yoagent::AgentMessage;
+
+    #[tokio::test]
+    async fn test_onboarding_state_and_freshness() {
+        let db = Db::open_memory().unwrap();
+        assert!(db.is_fresh().await.unwrap());
+        assert_eq!(db.onboarding_get().await.unwrap(), None);
+
+        db.onboarding_set("0 tg-1").await.unwrap();
+        db.onboarding_set("done").await.unwrap();
+        assert_eq!(db.onboarding_get().await.unwrap().as_deref(), Some("done"));
+
+        let msgs = vec![AgentMessage::Llm(Message::user("hello"))];
+        db.tape_save_messages("tg-1", &msgs).await.unwrap();
+        assert!(!db.is_fresh().await.unwrap());
+    }
+}
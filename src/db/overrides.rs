@@ -0,0 +1,69 @@
+use super::{now_ms, Db, DbError};
+
+/// State-table key prefix for config overrides set via `/admin`.
+const OVERRIDE_PREFIX: &str = "override:";
+
+impl Db {
+    /// Persist a config override. `key` is a dotted config path.
+    pub async fn override_set(&self, key: &str, value: &str) -> Result<(), DbError> {
+        let key = format!("{}{}", OVERRIDE_PREFIX, key);
+        let value = value.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, value, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// All config overrides as (key, value), oldest first.
+    pub async fn override_list(&self) -> Result<Vec<(String, String)>, DbError> {
+        self.exec_read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT key, value FROM state WHERE key LIKE 'override:%' ORDER BY updated_at, key",
+            )?;
+            let rows = stmt
+                .query_map([], |row| {
+                    let key: String = row.get(0)?;
+                    Ok((
+                        key[OVERRIDE_PREFIX.len()..].to_string(),
+                        row.get::<_, String>(1)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_override_set_replaces() {
+        let db = Db::open_memory().unwrap();
+        assert!(db.override_list().await.unwrap().is_empty());
+
+        db.override_set("security.tools.shell.enabled", "false")
+            .await
+            .unwrap();
+        db.override_set("security.tools.shell.enabled", "true")
+            .await
+            .unwrap();
+        db.privacy_set("tg-1", true).await.unwrap();
+
+        let overrides = db.override_list().await.unwrap();
+        assert_eq!(
+            overrides,
+            vec![(
+                "security.tools.shell.enabled".to_string(),
+                "true".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+/// Scope of a pause covering the whole daemon.
+pub const PAUSE_ALL: &str = "all";
+
+/// State-table key of a pause, for the whole daemon or one session.
+fn pause_key(scope: &str) -> String {
+    format!("pause:{}", scope)
+}
+
+/// Read-only mode: messages are queued but no agent runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pause {
+    /// [`PAUSE_ALL`] or a session ID.
+    pub scope: String,
+    /// Who paused: `cli`, `web`, `signal` or `admin`.
+    pub by: String,
+    pub reason: Option<String>,
+    pub since: u64,
+}
+
+impl Db {
+    /// Pause the daemon ([`PAUSE_ALL`]) or one session. Replaces an existing
+    /// pause of the same scope.
+    pub async fn pause_set(
+        &self,
+        scope: &str,
+        by: &str,
+        reason: Option<&str>,
+    ) -> Result<Pause, DbError> {
+        let pause = Pause {
+            scope: scope.to_string(),
+            by: by.to_string(),
+            reason: reason.map(str::to_string),
+            since: now_ms(),
+        };
+        let key = pause_key(scope);
+        let value = serde_json::to_string(&pause).unwrap_or_default();
+        let ts = pause.since as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, value, ts],
+            )?;
+            Ok(())
+        })
+        .await?;
+        Ok(pause)
+    }
+
+    /// Lift a pause. Returns false if there was none.
+    pub async fn pause_clear(&self, scope: &str) -> Result<bool, DbError> {
+        let key = pause_key(scope);
+        self.exec(move |conn| {
+            let removed =
+                conn.execute("DELETE FROM state WHERE key = ?1", rusqlite::params![key])?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    /// All pauses in effect, the daemon-wide one first.
+    pub async fn pause_list(&self) -> Result<Vec<Pause>, DbError> {
+        self.exec_read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT value FROM state WHERE key LIKE 'pause:%'
+                 ORDER BY key != 'pause:all', updated_at",
+            )?;
+            let values = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(values
+                .iter()
+                .filter_map(|v| serde_json::from_str(v).ok())
+                .collect())
+        })
+        .await
+    }
+
+    /// The pause holding back `session_id`: daemon-wide, else the session's
+    /// own. With `None`, only a daemon-wide pause counts.
+    pub async fn pause_check(&self, session_id: Option<&str>) -> Result<Option<Pause>, DbError> {
+        let keys: Vec<String> = std::iter::once(pause_key(PAUSE_ALL))
+            .chain(session_id.map(pause_key))
+            .collect();
+        self.exec_read(move |conn| {
+            for key in keys {
+                let value: Option<String> = conn
+                    .query_row(
+                        "SELECT value FROM state WHERE key = ?1",
+                        rusqlite::params![key],
+                        |r| r.get(0),
+                    )
+                    .optional()?;
+                if let Some(pause) = value.and_then(|v| serde_json::from_str(&v).ok()) {
+                    return Ok(Some(pause));
+                }
+            }
+            Ok(None)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pause_scopes() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.pause_check(Some("tg-1")).await.unwrap(), None);
+
+        db.pause_set("tg-1", "cli", Some("spam")).await.unwrap();
+        let pause = db.pause_check(Some("tg-1")).await.unwrap().unwrap();
+        assert_eq!(pause.scope, "tg-1");
+        assert_eq!(pause.reason.as_deref(), Some("spam"));
+        assert_eq!(db.pause_check(Some("tg-2")).await.unwrap(), None);
+        assert_eq!(db.pause_check(None).await.unwrap(), None);
+
+        // The daemon-wide pause covers every session and scheduled work
+        db.pause_set(PAUSE_ALL, "signal", None).await.unwrap();
+        assert_eq!(
+            db.pause_check(Some("tg-2")).await.unwrap().unwrap().by,
+            "signal"
+        );
+        assert_eq!(
+            db.pause_check(Some("tg-1")).await.unwrap().unwrap().scope,
+            PAUSE_ALL
+        );
+        assert!(db.pause_check(None).await.unwrap().is_some());
+        let scopes: Vec<String> = db
+            .pause_list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.scope)
+            .collect();
+        assert_eq!(scopes, vec!["all", "tg-1"]);
+
+        assert!(db.pause_clear(PAUSE_ALL).await.unwrap());
+        assert!(!db.pause_clear(PAUSE_ALL).await.unwrap());
+        assert_eq!(db.pause_check(Some("tg-2")).await.unwrap(), None);
+        assert!(db.pause_check(Some("tg-1")).await.unwrap().is_some());
+    }
+}
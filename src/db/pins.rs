@@ -0,0 +1,63 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key holding the worker a session is pinned to.
+fn pin_key(session_id: &str) -> String {
+    format!("pin:{}", session_id)
+}
+
+impl Db {
+    /// Worker the session is pinned to with `/use worker`, if any.
+    pub async fn pin_get(&self, session_id: &str) -> Result<Option<String>, DbError> {
+        let key = pin_key(session_id);
+        self.exec_read(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Pin a session to a worker, or back to the main agent with None.
+    pub async fn pin_set(&self, session_id: &str, worker: Option<&str>) -> Result<(), DbError> {
+        let key = pin_key(session_id);
+        let worker = worker.map(|w| w.to_string());
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            match worker {
+                Some(worker) => conn.execute(
+                    "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![key, worker, ts],
+                )?,
+                None => conn.execute("DELETE FROM state WHERE key = ?1", rusqlite::params![key])?,
+            };
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pin_and_unpin() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.pin_get("dc-1-2").await.unwrap(), None);
+
+        db.pin_set("dc-1-2", Some("coding")).await.unwrap();
+        assert_eq!(
+            db.pin_get("dc-1-2").await.unwrap().as_deref(),
+            Some("coding")
+        );
+        assert_eq!(db.pin_get("tg-1").await.unwrap(), None);
+
+        db.pin_set("dc-1-2", None).await.unwrap();
+        assert_eq!(db.pin_get("dc-1-2").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,74 @@
+//! Read-only connection pool used alongside the single writer connection.
+//!
+//! With WAL enabled, readers never block the writer (and vice versa), so read
+//! traffic from the web API and inspect commands no longer queues behind the
+//! conductor and scheduler on the writer mutex.
+
+use super::DbError;
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub(crate) struct ReadPool {
+    conns: Vec<Mutex<Connection>>,
+    next: AtomicUsize,
+}
+
+impl ReadPool {
+    /// A pool with no readers. All reads fall back to the writer connection.
+    pub(crate) fn empty() -> Self {
+        Self {
+            conns: Vec::new(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Open `size` read-only connections to an existing database file,
+    /// unlocked with `key` if it is encrypted.
+    pub(crate) fn open(path: &Path, size: usize, key: Option<&str>) -> Result<Self, DbError> {
+        let mut conns = Vec::with_capacity(size);
+        for _ in 0..size {
+            let conn = super::connect(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                key,
+            )?;
+            conn.execute_batch("PRAGMA busy_timeout = 5000;")?;
+
+            #[cfg(feature = "semantic")]
+            {
+                super::vector::load_sqlite_vec(&conn).ok();
+            }
+
+            conns.push(Mutex::new(conn));
+        }
+        Ok(Self {
+            conns,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.conns.is_empty()
+    }
+
+    /// Run `f` on an idle reader. If every reader is busy, wait for the next
+    /// one in round-robin order.
+    pub(crate) fn with_reader<F, T>(&self, f: F) -> Result<T, DbError>
+    where
+        F: FnOnce(&Connection) -> Result<T, DbError>,
+    {
+        let n = self.conns.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for i in 0..n {
+            if let Ok(conn) = self.conns[(start + i) % n].try_lock() {
+                return f(&conn);
+            }
+        }
+        let conn = self.conns[start % n]
+            .lock()
+            .map_err(|_| DbError::LockPoisoned)?;
+        f(&conn)
+    }
+}
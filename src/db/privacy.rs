@@ -0,0 +1,111 @@
+use super::{now_ms, Db, DbError};
+
+/// State-table key marking a session as private.
+fn private_key(session_id: &str) -> String {
+    format!("private:{}", session_id)
+}
+
+impl Db {
+    /// Turn private mode on or off for a session.
+    pub async fn privacy_set(&self, session_id: &str, private: bool) -> Result<(), DbError> {
+        let key = private_key(session_id);
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            if private {
+                conn.execute(
+                    "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, '1', ?2)",
+                    rusqlite::params![key, ts],
+                )?;
+            } else {
+                conn.execute("DELETE FROM state WHERE key = ?1", rusqlite::params![key])?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Check whether a session is in private mode.
+    pub async fn privacy_is_private(&self, session_id: &str) -> Result<bool, DbError> {
+        let key = private_key(session_id);
+        self.exec_read(move |conn| {
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM state WHERE key = ?1",
+                rusqlite::params![key],
+                |r| r.get(0),
+            )?;
+            Ok(count > 0)
+        })
+        .await
+    }
+
+    /// Delete tapes of private sessions that have been idle longer than `ttl_ms`.
+    /// The private flag itself is kept so the session stays private if it resumes.
+    /// Returns the number of purged tapes.
+    pub async fn privacy_purge_expired(&self, ttl_ms: u64) -> Result<usize, DbError> {
+        let cutoff = now_ms().saturating_sub(ttl_ms) as i64;
+        self.exec(move |conn| {
+            let purged = conn.execute(
+                "DELETE FROM tape WHERE updated_at < ?1
+                 AND ('private:' || session_id) IN (SELECT key FROM state WHERE key LIKE 'private:%')",
+                rusqlite::params![cutoff],
+            )?;
+            Ok(purged)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::types::Message;
+    use yoagent::AgentMessage;
+
+    #[tokio::test]
+    async fn test_privacy_toggle() {
+        let db = Db::open_memory().unwrap();
+        assert!(!db.privacy_is_private("tg-1").await.unwrap());
+
+        db.privacy_set("tg-1", true).await.unwrap();
+        assert!(db.privacy_is_private("tg-1").await.unwrap());
+        assert!(!db.privacy_is_private("tg-2").await.unwrap());
+
+        db.privacy_set("tg-1", false).await.unwrap();
+        assert!(!db.privacy_is_private("tg-1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_purge_only_expired_private_tapes() {
+        let db = Db::open_memory().unwrap();
+        let msgs = vec![AgentMessage::Llm(Message::user("secret"))];
+        db.tape_save_messages("private-old", &msgs).await.unwrap();
+        db.tape_save_messages("private-new", &msgs).await.unwrap();
+        db.tape_save_messages("public-old", &msgs).await.unwrap();
+        db.privacy_set("private-old", true).await.unwrap();
+        db.privacy_set("private-new", true).await.unwrap();
+
+        // Backdate two of the tapes by two hours
+        let old_ts = (now_ms() - 2 * 60 * 60 * 1000) as i64;
+        db.exec(move |conn| {
+            conn.execute(
+                "UPDATE tape SET updated_at = ?1 WHERE session_id IN ('private-old', 'public-old')",
+                rusqlite::params![old_ts],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let purged = db.privacy_purge_expired(60 * 60 * 1000).await.unwrap();
+        assert_eq!(purged, 1);
+        assert!(db
+            .tape_load_messages("private-old")
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(db.tape_load_messages("private-new").await.unwrap().len(), 1);
+        assert_eq!(db.tape_load_messages("public-old").await.unwrap().len(), 1);
+        // Flag survives the purge
+        assert!(db.privacy_is_private("private-old").await.unwrap());
+    }
+}
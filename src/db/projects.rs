@@ -0,0 +1,301 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// State-table key holding the project a session has selected.
+fn selected_key(session_id: &str) -> String {
+    format!("project:{}", session_id)
+}
+
+/// What a project item refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Memory,
+    File,
+    Session,
+}
+
+impl ItemKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ItemKind::Memory => "memory",
+            ItemKind::File => "file",
+            ItemKind::Session => "session",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "memory" => Some(ItemKind::Memory),
+            "file" => Some(ItemKind::File),
+            "session" => Some(ItemKind::Session),
+            _ => None,
+        }
+    }
+}
+
+/// A project (working set).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Project {
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// A memory, file or session in a project.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectItem {
+    pub kind: ItemKind,
+    /// Memory ID, file path or session ID.
+    pub item: String,
+    pub added_at: u64,
+}
+
+impl Db {
+    /// Create `name` if it doesn't exist.
+    pub async fn project_ensure(&self, name: &str) -> Result<(), DbError> {
+        let name = name.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO projects (name, created_at, updated_at) VALUES (?1, ?2, ?2)",
+                rusqlite::params![name, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn project_get(&self, name: &str) -> Result<Option<Project>, DbError> {
+        let name = name.to_string();
+        self.exec_read(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT name, description, created_at, updated_at FROM projects WHERE name = ?1",
+                    rusqlite::params![name],
+                    row_to_project,
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    /// All projects, most recently updated first.
+    pub async fn project_list(&self) -> Result<Vec<Project>, DbError> {
+        self.exec_read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT name, description, created_at, updated_at FROM projects
+                 ORDER BY updated_at DESC, name",
+            )?;
+            let projects = stmt
+                .query_map([], row_to_project)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(projects)
+        })
+        .await
+    }
+
+    /// Set the note shown with the project's context.
+    pub async fn project_describe(&self, name: &str, description: &str) -> Result<(), DbError> {
+        self.project_ensure(name).await?;
+        let name = name.to_string();
+        let description = description.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "UPDATE projects SET description = ?1, updated_at = ?2 WHERE name = ?3",
+                rusqlite::params![description, ts, name],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Add an item, creating the project if needed. Returns false if it was
+    /// already there.
+    pub async fn project_add(
+        &self,
+        name: &str,
+        kind: ItemKind,
+        item: &str,
+    ) -> Result<bool, DbError> {
+        self.project_ensure(name).await?;
+        let name = name.to_string();
+        let item = item.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            let added = conn.execute(
+                "INSERT OR IGNORE INTO project_items (project, kind, item, added_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![name, kind.as_str(), item, ts],
+            )?;
+            conn.execute(
+                "UPDATE projects SET updated_at = ?1 WHERE name = ?2",
+                rusqlite::params![ts, name],
+            )?;
+            Ok(added > 0)
+        })
+        .await
+    }
+
+    /// Remove an item. Returns false if it wasn't there.
+    pub async fn project_remove(
+        &self,
+        name: &str,
+        kind: ItemKind,
+        item: &str,
+    ) -> Result<bool, DbError> {
+        let name = name.to_string();
+        let item = item.to_string();
+        self.exec(move |conn| {
+            let removed = conn.execute(
+                "DELETE FROM project_items WHERE project = ?1 AND kind = ?2 AND item = ?3",
+                rusqlite::params![name, kind.as_str(), item],
+            )?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    /// Items of a project, oldest first.
+    pub async fn project_items(&self, name: &str) -> Result<Vec<ProjectItem>, DbError> {
+        let name = name.to_string();
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT kind, item, added_at FROM project_items WHERE project = ?1
+                 ORDER BY added_at, rowid",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![name], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)? as u64,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows
+                .into_iter()
+                .filter_map(|(kind, item, added_at)| {
+                    Some(ProjectItem {
+                        kind: ItemKind::parse(&kind)?,
+                        item,
+                        added_at,
+                    })
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Project selected in a session with `/project <name>`, if any.
+    pub async fn project_selected(&self, session_id: &str) -> Result<Option<String>, DbError> {
+        let key = selected_key(session_id);
+        self.exec_read(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Select a project for a session, creating it and adding the session to
+    /// it, or deselect with None.
+    pub async fn project_select(
+        &self,
+        session_id: &str,
+        name: Option<&str>,
+    ) -> Result<(), DbError> {
+        if let Some(name) = name {
+            self.project_add(name, ItemKind::Session, session_id)
+                .await?;
+        }
+        let key = selected_key(session_id);
+        let name = name.map(|n| n.to_string());
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            match name {
+                Some(name) => conn.execute(
+                    "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![key, name, ts],
+                )?,
+                None => conn.execute("DELETE FROM state WHERE key = ?1", rusqlite::params![key])?,
+            };
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn row_to_project(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        name: row.get(0)?,
+        description: row.get(1)?,
+        created_at: row.get::<_, i64>(2)? as u64,
+        updated_at: row.get::<_, i64>(3)? as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_project_items_and_selection() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.project_selected("tg-1").await.unwrap(), None);
+
+        db.project_select("tg-1", Some("rewrite")).await.unwrap();
+        assert_eq!(
+            db.project_selected("tg-1").await.unwrap().as_deref(),
+            Some("rewrite")
+        );
+        assert!(db
+            .project_add("rewrite", ItemKind::File, "docs/plan.md")
+            .await
+            .unwrap());
+        assert!(!db
+            .project_add("rewrite", ItemKind::File, "docs/plan.md")
+            .await
+            .unwrap());
+        db.project_add("rewrite", ItemKind::Memory, "7")
+            .await
+            .unwrap();
+        let kinds: Vec<ItemKind> = db
+            .project_items("rewrite")
+            .await
+            .unwrap()
+            .iter()
+            .map(|i| i.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![ItemKind::Session, ItemKind::File, ItemKind::Memory]
+        );
+
+        assert!(db
+            .project_remove("rewrite", ItemKind::Memory, "7")
+            .await
+            .unwrap());
+        assert_eq!(db.project_items("rewrite").await.unwrap().len(), 2);
+
+        db.project_describe("rewrite", "Port the daemon to the new runtime")
+            .await
+            .unwrap();
+        let project = db.project_get("rewrite").await.unwrap().unwrap();
+        assert_eq!(
+            project.description.as_deref(),
+            Some("Port the daemon to the new runtime")
+        );
+        assert_eq!(db.project_list().await.unwrap().len(), 1);
+
+        db.project_select("tg-1", None).await.unwrap();
+        assert_eq!(db.project_selected("tg-1").await.unwrap(), None);
+    }
+}
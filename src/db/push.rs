@@ -0,0 +1,246 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// State-table key holding the last budget alert, as `<day>:<percent>`.
+const BUDGET_ALERT_KEY: &str = "push_budget_alert";
+
+/// A browser subscribed to Web Push notifications.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PushSubscription {
+    pub endpoint: String,
+    pub p256dh: String,
+    pub auth: String,
+    pub topics: Vec<String>,
+    pub created_at: u64,
+}
+
+/// A cron run or background task that failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedJob {
+    /// Cron job name, or `background #<id>`.
+    pub name: String,
+    pub error: String,
+    pub finished_at: u64,
+}
+
+/// An incoming message, for matching mention keywords.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentMessage {
+    pub session_id: String,
+    pub sender: String,
+    pub content: String,
+    pub created_at: u64,
+}
+
+impl Db {
+    /// Add or replace the subscription for `endpoint`.
+    pub async fn push_subscribe(
+        &self,
+        endpoint: &str,
+        p256dh: &str,
+        auth: &str,
+        topics: &[String],
+    ) -> Result<(), DbError> {
+        let endpoint = endpoint.to_string();
+        let p256dh = p256dh.to_string();
+        let auth = auth.to_string();
+        let topics = topics.join(",");
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO push_subscriptions (endpoint, p256dh, auth, topics, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![endpoint, p256dh, auth, topics, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Remove a subscription. Returns false if there was none.
+    pub async fn push_unsubscribe(&self, endpoint: &str) -> Result<bool, DbError> {
+        let endpoint = endpoint.to_string();
+        self.exec(move |conn| {
+            let removed = conn.execute(
+                "DELETE FROM push_subscriptions WHERE endpoint = ?1",
+                rusqlite::params![endpoint],
+            )?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    /// Subscriptions that include `topic`.
+    pub async fn push_subscriptions(&self, topic: &str) -> Result<Vec<PushSubscription>, DbError> {
+        let topic = topic.to_string();
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT endpoint, p256dh, auth, topics, created_at FROM push_subscriptions
+                 ORDER BY created_at",
+            )?;
+            let rows = stmt
+                .query_map([], |row| {
+                    let topics: String = row.get(3)?;
+                    Ok(PushSubscription {
+                        endpoint: row.get(0)?,
+                        p256dh: row.get(1)?,
+                        auth: row.get(2)?,
+                        topics: topics.split(',').map(str::to_string).collect(),
+                        created_at: row.get::<_, i64>(4)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows
+                .into_iter()
+                .filter(|s| s.topics.contains(&topic))
+                .collect())
+        })
+        .await
+    }
+
+    /// Cron runs and background tasks that failed after `since_ms`, oldest first.
+    pub async fn push_failed_jobs(&self, since_ms: u64) -> Result<Vec<FailedJob>, DbError> {
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT j.name, COALESCE(r.result, ''), r.finished_at
+                 FROM cron_runs r JOIN cron_jobs j ON j.id = r.job_id
+                 WHERE r.status = 'error' AND r.finished_at > ?1
+                 UNION ALL
+                 SELECT 'background #' || id, COALESCE(result, ''), finished_at
+                 FROM background_tasks
+                 WHERE status = 'failed' AND finished_at > ?1
+                 ORDER BY 3",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![since_ms as i64], |row| {
+                    Ok(FailedJob {
+                        name: row.get(0)?,
+                        error: row.get(1)?,
+                        finished_at: row.get::<_, i64>(2)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Messages received after `since_ms`, oldest first.
+    pub async fn push_recent_messages(&self, since_ms: u64) -> Result<Vec<RecentMessage>, DbError> {
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT session_id, COALESCE(sender_name, sender_id), content, created_at
+                 FROM queue WHERE created_at > ?1 ORDER BY id",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![since_ms as i64], |row| {
+                    Ok(RecentMessage {
+                        session_id: row.get(0)?,
+                        sender: row.get(1)?,
+                        content: row.get(2)?,
+                        created_at: row.get::<_, i64>(3)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Highest budget alert sent on `day` (percent of the daily limit).
+    pub async fn push_budget_alerted(&self, day: &str) -> Result<Option<u64>, DbError> {
+        let day = day.to_string();
+        self.exec_read(move |conn| {
+            let value: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![BUDGET_ALERT_KEY],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(value.and_then(|v| {
+                let (alerted_day, percent) = v.split_once(':')?;
+                if alerted_day != day {
+                    return None;
+                }
+                percent.parse().ok()
+            }))
+        })
+        .await
+    }
+
+    pub async fn push_set_budget_alerted(&self, day: &str, percent: u64) -> Result<(), DbError> {
+        let value = format!("{}:{}", day, percent);
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![BUDGET_ALERT_KEY, value, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_push_subscriptions_by_topic() {
+        let db = Db::open_memory().unwrap();
+        let topics = |t: &[&str]| t.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        db.push_subscribe(
+            "https://push.example/a",
+            "key-a",
+            "auth-a",
+            &topics(&["jobs"]),
+        )
+        .await
+        .unwrap();
+        db.push_subscribe(
+            "https://push.example/b",
+            "key-b",
+            "auth-b",
+            &topics(&["jobs", "mentions"]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.push_subscriptions("jobs").await.unwrap().len(), 2);
+        let mentions = db.push_subscriptions("mentions").await.unwrap();
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0].p256dh, "key-b");
+        assert!(db.push_subscriptions("budget").await.unwrap().is_empty());
+
+        // Subscribing again replaces the topics
+        db.push_subscribe(
+            "https://push.example/b",
+            "key-b",
+            "auth-b",
+            &topics(&["budget"]),
+        )
+        .await
+        .unwrap();
+        assert!(db.push_subscriptions("mentions").await.unwrap().is_empty());
+
+        assert!(db.push_unsubscribe("https://push.example/a").await.unwrap());
+        assert!(!db.push_unsubscribe("https://push.example/a").await.unwrap());
+        assert!(db.push_subscriptions("jobs").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_push_budget_alerted() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.push_budget_alerted("2026-10-16").await.unwrap(), None);
+        db.push_set_budget_alerted("2026-10-16", 80).await.unwrap();
+        assert_eq!(
+            db.push_budget_alerted("2026-10-16").await.unwrap(),
+            Some(80)
+        );
+        // A new day starts over
+        assert_eq!(db.push_budget_alerted("2026-10-17").await.unwrap(), None);
+    }
+}
@@ -1,5 +1,10 @@
 use super::{now_ms, Db, DbError};
-use rusqlite::Connection;
+use crate::attachments::SavedAttachment;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// Characters of the message kept in failure listings.
+pub(super) const PREVIEW_CHARS: usize = 120;
 
 #[derive(Debug, Clone)]
 pub struct QueueEntry {
@@ -14,6 +19,8 @@ pub struct QueueEntry {
     pub error_msg: Option<String>,
     pub created_at: u64,
     pub processed_at: Option<u64>,
+    /// Files sent with the message, saved to the session's scratch directory.
+    pub attachments: Vec<SavedAttachment>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -22,43 +29,133 @@ pub enum QueueStatus {
     Processing,
     Done,
     Failed,
+    /// Failed, and queued for another attempt.
+    Retry,
+    /// Processed and on the tape; the reply may not have been delivered.
+    Answered,
 }
 
 impl QueueStatus {
-    fn as_str(&self) -> &str {
+    pub(super) fn as_str(&self) -> &str {
         match self {
             Self::Pending => "pending",
             Self::Processing => "processing",
             Self::Done => "done",
             Self::Failed => "failed",
+            Self::Retry => "retry",
+            Self::Answered => "answered",
         }
     }
 
-    fn from_str(s: &str) -> Self {
+    pub(super) fn from_str(s: &str) -> Self {
         match s {
             "pending" => Self::Pending,
             "processing" => Self::Processing,
             "done" => Self::Done,
             "failed" => Self::Failed,
+            "retry" => Self::Retry,
+            "answered" => Self::Answered,
             _ => Self::Pending,
         }
     }
 }
 
+/// Why processing a queue entry failed, recorded instead of a bare error string.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueFailure {
+    /// Coarse error class, e.g. "provider_overloaded", "worker", "storage".
+    pub class: String,
+    pub message: String,
+    /// Provider and model that were running when it failed.
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    /// Last tool (or worker) the agent called.
+    pub tool: Option<String>,
+    /// Agent turns completed before the failure.
+    pub turns: u32,
+    /// Tokens consumed before the failure.
+    pub tokens: u64,
+    /// Whether the same message is likely to succeed if processed again.
+    pub retryable: bool,
+}
+
+impl QueueFailure {
+    /// Failure without context, e.g. rows recorded before failures were structured.
+    pub fn unknown(message: &str) -> Self {
+        Self {
+            class: "unknown".into(),
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A failed queue entry with its failure context.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedEntry {
+    pub id: i64,
+    pub channel: String,
+    pub session_id: String,
+    pub preview: String,
+    pub created_at: u64,
+    pub failed_at: Option<u64>,
+    pub failure: QueueFailure,
+}
+
+/// A message answered before a crash, whose reply may not have been delivered.
+#[derive(Debug, Clone)]
+pub struct AnsweredEntry {
+    pub id: i64,
+    pub channel: String,
+    pub session_id: String,
+    pub reply: String,
+}
+
 impl Db {
     /// Enqueue an incoming message. Returns the queue entry ID.
     pub async fn queue_push(&self, entry: &QueueEntry) -> Result<i64, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_push(entry).await;
+        }
         let entry = entry.clone();
         self.exec(move |conn| queue_push_sync(conn, &entry)).await
     }
 
     /// Atomically claim the next pending entry. Returns None if queue is empty.
     pub async fn queue_claim_next(&self) -> Result<Option<QueueEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_claim_next().await;
+        }
         self.exec(queue_claim_sync).await
     }
 
+    /// Files saved for entry `id`.
+    pub async fn queue_attachments(&self, id: i64) -> Result<Vec<SavedAttachment>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_attachments(id).await;
+        }
+        self.exec_read(move |conn| {
+            let json: Option<String> = conn
+                .query_row(
+                    "SELECT attachments FROM queue WHERE id = ?1",
+                    rusqlite::params![id],
+                    |r| r.get(0),
+                )
+                .optional()?
+                .flatten();
+            Ok(match json {
+                Some(json) => serde_json::from_str(&json)?,
+                None => Vec::new(),
+            })
+        })
+        .await
+    }
+
     /// Mark an entry as done.
     pub async fn queue_mark_done(&self, id: i64) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_mark_done(id).await;
+        }
         let ts = now_ms();
         self.exec(move |conn| {
             conn.execute(
@@ -70,23 +167,184 @@ impl Db {
         .await
     }
 
-    /// Mark an entry as failed with an error message.
-    pub async fn queue_mark_failed(&self, id: i64, error: &str) -> Result<(), DbError> {
-        let error = error.to_string();
+    /// Mark an entry as failed. `error_msg` keeps the plain message.
+    pub async fn queue_mark_failed(&self, id: i64, failure: &QueueFailure) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_mark_failed(id, failure).await;
+        }
+        let json = serde_json::to_string(failure)?;
+        let error = failure.message.clone();
         let ts = now_ms();
         self.exec(move |conn| {
             conn.execute(
-                "UPDATE queue SET status = 'failed', error_msg = ?1, processed_at = ?2 WHERE id = ?3",
-                rusqlite::params![error, ts as i64, id],
+                "UPDATE queue SET status = 'failed', error_msg = ?1, failure = ?2, processed_at = ?3
+                 WHERE id = ?4",
+                rusqlite::params![error, json, ts as i64, id],
             )?;
             Ok(())
         })
         .await
     }
 
+    /// Failure context of a failed entry.
+    pub async fn queue_failure(&self, id: i64) -> Result<Option<QueueFailure>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_failure(id).await;
+        }
+        self.exec_read(move |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT error_msg, failure FROM queue WHERE id = ?1 AND status = 'failed'",
+                    rusqlite::params![id],
+                    |r| {
+                        Ok((
+                            r.get::<_, Option<String>>(0)?,
+                            r.get::<_, Option<String>>(1)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            Ok(row.map(|(error, failure)| parse_failure(error, failure)))
+        })
+        .await
+    }
+
+    /// Most recent failed entries, newest first.
+    pub async fn queue_failures(&self, limit: usize) -> Result<Vec<FailedEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_failures(limit).await;
+        }
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, channel, session_id, content, created_at, processed_at, error_msg, failure
+                 FROM queue WHERE status = 'failed'
+                 ORDER BY COALESCE(processed_at, created_at) DESC, id DESC LIMIT ?1",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![limit as i64], |r| {
+                    let content: String = r.get(3)?;
+                    Ok(FailedEntry {
+                        id: r.get(0)?,
+                        channel: r.get(1)?,
+                        session_id: r.get(2)?,
+                        preview: content.chars().take(PREVIEW_CHARS).collect(),
+                        created_at: r.get::<_, i64>(4)? as u64,
+                        failed_at: r.get::<_, Option<i64>>(5)?.map(|v| v as u64),
+                        failure: parse_failure(r.get(6)?, r.get(7)?),
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Count entries that failed since `since_ms`.
+    pub async fn queue_failed_count(&self, since_ms: u64) -> Result<u64, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_failed_count(since_ms).await;
+        }
+        self.exec_read(move |conn| {
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM queue WHERE status = 'failed' AND processed_at >= ?1",
+                rusqlite::params![since_ms as i64],
+                |r| r.get(0),
+            )?;
+            Ok(count as u64)
+        })
+        .await
+    }
+
+    /// Queue the entries that failed since `since_ms` for another attempt,
+    /// oldest first, `pace_ms` apart starting now. Returns how many.
+    pub async fn queue_retry_failed(&self, since_ms: u64, pace_ms: u64) -> Result<usize, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_retry_failed(since_ms, pace_ms).await;
+        }
+        let now = now_ms();
+        self.exec(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let ids = {
+                let mut stmt = tx.prepare(
+                    "SELECT id FROM queue WHERE status = 'failed' AND processed_at >= ?1
+                     ORDER BY created_at, id",
+                )?;
+                let ids = stmt
+                    .query_map(rusqlite::params![since_ms as i64], |r| r.get::<_, i64>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                ids
+            };
+            for (i, id) in ids.iter().enumerate() {
+                tx.execute(
+                    "UPDATE queue SET status = 'retry', retry_at = ?1 WHERE id = ?2",
+                    rusqlite::params![(now + i as u64 * pace_ms) as i64, id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(ids.len())
+        })
+        .await
+    }
+
+    /// Take the entries whose retry is due, in order, and mark them
+    /// pending again.
+    pub async fn queue_take_retries(&self) -> Result<Vec<QueueEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_take_retries().await;
+        }
+        let now = now_ms();
+        self.exec(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let entries = {
+                let mut stmt = tx.prepare(
+                    "SELECT id, channel, sender_id, sender_name, session_id, content, reply_to, status, error_msg, created_at, processed_at, attachments
+                     FROM queue WHERE status = 'retry' AND retry_at <= ?1 ORDER BY retry_at, id",
+                )?;
+                let entries = stmt
+                    .query_map(rusqlite::params![now as i64], row_to_entry)?
+                    .collect::<Result<Vec<_>, _>>()?;
+                entries
+            };
+            for entry in &entries {
+                tx.execute(
+                    "UPDATE queue SET status = 'pending', retry_at = NULL WHERE id = ?1",
+                    rusqlite::params![entry.id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(entries
+                .into_iter()
+                .map(|entry| QueueEntry {
+                    status: QueueStatus::Pending,
+                    ..entry
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Count entries waiting for a retry.
+    pub async fn queue_retry_count(&self) -> Result<usize, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_retry_count().await;
+        }
+        self.exec_read(|conn| {
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM queue WHERE status = 'retry'",
+                [],
+                |r| r.get(0),
+            )?;
+            Ok(count as usize)
+        })
+        .await
+    }
+
     /// Crash recovery: reset any 'processing' entries back to 'pending'.
     /// Returns the number of requeued entries.
     pub async fn queue_requeue_stale(&self) -> Result<usize, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_requeue_stale().await;
+        }
         self.exec(|conn| {
             let count = conn.execute(
                 "UPDATE queue SET status = 'pending' WHERE status = 'processing'",
@@ -97,9 +355,52 @@ impl Db {
         .await
     }
 
+    /// Crash recovery: entries answered but not marked done, oldest first.
+    /// Deliver each reply again, then `queue_mark_done`.
+    pub async fn queue_answered(&self) -> Result<Vec<AnsweredEntry>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_answered().await;
+        }
+        self.exec_read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, channel, session_id, reply FROM queue
+                 WHERE status = 'answered' ORDER BY created_at, id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(AnsweredEntry {
+                    id: row.get(0)?,
+                    channel: row.get(1)?,
+                    session_id: row.get(2)?,
+                    reply: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                })
+            })?;
+            Ok(rows.collect::<Result<Vec<_>, _>>()?)
+        })
+        .await
+    }
+
+    /// Delete processed and failed entries received before `before_ms`.
+    /// Returns how many were deleted.
+    pub async fn queue_prune(&self, before_ms: u64) -> Result<usize, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_prune(before_ms).await;
+        }
+        self.exec(move |conn| {
+            let deleted = conn.execute(
+                "DELETE FROM queue WHERE status IN ('done', 'failed') AND created_at < ?1",
+                rusqlite::params![before_ms as i64],
+            )?;
+            Ok(deleted)
+        })
+        .await
+    }
+
     /// Count pending entries.
     pub async fn queue_pending_count(&self) -> Result<usize, DbError> {
-        self.exec(|conn| {
+        if let Some(storage) = &self.storage {
+            return storage.queue_pending_count().await;
+        }
+        self.exec_read(|conn| {
             let count: i64 = conn.query_row(
                 "SELECT COUNT(*) FROM queue WHERE status = 'pending'",
                 [],
@@ -109,12 +410,64 @@ impl Db {
         })
         .await
     }
+
+    /// True when no message is waiting or being processed.
+    pub async fn queue_is_idle(&self) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_is_idle().await;
+        }
+        self.exec_read(|conn| {
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM queue WHERE status IN ('pending', 'processing', 'answered')",
+                [],
+                |r| r.get(0),
+            )?;
+            Ok(count == 0)
+        })
+        .await
+    }
+
+    /// Inbound messages per channel received in `[since_ms, until_ms)`.
+    pub async fn queue_counts_by_channel(
+        &self,
+        since_ms: u64,
+        until_ms: u64,
+    ) -> Result<Vec<(String, u64)>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.queue_counts_by_channel(since_ms, until_ms).await;
+        }
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT channel, COUNT(*) FROM queue
+                 WHERE created_at >= ?1 AND created_at < ?2
+                 GROUP BY channel ORDER BY channel",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![since_ms as i64, until_ms as i64], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+}
+
+pub(super) fn parse_failure(error: Option<String>, failure: Option<String>) -> QueueFailure {
+    failure
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| QueueFailure::unknown(error.as_deref().unwrap_or("")))
 }
 
 fn queue_push_sync(conn: &Connection, entry: &QueueEntry) -> Result<i64, DbError> {
+    let attachments = if entry.attachments.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&entry.attachments)?)
+    };
     conn.execute(
-        "INSERT INTO queue (channel, sender_id, sender_name, session_id, content, reply_to, status, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO queue (channel, sender_id, sender_name, session_id, content, reply_to, status, created_at, attachments)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         rusqlite::params![
             entry.channel,
             entry.sender_id,
@@ -124,6 +477,7 @@ fn queue_push_sync(conn: &Connection, entry: &QueueEntry) -> Result<i64, DbError
             entry.reply_to,
             entry.status.as_str(),
             entry.created_at as i64,
+            attachments,
         ],
     )?;
     Ok(conn.last_insert_rowid())
@@ -132,24 +486,10 @@ fn queue_push_sync(conn: &Connection, entry: &QueueEntry) -> Result<i64, DbError
 fn queue_claim_sync(conn: &Connection) -> Result<Option<QueueEntry>, DbError> {
     let tx = conn.unchecked_transaction()?;
     let result = tx.query_row(
-        "SELECT id, channel, sender_id, sender_name, session_id, content, reply_to, status, error_msg, created_at, processed_at
+        "SELECT id, channel, sender_id, sender_name, session_id, content, reply_to, status, error_msg, created_at, processed_at, attachments
          FROM queue WHERE status = 'pending' ORDER BY created_at ASC LIMIT 1",
         [],
-        |row| {
-            Ok(QueueEntry {
-                id: Some(row.get(0)?),
-                channel: row.get(1)?,
-                sender_id: row.get(2)?,
-                sender_name: row.get(3)?,
-                session_id: row.get(4)?,
-                content: row.get(5)?,
-                reply_to: row.get(6)?,
-                status: QueueStatus::from_str(&row.get::<_, String>(7)?),
-                error_msg: row.get(8)?,
-                created_at: row.get::<_, i64>(9)? as u64,
-                processed_at: row.get::<_, Option<i64>>(10)?.map(|v| v as u64),
-            })
-        },
+        row_to_entry,
     );
     match result {
         Ok(mut entry) => {
@@ -169,6 +509,36 @@ fn queue_claim_sync(conn: &Connection) -> Result<Option<QueueEntry>, DbError> {
     }
 }
 
+/// Mark entry `id` answered with `reply`, inside the transaction that saves
+/// its tape (`Db::tape_save_turn`).
+pub(super) fn queue_answered_sync(conn: &Connection, id: i64, reply: &str) -> Result<(), DbError> {
+    conn.execute(
+        "UPDATE queue SET status = 'answered', reply = ?1 WHERE id = ?2",
+        rusqlite::params![reply, id],
+    )?;
+    Ok(())
+}
+
+fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<QueueEntry> {
+    Ok(QueueEntry {
+        id: Some(row.get(0)?),
+        channel: row.get(1)?,
+        sender_id: row.get(2)?,
+        sender_name: row.get(3)?,
+        session_id: row.get(4)?,
+        content: row.get(5)?,
+        reply_to: row.get(6)?,
+        status: QueueStatus::from_str(&row.get::<_, String>(7)?),
+        error_msg: row.get(8)?,
+        created_at: row.get::<_, i64>(9)? as u64,
+        processed_at: row.get::<_, Option<i64>>(10)?.map(|v| v as u64),
+        attachments: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+    })
+}
+
 impl QueueEntry {
     /// Create a new pending queue entry.
     pub fn new(channel: &str, sender_id: &str, session_id: &str, content: &str) -> Self {
@@ -184,6 +554,7 @@ impl QueueEntry {
             error_msg: None,
             created_at: now_ms(),
             processed_at: None,
+            attachments: Vec::new(),
         }
     }
 }
@@ -209,6 +580,32 @@ mod tests {
         assert!(next.is_none());
     }
 
+    #[tokio::test]
+    async fn test_attachments() {
+        let db = Db::open_memory().unwrap();
+        let file = SavedAttachment {
+            name: "report.pdf".into(),
+            mime_type: "application/pdf".into(),
+            path: "/tmp/attachments/tg-123/report.pdf".into(),
+            size: 1024,
+            voice: false,
+        };
+        let entry = QueueEntry {
+            attachments: vec![file.clone()],
+            ..QueueEntry::new("telegram", "user1", "tg-123", "")
+        };
+        let id = db.queue_push(&entry).await.unwrap();
+        assert_eq!(db.queue_attachments(id).await.unwrap(), vec![file.clone()]);
+        let claimed = db.queue_claim_next().await.unwrap().unwrap();
+        assert_eq!(claimed.attachments, vec![file]);
+
+        let plain = db
+            .queue_push(&QueueEntry::new("telegram", "user1", "tg-123", "hi"))
+            .await
+            .unwrap();
+        assert!(db.queue_attachments(plain).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_mark_done() {
         let db = Db::open_memory().unwrap();
@@ -221,13 +618,114 @@ mod tests {
         assert_eq!(pending, 0);
     }
 
+    #[tokio::test]
+    async fn test_is_idle() {
+        let db = Db::open_memory().unwrap();
+        assert!(db.queue_is_idle().await.unwrap());
+        let id = db
+            .queue_push(&QueueEntry::new("tg", "u1", "s1", "msg"))
+            .await
+            .unwrap();
+        assert!(!db.queue_is_idle().await.unwrap());
+        db.queue_claim_next().await.unwrap();
+        assert!(!db.queue_is_idle().await.unwrap());
+        db.queue_mark_done(id).await.unwrap();
+        assert!(db.queue_is_idle().await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_mark_failed() {
         let db = Db::open_memory().unwrap();
         let entry = QueueEntry::new("tg", "u1", "s1", "msg");
         let id = db.queue_push(&entry).await.unwrap();
         db.queue_claim_next().await.unwrap();
-        db.queue_mark_failed(id, "something broke").await.unwrap();
+        let failure = QueueFailure {
+            class: "provider_overloaded".into(),
+            message: "Provider overloaded after 3 attempt(s): HTTP 529".into(),
+            provider: Some("anthropic".into()),
+            model: Some("claude-sonnet-4-20250514".into()),
+            tool: Some("web_fetch".into()),
+            turns: 2,
+            tokens: 1830,
+            retryable: true,
+        };
+        db.queue_mark_failed(id, &failure).await.unwrap();
+        assert_eq!(db.queue_failure(id).await.unwrap(), Some(failure.clone()));
+
+        let failed = db.queue_failures(10).await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].preview, "msg");
+        assert_eq!(failed[0].failure, failure);
+        assert_eq!(db.queue_failed_count(0).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failure_without_context() {
+        let db = Db::open_memory().unwrap();
+        let id = db
+            .queue_push(&QueueEntry::new("tg", "u1", "s1", "msg"))
+            .await
+            .unwrap();
+        db.exec(move |conn| {
+            conn.execute(
+                "UPDATE queue SET status = 'failed', error_msg = 'old error' WHERE id = ?1",
+                rusqlite::params![id],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            db.queue_failure(id).await.unwrap(),
+            Some(QueueFailure::unknown("old error"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed() {
+        let db = Db::open_memory().unwrap();
+        let mut ids = Vec::new();
+        for content in ["first", "second", "third"] {
+            let id = db
+                .queue_push(&QueueEntry::new("tg", "u1", "tg-1", content))
+                .await
+                .unwrap();
+            db.queue_mark_failed(id, &QueueFailure::unknown("HTTP 529"))
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        // Failed before the window
+        db.exec({
+            let id = ids[0];
+            move |conn| {
+                conn.execute(
+                    "UPDATE queue SET processed_at = 1 WHERE id = ?1",
+                    rusqlite::params![id],
+                )?;
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+
+        let since = now_ms() - 60_000;
+        assert_eq!(db.queue_retry_failed(since, 60_000).await.unwrap(), 2);
+        assert_eq!(db.queue_retry_count().await.unwrap(), 2);
+        assert_eq!(db.queue_failed_count(0).await.unwrap(), 1);
+
+        // Only the first is due; the next comes a minute later
+        let due = db.queue_take_retries().await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, Some(ids[1]));
+        assert_eq!(due[0].content, "second");
+        assert_eq!(due[0].status, QueueStatus::Pending);
+        assert!(db.queue_take_retries().await.unwrap().is_empty());
+        assert_eq!(db.queue_retry_count().await.unwrap(), 1);
+        assert_eq!(db.queue_pending_count().await.unwrap(), 1);
+
+        // Nothing left to retry
+        assert_eq!(db.queue_retry_failed(since, 0).await.unwrap(), 0);
     }
 
     #[tokio::test]
@@ -0,0 +1,148 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// A reply the agent sent, by its platform message ID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub channel: String,
+    pub message_id: String,
+    pub session_id: String,
+    pub content: String,
+    pub created_at: u64,
+}
+
+impl Db {
+    /// Record that `message_id` in `session_id` carries `content`. Recording
+    /// the same message again replaces its content. Message IDs are only
+    /// unique within a chat on some platforms, so replies are looked up by
+    /// session.
+    pub async fn reply_record(
+        &self,
+        channel: &str,
+        message_id: &str,
+        session_id: &str,
+        content: &str,
+    ) -> Result<(), DbError> {
+        let channel = channel.to_string();
+        let message_id = message_id.to_string();
+        let session_id = session_id.to_string();
+        let content = content.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO replies (channel, message_id, session_id, content, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(session_id, message_id) DO UPDATE SET content = excluded.content",
+                rusqlite::params![channel, message_id, session_id, content, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn reply_get(
+        &self,
+        session_id: &str,
+        message_id: &str,
+    ) -> Result<Option<Reply>, DbError> {
+        let session_id = session_id.to_string();
+        let message_id = message_id.to_string();
+        self.exec_read(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT channel, message_id, session_id, content, created_at
+                     FROM replies WHERE session_id = ?1 AND message_id = ?2",
+                    rusqlite::params![session_id, message_id],
+                    row_to_reply,
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    /// The most recent reply in `session_id`.
+    pub async fn reply_latest(&self, session_id: &str) -> Result<Option<Reply>, DbError> {
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT channel, message_id, session_id, content, created_at
+                     FROM replies WHERE session_id = ?1
+                     ORDER BY created_at DESC, rowid DESC LIMIT 1",
+                    rusqlite::params![session_id],
+                    row_to_reply,
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    pub async fn reply_delete(&self, session_id: &str, message_id: &str) -> Result<bool, DbError> {
+        let session_id = session_id.to_string();
+        let message_id = message_id.to_string();
+        self.exec(move |conn| {
+            let n = conn.execute(
+                "DELETE FROM replies WHERE session_id = ?1 AND message_id = ?2",
+                rusqlite::params![session_id, message_id],
+            )?;
+            Ok(n > 0)
+        })
+        .await
+    }
+}
+
+fn row_to_reply(row: &rusqlite::Row) -> rusqlite::Result<Reply> {
+    Ok(Reply {
+        channel: row.get(0)?,
+        message_id: row.get(1)?,
+        session_id: row.get(2)?,
+        content: row.get(3)?,
+        created_at: row.get::<_, i64>(4)? as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replies() {
+        let db = Db::open_memory().unwrap();
+        db.reply_record("telegram", "10", "tg-1", "first")
+            .await
+            .unwrap();
+        db.reply_record("telegram", "11", "tg-1", "second")
+            .await
+            .unwrap();
+        // Same message ID in another chat
+        db.reply_record("telegram", "10", "tg-2", "other")
+            .await
+            .unwrap();
+
+        let reply = db.reply_get("tg-1", "10").await.unwrap().unwrap();
+        assert_eq!(reply.content, "first");
+        assert_eq!(reply.session_id, "tg-1");
+        assert_eq!(
+            db.reply_latest("tg-1").await.unwrap().unwrap().message_id,
+            "11"
+        );
+
+        // A regenerated reply keeps its message
+        db.reply_record("telegram", "11", "tg-1", "second, again")
+            .await
+            .unwrap();
+        let reply = db.reply_get("tg-1", "11").await.unwrap().unwrap();
+        assert_eq!(reply.content, "second, again");
+
+        assert!(db.reply_delete("tg-1", "11").await.unwrap());
+        assert!(!db.reply_delete("tg-1", "11").await.unwrap());
+        assert_eq!(
+            db.reply_get("tg-2", "10").await.unwrap().unwrap().content,
+            "other"
+        );
+        assert_eq!(
+            db.reply_latest("tg-1").await.unwrap().unwrap().message_id,
+            "10"
+        );
+    }
+}
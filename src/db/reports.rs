@@ -0,0 +1,38 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key holding the end of the last period a report covered.
+fn report_key(name: &str) -> String {
+    format!("report:{}", name)
+}
+
+impl Db {
+    /// End (ms) of the last period report `name` covered, if it ever ran.
+    pub async fn report_last_period(&self, name: &str) -> Result<Option<u64>, DbError> {
+        let key = report_key(name);
+        self.exec_read(move |conn| {
+            let value: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(value.and_then(|v| v.parse().ok()))
+        })
+        .await
+    }
+
+    pub async fn report_set_last_period(&self, name: &str, end_ms: u64) -> Result<(), DbError> {
+        let key = report_key(name);
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, end_ms.to_string(), ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+}
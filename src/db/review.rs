@@ -0,0 +1,79 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key holding the memory IDs of a session's current review batch.
+fn review_key(session_id: &str) -> String {
+    format!("review:{}", session_id)
+}
+
+/// State-table key holding when the scheduler last started a review.
+const REVIEW_LAST_STARTED_KEY: &str = "review_last_started";
+
+impl Db {
+    async fn review_state_get(&self, key: String) -> Result<Option<String>, DbError> {
+        self.exec_read(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    async fn review_state_put(&self, key: String, value: String) -> Result<(), DbError> {
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, value, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Memory IDs of the batch being reviewed in `session_id`, or None if no
+    /// review is running there.
+    pub async fn review_batch(&self, session_id: &str) -> Result<Option<Vec<i64>>, DbError> {
+        let value = self.review_state_get(review_key(session_id)).await?;
+        Ok(value.map(|v| {
+            v.split_whitespace()
+                .filter_map(|id| id.parse().ok())
+                .collect()
+        }))
+    }
+
+    pub async fn review_set_batch(&self, session_id: &str, ids: &[i64]) -> Result<(), DbError> {
+        let value = ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.review_state_put(review_key(session_id), value).await
+    }
+
+    pub async fn review_clear(&self, session_id: &str) -> Result<(), DbError> {
+        let key = review_key(session_id);
+        self.exec(move |conn| {
+            conn.execute("DELETE FROM state WHERE key = ?1", rusqlite::params![key])?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// When (ms) the scheduler last started a memory review.
+    pub async fn review_last_started(&self) -> Result<Option<u64>, DbError> {
+        let value = self
+            .review_state_get(REVIEW_LAST_STARTED_KEY.to_string())
+            .await?;
+        Ok(value.and_then(|v| v.parse().ok()))
+    }
+
+    pub async fn review_set_last_started(&self, ts: u64) -> Result<(), DbError> {
+        self.review_state_put(REVIEW_LAST_STARTED_KEY.to_string(), ts.to_string())
+            .await
+    }
+}
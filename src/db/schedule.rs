@@ -0,0 +1,108 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key holding when cortex maintenance last completed.
+const CORTEX_LAST_RUN_KEY: &str = "cortex_last_run";
+
+/// Prefix of the state-table keys holding when each cron job last fired,
+/// followed by the job name.
+pub(crate) const CRON_FIRED_PREFIX: &str = "cron_fired:";
+
+impl Db {
+    async fn schedule_state_get(&self, key: String) -> Result<Option<u64>, DbError> {
+        self.exec_read(move |conn| {
+            let value: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(value.and_then(|v| v.parse().ok()))
+        })
+        .await
+    }
+
+    async fn schedule_state_put(&self, key: String, value: u64) -> Result<(), DbError> {
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                rusqlite::params![key, value.to_string(), ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// When (ms) cortex maintenance last completed, if ever.
+    pub async fn cortex_last_run(&self) -> Result<Option<u64>, DbError> {
+        self.schedule_state_get(CORTEX_LAST_RUN_KEY.to_string())
+            .await
+    }
+
+    pub async fn cortex_set_last_run(&self, ts: u64) -> Result<(), DbError> {
+        self.schedule_state_put(CORTEX_LAST_RUN_KEY.to_string(), ts)
+            .await
+    }
+
+    /// When (ms) cron job `name` last fired, if ever.
+    pub async fn cron_last_fired(&self, name: &str) -> Result<Option<u64>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_last_fired(name).await;
+        }
+        self.schedule_state_get(format!("{}{}", CRON_FIRED_PREFIX, name))
+            .await
+    }
+
+    /// Record that cron job `name` fires at `ts`, unless it fired after
+    /// `since` (on another host). Returns whether it was recorded.
+    pub async fn cron_claim(&self, name: &str, since: u64, ts: u64) -> Result<bool, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_claim(name, since, ts).await;
+        }
+        let key = format!("{}{}", CRON_FIRED_PREFIX, name);
+        self.exec(move |conn| {
+            let claimed = conn.execute(
+                "INSERT INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+                 WHERE CAST(state.value AS INTEGER) <= ?4",
+                rusqlite::params![key, ts.to_string(), now_ms() as i64, since as i64],
+            )?;
+            Ok(claimed > 0)
+        })
+        .await
+    }
+
+    pub async fn cron_set_last_fired(&self, name: &str, ts: u64) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.cron_set_last_fired(name, ts).await;
+        }
+        self.schedule_state_put(format!("{}{}", CRON_FIRED_PREFIX, name), ts)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_schedule_state() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.cortex_last_run().await.unwrap(), None);
+        db.cortex_set_last_run(1000).await.unwrap();
+        db.cortex_set_last_run(2000).await.unwrap();
+        assert_eq!(db.cortex_last_run().await.unwrap(), Some(2000));
+
+        db.cron_set_last_fired("daily", 3000).await.unwrap();
+        assert_eq!(db.cron_last_fired("daily").await.unwrap(), Some(3000));
+        assert_eq!(db.cron_last_fired("weekly").await.unwrap(), None);
+
+        // Another host already fired it after 2500
+        assert!(!db.cron_claim("daily", 2500, 4000).await.unwrap());
+        assert!(db.cron_claim("daily", 3000, 4000).await.unwrap());
+        assert!(db.cron_claim("weekly", 0, 4000).await.unwrap());
+        assert_eq!(db.cron_last_fired("daily").await.unwrap(), Some(4000));
+    }
+}
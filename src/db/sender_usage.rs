@@ -0,0 +1,105 @@
+use super::{Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// The UTC day usage is counted under, e.g. "2026-10-16".
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+impl Db {
+    /// Attribute `tokens` spent in group `session_id` to `sender_id`.
+    pub async fn sender_usage_add(
+        &self,
+        session_id: &str,
+        sender_id: &str,
+        tokens: u64,
+    ) -> Result<(), DbError> {
+        let session_id = session_id.to_string();
+        let sender_id = sender_id.to_string();
+        let day = today();
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO sender_usage (day, session_id, sender_id, tokens) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(day, session_id, sender_id) DO UPDATE SET tokens = tokens + ?4",
+                rusqlite::params![day, session_id, sender_id, tokens as i64],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Tokens attributed to `sender_id` in group `session_id` today.
+    pub async fn sender_usage_today(
+        &self,
+        session_id: &str,
+        sender_id: &str,
+    ) -> Result<u64, DbError> {
+        let session_id = session_id.to_string();
+        let sender_id = sender_id.to_string();
+        let day = today();
+        self.exec_read(move |conn| {
+            let tokens: Option<i64> = conn
+                .query_row(
+                    "SELECT tokens FROM sender_usage
+                     WHERE day = ?1 AND session_id = ?2 AND sender_id = ?3",
+                    rusqlite::params![day, session_id, sender_id],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(tokens.unwrap_or(0) as u64)
+        })
+        .await
+    }
+
+    /// Note that `sender_id` was told they hit their cap today. Returns
+    /// false if they already were, so the notice goes out once a day.
+    pub async fn sender_usage_notify(
+        &self,
+        session_id: &str,
+        sender_id: &str,
+    ) -> Result<bool, DbError> {
+        let session_id = session_id.to_string();
+        let sender_id = sender_id.to_string();
+        let day = today();
+        self.exec(move |conn| {
+            let n = conn.execute(
+                "INSERT INTO sender_usage (day, session_id, sender_id, notified) VALUES (?1, ?2, ?3, 1)
+                 ON CONFLICT(day, session_id, sender_id) DO UPDATE SET notified = 1 WHERE notified = 0",
+                rusqlite::params![day, session_id, sender_id],
+            )?;
+            Ok(n > 0)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sender_usage() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.sender_usage_today("tg-group-1", "42").await.unwrap(), 0);
+        db.sender_usage_add("tg-group-1", "42", 1200).await.unwrap();
+        db.sender_usage_add("tg-group-1", "42", 300).await.unwrap();
+        db.sender_usage_add("tg-group-1", "7", 50).await.unwrap();
+        db.sender_usage_add("tg-group-2", "42", 10).await.unwrap();
+        assert_eq!(
+            db.sender_usage_today("tg-group-1", "42").await.unwrap(),
+            1500
+        );
+        assert_eq!(db.sender_usage_today("tg-group-1", "7").await.unwrap(), 50);
+        assert_eq!(db.sender_usage_today("tg-group-2", "42").await.unwrap(), 10);
+
+        // Told once a day, without touching the count
+        assert!(db.sender_usage_notify("tg-group-1", "42").await.unwrap());
+        assert!(!db.sender_usage_notify("tg-group-1", "42").await.unwrap());
+        assert!(db.sender_usage_notify("tg-group-1", "9").await.unwrap());
+        assert_eq!(
+            db.sender_usage_today("tg-group-1", "42").await.unwrap(),
+            1500
+        );
+        assert_eq!(db.sender_usage_today("tg-group-1", "9").await.unwrap(), 0);
+    }
+}
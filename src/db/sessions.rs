@@ -0,0 +1,135 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+/// Where a session lives on its platform, as recorded by the adapter that
+/// received its messages.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub channel: String,
+    pub chat_id: Option<String>,
+    pub thread_id: Option<String>,
+    pub is_group: bool,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+impl Db {
+    /// Record a message in `session_id`. Channel and chat details are
+    /// refreshed; `first_seen` is kept. Missing chat or thread IDs don't
+    /// overwrite ones recorded earlier.
+    pub async fn session_touch(
+        &self,
+        channel: &str,
+        session_id: &str,
+        chat_id: Option<&str>,
+        thread_id: Option<&str>,
+        is_group: bool,
+    ) -> Result<(), DbError> {
+        let channel = channel.to_string();
+        let session_id = session_id.to_string();
+        let chat_id = chat_id.map(str::to_string);
+        let thread_id = thread_id.map(str::to_string);
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO sessions (session_id, channel, chat_id, thread_id, is_group, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT(session_id) DO UPDATE SET
+                     channel = excluded.channel,
+                     chat_id = COALESCE(excluded.chat_id, sessions.chat_id),
+                     thread_id = COALESCE(excluded.thread_id, sessions.thread_id),
+                     is_group = excluded.is_group,
+                     last_seen = excluded.last_seen",
+                rusqlite::params![session_id, channel, chat_id, thread_id, is_group, ts],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// The recorded session, if any message was ever received in it.
+    pub async fn session_get(&self, session_id: &str) -> Result<Option<SessionInfo>, DbError> {
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT session_id, channel, chat_id, thread_id, is_group, first_seen, last_seen
+                     FROM sessions WHERE session_id = ?1",
+                    rusqlite::params![session_id],
+                    |row| {
+                        Ok(SessionInfo {
+                            session_id: row.get(0)?,
+                            channel: row.get(1)?,
+                            chat_id: row.get(2)?,
+                            thread_id: row.get(3)?,
+                            is_group: row.get(4)?,
+                            first_seen: row.get::<_, i64>(5)? as u64,
+                            last_seen: row.get::<_, i64>(6)? as u64,
+                        })
+                    },
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Distinct senders whose messages were queued in `session_id`.
+    pub async fn session_participants(&self, session_id: &str) -> Result<usize, DbError> {
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(DISTINCT sender_id) FROM queue WHERE session_id = ?1",
+                rusqlite::params![session_id],
+                |row| row.get(0),
+            )?;
+            Ok(count as usize)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_touch() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.session_get("ops-room").await.unwrap(), None);
+
+        db.session_touch("slack", "ops-room", Some("C123"), Some("1700.01"), true)
+            .await
+            .unwrap();
+        let first = db.session_get("ops-room").await.unwrap().unwrap();
+        assert_eq!(first.channel, "slack");
+        assert_eq!(first.chat_id.as_deref(), Some("C123"));
+        assert!(first.is_group);
+
+        // A later message without a thread keeps the recorded one
+        db.session_touch("slack", "ops-room", Some("C123"), None, true)
+            .await
+            .unwrap();
+        let again = db.session_get("ops-room").await.unwrap().unwrap();
+        assert_eq!(again.thread_id.as_deref(), Some("1700.01"));
+        assert_eq!(again.first_seen, first.first_seen);
+        assert!(again.last_seen >= first.last_seen);
+    }
+
+    #[tokio::test]
+    async fn test_session_participants() {
+        use crate::db::queue::QueueEntry;
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.session_participants("ops-room").await.unwrap(), 0);
+        for sender in ["alice", "bob", "alice"] {
+            db.queue_push(&QueueEntry::new("slack", sender, "ops-room", "hi"))
+                .await
+                .unwrap();
+        }
+        db.queue_push(&QueueEntry::new("slack", "carol", "elsewhere", "hi"))
+            .await
+            .unwrap();
+        assert_eq!(db.session_participants("ops-room").await.unwrap(), 2);
+    }
+}
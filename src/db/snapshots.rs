@@ -0,0 +1,272 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use yoagent::types::AgentMessage;
+
+/// Everything the model received on one turn, and what it answered.
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnSnapshot {
+    pub session_id: String,
+    /// 1-based turn number within the session, assigned on save.
+    pub turn: u32,
+    pub model: String,
+    pub system_prompt: String,
+    /// `[{name, description, parameters}]` of every tool offered.
+    pub tools: serde_json::Value,
+    /// Messages sent to the model, after compaction.
+    pub context: Vec<AgentMessage>,
+    /// Input filters, context limits, compaction and privacy in effect.
+    pub filters: serde_json::Value,
+    pub response: Option<AgentMessage>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub created_at: u64,
+}
+
+impl TurnSnapshot {
+    /// Short version id of the system prompt.
+    pub fn prompt_version(&self) -> String {
+        version(&blob_hash(&self.system_prompt))
+    }
+
+    /// Short version id of the tool schemas.
+    pub fn tools_version(&self) -> String {
+        version(&blob_hash(&self.tools.to_string()))
+    }
+}
+
+/// One line per turn for listings.
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnSummary {
+    pub turn: u32,
+    pub model: String,
+    /// Messages in the context.
+    pub messages: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub created_at: u64,
+}
+
+impl Db {
+    /// Store a turn snapshot from sync code (agent callbacks). Returns the
+    /// turn number. Call inside `block_in_place` when on a tokio worker thread.
+    pub fn snapshot_save_sync(&self, snapshot: &TurnSnapshot) -> Result<u32, DbError> {
+        self.exec_sync(|conn| save_snapshot(conn, snapshot))
+    }
+
+    /// Snapshot of turn `turn` (1-based) of a session.
+    pub async fn snapshot_get(
+        &self,
+        session_id: &str,
+        turn: u32,
+    ) -> Result<Option<TurnSnapshot>, DbError> {
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT s.model, p.content, t.content, s.context, s.filters, s.response,
+                            s.input_tokens, s.output_tokens, s.created_at
+                     FROM turn_snapshots s
+                     JOIN snapshot_blobs p ON p.hash = s.prompt_hash
+                     JOIN snapshot_blobs t ON t.hash = s.tools_hash
+                     WHERE s.session_id = ?1 AND s.turn = ?2",
+                    rusqlite::params![session_id, turn],
+                    |r| {
+                        Ok((
+                            r.get::<_, String>(0)?,
+                            r.get::<_, String>(1)?,
+                            r.get::<_, String>(2)?,
+                            r.get::<_, String>(3)?,
+                            r.get::<_, String>(4)?,
+                            r.get::<_, Option<String>>(5)?,
+                            r.get::<_, i64>(6)? as u64,
+                            r.get::<_, i64>(7)? as u64,
+                            r.get::<_, i64>(8)? as u64,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((model, prompt, tools, context, filters, response, input, output, ts)) = row
+            else {
+                return Ok(None);
+            };
+            Ok(Some(TurnSnapshot {
+                session_id,
+                turn,
+                model,
+                system_prompt: prompt,
+                tools: serde_json::from_str(&tools)?,
+                context: serde_json::from_str(&context)?,
+                filters: serde_json::from_str(&filters)?,
+                response: response.map(|r| serde_json::from_str(&r)).transpose()?,
+                input_tokens: input,
+                output_tokens: output,
+                created_at: ts,
+            }))
+        })
+        .await
+    }
+
+    /// Recorded turns of a session, oldest first.
+    pub async fn snapshot_list(&self, session_id: &str) -> Result<Vec<TurnSummary>, DbError> {
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT turn, model, json_array_length(context), input_tokens, output_tokens, created_at
+                 FROM turn_snapshots WHERE session_id = ?1 ORDER BY turn",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![session_id], |r| {
+                    Ok(TurnSummary {
+                        turn: r.get(0)?,
+                        model: r.get(1)?,
+                        messages: r.get::<_, i64>(2)? as usize,
+                        input_tokens: r.get::<_, i64>(3)? as u64,
+                        output_tokens: r.get::<_, i64>(4)? as u64,
+                        created_at: r.get::<_, i64>(5)? as u64,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Delete snapshots older than `before_ms` and prompt/tool versions no
+    /// longer referenced. Sync, like `snapshot_save_sync`. Returns the number
+    /// of snapshots deleted.
+    pub fn snapshot_prune_sync(&self, before_ms: u64) -> Result<usize, DbError> {
+        self.exec_sync(|conn| {
+            let deleted = conn.execute(
+                "DELETE FROM turn_snapshots WHERE created_at < ?1",
+                rusqlite::params![before_ms as i64],
+            )?;
+            if deleted > 0 {
+                conn.execute(
+                    "DELETE FROM snapshot_blobs WHERE hash NOT IN
+                     (SELECT prompt_hash FROM turn_snapshots UNION SELECT tools_hash FROM turn_snapshots)",
+                    [],
+                )?;
+            }
+            Ok(deleted)
+        })
+    }
+}
+
+fn save_snapshot(conn: &Connection, s: &TurnSnapshot) -> Result<u32, DbError> {
+    let tools = s.tools.to_string();
+    let prompt_hash = blob_hash(&s.system_prompt);
+    let tools_hash = blob_hash(&tools);
+    let context = serde_json::to_string(&s.context)?;
+    let filters = s.filters.to_string();
+    let response = s.response.as_ref().map(serde_json::to_string).transpose()?;
+    let ts = now_ms() as i64;
+
+    let tx = conn.unchecked_transaction()?;
+    for (hash, content) in [(&prompt_hash, &s.system_prompt), (&tools_hash, &tools)] {
+        tx.execute(
+            "INSERT OR IGNORE INTO snapshot_blobs (hash, content, created_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![hash, content, ts],
+        )?;
+    }
+    let turn: u32 = tx.query_row(
+        "SELECT COALESCE(MAX(turn), 0) + 1 FROM turn_snapshots WHERE session_id = ?1",
+        rusqlite::params![s.session_id],
+        |r| r.get(0),
+    )?;
+    tx.execute(
+        "INSERT INTO turn_snapshots (session_id, turn, model, prompt_hash, tools_hash, context,
+                                     filters, response, input_tokens, output_tokens, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        rusqlite::params![
+            s.session_id,
+            turn,
+            s.model,
+            prompt_hash,
+            tools_hash,
+            context,
+            filters,
+            response,
+            s.input_tokens as i64,
+            s.output_tokens as i64,
+            ts,
+        ],
+    )?;
+    tx.commit()?;
+    Ok(turn)
+}
+
+fn blob_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// First 12 hex digits of a blob hash, enough to tell versions apart.
+fn version(hash: &str) -> String {
+    hash[..12].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::types::Message;
+
+    fn snapshot(session_id: &str, prompt: &str) -> TurnSnapshot {
+        TurnSnapshot {
+            session_id: session_id.into(),
+            turn: 0,
+            model: "mock".into(),
+            system_prompt: prompt.into(),
+            tools: serde_json::json!([{"name": "bash", "description": "Run", "parameters": {}}]),
+            context: vec![AgentMessage::Llm(Message::user("hello"))],
+            filters: serde_json::json!({"private": false}),
+            response: None,
+            input_tokens: 10,
+            output_tokens: 2,
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_and_get_snapshots() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.snapshot_save_sync(&snapshot("tg-1", "v1")).unwrap(), 1);
+        assert_eq!(db.snapshot_save_sync(&snapshot("tg-1", "v2")).unwrap(), 2);
+        assert_eq!(db.snapshot_save_sync(&snapshot("tg-2", "v1")).unwrap(), 1);
+
+        let turn = db.snapshot_get("tg-1", 2).await.unwrap().unwrap();
+        assert_eq!(turn.system_prompt, "v2");
+        assert_eq!(turn.context.len(), 1);
+        assert_eq!(turn.tools[0]["name"], "bash");
+        assert_eq!(turn.filters["private"], false);
+        assert_ne!(
+            turn.prompt_version(),
+            snapshot("tg-1", "v1").prompt_version()
+        );
+        assert!(db.snapshot_get("tg-1", 3).await.unwrap().is_none());
+
+        let turns = db.snapshot_list("tg-1").await.unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].messages, 1);
+        assert_eq!(turns[1].input_tokens, 10);
+    }
+
+    #[tokio::test]
+    async fn test_prune_snapshots() {
+        let db = Db::open_memory().unwrap();
+        db.snapshot_save_sync(&snapshot("tg-1", "v1")).unwrap();
+        assert_eq!(db.snapshot_prune_sync(0).unwrap(), 0);
+        assert_eq!(db.snapshot_prune_sync(now_ms() + 1).unwrap(), 1);
+        assert!(db.snapshot_list("tg-1").await.unwrap().is_empty());
+
+        let blobs: i64 = db
+            .exec_sync(|conn| {
+                Ok(conn.query_row("SELECT COUNT(*) FROM snapshot_blobs", [], |r| r.get(0))?)
+            })
+            .unwrap();
+        assert_eq!(blobs, 0);
+    }
+}
@@ -0,0 +1,258 @@
+//! Storage backends for the data shared between hosts.
+//!
+//! Tapes, the message queue, memory, the audit log, cron jobs and saved
+//! workers go through the [`Storage`] trait when a `Db` has one
+//! ([`Db::with_storage`](super::Db::with_storage)), so several yoclaw hosts
+//! can serve one agent from a shared database. Without one, the `Db` methods
+//! use the local SQLite database, as everything else still does.
+//!
+//! Methods mirror the `Db` methods of the same name; see those for details.
+
+use super::audit::{AuditDay, AuditEntry};
+use super::cron::CronJob;
+use super::memory::{MemoryEdit, MemoryEntry, MemoryFilter};
+use super::queue::{AnsweredEntry, FailedEntry, QueueEntry, QueueFailure};
+use super::tape::{SessionInfo, TapeExport};
+use super::{DbError, SavedWorker};
+use crate::attachments::SavedAttachment;
+use yoagent::AgentMessage;
+
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
+    // -- Tape --
+
+    async fn tape_save_messages(
+        &self,
+        session_id: &str,
+        messages: &[AgentMessage],
+    ) -> Result<(), DbError>;
+
+    async fn tape_save_turn(
+        &self,
+        session_id: &str,
+        messages: &[AgentMessage],
+        detail: &str,
+        answered: Option<(i64, &str)>,
+    ) -> Result<(), DbError>;
+
+    async fn tape_load_messages(&self, session_id: &str) -> Result<Vec<AgentMessage>, DbError>;
+
+    async fn tape_list_sessions(&self) -> Result<Vec<SessionInfo>, DbError>;
+
+    async fn tape_export(&self, session_id: &str) -> Result<Option<TapeExport>, DbError>;
+
+    /// Delete a session's tape only. Returns whether there was one.
+    async fn tape_delete(&self, session_id: &str) -> Result<bool, DbError>;
+
+    async fn tape_trim(&self, max: usize) -> Result<usize, DbError>;
+
+    /// Move a session's tape only to `new_id`. Returns whether there was one.
+    async fn tape_rename(&self, session_id: &str, new_id: &str) -> Result<bool, DbError>;
+
+    // -- Queue --
+
+    async fn queue_push(&self, entry: &QueueEntry) -> Result<i64, DbError>;
+
+    async fn queue_claim_next(&self) -> Result<Option<QueueEntry>, DbError>;
+
+    async fn queue_attachments(&self, id: i64) -> Result<Vec<SavedAttachment>, DbError>;
+
+    async fn queue_mark_done(&self, id: i64) -> Result<(), DbError>;
+
+    async fn queue_mark_failed(&self, id: i64, failure: &QueueFailure) -> Result<(), DbError>;
+
+    async fn queue_failure(&self, id: i64) -> Result<Option<QueueFailure>, DbError>;
+
+    async fn queue_failures(&self, limit: usize) -> Result<Vec<FailedEntry>, DbError>;
+
+    async fn queue_failed_count(&self, since_ms: u64) -> Result<u64, DbError>;
+
+    async fn queue_retry_failed(&self, since_ms: u64, pace_ms: u64) -> Result<usize, DbError>;
+
+    async fn queue_take_retries(&self) -> Result<Vec<QueueEntry>, DbError>;
+
+    async fn queue_retry_count(&self) -> Result<usize, DbError>;
+
+    /// Requeue entries left `processing` by this host.
+    async fn queue_requeue_stale(&self) -> Result<usize, DbError>;
+
+    /// Entries answered by this host but not marked done.
+    async fn queue_answered(&self) -> Result<Vec<AnsweredEntry>, DbError>;
+
+    async fn queue_prune(&self, before_ms: u64) -> Result<usize, DbError>;
+
+    async fn queue_pending_count(&self) -> Result<usize, DbError>;
+
+    async fn queue_is_idle(&self) -> Result<bool, DbError>;
+
+    async fn queue_counts_by_channel(
+        &self,
+        since_ms: u64,
+        until_ms: u64,
+    ) -> Result<Vec<(String, u64)>, DbError>;
+
+    // -- Memory --
+
+    #[allow(clippy::too_many_arguments)]
+    async fn memory_store(
+        &self,
+        namespace: Option<&str>,
+        key: Option<&str>,
+        content: &str,
+        tags: Option<&str>,
+        source: Option<&str>,
+        category: &str,
+        importance: i32,
+    ) -> Result<i64, DbError>;
+
+    /// Search, rank and touch the memories found, without the `Db` cache.
+    async fn memory_search(
+        &self,
+        query: &str,
+        limit: usize,
+        namespace: Option<&str>,
+    ) -> Result<Vec<MemoryEntry>, DbError>;
+
+    async fn memory_get(&self, key: &str) -> Result<Option<MemoryEntry>, DbError>;
+
+    async fn memory_list_filtered(
+        &self,
+        limit: usize,
+        filter: &MemoryFilter,
+    ) -> Result<Vec<MemoryEntry>, DbError>;
+
+    async fn memory_get_by_id(&self, id: i64) -> Result<Option<MemoryEntry>, DbError>;
+
+    async fn memory_review_candidates(
+        &self,
+        recent_since: u64,
+        expiring_before: u64,
+        limit: usize,
+    ) -> Result<(Vec<MemoryEntry>, usize), DbError>;
+
+    async fn memory_mark_reviewed(&self, ids: Vec<i64>) -> Result<(), DbError>;
+
+    async fn memory_update_content(&self, id: i64, content: &str) -> Result<(), DbError>;
+
+    async fn memory_edit(&self, id: i64, edit: &MemoryEdit) -> Result<bool, DbError>;
+
+    async fn memory_delete(&self, id: i64) -> Result<(), DbError>;
+
+    async fn memory_touch(&self, ids: Vec<i64>) -> Result<(), DbError>;
+
+    // -- Audit --
+
+    #[allow(clippy::too_many_arguments)]
+    async fn audit_log(
+        &self,
+        session_id: Option<&str>,
+        sender_id: Option<&str>,
+        event_type: &str,
+        tool_name: Option<&str>,
+        detail: Option<&str>,
+        tokens_used: u64,
+        cost_usd: Option<f64>,
+    ) -> Result<(), DbError>;
+
+    async fn audit_session_events(&self, session_id: &str) -> Result<Vec<AuditEntry>, DbError>;
+
+    async fn audit_query(
+        &self,
+        session_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>, DbError>;
+
+    async fn audit_range(&self, since_ms: u64, until_ms: u64) -> Result<Vec<AuditEntry>, DbError>;
+
+    async fn audit_rollup(&self, before_ms: u64) -> Result<usize, DbError>;
+
+    async fn audit_daily(&self, since_day: &str) -> Result<Vec<AuditDay>, DbError>;
+
+    async fn audit_token_usage_today(&self) -> Result<u64, DbError>;
+
+    async fn audit_cost_since(&self, since_ms: u64) -> Result<f64, DbError>;
+
+    async fn audit_usage_today(
+        &self,
+        session_id: Option<&str>,
+        sender_id: Option<&str>,
+    ) -> Result<(u64, u64), DbError>;
+
+    async fn audit_logged_today(
+        &self,
+        session_id: &str,
+        event_type: &str,
+        detail: &str,
+    ) -> Result<bool, DbError>;
+
+    // -- Cron --
+
+    async fn cron_job_upsert(
+        &self,
+        name: &str,
+        schedule: &str,
+        prompt: &str,
+        target: Option<&str>,
+        session: &str,
+    ) -> Result<i64, DbError>;
+
+    async fn cron_job_set_model(
+        &self,
+        name: &str,
+        provider: Option<&str>,
+        model: Option<&str>,
+        max_tokens: Option<u32>,
+    ) -> Result<bool, DbError>;
+
+    async fn cron_jobs_list(&self) -> Result<Vec<CronJob>, DbError>;
+
+    async fn cron_jobs_enabled(&self) -> Result<Vec<(CronJob, u64)>, DbError>;
+
+    async fn cron_job_delete(&self, name: &str) -> Result<bool, DbError>;
+
+    async fn cron_job_set_enabled(&self, name: &str, enabled: bool) -> Result<bool, DbError>;
+
+    async fn cron_run_start(&self, job_id: i64, started_at: u64) -> Result<i64, DbError>;
+
+    async fn cron_run_finish(&self, run_id: i64, ok: bool, result: &str) -> Result<(), DbError>;
+
+    async fn cron_last_fired(&self, name: &str) -> Result<Option<u64>, DbError>;
+
+    async fn cron_claim(&self, name: &str, since: u64, ts: u64) -> Result<bool, DbError>;
+
+    async fn cron_set_last_fired(&self, name: &str, ts: u64) -> Result<(), DbError>;
+
+    // -- Saved workers --
+
+    async fn saved_workers_list(&self) -> Result<Vec<SavedWorker>, DbError>;
+
+    async fn saved_workers_get(&self, name: &str) -> Result<Option<SavedWorker>, DbError>;
+
+    async fn saved_workers_upsert(&self, name: &str, system_prompt: &str) -> Result<(), DbError>;
+
+    async fn saved_workers_remove(&self, name: &str) -> Result<bool, DbError>;
+}
+
+/// Shared storage in the Postgres database at `url`, as host `node`.
+#[cfg(feature = "postgres")]
+pub fn connect(url: &str, node: &str) -> Result<std::sync::Arc<dyn Storage>, DbError> {
+    Ok(std::sync::Arc::new(super::postgres::PgStorage::connect(
+        url, node,
+    )?))
+}
+
+#[cfg(not(feature = "postgres"))]
+pub fn connect(_url: &str, _node: &str) -> Result<std::sync::Arc<dyn Storage>, DbError> {
+    Err(DbError::PostgresUnsupported)
+}
+
+/// Default name of this host in shared storage: its hostname.
+pub fn default_node() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "yoclaw".to_string())
+}
@@ -1,8 +1,9 @@
 use super::{now_ms, Db, DbError};
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
 use yoagent::AgentMessage;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SessionInfo {
     pub session_id: String,
     pub message_count: usize,
@@ -10,6 +11,15 @@ pub struct SessionInfo {
     pub updated_at: u64,
 }
 
+/// A session's tape with its timestamps.
+#[derive(Debug, Clone, Serialize)]
+pub struct TapeExport {
+    pub session_id: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub messages: Vec<AgentMessage>,
+}
+
 impl Db {
     /// Save (upsert) the full message list for a session.
     pub async fn tape_save_messages(
@@ -17,6 +27,9 @@ impl Db {
         session_id: &str,
         messages: &[AgentMessage],
     ) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.tape_save_messages(session_id, messages).await;
+        }
         let session_id = session_id.to_string();
         let json = serde_json::to_string(messages)?;
         let count = messages.len();
@@ -25,16 +38,225 @@ impl Db {
             .await
     }
 
+    /// Save a session's tape after a message, in one transaction with the
+    /// message's `response` audit event (`detail`) and, for a queued message,
+    /// marking queue entry `queue_id` answered with `reply`. After a crash
+    /// the entry is then either still `processing`, with nothing of the turn
+    /// saved, or `answered`, with only the reply left to deliver.
+    pub async fn tape_save_turn(
+        &self,
+        session_id: &str,
+        messages: &[AgentMessage],
+        detail: &str,
+        answered: Option<(i64, &str)>,
+    ) -> Result<(), DbError> {
+        if let Some(storage) = &self.storage {
+            return storage
+                .tape_save_turn(session_id, messages, detail, answered)
+                .await;
+        }
+        let session_id = session_id.to_string();
+        let json = serde_json::to_string(messages)?;
+        let count = messages.len();
+        let detail = detail.to_string();
+        let answered = answered.map(|(id, reply)| (id, reply.to_string()));
+        let ts = now_ms();
+        self.exec(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            tape_save_sync(&tx, &session_id, &json, count, ts)?;
+            super::audit::insert_audit(
+                &tx,
+                Some(&session_id),
+                None,
+                "response",
+                None,
+                Some(&detail),
+                0,
+                None,
+            )?;
+            if let Some((id, reply)) = answered {
+                super::queue::queue_answered_sync(&tx, id, &reply)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
     /// Load messages for a session. Returns empty vec if session not found.
     pub async fn tape_load_messages(&self, session_id: &str) -> Result<Vec<AgentMessage>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.tape_load_messages(session_id).await;
+        }
         let session_id = session_id.to_string();
-        self.exec(move |conn| tape_load_sync(conn, &session_id))
+        self.exec_read(move |conn| tape_load_sync(conn, &session_id))
             .await
     }
 
     /// List all sessions.
     pub async fn tape_list_sessions(&self) -> Result<Vec<SessionInfo>, DbError> {
-        self.exec(tape_list_sync).await
+        if let Some(storage) = &self.storage {
+            return storage.tape_list_sessions().await;
+        }
+        self.exec_read(tape_list_sync).await
+    }
+
+    /// A session's tape with its timestamps, or `None` if it has none.
+    pub async fn tape_export(&self, session_id: &str) -> Result<Option<TapeExport>, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.tape_export(session_id).await;
+        }
+        let session_id = session_id.to_string();
+        self.exec_read(move |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT messages_json, created_at, updated_at FROM tape WHERE session_id = ?1",
+                    rusqlite::params![session_id],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, i64>(1)? as u64,
+                            row.get::<_, i64>(2)? as u64,
+                        ))
+                    },
+                )
+                .optional()?;
+            let Some((json, created_at, updated_at)) = row else {
+                return Ok(None);
+            };
+            Ok(Some(TapeExport {
+                session_id,
+                created_at,
+                updated_at,
+                messages: serde_json::from_str(&json)?,
+            }))
+        })
+        .await
+    }
+
+    /// Delete a session's tape, with its turn snapshots, worker runs and
+    /// annotations. Queue, audit and delivery records are kept. Returns
+    /// whether there was a tape.
+    pub async fn tape_delete_session(&self, session_id: &str) -> Result<bool, DbError> {
+        self.tape_delete_with(
+            session_id,
+            &["turn_snapshots", "worker_runs", "annotations"],
+        )
+        .await
+    }
+
+    /// Delete the tapes of sessions pruned by retention, with their turn
+    /// snapshots and worker runs. Annotations are kept: they hold a copy of
+    /// their message.
+    pub async fn tape_prune_session(&self, session_id: &str) -> Result<bool, DbError> {
+        self.tape_delete_with(session_id, &["turn_snapshots", "worker_runs"])
+            .await
+    }
+
+    /// Delete a session's tape and its rows in `tables` in one transaction.
+    /// With a storage backend, the tape is deleted there first.
+    async fn tape_delete_with(
+        &self,
+        session_id: &str,
+        tables: &'static [&'static str],
+    ) -> Result<bool, DbError> {
+        let stored = match &self.storage {
+            Some(storage) => Some(storage.tape_delete(session_id).await?),
+            None => None,
+        };
+        let session_id = session_id.to_string();
+        self.exec(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let deleted = match stored {
+                Some(deleted) => deleted,
+                None => {
+                    tx.execute(
+                        "DELETE FROM tape WHERE session_id = ?1",
+                        rusqlite::params![session_id],
+                    )? > 0
+                }
+            };
+            for table in tables {
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE session_id = ?1", table),
+                    rusqlite::params![session_id],
+                )?;
+            }
+            tx.commit()?;
+            Ok(deleted)
+        })
+        .await
+    }
+
+    /// Drop the oldest messages of every tape longer than `max`, so it starts
+    /// with a user or assistant message, not the results of a dropped tool
+    /// call. Returns how many messages were dropped.
+    pub async fn tape_trim(&self, max: usize) -> Result<usize, DbError> {
+        if let Some(storage) = &self.storage {
+            return storage.tape_trim(max).await;
+        }
+        self.exec(move |conn| {
+            let long: Vec<String> = conn
+                .prepare("SELECT session_id FROM tape WHERE message_count > ?1")?
+                .query_map(rusqlite::params![max as i64], |r| r.get(0))?
+                .collect::<Result<_, _>>()?;
+            let mut dropped = 0;
+            for session_id in long {
+                let messages = tape_load_sync(conn, &session_id)?;
+                let mut start = messages.len().saturating_sub(max);
+                while start < messages.len() && messages[start].role() == "toolResult" {
+                    start += 1;
+                }
+                let kept = &messages[start..];
+                let json = serde_json::to_string(kept)?;
+                conn.execute(
+                    "UPDATE tape SET messages_json = ?2, message_count = ?3 WHERE session_id = ?1",
+                    rusqlite::params![session_id, json, kept.len() as i64],
+                )?;
+                dropped += start;
+            }
+            Ok(dropped)
+        })
+        .await
+    }
+
+    /// Move a session's tape, turn snapshots, worker runs and annotations to
+    /// `new_id`.
+    /// Returns whether there was a tape; fails if `new_id` already has one.
+    pub async fn tape_rename_session(
+        &self,
+        session_id: &str,
+        new_id: &str,
+    ) -> Result<bool, DbError> {
+        let stored = match &self.storage {
+            Some(storage) => Some(storage.tape_rename(session_id, new_id).await?),
+            None => None,
+        };
+        let session_id = session_id.to_string();
+        let new_id = new_id.to_string();
+        self.exec(move |conn| {
+            let tx = conn.unchecked_transaction()?;
+            let renamed = match stored {
+                Some(renamed) => renamed,
+                None => {
+                    tx.execute(
+                        "UPDATE tape SET session_id = ?2 WHERE session_id = ?1",
+                        rusqlite::params![session_id, new_id],
+                    )? > 0
+                }
+            };
+            if renamed {
+                for table in ["turn_snapshots", "worker_runs", "annotations"] {
+                    tx.execute(
+                        &format!("UPDATE {} SET session_id = ?2 WHERE session_id = ?1", table),
+                        rusqlite::params![session_id, new_id],
+                    )?;
+                }
+            }
+            tx.commit()?;
+            Ok(renamed)
+        })
+        .await
     }
 }
 
@@ -142,6 +364,40 @@ mod tests {
         assert_eq!(loaded.len(), 2); // replaced, not appended
     }
 
+    #[tokio::test]
+    async fn test_save_turn_marks_answered() {
+        use crate::db::queue::QueueEntry;
+        let db = Db::open_memory().unwrap();
+        let id = db
+            .queue_push(&QueueEntry::new("telegram", "u1", "tg-1", "Hello"))
+            .await
+            .unwrap();
+        db.queue_claim_next().await.unwrap();
+
+        db.tape_save_turn(
+            "tg-1",
+            &sample_messages(),
+            "latency_ms=5",
+            Some((id, "Hi there!")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(db.tape_load_messages("tg-1").await.unwrap().len(), 2);
+        let events = db.audit_session_events("tg-1").await.unwrap();
+        assert_eq!(events[0].event_type, "response");
+        // Not run again after a crash, only delivered
+        assert_eq!(db.queue_requeue_stale().await.unwrap(), 0);
+        assert!(!db.queue_is_idle().await.unwrap());
+        let answered = db.queue_answered().await.unwrap();
+        assert_eq!(answered.len(), 1);
+        assert_eq!(answered[0].id, id);
+        assert_eq!(answered[0].reply, "Hi there!");
+
+        db.queue_mark_done(id).await.unwrap();
+        assert!(db.queue_answered().await.unwrap().is_empty());
+        assert!(db.queue_is_idle().await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_list_sessions() {
         let db = Db::open_memory().unwrap();
@@ -156,4 +412,44 @@ mod tests {
         assert_eq!(sessions.len(), 2);
         assert_eq!(sessions[0].message_count, 2);
     }
+
+    #[tokio::test]
+    async fn test_export_rename_delete() {
+        let db = Db::open_memory().unwrap();
+        db.tape_save_messages("tg-1", &sample_messages())
+            .await
+            .unwrap();
+        db.tape_save_messages("tg-2", &sample_messages())
+            .await
+            .unwrap();
+        db.worker_run_start("tg-1", "call-1", None, "research", "x")
+            .await
+            .unwrap();
+
+        let export = db.tape_export("tg-1").await.unwrap().unwrap();
+        assert_eq!(export.messages.len(), 2);
+        assert!(export.updated_at >= export.created_at);
+        assert!(db.tape_export("tg-9").await.unwrap().is_none());
+
+        assert!(db.tape_rename_session("tg-1", "tg-1-old").await.unwrap());
+        assert!(db.tape_load_messages("tg-1").await.unwrap().is_empty());
+        assert_eq!(db.tape_load_messages("tg-1-old").await.unwrap().len(), 2);
+        let runs = db
+            .worker_run_list(Some("tg-1-old"), false, 10)
+            .await
+            .unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(!db.tape_rename_session("tg-1", "tg-3").await.unwrap());
+        // Taken names are refused
+        assert!(db.tape_rename_session("tg-2", "tg-1-old").await.is_err());
+
+        assert!(db.tape_delete_session("tg-1-old").await.unwrap());
+        assert!(!db.tape_delete_session("tg-1-old").await.unwrap());
+        assert!(db
+            .worker_run_list(Some("tg-1-old"), false, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(db.tape_list_sessions().await.unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,64 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key holding a session's `/temp` override.
+fn temperature_key(session_id: &str) -> String {
+    format!("temp:{}", session_id)
+}
+
+impl Db {
+    /// The temperature the session set with `/temp`, if any.
+    pub async fn temperature_get(&self, session_id: &str) -> Result<Option<f32>, DbError> {
+        let key = temperature_key(session_id);
+        self.exec_read(move |conn| {
+            let value: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(value.and_then(|v| v.parse().ok()))
+        })
+        .await
+    }
+
+    /// Set a session's temperature, or clear it with `None`.
+    pub async fn temperature_set(
+        &self,
+        session_id: &str,
+        temperature: Option<f32>,
+    ) -> Result<(), DbError> {
+        let key = temperature_key(session_id);
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            match temperature {
+                Some(t) => conn.execute(
+                    "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                    rusqlite::params![key, t.to_string(), ts],
+                )?,
+                None => conn.execute("DELETE FROM state WHERE key = ?1", rusqlite::params![key])?,
+            };
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_temperature_set_and_clear() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(db.temperature_get("tg-1").await.unwrap(), None);
+
+        db.temperature_set("tg-1", Some(0.2)).await.unwrap();
+        assert_eq!(db.temperature_get("tg-1").await.unwrap(), Some(0.2));
+        assert_eq!(db.temperature_get("tg-2").await.unwrap(), None);
+
+        db.temperature_set("tg-1", None).await.unwrap();
+        assert_eq!(db.temperature_get("tg-1").await.unwrap(), None);
+    }
+}
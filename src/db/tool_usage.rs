@@ -0,0 +1,65 @@
+use super::{Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// The UTC day counts are kept under, e.g. "2026-10-16".
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+impl Db {
+    /// Count a call of `tool` against its quota of `max` calls today.
+    /// Returns false, without counting it, once the quota is used up.
+    pub async fn tool_quota_take(&self, tool: &str, max: u64) -> Result<bool, DbError> {
+        if max == 0 {
+            return Ok(false);
+        }
+        let tool = tool.to_string();
+        let day = today();
+        self.exec(move |conn| {
+            let n = conn.execute(
+                "INSERT INTO tool_usage (tool, day, calls) VALUES (?1, ?2, 1)
+                 ON CONFLICT(tool, day) DO UPDATE SET calls = calls + 1 WHERE calls < ?3",
+                rusqlite::params![tool, day, max as i64],
+            )?;
+            Ok(n > 0)
+        })
+        .await
+    }
+
+    /// Calls of `tool` counted today.
+    pub async fn tool_calls_today(&self, tool: &str) -> Result<u64, DbError> {
+        let tool = tool.to_string();
+        let day = today();
+        self.exec_read(move |conn| {
+            let calls: Option<i64> = conn
+                .query_row(
+                    "SELECT calls FROM tool_usage WHERE tool = ?1 AND day = ?2",
+                    rusqlite::params![tool, day],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(calls.unwrap_or(0) as u64)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tool_quota() {
+        let db = Db::open_memory().unwrap();
+        assert!(db.tool_quota_take("http", 2).await.unwrap());
+        assert!(db.tool_quota_take("http", 2).await.unwrap());
+        assert!(!db.tool_quota_take("http", 2).await.unwrap());
+        assert_eq!(db.tool_calls_today("http").await.unwrap(), 2);
+
+        // Each tool has its own count; a raised quota allows more calls
+        assert!(db.tool_quota_take("shell", 2).await.unwrap());
+        assert!(db.tool_quota_take("http", 3).await.unwrap());
+        assert_eq!(db.tool_calls_today("http").await.unwrap(), 3);
+        assert!(!db.tool_quota_take("read_file", 0).await.unwrap());
+    }
+}
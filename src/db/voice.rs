@@ -0,0 +1,61 @@
+use super::{now_ms, Db, DbError};
+use rusqlite::OptionalExtension;
+
+/// State-table key marking a session whose replies are spoken.
+fn voice_key(session_id: &str) -> String {
+    format!("voice:{}", session_id)
+}
+
+impl Db {
+    /// Whether the session turned spoken replies on with `/voice on`.
+    pub async fn voice_get(&self, session_id: &str) -> Result<bool, DbError> {
+        let key = voice_key(session_id);
+        self.exec_read(move |conn| {
+            let value: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM state WHERE key = ?1",
+                    rusqlite::params![key],
+                    |r| r.get(0),
+                )
+                .optional()?;
+            Ok(value.is_some())
+        })
+        .await
+    }
+
+    /// Turn spoken replies on or off for a session.
+    pub async fn voice_set(&self, session_id: &str, on: bool) -> Result<(), DbError> {
+        let key = voice_key(session_id);
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            if on {
+                conn.execute(
+                    "INSERT OR REPLACE INTO state (key, value, updated_at) VALUES (?1, '1', ?2)",
+                    rusqlite::params![key, ts],
+                )?;
+            } else {
+                conn.execute("DELETE FROM state WHERE key = ?1", rusqlite::params![key])?;
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_voice_on_and_off() {
+        let db = Db::open_memory().unwrap();
+        assert!(!db.voice_get("tg-1").await.unwrap());
+
+        db.voice_set("tg-1", true).await.unwrap();
+        assert!(db.voice_get("tg-1").await.unwrap());
+        assert!(!db.voice_get("tg-2").await.unwrap());
+
+        db.voice_set("tg-1", false).await.unwrap();
+        assert!(!db.voice_get("tg-1").await.unwrap());
+    }
+}
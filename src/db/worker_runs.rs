@@ -0,0 +1,215 @@
+use super::{now_ms, Db, DbError};
+use serde::Serialize;
+use yoagent::types::AgentMessage;
+
+/// Where a worker run stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// One run of a worker sub-agent, with everything it said and did.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerRun {
+    pub id: i64,
+    pub session_id: String,
+    /// Id of the parent's tool call that started the worker, as found in the
+    /// session's tape and turn snapshots.
+    pub tool_call_id: String,
+    /// Run of the worker that started this one, for nested workers.
+    pub parent_id: Option<i64>,
+    pub worker: String,
+    pub task: String,
+    /// "running", "done" or "failed"
+    pub status: String,
+    pub result: Option<String>,
+    pub error: Option<String>,
+    /// The sub-agent's conversation: its task, tool calls and results, and
+    /// replies.
+    pub messages: Vec<AgentMessage>,
+    pub tokens: u64,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+impl Db {
+    /// Record a worker starting on `task`. Returns the run's id.
+    pub async fn worker_run_start(
+        &self,
+        session_id: &str,
+        tool_call_id: &str,
+        parent_id: Option<i64>,
+        worker: &str,
+        task: &str,
+    ) -> Result<i64, DbError> {
+        let session_id = session_id.to_string();
+        let tool_call_id = tool_call_id.to_string();
+        let worker = worker.to_string();
+        let task = task.to_string();
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "INSERT INTO worker_runs (session_id, tool_call_id, parent_id, worker, task, status, started_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'running', ?6)",
+                rusqlite::params![session_id, tool_call_id, parent_id, worker, task, ts],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .await
+    }
+
+    /// Record how a run ended, with its transcript.
+    pub async fn worker_run_finish(
+        &self,
+        id: i64,
+        status: RunStatus,
+        result: Option<&str>,
+        error: Option<&str>,
+        messages: &[AgentMessage],
+        tokens: u64,
+    ) -> Result<(), DbError> {
+        let result = result.map(|s| s.to_string());
+        let error = error.map(|s| s.to_string());
+        let messages = serde_json::to_string(messages)?;
+        let ts = now_ms() as i64;
+        self.exec(move |conn| {
+            conn.execute(
+                "UPDATE worker_runs SET status = ?1, result = ?2, error = ?3, messages = ?4,
+                 tokens = ?5, finished_at = ?6 WHERE id = ?7",
+                rusqlite::params![
+                    status.as_str(),
+                    result,
+                    error,
+                    messages,
+                    tokens as i64,
+                    ts,
+                    id
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Most recent runs, newest first, optionally of one session. With
+    /// `failed_only`, only runs that failed.
+    pub async fn worker_run_list(
+        &self,
+        session_id: Option<&str>,
+        failed_only: bool,
+        limit: usize,
+    ) -> Result<Vec<WorkerRun>, DbError> {
+        let session_id = session_id.map(|s| s.to_string());
+        self.exec_read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, session_id, tool_call_id, parent_id, worker, task, status, result,
+                        error, messages, tokens, started_at, finished_at
+                 FROM worker_runs
+                 WHERE (?1 IS NULL OR session_id = ?1) AND (?2 = 0 OR status = 'failed')
+                 ORDER BY started_at DESC, id DESC LIMIT ?3",
+            )?;
+            let rows = stmt
+                .query_map(
+                    rusqlite::params![session_id, failed_only, limit as i64],
+                    |row| {
+                        let messages: Option<String> = row.get(9)?;
+                        Ok(WorkerRun {
+                            id: row.get(0)?,
+                            session_id: row.get(1)?,
+                            tool_call_id: row.get(2)?,
+                            parent_id: row.get(3)?,
+                            worker: row.get(4)?,
+                            task: row.get(5)?,
+                            status: row.get(6)?,
+                            result: row.get(7)?,
+                            error: row.get(8)?,
+                            messages: messages
+                                .and_then(|m| serde_json::from_str(&m).ok())
+                                .unwrap_or_default(),
+                            tokens: row.get::<_, i64>(10)? as u64,
+                            started_at: row.get::<_, i64>(11)? as u64,
+                            finished_at: row.get::<_, Option<i64>>(12)?.map(|t| t as u64),
+                        })
+                    },
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    /// Delete runs started before `before_ms`. Returns how many were deleted.
+    pub async fn worker_run_prune(&self, before_ms: u64) -> Result<usize, DbError> {
+        self.exec(move |conn| {
+            let deleted = conn.execute(
+                "DELETE FROM worker_runs WHERE started_at < ?1",
+                rusqlite::params![before_ms as i64],
+            )?;
+            Ok(deleted)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::types::Message;
+
+    #[tokio::test]
+    async fn test_worker_run_lifecycle() {
+        let db = Db::open_memory().unwrap();
+        let id = db
+            .worker_run_start("tg-1", "call-1", None, "research", "find the docs")
+            .await
+            .unwrap();
+        let nested = db
+            .worker_run_start("tg-1", "call-2", Some(id), "reader", "read page 2")
+            .await
+            .unwrap();
+        db.worker_run_start("tg-2", "call-3", None, "research", "other")
+            .await
+            .unwrap();
+
+        let messages = vec![AgentMessage::Llm(Message::user("read page 2"))];
+        db.worker_run_finish(
+            nested,
+            RunStatus::Failed,
+            None,
+            Some("overloaded"),
+            &messages,
+            42,
+        )
+        .await
+        .unwrap();
+
+        let runs = db.worker_run_list(Some("tg-1"), false, 10).await.unwrap();
+        assert_eq!(runs.len(), 2);
+        let failed = db.worker_run_list(None, true, 10).await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].parent_id, Some(id));
+        assert_eq!(failed[0].error.as_deref(), Some("overloaded"));
+        assert_eq!(failed[0].messages.len(), 1);
+        assert_eq!(failed[0].tokens, 42);
+        assert!(failed[0].finished_at.is_some());
+        let running = runs.iter().find(|r| r.id == id).unwrap();
+        assert_eq!(running.status, "running");
+        assert!(running.messages.is_empty());
+
+        assert_eq!(db.worker_run_prune(0).await.unwrap(), 0);
+        assert_eq!(db.worker_run_prune(now_ms() + 1).await.unwrap(), 3);
+    }
+}
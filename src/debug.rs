@@ -0,0 +1,256 @@
+//! `yoclaw debug turn`: show what the agent saw on a recorded turn. Also
+//! renders worker transcripts for `yoclaw inspect --worker-runs`.
+
+use crate::db::snapshots::{TurnSnapshot, TurnSummary};
+use crate::db::worker_runs::WorkerRun;
+use crate::transcript::content_text;
+use yoagent::types::*;
+
+/// Full plain-text rendering of a turn snapshot.
+pub fn format_turn(s: &TurnSnapshot) -> String {
+    let mut out = format!(
+        "Session {}, turn {} ({})\n",
+        s.session_id,
+        s.turn,
+        timestamp(s.created_at)
+    );
+    out.push_str(&format!(
+        "Model: {} · {} input / {} output tokens\n",
+        s.model, s.input_tokens, s.output_tokens
+    ));
+    out.push_str(&format!(
+        "System prompt: version {} ({} chars)\n",
+        s.prompt_version(),
+        s.system_prompt.chars().count()
+    ));
+    let names: Vec<&str> = s
+        .tools
+        .as_array()
+        .map(|tools| tools.iter().filter_map(|t| t["name"].as_str()).collect())
+        .unwrap_or_default();
+    out.push_str(&format!(
+        "Tools: version {} ({}): {}\n",
+        s.tools_version(),
+        names.len(),
+        names.join(", ")
+    ));
+
+    out.push_str("\n=== Filters ===\n");
+    out.push_str(&serde_json::to_string_pretty(&s.filters).unwrap_or_default());
+    out.push_str("\n\n=== System prompt ===\n");
+    out.push_str(s.system_prompt.trim_end());
+    out.push_str(&format!(
+        "\n\n=== Context ({} messages) ===\n",
+        s.context.len()
+    ));
+    for (i, msg) in s.context.iter().enumerate() {
+        out.push_str(&format!("[{}] {}\n", i + 1, format_message(msg)));
+    }
+    out.push_str("\n=== Response ===\n");
+    match s.response {
+        Some(ref msg) => out.push_str(&format_message(msg)),
+        None => out.push_str("(none)"),
+    }
+    out.push('\n');
+    out
+}
+
+/// One line per recorded turn.
+pub fn format_turn_list(session_id: &str, turns: &[TurnSummary]) -> String {
+    if turns.is_empty() {
+        return format!("No recorded turns for session {}.", session_id);
+    }
+    let mut out = format!("Recorded turns of {}:\n", session_id);
+    for t in turns {
+        out.push_str(&format!(
+            "  {:>4}  {}  {}  {} messages, {} in / {} out tokens\n",
+            t.turn,
+            timestamp(t.created_at),
+            t.model,
+            t.messages,
+            t.input_tokens,
+            t.output_tokens
+        ));
+    }
+    out
+}
+
+/// Recorded worker runs, each with its transcript.
+pub fn format_worker_runs(runs: &[WorkerRun]) -> String {
+    if runs.is_empty() {
+        return "No recorded worker runs.".to_string();
+    }
+    let mut out = String::new();
+    for run in runs {
+        let parent = run
+            .parent_id
+            .map(|id| format!(", started by run #{}", id))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "=== Run #{}: {} [{}] ===
+",
+            run.id, run.worker, run.status
+        ));
+        out.push_str(&format!(
+            "Session {}, call {}{} · {} · {} tokens
+",
+            run.session_id,
+            run.tool_call_id,
+            parent,
+            timestamp(run.started_at),
+            run.tokens
+        ));
+        out.push_str(&format!(
+            "Task: {}
+",
+            run.task
+        ));
+        if let Some(ref error) = run.error {
+            out.push_str(&format!(
+                "Error: {}
+",
+                error
+            ));
+        }
+        for (i, msg) in run.messages.iter().enumerate() {
+            out.push_str(&format!(
+                "[{}] {}
+",
+                i + 1,
+                format_message(msg)
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The last `limit` messages of a tape, one per entry, numbered from the
+/// start of the tape.
+pub fn format_tape(messages: &[AgentMessage], limit: usize) -> String {
+    let start = messages.len().saturating_sub(limit);
+    messages[start..]
+        .iter()
+        .enumerate()
+        .map(|(i, msg)| format!("[{}] {}", start + i + 1, format_message(msg)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_message(msg: &AgentMessage) -> String {
+    let AgentMessage::Llm(msg) = msg else {
+        return "(extension message)".to_string();
+    };
+    match msg {
+        Message::User { content, .. } => format!("user: {}", content_text(content)),
+        Message::Assistant {
+            content,
+            error_message,
+            ..
+        } => {
+            let mut parts = Vec::new();
+            for c in content {
+                match c {
+                    Content::Text { text } => parts.push(text.clone()),
+                    Content::ToolCall {
+                        name, arguments, ..
+                    } => parts.push(format!("[tool call] {} {}", name, arguments)),
+                    _ => {}
+                }
+            }
+            if let Some(e) = error_message {
+                parts.push(format!("[error] {}", e));
+            }
+            format!("assistant: {}", parts.join("\n"))
+        }
+        Message::ToolResult {
+            tool_name,
+            content,
+            is_error,
+            ..
+        } => {
+            let label = if *is_error {
+                "tool error"
+            } else {
+                "tool result"
+            };
+            format!("{} ({}): {}", label, tool_name, content_text(content))
+        }
+    }
+}
+
+pub(crate) fn timestamp(ms: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms as i64)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "?".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_turn() {
+        let snapshot = TurnSnapshot {
+            session_id: "tg-1".into(),
+            turn: 3,
+            model: "mock".into(),
+            system_prompt: "You are a test assistant.".into(),
+            tools: serde_json::json!([{"name": "bash"}, {"name": "memory_search"}]),
+            context: vec![AgentMessage::Llm(Message::user("list files"))],
+            filters: serde_json::json!({"compaction": {"dropped": 4}}),
+            response: Some(AgentMessage::Llm(Message::Assistant {
+                content: vec![Content::ToolCall {
+                    id: "call-1".into(),
+                    name: "bash".into(),
+                    arguments: serde_json::json!({"command": "ls"}),
+                }],
+                stop_reason: StopReason::ToolUse,
+                model: "mock".into(),
+                provider: "mock".into(),
+                usage: Usage::default(),
+                timestamp: 0,
+                error_message: None,
+            })),
+            input_tokens: 120,
+            output_tokens: 8,
+            created_at: 0,
+        };
+        let text = format_turn(&snapshot);
+        assert!(text.starts_with("Session tg-1, turn 3"));
+        assert!(text.contains("Tools: version "));
+        assert!(text.contains("(2): bash, memory_search"));
+        assert!(text.contains("\"dropped\": 4"));
+        assert!(text.contains("=== Context (1 messages) ===\n[1] user: list files\n"));
+        assert!(text.contains("assistant: [tool call] bash {\"command\":\"ls\"}"));
+
+        assert_eq!(
+            format_turn_list("tg-1", &[]),
+            "No recorded turns for session tg-1."
+        );
+    }
+
+    #[test]
+    fn test_format_worker_runs() {
+        let run = WorkerRun {
+            id: 7,
+            session_id: "tg-1".into(),
+            tool_call_id: "call-1".into(),
+            parent_id: Some(6),
+            worker: "research".into(),
+            task: "find the docs".into(),
+            status: "failed".into(),
+            result: None,
+            error: Some("overloaded".into()),
+            messages: vec![AgentMessage::Llm(Message::user("find the docs"))],
+            tokens: 12,
+            started_at: 0,
+            finished_at: Some(0),
+        };
+        let text = format_worker_runs(&[run]);
+        assert!(text.starts_with("=== Run #7: research [failed] ===\n"));
+        assert!(text.contains("call call-1, started by run #6"));
+        assert!(text.contains("Error: overloaded\n[1] user: find the docs\n"));
+        assert_eq!(format_worker_runs(&[]), "No recorded worker runs.");
+    }
+}
@@ -0,0 +1,221 @@
+//! Draft review for sensitive chats.
+//!
+//! Replies in sessions covered by `[review]` are not posted. The main loop
+//! stores them in the `drafts` table and sends them to the `reviewer`
+//! session, where an admin answers with one command per draft:
+//!
+//! ```text
+//! /draft approve 12         post the draft as written
+//! /draft edit 12 <text>     post <text> instead
+//! /draft reject 12          post nothing
+//! /draft list               show drafts waiting for review
+//! ```
+//!
+//! The dashboard offers the same controls through `/api/drafts`. Either way
+//! the decision is only recorded here; the main loop posts approved and
+//! edited drafts and notes edits and rejections on the session's tape, so
+//! the agent knows what the user actually saw.
+
+use crate::channels::OutgoingMessage;
+use crate::db::drafts::{Draft, DraftDecision};
+use crate::db::{Db, DbError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DraftError {
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("No pending draft #{0}")]
+    NotPending(i64),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+pub const COMMAND: &str = "/draft";
+
+const USAGE: &str =
+    "/draft approve <id> | /draft edit <id> <text> | /draft reject <id> | /draft list";
+
+/// A parsed `/draft` command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DraftCommand {
+    Decide { id: i64, decision: DraftDecision },
+    List,
+}
+
+/// Parse `/draft ...`. Returns None if `text` isn't a draft command.
+pub fn parse(text: &str) -> Option<Result<DraftCommand, DraftError>> {
+    let rest = text.trim_start().strip_prefix(COMMAND)?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(parse_args(rest.trim()))
+}
+
+fn parse_args(args: &str) -> Result<DraftCommand, DraftError> {
+    let (action, rest) = split_word(args);
+    if action.is_empty() || action == "list" {
+        return Ok(DraftCommand::List);
+    }
+    let (id, text) = split_word(rest);
+    let id: i64 = id.parse().map_err(|_| DraftError::Usage(USAGE))?;
+    let decision = match action {
+        "approve" => DraftDecision::Approve,
+        "reject" => DraftDecision::Reject,
+        // The edited text keeps its line breaks
+        "edit" if !text.is_empty() => DraftDecision::Edit(text.to_string()),
+        _ => return Err(DraftError::Usage(USAGE)),
+    };
+    Ok(DraftCommand::Decide { id, decision })
+}
+
+/// First word of `s` and the rest, trimmed.
+fn split_word(s: &str) -> (&str, &str) {
+    match s.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (s, ""),
+    }
+}
+
+/// Run a draft command for reviewer `by`. Returns the reply to the reviewer.
+pub async fn execute(cmd: &DraftCommand, db: &Db, by: &str) -> Result<String, DraftError> {
+    match cmd {
+        DraftCommand::List => {
+            let drafts = db.draft_pending().await?;
+            if drafts.is_empty() {
+                return Ok("No drafts waiting for review.".to_string());
+            }
+            Ok(drafts
+                .iter()
+                .map(format_for_reviewer)
+                .collect::<Vec<_>>()
+                .join("\n\n"))
+        }
+        DraftCommand::Decide { id, decision } => {
+            let draft = db
+                .draft_decide(*id, decision, by)
+                .await?
+                .ok_or(DraftError::NotPending(*id))?;
+            Ok(format!("Draft #{} {}.", draft.id, draft.status))
+        }
+    }
+}
+
+/// Message to the reviewer presenting a draft.
+pub fn format_for_reviewer(draft: &Draft) -> String {
+    format!(
+        "Draft #{} for {} ({})\n\
+         > {}\n\n\
+         {}\n\n\
+         /draft approve {id} | /draft edit {id} <text> | /draft reject {id}",
+        draft.id,
+        draft.session_id,
+        draft.channel,
+        draft.request.lines().collect::<Vec<_>>().join("\n> "),
+        draft.content,
+        id = draft.id
+    )
+}
+
+/// Message that posts a decided draft, None for rejected ones.
+pub fn outgoing(draft: &Draft) -> Option<OutgoingMessage> {
+    Some(OutgoingMessage {
+        channel: draft.channel.clone(),
+        session_id: draft.session_id.clone(),
+        content: draft.sent_content.clone()?,
+        reply_to: None,
+    })
+}
+
+/// Note for the session's tape, which holds the reply as drafted. None when
+/// the draft was posted unchanged.
+pub fn tape_note(draft: &Draft) -> Option<String> {
+    match draft.status.as_str() {
+        "edited" => Some(format!(
+            "[A reviewer edited your reply before it was sent. The user saw: \"{}\"]",
+            draft.sent_content.as_deref().unwrap_or_default()
+        )),
+        "rejected" => Some("[A reviewer rejected your reply; the user did not see it]".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("approve 12").is_none());
+        assert!(parse("/drafts").is_none());
+        assert_eq!(parse("/draft").unwrap().unwrap(), DraftCommand::List);
+        assert_eq!(
+            parse("/draft approve 12").unwrap().unwrap(),
+            DraftCommand::Decide {
+                id: 12,
+                decision: DraftDecision::Approve
+            }
+        );
+        assert_eq!(
+            parse("/draft edit 3 Thanks for waiting!\nYour refund is on its way.")
+                .unwrap()
+                .unwrap(),
+            DraftCommand::Decide {
+                id: 3,
+                decision: DraftDecision::Edit(
+                    "Thanks for waiting!\nYour refund is on its way.".into()
+                )
+            }
+        );
+        assert!(matches!(
+            parse("/draft edit 3"),
+            Some(Err(DraftError::Usage(_)))
+        ));
+        assert!(matches!(
+            parse("/draft reject twelve"),
+            Some(Err(DraftError::Usage(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_execute() {
+        let db = Db::open_memory().unwrap();
+        let id = db
+            .draft_create(
+                "slack",
+                "slack-C1",
+                "Is the outage over?",
+                "Yes, since 10:00.",
+            )
+            .await
+            .unwrap();
+        let list = execute(&DraftCommand::List, &db, "telegram:42")
+            .await
+            .unwrap();
+        assert!(list.starts_with(&format!(
+            "Draft #{} for slack-C1 (slack)\n> Is the outage over?",
+            id
+        )));
+
+        let cmd = DraftCommand::Decide {
+            id,
+            decision: DraftDecision::Edit("Mostly; a fix is rolling out.".into()),
+        };
+        assert_eq!(
+            execute(&cmd, &db, "telegram:42").await.unwrap(),
+            format!("Draft #{} edited.", id)
+        );
+        assert!(matches!(
+            execute(&cmd, &db, "telegram:42").await,
+            Err(DraftError::NotPending(_))
+        ));
+
+        let draft = db.draft_get(id).await.unwrap().unwrap();
+        assert_eq!(
+            outgoing(&draft).unwrap().content,
+            "Mostly; a fix is rolling out."
+        );
+        assert!(tape_note(&draft)
+            .unwrap()
+            .contains("Mostly; a fix is rolling out."));
+    }
+}
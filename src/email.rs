@@ -0,0 +1,433 @@
+//! Outbound email.
+//!
+//! Cron jobs and reports can mail their results to people who don't use any
+//! chat channel by setting `target = "mailto:<address>"`. Mail is sent over
+//! SMTP with the settings of `[channels.email]`: STARTTLS on port 587 by
+//! default, TLS from the start with `tls = "tls"`, or plain text for a local
+//! relay with `tls = "none"`. The subject is the first line of the result.
+
+use crate::channels::OutgoingMessage;
+use crate::config::EmailConfig;
+use base64::Engine;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls;
+
+/// Session ID prefix that addresses an email recipient, e.g.
+/// "mailto:ops@example.com".
+pub const TARGET_PREFIX: &str = "mailto:";
+
+/// Longest subject taken from the first line of a message.
+const SUBJECT_CHARS: usize = 78;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("mailto: targets need [channels.email]")]
+    NotConfigured,
+    #[error("Not a valid email address: '{0}'")]
+    BadAddress(String),
+    #[error("Unknown tls mode '{0}' (expected starttls, tls or none)")]
+    TlsMode(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TLS error: {0}")]
+    Tls(String),
+    #[error("SMTP server answered {code}: {text}")]
+    Smtp { code: u16, text: String },
+    #[error("Timed out after {0}s")]
+    Timeout(u64),
+    #[error("Won't send the SMTP login in plain text: use tls = \"starttls\" or \"tls\"")]
+    PlainTextLogin,
+    #[error("SMTP server doesn't offer STARTTLS")]
+    NoStartTls,
+}
+
+/// Whether a delivery target is an email address (`mailto:`).
+pub fn is_target(target: &str) -> bool {
+    target
+        .get(..TARGET_PREFIX.len())
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case(TARGET_PREFIX))
+}
+
+/// The address of a `mailto:` target, if it is one and looks like an
+/// address. Anything that could end an SMTP command or a header is refused.
+pub fn address(target: &str) -> Option<&str> {
+    if !is_target(target) {
+        return None;
+    }
+    let address = &target[TARGET_PREFIX.len()..];
+    let (local, domain) = address.split_once('@')?;
+    let valid = !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !domain.contains('@')
+        && address
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !"<>,;:\"()[]\\".contains(c));
+    valid.then_some(address)
+}
+
+/// Delivers messages to `mailto:` targets.
+pub struct EmailSender {
+    config: Option<EmailConfig>,
+}
+
+impl EmailSender {
+    pub fn new(config: Option<EmailConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Mail `msg` to the address in its session ID ("mailto:<address>").
+    pub async fn send(&self, msg: &OutgoingMessage) -> Result<(), EmailError> {
+        let config = self.config.as_ref().ok_or(EmailError::NotConfigured)?;
+        let to = address(&msg.session_id)
+            .ok_or_else(|| EmailError::BadAddress(msg.session_id.clone()))?;
+        let timeout = Duration::from_secs(config.timeout_secs);
+        tokio::time::timeout(timeout, deliver(config, to, &msg.content))
+            .await
+            .map_err(|_| EmailError::Timeout(config.timeout_secs))?
+    }
+}
+
+async fn deliver(config: &EmailConfig, to: &str, content: &str) -> Result<(), EmailError> {
+    let implicit_tls = match config.tls.as_str() {
+        "starttls" | "none" => false,
+        "tls" => true,
+        other => return Err(EmailError::TlsMode(other.to_string())),
+    };
+    if config.tls == "none" && (config.username.is_some() || config.password.is_some()) {
+        return Err(EmailError::PlainTextLogin);
+    }
+    let port = config.port.unwrap_or(if implicit_tls { 465 } else { 587 });
+    let stream = TcpStream::connect((config.host.as_str(), port)).await?;
+    let message = format_message(&config.from, to, content);
+
+    if implicit_tls {
+        let mut smtp = Smtp::new(connect_tls(&config.host, stream).await?);
+        smtp.expect(220).await?;
+        return smtp.send_mail(config, to, &message).await;
+    }
+    let mut smtp = Smtp::new(stream);
+    smtp.expect(220).await?;
+    if config.tls == "none" {
+        return smtp.send_mail(config, to, &message).await;
+    }
+    smtp.write(&format!("EHLO {}", helo_name())).await?;
+    let extensions = match smtp.reply().await? {
+        (250, text) => text,
+        (code, text) => return Err(EmailError::Smtp { code, text }),
+    };
+    if !extensions
+        .split_whitespace()
+        .any(|word| word.eq_ignore_ascii_case("STARTTLS"))
+    {
+        return Err(EmailError::NoStartTls);
+    }
+    smtp.command("STARTTLS", 220).await?;
+    let stream = smtp.reader.into_inner();
+    let mut smtp = Smtp::new(connect_tls(&config.host, stream).await?);
+    smtp.send_mail(config, to, &message).await
+}
+
+async fn connect_tls(
+    host: &str,
+    stream: TcpStream,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>, EmailError> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let tls = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| EmailError::Tls(e.to_string()))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|e| EmailError::Tls(e.to_string()))?;
+    Ok(tokio_rustls::TlsConnector::from(Arc::new(tls))
+        .connect(server_name, stream)
+        .await?)
+}
+
+/// Name yoclaw greets the server with.
+fn helo_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_graphic()))
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// One SMTP session.
+struct Smtp<S> {
+    reader: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Smtp<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+        }
+    }
+
+    /// Read a reply, joining the lines of a multi-line one.
+    async fn reply(&mut self) -> Result<(u16, String), EmailError> {
+        let mut text = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return Err(EmailError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+            let line = line.trim_end();
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| EmailError::Smtp {
+                    code: 0,
+                    text: line.to_string(),
+                })?;
+            text.push(line.get(4..).unwrap_or_default().to_string());
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, text.join(" ")));
+            }
+        }
+    }
+
+    async fn expect(&mut self, code: u16) -> Result<(), EmailError> {
+        match self.reply().await? {
+            (got, _) if got == code => Ok(()),
+            (code, text) => Err(EmailError::Smtp { code, text }),
+        }
+    }
+
+    async fn command(&mut self, line: &str, code: u16) -> Result<(), EmailError> {
+        self.write(line).await?;
+        self.expect(code).await
+    }
+
+    async fn write(&mut self, line: &str) -> Result<(), EmailError> {
+        let stream = self.reader.get_mut();
+        stream.write_all(line.as_bytes()).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+        Ok(())
+    }
+
+    /// Greet, log in if configured, send `message` to `to` and say goodbye.
+    async fn send_mail(
+        &mut self,
+        config: &EmailConfig,
+        to: &str,
+        message: &str,
+    ) -> Result<(), EmailError> {
+        self.command(&format!("EHLO {}", helo_name()), 250).await?;
+        if let Some(ref username) = config.username {
+            let password = config.password.as_deref().unwrap_or_default();
+            let login = base64::engine::general_purpose::STANDARD
+                .encode(format!("\0{}\0{}", username, password));
+            self.command(&format!("AUTH PLAIN {}", login), 235).await?;
+        }
+        self.command(&format!("MAIL FROM:<{}>", config.from), 250)
+            .await?;
+        self.write(&format!("RCPT TO:<{}>", to)).await?;
+        match self.reply().await? {
+            (250 | 251, _) => {}
+            (code, text) => return Err(EmailError::Smtp { code, text }),
+        }
+        self.command("DATA", 354).await?;
+        self.command(&format!("{}\r\n.", message), 250).await?;
+        // The mail is accepted; a failed goodbye doesn't matter
+        let _ = self.command("QUIT", 221).await;
+        Ok(())
+    }
+}
+
+/// The mail for `content`: headers, then the text base64-encoded, so no line
+/// needs escaping and any character survives.
+fn format_message(from: &str, to: &str, content: &str) -> String {
+    let first_line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("Message from yoclaw");
+    let subject: String = first_line.chars().take(SUBJECT_CHARS).collect();
+    let engine = &base64::engine::general_purpose::STANDARD;
+    let subject = if subject.is_ascii() {
+        subject
+    } else {
+        format!("=?UTF-8?B?{}?=", engine.encode(&subject))
+    };
+    let body = engine.encode(content);
+    let body: Vec<&str> = body
+        .as_bytes()
+        .chunks(76)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base64 is ASCII"))
+        .collect();
+    format!(
+        "From: <{from}>\r\n\
+         To: <{to}>\r\n\
+         Subject: {subject}\r\n\
+         Date: {date}\r\n\
+         Message-ID: <{id}@yoclaw>\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: base64\r\n\
+         \r\n\
+         {body}",
+        date = chrono::Utc::now().to_rfc2822(),
+        id = uuid::Uuid::new_v4(),
+        body = body.join("\r\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_address() {
+        assert_eq!(address("mailto:ops@example.com"), Some("ops@example.com"));
+        assert_eq!(address("MAILTO:ops@example.com"), Some("ops@example.com"));
+        assert!(address("mailto:ops").is_none());
+        assert!(address("mailto:ops@example.com>\r\nRCPT TO:<x@y.z").is_none());
+        assert!(address("mailto:a@b@example.com").is_none());
+        assert!(address("tg-1").is_none());
+        assert!(is_target("Mailto:x"));
+        assert!(!is_target("mail"));
+    }
+
+    /// Plays an SMTP server for one session and returns what it was sent.
+    async fn fake_server(listener: TcpListener) -> Vec<String> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut reader = BufReader::new(stream);
+        reader
+            .get_mut()
+            .write_all(b"220 test ESMTP\r\n")
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await.unwrap() == 0 {
+                break;
+            }
+            let line = line.trim_end_matches("\r\n").to_string();
+            let answer: &[u8] = if in_data {
+                if line == "." {
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else {
+                    b""
+                }
+            } else if line.starts_with("EHLO") {
+                b"250-test\r\n250 AUTH PLAIN\r\n"
+            } else if line.starts_with("AUTH") {
+                b"235 ok\r\n"
+            } else if line == "DATA" {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line == "QUIT" {
+                b"221 bye\r\n"
+            } else {
+                b"250 ok\r\n"
+            };
+            received.push(line);
+            reader.get_mut().write_all(answer).await.unwrap();
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_send_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_server(listener));
+
+        let sender = EmailSender::new(Some(EmailConfig {
+            host: "127.0.0.1".into(),
+            port: Some(port),
+            tls: "none".into(),
+            username: None,
+            password: None,
+            from: "yoclaw@example.com".into(),
+            timeout_secs: 5,
+        }));
+        let content = "Daily digest\n\n.Three tickets closed — all green.";
+        sender
+            .send(&OutgoingMessage {
+                channel: "email".into(),
+                session_id: "mailto:ops@example.com".into(),
+                content: content.into(),
+                reply_to: None,
+            })
+            .await
+            .unwrap();
+
+        let received = server.await.unwrap();
+        assert!(!received.iter().any(|l| l.starts_with("AUTH")));
+        assert!(received.contains(&"MAIL FROM:<yoclaw@example.com>".to_string()));
+        assert!(received.contains(&"RCPT TO:<ops@example.com>".to_string()));
+        assert!(received.contains(&"Subject: Daily digest".to_string()));
+        let data = received.iter().position(|l| l.is_empty()).unwrap() + 1;
+        let end = received.iter().position(|l| l == ".").unwrap();
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(received[data..end].concat())
+            .unwrap();
+        assert_eq!(String::from_utf8(body).unwrap(), content);
+        assert_eq!(received.last().unwrap(), "QUIT");
+    }
+
+    fn message() -> OutgoingMessage {
+        OutgoingMessage {
+            channel: "email".into(),
+            session_id: "mailto:ops@example.com".into(),
+            content: "hi".into(),
+            reply_to: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_needs_tls() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut config = EmailConfig {
+            host: "127.0.0.1".into(),
+            port: Some(port),
+            tls: "none".into(),
+            username: Some("bot".into()),
+            password: Some("secret".into()),
+            from: "yoclaw@example.com".into(),
+            timeout_secs: 5,
+        };
+        // Never sent in plain text
+        assert!(matches!(
+            EmailSender::new(Some(config.clone()))
+                .send(&message())
+                .await,
+            Err(EmailError::PlainTextLogin)
+        ));
+
+        // Nor over a server that can't upgrade the connection
+        config.tls = "starttls".into();
+        let server = tokio::spawn(fake_server(listener));
+        assert!(matches!(
+            EmailSender::new(Some(config)).send(&message()).await,
+            Err(EmailError::NoStartTls)
+        ));
+        let received = server.await.unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].starts_with("EHLO"));
+    }
+
+    #[tokio::test]
+    async fn test_send_without_config() {
+        assert!(matches!(
+            EmailSender::new(None).send(&message()).await,
+            Err(EmailError::NotConfigured)
+        ));
+    }
+}
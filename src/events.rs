@@ -0,0 +1,336 @@
+//! Lifecycle events for outbound webhooks.
+//!
+//! A `[webhooks.<name>]` target with `events = [...]` is told about what
+//! happens in the daemon, so automation (n8n, Zapier, a custom service) can
+//! react without polling the API. Like push notifications, [`EventNotifier`]
+//! polls the database every few seconds for new events and POSTs each one,
+//! signed as described in [`crate::webhook`], to the targets that asked for
+//! it:
+//!
+//! ```json
+//! {"id": "…", "target": "n8n", "timestamp": 1700000000,
+//!  "event": "cron_failed", "data": {"job": "digest", "error": "…"}}
+//! ```
+//!
+//! Events from before the daemon started are not sent, and a failed POST is
+//! logged, not retried.
+
+use crate::config::WebhookTargetConfig;
+use crate::db::{now_ms, Db, DbError};
+use crate::webhook::WebhookSender;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How often the database is checked for new events.
+const POLL_SECS: u64 = 5;
+
+/// What a webhook target can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A queued message was answered.
+    MessageProcessed,
+    /// A message arrived in a session never seen before.
+    SessionCreated,
+    /// A tool call, input or admin command was refused by security checks.
+    SecurityDenied,
+    /// A cron run ended in an error.
+    CronFailed,
+    /// Daily token usage crossed `budget_alert_percent` or the limit.
+    BudgetThreshold,
+}
+
+impl Event {
+    pub const ALL: [Event; 5] = [
+        Event::MessageProcessed,
+        Event::SessionCreated,
+        Event::SecurityDenied,
+        Event::CronFailed,
+        Event::BudgetThreshold,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Event::MessageProcessed => "message_processed",
+            Event::SessionCreated => "session_created",
+            Event::SecurityDenied => "security_denied",
+            Event::CronFailed => "cron_failed",
+            Event::BudgetThreshold => "budget_threshold",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Event> {
+        Event::ALL.into_iter().find(|e| e.as_str() == s)
+    }
+}
+
+/// One event for one target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    pub target: String,
+    pub event: Event,
+    pub data: Value,
+}
+
+/// Posts lifecycle events to the webhook targets subscribed to them.
+pub struct EventNotifier {
+    db: Db,
+    targets: HashMap<String, WebhookTargetConfig>,
+    sender: WebhookSender,
+    /// `max_tokens_per_day`, for budget events.
+    budget_limit: Option<u64>,
+}
+
+impl EventNotifier {
+    /// `None` when no target subscribes to any event.
+    pub fn new(
+        db: Db,
+        targets: &HashMap<String, WebhookTargetConfig>,
+        budget_limit: Option<u64>,
+    ) -> Option<Self> {
+        let targets: HashMap<String, WebhookTargetConfig> = targets
+            .iter()
+            .filter(|(_, t)| !t.events.is_empty())
+            .map(|(name, t)| (name.clone(), t.clone()))
+            .collect();
+        if targets.is_empty() {
+            return None;
+        }
+        Some(Self {
+            db,
+            sender: WebhookSender::new(targets.clone()),
+            targets,
+            budget_limit,
+        })
+    }
+
+    /// Poll for events and post them until the process exits.
+    pub async fn run(self) {
+        let mut since = now_ms();
+        let mut interval = tokio::time::interval(Duration::from_secs(POLL_SECS));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let until = now_ms();
+            match self.poll(since, until).await {
+                Ok(deliveries) => {
+                    for d in &deliveries {
+                        let sent = self
+                            .sender
+                            .send_event(&d.target, d.event.as_str(), &d.data)
+                            .await;
+                        if let Err(e) = sent {
+                            tracing::warn!(
+                                "Webhook '{}' event {} failed: {}",
+                                d.target,
+                                d.event.as_str(),
+                                e
+                            );
+                        }
+                    }
+                    since = until;
+                }
+                Err(e) => tracing::error!("Webhook event poll failed: {}", e),
+            }
+        }
+    }
+
+    /// Events in `[since_ms, until_ms)` for each target subscribed to them.
+    pub async fn poll(&self, since_ms: u64, until_ms: u64) -> Result<Vec<Delivery>, DbError> {
+        let mut events = Vec::new();
+        if self.wanted(Event::SessionCreated) {
+            for s in self.db.events_new_sessions(since_ms, until_ms).await? {
+                events.push((Event::SessionCreated, json!(s)));
+            }
+        }
+        if self.wanted(Event::MessageProcessed) {
+            for m in self
+                .db
+                .events_processed_messages(since_ms, until_ms)
+                .await?
+            {
+                events.push((Event::MessageProcessed, json!(m)));
+            }
+        }
+        if self.wanted(Event::SecurityDenied) {
+            for entry in self.db.audit_range(since_ms, until_ms).await? {
+                if crate::scheduler::reports::SECURITY_EVENTS.contains(&entry.event_type.as_str()) {
+                    events.push((
+                        Event::SecurityDenied,
+                        json!({
+                            "session_id": entry.session_id,
+                            "kind": entry.event_type,
+                            "tool": entry.tool_name,
+                            "detail": entry.detail,
+                            "timestamp": entry.timestamp,
+                        }),
+                    ));
+                }
+            }
+        }
+        if self.wanted(Event::CronFailed) {
+            for run in self.db.events_failed_cron_runs(since_ms, until_ms).await? {
+                events.push((Event::CronFailed, json!(run)));
+            }
+        }
+
+        let mut names: Vec<&String> = self.targets.keys().collect();
+        names.sort();
+        let mut deliveries = Vec::new();
+        for (event, data) in events {
+            for name in &names {
+                if self.subscribed(name, event) {
+                    deliveries.push(Delivery {
+                        target: name.to_string(),
+                        event,
+                        data: data.clone(),
+                    });
+                }
+            }
+        }
+        for name in names {
+            if self.subscribed(name, Event::BudgetThreshold) {
+                if let Some(d) = self.budget_alert(name).await? {
+                    deliveries.push(d);
+                }
+            }
+        }
+        Ok(deliveries)
+    }
+
+    fn wanted(&self, event: Event) -> bool {
+        self.targets.keys().any(|name| self.subscribed(name, event))
+    }
+
+    fn subscribed(&self, name: &str, event: Event) -> bool {
+        self.targets[name]
+            .events
+            .iter()
+            .any(|e| e == event.as_str())
+    }
+
+    /// A budget event for target `name`, the first time today's usage
+    /// crosses one of its thresholds.
+    async fn budget_alert(&self, name: &str) -> Result<Option<Delivery>, DbError> {
+        let Some(limit) = self.budget_limit.filter(|l| *l > 0) else {
+            return Ok(None);
+        };
+        let threshold = self.targets[name].budget_alert_percent;
+        let used = self.db.audit_token_usage_today().await?;
+        let percent = used.saturating_mul(100) / limit;
+        let level = if percent >= 100 {
+            100
+        } else if percent >= threshold {
+            threshold
+        } else {
+            return Ok(None);
+        };
+
+        let day = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        if self
+            .db
+            .events_budget_alerted(name, &day)
+            .await?
+            .unwrap_or(0)
+            >= level
+        {
+            return Ok(None);
+        }
+        self.db.events_set_budget_alerted(name, &day, level).await?;
+        Ok(Some(Delivery {
+            target: name.to_string(),
+            event: Event::BudgetThreshold,
+            data: json!({
+                "threshold": level,
+                "percent": percent,
+                "tokens_used": used,
+                "tokens_limit": limit,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queue::QueueEntry;
+
+    fn target(events: &[&str]) -> WebhookTargetConfig {
+        WebhookTargetConfig {
+            url: "http://localhost:9/".into(),
+            secret: Some("s3cret".into()),
+            timeout_secs: 1,
+            events: events.iter().map(|e| e.to_string()).collect(),
+            budget_alert_percent: 80,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_routes_events_to_subscribers() {
+        let db = Db::open_memory().unwrap();
+        let targets = HashMap::from([
+            (
+                "ops".to_string(),
+                target(&["security_denied", "budget_threshold"]),
+            ),
+            (
+                "crm".to_string(),
+                target(&["session_created", "message_processed"]),
+            ),
+            ("ci".to_string(), target(&[])),
+        ]);
+        let notifier = EventNotifier::new(db.clone(), &targets, Some(1_000)).unwrap();
+        let since = now_ms();
+
+        db.session_touch("telegram", "tg-7", Some("7"), None, false)
+            .await
+            .unwrap();
+        let id = db
+            .queue_push(&QueueEntry::new("telegram", "7", "tg-7", "hi"))
+            .await
+            .unwrap();
+        db.queue_mark_done(id).await.unwrap();
+        db.audit_log(Some("tg-7"), "denied", Some("shell"), Some("rm -rf /"), 0)
+            .await
+            .unwrap();
+        db.audit_log(Some("tg-7"), "tool_call", Some("http"), None, 0)
+            .await
+            .unwrap();
+        db.audit_log(Some("tg-7"), "tokens", None, None, 900)
+            .await
+            .unwrap();
+
+        let deliveries = notifier.poll(since, now_ms() + 1).await.unwrap();
+        let routed: Vec<(&str, Event)> = deliveries
+            .iter()
+            .map(|d| (d.target.as_str(), d.event))
+            .collect();
+        assert_eq!(
+            routed,
+            vec![
+                ("crm", Event::SessionCreated),
+                ("crm", Event::MessageProcessed),
+                ("ops", Event::SecurityDenied),
+                ("ops", Event::BudgetThreshold),
+            ]
+        );
+        assert_eq!(deliveries[0].data["session_id"], "tg-7");
+        assert_eq!(deliveries[2].data["tool"], "shell");
+        assert_eq!(deliveries[3].data["threshold"], 80);
+
+        // The same threshold isn't announced twice in a day
+        let later = now_ms() + 1;
+        assert!(notifier.poll(later, later).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_no_subscribers() {
+        let db = Db::open_memory().unwrap();
+        let targets = HashMap::from([("ci".to_string(), target(&[]))]);
+        assert!(EventNotifier::new(db, &targets, None).is_none());
+        for event in Event::ALL {
+            assert_eq!(Event::parse(event.as_str()), Some(event));
+        }
+        assert_eq!(Event::parse("everything"), None);
+    }
+}
@@ -0,0 +1,452 @@
+//! Several agents supervised by one process (`yoclaw fleet --dir <dir>`).
+//!
+//! Each `*.toml` file in the directory is one agent, named after the file.
+//! An agent runs the normal daemon loop on its own thread with its own tokio
+//! runtime, conductor and database, so stopping it (dropping the runtime)
+//! also ends its channel adapters, scheduler and background tasks. The fleet
+//! keeps a read handle on every database and each agent's SSE channel for the
+//! combined dashboard (see `web::fleet`).
+
+use crate::config::{Config, ConfigError};
+use crate::db::{now_ms, Db, DbError};
+use crate::web::SseEvent;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+use tracing::Instrument;
+
+/// Tokio worker threads per agent. Agents mostly wait on the network.
+const AGENT_WORKER_THREADS: usize = 2;
+
+/// Time given to an agent's tasks to finish when it is stopped.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs one agent until it fails or its future is dropped: the daemon loop
+/// for the config at the given path, publishing dashboard events on the sender.
+pub type Runner = fn(
+    PathBuf,
+    broadcast::Sender<SseEvent>,
+) -> Pin<Box<dyn Future<Output = Result<(), anyhow::Error>>>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FleetError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database error: {0}")]
+    Db(#[from] DbError),
+    #[error("Config {path}: {source}")]
+    Config { path: PathBuf, source: ConfigError },
+    #[error("No agent configs (*.toml) in {0}")]
+    Empty(PathBuf),
+    #[error("Agent name '{0}' may only contain letters, digits, '-' and '_'")]
+    InvalidName(String),
+    #[error("Agents '{0}' and '{1}' share {2}; each agent needs its own")]
+    Shared(String, String, String),
+    #[error("Unknown agent '{0}'")]
+    UnknownAgent(String),
+    #[error("Agent '{0}' is already running")]
+    AlreadyRunning(String),
+    #[error("Agent '{0}' is not running")]
+    NotRunning(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentState {
+    Stopped,
+    Running,
+    /// The daemon loop returned an error; see [`AgentStatus::error`].
+    Failed,
+}
+
+/// An agent's lifecycle, as shown on the dashboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentStatus {
+    pub name: String,
+    pub config_path: PathBuf,
+    pub state: AgentState,
+    /// When (ms) the agent was last started.
+    pub started_at: Option<u64>,
+    /// Times the agent was started since the fleet came up.
+    pub starts: u32,
+    pub error: Option<String>,
+}
+
+struct Lifecycle {
+    state: AgentState,
+    started_at: Option<u64>,
+    starts: u32,
+    error: Option<String>,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+/// One supervised agent.
+pub struct FleetAgent {
+    pub name: String,
+    pub config_path: PathBuf,
+    pub config: Arc<Config>,
+    /// Read handle on the agent's database, for the dashboard.
+    pub db: Db,
+    /// Dashboard events published by the agent.
+    pub events: broadcast::Sender<SseEvent>,
+    lifecycle: Mutex<Lifecycle>,
+}
+
+impl FleetAgent {
+    fn status(&self) -> AgentStatus {
+        let lc = self.lifecycle.lock().unwrap();
+        AgentStatus {
+            name: self.name.clone(),
+            config_path: self.config_path.clone(),
+            state: lc.state,
+            started_at: lc.started_at,
+            starts: lc.starts,
+            error: lc.error.clone(),
+        }
+    }
+
+    /// Record how the agent's thread ended.
+    fn finished(&self, result: Result<(), String>) {
+        let mut lc = self.lifecycle.lock().unwrap();
+        lc.stop = None;
+        match result {
+            Ok(()) => {
+                lc.state = AgentState::Stopped;
+                tracing::info!("Agent '{}' stopped", self.name);
+            }
+            Err(e) => {
+                tracing::error!("Agent '{}' failed: {}", self.name, e);
+                lc.state = AgentState::Failed;
+                lc.error = Some(e);
+            }
+        }
+    }
+}
+
+/// The supervised agents.
+pub struct Fleet {
+    agents: Vec<Arc<FleetAgent>>,
+    runner: Runner,
+}
+
+impl Fleet {
+    /// Load every `*.toml` in `dir` as an agent. Nothing is started yet.
+    pub fn load(dir: &Path, runner: Runner) -> Result<Self, FleetError> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "toml"))
+            .collect();
+        paths.sort();
+        if paths.is_empty() {
+            return Err(FleetError::Empty(dir.to_path_buf()));
+        }
+
+        let mut agents = Vec::new();
+        // Resource → agent holding it; two agents can't share a DB or bot
+        let mut claimed: HashMap<String, String> = HashMap::new();
+        for path in paths {
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(FleetError::InvalidName(name));
+            }
+            let config =
+                crate::config::load_config(Some(&path)).map_err(|source| FleetError::Config {
+                    path: path.clone(),
+                    source,
+                })?;
+
+            for resource in exclusive_resources(&config) {
+                if let Some(other) = claimed.insert(resource.clone(), name.clone()) {
+                    return Err(FleetError::Shared(other, name, resource));
+                }
+            }
+
+            let key = config.db_key().map_err(|source| FleetError::Config {
+                path: path.clone(),
+                source,
+            })?;
+            let db = Db::open_with_key(
+                &config.db_path(),
+                config.persistence.read_pool_size,
+                key.as_deref(),
+            )?;
+            let (events, _) = broadcast::channel(256);
+            agents.push(Arc::new(FleetAgent {
+                name,
+                config_path: path,
+                config: Arc::new(config),
+                db,
+                events,
+                lifecycle: Mutex::new(Lifecycle {
+                    state: AgentState::Stopped,
+                    started_at: None,
+                    starts: 0,
+                    error: None,
+                    stop: None,
+                    thread: None,
+                }),
+            }));
+        }
+        Ok(Self { agents, runner })
+    }
+
+    pub fn agents(&self) -> &[Arc<FleetAgent>] {
+        &self.agents
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Arc<FleetAgent>, FleetError> {
+        self.agents
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| FleetError::UnknownAgent(name.to_string()))
+    }
+
+    pub fn status(&self) -> Vec<AgentStatus> {
+        self.agents.iter().map(|a| a.status()).collect()
+    }
+
+    /// Start the agent on its own thread and runtime.
+    pub fn start(&self, name: &str) -> Result<(), FleetError> {
+        let agent = self.get(name)?.clone();
+        let mut lc = agent.lifecycle.lock().unwrap();
+        if lc.state == AgentState::Running {
+            return Err(FleetError::AlreadyRunning(name.to_string()));
+        }
+        // The previous run has ended; reap its thread
+        if let Some(thread) = lc.thread.take() {
+            let _ = thread.join();
+        }
+
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let runner = self.runner;
+        let path = agent.config_path.clone();
+        let events = agent.events.clone();
+        let thread_agent = agent.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("agent-{}", agent.name))
+            .spawn(move || {
+                let agent = thread_agent;
+                let runtime = match tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(AGENT_WORKER_THREADS)
+                    .thread_name(format!("agent-{}", agent.name))
+                    .enable_all()
+                    .build()
+                {
+                    Ok(rt) => rt,
+                    Err(e) => return agent.finished(Err(e.to_string())),
+                };
+                let span = tracing::info_span!("agent", name = %agent.name);
+                let result = runtime.block_on(
+                    async move {
+                        tokio::select! {
+                            result = runner(path, events) => result.map_err(|e| e.to_string()),
+                            _ = stop_rx => Ok(()),
+                        }
+                    }
+                    .instrument(span),
+                );
+                // Ends the adapters, scheduler and other tasks the agent spawned
+                runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+                agent.finished(result);
+            })?;
+
+        lc.state = AgentState::Running;
+        lc.started_at = Some(now_ms());
+        lc.starts += 1;
+        lc.error = None;
+        lc.stop = Some(stop_tx);
+        lc.thread = Some(thread);
+        tracing::info!("Agent '{}' started", agent.name);
+        Ok(())
+    }
+
+    /// Start every agent. Agents that fail to start are logged and left stopped.
+    pub fn start_all(&self) {
+        for agent in &self.agents {
+            if let Err(e) = self.start(&agent.name) {
+                tracing::error!("Failed to start agent '{}': {}", agent.name, e);
+            }
+        }
+    }
+
+    /// Stop the agent and wait for its runtime to shut down.
+    pub async fn stop(&self, name: &str) -> Result<(), FleetError> {
+        let agent = self.get(name)?;
+        let (stop, thread) = {
+            let mut lc = agent.lifecycle.lock().unwrap();
+            if lc.state != AgentState::Running {
+                return Err(FleetError::NotRunning(name.to_string()));
+            }
+            (lc.stop.take(), lc.thread.take())
+        };
+        if let Some(stop) = stop {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = thread {
+            let _ = tokio::task::spawn_blocking(move || thread.join()).await;
+        }
+        Ok(())
+    }
+
+    /// Stop the agent if it is running, then start it again.
+    pub async fn restart(&self, name: &str) -> Result<(), FleetError> {
+        match self.stop(name).await {
+            Ok(()) | Err(FleetError::NotRunning(_)) => {}
+            Err(e) => return Err(e),
+        }
+        self.start(name)
+    }
+
+    /// Stop every running agent.
+    pub async fn stop_all(&self) {
+        for agent in &self.agents {
+            let _ = self.stop(&agent.name).await;
+        }
+    }
+}
+
+/// Resources only one agent may use: its database and its bot accounts.
+fn exclusive_resources(config: &Config) -> Vec<String> {
+    let mut resources = vec![format!("database {}", config.db_path().display())];
+    // Tokens stay out of the error message
+    if let Some(ref tg) = config.channels.telegram {
+        resources.push(format!("a Telegram bot ({})", short_hash(&tg.bot_token)));
+    }
+    if let Some(ref dc) = config.channels.discord {
+        resources.push(format!("a Discord bot ({})", short_hash(&dc.bot_token)));
+    }
+    if let Some(ref sl) = config.channels.slack {
+        resources.push(format!("a Slack app ({})", short_hash(&sl.app_token)));
+    }
+    resources
+}
+
+fn short_hash(secret: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .take(4)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_agent(dir: &Path, name: &str, db: &str, extra: &str) {
+        let db_path = dir.join(db);
+        std::fs::write(
+            dir.join(format!("{}.toml", name)),
+            format!(
+                "[agent]\nmodel = \"m\"\napi_key = \"k\"\n\n[persistence]\ndb_path = \"{}\"\n{}",
+                db_path.display(),
+                extra
+            ),
+        )
+        .unwrap();
+    }
+
+    fn wait_forever(
+        _: PathBuf,
+        _: broadcast::Sender<SseEvent>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), anyhow::Error>>>> {
+        Box::pin(std::future::pending())
+    }
+
+    fn fail(
+        path: PathBuf,
+        _: broadcast::Sender<SseEvent>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), anyhow::Error>>>> {
+        Box::pin(async move { Err(anyhow::anyhow!("cannot start {}", path.display())) })
+    }
+
+    async fn wait_for(fleet: &Fleet, name: &str, state: AgentState) -> AgentStatus {
+        for _ in 0..200 {
+            let status = fleet.get(name).unwrap().status();
+            if status.state == state {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("agent '{}' never reached {:?}", name, state);
+    }
+
+    #[test]
+    fn test_load_rejects_shared_resources() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            Fleet::load(dir.path(), wait_forever),
+            Err(FleetError::Empty(_))
+        ));
+
+        write_agent(dir.path(), "support", "support.db", "");
+        write_agent(dir.path(), "sales", "support.db", "");
+        let err = Fleet::load(dir.path(), wait_forever).err().unwrap();
+        assert!(
+            matches!(err, FleetError::Shared(ref a, ref b, _) if a == "sales" && b == "support")
+        );
+
+        write_agent(dir.path(), "sales", "sales.db", "");
+        let fleet = Fleet::load(dir.path(), wait_forever).unwrap();
+        let names: Vec<_> = fleet.agents().iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["sales", "support"]);
+
+        let bot = "\n[channels.telegram]\nbot_token = \"123:abc\"\n";
+        write_agent(dir.path(), "sales", "sales.db", bot);
+        write_agent(dir.path(), "support", "support.db", bot);
+        let err = Fleet::load(dir.path(), wait_forever).err().unwrap();
+        assert!(err.to_string().contains("Telegram bot"));
+        assert!(!err.to_string().contains("123:abc"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_agent_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        write_agent(dir.path(), "support", "support.db", "");
+        let fleet = Fleet::load(dir.path(), wait_forever).unwrap();
+
+        fleet.start_all();
+        assert_eq!(fleet.status()[0].state, AgentState::Running);
+        assert!(matches!(
+            fleet.start("support"),
+            Err(FleetError::AlreadyRunning(_))
+        ));
+
+        fleet.restart("support").await.unwrap();
+        let status = fleet.status().remove(0);
+        assert_eq!(status.state, AgentState::Running);
+        assert_eq!(status.starts, 2);
+
+        fleet.stop("support").await.unwrap();
+        wait_for(&fleet, "support", AgentState::Stopped).await;
+        assert!(matches!(
+            fleet.stop("support").await,
+            Err(FleetError::NotRunning(_))
+        ));
+        assert!(matches!(
+            fleet.start("nobody"),
+            Err(FleetError::UnknownAgent(_))
+        ));
+
+        let failing = Fleet::load(dir.path(), fail).unwrap();
+        failing.start("support").unwrap();
+        let status = wait_for(&failing, "support", AgentState::Failed).await;
+        assert!(status.error.unwrap().contains("cannot start"));
+        // A failed agent can be started again
+        failing.start("support").unwrap();
+    }
+}
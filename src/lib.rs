@@ -1,10 +1,37 @@
+pub mod admin;
+pub mod attachments;
+pub mod backup;
+pub mod bench;
 pub mod channels;
 pub mod conductor;
 pub mod config;
 pub mod db;
+pub mod debug;
+pub mod drafts;
+pub mod email;
+pub mod events;
+pub mod fleet;
+pub mod mcp_server;
+pub mod memory_review;
 pub mod migrate;
+pub mod onboarding;
+pub mod pricing;
+pub mod projects;
+pub mod reactions;
+pub mod remember;
+pub mod repl;
+pub mod retry;
+pub mod routing;
 pub mod scheduler;
+pub mod secrets;
 pub mod security;
+pub mod selftest;
 pub mod skills;
+pub mod stats;
+pub mod suggestions;
+pub mod templates;
+pub mod transcript;
+pub mod transcription;
 pub mod watcher;
 pub mod web;
+pub mod webhook;
@@ -1,9 +1,10 @@
-use clap::{Parser, Subcommand};
-use std::collections::HashMap;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use std::sync::Arc;
 use std::time::Duration;
 use yoclaw::channels::ChannelAdapter;
 
+mod cli;
+
 #[derive(Parser)]
 #[command(
     name = "yoclaw",
@@ -19,6 +20,13 @@ struct Cli {
     command: Option<Commands>,
 }
 
+/// How inspect and list commands print their results.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Show queue state, recent sessions, and token usage
@@ -32,301 +40,856 @@ enum Commands {
         /// Show configured workers
         #[arg(long)]
         workers: bool,
+        /// Show turns, tokens, cost, latency and tool usage for --session
+        #[arg(long, requires = "session")]
+        stats: bool,
+        /// Show recent worker runs with their transcripts (of --session, if given)
+        #[arg(long)]
+        worker_runs: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Manage cron jobs
+    Cron {
+        #[command(subcommand)]
+        command: CronCommands,
+    },
+    /// List, search, add, edit and delete long-term memories
+    Memory {
+        #[command(subcommand)]
+        command: MemoryCommands,
+    },
+    /// List, show, export, rename and delete session tapes
+    Sessions {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+    /// Work with the message queue
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommands,
+    },
+    /// Export a session transcript as HTML, PDF, Markdown or JSON
+    Export {
+        /// Session ID to export
+        #[arg(short, long)]
+        session: String,
+        /// Output format: html, pdf, markdown or json
+        #[arg(short, long, default_value = "html")]
+        format: String,
+        /// Output file (default: <session>.<format> in the current directory)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Back up or restore config, database, persona, skills and uploads
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+    /// Manage secrets in the OS keyring, used as "keyring:<name>" config values
+    Secrets {
+        #[command(subcommand)]
+        command: SecretsCommands,
+    },
+    /// Preview the system prompt
+    Persona {
+        #[command(subcommand)]
+        command: PersonaCommands,
+    },
+    /// Talk to the agent in the terminal, without a channel
+    Chat {
+        /// Session to talk in
+        #[arg(short, long, default_value = yoclaw::repl::DEFAULT_SESSION)]
+        session: String,
+    },
+    /// Postmortem tools
+    Debug {
+        #[command(subcommand)]
+        command: DebugCommands,
+    },
+    /// Read-only mode: queue incoming messages but run no agent, cron job or background task
+    #[command(group(clap::ArgGroup::new("scope").required(true).args(["all", "session"])))]
+    Pause {
+        /// Pause the whole daemon
+        #[arg(long)]
+        all: bool,
+        /// Pause one session
+        #[arg(short, long)]
+        session: Option<String>,
+        /// Why, shown by inspect and /admin status
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Leave read-only mode; queued messages are processed
+    #[command(group(clap::ArgGroup::new("scope").required(true).args(["all", "session"])))]
+    Resume {
+        /// Resume the whole daemon
+        #[arg(long)]
+        all: bool,
+        /// Resume one session
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+    /// Serve memory, cron jobs and sessions as MCP tools over stdio
+    McpServe,
+    /// Run several agents in one process, one per config file in a directory
+    Fleet {
+        /// Directory of agent configs (*.toml); each file is one agent, named after the file
+        #[arg(long)]
+        dir: std::path::PathBuf,
+        /// Address for the combined dashboard
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// Port for the combined dashboard
+        #[arg(long, default_value_t = 19898)]
+        port: u16,
+        /// Don't serve the combined dashboard
+        #[arg(long)]
+        no_web: bool,
+    },
+    /// Load-test the message pipeline with simulated channels and a mock model
+    Bench {
+        /// Concurrent channels, each its own session
+        #[arg(long, default_value_t = 8)]
+        channels: usize,
+        /// Messages each channel sends
+        #[arg(long, default_value_t = 25)]
+        messages: usize,
+        /// Tool calls per message
+        #[arg(long, default_value_t = 2)]
+        tool_calls: usize,
+        /// Conductors processing sessions in parallel
+        #[arg(long, default_value_t = 4)]
+        parallel_sessions: usize,
+        /// Milliseconds between a channel's messages
+        #[arg(long, default_value_t = 10)]
+        interval_ms: u64,
+        /// Coalescer debounce in milliseconds
+        #[arg(long, default_value_t = 0)]
+        debounce_ms: u64,
+        /// Database to write to (default: a temporary one, removed afterwards)
+        #[arg(long)]
+        db: Option<std::path::PathBuf>,
+        /// Report of an earlier run (--output json) to check for regressions
+        #[arg(long)]
+        baseline: Option<std::path::PathBuf>,
+        /// Percent worse than the baseline that counts as a regression
+        #[arg(long, default_value_t = 20.0)]
+        tolerance: f64,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
     },
     /// Initialize a new yoclaw config directory
-    Init,
+    Init {
+        /// Answer a few questions about yourself to seed the agent's memory
+        #[arg(long)]
+        interview: bool,
+        /// Allow shell, write_file and http without explicit grants
+        #[arg(long)]
+        permissive: bool,
+    },
     /// Migrate from an OpenClaw installation
     Migrate {
         /// Path to the OpenClaw data directory
         openclaw_dir: std::path::PathBuf,
     },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("yoclaw=info".parse().unwrap()),
-        )
-        .init();
+#[derive(Subcommand)]
+enum CronCommands {
+    /// List cron jobs
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+}
 
-    let cli = Cli::parse();
+/// Filters shared by `memory list` and `memory search`.
+#[derive(Args)]
+struct MemoryFilterArgs {
+    /// Only memories of this category (fact, preference, task, ...)
+    #[arg(long)]
+    category: Option<String>,
+    /// Only memories at least this important (1-10)
+    #[arg(long)]
+    min_importance: Option<i32>,
+    /// Only memories with this tag
+    #[arg(long)]
+    tag: Option<String>,
+}
 
-    match cli.command {
-        Some(Commands::Init) => run_init(cli.config.as_deref()),
-        Some(Commands::Inspect {
-            session,
-            skills,
-            workers,
-        }) => run_inspect(cli.config.as_deref(), session, skills, workers).await,
-        Some(Commands::Migrate { openclaw_dir }) => yoclaw::migrate::run_migrate(&openclaw_dir),
-        None => run_main(cli.config.as_deref()).await,
+impl MemoryFilterArgs {
+    fn into_filter(self) -> yoclaw::db::memory::MemoryFilter {
+        yoclaw::db::memory::MemoryFilter {
+            category: self.category,
+            min_importance: self.min_importance,
+            tag: self.tag,
+        }
     }
 }
 
-// ---------------------------------------------------------------------------
-// Init
-// ---------------------------------------------------------------------------
+#[derive(Subcommand)]
+enum MemoryCommands {
+    /// List the most recently updated memories
+    List {
+        /// Maximum number of memories
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+        #[command(flatten)]
+        filter: MemoryFilterArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Search memories the way the agent does, best match first
+    Search {
+        /// Words to look for
+        query: String,
+        /// Maximum number of memories
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+        #[command(flatten)]
+        filter: MemoryFilterArgs,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Store a memory
+    Add {
+        /// What to remember
+        content: String,
+        /// Unique key; a memory with the same key is replaced
+        #[arg(long)]
+        key: Option<String>,
+        /// Category: fact, preference, decision, event, task or reflection
+        #[arg(long, default_value = "fact")]
+        category: String,
+        /// Importance, 1-10
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(i32).range(1..=10))]
+        importance: i32,
+        /// Comma-separated tags
+        #[arg(long)]
+        tags: Option<String>,
+    },
+    /// Change a memory's content, category, importance or tags
+    Edit {
+        /// Memory ID, as shown by list and search
+        id: i64,
+        /// New content
+        #[arg(long)]
+        content: Option<String>,
+        /// New category
+        #[arg(long)]
+        category: Option<String>,
+        /// New importance, 1-10
+        #[arg(long, value_parser = clap::value_parser!(i32).range(1..=10))]
+        importance: Option<i32>,
+        /// New comma-separated tags
+        #[arg(long)]
+        tags: Option<String>,
+    },
+    /// Delete memories
+    Delete {
+        /// Memory IDs, as shown by list and search
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+}
 
-fn run_init(config_override: Option<&std::path::Path>) -> anyhow::Result<()> {
-    let dir = match config_override {
-        Some(p) => p
-            .parent()
-            .map(|d| d.to_path_buf())
-            .unwrap_or_else(yoclaw::config::config_dir),
-        None => yoclaw::config::config_dir(),
-    };
-    std::fs::create_dir_all(&dir)?;
-    std::fs::create_dir_all(dir.join("skills"))?;
+#[derive(Subcommand)]
+enum SessionCommands {
+    /// List sessions with a tape, most recently active first
+    List {
+        /// Only sessions idle at least this long, e.g. 30d
+        #[arg(long, value_parser = yoclaw::retry::parse_duration)]
+        idle: Option<Duration>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Show a session's latest messages, tool calls and results included
+    Show {
+        /// Session ID
+        session: String,
+        /// Number of messages
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+    /// Delete a session's tape, or every tape idle for a while
+    Delete {
+        /// Session ID
+        #[arg(required_unless_present = "idle", conflicts_with = "idle")]
+        session: Option<String>,
+        /// Delete all sessions idle at least this long, e.g. 90d
+        #[arg(long, value_parser = yoclaw::retry::parse_duration)]
+        idle: Option<Duration>,
+    },
+    /// Write a session's transcript to a file
+    Export {
+        /// Session ID
+        session: String,
+        /// Output format: markdown, json, html or pdf
+        #[arg(short, long, default_value = "markdown")]
+        format: String,
+        /// Output file (default: <session>.<format> in the current directory)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Move a session's tape to a new session ID
+    Rename {
+        /// Current session ID
+        session: String,
+        /// New session ID
+        new_id: String,
+    },
+}
 
-    let config_path = match config_override {
-        Some(p) => p.to_path_buf(),
-        None => dir.join("config.toml"),
-    };
-    if !config_path.exists() {
-        std::fs::write(
-            &config_path,
-            r#"[agent]
-provider = "anthropic"
-model = "claude-sonnet-4-20250514"
-api_key = "${ANTHROPIC_API_KEY}"
-
-[agent.budget]
-max_tokens_per_day = 1_000_000
-max_turns_per_session = 50
-
-[channels.telegram]
-bot_token = "${TELEGRAM_BOT_TOKEN}"
-allowed_senders = []
-debounce_ms = 2000
-
-[security]
-shell_deny_patterns = ["rm -rf", "sudo", "chmod 777"]
-"#,
-        )?;
-        println!("Created {}", config_path.display());
-    } else {
-        println!("Config already exists: {}", config_path.display());
-    }
+#[derive(Subcommand)]
+enum QueueCommands {
+    /// Process the messages that failed recently again, oldest first
+    RetryFailed {
+        /// How far back to look, e.g. 30m, 2h or 1d
+        #[arg(long, value_parser = yoclaw::retry::parse_duration)]
+        since: Duration,
+        /// Time between two retries, e.g. 5s or 1m
+        #[arg(long, value_parser = yoclaw::retry::parse_duration, default_value = "10s")]
+        pace: Duration,
+    },
+}
 
-    let persona_path = dir.join("persona.md");
-    if !persona_path.exists() {
-        std::fs::write(
-            &persona_path,
-            "You are a helpful AI assistant. Be concise and clear in your responses.\n",
-        )?;
-        println!("Created {}", persona_path.display());
-    }
+#[derive(Subcommand)]
+enum PersonaCommands {
+    /// Print the system prompt as assembled from persona fragments (or the persona file) and skills
+    Render {
+        /// Include the fragments for this channel (telegram, discord, slack, ...)
+        #[arg(long, conflicts_with = "session")]
+        channel: Option<String>,
+        /// Render for this session: its channel and selected project
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+}
 
-    println!("yoclaw initialized at {}", dir.display());
-    Ok(())
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// Write a backup archive (.tar.gz)
+    Create {
+        /// Archive to write
+        file: std::path::PathBuf,
+        /// Replace API keys, tokens and secrets in the config with placeholders
+        #[arg(long)]
+        redact: bool,
+    },
+    /// Write a consistent copy of the database, safe while yoclaw runs
+    Db {
+        /// File to write (default: yoclaw-<time>.db in the backup directory)
+        file: Option<std::path::PathBuf>,
+    },
+    /// Restore a backup archive or database copy
+    Restore {
+        /// Archive or database copy to read
+        file: std::path::PathBuf,
+        /// Overwrite an existing config and database
+        #[arg(long)]
+        force: bool,
+    },
+    /// Encrypt the existing database with the [persistence.encryption] key (stop yoclaw first)
+    Encrypt,
 }
 
-// ---------------------------------------------------------------------------
-// Inspect
-// ---------------------------------------------------------------------------
+#[derive(Subcommand)]
+enum SecretsCommands {
+    /// Store a secret, read from stdin
+    Set {
+        /// <service>/<name>, or <name> for the yoclaw service
+        name: String,
+    },
+    /// Print a secret
+    Get {
+        /// <service>/<name>, or <name> for the yoclaw service
+        name: String,
+    },
+    /// Delete a secret
+    Rm {
+        /// <service>/<name>, or <name> for the yoclaw service
+        name: String,
+    },
+}
 
-async fn run_inspect(
-    config_path: Option<&std::path::Path>,
-    session_filter: Option<String>,
-    show_skills: bool,
-    show_workers: bool,
-) -> anyhow::Result<()> {
-    let config = yoclaw::config::load_config(config_path)?;
-    let db = yoclaw::db::Db::open(&config.db_path())?;
-
-    // Skills info
-    if show_skills {
-        let skills_dirs = config.skills_dirs();
-        let skills_refs: Vec<&std::path::Path> = skills_dirs.iter().map(|p| p.as_path()).collect();
-        let policy = yoclaw::security::SecurityPolicy::from_config(&config.security);
-        let (_prompt, loaded) = yoclaw::skills::load_filtered_skills(&skills_refs, &policy);
-
-        println!("=== Skills ({}) ===", loaded.len());
-        println!("{}", yoclaw::skills::format_skills_info(&loaded));
-        println!();
-    }
+#[derive(Subcommand)]
+enum DebugCommands {
+    /// Show what the agent saw on a turn: system prompt, context, tools and filters
+    Turn {
+        /// Session ID
+        #[arg(short, long)]
+        session: String,
+        /// Turn number (1-based); lists the recorded turns if omitted
+        #[arg(short, long)]
+        turn: Option<u32>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+}
 
-    // Workers info
-    if show_workers {
-        let worker_tools: Vec<std::sync::Arc<dyn yoagent::AgentTool>> = Vec::new();
-        let workers = yoclaw::conductor::delegate::build_workers(&config, &worker_tools);
-        let infos: Vec<_> = workers.into_iter().map(|(_, info)| info).collect();
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
 
-        println!("=== Workers ({}) ===", infos.len());
-        println!(
-            "{}",
-            yoclaw::conductor::delegate::format_workers_info(&infos)
-        );
-        println!();
-    }
+    // Keep the chat prompt readable: only warnings unless RUST_LOG says otherwise
+    let level = match cli.command {
+        Some(Commands::Chat { .. } | Commands::Bench { .. }) => "yoclaw=warn",
+        _ => "yoclaw=info",
+    };
+    // stdout carries the MCP protocol, so mcp-serve logs to stderr
+    let to_stderr = matches!(cli.command, Some(Commands::McpServe));
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env().add_directive(level.parse().unwrap()),
+        )
+        .with_writer(move || -> Box<dyn std::io::Write> {
+            if to_stderr {
+                Box::new(std::io::stderr())
+            } else {
+                Box::new(std::io::stdout())
+            }
+        })
+        .init();
 
-    // Always show queue, sessions, budget, audit
-    let pending = db.queue_pending_count().await?;
-    println!("=== Queue ===");
-    println!("Pending messages: {}", pending);
-    println!();
-
-    // Sessions
-    let sessions = db.tape_list_sessions().await?;
-    println!("=== Sessions ({}) ===", sessions.len());
-    for s in &sessions {
-        let updated = chrono::DateTime::from_timestamp_millis(s.updated_at as i64)
-            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
-            .unwrap_or_else(|| "unknown".to_string());
-        println!(
-            "  {} — {} messages, last updated {}",
-            s.session_id, s.message_count, updated
-        );
-    }
-    println!();
-
-    // Token usage
-    let tokens_today = db.audit_token_usage_today().await?;
-    println!("=== Budget ===");
-    println!("Tokens used today: {}", tokens_today);
-    if let Some(max) = config.agent.budget.max_tokens_per_day {
-        println!("Daily limit: {}", max);
-        println!("Remaining: {}", max.saturating_sub(tokens_today));
-    }
-    println!();
-
-    // Audit log (recent or filtered)
-    let audit = db.audit_query(session_filter.as_deref(), 20).await?;
-    if !audit.is_empty() {
-        println!("=== Recent Audit ({}) ===", audit.len());
-        for entry in &audit {
-            let ts = chrono::DateTime::from_timestamp_millis(entry.timestamp as i64)
-                .map(|dt| dt.format("%H:%M:%S").to_string())
-                .unwrap_or_else(|| "?".to_string());
-            println!(
-                "  [{}] {} {} {}",
-                ts,
-                entry.event_type,
-                entry.tool_name.as_deref().unwrap_or(""),
-                entry
-                    .detail
-                    .as_ref()
-                    .map(|d| {
-                        if d.len() > 60 {
-                            format!("{}...", &d[..60])
-                        } else {
-                            d.clone()
-                        }
-                    })
-                    .unwrap_or_default()
-            );
+    match cli.command {
+        Some(Commands::Init {
+            interview,
+            permissive,
+        }) => cli::init::run_init(cli.config.as_deref(), interview, permissive).await,
+        Some(Commands::Inspect {
+            session,
+            skills,
+            workers,
+            stats,
+            worker_runs,
+            output,
+        }) => {
+            cli::inspect::run_inspect(
+                cli.config.as_deref(),
+                session,
+                skills,
+                workers,
+                stats,
+                worker_runs,
+                output,
+            )
+            .await
+        }
+        Some(Commands::Cron {
+            command: CronCommands::List { output },
+        }) => cli::cron::run_cron_list(cli.config.as_deref(), output).await,
+        Some(Commands::Memory {
+            command:
+                MemoryCommands::List {
+                    limit,
+                    filter,
+                    output,
+                },
+        }) => {
+            cli::memory::run_memory_list(cli.config.as_deref(), limit, filter.into_filter(), output)
+                .await
+        }
+        Some(Commands::Memory {
+            command:
+                MemoryCommands::Search {
+                    query,
+                    limit,
+                    filter,
+                    output,
+                },
+        }) => {
+            cli::memory::run_memory_search(
+                cli.config.as_deref(),
+                &query,
+                limit,
+                filter.into_filter(),
+                output,
+            )
+            .await
+        }
+        Some(Commands::Memory {
+            command:
+                MemoryCommands::Add {
+                    content,
+                    key,
+                    category,
+                    importance,
+                    tags,
+                },
+        }) => {
+            cli::memory::run_memory_add(
+                cli.config.as_deref(),
+                &content,
+                key.as_deref(),
+                &category,
+                importance,
+                tags.as_deref(),
+            )
+            .await
+        }
+        Some(Commands::Memory {
+            command:
+                MemoryCommands::Edit {
+                    id,
+                    content,
+                    category,
+                    importance,
+                    tags,
+                },
+        }) => {
+            let edit = yoclaw::db::memory::MemoryEdit {
+                content,
+                category,
+                importance,
+                tags,
+            };
+            cli::memory::run_memory_edit(cli.config.as_deref(), id, edit).await
+        }
+        Some(Commands::Memory {
+            command: MemoryCommands::Delete { ids },
+        }) => cli::memory::run_memory_delete(cli.config.as_deref(), &ids).await,
+        Some(Commands::Sessions {
+            command: SessionCommands::List { idle, output },
+        }) => cli::sessions::run_sessions_list(cli.config.as_deref(), idle, output).await,
+        Some(Commands::Sessions {
+            command:
+                SessionCommands::Show {
+                    session,
+                    limit,
+                    output,
+                },
+        }) => {
+            cli::sessions::run_sessions_show(cli.config.as_deref(), &session, limit, output).await
+        }
+        Some(Commands::Sessions {
+            command: SessionCommands::Delete { session, idle },
+        }) => {
+            cli::sessions::run_sessions_delete(cli.config.as_deref(), session.as_deref(), idle)
+                .await
+        }
+        Some(Commands::Sessions {
+            command:
+                SessionCommands::Export {
+                    session,
+                    format,
+                    output,
+                },
+        }) => cli::export::run_export(cli.config.as_deref(), &session, &format, output).await,
+        Some(Commands::Sessions {
+            command: SessionCommands::Rename { session, new_id },
+        }) => cli::sessions::run_sessions_rename(cli.config.as_deref(), &session, &new_id).await,
+        Some(Commands::Queue {
+            command: QueueCommands::RetryFailed { since, pace },
+        }) => cli::pause::run_queue_retry_failed(cli.config.as_deref(), since, pace).await,
+        Some(Commands::Export {
+            session,
+            format,
+            output,
+        }) => cli::export::run_export(cli.config.as_deref(), &session, &format, output).await,
+        Some(Commands::Backup {
+            command: BackupCommands::Create { file, redact },
+        }) => cli::backup::run_backup_create(cli.config.as_deref(), &file, redact).await,
+        Some(Commands::Backup {
+            command: BackupCommands::Db { file },
+        }) => cli::backup::run_backup_db(cli.config.as_deref(), file).await,
+        Some(Commands::Backup {
+            command: BackupCommands::Restore { file, force },
+        }) => cli::backup::run_backup_restore(cli.config.as_deref(), &file, force),
+        Some(Commands::Backup {
+            command: BackupCommands::Encrypt,
+        }) => cli::backup::run_backup_encrypt(cli.config.as_deref()),
+        Some(Commands::Secrets { command }) => cli::secrets::run_secrets(command),
+        Some(Commands::Persona {
+            command: PersonaCommands::Render { channel, session },
+        }) => {
+            cli::persona::run_persona_render(cli.config.as_deref(), channel, session.as_deref())
+                .await
+        }
+        Some(Commands::Chat { session }) => {
+            cli::chat::run_chat(cli.config.as_deref(), session).await
+        }
+        Some(Commands::Debug {
+            command:
+                DebugCommands::Turn {
+                    session,
+                    turn,
+                    output,
+                },
+        }) => cli::debug::run_debug_turn(cli.config.as_deref(), &session, turn, output).await,
+        Some(Commands::Pause {
+            all: _,
+            session,
+            reason,
+        }) => {
+            cli::pause::run_pause(cli.config.as_deref(), session.as_deref(), reason.as_deref())
+                .await
+        }
+        Some(Commands::Resume { all: _, session }) => {
+            cli::pause::run_resume(cli.config.as_deref(), session.as_deref()).await
+        }
+        Some(Commands::McpServe) => cli::mcp::run_mcp_serve(cli.config.as_deref()).await,
+        Some(Commands::Fleet {
+            dir,
+            bind,
+            port,
+            no_web,
+        }) => cli::fleet::run_fleet(&dir, &bind, port, no_web).await,
+        Some(Commands::Bench {
+            channels,
+            messages,
+            tool_calls,
+            parallel_sessions,
+            interval_ms,
+            debounce_ms,
+            db,
+            baseline,
+            tolerance,
+            output,
+        }) => {
+            let options = yoclaw::bench::BenchOptions {
+                channels,
+                messages,
+                tool_calls,
+                parallel_sessions,
+                interval: std::time::Duration::from_millis(interval_ms),
+                debounce: std::time::Duration::from_millis(debounce_ms),
+            };
+            cli::bench::run_bench(options, db, baseline.as_deref(), tolerance, output).await
         }
+        Some(Commands::Migrate { openclaw_dir }) => yoclaw::migrate::run_migrate(&openclaw_dir),
+        Some(Commands::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "yoclaw", &mut std::io::stdout());
+            Ok(())
+        }
+        None => run_main(cli.config.as_deref(), None).await,
     }
-
-    Ok(())
 }
 
 // ---------------------------------------------------------------------------
 // Main loop
 // ---------------------------------------------------------------------------
 
-async fn run_main(config_path: Option<&std::path::Path>) -> anyhow::Result<()> {
+/// Run the daemon. Under `yoclaw fleet`, `fleet_events` is the agent's channel
+/// to the combined dashboard, which replaces the agent's own web server.
+async fn run_main(
+    config_path: Option<&std::path::Path>,
+    fleet_events: Option<tokio::sync::broadcast::Sender<yoclaw::web::SseEvent>>,
+) -> anyhow::Result<()> {
     let config_file_path = match config_path {
         Some(p) => p.to_path_buf(),
         None => yoclaw::config::config_dir().join("config.toml"),
     };
-    let config = yoclaw::config::load_config(config_path)?;
+    let mut config = yoclaw::config::load_config(config_path)?;
     let db_path = config.db_path();
-    let db = yoclaw::db::Db::open(&db_path)?;
+    let db = cli::open_db(&config)?.with_memory_cache(
+        config.persistence.memory_cache_size,
+        Duration::from_secs(config.persistence.memory_cache_ttl_secs),
+    );
 
     tracing::info!("Database: {}", db_path.display());
 
+    // Settings changed via /admin take precedence over config.toml
+    let overrides = db.override_list().await?;
+    if !overrides.is_empty() {
+        tracing::info!("Applying {} admin override(s)", overrides.len());
+        yoclaw::admin::apply_overrides(&mut config, &overrides);
+    }
+
+    // Configs written for allow-by-default lose these tools silently otherwise
+    let ungranted = yoclaw::security::SecurityPolicy::ungranted(&config.security);
+    if !ungranted.is_empty() {
+        tracing::warn!(
+            "Tools not granted, so disabled: {}. To use them, add [security.tools.<name>] enabled = true \
+             for each (or permissive = true under [security] to allow them without a grant, as before)",
+            ungranted.join(", ")
+        );
+    }
+
+    // Refuse to start on a setup that would only fail at the first message
+    let report = yoclaw::selftest::run(&config, &db).await?;
+    for check in report.warnings() {
+        tracing::warn!("Self-test: {}: {}", check.name, check.detail);
+    }
+    if report.failed() {
+        anyhow::bail!("{}", report);
+    }
+
     // Crash recovery: requeue stale messages
     let requeued = db.queue_requeue_stale().await?;
     if requeued > 0 {
         tracing::info!("Requeued {} messages from previous crash", requeued);
     }
 
-    // Build conductor
-    let mut conductor = yoclaw::conductor::Conductor::new(&config, db.clone()).await?;
-    tracing::info!("Conductor initialized");
+    // Build conductors, one per session processed at once
+    let conductors = yoclaw::conductor::pool::conductors(&config, db.clone()).await?;
+    tracing::info!("{} conductor(s) initialized", conductors.len());
+
+    let router = yoclaw::routing::Router::from_config(&config.routing)?;
+    if !router.is_empty() {
+        tracing::info!("Loaded {} routing rule(s)", router.len());
+    }
 
     // Channel adapters
     let (raw_tx, raw_rx) = tokio::sync::mpsc::unbounded_channel();
-    let (coalesced_tx, mut coalesced_rx) = tokio::sync::mpsc::unbounded_channel();
-
-    // Build per-channel debounce map
-    let mut channel_debounce: HashMap<String, Duration> = HashMap::new();
-    if let Some(ref tg) = config.channels.telegram {
-        channel_debounce.insert("telegram".into(), Duration::from_millis(tg.debounce_ms));
-    }
-    if let Some(ref dc) = config.channels.discord {
-        channel_debounce.insert("discord".into(), Duration::from_millis(dc.debounce_ms));
-    }
-    if let Some(ref sl) = config.channels.slack {
-        channel_debounce.insert("slack".into(), Duration::from_millis(sl.debounce_ms));
-    }
+    let (coalesced_tx, answers_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (incoming_tx, coalesced_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    // Answers to `ask_user` and approval decisions go straight to the waiting
+    // tool: the session's worker is blocked on the turn that asked
+    tokio::spawn(yoclaw::conductor::ask::forward_answers(
+        answers_rx,
+        incoming_tx,
+        conductors[0].questions(),
+        conductors[0].approvals(),
+    ));
 
     let coalescer = yoclaw::channels::coalesce::MessageCoalescer::new(
         Duration::from_secs(2),
         raw_rx,
         coalesced_tx,
     )
-    .with_channel_debounce(channel_debounce);
+    .with_channel_debounce(config.channels.debounce());
     let shared_debounce = coalescer.shared_debounce();
     tokio::spawn(coalescer.run());
 
-    // Collect adapters for sending responses (Arc for sharing with scheduler delivery)
-    let mut adapters: Vec<Arc<dyn yoclaw::channels::ChannelAdapter>> = Vec::new();
-
-    if let Some(tg_config) = config.channels.telegram.clone() {
-        let adapter = yoclaw::channels::telegram::TelegramAdapter::new(tg_config);
-        adapter.start(raw_tx.clone()).await?;
-        adapters.push(Arc::new(adapter));
+    // Web UI events; under `yoclaw fleet` they go to the fleet's dashboard
+    let in_fleet = fleet_events.is_some();
+    let sse_tx = fleet_events
+        .unwrap_or_else(|| tokio::sync::broadcast::channel::<yoclaw::web::SseEvent>(256).0);
+
+    // Adapters for sending responses, shared with scheduler delivery and
+    // replaced on config reload
+    let adapters = yoclaw::channels::registry::Adapters::default();
+    for &channel in yoclaw::channels::registry::RELOADABLE {
+        if let Some(adapter) = yoclaw::channels::registry::build(&config, channel) {
+            adapter.start(raw_tx.clone()).await?;
+            adapters.insert(adapter);
+        }
     }
 
-    if let Some(dc_config) = config.channels.discord.clone() {
-        let adapter = yoclaw::channels::discord::DiscordAdapter::new(dc_config);
+    // Messages from other systems come in through the web server
+    let mut webhook_inbox = None;
+    if let Some(wh_config) = config.channels.webhook.clone() {
+        if !config.web.enabled || in_fleet {
+            anyhow::bail!("[channels.webhook] needs the agent's own web server: set [web] enabled = true (not available under `yoclaw fleet`).");
+        }
+        let adapter =
+            yoclaw::channels::webhook::WebhookAdapter::new(wh_config, config.webhooks.clone());
         adapter.start(raw_tx.clone()).await?;
-        adapters.push(Arc::new(adapter));
+        webhook_inbox = Some(adapter.inbox());
+        adapters.insert(Arc::new(adapter));
     }
 
-    if let Some(sl_config) = config.channels.slack.clone() {
-        let adapter = yoclaw::channels::slack::SlackAdapter::new(sl_config);
+    // ...and so does the web UI's chat box
+    let mut web_chat_inbox = None;
+    if let Some(web_config) = config.channels.web.clone() {
+        if !config.web.enabled || in_fleet {
+            anyhow::bail!("[channels.web] needs the agent's own web server: set [web] enabled = true (not available under `yoclaw fleet`).");
+        }
+        let adapter = yoclaw::channels::web::WebChatAdapter::new(web_config, sse_tx.clone());
         adapter.start(raw_tx.clone()).await?;
-        adapters.push(Arc::new(adapter));
+        web_chat_inbox = Some(adapter.inbox());
+        adapters.insert(Arc::new(adapter));
     }
 
     if adapters.is_empty() {
-        anyhow::bail!("No channels configured. Add [channels.telegram], [channels.discord], or [channels.slack] to config.toml.");
+        anyhow::bail!("No channels configured. Add [channels.telegram], [channels.discord], [channels.slack], [channels.signal], [channels.webhook] or [channels.web] to config.toml.");
+    }
+
+    // Outbound sends are retried and recorded in the deliveries log
+    let deliverer =
+        yoclaw::channels::delivery::Deliverer::new(db.clone()).with_events(sse_tx.clone());
+
+    // Crash recovery: replies saved to the tape but maybe never sent
+    yoclaw::conductor::daemon::deliver_answered(&db, &adapters, &deliverer).await;
+
+    // Push notifications go to browsers subscribed from whichever dashboard is served
+    if config.web.enabled || in_fleet {
+        if let Some(push) = config.web.push.clone() {
+            let notifier = yoclaw::web::push::PushNotifier::new(
+                db.clone(),
+                push,
+                config.agent.budget.max_tokens_per_day,
+            );
+            tokio::spawn(notifier.run());
+        }
     }
 
-    // Web UI
-    let (sse_tx, _) = tokio::sync::broadcast::channel::<yoclaw::web::SseEvent>(256);
-    let sse_tx_clone = sse_tx.clone();
+    // Lifecycle events go to the [webhooks] targets that subscribe to them
+    if let Some(notifier) = yoclaw::events::EventNotifier::new(
+        db.clone(),
+        &config.webhooks,
+        config.agent.budget.max_tokens_per_day,
+    ) {
+        tokio::spawn(notifier.run());
+    }
 
-    if config.web.enabled {
+    if config.web.enabled && !in_fleet {
         let web_db = db.clone();
         let web_sse_tx = sse_tx.clone();
         // Scheduler needs &config below, so build Arc separately for the web server
         let web_config = Arc::new(yoclaw::config::load_config(config_path)?);
         tokio::spawn(async move {
-            if let Err(e) = yoclaw::web::start_server(web_db, web_config, web_sse_tx).await {
+            if let Err(e) = yoclaw::web::start_server(
+                web_db,
+                web_config,
+                web_sse_tx,
+                webhook_inbox,
+                web_chat_inbox,
+            )
+            .await
+            {
                 tracing::error!("Web server error: {}", e);
             }
         });
     }
 
-    // Scheduler
-    if config.scheduler.enabled {
-        // Create a delivery channel for cron job results
+    // Private session tapes expire whether or not the scheduler runs
+    tokio::spawn(yoclaw::scheduler::run_privacy_purge(
+        db.clone(),
+        Duration::from_secs(config.security.privacy.tape_ttl_hours * 3600),
+    ));
+
+    // Scheduler and background tasks
+    if config.scheduler.enabled || config.background.enabled {
+        // Create a delivery channel for cron job and background task results
         let (delivery_tx, mut delivery_rx) =
             tokio::sync::mpsc::unbounded_channel::<yoclaw::channels::OutgoingMessage>();
 
-        let scheduler = yoclaw::scheduler::Scheduler::new(db.clone(), &config, Some(delivery_tx));
-        tokio::spawn(async move {
-            scheduler.run().await;
-        });
+        if config.scheduler.enabled {
+            let scheduler =
+                yoclaw::scheduler::Scheduler::new(db.clone(), &config, Some(delivery_tx.clone()));
+            tokio::spawn(async move {
+                scheduler.run().await;
+            });
+        }
+        if config.background.enabled {
+            yoclaw::scheduler::background::BackgroundRunner::new(db.clone(), &config, delivery_tx)
+                .start()
+                .await;
+        }
 
-        // Route scheduler deliveries to channel adapters
+        // Route scheduler deliveries to channel adapters, webhook and email targets
         let delivery_adapters = adapters.clone();
+        let webhooks = yoclaw::webhook::WebhookSender::new(config.webhooks.clone());
+        let email = yoclaw::email::EmailSender::new(config.channels.email.clone());
+        let deliverer = deliverer.clone();
         tokio::spawn(async move {
             while let Some(outgoing) = delivery_rx.recv().await {
                 tracing::info!(
@@ -338,235 +901,94 @@ async fn run_main(config_path: Option<&std::path::Path>) -> anyhow::Result<()> {
                         outgoing.content.clone()
                     }
                 );
-                for adapter in &delivery_adapters {
-                    if adapter.name() == outgoing.channel {
-                        if let Err(e) = adapter.send(outgoing.clone()).await {
-                            tracing::error!("Scheduler delivery error: {}", e);
-                        }
-                        break;
-                    }
+                // `webhook:<name>` targets; `wh-` sessions belong to the webhook channel
+                if outgoing
+                    .session_id
+                    .starts_with(yoclaw::webhook::TARGET_PREFIX)
+                {
+                    deliverer
+                        .track(
+                            "webhook",
+                            &outgoing.session_id,
+                            "send",
+                            &outgoing.content,
+                            || async {
+                                webhooks.send(&outgoing).await.map_err(anyhow::Error::from)
+                            },
+                        )
+                        .await;
+                    continue;
+                }
+                if yoclaw::email::is_target(&outgoing.session_id) {
+                    deliverer
+                        .track(
+                            "email",
+                            &outgoing.session_id,
+                            "send",
+                            &outgoing.content,
+                            || async { email.send(&outgoing).await.map_err(anyhow::Error::from) },
+                        )
+                        .await;
+                    continue;
+                }
+                if let Some(adapter) = delivery_adapters.get(&outgoing.channel) {
+                    deliverer.send(adapter.as_ref(), outgoing).await;
                 }
             }
         });
     }
 
-    // Ctrl+C handler: first signal logs + exits cleanly, second forces exit
-    tokio::spawn(async {
-        let _ = tokio::signal::ctrl_c().await;
-        tracing::info!("Shutting down...");
-        // Give a moment for cleanup, then force exit
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        std::process::exit(0);
-    });
-
-    // Config hot-reload watcher (polls every 5 seconds)
-    let mut config_watcher = yoclaw::watcher::ConfigWatcher::new(config_file_path);
-    let mut current_config = config;
-    let mut reload_interval = tokio::time::interval(Duration::from_secs(5));
-    reload_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-
-    tracing::info!("yoclaw running. Waiting for messages...");
-
-    // Process loop
-    loop {
-        tokio::select! {
-            // Config hot-reload poll
-            _ = reload_interval.tick() => {
-                if let Some(new_config) = config_watcher.check() {
-                    let diff = yoclaw::watcher::diff_configs(&current_config, &new_config);
-                    yoclaw::watcher::apply_hot_reload(&diff, &new_config, &mut conductor, &shared_debounce);
-                    current_config = new_config;
-                }
-                continue;
-            }
-            // Incoming message
-            msg = coalesced_rx.recv() => {
-                let incoming = match msg {
-                    Some(m) => m,
-                    None => break, // channel closed
-                };
-
-        let queue_entry = yoclaw::db::queue::QueueEntry::new(
-            &incoming.channel,
-            &incoming.sender_id,
-            &incoming.session_id,
-            &incoming.content,
-        );
-        let queue_id = db.queue_push(&queue_entry).await?;
-
-        tracing::info!(
-            "[{}] {} ({}): {}",
-            incoming.channel,
-            incoming.sender_name.as_deref().unwrap_or("unknown"),
-            incoming.session_id,
-            truncate(&incoming.content, 80)
-        );
-
-        // Find the adapter for this channel
-        let adapter = adapters
-            .iter()
-            .find(|a| a.name() == incoming.channel)
-            .cloned();
-
-        // Start typing indicator
-        let typing_handle = adapter.as_ref().and_then(|a| a.start_typing(&incoming.session_id));
-
-        // Send a streaming placeholder message (skip for worker delegations — no streaming)
-        let placeholder = if incoming.worker_hint.is_none() {
-            if let Some(ref adapter) = adapter {
-                adapter.send_placeholder(&incoming.session_id, "...").await
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        // Build debounced on_chunk callback for streaming edits
-        let on_chunk: Option<yoclaw::conductor::OnStreamChunk> = {
-            if let (Some(ref ph), Some(ref adapter)) = (&placeholder, &adapter) {
-                let ph = ph.clone();
-                let adapter = adapter.clone();
-                // Get stream debounce from current config
-                let debounce_ms = match incoming.channel.as_str() {
-                    "telegram" => current_config.channels.telegram.as_ref().map(|c| c.stream_debounce_ms).unwrap_or(300),
-                    "discord" => current_config.channels.discord.as_ref().map(|c| c.stream_debounce_ms).unwrap_or(300),
-                    "slack" => current_config.channels.slack.as_ref().map(|c| c.stream_debounce_ms).unwrap_or(300),
-                    _ => 300,
-                };
-                let debounce = Duration::from_millis(debounce_ms);
-                let last_edit = Arc::new(std::sync::Mutex::new(std::time::Instant::now() - debounce));
-                // Also emit SSE events for web UI streaming
-                let sse_tx = sse_tx_clone.clone();
-                let sse_session = incoming.session_id.clone();
-                let sse_channel = incoming.channel.clone();
-
-                Some(Box::new(move |accumulated: &str| {
-                    let mut last = last_edit.lock().unwrap();
-                    if last.elapsed() >= debounce {
-                        *last = std::time::Instant::now();
-                        let ph = ph.clone();
-                        let adapter = adapter.clone();
-                        let text = accumulated.to_string();
-                        tokio::spawn(async move {
-                            let _ = adapter.edit_message(&ph, &text).await;
-                        });
-                    }
-                    // Emit SSE stream chunk
-                    let _ = sse_tx.send(yoclaw::web::SseEvent::StreamChunk {
-                        session_id: sse_session.clone(),
-                        channel: sse_channel.clone(),
-                        text: accumulated.to_string(),
-                    });
-                }) as yoclaw::conductor::OnStreamChunk)
-            } else {
-                None
-            }
-        };
-
-        // Build progress callback to route send_message tool output to the channel
-        let on_progress: Option<Box<dyn Fn(String) + Send + Sync>> = {
-            if let Some(ref adapter) = adapter {
-                let adapter = adapter.clone();
-                let channel = incoming.channel.clone();
-                let session_id = incoming.session_id.clone();
-                Some(Box::new(move |text: String| {
-                    let outgoing = yoclaw::channels::OutgoingMessage {
-                        channel: channel.clone(),
-                        session_id: session_id.clone(),
-                        content: text,
-                        reply_to: None,
-                    };
-                    let adapter = adapter.clone();
-                    tokio::spawn(async move {
-                        let _ = adapter.send(outgoing).await;
-                    });
-                }))
-            } else {
-                None
-            }
-        };
-
-        let result = if let Some(ref worker_name) = incoming.worker_hint {
-            conductor
-                .delegate_to_worker(&incoming.session_id, worker_name, &incoming.content)
-                .await
-        } else if incoming.is_group {
-            conductor
-                .process_group_message(&incoming.session_id, &incoming.content, on_chunk, on_progress)
-                .await
-        } else {
-            conductor
-                .process_message(&incoming.session_id, &incoming.content, on_chunk, on_progress)
-                .await
-        };
-
-        // Stop typing indicator
-        if let Some(handle) = typing_handle {
-            handle.abort();
-        }
-
-        match result {
-            Ok(response) => {
-                tracing::info!("Response: {}", truncate(&response, 80));
-
-                // Final edit to ensure complete text if we had a placeholder
-                if let Some(ref ph) = placeholder {
-                    if let Some(ref adapter) = adapter {
-                        let _ = adapter.edit_message(ph, &response).await;
-                    }
-                } else {
-                    // No placeholder — send the full response as a new message
-                    let outgoing = yoclaw::channels::OutgoingMessage {
-                        channel: incoming.channel.clone(),
-                        session_id: incoming.session_id.clone(),
-                        content: response,
-                        reply_to: None,
-                    };
-
-                    if let Some(ref adapter) = adapter {
-                        if let Err(e) = adapter.send(outgoing).await {
-                            tracing::error!("Failed to send response: {}", e);
-                        }
-                    }
-                }
+    // Ctrl+C handler: first signal logs + exits cleanly, second forces exit.
+    // In a fleet, the supervisor stops agents instead
+    if !in_fleet {
+        tokio::spawn(async {
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::info!("Shutting down...");
+            // Give a moment for cleanup, then force exit
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            std::process::exit(0);
+        });
+    }
 
-                db.queue_mark_done(queue_id).await?;
-
-                // Emit SSE events for web UI
-                let _ = sse_tx_clone.send(yoclaw::web::SseEvent::StreamEnd {
-                    session_id: incoming.session_id.clone(),
-                    channel: incoming.channel.clone(),
-                });
-                let _ = sse_tx_clone.send(yoclaw::web::SseEvent::MessageProcessed {
-                    session_id: incoming.session_id.clone(),
-                    channel: incoming.channel.clone(),
-                });
-            }
-            Err(e) => {
-                tracing::error!("Processing error: {}", e);
-                // Clean up streaming placeholder on error
-                if let Some(ref ph) = placeholder {
-                    if let Some(ref adapter) = adapter {
-                        let _ = adapter
-                            .edit_message(ph, "An error occurred processing your message.")
-                            .await;
-                    }
+    // SIGUSR1 pauses everything, for when there's no time to find the CLI
+    #[cfg(unix)]
+    {
+        let db = db.clone();
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let Ok(mut usr1) = signal(SignalKind::user_defined1()) else {
+                return;
+            };
+            while usr1.recv().await.is_some() {
+                tracing::warn!("SIGUSR1 received, pausing until `yoclaw resume --all`");
+                if let Err(e) = db
+                    .pause_set(yoclaw::db::pause::PAUSE_ALL, "signal", Some("SIGUSR1"))
+                    .await
+                {
+                    tracing::error!("Failed to pause: {}", e);
                 }
-                db.queue_mark_failed(queue_id, &e.to_string()).await?;
             }
-        }
-            } // end select msg arm
-        } // end select
-    } // end loop
-
-    Ok(())
-}
-
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max])
+        });
     }
+
+    // Sessions are answered on the workers; the loop takes care of
+    // everything that doesn't need a conductor
+    let handler = yoclaw::conductor::daemon::Handler {
+        db: db.clone(),
+        adapters,
+        deliverer,
+        events: sse_tx,
+    };
+    let config_watcher = yoclaw::watcher::ConfigWatcher::new(config_file_path);
+    yoclaw::conductor::daemon::Daemon::new(
+        handler,
+        conductors,
+        config,
+        router,
+        shared_debounce,
+        config_watcher,
+        raw_tx,
+    )
+    .run(coalesced_rx)
+    .await
 }
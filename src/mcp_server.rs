@@ -0,0 +1,476 @@
+//! `yoclaw mcp-serve`: yoclaw's memory, cron jobs and sessions as MCP tools.
+//!
+//! Other agents start `yoclaw mcp-serve` as an MCP server over stdio:
+//! newline-delimited JSON-RPC 2.0 on stdin and stdout, with logs on stderr.
+//! The tools are the agent's own `memory_search`, `memory_store` and
+//! `cron_schedule`, plus `session_list` and `session_history` for reading
+//! conversations other than private ones. They work on the database directly, so the daemon doesn't
+//! need to be running; jobs created here are picked up by its scheduler.
+//! Every call is written to the audit log as an `mcp_tool` event.
+
+use crate::conductor::tools::{MemorySearchTool, MemoryStoreTool};
+use crate::db::Db;
+use crate::scheduler::cron::JobRules;
+use crate::scheduler::tools::CronScheduleTool;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use yoagent::mcp::types::{
+    JsonRpcError, McpContent, McpToolCallResult, McpToolInfo, ServerCapabilities, ServerInfo,
+};
+use yoagent::types::*;
+
+/// Protocol version answered when the client doesn't ask for one.
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Messages shown by `session_history` unless asked for more.
+const DEFAULT_HISTORY: usize = 20;
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub struct McpServer {
+    db: Db,
+    tools: Vec<Box<dyn AgentTool>>,
+}
+
+impl McpServer {
+    pub fn new(db: Db, job_rules: JobRules) -> Self {
+        let tools: Vec<Box<dyn AgentTool>> = vec![
+            Box::new(MemorySearchTool::new(db.clone())),
+            Box::new(MemoryStoreTool::new(db.clone())),
+            // No session in flight: jobs need an explicit target
+            Box::new(
+                CronScheduleTool::new(db.clone(), Default::default()).with_job_rules(job_rules),
+            ),
+            Box::new(SessionListTool { db: db.clone() }),
+            Box::new(SessionHistoryTool { db: db.clone() }),
+        ];
+        Self { db, tools }
+    }
+
+    /// Answer requests from `reader` on `writer` until the input ends.
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> std::io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line).await {
+                writer.write_all(response.to_string().as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The response to one JSON-RPC message; `None` for notifications.
+    pub async fn handle(&self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error(Value::Null, PARSE_ERROR, e.to_string())),
+        };
+        // Notifications (no id) get no answer
+        let id = request.get("id")?.clone();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let method = request["method"].as_str().unwrap_or_default();
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => self.call_tool(&params).await,
+            other => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", other))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error(id, code, message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let version = params["protocolVersion"]
+            .as_str()
+            .unwrap_or(PROTOCOL_VERSION);
+        json!({
+            "protocolVersion": version,
+            "capabilities": ServerCapabilities {
+                tools: Some(json!({})),
+                ..Default::default()
+            },
+            "serverInfo": ServerInfo {
+                name: "yoclaw".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            },
+        })
+    }
+
+    fn list_tools(&self) -> Value {
+        let tools: Vec<McpToolInfo> = self
+            .tools
+            .iter()
+            .map(|tool| McpToolInfo {
+                name: tool.name().to_string(),
+                description: Some(tool.description().to_string()),
+                input_schema: tool.parameters_schema(),
+            })
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    /// Run a tool. Failures of the tool itself are results with `isError`,
+    /// so the calling agent sees them; only unknown tools are protocol errors.
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params["name"].as_str().unwrap_or_default();
+        let Some(tool) = self.tools.iter().find(|t| t.name() == name) else {
+            return Err((INVALID_PARAMS, format!("Unknown tool: {}", name)));
+        };
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+        let _ = self
+            .db
+            .audit_log(
+                None,
+                "mcp_tool",
+                Some(name),
+                Some(&arguments.to_string()),
+                0,
+            )
+            .await;
+        let ctx = ToolContext {
+            tool_call_id: format!("mcp-{}", uuid::Uuid::new_v4()),
+            tool_name: name.to_string(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        };
+        let result = match tool.execute(arguments, ctx).await {
+            Ok(result) => McpToolCallResult {
+                content: result
+                    .content
+                    .into_iter()
+                    .filter_map(|c| match c {
+                        Content::Text { text } => Some(McpContent::Text { text }),
+                        Content::Image { data, mime_type } => {
+                            Some(McpContent::Image { data, mime_type })
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+                is_error: false,
+            },
+            Err(e) => McpToolCallResult {
+                content: vec![McpContent::Text {
+                    text: e.to_string(),
+                }],
+                is_error: true,
+            },
+        };
+        Ok(serde_json::to_value(result).unwrap_or_default())
+    }
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+    let error = JsonRpcError {
+        code,
+        message,
+        data: None,
+    };
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+/// `session_list`: the sessions with a conversation, most recent first.
+struct SessionListTool {
+    db: Db,
+}
+
+#[async_trait::async_trait]
+impl AgentTool for SessionListTool {
+    fn name(&self) -> &str {
+        "session_list"
+    }
+
+    fn label(&self) -> &str {
+        "Sessions"
+    }
+
+    fn description(&self) -> &str {
+        "List yoclaw's conversations (sessions) with their message counts and when they were last active."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn execute(&self, _params: Value, _ctx: ToolContext) -> Result<ToolResult, ToolError> {
+        let sessions = self
+            .db
+            .tape_list_sessions()
+            .await
+            .map_err(|e| ToolError::Failed(e.to_string()))?;
+        // Private sessions stay out of reach, as they do for the cortex
+        let mut visible = Vec::new();
+        for session in sessions {
+            if !self
+                .db
+                .privacy_is_private(&session.session_id)
+                .await
+                .unwrap_or(true)
+            {
+                visible.push(session);
+            }
+        }
+        let mut sessions = visible;
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        let text = if sessions.is_empty() {
+            "No sessions.".to_string()
+        } else {
+            sessions
+                .iter()
+                .map(|s| {
+                    format!(
+                        "- {}: {} messages, last active {}",
+                        s.session_id,
+                        s.message_count,
+                        crate::debug::timestamp(s.updated_at)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        Ok(ToolResult {
+            content: vec![Content::Text { text }],
+            details: Value::Null,
+        })
+    }
+}
+
+/// `session_history`: the latest messages of one session.
+struct SessionHistoryTool {
+    db: Db,
+}
+
+#[async_trait::async_trait]
+impl AgentTool for SessionHistoryTool {
+    fn name(&self) -> &str {
+        "session_history"
+    }
+
+    fn label(&self) -> &str {
+        "Session history"
+    }
+
+    fn description(&self) -> &str {
+        "Read the latest messages of a yoclaw session: what the user said, the agent's replies and its tool calls."
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "session_id": {
+                    "type": "string",
+                    "description": "Session ID from session_list, e.g. 'tg-514133400'"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "How many of the latest messages to return (default 20)"
+                }
+            },
+            "required": ["session_id"]
+        })
+    }
+
+    async fn execute(&self, params: Value, _ctx: ToolContext) -> Result<ToolResult, ToolError> {
+        let session_id = params["session_id"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidArgs("Missing 'session_id' parameter".into()))?;
+        let limit = params["limit"]
+            .as_u64()
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_HISTORY);
+        if self.db.privacy_is_private(session_id).await.unwrap_or(true) {
+            return Err(ToolError::Failed(format!(
+                "Session '{}' is private",
+                session_id
+            )));
+        }
+        let messages = self
+            .db
+            .tape_load_messages(session_id)
+            .await
+            .map_err(|e| ToolError::Failed(e.to_string()))?;
+        if messages.is_empty() {
+            return Err(ToolError::Failed(format!(
+                "No messages in session '{}'",
+                session_id
+            )));
+        }
+        let text = crate::debug::format_tape(&messages, limit);
+        Ok(ToolResult {
+            content: vec![Content::Text { text }],
+            details: Value::Null,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> ToolContext {
+        ToolContext {
+            tool_call_id: "t".into(),
+            tool_name: "session".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        }
+    }
+
+    async fn call(server: &McpServer, request: Value) -> Value {
+        server.handle(&request.to_string()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_initialize_and_list_tools() {
+        let server = McpServer::new(Db::open_memory().unwrap(), JobRules::default());
+        let response = call(
+            &server,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                   "params": {"protocolVersion": "2025-03-26"}}),
+        )
+        .await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(response["result"]["serverInfo"]["name"], "yoclaw");
+
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(server.handle(&notification.to_string()).await.is_none());
+
+        let response = call(
+            &server,
+            json!({"jsonrpc": "2.0", "id": "a", "method": "tools/list"}),
+        )
+        .await;
+        let names: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "memory_search",
+                "memory_store",
+                "cron_schedule",
+                "session_list",
+                "session_history"
+            ]
+        );
+        assert!(response["result"]["tools"][0]["inputSchema"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_call_tools() {
+        let db = Db::open_memory().unwrap();
+        let server = McpServer::new(db.clone(), JobRules::default());
+        let tool_call = |id: u64, name: &str, arguments: Value| {
+            json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                   "params": {"name": name, "arguments": arguments}})
+        };
+
+        call(
+            &server,
+            tool_call(
+                1,
+                "memory_store",
+                json!({"content": "The user's cat is called Miso", "category": "fact"}),
+            ),
+        )
+        .await;
+        let response = call(
+            &server,
+            tool_call(2, "memory_search", json!({"query": "cat"})),
+        )
+        .await;
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(text.contains("Miso"));
+
+        // Tool failures are results, unknown tools are errors
+        let response = call(
+            &server,
+            tool_call(3, "session_history", json!({"session_id": "tg-1"})),
+        )
+        .await;
+        assert_eq!(response["result"]["isError"], true);
+        let response = call(&server, tool_call(4, "shell", json!({}))).await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let audited = db.audit_query(None, 10).await.unwrap();
+        assert_eq!(
+            audited
+                .iter()
+                .filter(|e| e.event_type == "mcp_tool")
+                .count(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_tools() {
+        let db = Db::open_memory().unwrap();
+        let messages: Vec<AgentMessage> = (1..=3)
+            .map(|i| AgentMessage::Llm(Message::user(format!("message {}", i))))
+            .collect();
+        db.tape_save_messages("tg-1", &messages).await.unwrap();
+        db.tape_save_messages("tg-2", &messages).await.unwrap();
+        db.privacy_set("tg-2", true).await.unwrap();
+        let list = SessionListTool { db: db.clone() }
+            .execute(json!({}), ctx())
+            .await
+            .unwrap();
+        let Content::Text { ref text } = list.content[0] else {
+            panic!("expected text");
+        };
+        assert!(text.starts_with("- tg-1: 3 messages"));
+        assert!(!text.contains("tg-2"));
+
+        let tool = SessionHistoryTool { db };
+        assert!(tool
+            .execute(json!({"session_id": "tg-2"}), ctx())
+            .await
+            .is_err());
+        let result = tool
+            .execute(json!({"session_id": "tg-1", "limit": 2}), ctx())
+            .await
+            .unwrap();
+        let Content::Text { ref text } = result.content[0] else {
+            panic!("expected text");
+        };
+        assert_eq!(text, "[2] user: message 2\n[3] user: message 3");
+    }
+
+    #[tokio::test]
+    async fn test_serve_and_bad_input() {
+        let server = McpServer::new(Db::open_memory().unwrap(), JobRules::default());
+        let input = b"not json\n\n{\"jsonrpc\":\"2.0\",\"id\":7,\"method\":\"nope\"}\n".to_vec();
+        let mut output = Vec::new();
+        server.serve(&input[..], &mut output).await.unwrap();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["error"]["code"], PARSE_ERROR);
+        assert_eq!(lines[1]["id"], 7);
+        assert_eq!(lines[1]["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
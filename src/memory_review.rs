@@ -0,0 +1,311 @@
+//! Interactive memory review.
+//!
+//! `/review-memories` (or the scheduler, with `[scheduler.memory_review]`)
+//! starts a review in a DM: memories added in the last week and low-importance
+//! ones that cortex cleanup is about to drop are shown in numbered batches.
+//! The user replies with one command per line:
+//!
+//! ```text
+//! keep              keep the whole batch
+//! delete 1 3        forget memories 1 and 3, keep the rest
+//! edit 2 <text>     replace memory 2, keep the rest
+//! stop              end the review
+//! ```
+//!
+//! Kept memories count as accessed (so they don't expire) and are not shown
+//! again until they change. The current batch lives in the `state` table
+//! under `review:<session_id>`. Messages that don't start with a review
+//! command go to the agent as usual; the review stays open.
+
+use crate::db::memory::MemoryEntry;
+use crate::db::{now_ms, Db, DbError};
+use crate::scheduler::background::ago;
+
+pub const COMMAND: &str = "/review-memories";
+
+/// Memories added within this many days are up for review.
+const RECENT_DAYS: u64 = 7;
+/// Cortex drops unimportant memories unused for 90 days; flag them 15 days early.
+const EXPIRING_AFTER_DAYS: u64 = 75;
+const BATCH_SIZE: usize = 5;
+const DAY_MS: u64 = 86_400_000;
+
+const USAGE: &str = "Reply \"keep\" to keep them all, \"delete 1 3\" or \"edit 2 <new text>\" \
+                     (one per line, the rest are kept), or \"stop\" to end the review.";
+const NOTHING_TO_REVIEW: &str = "Nothing to review: no new or expiring memories.";
+
+/// What a review reply asks for.
+#[derive(Debug, Default, PartialEq)]
+struct Reply {
+    /// Batch positions (0-based) to delete.
+    delete: Vec<usize>,
+    /// Batch position (0-based) and replacement content.
+    edit: Vec<(usize, String)>,
+    stop: bool,
+}
+
+/// Start a review in `session_id`. Returns the first batch, or None if there
+/// is nothing to review.
+pub async fn start(db: &Db, session_id: &str) -> Result<Option<String>, DbError> {
+    let (batch, total) = next_batch(db).await?;
+    if batch.is_empty() {
+        db.review_clear(session_id).await?;
+        return Ok(None);
+    }
+    let ids: Vec<i64> = batch.iter().filter_map(|m| m.id).collect();
+    db.review_set_batch(session_id, &ids).await?;
+    Ok(Some(format_batch(&batch, total, now_ms())))
+}
+
+/// Handle a DM. Returns the reply if the message is `/review-memories` or
+/// answers an open review, or None if it should go to the agent as usual.
+pub async fn handle(db: &Db, session_id: &str, text: &str) -> Result<Option<String>, DbError> {
+    if text.trim() == COMMAND {
+        let reply = start(db, session_id).await?;
+        return Ok(Some(reply.unwrap_or_else(|| NOTHING_TO_REVIEW.to_string())));
+    }
+    let Some(ids) = db.review_batch(session_id).await? else {
+        return Ok(None);
+    };
+    let reply = match parse_reply(text, ids.len()) {
+        None => return Ok(None),
+        Some(Ok(reply)) => reply,
+        Some(Err(e)) => return Ok(Some(format!("{}\n\n{}", e, USAGE))),
+    };
+
+    let mut kept = Vec::new();
+    for (i, id) in ids.iter().enumerate() {
+        if reply.delete.contains(&i) {
+            db.memory_delete(*id).await?;
+        } else if let Some((_, content)) = reply.edit.iter().find(|(pos, _)| *pos == i) {
+            db.memory_update_content(*id, content).await?;
+        } else {
+            kept.push(*id);
+        }
+    }
+    // Stopping only applies what was asked; the rest stays unreviewed
+    if !reply.stop {
+        db.memory_mark_reviewed(kept).await?;
+    }
+
+    let changes = summarize(&reply);
+    if reply.stop {
+        db.review_clear(session_id).await?;
+        return Ok(Some(format!("{}Review stopped.", changes)));
+    }
+    match start(db, session_id).await? {
+        Some(batch) => Ok(Some(format!("{}{}", changes, batch))),
+        None => Ok(Some(format!(
+            "{}That's all, memory review finished.",
+            changes
+        ))),
+    }
+}
+
+async fn next_batch(db: &Db) -> Result<(Vec<MemoryEntry>, usize), DbError> {
+    let now = now_ms();
+    db.memory_review_candidates(
+        now.saturating_sub(RECENT_DAYS * DAY_MS),
+        now.saturating_sub(EXPIRING_AFTER_DAYS * DAY_MS),
+        BATCH_SIZE,
+    )
+    .await
+}
+
+/// Parse a reply to a batch of `len` memories. None if the message isn't a
+/// review reply at all, Err with an explanation if it is but can't be applied.
+fn parse_reply(text: &str, len: usize) -> Option<Result<Reply, String>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let first = lines.first()?.split_whitespace().next()?.to_lowercase();
+    if !is_command(&first) {
+        return None;
+    }
+
+    let mut reply = Reply::default();
+    for line in lines {
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_lowercase().as_str() {
+            "keep" | "ok" => {}
+            "stop" | "done" | "/skip" => reply.stop = true,
+            "delete" | "del" | "d" | "forget" => {
+                let numbers: Vec<&str> = rest
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|n| !n.is_empty())
+                    .collect();
+                if numbers.is_empty() {
+                    return Some(Err("Which memories should I delete?".to_string()));
+                }
+                for n in numbers {
+                    match position(n, len) {
+                        Ok(i) if reply.delete.contains(&i) => {}
+                        Ok(i) => reply.delete.push(i),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            }
+            "edit" | "e" => {
+                let (n, content) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                let content = content.trim();
+                let i = match position(n, len) {
+                    Ok(i) => i,
+                    Err(e) => return Some(Err(e)),
+                };
+                if content.is_empty() {
+                    return Some(Err(format!("What should memory {} say instead?", i + 1)));
+                }
+                reply.edit.push((i, content.to_string()));
+            }
+            _ => return Some(Err(format!("I didn't understand \"{}\".", line))),
+        }
+    }
+    Some(Ok(reply))
+}
+
+fn is_command(word: &str) -> bool {
+    matches!(
+        word,
+        "keep"
+            | "ok"
+            | "stop"
+            | "done"
+            | "/skip"
+            | "delete"
+            | "del"
+            | "d"
+            | "forget"
+            | "edit"
+            | "e"
+    )
+}
+
+/// "2" → Ok(1) for a batch of at least two memories.
+fn position(n: &str, len: usize) -> Result<usize, String> {
+    match n.parse::<usize>() {
+        Ok(n) if (1..=len).contains(&n) => Ok(n - 1),
+        _ => Err(format!("\"{}\" isn't a memory number (1-{}).", n, len)),
+    }
+}
+
+/// "Deleted 2, edited 1.\n\n", or "" if nothing changed.
+fn summarize(reply: &Reply) -> String {
+    let mut parts = Vec::new();
+    if !reply.delete.is_empty() {
+        parts.push(format!("deleted {}", reply.delete.len()));
+    }
+    if !reply.edit.is_empty() {
+        parts.push(format!("edited {}", reply.edit.len()));
+    }
+    if parts.is_empty() {
+        return String::new();
+    }
+    let text = parts.join(", ");
+    format!("{}{}.\n\n", text[..1].to_uppercase(), &text[1..])
+}
+
+fn format_batch(batch: &[MemoryEntry], total: usize, now: u64) -> String {
+    let expiring_before = now.saturating_sub(EXPIRING_AFTER_DAYS * DAY_MS);
+    let mut out = format!("Memory review ({} of {}):\n", batch.len(), total);
+    for (i, m) in batch.iter().enumerate() {
+        let expiring = m.importance <= 3
+            && m.category != "decision"
+            && m.last_accessed.is_some_and(|t| t < expiring_before);
+        let status = match m.last_accessed {
+            Some(t) if expiring => format!("unused for {}, expiring soon", ago(now, t)),
+            _ => format!("added {} ago", ago(now, m.created_at)),
+        };
+        out.push_str(&format!(
+            "{}. [{}, {}] {}\n",
+            i + 1,
+            m.category,
+            status,
+            m.content
+        ));
+    }
+    out.push('\n');
+    out.push_str(USAGE);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("keep", 3), Some(Ok(Reply::default())));
+        assert_eq!(parse_reply("what's the weather?", 3), None);
+
+        let reply = parse_reply("delete 1, 3\nedit 2 Prefers green tea", 3)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply.delete, vec![0, 2]);
+        assert_eq!(reply.edit, vec![(1, "Prefers green tea".to_string())]);
+        assert!(!reply.stop);
+
+        assert!(parse_reply("Stop", 3).unwrap().unwrap().stop);
+        assert!(parse_reply("delete 4", 3).unwrap().is_err());
+        assert!(parse_reply("edit 1", 3).unwrap().is_err());
+        assert!(parse_reply("keep\nmaybe later", 3).unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_review_flow() {
+        let db = Db::open_memory().unwrap();
+        assert_eq!(
+            handle(&db, "tg-1", COMMAND).await.unwrap().unwrap(),
+            NOTHING_TO_REVIEW
+        );
+
+        for i in 0..7 {
+            db.memory_store_with_meta(None, &format!("fact {}", i), None, None, "fact", 5)
+                .await
+                .unwrap();
+        }
+        let first = handle(&db, "tg-1", COMMAND).await.unwrap().unwrap();
+        assert!(first.starts_with("Memory review (5 of 7):"));
+        assert!(first.contains("1. [fact, added 0s ago] fact 6"));
+
+        // Unrelated messages go to the agent; other sessions are not intercepted
+        assert!(handle(&db, "tg-1", "hello").await.unwrap().is_none());
+        assert!(handle(&db, "tg-2", "keep").await.unwrap().is_none());
+
+        let second = handle(&db, "tg-1", "delete 1\nedit 2 fact five, revised")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(second.starts_with("Deleted 1, edited 1.\n\nMemory review (2 of 2):"));
+        assert_eq!(db.memory_list(10, None).await.unwrap().len(), 6);
+
+        let done = handle(&db, "tg-1", "keep").await.unwrap().unwrap();
+        assert_eq!(done, "That's all, memory review finished.");
+        assert!(db.review_batch("tg-1").await.unwrap().is_none());
+
+        // Everything was reviewed
+        assert_eq!(
+            handle(&db, "tg-1", COMMAND).await.unwrap().unwrap(),
+            NOTHING_TO_REVIEW
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_leaves_rest_unreviewed() {
+        let db = Db::open_memory().unwrap();
+        db.memory_store(None, "a", None, None).await.unwrap();
+        db.memory_store(None, "b", None, None).await.unwrap();
+
+        handle(&db, "tg-1", COMMAND).await.unwrap().unwrap();
+        let reply = handle(&db, "tg-1", "delete 2\nstop")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply, "Deleted 1.\n\nReview stopped.");
+
+        let again = handle(&db, "tg-1", COMMAND).await.unwrap().unwrap();
+        assert!(again.starts_with("Memory review (1 of 1):"));
+    }
+}
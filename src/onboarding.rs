@@ -0,0 +1,196 @@
+//! First-run interview.
+//!
+//! With `[onboarding] enabled = true`, the first DM on a fresh database starts
+//! a short interview instead of going to the agent. Each answer is stored as a
+//! keyed memory (`profile:name`, `profile:timezone`, ...), so the agent starts
+//! out knowing who it's talking to. `yoclaw init --interview` asks the same
+//! questions on the terminal.
+//!
+//! Progress lives in the `state` table as `"<step> <session_id>"` while the
+//! interview runs, and `"done"` once it finished or was skipped. Only the
+//! session that started the interview is intercepted.
+
+use crate::db::{Db, DbError};
+
+/// One interview question and how its answer is remembered.
+pub struct Question {
+    pub key: &'static str,
+    pub prompt: &'static str,
+    /// Prefix for the stored memory, e.g. "The user's name".
+    pub label: &'static str,
+    pub category: &'static str,
+    pub importance: i32,
+}
+
+pub const QUESTIONS: [Question; 4] = [
+    Question {
+        key: "profile:name",
+        prompt: "What should I call you?",
+        label: "The user's name",
+        category: "fact",
+        importance: 8,
+    },
+    Question {
+        key: "profile:timezone",
+        prompt: "Which timezone are you in? (e.g. Europe/Berlin)",
+        label: "The user's timezone",
+        category: "fact",
+        importance: 7,
+    },
+    Question {
+        key: "profile:preferences",
+        prompt:
+            "How do you like answers: short or detailed, formal or casual? Anything I should avoid?",
+        label: "The user's preferences",
+        category: "preference",
+        importance: 7,
+    },
+    Question {
+        key: "profile:projects",
+        prompt: "What are you working on at the moment?",
+        label: "The user's current projects",
+        category: "fact",
+        importance: 6,
+    },
+];
+
+pub const INTRO: &str = "Hi! Before we start, a few quick questions so I can be more useful. \
+                         Reply /skip at any time to skip the rest.";
+pub const OUTRO: &str = "Thanks, I'll remember that. What can I help you with?";
+const SKIPPED: &str = "No problem, skipping the rest. What can I help you with?";
+
+const DONE: &str = "done";
+const SKIP_COMMAND: &str = "/skip";
+
+/// Store one answer as a profile memory. Blank answers are ignored.
+pub async fn save_answer(db: &Db, question: &Question, answer: &str) -> Result<(), DbError> {
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(());
+    }
+    db.memory_store_with_meta(
+        Some(question.key),
+        &format!("{}: {}", question.label, answer),
+        Some("profile"),
+        Some("onboarding"),
+        question.category,
+        question.importance,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Mark onboarding as finished so it never starts again.
+pub async fn finish(db: &Db) -> Result<(), DbError> {
+    db.onboarding_set(DONE).await
+}
+
+/// Handle a DM. Returns the reply if the message belongs to the interview,
+/// or None if it should go to the agent as usual.
+pub async fn handle(db: &Db, session_id: &str, text: &str) -> Result<Option<String>, DbError> {
+    match db.onboarding_get().await?.as_deref() {
+        Some(DONE) => Ok(None),
+        None => {
+            if !db.is_fresh().await? {
+                // Existing install: nothing to bootstrap
+                finish(db).await?;
+                return Ok(None);
+            }
+            if db.privacy_is_private(session_id).await? {
+                return Ok(None);
+            }
+            db.onboarding_set(&progress(0, session_id)).await?;
+            Ok(Some(format!("{}\n\n{}", INTRO, QUESTIONS[0].prompt)))
+        }
+        Some(value) => {
+            let Some((step, owner)) = parse_progress(value) else {
+                tracing::warn!("Resetting malformed onboarding state '{}'", value);
+                finish(db).await?;
+                return Ok(None);
+            };
+            if owner != session_id {
+                return Ok(None);
+            }
+            if text.trim() == SKIP_COMMAND {
+                finish(db).await?;
+                return Ok(Some(SKIPPED.to_string()));
+            }
+            save_answer(db, &QUESTIONS[step], text).await?;
+            match QUESTIONS.get(step + 1) {
+                Some(next) => {
+                    db.onboarding_set(&progress(step + 1, session_id)).await?;
+                    Ok(Some(next.prompt.to_string()))
+                }
+                None => {
+                    finish(db).await?;
+                    Ok(Some(OUTRO.to_string()))
+                }
+            }
+        }
+    }
+}
+
+fn progress(step: usize, session_id: &str) -> String {
+    format!("{} {}", step, session_id)
+}
+
+/// "<step> <session_id>" → (step, session_id)
+fn parse_progress(value: &str) -> Option<(usize, &str)> {
+    let (step, session_id) = value.split_once(' ')?;
+    let step: usize = step.parse().ok()?;
+    (step < QUESTIONS.len()).then_some((step, session_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_interview_stores_profile_memories() {
+        let db = Db::open_memory().unwrap();
+
+        let reply = handle(&db, "tg-1", "hello").await.unwrap().unwrap();
+        assert!(reply.starts_with(INTRO));
+        assert!(reply.ends_with(QUESTIONS[0].prompt));
+
+        // Other sessions are not intercepted
+        assert!(handle(&db, "tg-2", "hi").await.unwrap().is_none());
+
+        let answers = ["Ada", "Europe/London", "short and casual", "a compiler"];
+        for (i, answer) in answers.iter().enumerate() {
+            let reply = handle(&db, "tg-1", answer).await.unwrap().unwrap();
+            match QUESTIONS.get(i + 1) {
+                Some(next) => assert_eq!(reply, next.prompt),
+                None => assert_eq!(reply, OUTRO),
+            }
+        }
+
+        let name = db.memory_get("profile:name").await.unwrap().unwrap();
+        assert_eq!(name.content, "The user's name: Ada");
+        assert_eq!(name.importance, 8);
+        let prefs = db.memory_get("profile:preferences").await.unwrap().unwrap();
+        assert_eq!(prefs.category, "preference");
+
+        // Finished: everything goes to the agent again
+        assert!(handle(&db, "tg-1", "hello").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_skip_and_existing_install() {
+        let db = Db::open_memory().unwrap();
+        handle(&db, "tg-1", "hello").await.unwrap().unwrap();
+        handle(&db, "tg-1", "Ada").await.unwrap().unwrap();
+        assert_eq!(
+            handle(&db, "tg-1", "/skip").await.unwrap().unwrap(),
+            SKIPPED
+        );
+        assert!(db.memory_get("profile:timezone").await.unwrap().is_none());
+        assert!(handle(&db, "tg-1", "hello").await.unwrap().is_none());
+
+        // A database with history never starts the interview
+        let db = Db::open_memory().unwrap();
+        db.memory_store(None, "existing", None, None).await.unwrap();
+        assert!(handle(&db, "tg-1", "hello").await.unwrap().is_none());
+        assert_eq!(db.onboarding_get().await.unwrap().as_deref(), Some(DONE));
+    }
+}
@@ -0,0 +1,173 @@
+//! Token prices, for estimating what the agent spends.
+//!
+//! Each model call's cost is estimated from the model that answered it and
+//! recorded with its `llm_usage` audit entry, so daily and weekly spend can
+//! be summed from the audit table and `[agent.budget] max_cost_per_day` can
+//! stop the agent. Prices come from, in order:
+//!
+//! 1. `[agent.prices."<provider>/<model>"]`, then `[agent.prices."<model>"]`
+//! 2. `[agent.pricing]`, for `agent.model` only
+//! 3. the built-in table below, by model name prefix
+//!
+//! The built-in prices are list prices per million tokens at the time of
+//! writing and ignore caching discounts; override them where they differ.
+//! Models without a price cost nothing in the estimate.
+
+use crate::config::{AgentConfig, PricingConfig};
+use std::collections::HashMap;
+
+/// List prices in dollars per million input and output tokens, by model
+/// name prefix. More specific prefixes come first.
+const BUILTIN: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-haiku-4", 1.0, 5.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o4-mini", 1.1, 4.4),
+    ("o3-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.0-flash", 0.1, 0.4),
+];
+
+impl PricingConfig {
+    /// Dollars for `input` and `output` tokens.
+    pub fn cost(&self, input: u64, output: u64) -> f64 {
+        (input as f64 * self.input_per_mtok + output as f64 * self.output_per_mtok) / 1_000_000.0
+    }
+}
+
+/// The built-in price of `model`, if it's in the table. A `vendor/` prefix,
+/// as routers like OpenRouter use, is ignored.
+pub fn builtin(model: &str) -> Option<PricingConfig> {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    BUILTIN
+        .iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .map(|&(_, input, output)| PricingConfig {
+            input_per_mtok: input,
+            output_per_mtok: output,
+        })
+}
+
+/// Prices for every model the agent may use.
+#[derive(Debug, Clone, Default)]
+pub struct Prices {
+    overrides: HashMap<String, PricingConfig>,
+    model: String,
+    pricing: Option<PricingConfig>,
+}
+
+impl Prices {
+    pub fn from_config(agent: &AgentConfig) -> Self {
+        Self {
+            overrides: agent.prices.clone(),
+            model: agent.model.clone(),
+            pricing: agent.pricing.clone(),
+        }
+    }
+
+    /// Price of `model` on `provider`.
+    pub fn get(&self, provider: &str, model: &str) -> Option<PricingConfig> {
+        self.overrides
+            .get(&format!("{}/{}", provider, model))
+            .or_else(|| self.overrides.get(model))
+            .cloned()
+            .or_else(|| self.pricing.clone().filter(|_| model == self.model))
+            .or_else(|| builtin(model))
+    }
+
+    /// Estimated dollars for a call, if `model` has a price.
+    pub fn cost(&self, provider: &str, model: &str, input: u64, output: u64) -> Option<f64> {
+        self.get(provider, model).map(|p| p.cost(input, output))
+    }
+}
+
+/// Dollars with cents, or more digits for small amounts.
+pub fn format_usd(usd: f64) -> String {
+    if usd != 0.0 && usd.abs() < 1.0 {
+        format!("${:.4}", usd)
+    } else {
+        format!("${:.2}", usd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin() {
+        let opus = builtin("claude-opus-4-20250514").unwrap();
+        assert_eq!(opus.input_per_mtok, 15.0);
+        assert_eq!(builtin("claude-opus-4-5").unwrap().input_per_mtok, 5.0);
+        assert_eq!(builtin("gpt-4o-mini").unwrap().output_per_mtok, 0.6);
+        assert_eq!(builtin("gpt-4o-2024-08-06").unwrap().output_per_mtok, 10.0);
+        assert_eq!(
+            builtin("anthropic/claude-sonnet-4").unwrap().input_per_mtok,
+            3.0
+        );
+        assert!(builtin("llama3.1:8b").is_none());
+    }
+
+    #[test]
+    fn test_prices_lookup_order() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+[agent]
+provider = "openai"
+model = "gpt-4o"
+api_key = "k"
+
+[agent.pricing]
+input_per_mtok = 2.0
+output_per_mtok = 8.0
+
+[agent.prices."openai/gpt-4o-mini"]
+input_per_mtok = 0.1
+output_per_mtok = 0.5
+
+[agent.prices."llama3.1:8b"]
+input_per_mtok = 0.0
+output_per_mtok = 0.0
+"#,
+        )
+        .unwrap();
+        let prices = Prices::from_config(&config.agent);
+
+        assert_eq!(prices.get("openai", "gpt-4o").unwrap().input_per_mtok, 2.0);
+        assert_eq!(
+            prices.get("openai", "gpt-4o-mini").unwrap().output_per_mtok,
+            0.5
+        );
+        // Not the same provider: the built-in price
+        assert_eq!(
+            prices.get("azure", "gpt-4o-mini").unwrap().output_per_mtok,
+            0.6
+        );
+        assert_eq!(
+            prices.get("ollama", "llama3.1:8b").unwrap().input_per_mtok,
+            0.0
+        );
+        assert!(prices.get("ollama", "mistral").is_none());
+
+        let cost = prices.cost("openai", "gpt-4o", 1_000_000, 500_000).unwrap();
+        assert!((cost - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_format_usd() {
+        assert_eq!(format_usd(0.0), "$0.00");
+        assert_eq!(format_usd(0.01234), "$0.0123");
+        assert_eq!(format_usd(12.5), "$12.50");
+    }
+}
@@ -0,0 +1,390 @@
+//! Projects (working sets).
+//!
+//! A project groups the memories, files and sessions of one piece of ongoing
+//! work. A session selects one with `/project <name>`; from then on every
+//! turn's system prompt carries the project's context: its note, its
+//! memories, excerpts of its files and the latest summaries of its other
+//! sessions. Memories the agent stores while a project is selected join it.
+//!
+//! ```text
+//! /project                          show the selected project
+//! /project <name>                   select a project, creating it if needed
+//! /project off                      deselect
+//! /project list                     list projects
+//! /project note <text>              describe the selected project
+//! /project add file <path>          add a file (also: memory <id>, session <id>)
+//! /project remove file <path>       remove an item
+//! ```
+//!
+//! Cron prompts containing `{projects}` get a progress digest of all projects
+//! in its place, so a briefing job can summarize them.
+
+use crate::db::projects::{ItemKind, Project, ProjectItem};
+use crate::db::{now_ms, Db, DbError};
+use crate::scheduler::background::ago;
+
+pub const COMMAND: &str = "/project";
+
+/// Cron prompt placeholder replaced by [`progress`].
+pub const PLACEHOLDER: &str = "{projects}";
+
+/// Max characters of each file included in the context.
+const FILE_EXCERPT_CHARS: usize = 3_000;
+/// Max memories included in the context.
+const MAX_MEMORIES: usize = 20;
+/// Activity within this window counts as progress in a briefing.
+const PROGRESS_WINDOW_MS: u64 = 86_400_000;
+
+const USAGE: &str = "Usage: /project [<name> | off | list | note <text> \
+                     | add <memory|file|session> <ref> | remove <memory|file|session> <ref>]";
+
+/// Handle `/project ...`. Returns the reply if `text` was a project command.
+pub async fn handle(db: &Db, session_id: &str, text: &str) -> Result<Option<String>, DbError> {
+    let mut words = text.split_whitespace();
+    if words.next() != Some(COMMAND) {
+        return Ok(None);
+    }
+    let args: Vec<&str> = words.collect();
+    let selected = db.project_selected(session_id).await?;
+
+    let reply = match (args.as_slice(), selected) {
+        ([], None) => "No project selected. Start one with /project <name>.".to_string(),
+        ([], Some(name)) => status(db, &name).await?,
+        (["off"], _) => {
+            db.project_select(session_id, None).await?;
+            "Project deselected.".to_string()
+        }
+        (["list"], selected) => {
+            let projects = db.project_list().await?;
+            if projects.is_empty() {
+                "No projects yet. Start one with /project <name>.".to_string()
+            } else {
+                projects
+                    .iter()
+                    .map(|p| {
+                        let marker = if selected.as_deref() == Some(&p.name) {
+                            " (selected)"
+                        } else {
+                            ""
+                        };
+                        format!(
+                            "- {}{}, updated {} ago",
+                            p.name,
+                            marker,
+                            ago(now_ms(), p.updated_at)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        (["note", ..], None) | (["add", ..], None) | (["remove", ..], None) => {
+            "Select a project first with /project <name>.".to_string()
+        }
+        (["note", note @ ..], Some(name)) if !note.is_empty() => {
+            db.project_describe(&name, &note.join(" ")).await?;
+            format!("Noted for '{}'.", name)
+        }
+        ([action @ ("add" | "remove"), kind, item @ ..], Some(name)) if !item.is_empty() => {
+            let Some(kind) = ItemKind::parse(kind) else {
+                return Ok(Some(USAGE.to_string()));
+            };
+            let item = item.join(" ");
+            if kind == ItemKind::Memory && item.parse::<i64>().is_err() {
+                return Ok(Some(format!("'{}' is not a memory ID.", item)));
+            }
+            let changed = if *action == "add" {
+                db.project_add(&name, kind, &item).await?
+            } else {
+                db.project_remove(&name, kind, &item).await?
+            };
+            match (*action, changed) {
+                ("add", true) => format!("Added {} {} to '{}'.", kind.as_str(), item, name),
+                ("add", false) => format!("'{}' already has {} {}.", name, kind.as_str(), item),
+                (_, true) => format!("Removed {} {} from '{}'.", kind.as_str(), item, name),
+                (_, false) => format!("'{}' has no {} {}.", name, kind.as_str(), item),
+            }
+        }
+        ([name], _) if valid_name(name) => {
+            db.project_select(session_id, Some(name)).await?;
+            format!(
+                "Working on '{}'. Its memories, files and sessions are now in context.",
+                name
+            )
+        }
+        _ => USAGE.to_string(),
+    };
+    Ok(Some(reply))
+}
+
+/// Project names: letters, digits, '-', '_' and '.', not a subcommand.
+fn valid_name(name: &str) -> bool {
+    !matches!(name, "off" | "list" | "note" | "add" | "remove")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+async fn status(db: &Db, name: &str) -> Result<String, DbError> {
+    let items = db.project_items(name).await?;
+    let count = |kind: ItemKind| items.iter().filter(|i| i.kind == kind).count();
+    let mut reply = format!(
+        "Project '{}': {} memories, {} files, {} sessions.",
+        name,
+        count(ItemKind::Memory),
+        count(ItemKind::File),
+        count(ItemKind::Session)
+    );
+    if let Some(description) = db.project_get(name).await?.and_then(|p| p.description) {
+        reply.push_str(&format!("\n{}", description));
+    }
+    Ok(reply)
+}
+
+/// System prompt section for `name`, built fresh for each turn.
+pub async fn context(db: &Db, name: &str, session_id: &str) -> Result<String, DbError> {
+    let project = db.project_get(name).await?;
+    let items = db.project_items(name).await?;
+    let mut out = format!(
+        "# Current project: {}\n\nThis conversation is part of the project below. Prefer its \
+         memories and files when relevant, and keep the project's note in mind.\n",
+        name
+    );
+    if let Some(description) = project.and_then(|p| p.description) {
+        out.push_str(&format!("\n{}\n", description));
+    }
+
+    let mut memories = Vec::new();
+    for item in items.iter().filter(|i| i.kind == ItemKind::Memory).rev() {
+        if memories.len() >= MAX_MEMORIES {
+            break;
+        }
+        let Ok(id) = item.item.parse::<i64>() else {
+            continue;
+        };
+        // Memories deleted since they were added are skipped
+        if let Some(memory) = db.memory_get_by_id(id).await? {
+            memories.push(format!("- [#{}] {}", id, memory.content));
+        }
+    }
+    if !memories.is_empty() {
+        memories.reverse();
+        out.push_str(&format!("\n## Project memories\n{}\n", memories.join("\n")));
+    }
+
+    for item in items.iter().filter(|i| i.kind == ItemKind::File) {
+        out.push_str(&format!("\n## File: {}\n", item.item));
+        match tokio::fs::read_to_string(&item.item).await {
+            Ok(text) => out.push_str(&format!("```\n{}\n```\n", excerpt(&text))),
+            Err(e) => out.push_str(&format!("(unreadable: {})\n", e)),
+        }
+    }
+
+    let summaries = session_summaries(db, &items, Some(session_id)).await?;
+    if !summaries.is_empty() {
+        out.push_str(&format!(
+            "\n## Other project sessions\n{}\n",
+            summaries.join("\n")
+        ));
+    }
+    Ok(out)
+}
+
+/// First [`FILE_EXCERPT_CHARS`] characters of a file.
+fn excerpt(text: &str) -> String {
+    match text.char_indices().nth(FILE_EXCERPT_CHARS) {
+        Some((i, _)) => format!("{}\n[... truncated ...]", &text[..i]),
+        None => text.trim_end().to_string(),
+    }
+}
+
+/// "- <session>: <cortex summary>" for the project's sessions, except `skip`.
+async fn session_summaries(
+    db: &Db,
+    items: &[ProjectItem],
+    skip: Option<&str>,
+) -> Result<Vec<String>, DbError> {
+    let mut lines = Vec::new();
+    for item in items.iter().filter(|i| i.kind == ItemKind::Session) {
+        if Some(item.item.as_str()) == skip {
+            continue;
+        }
+        let summary = db
+            .memory_get(&format!("session_index:{}", item.item))
+            .await?
+            .map(|m| m.content)
+            .unwrap_or_else(|| "not summarized yet".to_string());
+        lines.push(format!("- {}: {}", item.item, summary));
+    }
+    Ok(lines)
+}
+
+/// Progress digest of every project for a briefing: what was added and which
+/// sessions were active in the last day, with their latest summaries.
+pub async fn progress(db: &Db) -> Result<String, DbError> {
+    let projects = db.project_list().await?;
+    if projects.is_empty() {
+        return Ok("No projects.".to_string());
+    }
+    let now = now_ms();
+    let since = now.saturating_sub(PROGRESS_WINDOW_MS);
+    let mut sections = Vec::new();
+    for project in &projects {
+        sections.push(project_progress(db, project, since, now).await?);
+    }
+    Ok(sections.join("\n\n"))
+}
+
+async fn project_progress(
+    db: &Db,
+    project: &Project,
+    since: u64,
+    now: u64,
+) -> Result<String, DbError> {
+    let items = db.project_items(&project.name).await?;
+    let mut out = format!("Project {}", project.name);
+    if let Some(ref description) = project.description {
+        out.push_str(&format!(" ({})", description));
+    }
+    out.push_str(&format!(", updated {} ago:", ago(now, project.updated_at)));
+
+    let mut added = Vec::new();
+    for item in items.iter().filter(|i| i.added_at >= since) {
+        let label = match item.kind {
+            ItemKind::Memory => match item.item.parse().ok() {
+                Some(id) => match db.memory_get_by_id(id).await? {
+                    Some(memory) => format!("memory: {}", memory.content),
+                    None => continue,
+                },
+                None => continue,
+            },
+            kind => format!("{}: {}", kind.as_str(), item.item),
+        };
+        added.push(format!("- new {}", label));
+    }
+    if added.is_empty() {
+        out.push_str("\n- nothing new in the last day");
+    } else {
+        out.push_str(&format!("\n{}", added.join("\n")));
+    }
+
+    let mut active = Vec::new();
+    for item in items.iter().filter(|i| i.kind == ItemKind::Session) {
+        if let Some(session) = db.session_get(&item.item).await? {
+            if session.last_seen >= since {
+                active.push(item.clone());
+            }
+        }
+    }
+    for line in session_summaries(db, &active, None).await? {
+        out.push_str(&format!("\n- active {}", line.trim_start_matches("- ")));
+    }
+    Ok(out)
+}
+
+/// `prompt` with [`PLACEHOLDER`] replaced by the progress digest.
+pub async fn expand_placeholder(db: &Db, prompt: &str) -> String {
+    if !prompt.contains(PLACEHOLDER) {
+        return prompt.to_string();
+    }
+    let digest = match progress(db).await {
+        Ok(digest) => digest,
+        Err(e) => {
+            tracing::warn!("Failed to build project progress: {}", e);
+            "(project progress unavailable)".to_string()
+        }
+    };
+    prompt.replace(PLACEHOLDER, &digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_project_commands() {
+        let db = Db::open_memory().unwrap();
+        let reply = |text: &'static str| {
+            let db = db.clone();
+            async move { handle(&db, "tg-1", text).await.unwrap().unwrap() }
+        };
+        assert!(handle(&db, "tg-1", "hello").await.unwrap().is_none());
+        assert!(reply("/project add file x.md")
+            .await
+            .contains("Select a project"));
+        assert!(reply("/project rewrite")
+            .await
+            .contains("Working on 'rewrite'"));
+        assert_eq!(
+            reply("/project add file docs/plan.md").await,
+            "Added file docs/plan.md to 'rewrite'."
+        );
+        assert!(reply("/project add memory abc")
+            .await
+            .contains("not a memory ID"));
+        reply("/project note Port the daemon").await;
+        assert_eq!(
+            reply("/project").await,
+            "Project 'rewrite': 0 memories, 1 files, 1 sessions.\nPort the daemon"
+        );
+        assert!(reply("/project list")
+            .await
+            .starts_with("- rewrite (selected)"));
+        assert!(reply("/project bad/name").await.starts_with("Usage"));
+        assert_eq!(reply("/project off").await, "Project deselected.");
+        assert_eq!(db.project_selected("tg-1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_context_and_progress() {
+        let db = Db::open_memory().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let plan = dir.path().join("plan.md");
+        std::fs::write(&plan, "1. Replace the queue\n2. Ship it\n").unwrap();
+        let id = db
+            .memory_store(None, "The rewrite targets tokio 2", None, Some("agent"))
+            .await
+            .unwrap();
+
+        db.project_select("tg-1", Some("rewrite")).await.unwrap();
+        db.project_select("dc-9", Some("rewrite")).await.unwrap();
+        db.project_add("rewrite", ItemKind::Memory, &id.to_string())
+            .await
+            .unwrap();
+        db.project_add("rewrite", ItemKind::File, plan.to_str().unwrap())
+            .await
+            .unwrap();
+        db.memory_store(
+            Some("session_index:dc-9"),
+            "Session dc-9 summary: agreed on the queue design",
+            None,
+            Some("cortex:indexer"),
+        )
+        .await
+        .unwrap();
+        db.session_touch("discord", "dc-9", Some("9"), None, true)
+            .await
+            .unwrap();
+
+        let ctx = context(&db, "rewrite", "tg-1").await.unwrap();
+        assert!(ctx.starts_with("# Current project: rewrite"));
+        assert!(ctx.contains(&format!("- [#{}] The rewrite targets tokio 2", id)));
+        assert!(ctx.contains("1. Replace the queue\n2. Ship it\n```"));
+        assert!(ctx.contains("- dc-9: Session dc-9 summary: agreed on the queue design"));
+        // The session itself is not listed among the others
+        assert!(!ctx.contains("- tg-1:"));
+
+        let prompt = expand_placeholder(&db, "Morning briefing.\n\n{projects}").await;
+        assert!(prompt.starts_with("Morning briefing.\n\nProject rewrite, updated "));
+        assert!(prompt.contains("- new memory: The rewrite targets tokio 2"));
+        assert!(prompt.contains("- active dc-9: Session dc-9 summary"));
+        assert_eq!(expand_placeholder(&db, "No digest").await, "No digest");
+    }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("short\n"), "short");
+        let long = "x".repeat(FILE_EXCERPT_CHARS + 10);
+        assert!(excerpt(&long).ends_with("x\n[... truncated ...]"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Reaction commands on the agent's replies.
+//!
+//! Reacting to one of the agent's replies with one of these emoji acts on it
+//! (the emoji are set in `[reactions]`):
+//!
+//! ```text
+//! 🔁  regenerate: answer the same message again, in place of the reply
+//! 📌  pin: store the reply as a memory
+//! 🗑️  delete: remove the reply from the chat and the exchange from the tape
+//! ```
+//!
+//! The main loop records the platform message ID of each streamed reply in
+//! the `replies` table; adapters report reactions as `MessageKind::Reaction`
+//! with the ID of the message reacted to, and reactions on anything else are
+//! ignored. Only the latest reply of a session can be regenerated.
+
+use crate::config::ReactionsConfig;
+use std::fmt;
+use std::ops::Range;
+use yoagent::types::*;
+
+/// What a reaction asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionCommand {
+    Regenerate,
+    Pin,
+    Delete,
+}
+
+impl fmt::Display for ReactionCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReactionCommand::Regenerate => "regenerate",
+            ReactionCommand::Pin => "pin",
+            ReactionCommand::Delete => "delete",
+        })
+    }
+}
+
+/// The command `emoji` triggers. Variation selectors are ignored, so "🗑"
+/// matches "🗑️".
+pub fn parse(config: &ReactionsConfig, emoji: &str) -> Option<ReactionCommand> {
+    if !config.enabled {
+        return None;
+    }
+    let bare = |e: &str| e.trim_end_matches('\u{FE0F}').to_string();
+    let emoji = bare(emoji);
+    [
+        (ReactionCommand::Regenerate, &config.regenerate),
+        (ReactionCommand::Pin, &config.pin),
+        (ReactionCommand::Delete, &config.delete),
+    ]
+    .into_iter()
+    .find(|(_, list)| list.iter().any(|e| bare(e) == emoji))
+    .map(|(command, _)| command)
+}
+
+/// Text blocks of a message, joined.
+pub fn message_text(content: &[Content]) -> String {
+    content
+        .iter()
+        .filter_map(|c| match c {
+            Content::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Tape positions of the exchange ending in the reply `sent`: the user
+/// message it answered through the reply itself. `sent` is the text as
+/// posted, which may carry a notice before and sources after the tape's
+/// text. With `None`, the latest reply.
+pub fn find_exchange(messages: &[AgentMessage], sent: Option<&str>) -> Option<Range<usize>> {
+    let reply = messages.iter().rposition(|m| match m {
+        AgentMessage::Llm(Message::Assistant { content, .. }) => {
+            let text = message_text(content);
+            let text = text.trim();
+            !text.is_empty() && sent.map_or(true, |s| s.contains(text))
+        }
+        _ => false,
+    })?;
+    let start = messages[..reply]
+        .iter()
+        .rposition(|m| matches!(m, AgentMessage::Llm(Message::User { .. })))?;
+    Some(start..reply + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assistant(text: &str) -> AgentMessage {
+        AgentMessage::Llm(Message::Assistant {
+            content: vec![Content::Text {
+                text: text.to_string(),
+            }],
+            stop_reason: StopReason::Stop,
+            model: "mock".into(),
+            provider: "mock".into(),
+            usage: Usage::default(),
+            timestamp: 0,
+            error_message: None,
+        })
+    }
+
+    #[test]
+    fn test_parse() {
+        let config = ReactionsConfig::default();
+        assert_eq!(parse(&config, "🔁"), Some(ReactionCommand::Regenerate));
+        assert_eq!(parse(&config, "📌"), Some(ReactionCommand::Pin));
+        assert_eq!(parse(&config, "🗑️"), Some(ReactionCommand::Delete));
+        assert_eq!(parse(&config, "🗑"), Some(ReactionCommand::Delete));
+        assert_eq!(parse(&config, "👍"), None);
+
+        // Telegram only offers a fixed set of reactions
+        let config = ReactionsConfig {
+            regenerate: vec!["🔁".into(), "🤔".into()],
+            ..Default::default()
+        };
+        assert_eq!(parse(&config, "🤔"), Some(ReactionCommand::Regenerate));
+        let off = ReactionsConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(parse(&off, "🔁"), None);
+    }
+
+    #[test]
+    fn test_find_exchange() {
+        let messages = vec![
+            AgentMessage::Llm(Message::user("capital of France?")),
+            assistant("Paris."),
+            AgentMessage::Llm(Message::user("and Italy?")),
+            assistant("Rome."),
+            AgentMessage::Llm(Message::user("[The user edited an earlier message]")),
+        ];
+        assert_eq!(find_exchange(&messages, None), Some(2..4));
+        // Sources appended to the posted reply don't hide it
+        assert_eq!(
+            find_exchange(
+                &messages,
+                Some("Paris.\n\nSources:\n[1] https://example.com")
+            ),
+            Some(0..2)
+        );
+        assert_eq!(find_exchange(&messages, Some("Berlin.")), None);
+        assert_eq!(find_exchange(&messages[..1], None), None);
+    }
+}
@@ -0,0 +1,91 @@
+//! `/remember that`: save a quoted message as a memory.
+//!
+//! Replying to any message, the user's own, someone else's in a group or one
+//! of the agent's replies, with `/remember that` stores the quoted text as a
+//! high-importance fact, so users can curate memory directly instead of
+//! hoping the cortex pass picks the right facts. The text comes from the
+//! quote the platform sends with the reply (Telegram, Discord, Signal), or
+//! for the agent's own replies from the `replies` table. The memory's source
+//! records where it came from:
+//!
+//! ```text
+//! remember:telegram:Alice:2026-10-16T09:30:00Z
+//! ```
+//!
+//! Private sessions keep nothing, so the command is refused there.
+
+/// Importance of remembered messages, above the 6 of facts the cortex pass
+/// extracts and the 7 of pinned replies.
+pub const IMPORTANCE: i32 = 9;
+
+/// Tag of remembered messages.
+pub const TAG: &str = "remembered";
+
+/// Whether `text` is the `/remember that` command (`/remember` alone also
+/// works).
+pub fn is_command(text: &str) -> bool {
+    let mut words = text.split_whitespace();
+    if words.next() != Some("/remember") {
+        return false;
+    }
+    match (words.next(), words.next()) {
+        (None, _) => true,
+        (Some(word), None) => word.eq_ignore_ascii_case("that"),
+        _ => false,
+    }
+}
+
+/// Memory source for a message from `sender` on `channel`, sent at
+/// `timestamp` (ms since epoch). Unknown parts are left empty.
+pub fn source(channel: &str, sender: Option<&str>, timestamp: Option<u64>) -> String {
+    let time = timestamp
+        .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64))
+        .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default();
+    format!("remember:{}:{}:{}", channel, sender.unwrap_or(""), time)
+}
+
+/// Confirmation sent back once `text` is stored.
+pub fn stored_reply(text: &str) -> String {
+    const PREVIEW_CHARS: usize = 80;
+    let line = text.lines().next().unwrap_or("").trim();
+    if line.chars().count() > PREVIEW_CHARS || text.trim() != line {
+        let preview: String = line.chars().take(PREVIEW_CHARS).collect();
+        format!("Remembered: \"{}…\"", preview.trim_end())
+    } else {
+        format!("Remembered: \"{}\"", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_command() {
+        assert!(is_command("/remember that"));
+        assert!(is_command(" /remember  That "));
+        assert!(is_command("/remember"));
+        assert!(!is_command("/remember that I like tea"));
+        assert!(!is_command("/remembering"));
+        assert!(!is_command("please /remember that"));
+    }
+
+    #[test]
+    fn test_source_and_reply() {
+        assert_eq!(
+            source("telegram", Some("Alice"), Some(1_700_000_000_000)),
+            "remember:telegram:Alice:2023-11-14T22:13:20Z"
+        );
+        assert_eq!(source("web", None, None), "remember:web::");
+        assert_eq!(
+            stored_reply("Tea, no sugar"),
+            "Remembered: \"Tea, no sugar\""
+        );
+        assert_eq!(
+            stored_reply("First line\nsecond line"),
+            "Remembered: \"First line…\""
+        );
+        assert!(stored_reply(&"x".repeat(200)).ends_with("…\""));
+    }
+}
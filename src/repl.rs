@@ -0,0 +1,195 @@
+//! `yoclaw chat`: a terminal conversation with the agent, for trying out
+//! personas and skills without a channel.
+//!
+//! Lines go straight to the Conductor, so session commands (`/stats`,
+//! `/private`, `/project`, ...) work as they do on any channel. A few more
+//! are handled by the REPL itself, listed in [`HELP`].
+
+use yoagent::types::*;
+
+/// Session used when `--session` isn't given.
+pub const DEFAULT_SESSION: &str = "cli-local";
+
+pub const HELP: &str = "\
+/new              set this conversation aside and start an empty one
+/history [n]      show the last n messages (default 10)
+/budget           show today's token usage and limits
+/session <id>     switch to another session
+/help             show this list
+/quit             leave (Ctrl-D works too)";
+
+/// A command the REPL handles itself.
+#[derive(Debug, PartialEq)]
+pub enum ReplCommand {
+    New,
+    History(usize),
+    Budget,
+    Session(String),
+    Help,
+    Quit,
+}
+
+/// The REPL command in `line`. `None` for messages to the agent, including
+/// slash commands the Conductor answers.
+pub fn parse(line: &str) -> Option<Result<ReplCommand, String>> {
+    let mut words = line.split_whitespace();
+    let command = match words.next()? {
+        "/new" => Ok(ReplCommand::New),
+        "/history" => match words.next() {
+            None => Ok(ReplCommand::History(10)),
+            Some(n) => n
+                .parse()
+                .map(ReplCommand::History)
+                .map_err(|_| format!("Not a number: {}", n)),
+        },
+        "/budget" => Ok(ReplCommand::Budget),
+        "/session" => match words.next() {
+            Some(id) => Ok(ReplCommand::Session(id.to_string())),
+            None => Err("Usage: /session <id>".to_string()),
+        },
+        "/help" => Ok(ReplCommand::Help),
+        "/quit" | "/exit" => Ok(ReplCommand::Quit),
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// The last `limit` user and assistant messages of a tape, one block each.
+/// Tool calls and results are summarized to their tool name.
+pub fn format_history(messages: &[AgentMessage], limit: usize) -> String {
+    let mut entries = Vec::new();
+    for msg in messages {
+        let AgentMessage::Llm(msg) = msg else {
+            continue;
+        };
+        match msg {
+            Message::User { content, .. } => {
+                let text = text_of(content);
+                if !text.is_empty() {
+                    entries.push(format!("you> {}", text));
+                }
+            }
+            Message::Assistant { content, .. } => {
+                let text = text_of(content);
+                if !text.is_empty() {
+                    entries.push(format!("agent> {}", text));
+                }
+                for c in content {
+                    if let Content::ToolCall { name, .. } = c {
+                        entries.push(format!("  [tool: {}]", name));
+                    }
+                }
+            }
+            Message::ToolResult { .. } => {}
+        }
+    }
+    if entries.is_empty() {
+        return "(no messages yet)".to_string();
+    }
+    let start = entries.len().saturating_sub(limit);
+    entries[start..].join("\n")
+}
+
+/// `/budget` output.
+pub fn format_budget(
+    used_today: u64,
+    daily_limit: Option<u64>,
+    max_turns: Option<usize>,
+) -> String {
+    let mut out = match daily_limit {
+        Some(limit) => format!(
+            "Tokens today: {} of {} ({} left)",
+            used_today,
+            limit,
+            limit.saturating_sub(used_today)
+        ),
+        None => format!("Tokens today: {} (no daily limit)", used_today),
+    };
+    if let Some(turns) = max_turns {
+        out.push_str(&format!("\nTurns per session: at most {}", turns));
+    }
+    out
+}
+
+fn text_of(content: &[Content]) -> String {
+    content
+        .iter()
+        .filter_map(|c| match c {
+            Content::Text { text } => Some(text.trim()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("/new"), Some(Ok(ReplCommand::New)));
+        assert_eq!(parse("/history"), Some(Ok(ReplCommand::History(10))));
+        assert_eq!(parse(" /history 3 "), Some(Ok(ReplCommand::History(3))));
+        assert!(matches!(parse("/history many"), Some(Err(_))));
+        assert_eq!(
+            parse("/session tg-42"),
+            Some(Ok(ReplCommand::Session("tg-42".into())))
+        );
+        assert!(matches!(parse("/session"), Some(Err(_))));
+        assert_eq!(parse("/exit"), Some(Ok(ReplCommand::Quit)));
+        // Left to the agent and the Conductor
+        assert_eq!(parse("/stats"), None);
+        assert_eq!(parse("hello /new"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_format_history() {
+        let messages = vec![
+            AgentMessage::Llm(Message::user("What's the weather?")),
+            AgentMessage::Llm(Message::Assistant {
+                content: vec![Content::ToolCall {
+                    id: "tc-1".into(),
+                    name: "http".into(),
+                    arguments: serde_json::json!({}),
+                }],
+                stop_reason: StopReason::ToolUse,
+                model: "mock".into(),
+                provider: "mock".into(),
+                usage: Usage::default(),
+                timestamp: 0,
+                error_message: None,
+            }),
+            AgentMessage::Llm(Message::Assistant {
+                content: vec![Content::Text {
+                    text: "Sunny, 21°C.".into(),
+                }],
+                stop_reason: StopReason::Stop,
+                model: "mock".into(),
+                provider: "mock".into(),
+                usage: Usage::default(),
+                timestamp: 0,
+                error_message: None,
+            }),
+        ];
+        assert_eq!(
+            format_history(&messages, 10),
+            "you> What's the weather?\n  [tool: http]\nagent> Sunny, 21°C."
+        );
+        assert_eq!(format_history(&messages, 1), "agent> Sunny, 21°C.");
+        assert_eq!(format_history(&[], 10), "(no messages yet)");
+    }
+
+    #[test]
+    fn test_format_budget() {
+        assert_eq!(
+            format_budget(1200, Some(5000), None),
+            "Tokens today: 1200 of 5000 (3800 left)"
+        );
+        assert_eq!(
+            format_budget(1200, None, Some(20)),
+            "Tokens today: 1200 (no daily limit)\nTurns per session: at most 20"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Processing failed messages again.
+//!
+//! After an outage, `yoclaw queue retry-failed --since 2h --pace 10s` (or
+//! `POST /api/queue/retry`) puts every message that failed in the window
+//! back in line, oldest first. The entries get status `retry` and a
+//! `retry_at` spaced `pace` apart, so the provider sees them one at a time
+//! rather than all at once. The daemon checks for due retries on its
+//! 5-second tick and hands them to the usual dispatch, which treats them
+//! like messages released from a pause: pauses, routing and sessions apply
+//! as for a new message, but nothing is queued or greeted twice.
+
+use crate::channels::{IncomingMessage, MessageKind};
+use crate::db::queue::QueueEntry;
+use crate::db::sessions::SessionInfo;
+use crate::db::{Db, DbError};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RetryError {
+    #[error("Invalid duration '{0}': use a number with s, m, h or d, like 90s or 2h")]
+    Duration(String),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+/// Pace of a bulk retry when none is given.
+pub const DEFAULT_PACE: Duration = Duration::from_secs(10);
+
+/// Parse a duration like `90s`, `15m`, `2h` or `1d`.
+pub fn parse_duration(s: &str) -> Result<Duration, RetryError> {
+    let s = s.trim();
+    let invalid = || RetryError::Duration(s.to_string());
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let value: u64 = s[..split].parse().map_err(|_| invalid())?;
+    let unit = match &s[split..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    value
+        .checked_mul(unit)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// Queue the messages that failed in the last `since` for another attempt,
+/// `pace` apart. Returns how many.
+pub async fn retry_failed(db: &Db, since: Duration, pace: Duration) -> Result<usize, RetryError> {
+    let since_ms = crate::db::now_ms().saturating_sub(since.as_millis() as u64);
+    Ok(db
+        .queue_retry_failed(since_ms, pace.as_millis() as u64)
+        .await?)
+}
+
+/// Rebuild the message of a queue entry, with where its session lives.
+pub fn to_incoming(entry: &QueueEntry, session: Option<&SessionInfo>) -> IncomingMessage {
+    IncomingMessage {
+        channel: entry.channel.clone(),
+        sender_id: entry.sender_id.clone(),
+        sender_name: entry.sender_name.clone(),
+        session_id: entry.session_id.clone(),
+        chat_id: session.and_then(|s| s.chat_id.clone()),
+        thread_id: session.and_then(|s| s.thread_id.clone()),
+        content: entry.content.clone(),
+        reply_to: entry.reply_to.clone(),
+        quote: None,
+        timestamp: entry.created_at,
+        worker_hint: None,
+        is_group: session.is_some_and(|s| s.is_group),
+        message_id: None,
+        kind: MessageKind::New,
+        attachments: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_duration(" 2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+        assert_eq!(parse_duration("0s").unwrap(), Duration::ZERO);
+        for bad in ["2", "h", "2w", "-1h", "1.5h", ""] {
+            assert!(
+                matches!(parse_duration(bad), Err(RetryError::Duration(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed() {
+        let db = Db::open_memory().unwrap();
+        let id = db
+            .queue_push(&QueueEntry::new("telegram", "42", "tg-group-1", "hi"))
+            .await
+            .unwrap();
+        db.queue_mark_failed(id, &crate::db::queue::QueueFailure::unknown("HTTP 529"))
+            .await
+            .unwrap();
+        db.session_touch("telegram", "tg-group-1", Some("-100"), None, true)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            retry_failed(&db, Duration::from_secs(3600), DEFAULT_PACE)
+                .await
+                .unwrap(),
+            1
+        );
+        let entry = db.queue_take_retries().await.unwrap().remove(0);
+        let session = db.session_get(&entry.session_id).await.unwrap();
+        let incoming = to_incoming(&entry, session.as_ref());
+        assert_eq!(incoming.content, "hi");
+        assert_eq!(incoming.chat_id.as_deref(), Some("-100"));
+        assert!(incoming.is_group);
+        assert_eq!(incoming.kind, MessageKind::New);
+    }
+}
@@ -0,0 +1,364 @@
+//! Declarative message routing.
+//!
+//! Rules from `[[routing.rules]]` are evaluated in order against each incoming
+//! message before it reaches the conductor. The first matching rule decides
+//! what happens to the message; unmatched messages take the normal path
+//! (including Discord's per-channel `worker_hint`).
+
+use crate::channels::IncomingMessage;
+use crate::config::{RouteRuleConfig, RoutingConfig};
+use chrono::NaiveTime;
+use regex::Regex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoutingError {
+    #[error("Rule '{rule}': invalid pattern: {source}")]
+    InvalidPattern { rule: String, source: regex::Error },
+    #[error("Rule '{rule}': invalid hours '{hours}' (expected HH:MM-HH:MM)")]
+    InvalidHours { rule: String, hours: String },
+    #[error("Rule '{rule}': invalid chat '{chat}' (expected \"group\" or \"dm\")")]
+    InvalidChat { rule: String, chat: String },
+    #[error("Rule '{rule}': exactly one of worker, pipeline, model or reply must be set")]
+    InvalidAction { rule: String },
+}
+
+/// What to do with a message matched by a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteAction {
+    /// Send straight to a named worker.
+    Worker(String),
+    /// Run workers in sequence, feeding each one the previous output.
+    Pipeline(Vec<String>),
+    /// Process with the main agent using a different model.
+    Model(String),
+    /// Reply with a rendered template without involving any agent.
+    Reply(String),
+}
+
+impl std::fmt::Display for RouteAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteAction::Worker(w) => write!(f, "worker {}", w),
+            RouteAction::Pipeline(ws) => write!(f, "pipeline {}", ws.join(" > ")),
+            RouteAction::Model(m) => write!(f, "model {}", m),
+            RouteAction::Reply(_) => write!(f, "auto-reply"),
+        }
+    }
+}
+
+/// A rule that matched an incoming message.
+#[derive(Debug, Clone)]
+pub struct RouteMatch<'a> {
+    pub rule: &'a str,
+    pub action: &'a RouteAction,
+}
+
+struct Rule {
+    name: String,
+    channel: Option<String>,
+    sender: Option<String>,
+    pattern: Option<Regex>,
+    hours: Option<(NaiveTime, NaiveTime)>,
+    is_group: Option<bool>,
+    action: RouteAction,
+}
+
+impl Rule {
+    fn compile(index: usize, cfg: &RouteRuleConfig) -> Result<Self, RoutingError> {
+        let name = cfg
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("rule-{}", index + 1));
+
+        let pattern = match cfg.pattern {
+            Some(ref p) => Some(
+                Regex::new(p).map_err(|source| RoutingError::InvalidPattern {
+                    rule: name.clone(),
+                    source,
+                })?,
+            ),
+            None => None,
+        };
+
+        let hours = match cfg.hours {
+            Some(ref h) => Some(parse_hours(h).ok_or_else(|| RoutingError::InvalidHours {
+                rule: name.clone(),
+                hours: h.clone(),
+            })?),
+            None => None,
+        };
+
+        let is_group = match cfg.chat.as_deref() {
+            None => None,
+            Some("group") => Some(true),
+            Some("dm") => Some(false),
+            Some(other) => {
+                return Err(RoutingError::InvalidChat {
+                    rule: name,
+                    chat: other.to_string(),
+                })
+            }
+        };
+
+        let mut actions = Vec::new();
+        if let Some(ref w) = cfg.worker {
+            actions.push(RouteAction::Worker(w.clone()));
+        }
+        if !cfg.pipeline.is_empty() {
+            actions.push(RouteAction::Pipeline(cfg.pipeline.clone()));
+        }
+        if let Some(ref m) = cfg.model {
+            actions.push(RouteAction::Model(m.clone()));
+        }
+        if let Some(ref r) = cfg.reply {
+            actions.push(RouteAction::Reply(r.clone()));
+        }
+        if actions.len() != 1 {
+            return Err(RoutingError::InvalidAction { rule: name });
+        }
+
+        Ok(Self {
+            name,
+            channel: cfg.channel.clone(),
+            sender: cfg.sender.clone(),
+            pattern,
+            hours,
+            is_group,
+            action: actions.remove(0),
+        })
+    }
+
+    fn matches(&self, msg: &IncomingMessage, now: NaiveTime) -> bool {
+        if let Some(ref channel) = self.channel {
+            if *channel != msg.channel {
+                return false;
+            }
+        }
+        if let Some(ref sender) = self.sender {
+            if *sender != msg.sender_id && msg.sender_name.as_ref() != Some(sender) {
+                return false;
+            }
+        }
+        if let Some(is_group) = self.is_group {
+            if is_group != msg.is_group {
+                return false;
+            }
+        }
+        if let Some((start, end)) = self.hours {
+            let inside = if start <= end {
+                now >= start && now < end
+            } else {
+                // Window wraps past midnight, e.g. 22:00-07:00
+                now >= start || now < end
+            };
+            if !inside {
+                return false;
+            }
+        }
+        if let Some(ref pattern) = self.pattern {
+            if !pattern.is_match(&msg.content) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Compiled routing rules.
+#[derive(Default)]
+pub struct Router {
+    rules: Vec<Rule>,
+}
+
+impl Router {
+    /// Compile rules from config. Fails on the first invalid rule.
+    pub fn from_config(config: &RoutingConfig) -> Result<Self, RoutingError> {
+        let rules = config
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(i, r)| Rule::compile(i, r))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Find the first rule matching `msg`, using the local time of day.
+    pub fn evaluate(&self, msg: &IncomingMessage) -> Option<RouteMatch<'_>> {
+        self.evaluate_at(msg, chrono::Local::now().time())
+    }
+
+    fn evaluate_at(&self, msg: &IncomingMessage, now: NaiveTime) -> Option<RouteMatch<'_>> {
+        self.rules
+            .iter()
+            .find(|r| r.matches(msg, now))
+            .map(|r| RouteMatch {
+                rule: &r.name,
+                action: &r.action,
+            })
+    }
+}
+
+/// Fill in an auto-reply template.
+pub fn render_reply(template: &str, msg: &IncomingMessage) -> String {
+    template
+        .replace(
+            "{sender}",
+            msg.sender_name.as_deref().unwrap_or(&msg.sender_id),
+        )
+        .replace("{channel}", &msg.channel)
+        .replace("{content}", &msg.content)
+}
+
+/// Parse "HH:MM-HH:MM".
+fn parse_hours(s: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = s.split_once('-')?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(channel: &str, content: &str, is_group: bool) -> IncomingMessage {
+        IncomingMessage {
+            channel: channel.to_string(),
+            sender_id: "42".to_string(),
+            sender_name: Some("alice".to_string()),
+            session_id: format!("{}-42", channel),
+            chat_id: Some("42".to_string()),
+            thread_id: None,
+            content: content.to_string(),
+            reply_to: None,
+            quote: None,
+            timestamp: 0,
+            worker_hint: None,
+            is_group,
+            message_id: None,
+            kind: Default::default(),
+            attachments: Vec::new(),
+        }
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    fn router(toml: &str) -> Router {
+        let config: RoutingConfig = toml::from_str(toml).unwrap();
+        Router::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let r = router(
+            r#"
+[[rules]]
+name = "deploys"
+pattern = "(?i)deploy"
+worker = "ops"
+
+[[rules]]
+name = "discord"
+channel = "discord"
+model = "claude-haiku-4-5-20251001"
+"#,
+        );
+        let m = r
+            .evaluate_at(&msg("discord", "Deploy now", false), time("12:00"))
+            .unwrap();
+        assert_eq!(m.rule, "deploys");
+        assert_eq!(*m.action, RouteAction::Worker("ops".into()));
+
+        let m = r
+            .evaluate_at(&msg("discord", "hello", false), time("12:00"))
+            .unwrap();
+        assert_eq!(m.rule, "discord");
+
+        assert!(r
+            .evaluate_at(&msg("telegram", "hello", false), time("12:00"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_sender_and_chat_conditions() {
+        let r = router(
+            r#"
+[[rules]]
+sender = "alice"
+chat = "group"
+pipeline = ["research", "writer"]
+"#,
+        );
+        let m = r
+            .evaluate_at(&msg("slack", "hi", true), time("12:00"))
+            .unwrap();
+        assert_eq!(m.rule, "rule-1");
+        assert_eq!(
+            *m.action,
+            RouteAction::Pipeline(vec!["research".into(), "writer".into()])
+        );
+        assert!(r
+            .evaluate_at(&msg("slack", "hi", false), time("12:00"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_hours_window_wraps_midnight() {
+        let r = router(
+            r#"
+[[rules]]
+hours = "22:00-07:00"
+reply = "Offline, {sender}. Got: {content}"
+"#,
+        );
+        let m = msg("telegram", "ping", false);
+        assert!(r.evaluate_at(&m, time("23:30")).is_some());
+        assert!(r.evaluate_at(&m, time("06:59")).is_some());
+        assert!(r.evaluate_at(&m, time("07:00")).is_none());
+        assert!(r.evaluate_at(&m, time("12:00")).is_none());
+
+        let RouteAction::Reply(ref template) = *r.evaluate_at(&m, time("23:30")).unwrap().action
+        else {
+            panic!("expected reply action");
+        };
+        assert_eq!(render_reply(template, &m), "Offline, alice. Got: ping");
+    }
+
+    #[test]
+    fn test_invalid_rules_rejected() {
+        let parse = |toml: &str| {
+            let config: RoutingConfig = toml::from_str(toml).unwrap();
+            Router::from_config(&config)
+        };
+        assert!(matches!(
+            parse("[[rules]]\npattern = \"(\"\nworker = \"a\""),
+            Err(RoutingError::InvalidPattern { .. })
+        ));
+        assert!(matches!(
+            parse("[[rules]]\nhours = \"late\"\nworker = \"a\""),
+            Err(RoutingError::InvalidHours { .. })
+        ));
+        assert!(matches!(
+            parse("[[rules]]\nchat = \"channel\"\nworker = \"a\""),
+            Err(RoutingError::InvalidChat { .. })
+        ));
+        assert!(matches!(
+            parse("[[rules]]\nchannel = \"discord\""),
+            Err(RoutingError::InvalidAction { .. })
+        ));
+        assert!(matches!(
+            parse("[[rules]]\nworker = \"a\"\nreply = \"b\""),
+            Err(RoutingError::InvalidAction { .. })
+        ));
+    }
+}
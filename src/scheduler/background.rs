@@ -0,0 +1,236 @@
+//! Background tasks.
+//!
+//! `/background <task>` and the `work_on_this_later` tool queue a task in the
+//! `background_tasks` table instead of answering in the interactive turn. A
+//! fixed number of worker loops pick tasks up oldest first, run each with an
+//! ephemeral agent (like isolated cron jobs), and deliver the result to the
+//! session that queued it. `/jobs` shows the session's recent tasks.
+
+use super::AgentRunConfig;
+use crate::channels::OutgoingMessage;
+use crate::config::Config;
+use crate::db::background::BackgroundTask;
+use crate::db::{now_ms, Db, DbError};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const SYSTEM_PROMPT: &str = "You are a background task agent. The user asked for this to be \
+                             done later, so work through it thoroughly and reply with the \
+                             complete result.";
+
+/// Tasks shown by `/jobs`.
+const JOBS_LIMIT: usize = 10;
+
+/// Runs queued background tasks and delivers their results.
+#[derive(Clone)]
+pub struct BackgroundRunner {
+    db: Db,
+    agent_config: AgentRunConfig,
+    delivery_tx: mpsc::UnboundedSender<OutgoingMessage>,
+    workers: usize,
+    poll: Duration,
+}
+
+impl BackgroundRunner {
+    pub fn new(
+        db: Db,
+        config: &Config,
+        delivery_tx: mpsc::UnboundedSender<OutgoingMessage>,
+    ) -> Self {
+        Self {
+            db,
+            agent_config: AgentRunConfig {
+                provider: config.agent.provider.clone(),
+                model: config.agent.model.clone(),
+                api_key: config.agent.api_key.clone(),
+                endpoint: crate::conductor::endpoint::Endpoint::agent(&config.agent),
+                context: config.agent.context.clone(),
+                max_tokens: None,
+                stream_provider: None,
+                fallback_providers: Vec::new(),
+            },
+            delivery_tx,
+            workers: config.background.workers.max(1),
+            poll: Duration::from_secs(config.background.poll_interval_secs.max(1)),
+        }
+    }
+
+    /// Requeue tasks interrupted by a restart, then spawn the worker loops.
+    pub async fn start(self) {
+        match self.db.background_requeue_running().await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Requeued {} interrupted background task(s)", n),
+            Err(e) => tracing::error!("Failed to requeue background tasks: {}", e),
+        }
+        tracing::info!("Background tasks started ({} worker(s))", self.workers);
+        for _ in 0..self.workers {
+            let runner = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    match runner.run_next().await {
+                        Ok(true) => continue,
+                        Ok(false) => {}
+                        Err(e) => tracing::error!("Background task error: {}", e),
+                    }
+                    tokio::time::sleep(runner.poll).await;
+                }
+            });
+        }
+    }
+
+    /// Run the oldest pending task, if any. Returns whether one ran.
+    async fn run_next(&self) -> Result<bool, DbError> {
+        // Tasks stay queued in read-only mode
+        if self.db.pause_check(None).await?.is_some() {
+            return Ok(false);
+        }
+        let Some(task) = self.db.background_claim().await? else {
+            return Ok(false);
+        };
+        tracing::info!(
+            "Running background task #{} for {}",
+            task.id,
+            task.session_id
+        );
+
+        let result =
+            super::run_ephemeral_prompt(&self.agent_config, SYSTEM_PROMPT, &task.task).await;
+        let content = match result {
+            Ok(response) => {
+                self.db.background_finish(task.id, true, &response).await?;
+                format!("Background task #{} is done:\n\n{}", task.id, response)
+            }
+            Err(e) => {
+                tracing::error!("Background task #{} failed: {}", task.id, e);
+                self.db
+                    .background_finish(task.id, false, &e.to_string())
+                    .await?;
+                format!("Background task #{} failed: {}", task.id, e)
+            }
+        };
+
+        let _ = self.delivery_tx.send(OutgoingMessage {
+            channel: super::cron::resolve_channel(&self.db, &task.session_id).await,
+            session_id: task.session_id,
+            content,
+            reply_to: None,
+        });
+        Ok(true)
+    }
+}
+
+/// Reply for `/jobs`: the session's recent background tasks.
+pub fn format_jobs(tasks: &[BackgroundTask]) -> String {
+    if tasks.is_empty() {
+        return "No background tasks. Start one with /background <task>.".to_string();
+    }
+    let now = now_ms();
+    let lines: Vec<String> = tasks
+        .iter()
+        .map(|t| {
+            let since = match t.status.as_str() {
+                "pending" => format!("queued {} ago", ago(now, t.created_at)),
+                "running" => format!(
+                    "started {} ago",
+                    ago(now, t.started_at.unwrap_or(t.created_at))
+                ),
+                _ => format!(
+                    "finished {} ago",
+                    ago(now, t.finished_at.unwrap_or(t.created_at))
+                ),
+            };
+            format!(
+                "#{} [{}] {} ({})",
+                t.id,
+                t.status,
+                super::tools::truncate_str(&t.task, 60),
+                since
+            )
+        })
+        .collect();
+    format!("Background tasks:\n{}", lines.join("\n"))
+}
+
+/// Most recent tasks for `/jobs`.
+pub async fn list_jobs(db: &Db, session_id: &str) -> Result<String, DbError> {
+    Ok(format_jobs(
+        &db.background_list(session_id, JOBS_LIMIT).await?,
+    ))
+}
+
+/// "42s", "5m", "3h" or "2d".
+pub(crate) fn ago(now_ms: u64, then_ms: u64) -> String {
+    let secs = now_ms.saturating_sub(then_ms) / 1000;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86_399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_jobs() {
+        assert!(format_jobs(&[]).starts_with("No background tasks"));
+
+        let now = now_ms();
+        let tasks = vec![BackgroundTask {
+            id: 7,
+            session_id: "tg-1".into(),
+            task: "compare the three vendors".into(),
+            status: "running".into(),
+            result: None,
+            created_at: now - 600_000,
+            started_at: Some(now - 120_000),
+            finished_at: None,
+        }];
+        assert_eq!(
+            format_jobs(&tasks),
+            "Background tasks:\n#7 [running] compare the three vendors (started 2m ago)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_next_delivers_to_session() {
+        let db = Db::open_memory().unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let runner = BackgroundRunner {
+            db: db.clone(),
+            agent_config: AgentRunConfig {
+                provider: "anthropic".to_string(),
+                model: "mock".to_string(),
+                api_key: "test-key".to_string(),
+                endpoint: Default::default(),
+                context: Default::default(),
+                max_tokens: None,
+                stream_provider: None,
+                fallback_providers: Vec::new(),
+            },
+            delivery_tx: tx,
+            workers: 1,
+            poll: Duration::from_secs(1),
+        };
+
+        assert!(!runner.run_next().await.unwrap());
+
+        // The fake API key makes the agent call fail or come back empty;
+        // either way the task finishes and the session hears about it
+        let id = db
+            .background_enqueue("tg-1", "write a report")
+            .await
+            .unwrap();
+        assert!(runner.run_next().await.unwrap());
+
+        let task = &db.background_list("tg-1", 10).await.unwrap()[0];
+        assert_eq!(task.id, id);
+        assert_ne!(task.status, "running");
+        let msg = rx.try_recv().unwrap();
+        assert_eq!(msg.channel, "telegram");
+        assert_eq!(msg.session_id, "tg-1");
+        assert!(msg.content.starts_with(&format!("Background task #{}", id)));
+    }
+}
@@ -52,74 +52,108 @@ pub async fn run_maintenance(db: &Db, agent_config: &AgentRunConfig) -> Result<S
     }
 }
 
+/// Whether anything happened since the last pass at `since` (ms) that a pass
+/// could act on: a non-private session with new messages, or new memories.
+pub async fn has_work(db: &Db, since: u64) -> Result<bool, DbError> {
+    db.exec_read(move |conn| {
+        let sessions: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM tape
+             WHERE updated_at > ?1 AND message_count >= 2
+               AND 'private:' || session_id NOT IN (SELECT key FROM state)",
+            rusqlite::params![since as i64],
+            |r| r.get(0),
+        )?;
+        if sessions > 0 {
+            return Ok(true);
+        }
+        let memories: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM memory WHERE created_at > ?1",
+            rusqlite::params![since as i64],
+            |r| r.get(0),
+        )?;
+        Ok(memories > 0)
+    })
+    .await
+}
+
 /// Remove memory entries not accessed in 90+ days with importance <= 3.
 async fn cleanup_stale_memories(db: &Db) -> Result<usize, DbError> {
     let now = now_ms();
     let ninety_days_ms: u64 = 90 * 24 * 60 * 60 * 1000;
     let cutoff = now.saturating_sub(ninety_days_ms) as i64;
 
-    db.exec(move |conn| {
-        // Clean up vector embeddings before deleting memories
-        #[cfg(feature = "semantic")]
-        {
-            if crate::db::vector::vec_table_exists(conn) {
-                let mut stmt = conn.prepare(
-                    "SELECT id FROM memory WHERE importance <= 3
+    let deleted = db
+        .exec(move |conn| {
+            // Clean up vector embeddings before deleting memories
+            #[cfg(feature = "semantic")]
+            {
+                if crate::db::vector::vec_table_exists(conn) {
+                    let mut stmt = conn.prepare(
+                        "SELECT id FROM memory WHERE importance <= 3
                      AND (last_accessed IS NOT NULL AND last_accessed < ?1)
                      AND category != 'decision'",
-                )?;
-                let ids: Vec<i64> = stmt
-                    .query_map(rusqlite::params![cutoff], |r| r.get(0))?
-                    .filter_map(|r| r.ok())
-                    .collect();
-                for id in &ids {
-                    crate::db::vector::vec_delete(conn, *id).ok();
+                    )?;
+                    let ids: Vec<i64> = stmt
+                        .query_map(rusqlite::params![cutoff], |r| r.get(0))?
+                        .filter_map(|r| r.ok())
+                        .collect();
+                    for id in &ids {
+                        crate::db::vector::vec_delete(conn, *id).ok();
+                    }
                 }
             }
-        }
 
-        let deleted = conn.execute(
-            "DELETE FROM memory WHERE importance <= 3
+            let deleted = conn.execute(
+                "DELETE FROM memory WHERE importance <= 3
              AND (last_accessed IS NOT NULL AND last_accessed < ?1)
              AND category != 'decision'",
-            rusqlite::params![cutoff],
-        )?;
-        Ok(deleted)
-    })
-    .await
+                rusqlite::params![cutoff],
+            )?;
+            Ok(deleted)
+        })
+        .await?;
+    if deleted > 0 {
+        db.memory_cache_invalidate();
+    }
+    Ok(deleted)
 }
 
 /// Remove exact duplicate memory entries (keep the most recently updated).
 async fn deduplicate_memories(db: &Db) -> Result<usize, DbError> {
-    db.exec(|conn| {
-        // Clean up vector embeddings before deleting duplicate memories
-        #[cfg(feature = "semantic")]
-        {
-            if crate::db::vector::vec_table_exists(conn) {
-                let mut stmt = conn.prepare(
-                    "SELECT id FROM memory WHERE id NOT IN (
+    let deleted = db
+        .exec(|conn| {
+            // Clean up vector embeddings before deleting duplicate memories
+            #[cfg(feature = "semantic")]
+            {
+                if crate::db::vector::vec_table_exists(conn) {
+                    let mut stmt = conn.prepare(
+                        "SELECT id FROM memory WHERE id NOT IN (
                         SELECT MAX(id) FROM memory GROUP BY content
                     )",
-                )?;
-                let ids: Vec<i64> = stmt
-                    .query_map([], |r| r.get(0))?
-                    .filter_map(|r| r.ok())
-                    .collect();
-                for id in &ids {
-                    crate::db::vector::vec_delete(conn, *id).ok();
+                    )?;
+                    let ids: Vec<i64> = stmt
+                        .query_map([], |r| r.get(0))?
+                        .filter_map(|r| r.ok())
+                        .collect();
+                    for id in &ids {
+                        crate::db::vector::vec_delete(conn, *id).ok();
+                    }
                 }
             }
-        }
 
-        let deleted = conn.execute(
-            "DELETE FROM memory WHERE id NOT IN (
+            let deleted = conn.execute(
+                "DELETE FROM memory WHERE id NOT IN (
                 SELECT MAX(id) FROM memory GROUP BY content
             )",
-            [],
-        )?;
-        Ok(deleted)
-    })
-    .await
+                [],
+            )?;
+            Ok(deleted)
+        })
+        .await?;
+    if deleted > 0 {
+        db.memory_cache_invalidate();
+    }
+    Ok(deleted)
 }
 
 /// Extract durable facts from recent conversations and store them as memories.
@@ -146,10 +180,14 @@ async fn consolidate_memories(
     // Check which sessions have already been consolidated (via state table)
     let mut to_consolidate = Vec::new();
     for session in &recent {
+        // Private sessions are never mined for memories
+        if db.privacy_is_private(&session.session_id).await? {
+            continue;
+        }
         let sid = session.session_id.clone();
         let key = format!("cortex_consolidated:{}", sid);
         let already_done = db
-            .exec(move |conn| {
+            .exec_read(move |conn| {
                 let count: i64 = conn.query_row(
                     "SELECT COUNT(*) FROM state WHERE key = ?1",
                     rusqlite::params![key],
@@ -267,11 +305,14 @@ async fn index_recent_sessions(
     let mut indexed = 0;
 
     for session in recent.iter().take(5) {
+        if db.privacy_is_private(&session.session_id).await? {
+            continue;
+        }
         let key = format!("session_index:{}", session.session_id);
 
         // Skip if already indexed
         let already = db
-            .exec({
+            .exec_read({
                 let key = key.clone();
                 move |conn| {
                     let count: i64 = conn.query_row(
@@ -347,7 +388,7 @@ async fn index_recent_sessions(
 }
 
 /// Extract readable text from conversation messages, truncated to max_chars.
-fn extract_conversation_text(messages: &[AgentMessage], max_chars: usize) -> String {
+pub(super) fn extract_conversation_text(messages: &[AgentMessage], max_chars: usize) -> String {
     let mut text = String::new();
 
     for msg in messages {
@@ -381,7 +422,11 @@ mod tests {
             provider: "anthropic".to_string(),
             model: "mock".to_string(),
             api_key: "test-key".to_string(),
+            endpoint: Default::default(),
             context: Default::default(),
+            max_tokens: None,
+            stream_provider: None,
+            fallback_providers: Vec::new(),
         }
     }
 
@@ -480,6 +525,34 @@ mod tests {
         assert_eq!(summary, "no maintenance needed");
     }
 
+    #[tokio::test]
+    async fn test_has_work() {
+        let db = Db::open_memory().unwrap();
+        let since = now_ms();
+        assert!(!has_work(&db, since).await.unwrap());
+
+        // A one-message session isn't worth a pass, a conversation is
+        let one = vec![AgentMessage::Llm(Message::user("hi"))];
+        db.tape_save_messages("tg-1", &one).await.unwrap();
+        assert!(!has_work(&db, since - 1).await.unwrap());
+        let two = vec![
+            AgentMessage::Llm(Message::user("hi")),
+            AgentMessage::Llm(Message::user("still there?")),
+        ];
+        db.tape_save_messages("tg-1", &two).await.unwrap();
+        assert!(has_work(&db, since - 1).await.unwrap());
+
+        // Private sessions are never mined, so they don't count
+        db.privacy_set("tg-1", true).await.unwrap();
+        assert!(!has_work(&db, since - 1).await.unwrap());
+
+        db.memory_store(None, "likes tea", None, Some("test"))
+            .await
+            .unwrap();
+        assert!(has_work(&db, since - 1).await.unwrap());
+        assert!(!has_work(&db, now_ms() + 1).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_extract_conversation_text() {
         use yoagent::types::{Content, Message, StopReason, Usage};
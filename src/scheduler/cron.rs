@@ -2,15 +2,20 @@
 
 use super::AgentRunConfig;
 use crate::channels::OutgoingMessage;
+use crate::config::{Config, CronConfig};
 use crate::db::{now_ms, Db, DbError};
 use chrono::{TimeZone, Utc};
 use cron::Schedule;
+use serde::Serialize;
 use std::str::FromStr;
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
 
 /// Normalize a cron expression to the 6/7-field format the `cron` crate expects.
 /// Standard 5-field (min hour dom month dow) gets "0 " prepended for seconds.
-fn normalize_cron(expr: &str) -> String {
+pub(crate) fn normalize_cron(expr: &str) -> String {
     let fields: Vec<&str> = expr.split_whitespace().collect();
     if fields.len() == 5 {
         format!("0 {}", expr)
@@ -19,51 +24,78 @@ fn normalize_cron(expr: &str) -> String {
     }
 }
 
-/// Check all enabled cron jobs and run those that are due. Returns number of jobs executed.
+/// Check all enabled cron jobs and run those that are due, up to
+/// `config.max_parallel` at a time. Each job runs in its own task with
+/// `config.job_timeout_secs` to finish, so a slow or failing job doesn't hold
+/// up the others. Returns number of jobs executed.
 pub async fn check_and_run_due_jobs(
     db: &Db,
     agent_config: &AgentRunConfig,
     delivery_tx: Option<&mpsc::UnboundedSender<OutgoingMessage>>,
+    config: &CronConfig,
 ) -> Result<usize, DbError> {
     let jobs = list_due_jobs(db).await?;
-    let mut ran = 0;
+    let ran = jobs.len();
+    let slots = Arc::new(Semaphore::new(config.max_parallel.max(1)));
+    let timeout = Duration::from_secs(config.job_timeout_secs);
 
+    let mut tasks = JoinSet::new();
     for job in jobs {
-        tracing::info!(
-            "Cron job '{}' is due, executing... (mode: {})",
-            job.name,
-            job.session_mode
-        );
-
-        let started_at = now_ms() as i64;
-        let job_id = job.id;
-
-        // Record the run as started
-        let run_id = db
-            .exec(move |conn| {
-                conn.execute(
-                    "INSERT INTO cron_runs (job_id, status, started_at) VALUES (?1, 'running', ?2)",
-                    rusqlite::params![job_id, started_at],
-                )?;
-                let id = conn.last_insert_rowid();
-                Ok(id)
-            })
-            .await?;
+        let db = db.clone();
+        let agent_config = agent_config.clone();
+        let delivery_tx = delivery_tx.cloned();
+        let slots = slots.clone();
+        tasks.spawn(async move {
+            let _slot = slots.acquire_owned().await;
+            let name = job.name.clone();
+            if let Err(e) = run_job(&db, &agent_config, delivery_tx.as_ref(), job, timeout).await {
+                tracing::error!("Cron job '{}' could not be recorded: {}", name, e);
+            }
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Err(e) = joined {
+            tracing::error!("Cron job task panicked: {}", e);
+        }
+    }
 
-        // Execute based on session mode
-        let session_id = format!("cron-{}", job.name);
-        let system_prompt = "You are a scheduled task agent. Execute the following task concisely.";
+    Ok(ran)
+}
 
-        let result = match job.session_mode.as_str() {
+/// Run one due job: record the run, execute it within `timeout`, deliver the
+/// response and mark the job as run.
+async fn run_job(
+    db: &Db,
+    agent_config: &AgentRunConfig,
+    delivery_tx: Option<&mpsc::UnboundedSender<OutgoingMessage>>,
+    job: CronJob,
+    timeout: Duration,
+) -> Result<(), DbError> {
+    tracing::info!(
+        "Cron job '{}' is due, executing... (mode: {})",
+        job.name,
+        job.session_mode
+    );
+
+    // Record the run as started
+    let run_id = db.cron_run_start(job.id, now_ms()).await?;
+
+    // Execute based on session mode, with the job's model overrides
+    let agent_config = agent_config.with_overrides(
+        job.provider.as_deref(),
+        job.model.as_deref(),
+        job.max_tokens,
+    );
+    let session_id = format!("cron-{}", job.name);
+    let system_prompt = "You are a scheduled task agent. Execute the following task concisely.";
+    // Briefing jobs can ask for the state of all projects with `{projects}`
+    let prompt = crate::projects::expand_placeholder(db, &job.prompt).await;
+
+    let run = async {
+        match job.session_mode.as_str() {
             "persistent" => {
-                super::run_persistent_prompt(
-                    db,
-                    agent_config,
-                    &session_id,
-                    system_prompt,
-                    &job.prompt,
-                )
-                .await
+                super::run_persistent_prompt(db, &agent_config, &session_id, system_prompt, &prompt)
+                    .await
             }
             _ => {
                 if job.session_mode != "isolated" {
@@ -73,179 +105,184 @@ pub async fn check_and_run_due_jobs(
                         job.session_mode
                     );
                 }
-                super::run_ephemeral_prompt(agent_config, system_prompt, &job.prompt).await
+                super::run_ephemeral_prompt(&agent_config, system_prompt, &prompt).await
             }
-        };
-
-        match result {
-            Ok(response) => {
-                tracing::info!(
-                    "Cron job '{}' completed ({} chars)",
-                    job.name,
-                    response.len()
-                );
-
-                // Record successful run
-                let finished_at = now_ms() as i64;
-                let result_text = response.clone();
-                db.exec(move |conn| {
-                    conn.execute(
-                        "UPDATE cron_runs SET status = 'ok', result = ?1, finished_at = ?2 WHERE id = ?3",
-                        rusqlite::params![result_text, finished_at, run_id],
-                    )?;
-                    Ok(())
-                })
-                .await?;
-
-                // Deliver to target channel if configured
-                if let (Some(target), Some(tx)) = (&job.target_channel, delivery_tx) {
-                    // target is a session_id like "tg-514133400" or "dc-guild-channel"
-                    // Derive the adapter name from the prefix
-                    let adapter_name = channel_from_session_id(target);
-                    let _ = tx.send(OutgoingMessage {
-                        channel: adapter_name.to_string(),
-                        session_id: target.clone(),
-                        content: response,
-                        reply_to: None,
-                    });
-                }
-            }
-            Err(e) => {
-                tracing::error!("Cron job '{}' failed: {}", job.name, e);
-
-                // Record failed run
-                let finished_at = now_ms() as i64;
-                let err_msg = e.to_string();
-                db.exec(move |conn| {
-                    conn.execute(
-                        "UPDATE cron_runs SET status = 'error', result = ?1, finished_at = ?2 WHERE id = ?3",
-                        rusqlite::params![err_msg, finished_at, run_id],
-                    )?;
-                    Ok(())
-                })
-                .await?;
+        }
+    };
+    let result = match tokio::time::timeout(timeout, run).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("Timed out after {}s", timeout.as_secs())),
+    };
+
+    match result {
+        Ok(response) => {
+            tracing::info!(
+                "Cron job '{}' completed ({} chars)",
+                job.name,
+                response.len()
+            );
+
+            // Record successful run
+            db.cron_run_finish(run_id, true, &response).await?;
+
+            // Deliver to target channel if configured
+            if let (Some(target), Some(tx)) = (&job.target_channel, delivery_tx) {
+                // target is a session_id like "tg-514133400" or "dc-guild-channel"
+                let _ = tx.send(OutgoingMessage {
+                    channel: resolve_channel(db, target).await,
+                    session_id: target.clone(),
+                    content: response,
+                    reply_to: None,
+                });
             }
         }
+        Err(e) => {
+            tracing::error!("Cron job '{}' failed: {}", job.name, e);
 
-        // Update the job's updated_at to prevent re-running within the same tick
-        let now = now_ms() as i64;
-        let jid = job.id;
-        db.exec(move |conn| {
-            conn.execute(
-                "UPDATE cron_jobs SET updated_at = ?1 WHERE id = ?2",
-                rusqlite::params![now, jid],
-            )?;
-            Ok(())
-        })
-        .await?;
-
-        ran += 1;
+            // Record failed run
+            db.cron_run_finish(run_id, false, &e.to_string()).await?;
+        }
     }
 
-    Ok(ran)
+    Ok(())
+}
+
+/// Adapter that delivers to `session_id`: the channel recorded in the
+/// `sessions` table when its messages came in, else a guess from the prefix
+/// (sessions that never received a message, webhook targets).
+pub async fn resolve_channel(db: &Db, session_id: &str) -> String {
+    match db.session_get(session_id).await {
+        Ok(Some(session)) => session.channel,
+        Ok(None) => channel_from_session_id(session_id).to_string(),
+        Err(e) => {
+            tracing::warn!("Failed to look up session {}: {}", session_id, e);
+            channel_from_session_id(session_id).to_string()
+        }
+    }
 }
 
 /// Derive the adapter/channel name from a session_id prefix.
-/// e.g. "tg-514133400" → "telegram", "dc-guild-chan" → "discord", "slack-chan" → "slack"
-fn channel_from_session_id(session_id: &str) -> &str {
+/// e.g. "tg-514133400" → "telegram", "dc-guild-chan" → "discord", "slack-chan" → "slack",
+/// "webhook:ci" and "wh-builds" → "webhook", "web-4f1c…" → "web",
+/// "mailto:ops@example.com" → "email"
+pub(crate) fn channel_from_session_id(session_id: &str) -> &str {
     if session_id.starts_with("tg-") {
         "telegram"
     } else if session_id.starts_with("dc-") {
         "discord"
     } else if session_id.starts_with("slack-") {
         "slack"
+    } else if session_id.starts_with(crate::channels::signal::SESSION_PREFIX) {
+        "signal"
+    } else if session_id.starts_with(crate::webhook::TARGET_PREFIX)
+        || session_id.starts_with(crate::channels::webhook::SESSION_PREFIX)
+    {
+        "webhook"
+    } else if session_id.starts_with(crate::channels::web::SESSION_PREFIX) {
+        "web"
+    } else if crate::email::is_target(session_id) {
+        "email"
     } else {
         // Fallback: use the session_id as-is (legacy behavior)
         session_id
     }
 }
 
-/// A loaded cron job from the database.
-#[derive(Debug, Clone)]
-pub struct CronJob {
-    pub id: i64,
-    pub name: String,
-    pub schedule: String,
-    pub prompt: String,
-    pub target_channel: Option<String>,
-    pub session_mode: String,
-    pub enabled: bool,
+pub use crate::db::cron::CronJob;
+
+impl CronJob {
+    /// The schedule in words, like "every weekday at 9am".
+    pub fn describe_schedule(&self) -> String {
+        super::schedule::describe(&self.schedule)
+    }
+
+    /// Next scheduled time after now, None if the expression is invalid.
+    pub fn next_run(&self) -> Option<chrono::DateTime<Utc>> {
+        Schedule::from_str(&normalize_cron(&self.schedule))
+            .ok()?
+            .upcoming(Utc)
+            .next()
+    }
+
+    /// The job with its described schedule and next run, for listings.
+    pub fn listing(&self) -> JobListing<'_> {
+        JobListing {
+            job: self,
+            schedule_text: self.describe_schedule(),
+            next_run: self
+                .next_run()
+                .filter(|_| self.enabled)
+                .map(|t| t.timestamp_millis() as u64),
+        }
+    }
+}
+
+/// A cron job as shown by `yoclaw cron list --output json` and `/api/cron`.
+#[derive(Debug, Serialize)]
+pub struct JobListing<'a> {
+    #[serde(flatten)]
+    pub job: &'a CronJob,
+    /// The schedule in words.
+    pub schedule_text: String,
+    /// Next scheduled run (ms since epoch); null for disabled jobs.
+    pub next_run: Option<u64>,
 }
 
-/// List all enabled cron jobs that are due to run based on their schedule.
+/// List all enabled cron jobs that are due to run based on their schedule:
+/// those with a scheduled time between their last fire (or their last
+/// schedule change or re-enable, whichever is later) and now. Each is
+/// recorded as fired now, so it isn't due again until its next scheduled
+/// time, across restarts too, and no other host sharing the database runs it.
 async fn list_due_jobs(db: &Db) -> Result<Vec<CronJob>, DbError> {
-    db.exec(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, name, schedule, prompt, target_channel, session_mode, enabled, updated_at
-             FROM cron_jobs WHERE enabled = 1",
-        )?;
-
-        let now = Utc::now();
-        let mut due = Vec::new();
-
-        let rows = stmt.query_map([], |row| {
-            Ok((
-                CronJob {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    schedule: row.get(2)?,
-                    prompt: row.get(3)?,
-                    target_channel: row.get(4)?,
-                    session_mode: row
-                        .get::<_, Option<String>>(5)?
-                        .unwrap_or_else(|| "isolated".to_string()),
-                    enabled: row.get::<_, i64>(6)? == 1,
-                },
-                row.get::<_, i64>(7)?, // updated_at
-            ))
-        })?;
-
-        for row in rows {
-            let (job, updated_at) = row?;
-
-            // Parse cron expression (normalize 5-field to 6-field)
-            let normalized = normalize_cron(&job.schedule);
-            let schedule = match Schedule::from_str(&normalized) {
-                Ok(s) => s,
-                Err(e) => {
-                    tracing::warn!(
-                        "Invalid cron expression '{}' for job '{}': {}",
-                        job.schedule,
-                        job.name,
-                        e
-                    );
-                    continue;
-                }
-            };
+    let now = Utc::now();
+    let now_ms = now.timestamp_millis() as u64;
+    let mut due = Vec::new();
+    for (job, last) in db.cron_jobs_enabled().await? {
+        // Parse cron expression (normalize 5-field to 6-field)
+        let normalized = normalize_cron(&job.schedule);
+        let schedule = match Schedule::from_str(&normalized) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!(
+                    "Invalid cron expression '{}' for job '{}': {}",
+                    job.schedule,
+                    job.name,
+                    e
+                );
+                continue;
+            }
+        };
 
-            // Find the last time this job should have run
-            let last_update = Utc.timestamp_millis_opt(updated_at).single();
-            let since = last_update.unwrap_or(now - chrono::Duration::hours(24));
+        let since = Utc
+            .timestamp_millis_opt(last as i64)
+            .single()
+            .unwrap_or(now - chrono::Duration::hours(24));
 
-            // Check if there's a scheduled time between last update and now
-            if let Some(next) = schedule.after(&since).next() {
-                if next <= now {
-                    due.push(job);
-                }
+        // Check if there's a scheduled time between last update and now
+        if let Some(next) = schedule.after(&since).next() {
+            if next <= now && db.cron_claim(&job.name, last, now_ms).await? {
+                due.push(job);
             }
         }
-
-        Ok(due)
-    })
-    .await
+    }
+    Ok(due)
 }
 
 /// Create a new cron job in the database. Returns the job ID.
 pub async fn create_job(
     db: &Db,
+    rules: &JobRules,
     name: &str,
     schedule: &str,
     prompt: &str,
     target: Option<&str>,
     session: &str,
 ) -> Result<i64, DbError> {
+    // Text like "every weekday at 9am" is stored as its cron expression
+    let schedule = super::schedule::to_cron(schedule)
+        .map_err(|e| DbError::Sqlite(rusqlite::Error::InvalidParameterName(e.to_string())))?;
     // Validate cron expression first (normalize 5-field to 6-field)
-    let normalized = normalize_cron(schedule);
+    let normalized = normalize_cron(&schedule);
     Schedule::from_str(&normalized).map_err(|e| {
         DbError::Sqlite(rusqlite::Error::InvalidParameterName(format!(
             "Invalid cron expression: {}",
@@ -253,86 +290,93 @@ pub async fn create_job(
         )))
     })?;
 
-    let name = name.to_string();
-    let schedule = schedule.to_string();
-    let prompt = prompt.to_string();
-    let target = target.map(|s| s.to_string());
-    let session = session.to_string();
-
-    db.exec(move |conn| {
-        let ts = now_ms() as i64;
-        conn.execute(
-            "INSERT INTO cron_jobs (name, schedule, prompt, target_channel, session_mode, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
-             ON CONFLICT(name) DO UPDATE SET
-                schedule = excluded.schedule,
-                prompt = excluded.prompt,
-                target_channel = excluded.target_channel,
-                session_mode = excluded.session_mode,
-                updated_at = excluded.updated_at",
-            rusqlite::params![name, schedule, prompt, target, session, ts],
-        )?;
-        let id = conn.last_insert_rowid();
-        Ok(id)
-    })
-    .await
+    if let Some(target) = target.filter(|t| crate::email::is_target(t)) {
+        let reason = if crate::email::address(target).is_none() {
+            Some("is not a valid email address")
+        } else if !rules.email {
+            Some("needs [channels.email] to send mail with")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            return Err(DbError::Sqlite(rusqlite::Error::InvalidParameterName(
+                format!("Target '{}' {}", target, reason),
+            )));
+        }
+    }
+
+    db.cron_job_upsert(name, &schedule, prompt, target, session)
+        .await
+}
+
+/// What jobs created at runtime are checked against, as `parse_config()`
+/// checks those in the config file.
+#[derive(Debug, Clone, Default)]
+pub struct JobRules {
+    /// `agent.provider` and the `[[agent.fallback_providers]]`: the providers
+    /// with an API key.
+    pub providers: Vec<String>,
+    /// Whether `[channels.email]` is set, so `mailto:` targets can be mailed.
+    pub email: bool,
+}
+
+impl JobRules {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            providers: std::iter::once(&config.agent.provider)
+                .chain(config.agent.fallback_providers.iter().map(|f| &f.provider))
+                .cloned()
+                .collect(),
+            email: config.channels.email.is_some(),
+        }
+    }
+
+    /// Fail unless `provider` has an API key to take, so an override never
+    /// gets the main provider's key.
+    pub fn check_provider(&self, provider: Option<&str>) -> Result<(), DbError> {
+        match provider {
+            Some(provider) if !self.providers.iter().any(|p| p == provider) => Err(
+                DbError::Sqlite(rusqlite::Error::InvalidParameterName(format!(
+                    "Provider '{}' needs an [[agent.fallback_providers]] entry for its API key",
+                    provider
+                ))),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Set or clear a job's provider, model and max_tokens overrides. Returns
+/// false if there is no job with that name.
+pub async fn set_job_model(
+    db: &Db,
+    rules: &JobRules,
+    name: &str,
+    provider: Option<&str>,
+    model: Option<&str>,
+    max_tokens: Option<u32>,
+) -> Result<bool, DbError> {
+    rules.check_provider(provider)?;
+    db.cron_job_set_model(name, provider, model, max_tokens)
+        .await
 }
 
 /// List all cron jobs (for display).
 pub async fn list_jobs(db: &Db) -> Result<Vec<CronJob>, DbError> {
-    db.exec(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, name, schedule, prompt, target_channel, session_mode, enabled FROM cron_jobs ORDER BY name",
-        )?;
-
-        let jobs = stmt
-            .query_map([], |row| {
-                Ok(CronJob {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    schedule: row.get(2)?,
-                    prompt: row.get(3)?,
-                    target_channel: row.get(4)?,
-                    session_mode: row.get::<_, Option<String>>(5)?.unwrap_or_else(|| "isolated".to_string()),
-                    enabled: row.get::<_, i64>(6)? == 1,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(jobs)
-    })
-    .await
+    db.cron_jobs_list().await
 }
 
 /// Delete a cron job by name. Returns true if a job was deleted.
 pub async fn delete_job(db: &Db, name: &str) -> Result<bool, DbError> {
-    let name = name.to_string();
-    db.exec(move |conn| {
-        let deleted = conn.execute(
-            "DELETE FROM cron_jobs WHERE name = ?1",
-            rusqlite::params![name],
-        )?;
-        Ok(deleted > 0)
-    })
-    .await
+    db.cron_job_delete(name).await
 }
 
 /// Toggle a cron job's enabled state by name. Returns the new enabled state, or None if not found.
 pub async fn toggle_job(db: &Db, name: &str, enabled: bool) -> Result<Option<bool>, DbError> {
-    let name = name.to_string();
-    let enabled_int: i64 = if enabled { 1 } else { 0 };
-    db.exec(move |conn| {
-        let updated = conn.execute(
-            "UPDATE cron_jobs SET enabled = ?1, updated_at = ?2 WHERE name = ?3",
-            rusqlite::params![enabled_int, now_ms() as i64, name],
-        )?;
-        if updated > 0 {
-            Ok(Some(enabled))
-        } else {
-            Ok(None)
-        }
-    })
-    .await
+    Ok(db
+        .cron_job_set_enabled(name, enabled)
+        .await?
+        .then_some(enabled))
 }
 
 #[cfg(test)]
@@ -347,7 +391,11 @@ mod tests {
             provider: "anthropic".to_string(),
             model: "mock".to_string(),
             api_key: "test-key".to_string(),
+            endpoint: Default::default(),
             context: Default::default(),
+            max_tokens: None,
+            stream_provider: None,
+            fallback_providers: Vec::new(),
         }
     }
 
@@ -357,6 +405,7 @@ mod tests {
 
         create_job(
             &db,
+            &JobRules::default(),
             "test-job",
             "0 9 * * *",
             "Do something",
@@ -367,6 +416,7 @@ mod tests {
         .unwrap();
         create_job(
             &db,
+            &JobRules::default(),
             "another-job",
             "0 18 * * 1-5",
             "Evening summary",
@@ -382,19 +432,166 @@ mod tests {
         assert_eq!(jobs[1].name, "test-job");
     }
 
+    #[tokio::test]
+    async fn test_create_job_from_text() {
+        let db = Db::open_memory().unwrap();
+        create_job(
+            &db,
+            &JobRules::default(),
+            "standup",
+            "every weekday at 9:30am",
+            "Prepare the standup notes",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
+
+        let jobs = list_jobs(&db).await.unwrap();
+        assert_eq!(jobs[0].schedule, "30 9 * * Mon-Fri");
+        let listing = jobs[0].listing();
+        assert_eq!(listing.schedule_text, "every weekday at 9:30am");
+        let next = listing.next_run.unwrap();
+        assert!(next > now_ms());
+
+        toggle_job(&db, "standup", false).await.unwrap();
+        let jobs = list_jobs(&db).await.unwrap();
+        assert_eq!(jobs[0].listing().next_run, None);
+    }
+
     #[tokio::test]
     async fn test_create_job_invalid_cron() {
         let db = Db::open_memory().unwrap();
-        let result = create_job(&db, "bad", "not a cron", "test", None, "isolated").await;
+        let result = create_job(
+            &db,
+            &JobRules::default(),
+            "bad",
+            "not a cron",
+            "test",
+            None,
+            "isolated",
+        )
+        .await;
+        assert!(result.is_err());
+        let result = create_job(
+            &db,
+            &JobRules::default(),
+            "mail",
+            "0 9 * * *",
+            "test",
+            Some("mailto:ops@example.com\r\nBcc: x@y.z"),
+            "isolated",
+        )
+        .await;
         assert!(result.is_err());
+
+        // Nothing to send mail with
+        let db = &db;
+        let mail = |rules| async move {
+            create_job(
+                db,
+                &rules,
+                "mail",
+                "0 9 * * *",
+                "test",
+                Some("mailto:ops@example.com"),
+                "isolated",
+            )
+            .await
+        };
+        assert!(mail(JobRules::default()).await.is_err());
+        let rules = JobRules {
+            email: true,
+            ..Default::default()
+        };
+        assert!(mail(rules).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_job_model_override() {
+        let db = Db::open_memory().unwrap();
+        create_job(
+            &db,
+            &JobRules::default(),
+            "hourly",
+            "0 * * * *",
+            "Check",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
+        assert!(set_job_model(
+            &db,
+            &JobRules::default(),
+            "hourly",
+            None,
+            Some("cheap-model"),
+            Some(256)
+        )
+        .await
+        .unwrap());
+        assert!(
+            !set_job_model(&db, &JobRules::default(), "missing", None, None, None)
+                .await
+                .unwrap()
+        );
+
+        let job = &list_jobs(&db).await.unwrap()[0];
+        assert_eq!(job.provider, None);
+        assert_eq!(job.model.as_deref(), Some("cheap-model"));
+        assert_eq!(job.max_tokens, Some(256));
+
+        let base = test_agent_config();
+        let run = base.with_overrides(
+            job.provider.as_deref(),
+            job.model.as_deref(),
+            job.max_tokens,
+        );
+        assert_eq!(run.provider, "anthropic");
+        assert_eq!(run.model, "cheap-model");
+        assert_eq!(run.max_tokens, Some(256));
+        assert_eq!(run.api_key, base.api_key);
+    }
+
+    #[test]
+    fn test_provider_override_uses_that_providers_key() {
+        let mut base = test_agent_config();
+        base.fallback_providers = vec![crate::config::FallbackProviderConfig {
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            api_key: "openai-key".to_string(),
+            base_url: Some("https://llm.example.com/v1".to_string()),
+            provider_options: Default::default(),
+        }];
+
+        let run = base.with_overrides(Some("openai"), Some("gpt-4o-mini"), None);
+        assert_eq!(run.api_key, "openai-key");
+        assert_eq!(
+            run.endpoint.base_url.as_deref(),
+            Some("https://llm.example.com/v1")
+        );
+
+        // The agent's key is never sent to another provider
+        let run = base.with_overrides(Some("google"), None, None);
+        assert_eq!(run.api_key, "");
+        assert_eq!(run.endpoint.base_url, None);
     }
 
     #[tokio::test]
     async fn test_delete_job() {
         let db = Db::open_memory().unwrap();
-        create_job(&db, "to-delete", "0 9 * * *", "test", None, "isolated")
-            .await
-            .unwrap();
+        create_job(
+            &db,
+            &JobRules::default(),
+            "to-delete",
+            "0 9 * * *",
+            "test",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
 
         let deleted = delete_job(&db, "to-delete").await.unwrap();
         assert!(deleted);
@@ -406,9 +603,17 @@ mod tests {
     #[tokio::test]
     async fn test_toggle_job() {
         let db = Db::open_memory().unwrap();
-        create_job(&db, "toggleable", "0 9 * * *", "test", None, "isolated")
-            .await
-            .unwrap();
+        create_job(
+            &db,
+            &JobRules::default(),
+            "toggleable",
+            "0 9 * * *",
+            "test",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
 
         let state = toggle_job(&db, "toggleable", false).await.unwrap();
         assert_eq!(state, Some(false));
@@ -426,12 +631,22 @@ mod tests {
         let agent = test_agent_config();
 
         // Create a job that just ran (updated_at = now)
-        create_job(&db, "recent", "0 9 * * *", "test", None, "isolated")
-            .await
-            .unwrap();
+        create_job(
+            &db,
+            &JobRules::default(),
+            "recent",
+            "0 9 * * *",
+            "test",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
 
         // No jobs should be due since the job was just created (updated_at = now)
-        let ran = check_and_run_due_jobs(&db, &agent, None).await.unwrap();
+        let ran = check_and_run_due_jobs(&db, &agent, None, &CronConfig::default())
+            .await
+            .unwrap();
         assert_eq!(ran, 0);
     }
 
@@ -443,6 +658,7 @@ mod tests {
         // Create a job, then backdate its updated_at to 25 hours ago
         create_job(
             &db,
+            &JobRules::default(),
             "overdue",
             "* * * * *",
             "every minute",
@@ -465,7 +681,9 @@ mod tests {
 
         // This will try to run the ephemeral agent with a fake API key,
         // so the agent call will fail. But the run should still be recorded as error.
-        let ran = check_and_run_due_jobs(&db, &agent, None).await.unwrap();
+        let ran = check_and_run_due_jobs(&db, &agent, None, &CronConfig::default())
+            .await
+            .unwrap();
         assert_eq!(ran, 1);
 
         // Verify a run was recorded (either ok or error)
@@ -487,6 +705,7 @@ mod tests {
         // Create a persistent-mode job
         create_job(
             &db,
+            &JobRules::default(),
             "persistent-job",
             "* * * * *",
             "check status",
@@ -509,7 +728,9 @@ mod tests {
         .unwrap();
 
         // Will fail at provider level (fake API key), but should record run attempt
-        let ran = check_and_run_due_jobs(&db, &agent, None).await.unwrap();
+        let ran = check_and_run_due_jobs(&db, &agent, None, &CronConfig::default())
+            .await
+            .unwrap();
         assert_eq!(ran, 1);
 
         // Verify run was recorded
@@ -529,9 +750,17 @@ mod tests {
         let agent = test_agent_config();
 
         // Create a job with unknown session mode
-        create_job(&db, "weird-mode", "* * * * *", "test", None, "unknown_mode")
-            .await
-            .unwrap();
+        create_job(
+            &db,
+            &JobRules::default(),
+            "weird-mode",
+            "* * * * *",
+            "test",
+            None,
+            "unknown_mode",
+        )
+        .await
+        .unwrap();
 
         // Backdate so it's due
         let old_ts = (now_ms() - 25 * 60 * 60 * 1000) as i64;
@@ -546,15 +775,259 @@ mod tests {
         .unwrap();
 
         // Should run (falls back to isolated) without panic
-        let ran = check_and_run_due_jobs(&db, &agent, None).await.unwrap();
+        let ran = check_and_run_due_jobs(&db, &agent, None, &CronConfig::default())
+            .await
+            .unwrap();
         assert_eq!(ran, 1);
     }
 
+    /// Never answers. Counts the calls in flight and the most at once.
+    #[derive(Default)]
+    struct PendingProvider {
+        in_flight: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    /// Ends a [`PendingProvider`] call when the job's timeout drops it.
+    struct InFlight<'a>(&'a std::sync::atomic::AtomicUsize);
+
+    impl Drop for InFlight<'_> {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl yoagent::provider::StreamProvider for PendingProvider {
+        async fn stream(
+            &self,
+            _config: yoagent::provider::StreamConfig,
+            _tx: mpsc::UnboundedSender<yoagent::provider::StreamEvent>,
+            _cancel: tokio_util::sync::CancellationToken,
+        ) -> Result<yoagent::types::Message, yoagent::provider::ProviderError> {
+            use std::sync::atomic::Ordering;
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            let _in_flight = InFlight(&self.in_flight);
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_due_jobs_run_concurrently_with_timeout() {
+        let db = Db::open_memory().unwrap();
+        let provider = Arc::new(PendingProvider::default());
+        let agent = AgentRunConfig {
+            stream_provider: Some(provider.clone()),
+            ..test_agent_config()
+        };
+        for name in ["slow-a", "slow-b", "slow-c"] {
+            create_job(
+                &db,
+                &JobRules::default(),
+                name,
+                "* * * * *",
+                "test",
+                None,
+                "isolated",
+            )
+            .await
+            .unwrap();
+        }
+        let old_ts = (now_ms() - 25 * 60 * 60 * 1000) as i64;
+        db.exec(move |conn| {
+            conn.execute(
+                "UPDATE cron_jobs SET updated_at = ?1",
+                rusqlite::params![old_ts],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        // No job can finish within the timeout; each is recorded as failed
+        // and marked as run, and the others still run, two at a time
+        let config = CronConfig {
+            max_parallel: 2,
+            job_timeout_secs: 1,
+            ..Default::default()
+        };
+        // The clock jumps ahead whenever every job is waiting on its timeout
+        tokio::time::pause();
+        let started = tokio::time::Instant::now();
+        let ran = check_and_run_due_jobs(&db, &agent, None, &config)
+            .await
+            .unwrap();
+        assert_eq!(ran, 3);
+        assert_eq!(provider.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(
+            provider.in_flight.load(std::sync::atomic::Ordering::SeqCst),
+            0
+        );
+        // The third job waited for a slot
+        assert!(started.elapsed() >= Duration::from_secs(2));
+
+        let results: Vec<(String, String)> = db
+            .exec(|conn| {
+                let mut stmt = conn.prepare("SELECT status, result FROM cron_runs")?;
+                let rows = stmt
+                    .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(rows)
+            })
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results
+            .iter()
+            .all(|(status, result)| status == "error" && result == "Timed out after 1s"));
+        assert_eq!(
+            check_and_run_due_jobs(&db, &agent, None, &config)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_agent_run_is_an_error() {
+        let db = Db::open_memory().unwrap();
+        let agent = AgentRunConfig {
+            stream_provider: Some(Arc::new(
+                crate::conductor::fixture::FixtureProvider::from_json(
+                    r#"{"responses": [{"error": "api_error: Internal server error"}]}"#,
+                )
+                .unwrap(),
+            )),
+            ..test_agent_config()
+        };
+        create_job(
+            &db,
+            &JobRules::default(),
+            "failing",
+            "* * * * *",
+            "test",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
+        let old_ts = (now_ms() - 25 * 60 * 60 * 1000) as i64;
+        db.exec(move |conn| {
+            conn.execute(
+                "UPDATE cron_jobs SET updated_at = ?1",
+                rusqlite::params![old_ts],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let ran = check_and_run_due_jobs(&db, &agent, None, &CronConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(ran, 1);
+        let (status, result): (String, String) = db
+            .exec(|conn| {
+                Ok(
+                    conn.query_row("SELECT status, result FROM cron_runs", [], |r| {
+                        Ok((r.get(0)?, r.get(1)?))
+                    })?,
+                )
+            })
+            .await
+            .unwrap();
+        assert_eq!(status, "error");
+        assert!(result.contains("Internal server error"), "{}", result);
+    }
+
+    #[tokio::test]
+    async fn test_last_fire_is_persisted() {
+        let db = Db::open_memory().unwrap();
+        let agent = test_agent_config();
+        let config = CronConfig {
+            job_timeout_secs: 0,
+            ..Default::default()
+        };
+        create_job(
+            &db,
+            &JobRules::default(),
+            "hourly",
+            "0 * * * *",
+            "test",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
+        let old_ts = (now_ms() - 25 * 60 * 60 * 1000) as i64;
+        db.exec(move |conn| {
+            conn.execute(
+                "UPDATE cron_jobs SET updated_at = ?1",
+                rusqlite::params![old_ts],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let before = now_ms();
+        let ran = check_and_run_due_jobs(&db, &agent, None, &config)
+            .await
+            .unwrap();
+        assert_eq!(ran, 1);
+        let fired = db.cron_last_fired("hourly").await.unwrap().unwrap();
+        assert!(fired >= before);
+
+        // Re-syncing an unchanged job (as on restart) keeps its schedule state
+        create_job(
+            &db,
+            &JobRules::default(),
+            "hourly",
+            "0 * * * *",
+            "changed prompt",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
+        let updated_at: i64 = db
+            .exec(|conn| Ok(conn.query_row("SELECT updated_at FROM cron_jobs", [], |r| r.get(0))?))
+            .await
+            .unwrap();
+        assert_eq!(updated_at, old_ts);
+        let ran = check_and_run_due_jobs(&db, &agent, None, &config)
+            .await
+            .unwrap();
+        assert_eq!(ran, 0);
+
+        delete_job(&db, "hourly").await.unwrap();
+        assert_eq!(db.cron_last_fired("hourly").await.unwrap(), None);
+    }
+
     #[test]
     fn test_channel_from_session_id() {
         assert_eq!(channel_from_session_id("tg-514133400"), "telegram");
         assert_eq!(channel_from_session_id("dc-guild-channel"), "discord");
         assert_eq!(channel_from_session_id("slack-general"), "slack");
+        assert_eq!(channel_from_session_id("webhook:ci"), "webhook");
+        assert_eq!(channel_from_session_id("wh-builds"), "webhook");
+        assert_eq!(channel_from_session_id("web-4f1c"), "web");
+        assert_eq!(channel_from_session_id("sig-group-aGVsbG8="), "signal");
+        assert_eq!(channel_from_session_id("mailto:ops@example.com"), "email");
         assert_eq!(channel_from_session_id("unknown-id"), "unknown-id");
     }
+
+    #[tokio::test]
+    async fn test_resolve_channel() {
+        let db = Db::open_memory().unwrap();
+        // A routed session whose ID carries no adapter prefix
+        db.session_touch("discord", "ops-room", Some("1234"), None, true)
+            .await
+            .unwrap();
+        assert_eq!(resolve_channel(&db, "ops-room").await, "discord");
+        // Never seen: fall back to the prefix
+        assert_eq!(resolve_channel(&db, "tg-42").await, "telegram");
+        assert_eq!(resolve_channel(&db, "webhook:ci").await, "webhook");
+    }
 }
@@ -1,12 +1,19 @@
+pub mod background;
 pub mod cortex;
 pub mod cron;
+pub mod reports;
+pub mod retention;
+pub mod schedule;
 pub mod tools;
 
 use crate::channels::OutgoingMessage;
-use crate::config::{Config, SchedulerConfig};
+use crate::conductor::endpoint::Endpoint;
+use crate::config::{Config, FallbackProviderConfig, SchedulerConfig};
 use crate::db::Db;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use yoagent::provider::StreamProvider;
 
 /// Agent configuration needed to spawn ephemeral agents for cron/cortex tasks.
 #[derive(Clone)]
@@ -14,8 +21,85 @@ pub struct AgentRunConfig {
     pub provider: String,
     pub model: String,
     pub api_key: String,
+    /// Base URL and options of `provider`.
+    pub endpoint: Endpoint,
     /// Context window settings from user config (for persistent agents).
     pub context: crate::config::ContextConfig,
+    /// Max tokens per response (None = provider default).
+    pub max_tokens: Option<u32>,
+    /// Provider to call instead of resolving `provider`, e.g. a test's mock.
+    pub stream_provider: Option<Arc<dyn StreamProvider>>,
+    /// `[[agent.fallback_providers]]`, whose keys and endpoints overrides of
+    /// `provider` use.
+    pub fallback_providers: Vec<FallbackProviderConfig>,
+}
+
+impl std::fmt::Debug for AgentRunConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentRunConfig")
+            .field("provider", &self.provider)
+            .field("model", &self.model)
+            .field("endpoint", &self.endpoint)
+            .field("context", &self.context)
+            .field("max_tokens", &self.max_tokens)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AgentRunConfig {
+    /// Copy with a job's provider, model and max_tokens overrides applied.
+    /// Another provider takes its key and endpoint from the first fallback
+    /// provider of that name, and has no key and its default endpoint if
+    /// there is none: the agent's key belongs to the agent's provider.
+    pub fn with_overrides(
+        &self,
+        provider: Option<&str>,
+        model: Option<&str>,
+        max_tokens: Option<u32>,
+    ) -> Self {
+        let provider = provider.unwrap_or(&self.provider);
+        let (api_key, endpoint, stream_provider) = if provider == self.provider {
+            (
+                self.api_key.clone(),
+                self.endpoint.clone(),
+                self.stream_provider.clone(),
+            )
+        } else {
+            match self
+                .fallback_providers
+                .iter()
+                .find(|f| f.provider == provider)
+            {
+                Some(fallback) => (
+                    fallback.api_key.clone(),
+                    Endpoint::new(fallback.base_url.as_deref(), &fallback.provider_options),
+                    None,
+                ),
+                None => (String::new(), Endpoint::default(), None),
+            }
+        };
+        Self {
+            provider: provider.to_string(),
+            model: model.unwrap_or(&self.model).to_string(),
+            api_key,
+            endpoint,
+            context: self.context.clone(),
+            max_tokens: max_tokens.or(self.max_tokens),
+            stream_provider,
+            fallback_providers: self.fallback_providers.clone(),
+        }
+    }
+
+    /// The provider to call: `stream_provider`, or `provider` at `endpoint`.
+    fn resolve_provider(&self) -> Arc<dyn StreamProvider> {
+        match &self.stream_provider {
+            Some(provider) => provider.clone(),
+            None => Arc::new(crate::conductor::resolve_provider(
+                &self.provider,
+                &self.endpoint,
+            )),
+        }
+    }
 }
 
 /// Unified scheduler for both cortex maintenance and user-defined cron jobs.
@@ -25,6 +109,14 @@ pub struct Scheduler {
     agent_config: AgentRunConfig,
     /// Sender for delivering cron job results to channel adapters.
     delivery_tx: Option<mpsc::UnboundedSender<OutgoingMessage>>,
+    /// Token prices for report cost estimates.
+    pricing: Option<crate::config::PricingConfig>,
+    /// Days audit entries are kept before they are rolled up.
+    audit_keep_days: Option<u64>,
+    /// Limits on how much history is kept.
+    retention: crate::config::RetentionConfig,
+    /// Scheduled database backups and the directory they go to.
+    backup: Option<(crate::config::BackupConfig, std::path::PathBuf)>,
 }
 
 impl Scheduler {
@@ -41,25 +133,38 @@ impl Scheduler {
                 cortex: crate::config::CortexConfig {
                     interval_hours: config.scheduler.cortex.interval_hours,
                     model: config.scheduler.cortex.model.clone(),
+                    provider: config.scheduler.cortex.provider.clone(),
+                    max_tokens: config.scheduler.cortex.max_tokens,
                 },
-                cron: crate::config::CronConfig {
-                    jobs: config.scheduler.cron.jobs.clone(),
-                },
+                cron: config.scheduler.cron.clone(),
+                reports: config.scheduler.reports.clone(),
+                memory_review: config.scheduler.memory_review.clone(),
             },
             agent_config: AgentRunConfig {
                 provider: config.agent.provider.clone(),
                 model: config.agent.model.clone(),
                 api_key: config.agent.api_key.clone(),
+                endpoint: Endpoint::agent(&config.agent),
                 context: config.agent.context.clone(),
+                max_tokens: None,
+                stream_provider: None,
+                fallback_providers: config.agent.fallback_providers.clone(),
             },
             delivery_tx,
+            pricing: config.agent.pricing.clone(),
+            audit_keep_days: config.audit.keep_days,
+            retention: config.persistence.retention.clone(),
+            backup: config
+                .persistence
+                .backup
+                .clone()
+                .map(|backup| (backup, config.backup_dir())),
         }
     }
 
     /// Run the scheduler tick loop. Blocks forever (should be spawned).
     pub async fn run(self) {
         let tick = Duration::from_secs(self.config.tick_interval_secs);
-        let mut cortex_last_run: Option<std::time::Instant> = None;
         let cortex_interval = Duration::from_secs(self.config.cortex.interval_hours * 3600);
 
         // Load static cron jobs from config into DB
@@ -68,34 +173,48 @@ impl Scheduler {
         }
 
         tracing::info!(
-            "Scheduler started (tick: {}s, cortex interval: {}h, {} cron jobs)",
+            "Scheduler started (tick: {}s, cortex interval: {}h, {} cron jobs, {} reports)",
             self.config.tick_interval_secs,
             self.config.cortex.interval_hours,
             self.config.cron.jobs.len(),
+            self.config.reports.len(),
         );
 
+        // The first pass after a start waits for the message loop to go idle,
+        // so catching up on maintenance doesn't hold up queued messages
+        let mut started = false;
+        let mut last_retention: Option<std::time::Instant> = None;
+
         loop {
             tokio::time::sleep(tick).await;
 
-            // 1. Check cortex: time for maintenance?
-            let run_cortex = match cortex_last_run {
-                Some(last) => last.elapsed() >= cortex_interval,
-                None => true, // run on first tick
+            // Nothing runs in read-only mode. Missed cron runs fire once on resume
+            match self.db.pause_check(None).await {
+                Ok(Some(_)) => continue,
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to read pause state: {}", e),
+            }
+
+            // 1. Check cortex: time for maintenance, and anything to maintain?
+            // The last run is persisted, so a restart doesn't trigger an extra pass.
+            let run_cortex = match self.cortex_due(cortex_interval, started).await {
+                Ok(due) => due,
+                Err(e) => {
+                    tracing::error!("Failed to read cortex state: {}", e);
+                    false
+                }
             };
 
             if run_cortex {
                 tracing::info!("Running cortex maintenance...");
-                let cortex_model = self.config.cortex.model.clone();
-                let cortex_agent = AgentRunConfig {
-                    provider: self.agent_config.provider.clone(),
-                    model: cortex_model,
-                    api_key: self.agent_config.api_key.clone(),
-                    context: Default::default(),
-                };
+                let cortex_agent = self.cortex_agent();
+                started = true;
                 match cortex::run_maintenance(&self.db, &cortex_agent).await {
                     Ok(summary) => {
                         tracing::info!("Cortex maintenance complete: {}", summary);
-                        cortex_last_run = Some(std::time::Instant::now());
+                        if let Err(e) = self.db.cortex_set_last_run(crate::db::now_ms()).await {
+                            tracing::error!("Failed to save cortex state: {}", e);
+                        }
                     }
                     Err(e) => {
                         tracing::error!("Cortex maintenance error: {}", e);
@@ -108,6 +227,7 @@ impl Scheduler {
                 &self.db,
                 &self.agent_config,
                 self.delivery_tx.as_ref(),
+                &self.config.cron,
             )
             .await
             {
@@ -120,40 +240,201 @@ impl Scheduler {
                     tracing::error!("Cron check error: {}", e);
                 }
             }
+
+            // 3. Usage reports for periods that ended since the last tick
+            match reports::run_due_reports(
+                &self.db,
+                &self.config.reports,
+                self.pricing.as_ref(),
+                self.delivery_tx.as_ref(),
+            )
+            .await
+            {
+                Ok(ran) => {
+                    if ran > 0 {
+                        tracing::info!("Generated {} usage report(s)", ran);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Usage report error: {}", e);
+                }
+            }
+
+            // 4. Periodic memory review
+            if let Err(e) = self.start_due_memory_review().await {
+                tracing::error!("Memory review error: {}", e);
+            }
+
+            // 5. Roll audit entries past their retention up into daily counts.
+            // Today's entries stay, the budget is counted from them
+            if let Some(days) = self.audit_keep_days {
+                let cutoff = crate::db::now_ms().saturating_sub(days.max(1) * 86_400_000);
+                match self.db.audit_rollup(cutoff).await {
+                    Ok(rolled) => {
+                        if rolled > 0 {
+                            tracing::info!("Rolled up {} old audit entries", rolled);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Audit rollup error: {}", e);
+                    }
+                }
+            }
+
+            // 6. Prune history past [persistence.retention] limits, hourly
+            if self.retention.is_set()
+                && last_retention.map_or(true, |t| t.elapsed() >= retention::INTERVAL)
+            {
+                last_retention = Some(std::time::Instant::now());
+                let summarizer = self.retention.summarize.then(|| self.cortex_agent());
+                match retention::run(&self.db, &self.retention, summarizer.as_ref()).await {
+                    Ok(pruned) => {
+                        if !pruned.is_empty() {
+                            tracing::info!("Retention pruned {}", pruned);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Retention error: {}", e);
+                    }
+                }
+            }
+
+            // 7. Database backup, once the newest in the directory is old enough
+            if let Some((backup, dir)) = &self.backup {
+                match crate::backup::run_scheduled(&self.db, dir, backup).await {
+                    Ok(Some(path)) => {
+                        tracing::info!("Database backed up to {}", path.display());
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::error!("Database backup error: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Agent for cortex maintenance and retention summaries: the cortex
+    /// model, on the agent's provider unless the cortex sets its own.
+    fn cortex_agent(&self) -> AgentRunConfig {
+        let cortex = &self.config.cortex;
+        AgentRunConfig {
+            provider: self.agent_config.provider.clone(),
+            model: cortex.model.clone(),
+            api_key: self.agent_config.api_key.clone(),
+            endpoint: self.agent_config.endpoint.clone(),
+            context: Default::default(),
+            max_tokens: cortex.max_tokens,
+            stream_provider: None,
+            fallback_providers: self.agent_config.fallback_providers.clone(),
+        }
+        .with_overrides(cortex.provider.as_deref(), None, None)
+    }
+
+    /// Whether a cortex pass should run now: `interval` has passed since the
+    /// last one, there was activity since, and, for the first pass since
+    /// `started`, no message is waiting. A pass with nothing to do is skipped
+    /// without being recorded, so the next activity triggers one.
+    async fn cortex_due(
+        &self,
+        interval: Duration,
+        started: bool,
+    ) -> Result<bool, crate::db::DbError> {
+        let now = crate::db::now_ms();
+        if let Some(last) = self.db.cortex_last_run().await? {
+            if now.saturating_sub(last) < interval.as_millis() as u64 {
+                return Ok(false);
+            }
+            if !cortex::has_work(&self.db, last).await? {
+                tracing::debug!("Cortex due but no activity since the last pass");
+                return Ok(false);
+            }
+        }
+        if !started && !self.db.queue_is_idle().await? {
+            tracing::debug!("Deferring the first cortex pass until the queue is idle");
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Start a memory review in the configured DM if one is due and there is
+    /// something to review.
+    async fn start_due_memory_review(&self) -> Result<(), crate::db::DbError> {
+        let review = &self.config.memory_review;
+        let (Some(target), Some(tx)) = (&review.target, &self.delivery_tx) else {
+            return Ok(());
+        };
+        if review.interval_days == 0 {
+            return Ok(());
+        }
+        let now = crate::db::now_ms();
+        let interval_ms = review.interval_days * 86_400_000;
+        if let Some(last) = self.db.review_last_started().await? {
+            if now.saturating_sub(last) < interval_ms {
+                return Ok(());
+            }
         }
+        // Counts as a run even if there was nothing to review
+        self.db.review_set_last_started(now).await?;
+        if let Some(batch) = crate::memory_review::start(&self.db, target).await? {
+            tracing::info!("Started memory review in {}", target);
+            let _ = tx.send(OutgoingMessage {
+                channel: cron::resolve_channel(&self.db, target).await,
+                session_id: target.clone(),
+                content: batch,
+                reply_to: None,
+            });
+        }
+        Ok(())
     }
 
     /// Sync static cron jobs from config into the database.
     async fn sync_config_jobs(&self) -> Result<(), crate::db::DbError> {
         for job in &self.config.cron.jobs {
-            let name = job.name.clone();
-            let schedule = job.schedule.clone();
-            let prompt = job.prompt.clone();
-            let target = job.target.clone();
-            let session = job.session.clone();
-
             self.db
-                .exec(move |conn| {
-                    let ts = crate::db::now_ms() as i64;
-                    conn.execute(
-                        "INSERT INTO cron_jobs (name, schedule, prompt, target_channel, session_mode, created_at, updated_at)
-                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
-                         ON CONFLICT(name) DO UPDATE SET
-                            schedule = excluded.schedule,
-                            prompt = excluded.prompt,
-                            target_channel = excluded.target_channel,
-                            session_mode = excluded.session_mode,
-                            updated_at = excluded.updated_at",
-                        rusqlite::params![name, schedule, prompt, target, session, ts],
-                    )?;
-                    Ok(())
-                })
+                .cron_job_upsert(
+                    &job.name,
+                    &job.schedule,
+                    &job.prompt,
+                    job.target.as_deref(),
+                    &job.session,
+                )
+                .await?;
+            self.db
+                .cron_job_set_model(
+                    &job.name,
+                    job.provider.as_deref(),
+                    job.model.as_deref(),
+                    job.max_tokens,
+                )
                 .await?;
         }
         Ok(())
     }
 }
 
+/// How often private session tapes are checked for expiry.
+const PRIVACY_PURGE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Purge tapes of private sessions idle longer than `ttl`, every few
+/// minutes. Blocks forever (should be spawned); runs even with the
+/// scheduler disabled or paused, since private tapes must not outlive it.
+pub async fn run_privacy_purge(db: Db, ttl: Duration) {
+    loop {
+        tokio::time::sleep(PRIVACY_PURGE_INTERVAL).await;
+        match db.privacy_purge_expired(ttl.as_millis() as u64).await {
+            Ok(purged) => {
+                if purged > 0 {
+                    tracing::info!("Purged {} expired private session tape(s)", purged);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Private tape purge error: {}", e);
+            }
+        }
+    }
+}
+
 /// Run an ephemeral agent with a single prompt and return the text response.
 /// Uses `agent_loop` directly for a fresh, stateless agent invocation.
 pub async fn run_ephemeral_prompt(
@@ -161,13 +442,12 @@ pub async fn run_ephemeral_prompt(
     system_prompt: &str,
     task: &str,
 ) -> Result<String, anyhow::Error> {
-    use crate::conductor::resolve_provider;
     use yoagent::agent_loop::{agent_loop, AgentLoopConfig};
     use yoagent::context::ExecutionLimits;
     use yoagent::types::*;
 
-    let provider = resolve_provider(&agent_config.provider);
-    let provider_ref: &dyn yoagent::provider::StreamProvider = &provider;
+    let provider = agent_config.resolve_provider();
+    let provider_ref: &dyn StreamProvider = provider.as_ref();
 
     let mut context = AgentContext {
         system_prompt: system_prompt.to_string(),
@@ -180,7 +460,7 @@ pub async fn run_ephemeral_prompt(
         model: agent_config.model.clone(),
         api_key: agent_config.api_key.clone(),
         thinking_level: ThinkingLevel::Off,
-        max_tokens: None,
+        max_tokens: agent_config.max_tokens,
         temperature: None,
         convert_to_llm: None,
         transform_context: None,
@@ -208,23 +488,7 @@ pub async fn run_ephemeral_prompt(
     let prompt_msg = AgentMessage::Llm(Message::user(task));
     let messages = agent_loop(vec![prompt_msg], &mut context, &config, tx, cancel).await;
 
-    // Extract text from the last assistant message
-    for msg in messages.iter().rev() {
-        if let AgentMessage::Llm(Message::Assistant { content, .. }) = msg {
-            let texts: Vec<&str> = content
-                .iter()
-                .filter_map(|c| match c {
-                    Content::Text { text } => Some(text.as_str()),
-                    _ => None,
-                })
-                .collect();
-            if !texts.is_empty() {
-                return Ok(texts.join("\n"));
-            }
-        }
-    }
-
-    Ok("(no response)".to_string())
+    reply_text(&messages)
 }
 
 /// Run a persistent agent: loads prior conversation from tape, appends the new prompt,
@@ -237,7 +501,6 @@ pub async fn run_persistent_prompt(
     task: &str,
 ) -> Result<String, anyhow::Error> {
     use crate::conductor::compaction::MemoryAwareCompaction;
-    use crate::conductor::resolve_provider;
     use yoagent::agent_loop::{agent_loop, AgentLoopConfig};
     use yoagent::context::{ContextConfig, ExecutionLimits};
     use yoagent::types::*;
@@ -247,8 +510,8 @@ pub async fn run_persistent_prompt(
     // 2. Append new user message
     prompts.push(AgentMessage::Llm(Message::user(task)));
 
-    let provider = resolve_provider(&agent_config.provider);
-    let provider_ref: &dyn yoagent::provider::StreamProvider = &provider;
+    let provider = agent_config.resolve_provider();
+    let provider_ref: &dyn StreamProvider = provider.as_ref();
 
     let mut context = AgentContext {
         system_prompt: system_prompt.to_string(),
@@ -288,7 +551,7 @@ pub async fn run_persistent_prompt(
         model: agent_config.model.clone(),
         api_key: agent_config.api_key.clone(),
         thinking_level: ThinkingLevel::Off,
-        max_tokens: None,
+        max_tokens: agent_config.max_tokens,
         temperature: None,
         convert_to_llm: None,
         transform_context: None,
@@ -320,21 +583,39 @@ pub async fn run_persistent_prompt(
     db.tape_save_messages(session_id, &all_messages).await?;
 
     // 5. Extract text from the last assistant message
-    for msg in all_messages.iter().rev() {
-        if let AgentMessage::Llm(Message::Assistant { content, .. }) = msg {
-            let texts: Vec<&str> = content
-                .iter()
-                .filter_map(|c| match c {
-                    Content::Text { text } => Some(text.as_str()),
-                    _ => None,
-                })
-                .collect();
-            if !texts.is_empty() {
-                return Ok(texts.join("\n"));
-            }
+    reply_text(&all_messages)
+}
+
+/// Text of the last assistant message with any, else "(no response)". Fails
+/// if the last assistant message is a provider error, which `agent_loop`
+/// returns instead of an `Err`.
+fn reply_text(messages: &[yoagent::types::AgentMessage]) -> Result<String, anyhow::Error> {
+    use yoagent::types::*;
+
+    let assistant = messages.iter().rev().filter_map(|msg| match msg {
+        AgentMessage::Llm(Message::Assistant {
+            content,
+            stop_reason,
+            error_message,
+            ..
+        }) => Some((content, stop_reason, error_message)),
+        _ => None,
+    });
+    for (i, (content, stop_reason, error_message)) in assistant.enumerate() {
+        if i == 0 && *stop_reason == StopReason::Error {
+            anyhow::bail!("{}", error_message.as_deref().unwrap_or("Provider error"));
+        }
+        let texts: Vec<&str> = content
+            .iter()
+            .filter_map(|c| match c {
+                Content::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        if !texts.is_empty() {
+            return Ok(texts.join("\n"));
         }
     }
-
     Ok("(no response)".to_string())
 }
 
@@ -0,0 +1,463 @@
+//! Scheduled usage reports.
+//!
+//! Each `[[scheduler.reports]]` entry summarizes a week or month of usage:
+//! tokens and estimated cost, inbound messages per channel, the busiest
+//! sessions and security events, all taken from the audit log and queue.
+//! Once a period has ended the scheduler renders the report as Markdown or
+//! CSV, writes it to `dir` and/or delivers it to `target`.
+//!
+//! The end of the last reported period is kept in the `state` table, so a
+//! restart neither skips nor repeats a report. A newly added report starts
+//! with the first full period after it was configured.
+
+use super::cron::resolve_channel;
+use crate::channels::OutgoingMessage;
+use crate::config::{PricingConfig, ReportConfig};
+use crate::db::audit::AuditEntry;
+use crate::db::{Db, DbError};
+use crate::stats::SessionStats;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// Audit event types counted as security events.
+pub(crate) const SECURITY_EVENTS: &[&str] = &["denied", "input_rejected", "admin_denied"];
+
+/// Usage over one report period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageReport {
+    pub name: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub responses: u64,
+    pub estimated_cost: Option<f64>,
+    pub messages_by_channel: BTreeMap<String, u64>,
+    /// Sessions with the most tokens, busiest first.
+    pub top_sessions: Vec<SessionStats>,
+    pub security_events: BTreeMap<String, u64>,
+}
+
+impl UsageReport {
+    pub fn messages(&self) -> u64 {
+        self.messages_by_channel.values().sum()
+    }
+
+    pub fn render(&self, format: &str) -> String {
+        match format {
+            "csv" => self.to_csv(),
+            _ => self.to_markdown(),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Usage report: {}\n\n{} to {} (UTC)\n\n## Totals\n\n",
+            self.name,
+            format_day(self.start_ms),
+            format_day(self.end_ms.saturating_sub(1))
+        );
+        out.push_str(&format!("- Messages: {}\n", self.messages()));
+        out.push_str(&format!("- Responses: {}\n", self.responses));
+        out.push_str(&format!(
+            "- Tokens: {} in / {} out ({} total)\n",
+            self.input_tokens,
+            self.output_tokens,
+            self.input_tokens + self.output_tokens
+        ));
+        match self.estimated_cost {
+            Some(cost) => out.push_str(&format!("- Estimated cost: ${:.4}\n", cost)),
+            None => out.push_str("- Estimated cost: n/a (set [agent.pricing])\n"),
+        }
+
+        out.push_str("\n## Messages per channel\n\n");
+        if self.messages_by_channel.is_empty() {
+            out.push_str("None\n");
+        } else {
+            out.push_str("| Channel | Messages |\n|---|---|\n");
+            for (channel, count) in &self.messages_by_channel {
+                out.push_str(&format!("| {} | {} |\n", channel, count));
+            }
+        }
+
+        out.push_str("\n## Top sessions\n\n");
+        if self.top_sessions.is_empty() {
+            out.push_str("None\n");
+        } else {
+            out.push_str("| Session | Responses | Tokens | Tool calls |\n|---|---|---|---|\n");
+            for s in &self.top_sessions {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    s.session_id,
+                    s.latencies_ms.len(),
+                    s.total_tokens(),
+                    s.tool_calls.values().sum::<u64>()
+                ));
+            }
+        }
+
+        out.push_str("\n## Security events\n\n");
+        if self.security_events.is_empty() {
+            out.push_str("None\n");
+        } else {
+            for (event, count) in &self.security_events {
+                out.push_str(&format!("- {}: {}\n", event, count));
+            }
+        }
+        out
+    }
+
+    /// One `section,key,value` row per figure.
+    pub fn to_csv(&self) -> String {
+        let mut rows = vec![
+            ("period", "start".to_string(), format_day(self.start_ms)),
+            (
+                "period",
+                "end".to_string(),
+                format_day(self.end_ms.saturating_sub(1)),
+            ),
+            (
+                "totals",
+                "messages".to_string(),
+                self.messages().to_string(),
+            ),
+            (
+                "totals",
+                "responses".to_string(),
+                self.responses.to_string(),
+            ),
+            (
+                "totals",
+                "input_tokens".to_string(),
+                self.input_tokens.to_string(),
+            ),
+            (
+                "totals",
+                "output_tokens".to_string(),
+                self.output_tokens.to_string(),
+            ),
+        ];
+        if let Some(cost) = self.estimated_cost {
+            rows.push((
+                "totals",
+                "estimated_cost".to_string(),
+                format!("{:.4}", cost),
+            ));
+        }
+        for (channel, count) in &self.messages_by_channel {
+            rows.push(("channel", channel.clone(), count.to_string()));
+        }
+        for s in &self.top_sessions {
+            rows.push((
+                "session",
+                s.session_id.clone(),
+                s.total_tokens().to_string(),
+            ));
+        }
+        for (event, count) in &self.security_events {
+            rows.push(("security", event.clone(), count.to_string()));
+        }
+
+        let mut out = String::from("section,key,value\n");
+        for (section, key, value) in rows {
+            out.push_str(&format!("{},{},{}\n", section, csv_field(&key), value));
+        }
+        out
+    }
+}
+
+/// Collect usage in `[start_ms, end_ms)`.
+pub async fn build_report(
+    db: &Db,
+    name: &str,
+    start_ms: u64,
+    end_ms: u64,
+    top_sessions: usize,
+    pricing: Option<&PricingConfig>,
+) -> Result<UsageReport, DbError> {
+    let events = db.audit_range(start_ms, end_ms).await?;
+    let totals = SessionStats::from_events("", &events, 0);
+
+    let mut by_session: BTreeMap<&str, Vec<AuditEntry>> = BTreeMap::new();
+    let mut security_events: BTreeMap<String, u64> = BTreeMap::new();
+    for event in &events {
+        if SECURITY_EVENTS.contains(&event.event_type.as_str()) {
+            *security_events.entry(event.event_type.clone()).or_default() += 1;
+        }
+        if let Some(ref session) = event.session_id {
+            by_session
+                .entry(session.as_str())
+                .or_default()
+                .push(event.clone());
+        }
+    }
+    let mut sessions: Vec<SessionStats> = by_session
+        .iter()
+        .map(|(session, events)| SessionStats::from_events(session, events, 0))
+        .filter(|s| s.total_tokens() > 0)
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.total_tokens()));
+    sessions.truncate(top_sessions);
+
+    Ok(UsageReport {
+        name: name.to_string(),
+        start_ms,
+        end_ms,
+        input_tokens: totals.input_tokens,
+        output_tokens: totals.output_tokens,
+        responses: totals.latencies_ms.len() as u64,
+        estimated_cost: pricing.map(|p| totals.estimated_cost(p)),
+        messages_by_channel: db
+            .queue_counts_by_channel(start_ms, end_ms)
+            .await?
+            .into_iter()
+            .collect(),
+        top_sessions: sessions,
+        security_events,
+    })
+}
+
+/// Generate every report whose period has ended since it last ran.
+/// Returns the number of reports generated.
+pub async fn run_due_reports(
+    db: &Db,
+    reports: &[ReportConfig],
+    pricing: Option<&PricingConfig>,
+    delivery_tx: Option<&mpsc::UnboundedSender<OutgoingMessage>>,
+) -> Result<usize, DbError> {
+    let now = Utc::now();
+    let mut ran = 0;
+
+    for report in reports {
+        let Some((start, end)) = period_bounds(&report.period, now) else {
+            tracing::warn!(
+                "Report '{}' has unknown period '{}'; use weekly or monthly",
+                report.name,
+                report.period
+            );
+            continue;
+        };
+        let start_ms = start.timestamp_millis() as u64;
+        let end_ms = end.timestamp_millis() as u64;
+        match db.report_last_period(&report.name).await? {
+            // First run: start with the next full period
+            None => {
+                db.report_set_last_period(&report.name, end_ms).await?;
+                continue;
+            }
+            Some(last) if last >= end_ms => continue,
+            Some(_) => {}
+        }
+
+        let usage = build_report(
+            db,
+            &report.name,
+            start_ms,
+            end_ms,
+            report.top_sessions,
+            pricing,
+        )
+        .await?;
+        let text = usage.render(&report.format);
+
+        if let Some(ref dir) = report.dir {
+            match write_report(dir, report, start_ms, &text) {
+                Ok(path) => tracing::info!("Wrote report '{}' to {}", report.name, path.display()),
+                Err(e) => tracing::error!("Failed to write report '{}': {}", report.name, e),
+            }
+        }
+        if let (Some(target), Some(tx)) = (&report.target, delivery_tx) {
+            let _ = tx.send(OutgoingMessage {
+                channel: resolve_channel(db, target).await,
+                session_id: target.clone(),
+                content: text,
+                reply_to: None,
+            });
+        }
+
+        db.report_set_last_period(&report.name, end_ms).await?;
+        ran += 1;
+    }
+
+    Ok(ran)
+}
+
+/// Start of the period before the one containing `now`, and of the current one.
+fn period_bounds(period: &str, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let today = now.date_naive();
+    let (previous, current) = match period {
+        "weekly" => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+            (monday - Duration::weeks(1), monday)
+        }
+        "monthly" => {
+            let first = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)?;
+            let previous = match today.month() {
+                1 => NaiveDate::from_ymd_opt(today.year() - 1, 12, 1)?,
+                m => NaiveDate::from_ymd_opt(today.year(), m - 1, 1)?,
+            };
+            (previous, first)
+        }
+        _ => return None,
+    };
+    Some((
+        previous.and_hms_opt(0, 0, 0)?.and_utc(),
+        current.and_hms_opt(0, 0, 0)?.and_utc(),
+    ))
+}
+
+/// Write `<dir>/<name>-<start date>.<md|csv>`.
+fn write_report(
+    dir: &str,
+    report: &ReportConfig,
+    start_ms: u64,
+    text: &str,
+) -> std::io::Result<PathBuf> {
+    let dir = crate::config::expand_tilde(dir);
+    std::fs::create_dir_all(&dir)?;
+    let ext = if report.format == "csv" { "csv" } else { "md" };
+    let path = dir.join(format!("{}-{}.{}", report.name, format_day(start_ms), ext));
+    std::fs::write(&path, text)?;
+    Ok(path)
+}
+
+fn format_day(ms: u64) -> String {
+    DateTime::from_timestamp_millis(ms as i64)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| "?".to_string())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queue::QueueEntry;
+    use chrono::TimeZone;
+
+    fn report_config(format: &str) -> ReportConfig {
+        ReportConfig {
+            name: "usage".to_string(),
+            period: "weekly".to_string(),
+            format: format.to_string(),
+            target: Some("tg-1".to_string()),
+            dir: None,
+            top_sessions: 5,
+        }
+    }
+
+    #[test]
+    fn test_period_bounds() {
+        // Thursday
+        let now = Utc.with_ymd_and_hms(2026, 1, 15, 10, 30, 0).unwrap();
+        let (start, end) = period_bounds("weekly", now).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2026, 1, 12, 0, 0, 0).unwrap());
+
+        let (start, end) = period_bounds("monthly", now).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+
+        assert!(period_bounds("daily", now).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_build_and_render_report() {
+        let db = Db::open_memory().unwrap();
+        db.audit_log(
+            Some("tg-1"),
+            "llm_usage",
+            None,
+            Some("in=1000 out=200"),
+            1200,
+        )
+        .await
+        .unwrap();
+        db.audit_log(Some("dc-1"), "llm_usage", None, Some("in=50 out=10"), 60)
+            .await
+            .unwrap();
+        db.audit_log(Some("tg-1"), "response", None, Some("latency_ms=900"), 0)
+            .await
+            .unwrap();
+        db.audit_log(Some("tg-1"), "denied", Some("bash"), None, 0)
+            .await
+            .unwrap();
+        db.queue_push(&QueueEntry::new("telegram", "u1", "tg-1", "hi"))
+            .await
+            .unwrap();
+
+        let pricing = PricingConfig {
+            input_per_mtok: 3.0,
+            output_per_mtok: 15.0,
+        };
+        let report = build_report(&db, "usage", 0, crate::db::now_ms() + 1, 1, Some(&pricing))
+            .await
+            .unwrap();
+        assert_eq!(report.input_tokens, 1050);
+        assert_eq!(report.output_tokens, 210);
+        assert_eq!(report.responses, 1);
+        assert_eq!(report.messages_by_channel.get("telegram"), Some(&1));
+        assert_eq!(report.security_events.get("denied"), Some(&1));
+        assert_eq!(report.top_sessions.len(), 1);
+        assert_eq!(report.top_sessions[0].session_id, "tg-1");
+
+        let md = report.to_markdown();
+        assert!(md.starts_with("# Usage report: usage\n"));
+        assert!(md.contains("- Tokens: 1050 in / 210 out (1260 total)"));
+        assert!(md.contains("| telegram | 1 |"));
+        assert!(md.contains("- denied: 1"));
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("section,key,value\n"));
+        assert!(csv.contains("totals,input_tokens,1050\n"));
+        assert!(csv.contains("session,tg-1,1200\n"));
+    }
+
+    #[tokio::test]
+    async fn test_run_due_reports_once_per_period() {
+        let db = Db::open_memory().unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let reports = vec![report_config("markdown")];
+
+        // First run only records the current period
+        assert_eq!(
+            run_due_reports(&db, &reports, None, Some(&tx))
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(rx.try_recv().is_err());
+
+        // A period ended since the last report: it runs, then not again
+        db.report_set_last_period("usage", 0).await.unwrap();
+        assert_eq!(
+            run_due_reports(&db, &reports, None, Some(&tx))
+                .await
+                .unwrap(),
+            1
+        );
+        let msg = rx.try_recv().unwrap();
+        assert_eq!(msg.channel, "telegram");
+        assert!(msg.content.starts_with("# Usage report: usage"));
+        assert_eq!(
+            run_due_reports(&db, &reports, None, Some(&tx))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("tg-1"), "tg-1");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
@@ -0,0 +1,321 @@
+//! Automatic pruning (`[persistence.retention]`).
+//!
+//! Once an hour the scheduler applies the configured limits:
+//!
+//! - `max_session_days`: tapes of sessions idle that long are pruned, along
+//!   with processed queue entries and audit entries older than that (audit
+//!   entries are rolled up into daily counts first, as with `[audit]
+//!   keep_days`).
+//! - `max_messages`: longer tapes lose their oldest messages.
+//! - `max_db_mb`: while the database uses more, the least recently active
+//!   sessions are pruned, never one active in the last day.
+//!
+//! A pruned session loses its tape, turn snapshots and worker runs; its
+//! annotations stay. With `summarize`, its conversation is summarized into a
+//! `reflection` memory first (raw text if the model fails); private sessions
+//! are pruned without one.
+
+use super::cortex::extract_conversation_text;
+use super::AgentRunConfig;
+use crate::config::RetentionConfig;
+use crate::db::{now_ms, Db, DbError};
+use std::time::Duration;
+
+/// How often retention runs.
+pub const INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Sessions active this recently are never pruned for size.
+const SIZE_GRACE_MS: u64 = 86_400_000;
+
+/// Max characters of a session sent to the summarizer.
+const SUMMARY_INPUT_CHARS: usize = 16_000;
+/// Max characters stored when the summarizer fails.
+const RAW_FALLBACK_CHARS: usize = 4_000;
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What one retention pass removed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Pruned {
+    pub sessions: usize,
+    /// Messages trimmed from tapes over `max_messages`.
+    pub messages: usize,
+    pub queue: usize,
+    pub audit: usize,
+}
+
+impl Pruned {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::fmt::Display for Pruned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} session(s), {} tape message(s), {} queue entries, {} audit entries",
+            self.sessions, self.messages, self.queue, self.audit
+        )
+    }
+}
+
+/// Apply the retention limits once. Sessions are summarized with
+/// `summarizer` before they are pruned, if given.
+pub async fn run(
+    db: &Db,
+    config: &RetentionConfig,
+    summarizer: Option<&AgentRunConfig>,
+) -> Result<Pruned, DbError> {
+    let mut pruned = Pruned::default();
+    let now = now_ms();
+
+    if let Some(days) = config.max_session_days {
+        // At least a day, so today's audit entries stay for the budget
+        let cutoff = now.saturating_sub(days.max(1) * 86_400_000);
+        for session in db.tape_list_sessions().await? {
+            if session.updated_at < cutoff
+                && prune_session(db, &session.session_id, summarizer).await?
+            {
+                pruned.sessions += 1;
+            }
+        }
+        pruned.queue = db.queue_prune(cutoff).await?;
+        pruned.audit = db.audit_rollup(cutoff).await?;
+    }
+
+    if let Some(max) = config.max_messages {
+        pruned.messages = db.tape_trim(max.max(1)).await?;
+    }
+
+    if let Some(mb) = config.max_db_mb {
+        let max_bytes = mb * 1024 * 1024;
+        if db.used_bytes().await? > max_bytes {
+            let grace = now.saturating_sub(SIZE_GRACE_MS);
+            let mut sessions = db.tape_list_sessions().await?;
+            sessions.retain(|s| s.updated_at < grace);
+            sessions.sort_by_key(|s| s.updated_at);
+            for session in sessions {
+                if db.used_bytes().await? <= max_bytes {
+                    break;
+                }
+                if prune_session(db, &session.session_id, summarizer).await? {
+                    pruned.sessions += 1;
+                }
+            }
+            if db.used_bytes().await? > max_bytes {
+                tracing::warn!(
+                    "Database still uses more than max_db_mb ({} MB) with no idle sessions left to prune",
+                    mb
+                );
+            }
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Summarize a session into memory, unless it is private, then prune it.
+async fn prune_session(
+    db: &Db,
+    session_id: &str,
+    summarizer: Option<&AgentRunConfig>,
+) -> Result<bool, DbError> {
+    if let Some(agent_config) = summarizer {
+        if !db.privacy_is_private(session_id).await? {
+            let messages = db.tape_load_messages(session_id).await?;
+            let text = extract_conversation_text(&messages, SUMMARY_INPUT_CHARS);
+            if !text.is_empty() {
+                let summary = match summarize(agent_config, &text).await {
+                    Some(summary) => summary,
+                    None => extract_conversation_text(&messages, RAW_FALLBACK_CHARS),
+                };
+                db.memory_store_with_meta(
+                    Some(&format!("retention:{}", session_id)),
+                    &format!("Session {} summary: {}", session_id, summary),
+                    Some("retention"),
+                    Some("retention"),
+                    "reflection",
+                    4,
+                )
+                .await?;
+            }
+        }
+    }
+    let deleted = db.tape_prune_session(session_id).await?;
+    if deleted {
+        tracing::info!("Retention: pruned session {}", session_id);
+    }
+    Ok(deleted)
+}
+
+/// Ask the summarizer for a summary of a whole session. None if it fails.
+async fn summarize(agent_config: &AgentRunConfig, text: &str) -> Option<String> {
+    let prompt = format!(
+        "Summarize this conversation in at most 150 words. Keep names, facts, \
+         decisions, dates and open tasks; drop greetings and filler. \
+         Reply with the summary only.\n\n{}",
+        text
+    );
+    let result = tokio::time::timeout(
+        SUMMARY_TIMEOUT,
+        super::run_ephemeral_prompt(
+            agent_config,
+            "You summarize conversations concisely. Output a brief summary only.",
+            &prompt,
+        ),
+    )
+    .await;
+    match result {
+        Ok(Ok(summary)) if !summary.trim().is_empty() => Some(summary.trim().to_string()),
+        Ok(Ok(_)) => {
+            tracing::warn!("Retention summary was empty, storing raw text");
+            None
+        }
+        Ok(Err(e)) => {
+            tracing::warn!("Retention summary failed, storing raw text: {}", e);
+            None
+        }
+        Err(_) => {
+            tracing::warn!("Retention summary timed out, storing raw text");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queue::QueueEntry;
+    use yoagent::types::{Content, Message, StopReason, Usage};
+    use yoagent::AgentMessage;
+
+    const DAY_MS: u64 = 86_400_000;
+
+    async fn backdate(db: &Db, session_id: &str, days: u64) {
+        let ts = (now_ms() - days * DAY_MS) as i64;
+        let session_id = session_id.to_string();
+        db.exec(move |conn| {
+            conn.execute(
+                "UPDATE tape SET updated_at = ?1 WHERE session_id = ?2",
+                rusqlite::params![ts, session_id],
+            )?;
+            conn.execute(
+                "UPDATE queue SET created_at = ?1 WHERE session_id = ?2",
+                rusqlite::params![ts, session_id],
+            )?;
+            conn.execute(
+                "UPDATE audit SET timestamp = ?1 WHERE session_id = ?2",
+                rusqlite::params![ts, session_id],
+            )?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prune_by_age() {
+        let db = Db::open_memory().unwrap();
+        let msgs = vec![AgentMessage::Llm(Message::user("hi"))];
+        for session in ["tg-old", "tg-new"] {
+            db.tape_save_messages(session, &msgs).await.unwrap();
+            let id = db
+                .queue_push(&QueueEntry::new("telegram", "1", session, "hi"))
+                .await
+                .unwrap();
+            db.queue_mark_done(id).await.unwrap();
+            db.audit_log(Some(session), "tool_call", Some("shell"), None, 10)
+                .await
+                .unwrap();
+        }
+        // Still waiting: never pruned
+        db.queue_push(&QueueEntry::new("telegram", "1", "tg-old", "again"))
+            .await
+            .unwrap();
+        db.annotation_add("tg-old", 0, "", &["great answer".into()], &msgs[0])
+            .await
+            .unwrap();
+        backdate(&db, "tg-old", 40).await;
+
+        let config = RetentionConfig {
+            max_session_days: Some(30),
+            ..Default::default()
+        };
+        let pruned = run(&db, &config, None).await.unwrap();
+        assert_eq!(
+            pruned,
+            Pruned {
+                sessions: 1,
+                messages: 0,
+                queue: 1,
+                audit: 1,
+            }
+        );
+        assert!(db.tape_load_messages("tg-old").await.unwrap().is_empty());
+        assert_eq!(db.tape_load_messages("tg-new").await.unwrap().len(), 1);
+        assert_eq!(db.queue_pending_count().await.unwrap(), 1);
+        assert_eq!(
+            db.annotation_list(Some("tg-old"), None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(run(&db, &config, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_trim_and_size() {
+        let db = Db::open_memory().unwrap();
+        let call = AgentMessage::Llm(Message::Assistant {
+            content: vec![Content::ToolCall {
+                id: "tc-1".to_string(),
+                name: "shell".to_string(),
+                arguments: serde_json::json!({}),
+            }],
+            stop_reason: StopReason::ToolUse,
+            model: "mock".to_string(),
+            provider: "mock".to_string(),
+            usage: Usage::default(),
+            timestamp: 0,
+            error_message: None,
+        });
+        let result = AgentMessage::Llm(Message::ToolResult {
+            tool_call_id: "tc-1".to_string(),
+            tool_name: "shell".to_string(),
+            content: vec![Content::Text {
+                text: "ok".to_string(),
+            }],
+            is_error: false,
+            timestamp: 0,
+        });
+        let msgs = vec![
+            AgentMessage::Llm(Message::user("run it")),
+            call,
+            result,
+            AgentMessage::Llm(Message::user("thanks")),
+        ];
+        db.tape_save_messages("tg-1", &msgs).await.unwrap();
+        db.tape_save_messages("tg-2", &msgs[..1]).await.unwrap();
+
+        // Two would start with the tool result, so only one is kept
+        let config = RetentionConfig {
+            max_messages: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(run(&db, &config, None).await.unwrap().messages, 3);
+        assert_eq!(db.tape_load_messages("tg-1").await.unwrap(), msgs[3..]);
+        assert_eq!(db.tape_load_messages("tg-2").await.unwrap().len(), 1);
+
+        backdate(&db, "tg-1", 2).await;
+        let config = RetentionConfig {
+            max_db_mb: Some(0),
+            ..Default::default()
+        };
+        // tg-2 was active today
+        assert_eq!(run(&db, &config, None).await.unwrap().sessions, 1);
+        assert!(db.tape_load_messages("tg-1").await.unwrap().is_empty());
+        assert_eq!(db.tape_load_messages("tg-2").await.unwrap().len(), 1);
+    }
+}
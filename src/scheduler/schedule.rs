@@ -0,0 +1,324 @@
+//! Natural-language schedules and human-readable descriptions.
+//!
+//! Cron jobs can be written as text like "every weekday at 9am" or "first
+//! Monday of the month at 10:30". [`to_cron`] converts them to cron
+//! expressions when the config is loaded or the `cron_schedule` tool creates
+//! a job, so only cron expressions are stored. [`describe`] goes the other
+//! way for `yoclaw cron list`, the tool's list and `/api/cron`.
+//!
+//! Weekdays are written as names (`Mon-Fri`), and day-of-month and
+//! day-of-week fields must both match, so "first Monday" is `1-7 * Mon`.
+
+use super::cron::normalize_cron;
+use cron::Schedule;
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error(
+        "Unrecognized schedule '{0}': use a cron expression like '0 9 * * Mon-Fri' \
+         or text like 'every weekday at 9am'"
+    )]
+    Unrecognized(String),
+}
+
+const DAYS: [(&str, &str); 7] = [
+    ("Mon", "Monday"),
+    ("Tue", "Tuesday"),
+    ("Wed", "Wednesday"),
+    ("Thu", "Thursday"),
+    ("Fri", "Friday"),
+    ("Sat", "Saturday"),
+    ("Sun", "Sunday"),
+];
+
+const ORDINALS: [&str; 4] = ["first", "second", "third", "fourth"];
+
+/// Words that carry no meaning in a schedule.
+const FILLER: [&str; 7] = ["every", "each", "on", "the", "and", "of", "in"];
+
+/// `schedule` as a cron expression: cron expressions are kept as they are,
+/// text is converted.
+pub fn to_cron(schedule: &str) -> Result<String, ScheduleError> {
+    let schedule = schedule.trim();
+    if Schedule::from_str(&normalize_cron(schedule)).is_ok() {
+        return Ok(schedule.to_string());
+    }
+    parse(schedule).ok_or_else(|| ScheduleError::Unrecognized(schedule.to_string()))
+}
+
+/// Convert a natural-language schedule to a 5-field cron expression.
+fn parse(text: &str) -> Option<String> {
+    let text = text.to_lowercase().replace(',', " ");
+    let (days, time) = match text.rsplit_once(" at ") {
+        Some((days, time)) => (days.to_string(), Some(parse_time(time.trim())?)),
+        None => match text.strip_prefix("at ") {
+            // "at 9am" alone means daily
+            Some(time) => ("daily".to_string(), Some(parse_time(time.trim())?)),
+            None => (text.clone(), None),
+        },
+    };
+    let (minute, hour) = time.unwrap_or((0, 0));
+
+    let mut words: Vec<&str> = days
+        .split_whitespace()
+        .filter(|w| !FILLER.contains(w))
+        .collect();
+    let monthly = words.last() == Some(&"month") || words.first() == Some(&"month");
+    words.retain(|w| *w != "month");
+
+    let expr = match (words.as_slice(), monthly, time) {
+        (["minute"], false, None) => "* * * * *".to_string(),
+        ([n, "minutes" | "minute" | "mins"], false, None) => {
+            format!("*/{} * * * *", interval(n, 59)?)
+        }
+        (["hour"] | ["hourly"], false, None) => "0 * * * *".to_string(),
+        ([n, "hours" | "hour"], false, None) => format!("0 */{} * * *", interval(n, 23)?),
+        (["day"] | ["days"] | ["daily"], false, _) => format!("{} {} * * *", minute, hour),
+        (["weekday"] | ["weekdays"], false, _) => format!("{} {} * * Mon-Fri", minute, hour),
+        (["weekend"] | ["weekends"], false, _) => format!("{} {} * * Sat,Sun", minute, hour),
+        ([] | ["monthly"], true, _) | (["monthly"], false, _) => {
+            format!("{} {} 1 * *", minute, hour)
+        }
+        ([day], true, _) => format!("{} {} {} * *", minute, hour, day_of_month(day)?),
+        ([nth, day], _, _) if ORDINALS.contains(nth) => {
+            let week = ORDINALS.iter().position(|o| o == nth)? as u32;
+            format!(
+                "{} {} {}-{} * {}",
+                minute,
+                hour,
+                week * 7 + 1,
+                week * 7 + 7,
+                weekday(day)?
+            )
+        }
+        (days, false, _) if !days.is_empty() => {
+            let days = days
+                .iter()
+                .map(|d| weekday(d))
+                .collect::<Option<Vec<_>>>()?;
+            format!("{} {} * * {}", minute, hour, days.join(","))
+        }
+        _ => return None,
+    };
+    Some(expr)
+}
+
+/// "9am", "9:30 pm", "17:00", "noon" or "midnight" as (minute, hour).
+fn parse_time(text: &str) -> Option<(u32, u32)> {
+    match text {
+        "noon" | "midday" => return Some((0, 12)),
+        "midnight" => return Some((0, 0)),
+        _ => {}
+    }
+    let text = text.replace('.', "");
+    let (clock, meridiem) = if let Some(t) = text.strip_suffix("am") {
+        (t.trim(), Some(false))
+    } else if let Some(t) = text.strip_suffix("pm") {
+        (t.trim(), Some(true))
+    } else {
+        (text.trim(), None)
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) if m.len() == 2 => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        Some(_) => return None,
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    if minute > 59 {
+        return None;
+    }
+    let hour = match meridiem {
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None if hour <= 23 => hour,
+        None => return None,
+    };
+    Some((minute, hour))
+}
+
+fn interval(n: &str, max: u32) -> Option<u32> {
+    let n = match n {
+        "other" => 2,
+        n => n.parse().ok()?,
+    };
+    (1..=max).contains(&n).then_some(n)
+}
+
+/// "15th", "1st" or "15" as a day of the month.
+fn day_of_month(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    digits.parse().ok().filter(|d| (1..=31).contains(d))
+}
+
+/// "monday", "mondays" or "mon" as the cron name "Mon".
+fn weekday(word: &str) -> Option<&'static str> {
+    let word = word.trim_end_matches('s');
+    DAYS.iter()
+        .find(|(short, long)| {
+            word.eq_ignore_ascii_case(short)
+                || word.eq_ignore_ascii_case(long.trim_end_matches('s'))
+        })
+        .map(|(short, _)| *short)
+}
+
+/// Human-readable form of a cron expression, or the expression itself when
+/// it doesn't fit one of the common shapes.
+pub fn describe(expr: &str) -> String {
+    describe_fields(expr).unwrap_or_else(|| expr.to_string())
+}
+
+fn describe_fields(expr: &str) -> Option<String> {
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    let [minute, hour, dom, month, dow] = fields.as_slice() else {
+        return None;
+    };
+    if *month != "*" {
+        return None;
+    }
+    match (*minute, *hour, *dom, *dow) {
+        ("*", "*", "*", "*") => return Some("every minute".to_string()),
+        ("0", "*", "*", "*") => return Some("every hour".to_string()),
+        (m, "*", "*", "*") => {
+            return match m.strip_prefix("*/") {
+                Some(n) => Some(format!("every {} minutes", n.parse::<u32>().ok()?)),
+                None => Some(format!("every hour at :{:02}", m.parse::<u32>().ok()?)),
+            }
+        }
+        ("0", h, "*", "*") if h.starts_with("*/") => {
+            return Some(format!("every {} hours", h[2..].parse::<u32>().ok()?))
+        }
+        _ => {}
+    }
+
+    let time = format_time(minute.parse().ok()?, hour.parse().ok()?);
+    let days = match (*dom, *dow) {
+        ("*", "*") => "every day".to_string(),
+        ("*", dow) => format!("every {}", describe_weekdays(dow)?),
+        (dom, "*") => format!("on the {} of every month", ordinal(dom.parse().ok()?)),
+        (dom, dow) => {
+            let (first, last) = dom.split_once('-')?;
+            let (first, last): (u32, u32) = (first.parse().ok()?, last.parse().ok()?);
+            let week = ORDINALS.get((first / 7) as usize)?;
+            if first % 7 != 1 || last != first + 6 {
+                return None;
+            }
+            format!("on the {} {} of every month", week, long_day(dow)?)
+        }
+    };
+    Some(format!("{} at {}", days, time))
+}
+
+fn describe_weekdays(dow: &str) -> Option<String> {
+    let key = dow.to_ascii_lowercase();
+    match key.as_str() {
+        "mon-fri" => return Some("weekday".to_string()),
+        "sat,sun" | "sun,sat" => return Some("weekend day".to_string()),
+        _ => {}
+    }
+    let days = dow.split(',').map(long_day).collect::<Option<Vec<_>>>()?;
+    Some(match days.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => days.join(""),
+    })
+}
+
+fn long_day(short: &str) -> Option<&'static str> {
+    DAYS.iter()
+        .find(|(s, _)| s.eq_ignore_ascii_case(short))
+        .map(|(_, long)| *long)
+}
+
+fn format_time(minute: u32, hour: u32) -> String {
+    match (hour, minute) {
+        (0, 0) => "midnight".to_string(),
+        (12, 0) => "noon".to_string(),
+        _ => {
+            let suffix = if hour < 12 { "am" } else { "pm" };
+            let h = match hour % 12 {
+                0 => 12,
+                h => h,
+            };
+            if minute == 0 {
+                format!("{}{}", h, suffix)
+            } else {
+                format!("{}:{:02}{}", h, minute, suffix)
+            }
+        }
+    }
+}
+
+fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_cron() {
+        let cases = [
+            ("0 9 * * *", "0 9 * * *"),
+            ("every weekday at 9am", "0 9 * * Mon-Fri"),
+            ("Every day at 6:30 PM", "30 18 * * *"),
+            ("at noon", "0 12 * * *"),
+            ("every Monday and Friday at 17:00", "0 17 * * Mon,Fri"),
+            ("mondays, wednesdays at 8am", "0 8 * * Mon,Wed"),
+            ("every weekend at 10am", "0 10 * * Sat,Sun"),
+            ("first Monday of the month", "0 0 1-7 * Mon"),
+            ("third friday of every month at 4pm", "0 16 15-21 * Fri"),
+            ("on the 15th of every month at 9am", "0 9 15 * *"),
+            ("every month on the 1st", "0 0 1 * *"),
+            ("monthly at midnight", "0 0 1 * *"),
+            ("every 15 minutes", "*/15 * * * *"),
+            ("every other hour", "0 */2 * * *"),
+            ("hourly", "0 * * * *"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(to_cron(text).unwrap(), expected, "{}", text);
+            // Every conversion is a valid expression
+            assert!(Schedule::from_str(&normalize_cron(expected)).is_ok());
+        }
+        for bad in [
+            "whenever",
+            "every day at 25:00",
+            "every 90 minutes",
+            "at 13pm",
+        ] {
+            assert!(to_cron(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_describe() {
+        let cases = [
+            ("0 9 * * Mon-Fri", "every weekday at 9am"),
+            ("30 18 * * *", "every day at 6:30pm"),
+            (
+                "0 17 * * Mon,Wed,Fri",
+                "every Monday, Wednesday and Friday at 5pm",
+            ),
+            (
+                "0 0 1-7 * Mon",
+                "on the first Monday of every month at midnight",
+            ),
+            ("0 12 15 * *", "on the 15th of every month at noon"),
+            ("*/15 * * * *", "every 15 minutes"),
+            ("0 */2 * * *", "every 2 hours"),
+            ("5 * * * *", "every hour at :05"),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(describe(expr), expected, "{}", expr);
+        }
+        // Shapes without a description fall back to the expression
+        assert_eq!(describe("0 18 * * 1-5"), "0 18 * * 1-5");
+        assert_eq!(describe("0 0 9 1 * *"), "0 0 9 1 * *");
+    }
+}
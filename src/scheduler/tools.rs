@@ -1,6 +1,7 @@
-//! AgentTool for managing cron jobs conversationally.
+//! AgentTools for scheduling work conversationally: cron jobs and background tasks.
 
 use crate::db::Db;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use yoagent::types::*;
 
@@ -8,11 +9,22 @@ use yoagent::types::*;
 pub struct CronScheduleTool {
     db: Db,
     session_id_ref: Arc<RwLock<String>>,
+    rules: super::cron::JobRules,
 }
 
 impl CronScheduleTool {
     pub fn new(db: Db, session_id_ref: Arc<RwLock<String>>) -> Self {
-        Self { db, session_id_ref }
+        Self {
+            db,
+            session_id_ref,
+            rules: Default::default(),
+        }
+    }
+
+    /// Check new jobs against `rules` (the config's providers).
+    pub fn with_job_rules(mut self, rules: super::cron::JobRules) -> Self {
+        self.rules = rules;
+        self
     }
 }
 
@@ -47,7 +59,7 @@ impl AgentTool for CronScheduleTool {
                 },
                 "schedule": {
                     "type": "string",
-                    "description": "Cron expression, e.g. '0 9 * * *' for 9am daily (required for create)"
+                    "description": "Cron expression like '0 9 * * *', or text like 'every weekday at 9am' or 'first Monday of the month at 10:30' (required for create)"
                 },
                 "prompt": {
                     "type": "string",
@@ -55,7 +67,7 @@ impl AgentTool for CronScheduleTool {
                 },
                 "target": {
                     "type": "string",
-                    "description": "Target session ID to deliver results to (e.g. 'tg-514133400' for Telegram, 'dc-guild-channel' for Discord)"
+                    "description": "Target session ID to deliver results to (e.g. 'tg-514133400' for Telegram, 'dc-guild-channel' for Discord, 'webhook:<name>' for a configured webhook)"
                 },
                 "session": {
                     "type": "string",
@@ -65,6 +77,18 @@ impl AgentTool for CronScheduleTool {
                 "enabled": {
                     "type": "boolean",
                     "description": "For toggle action: whether to enable (true) or disable (false) the job"
+                },
+                "model": {
+                    "type": "string",
+                    "description": "For create: model to run the job with instead of the main model, e.g. a cheap one for trivial checks"
+                },
+                "provider": {
+                    "type": "string",
+                    "description": "For create: provider for the job's model, if different from the main provider"
+                },
+                "max_tokens": {
+                    "type": "integer",
+                    "description": "For create: max tokens per response for the job"
                 }
             },
             "required": ["action"]
@@ -108,6 +132,8 @@ impl CronScheduleTool {
         let schedule = params["schedule"]
             .as_str()
             .ok_or_else(|| ToolError::InvalidArgs("Missing 'schedule' for create".into()))?;
+        let schedule = super::schedule::to_cron(schedule)
+            .map_err(|e| ToolError::InvalidArgs(e.to_string()))?;
         let prompt = params["prompt"]
             .as_str()
             .ok_or_else(|| ToolError::InvalidArgs("Missing 'prompt' for create".into()))?;
@@ -128,16 +154,38 @@ impl CronScheduleTool {
         };
         let session = params["session"].as_str().unwrap_or("isolated");
 
-        super::cron::create_job(&self.db, name, schedule, prompt, target, session)
+        let model = params["model"].as_str();
+        let provider = params["provider"].as_str();
+        let max_tokens = params["max_tokens"].as_u64().map(|n| n as u32);
+        // Before the job exists, so a bad override doesn't leave it behind
+        self.rules
+            .check_provider(provider)
+            .map_err(|e| ToolError::InvalidArgs(e.to_string()))?;
+
+        super::cron::create_job(
+            &self.db,
+            &self.rules,
+            name,
+            &schedule,
+            prompt,
+            target,
+            session,
+        )
+        .await
+        .map_err(|e| ToolError::Failed(format!("Failed to create job: {}", e)))?;
+        // Always set, so re-creating a job clears old overrides
+        super::cron::set_job_model(&self.db, &self.rules, name, provider, model, max_tokens)
             .await
             .map_err(|e| ToolError::Failed(format!("Failed to create job: {}", e)))?;
 
         Ok(format!(
-            "Created cron job '{}' with schedule '{}'. Target: {}. Session: {}.",
+            "Created cron job '{}' with schedule '{}' ({}). Target: {}. Session: {}. Model: {}.",
             name,
             schedule,
+            super::schedule::describe(&schedule),
             target.unwrap_or("none"),
-            session
+            session,
+            model.unwrap_or("default")
         ))
     }
 
@@ -155,13 +203,20 @@ impl CronScheduleTool {
             .map(|j| {
                 let status = if j.enabled { "enabled" } else { "disabled" };
                 let target = j.target_channel.as_deref().unwrap_or("none");
+                let next = match j.next_run().filter(|_| j.enabled) {
+                    Some(t) => t.format("%Y-%m-%d %H:%M UTC").to_string(),
+                    None => "none".to_string(),
+                };
                 format!(
-                    "- {} [{}] schedule='{}' target={} session={} prompt='{}'",
+                    "- {} [{}] schedule='{}' ({}) next={} target={} session={} model={} prompt='{}'",
                     j.name,
                     status,
                     j.schedule,
+                    j.describe_schedule(),
+                    next,
                     target,
                     j.session_mode,
+                    j.model.as_deref().unwrap_or("default"),
                     truncate_str(&j.prompt, 60)
                 )
             })
@@ -206,7 +261,93 @@ impl CronScheduleTool {
     }
 }
 
-fn truncate_str(s: &str, max: usize) -> String {
+/// Tool for the agent to hand a long task to a background worker.
+pub struct BackgroundTaskTool {
+    db: Db,
+    session_id_ref: Arc<RwLock<String>>,
+    private_ref: Arc<AtomicBool>,
+}
+
+impl BackgroundTaskTool {
+    pub fn new(db: Db, session_id_ref: Arc<RwLock<String>>, private_ref: Arc<AtomicBool>) -> Self {
+        Self {
+            db,
+            session_id_ref,
+            private_ref,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentTool for BackgroundTaskTool {
+    fn name(&self) -> &str {
+        "work_on_this_later"
+    }
+
+    fn label(&self) -> &str {
+        "Work On This Later"
+    }
+
+    fn description(&self) -> &str {
+        "Queue a long-running task to be worked on in the background. The result is sent to \
+         this conversation when it's done, and the user can check progress with /jobs. Use this \
+         when a request would take too long to answer right away. The task must be \
+         self-contained: the background worker does not see this conversation."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "task": {
+                    "type": "string",
+                    "description": "Complete description of the work, including any context from the conversation it needs"
+                }
+            },
+            "required": ["task"]
+        })
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        _ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let task = params["task"]
+            .as_str()
+            .filter(|t| !t.trim().is_empty())
+            .ok_or_else(|| ToolError::InvalidArgs("Missing 'task' parameter".into()))?;
+        if self.private_ref.load(Ordering::SeqCst) {
+            return Err(ToolError::Failed(
+                "Background tasks are not available in private sessions".into(),
+            ));
+        }
+        let session_id = self.session_id_ref.read().unwrap().clone();
+        if session_id.is_empty() {
+            return Err(ToolError::Failed(
+                "No session to deliver the result to".into(),
+            ));
+        }
+
+        let id = self
+            .db
+            .background_enqueue(&session_id, task)
+            .await
+            .map_err(|e| ToolError::Failed(format!("Failed to queue task: {}", e)))?;
+
+        Ok(ToolResult {
+            content: vec![Content::Text {
+                text: format!(
+                    "Queued background task #{}. The result will be sent to this conversation when it's done.",
+                    id
+                ),
+            }],
+            details: serde_json::json!({ "id": id }),
+        })
+    }
+}
+
+pub(crate) fn truncate_str(s: &str, max: usize) -> String {
     if s.len() <= max {
         s.to_string()
     } else {
@@ -259,6 +400,38 @@ mod tests {
         let text = content_text(&result.content[0]);
         assert!(text.contains("morning-check"));
         assert!(text.contains("0 9 * * *"));
+        assert!(text.contains("(every day at 9am) next="));
+    }
+
+    #[tokio::test]
+    async fn test_cron_tool_provider_needs_a_key() {
+        let db = Db::open_memory().unwrap();
+        let tool = CronScheduleTool::new(db.clone(), Arc::new(RwLock::new(String::new())))
+            .with_job_rules(super::super::cron::JobRules {
+                providers: vec!["anthropic".into(), "openai".into()],
+                ..Default::default()
+            });
+        let create = |provider: &str| {
+            serde_json::json!({
+                "action": "create",
+                "name": "cheap",
+                "schedule": "0 9 * * *",
+                "prompt": "test",
+                "provider": provider,
+                "model": "small"
+            })
+        };
+
+        let err = tool
+            .execute(create("google"), test_ctx())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("fallback_providers"));
+        assert!(db.cron_jobs_list().await.unwrap().is_empty());
+
+        tool.execute(create("openai"), test_ctx()).await.unwrap();
+        let jobs = db.cron_jobs_list().await.unwrap();
+        assert_eq!(jobs[0].provider.as_deref(), Some("openai"));
     }
 
     #[tokio::test]
@@ -326,6 +499,35 @@ mod tests {
         assert!(content_text(&result.content[0]).contains("Disabled"));
     }
 
+    #[tokio::test]
+    async fn test_background_task_tool() {
+        let db = Db::open_memory().unwrap();
+        let private = Arc::new(AtomicBool::new(false));
+        let tool = BackgroundTaskTool::new(
+            db.clone(),
+            Arc::new(RwLock::new("tg-1".to_string())),
+            private.clone(),
+        );
+
+        let result = tool
+            .execute(
+                serde_json::json!({ "task": "research flights" }),
+                test_ctx(),
+            )
+            .await
+            .unwrap();
+        assert!(content_text(&result.content[0]).starts_with("Queued background task #"));
+        let tasks = db.background_list("tg-1", 10).await.unwrap();
+        assert_eq!(tasks[0].task, "research flights");
+        assert_eq!(tasks[0].status, "pending");
+
+        private.store(true, Ordering::SeqCst);
+        assert!(tool
+            .execute(serde_json::json!({ "task": "secret" }), test_ctx())
+            .await
+            .is_err());
+    }
+
     /// Helper: extract text from Content.
     fn content_text(c: &Content) -> &str {
         match c {
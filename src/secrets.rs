@@ -0,0 +1,129 @@
+//! Secrets kept in the OS keyring instead of the config file.
+//!
+//! A config string that is exactly `"keyring:<service>/<name>"` is replaced
+//! by the secret stored under that service and name before the TOML is
+//! parsed, the same way `${VAR}` is replaced by an environment variable.
+//! Without a `/`, the service is `yoclaw`. `yoclaw secrets set|get|rm`
+//! manage the entries.
+//!
+//! The keyring is the macOS Keychain, the Windows Credential Manager or the
+//! Secret Service (GNOME Keyring, KWallet) on Linux, and needs yoclaw built
+//! with the `keyring` feature.
+
+/// Prefix of config values read from the keyring.
+pub const PREFIX: &str = "keyring:";
+
+/// Service of names given without one.
+pub const DEFAULT_SERVICE: &str = "yoclaw";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    #[error("'{0}': keyring secrets need yoclaw built with the `keyring` feature")]
+    Unsupported(String),
+    #[error("No secret '{0}' in the keyring; add it with `yoclaw secrets set {0}`")]
+    Missing(String),
+    #[error("Invalid secret name '{0}': use <service>/<name> or <name>")]
+    InvalidName(String),
+    #[error("Keyring error for '{name}': {message}")]
+    Keyring { name: String, message: String },
+}
+
+/// Split `name` into keyring service and entry name.
+pub fn parse_name(name: &str) -> Result<(&str, &str), SecretError> {
+    let (service, user) = name.split_once('/').unwrap_or((DEFAULT_SERVICE, name));
+    if service.is_empty() || user.is_empty() || user.contains('/') {
+        return Err(SecretError::InvalidName(name.to_string()));
+    }
+    Ok((service, user))
+}
+
+/// The secret stored under `name`.
+pub fn get(name: &str) -> Result<String, SecretError> {
+    let (service, user) = parse_name(name)?;
+    imp::get(name, service, user)
+}
+
+/// Store `value` under `name`, replacing any previous one.
+pub fn set(name: &str, value: &str) -> Result<(), SecretError> {
+    let (service, user) = parse_name(name)?;
+    imp::set(name, service, user, value)
+}
+
+/// Delete the secret stored under `name`.
+pub fn remove(name: &str) -> Result<(), SecretError> {
+    let (service, user) = parse_name(name)?;
+    imp::remove(name, service, user)
+}
+
+#[cfg(feature = "keyring")]
+mod imp {
+    use super::SecretError;
+    use keyring::{Entry, Error};
+
+    fn entry(name: &str, service: &str, user: &str) -> Result<Entry, SecretError> {
+        Entry::new(service, user).map_err(|e| map_error(name, e))
+    }
+
+    fn map_error(name: &str, error: Error) -> SecretError {
+        match error {
+            Error::NoEntry => SecretError::Missing(name.to_string()),
+            e => SecretError::Keyring {
+                name: name.to_string(),
+                message: e.to_string(),
+            },
+        }
+    }
+
+    pub fn get(name: &str, service: &str, user: &str) -> Result<String, SecretError> {
+        entry(name, service, user)?
+            .get_password()
+            .map_err(|e| map_error(name, e))
+    }
+
+    pub fn set(name: &str, service: &str, user: &str, value: &str) -> Result<(), SecretError> {
+        entry(name, service, user)?
+            .set_password(value)
+            .map_err(|e| map_error(name, e))
+    }
+
+    pub fn remove(name: &str, service: &str, user: &str) -> Result<(), SecretError> {
+        entry(name, service, user)?
+            .delete_credential()
+            .map_err(|e| map_error(name, e))
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+mod imp {
+    use super::SecretError;
+
+    pub fn get(name: &str, _service: &str, _user: &str) -> Result<String, SecretError> {
+        Err(SecretError::Unsupported(name.to_string()))
+    }
+
+    pub fn set(name: &str, _service: &str, _user: &str, _value: &str) -> Result<(), SecretError> {
+        Err(SecretError::Unsupported(name.to_string()))
+    }
+
+    pub fn remove(name: &str, _service: &str, _user: &str) -> Result<(), SecretError> {
+        Err(SecretError::Unsupported(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_name() {
+        assert_eq!(
+            parse_name("yoclaw/anthropic").unwrap(),
+            ("yoclaw", "anthropic")
+        );
+        assert_eq!(parse_name("telegram").unwrap(), ("yoclaw", "telegram"));
+        assert_eq!(parse_name("work/slack").unwrap(), ("work", "slack"));
+        for bad in ["", "/x", "x/", "a/b/c"] {
+            assert!(parse_name(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! Approval of tool calls marked `requires_approval`.
+//!
+//! The call is parked inside the turn, like an `ask_user` question: the
+//! prompt goes to the chat the message came from and the wrapper waits for a
+//! reply in that session ("yes" approves, anything else denies), a 👍/👎
+//! reaction where the channel reports reactions, or a tap on the Approve or
+//! Deny button where it has buttons. Only the sender of the message being
+//! processed, or one of `security.admins`, decides; others in a group chat
+//! are ignored. No answer within `[security.approval] timeout_secs` denies
+//! the call. Scheduled runs, delegations and workers have nobody to ask, so
+//! their calls are denied.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Longest rendering of the call's arguments in the prompt.
+const MAX_ARGS_CHARS: usize = 500;
+
+/// Labels of the approval buttons. A tap sends the label as a reply, which
+/// [`parse_reply`] reads as yes or no.
+pub const BUTTONS: [&str; 2] = ["Approve", "Deny"];
+
+/// Sends a prompt with buttons under it: the text, then the button labels.
+pub type ButtonsFn = Arc<dyn Fn(String, Vec<String>) + Send + Sync>;
+
+/// What became of an approval request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Approved,
+    Denied,
+    TimedOut,
+    /// No chat to ask in: scheduled runs, workers.
+    NoOneToAsk,
+    /// A newer request in the session replaced this one.
+    Replaced,
+}
+
+impl Outcome {
+    /// Audit detail and the start of the error the agent sees.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Outcome::Approved => "approved",
+            Outcome::Denied => "denied by the user",
+            Outcome::TimedOut => "not approved in time",
+            Outcome::NoOneToAsk => "no one to ask for approval",
+            Outcome::Replaced => "replaced by a newer approval request",
+        }
+    }
+}
+
+/// Who a session's approvals are for, and how its channel can ask.
+#[derive(Clone, Default)]
+pub struct Requester {
+    /// `<channel>:<sender_id>` of the message being processed.
+    pub sender: String,
+    /// `security.admins`, who may decide any approval.
+    pub admins: Vec<String>,
+    /// The channel reports reactions.
+    pub reactions: bool,
+    /// Sends the prompt with Approve/Deny buttons, where the channel has them.
+    pub buttons: Option<ButtonsFn>,
+}
+
+/// A request waiting for its decision.
+struct Pending {
+    tx: oneshot::Sender<bool>,
+    /// Senders whose decision counts; `None` if no requester was noted, so
+    /// the session has a single user.
+    deciders: Option<Vec<String>>,
+}
+
+/// Sessions with a tool call waiting for approval.
+#[derive(Clone)]
+pub struct Approvals {
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+    /// The requester of each session's current message.
+    requesters: Arc<Mutex<HashMap<String, Requester>>>,
+    timeout: Duration,
+}
+
+impl Default for Approvals {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(
+            crate::config::ApprovalConfig::default().timeout_secs,
+        ))
+    }
+}
+
+impl Approvals {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            pending: Arc::default(),
+            requesters: Arc::default(),
+            timeout,
+        }
+    }
+
+    /// Ask for approval of `tool` in `session_id` and wait for the decision:
+    /// with buttons where the session's channel has them, otherwise through
+    /// `on_progress`.
+    pub async fn request(
+        &self,
+        session_id: &str,
+        tool: &str,
+        params: &serde_json::Value,
+        ctx: &yoagent::types::ToolContext,
+    ) -> Outcome {
+        let Some(ref on_progress) = ctx.on_progress else {
+            return Outcome::NoOneToAsk;
+        };
+
+        let requester = self.requesters.lock().unwrap().get(session_id).cloned();
+        let (tx, rx) = oneshot::channel();
+        let deciders = requester.as_ref().map(|r| {
+            std::iter::once(r.sender.clone())
+                .chain(r.admins.iter().cloned())
+                .collect()
+        });
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), Pending { tx, deciders });
+        let reactions = requester.as_ref().map_or(true, |r| r.reactions);
+        match requester.and_then(|r| r.buttons) {
+            Some(send) => send(
+                prompt(tool, params, self.timeout, reactions, true),
+                BUTTONS.map(String::from).to_vec(),
+            ),
+            None => on_progress(prompt(tool, params, self.timeout, reactions, false)),
+        }
+
+        tokio::select! {
+            result = tokio::time::timeout(self.timeout, rx) => match result {
+                Ok(Ok(true)) => Outcome::Approved,
+                Ok(Ok(false)) => Outcome::Denied,
+                Ok(Err(_)) => Outcome::Replaced,
+                Err(_) => {
+                    self.withdraw(session_id);
+                    Outcome::TimedOut
+                }
+            },
+            _ = ctx.cancel.cancelled() => {
+                self.withdraw(session_id);
+                Outcome::Denied
+            }
+        }
+    }
+
+    fn withdraw(&self, session_id: &str) {
+        self.pending.lock().unwrap().remove(session_id);
+    }
+
+    /// Whether `sender` (`<channel>:<sender_id>`) may decide the request
+    /// waiting in `session_id`.
+    pub fn may_decide(&self, session_id: &str, sender: &str) -> bool {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .is_some_and(|p| {
+                p.deciders
+                    .as_ref()
+                    .map_or(true, |deciders| deciders.iter().any(|d| d == sender))
+            })
+    }
+
+    /// Settle the request waiting in `session_id` on behalf of `sender`.
+    /// Returns false if there is none or `sender` may not decide it.
+    pub fn decide(&self, session_id: &str, sender: &str, approved: bool) -> bool {
+        if !self.may_decide(session_id, sender) {
+            return false;
+        }
+        let Some(pending) = self.pending.lock().unwrap().remove(session_id) else {
+            return false;
+        };
+        pending.tx.send(approved).is_ok()
+    }
+
+    /// Settle the request waiting in `session_id` with a text reply from
+    /// `sender`: yes approves, no and anything else deny. Returns true if the
+    /// reply was a plain yes or no to a request `sender` may decide, so it
+    /// needs no further handling. Replies from anyone else are left alone.
+    pub fn reply(&self, session_id: &str, sender: &str, text: &str) -> bool {
+        if !self.may_decide(session_id, sender) {
+            return false;
+        }
+        match parse_reply(text) {
+            Some(approved) => self.decide(session_id, sender, approved),
+            None => {
+                self.decide(session_id, sender, false);
+                false
+            }
+        }
+    }
+
+    pub fn is_waiting(&self, session_id: &str) -> bool {
+        self.pending.lock().unwrap().contains_key(session_id)
+    }
+
+    /// Record who the message being processed in `session_id` is from and
+    /// what its channel can do, for the approvals it leads to.
+    pub fn note_requester(&self, session_id: &str, requester: Requester) {
+        self.requesters
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), requester);
+    }
+}
+
+/// `Some(true)` for a yes, `Some(false)` for a no, `None` for anything else.
+pub fn parse_reply(text: &str) -> Option<bool> {
+    let word = text.trim().trim_end_matches(['.', '!']).to_lowercase();
+    match word.as_str() {
+        "yes" | "y" | "ok" | "approve" | "approved" | "go" | "go ahead" | "👍" | "✅" => {
+            Some(true)
+        }
+        "no" | "n" | "deny" | "denied" | "stop" | "cancel" | "👎" | "❌" => Some(false),
+        _ => None,
+    }
+}
+
+/// `Some(true)` for approving reactions, `Some(false)` for denying ones.
+pub fn parse_reaction(emoji: &str) -> Option<bool> {
+    match emoji.trim_end_matches('\u{fe0f}') {
+        "👍" | "✅" | "👌" => Some(true),
+        "👎" | "❌" | "🚫" => Some(false),
+        _ => None,
+    }
+}
+
+/// The message asking the user to approve a call. With `reactions`, it
+/// offers reacting as well as replying; with `buttons`, tapping a button.
+pub fn prompt(
+    tool: &str,
+    params: &serde_json::Value,
+    timeout: Duration,
+    reactions: bool,
+    buttons: bool,
+) -> String {
+    let args = serde_json::to_string_pretty(params).unwrap_or_default();
+    let args = match args.char_indices().nth(MAX_ARGS_CHARS) {
+        Some((cut, _)) => format!("{}…", &args[..cut]),
+        None => args,
+    };
+    let how = match (buttons, reactions) {
+        (true, _) => "Tap Approve or Deny, or reply yes or no,",
+        (false, true) => "Reply yes or no (or react 👍/👎)",
+        (false, false) => "Reply yes or no",
+    };
+    format!(
+        "Approve this {} call?\n```\n{}\n```\n{} within {}.",
+        tool,
+        args,
+        how,
+        format_duration(timeout)
+    )
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 60 && secs % 60 == 0 {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yoagent::types::ToolContext;
+
+    fn ctx(prompts: Arc<Mutex<Vec<String>>>) -> ToolContext {
+        ToolContext {
+            tool_call_id: "tc-1".into(),
+            tool_name: "bash".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: Some(Arc::new(move |text: String| {
+                prompts.lock().unwrap().push(text);
+            })),
+        }
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply(" Yes! "), Some(true));
+        assert_eq!(parse_reply("go ahead"), Some(true));
+        assert_eq!(parse_reply("No."), Some(false));
+        assert_eq!(parse_reply("what does it do?"), None);
+        assert_eq!(parse_reaction("👍"), Some(true));
+        assert_eq!(parse_reaction("❌"), Some(false));
+        assert_eq!(parse_reaction("😂"), None);
+    }
+
+    #[test]
+    fn test_prompt() {
+        let params = serde_json::json!({"command": "rm -rf build"});
+        let text = prompt("bash", &params, Duration::from_secs(300), true, false);
+        assert!(text.starts_with("Approve this bash call?"));
+        assert!(text.contains("rm -rf build"));
+        assert!(text.contains("react 👍/👎"));
+        assert!(text.ends_with("within 5 min."));
+
+        let text = prompt("bash", &params, Duration::from_secs(300), false, false);
+        assert!(!text.contains("react"));
+        assert!(text.ends_with("Reply yes or no within 5 min."));
+
+        let text = prompt("bash", &params, Duration::from_secs(300), true, true);
+        assert!(text.ends_with("Tap Approve or Deny, or reply yes or no, within 5 min."));
+        assert!(BUTTONS.iter().all(|b| parse_reply(b).is_some()));
+
+        let long = serde_json::json!({"content": "x".repeat(2000)});
+        assert!(prompt("write_file", &long, Duration::from_secs(90), true, false).len() < 700);
+    }
+
+    /// Run a request for a bash call in `session_id` until it is waiting.
+    async fn waiting_request(
+        approvals: &Approvals,
+        session_id: &'static str,
+        prompts: Arc<Mutex<Vec<String>>>,
+    ) -> tokio::task::JoinHandle<Outcome> {
+        let run = tokio::spawn({
+            let approvals = approvals.clone();
+            let ctx = ctx(prompts);
+            async move {
+                approvals
+                    .request(session_id, "bash", &serde_json::json!({}), &ctx)
+                    .await
+            }
+        });
+        while !approvals.is_waiting(session_id) {
+            tokio::task::yield_now().await;
+        }
+        run
+    }
+
+    #[tokio::test]
+    async fn test_request_waits_for_decision() {
+        let approvals = Approvals::new(Duration::from_secs(5));
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+
+        // Not a yes or no: denies, and is left for the main loop
+        let run = waiting_request(&approvals, "tg-1", prompts.clone()).await;
+        assert!(!approvals.reply("tg-1", "telegram:1", "what's that for?"));
+        assert_eq!(run.await.unwrap(), Outcome::Denied);
+        assert_eq!(prompts.lock().unwrap().len(), 1);
+
+        let run = waiting_request(&approvals, "tg-1", prompts.clone()).await;
+        assert!(approvals.reply("tg-1", "telegram:1", "yes"));
+        assert_eq!(run.await.unwrap(), Outcome::Approved);
+        assert!(!approvals.reply("tg-1", "telegram:1", "yes"));
+        assert!(prompts.lock().unwrap()[1].contains("react"));
+
+        // A channel without reactions isn't offered them
+        approvals.note_requester(
+            "tg-1",
+            Requester {
+                sender: "telegram:1".into(),
+                ..Default::default()
+            },
+        );
+        let run = waiting_request(&approvals, "tg-1", prompts.clone()).await;
+        assert!(approvals.reply("tg-1", "telegram:1", "no"));
+        assert_eq!(run.await.unwrap(), Outcome::Denied);
+        assert!(!prompts.lock().unwrap()[2].contains("react"));
+    }
+
+    #[tokio::test]
+    async fn test_only_requester_or_admin_decides() {
+        let approvals = Approvals::new(Duration::from_secs(5));
+        approvals.note_requester(
+            "tg--100",
+            Requester {
+                sender: "telegram:1".into(),
+                admins: vec!["telegram:9".into()],
+                reactions: true,
+                buttons: None,
+            },
+        );
+
+        // Someone else in the group can't approve, or deny by talking
+        let run = waiting_request(&approvals, "tg--100", Arc::default()).await;
+        assert!(!approvals.may_decide("tg--100", "telegram:2"));
+        assert!(!approvals.reply("tg--100", "telegram:2", "yes"));
+        assert!(!approvals.reply("tg--100", "telegram:2", "lol"));
+        assert!(!approvals.decide("tg--100", "discord:1", true));
+        assert!(approvals.is_waiting("tg--100"));
+        assert!(approvals.reply("tg--100", "telegram:1", "yes"));
+        assert_eq!(run.await.unwrap(), Outcome::Approved);
+
+        // An admin can
+        let run = waiting_request(&approvals, "tg--100", Arc::default()).await;
+        assert!(approvals.decide("tg--100", "telegram:9", false));
+        assert_eq!(run.await.unwrap(), Outcome::Denied);
+    }
+
+    #[tokio::test]
+    async fn test_request_with_buttons() {
+        let approvals = Approvals::new(Duration::from_secs(5));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let buttons: ButtonsFn = Arc::new({
+            let sent = sent.clone();
+            move |text, labels| sent.lock().unwrap().push((text, labels))
+        });
+        approvals.note_requester(
+            "dc-1",
+            Requester {
+                sender: "discord:1".into(),
+                buttons: Some(buttons),
+                ..Default::default()
+            },
+        );
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let run = waiting_request(&approvals, "dc-1", prompts.clone()).await;
+        // A tap on Deny arrives as its label
+        assert!(approvals.reply("dc-1", "discord:1", "Deny"));
+        assert_eq!(run.await.unwrap(), Outcome::Denied);
+
+        // The prompt went with the buttons, not through on_progress
+        assert!(prompts.lock().unwrap().is_empty());
+        let sent = sent.lock().unwrap();
+        assert!(sent[0].0.contains("Tap Approve or Deny"));
+        assert_eq!(sent[0].1, vec!["Approve", "Deny"]);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_and_no_channel() {
+        let approvals = Approvals::new(Duration::from_millis(20));
+        let params = serde_json::json!({});
+        let outcome = approvals
+            .request("tg-1", "bash", &params, &ctx(Arc::default()))
+            .await;
+        assert_eq!(outcome, Outcome::TimedOut);
+        assert!(!approvals.is_waiting("tg-1"));
+
+        let mut no_channel = ctx(Arc::default());
+        no_channel.on_progress = None;
+        let outcome = approvals
+            .request("tg-1", "bash", &params, &no_channel)
+            .await;
+        assert_eq!(outcome, Outcome::NoOneToAsk);
+    }
+}
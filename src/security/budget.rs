@@ -1,17 +1,99 @@
-use crate::db::Db;
+use crate::config::BudgetConfig;
+use crate::db::{Db, DbError};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// Tracks token usage with atomic counters for sync callback compatibility.
 #[derive(Clone)]
 pub struct BudgetTracker {
     max_tokens_per_day: Option<u64>,
     max_turns_per_session: Option<usize>,
+    max_cost_per_day: Option<f64>,
     tokens_today: Arc<AtomicU64>,
+    /// Estimated dollars spent today.
+    cost_today: Arc<Mutex<f64>>,
     turns_this_session: Arc<AtomicU64>,
+    /// Allowance of the message being processed, per conductor.
+    message: Arc<Mutex<MessageBudget>>,
     db: Db,
 }
 
+/// Which daily limit a message ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Session,
+    Sender,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Session => "session",
+            Scope::Sender => "sender",
+        }
+    }
+}
+
+/// What one message may use: the least of what its session and its sender
+/// have left today under `[agent.budget.per_session]`/`[agent.budget.per_sender]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MessageBudget {
+    /// `<channel>:<sender id>` the usage is charged to in the audit table.
+    pub sender: Option<String>,
+    pub max_tokens: Option<u64>,
+    pub max_turns: Option<u64>,
+    /// The limit with nothing left, if any.
+    pub exhausted: Option<Scope>,
+    tokens: u64,
+    turns: u64,
+}
+
+impl MessageBudget {
+    /// No allowance, with usage still charged to `sender`.
+    pub fn unlimited(sender: &str) -> Self {
+        Self {
+            sender: Some(sender.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Work out the allowance of a message from `sender` (`<channel>:<id>`)
+    /// in `session_id`, from today's usage in the audit table.
+    pub async fn load(
+        db: &Db,
+        config: &BudgetConfig,
+        session_id: &str,
+        sender: &str,
+    ) -> Result<Self, DbError> {
+        let mut budget = Self::unlimited(sender);
+        let scopes = [
+            (Scope::Session, &config.per_session, Some(session_id), None),
+            (Scope::Sender, &config.per_sender, None, Some(sender)),
+        ];
+        for (scope, limits, session_id, sender) in scopes {
+            if !limits.is_set() {
+                continue;
+            }
+            let (tokens, turns) = db.audit_usage_today(session_id, sender).await?;
+            let left_tokens = limits.max_tokens_per_day.map(|m| m.saturating_sub(tokens));
+            let left_turns = limits.max_turns_per_day.map(|m| m.saturating_sub(turns));
+            if budget.exhausted.is_none() && (left_tokens == Some(0) || left_turns == Some(0)) {
+                budget.exhausted = Some(scope);
+            }
+            budget.max_tokens = min(budget.max_tokens, left_tokens);
+            budget.max_turns = min(budget.max_turns, left_turns);
+        }
+        Ok(budget)
+    }
+}
+
+fn min(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        _ => a.or(b),
+    }
+}
+
 impl BudgetTracker {
     pub fn new(
         max_tokens_per_day: Option<u64>,
@@ -21,17 +103,38 @@ impl BudgetTracker {
         Self {
             max_tokens_per_day,
             max_turns_per_session,
+            max_cost_per_day: None,
             tokens_today: Arc::new(AtomicU64::new(0)),
+            cost_today: Arc::default(),
             turns_this_session: Arc::new(AtomicU64::new(0)),
+            message: Arc::default(),
             db,
         }
     }
 
-    /// Load today's token usage from the audit table.
+    /// Stop the agent once it has spent `max` estimated dollars today.
+    pub fn with_max_cost_per_day(mut self, max: Option<f64>) -> Self {
+        self.max_cost_per_day = max;
+        self
+    }
+
+    /// A tracker with the same limits counting toward the same daily tokens,
+    /// with its own turn count and message allowance.
+    pub fn sharing_tokens(&self) -> Self {
+        Self {
+            turns_this_session: Arc::new(AtomicU64::new(0)),
+            message: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Load today's token usage and spend from the audit table.
     pub async fn load_from_db(&self) -> Result<(), crate::db::DbError> {
         let usage = self.db.audit_token_usage_today().await?;
         self.tokens_today.store(usage, Ordering::Relaxed);
-        tracing::info!("Loaded today's token usage: {}", usage);
+        let cost = self.db.audit_cost_today().await?;
+        *self.cost_today.lock().unwrap() = cost;
+        tracing::info!("Loaded today's token usage: {} (${:.4})", usage, cost);
         Ok(())
     }
 
@@ -39,24 +142,62 @@ impl BudgetTracker {
     pub fn record_usage(&self, input: u64, output: u64) -> bool {
         let total = input + output;
         let prev = self.tokens_today.fetch_add(total, Ordering::Relaxed);
+        let mut message = self.message.lock().unwrap();
+        message.tokens += total;
         if let Some(max) = self.max_tokens_per_day {
             if prev + total > max {
                 tracing::warn!("Token budget exceeded: {} + {} > {}", prev, total, max);
                 return false;
             }
         }
+        if let Some(max) = message.max_tokens {
+            if message.tokens > max {
+                tracing::warn!(
+                    "Message token allowance exceeded: {} > {}",
+                    message.tokens,
+                    max
+                );
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Record the estimated cost of a model call. Returns true if within
+    /// budget.
+    pub fn record_cost(&self, usd: f64) -> bool {
+        let mut cost = self.cost_today.lock().unwrap();
+        *cost += usd;
+        if let Some(max) = self.max_cost_per_day {
+            if *cost > max {
+                tracing::warn!("Cost budget exceeded: ${:.4} > ${:.2}", *cost, max);
+                return false;
+            }
+        }
         true
     }
 
     /// Record a turn. Returns true if within budget.
     pub fn record_turn(&self) -> bool {
         let prev = self.turns_this_session.fetch_add(1, Ordering::Relaxed);
+        let mut message = self.message.lock().unwrap();
+        message.turns += 1;
         if let Some(max) = self.max_turns_per_session {
             if prev + 1 > max as u64 {
                 tracing::warn!("Turn limit exceeded: {} > {}", prev + 1, max);
                 return false;
             }
         }
+        if let Some(max) = message.max_turns {
+            if message.turns > max {
+                tracing::warn!(
+                    "Message turn allowance exceeded: {} > {}",
+                    message.turns,
+                    max
+                );
+                return false;
+            }
+        }
         true
     }
 
@@ -72,9 +213,39 @@ impl BudgetTracker {
                 return false;
             }
         }
+        if let Some(max) = self.max_cost_per_day {
+            if *self.cost_today.lock().unwrap() >= max {
+                return false;
+            }
+        }
+        let message = self.message.lock().unwrap();
+        if message.max_tokens.is_some_and(|max| message.tokens >= max)
+            || message.max_turns.is_some_and(|max| message.turns >= max)
+        {
+            return false;
+        }
         true
     }
 
+    /// Limit the next message to `budget`, and charge its usage to its
+    /// sender. [`end_message`](Self::end_message) lifts it.
+    pub fn start_message(&self, budget: MessageBudget) {
+        *self.message.lock().unwrap() = MessageBudget {
+            tokens: 0,
+            turns: 0,
+            ..budget
+        };
+    }
+
+    pub fn end_message(&self) {
+        *self.message.lock().unwrap() = MessageBudget::default();
+    }
+
+    /// Who the current message's usage is charged to.
+    pub fn sender(&self) -> Option<String> {
+        self.message.lock().unwrap().sender.clone()
+    }
+
     /// Reset turn counter (for new sessions).
     pub fn reset_turns(&self) {
         self.turns_this_session.store(0, Ordering::Relaxed);
@@ -85,22 +256,62 @@ impl BudgetTracker {
         self.tokens_today.load(Ordering::Relaxed)
     }
 
+    /// Get estimated dollars spent today.
+    pub fn cost_today(&self) -> f64 {
+        *self.cost_today.lock().unwrap()
+    }
+
     /// Get current turn count.
     pub fn turns_used(&self) -> u64 {
         self.turns_this_session.load(Ordering::Relaxed)
     }
 
     /// Update budget limits at runtime (for hot-reload).
-    pub fn update_limits(&mut self, max_tokens: Option<u64>, max_turns: Option<usize>) {
+    pub fn update_limits(
+        &mut self,
+        max_tokens: Option<u64>,
+        max_turns: Option<usize>,
+        max_cost: Option<f64>,
+    ) {
         self.max_tokens_per_day = max_tokens;
         self.max_turns_per_session = max_turns;
+        self.max_cost_per_day = max_cost;
+    }
+}
+
+/// The notice sent once a day when a session or sender has used up its
+/// `[agent.budget.per_session]`/`[agent.budget.per_sender]` allowance.
+pub fn scope_limit_notice(scope: Scope) -> &'static str {
+    match scope {
+        Scope::Session => {
+            "This chat has reached its usage limit for today. I'll answer again tomorrow (UTC)."
+        }
+        Scope::Sender => {
+            "You've reached your usage limit for today. I'll answer again tomorrow (UTC)."
+        }
     }
 }
 
+/// The notice sent to a group when `name` has used up their daily tokens.
+pub fn sender_limit_notice(name: &str, max_tokens: u64) -> String {
+    format!(
+        "{} has used their {} tokens for today. I'll answer their messages again tomorrow (UTC).",
+        name, max_tokens
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sender_limit_notice() {
+        assert_eq!(
+            sender_limit_notice("Ana", 50000),
+            "Ana has used their 50000 tokens for today. I'll answer their messages again tomorrow (UTC)."
+        );
+    }
+
     #[tokio::test]
     async fn test_budget_within_limits() {
         let db = Db::open_memory().unwrap();
@@ -132,6 +343,21 @@ mod tests {
         assert!(!tracker.record_turn()); // 3 > 2
     }
 
+    #[tokio::test]
+    async fn test_cost_budget() {
+        let db = Db::open_memory().unwrap();
+        db.audit_usage_sync("s1", None, "in=1000 out=100", 1100, Some(0.75))
+            .unwrap();
+        let tracker = BudgetTracker::new(None, None, db).with_max_cost_per_day(Some(1.0));
+        tracker.load_from_db().await.unwrap();
+
+        assert_eq!(tracker.cost_today(), 0.75);
+        assert!(tracker.can_continue());
+        assert!(tracker.record_cost(0.125));
+        assert!(!tracker.record_cost(0.25));
+        assert!(!tracker.can_continue());
+    }
+
     #[tokio::test]
     async fn test_no_limits() {
         let db = Db::open_memory().unwrap();
@@ -153,4 +379,76 @@ mod tests {
         tracker.reset_turns();
         assert!(tracker.can_continue());
     }
+
+    #[tokio::test]
+    async fn test_message_budget() {
+        let db = Db::open_memory().unwrap();
+        let config: BudgetConfig = toml::from_str(
+            r#"
+[per_session]
+max_tokens_per_day = 1000
+
+[per_sender]
+max_tokens_per_day = 300
+max_turns_per_day = 3
+"#,
+        )
+        .unwrap();
+        db.audit_usage_sync("tg-group-1", Some("telegram:42"), "", 200, None)
+            .unwrap();
+        db.audit_usage_sync("tg-group-1", Some("telegram:7"), "", 700, None)
+            .unwrap();
+
+        let budget = MessageBudget::load(&db, &config, "tg-group-1", "telegram:42")
+            .await
+            .unwrap();
+        assert_eq!(budget.sender.as_deref(), Some("telegram:42"));
+        assert_eq!(budget.max_tokens, Some(100));
+        assert_eq!(budget.max_turns, Some(2));
+        assert_eq!(budget.exhausted, None);
+
+        // The tracker stops the message once its allowance is used
+        let tracker = BudgetTracker::new(None, None, db.clone());
+        tracker.start_message(budget);
+        assert!(tracker.can_continue());
+        assert!(tracker.record_usage(50, 30));
+        assert!(!tracker.record_usage(10, 20));
+        assert!(!tracker.can_continue());
+        tracker.end_message();
+        assert!(tracker.can_continue());
+        assert_eq!(tracker.sender(), None);
+
+        db.audit_usage_sync("tg-group-1", Some("telegram:7"), "", 100, None)
+            .unwrap();
+        let budget = MessageBudget::load(&db, &config, "tg-group-1", "telegram:42")
+            .await
+            .unwrap();
+        assert_eq!(budget.exhausted, Some(Scope::Session));
+        let budget = MessageBudget::load(&db, &config, "tg-9", "telegram:9")
+            .await
+            .unwrap();
+        assert_eq!(budget.max_tokens, Some(300));
+        assert_eq!(budget.exhausted, None);
+
+        let unlimited = MessageBudget::load(&db, &BudgetConfig::default(), "tg-9", "telegram:9")
+            .await
+            .unwrap();
+        assert_eq!(unlimited.max_tokens, None);
+        assert_eq!(unlimited.max_turns, None);
+    }
+
+    #[tokio::test]
+    async fn test_sharing_tokens() {
+        let db = Db::open_memory().unwrap();
+        let tracker = BudgetTracker::new(Some(100), Some(1), db);
+        let other = tracker.sharing_tokens();
+
+        tracker.record_turn();
+        assert!(other.record_usage(60, 30));
+        assert_eq!(tracker.tokens_used_today(), 90);
+        assert!(!tracker.can_continue());
+        assert!(other.can_continue());
+        assert!(!tracker.record_usage(10, 10));
+        assert!(!other.can_continue());
+    }
 }
@@ -1,4 +1,5 @@
 use super::heuristics::HeuristicScorer;
+use crate::config::HeuristicsConfig;
 use yoagent::types::{FilterResult, InputFilter};
 
 /// Built-in patterns that indicate prompt injection attempts.
@@ -56,6 +57,7 @@ pub struct InjectionDetector {
     /// Messages scoring between llm_judge_threshold and heuristic_threshold get
     /// a `FilterResult::Warn` with a special marker for the conductor to intercept.
     llm_judge_threshold: Option<f64>,
+    scorer: HeuristicScorer,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -106,9 +108,16 @@ impl InjectionDetector {
             patterns,
             heuristic_threshold,
             llm_judge_threshold,
+            scorer: HeuristicScorer::default(),
         }
     }
 
+    /// Use the weights, thresholds and allowlist of `[security.injection.heuristics]`.
+    pub fn with_heuristics(mut self, config: &HeuristicsConfig) -> Self {
+        self.scorer = HeuristicScorer::new(config);
+        self
+    }
+
     /// Check if the input text matches any injection patterns (Layer 1 only).
     /// Returns the matched pattern or None.
     pub fn analyze_patterns(&self, text: &str) -> Option<String> {
@@ -124,7 +133,7 @@ impl InjectionDetector {
     /// Full analysis: patterns (L1) + heuristics (L2) + LLM judge flag (L3 marker).
     pub fn full_analysis(&self, text: &str) -> InjectionAnalysis {
         let pattern_match = self.analyze_patterns(text);
-        let heuristic = HeuristicScorer::analyze(text);
+        let heuristic = self.scorer.score(text);
         let signals: Vec<String> = heuristic
             .signals
             .iter()
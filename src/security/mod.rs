@@ -1,23 +1,119 @@
+pub mod approval;
 pub mod budget;
 pub mod heuristics;
 pub mod injection;
 pub mod llm_judge;
+pub mod sandbox;
+pub mod shell_env;
 
 use crate::config::SecurityConfig;
 use crate::db::Db;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Powerful tools (config names) that stay disabled until granted under
+/// `[security.tools.<name>]`, unless `[security] permissive = true`.
+pub const GRANT_REQUIRED: [&str; 3] = ["shell", "write_file", "http"];
 
 #[derive(Debug, thiserror::Error)]
 pub enum SecurityDenied {
     #[error("Tool '{tool}' is disabled")]
     ToolDisabled { tool: String },
+    #[error(
+        "Tool '{tool}' has not been granted; it needs [security.tools.{grant}] enabled = true"
+    )]
+    NotGranted { tool: String, grant: String },
     #[error("Command blocked by deny pattern: {pattern}")]
     CommandBlocked { pattern: String },
     #[error("Path '{path}' not in allowed paths for tool '{tool}'")]
-    PathNotAllowed { tool: String, path: String },
+    PathNotAllowed {
+        tool: String,
+        path: String,
+        allowed: Vec<String>,
+    },
     #[error("Host '{host}' not in allowed hosts for tool '{tool}'")]
-    HostNotAllowed { tool: String, host: String },
+    HostNotAllowed {
+        tool: String,
+        host: String,
+        allowed: Vec<String>,
+    },
+    #[error("Tool '{tool}' has used its {limit} calls for today")]
+    QuotaExceeded { tool: String, limit: u64 },
+}
+
+impl SecurityDenied {
+    /// Machine-readable reason, e.g. "path_not_allowed".
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::ToolDisabled { .. } => "tool_disabled",
+            Self::NotGranted { .. } => "tool_not_granted",
+            Self::CommandBlocked { .. } => "command_blocked",
+            Self::PathNotAllowed { .. } => "path_not_allowed",
+            Self::HostNotAllowed { .. } => "host_not_allowed",
+            Self::QuotaExceeded { .. } => "quota_exceeded",
+        }
+    }
+
+    /// Structured denial returned to the agent, with the allowed
+    /// alternatives where the policy has any.
+    pub fn to_json(&self, tool: &str) -> serde_json::Value {
+        let mut value = serde_json::json!({
+            "error": "security_denied",
+            "reason": self.reason(),
+            "tool": tool,
+            "message": self.to_string(),
+            "retryable": false,
+        });
+        match self {
+            Self::ToolDisabled { .. } | Self::NotGranted { .. } => {}
+            Self::CommandBlocked { pattern } => {
+                value["blocked_pattern"] = serde_json::json!(pattern);
+            }
+            Self::PathNotAllowed { path, allowed, .. } => {
+                value["path"] = serde_json::json!(path);
+                value["allowed_paths"] = serde_json::json!(allowed);
+            }
+            Self::HostNotAllowed { host, allowed, .. } => {
+                value["url"] = serde_json::json!(host);
+                value["allowed_hosts"] = serde_json::json!(allowed);
+            }
+            Self::QuotaExceeded { limit, .. } => {
+                value["max_calls_per_day"] = serde_json::json!(limit);
+            }
+        }
+        value
+    }
+}
+
+/// Denials already explained to the agent while processing the current
+/// message, as "tool:reason". The conductor clears it for every message.
+pub type DenialHints = Arc<Mutex<HashSet<String>>>;
+
+const DENIAL_HINT: &str = "[SYSTEM HINT] This call was blocked by the operator's security policy, \
+                           not by a transient error, so retrying it will be denied again. Use one \
+                           of the allowed alternatives above if one fits the task; otherwise tell \
+                           the user what was blocked and continue without it.";
+const REPEATED_DENIAL_HINT: &str =
+    "[SYSTEM HINT] Denied again for the same reason. Do not retry this call.";
+
+/// Error text for a denied call: the structured denial, followed by the
+/// hint the first time this tool is denied for this reason.
+pub fn denial_message(denied: &SecurityDenied, tool: &str, hints: &DenialHints) -> String {
+    let first = hints
+        .lock()
+        .unwrap()
+        .insert(format!("{}:{}", tool, denied.reason()));
+    format!(
+        "Security policy: {}\n{}\n\n{}",
+        denied,
+        denied.to_json(tool),
+        if first {
+            DENIAL_HINT
+        } else {
+            REPEATED_DENIAL_HINT
+        }
+    )
 }
 
 /// Security policy derived from config.
@@ -25,6 +121,11 @@ pub enum SecurityDenied {
 pub struct SecurityPolicy {
     pub shell_deny_patterns: Vec<String>,
     pub tool_permissions: HashMap<String, ToolPerm>,
+    /// Allow tools without an entry, including those in `GRANT_REQUIRED`.
+    pub permissive: bool,
+    /// Directories yoclaw itself puts files in for the agent to read, such as
+    /// saved oversized messages. Readable despite `allowed_paths`.
+    pub readable_dirs: Vec<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +134,20 @@ pub struct ToolPerm {
     pub allowed_paths: Vec<String>,
     pub allowed_hosts: Vec<String>,
     pub requires_approval: bool,
+    pub max_calls_per_day: Option<u64>,
+}
+
+/// Security config name of a yoagent tool, e.g. "bash" → "shell".
+fn config_name(tool_name: &str) -> &str {
+    match tool_name {
+        "bash" => "shell",
+        "read_file" => "read_file",
+        "write_file" => "write_file",
+        "edit_file" => "write_file", // edit shares write_file permissions
+        "list_files" => "read_file",
+        "search" => "read_file",
+        _ => tool_name,
+    }
 }
 
 impl SecurityPolicy {
@@ -48,6 +163,7 @@ impl SecurityPolicy {
                         allowed_paths: perm.allowed_paths.clone(),
                         allowed_hosts: perm.allowed_hosts.clone(),
                         requires_approval: perm.requires_approval,
+                        max_calls_per_day: perm.max_calls_per_day,
                     },
                 )
             })
@@ -55,6 +171,34 @@ impl SecurityPolicy {
         Self {
             shell_deny_patterns: config.shell_deny_patterns.clone(),
             tool_permissions,
+            permissive: config.permissive,
+            readable_dirs: Vec::new(),
+        }
+    }
+
+    /// Let the file-reading tools read `dir` whatever their `allowed_paths`.
+    pub fn with_readable_dir(mut self, dir: PathBuf) -> Self {
+        self.readable_dirs.push(dir);
+        self
+    }
+
+    /// Tools in `GRANT_REQUIRED` that the config neither grants nor
+    /// explicitly disables. Empty in permissive mode.
+    pub fn ungranted(config: &SecurityConfig) -> Vec<&'static str> {
+        if config.permissive {
+            return Vec::new();
+        }
+        GRANT_REQUIRED
+            .into_iter()
+            .filter(|name| !config.tools.contains_key(*name))
+            .collect()
+    }
+
+    /// Whether a tool (by config name) is available at all.
+    pub fn tool_enabled(&self, config_name: &str) -> bool {
+        match self.tool_permissions.get(config_name) {
+            Some(perm) => perm.enabled,
+            None => self.permissive || !GRANT_REQUIRED.contains(&config_name),
         }
     }
 
@@ -65,77 +209,105 @@ impl SecurityPolicy {
         args: &serde_json::Value,
     ) -> Result<(), SecurityDenied> {
         // Map yoagent tool names to our security config names
-        let config_name = match tool_name {
-            "bash" => "shell",
-            "read_file" => "read_file",
-            "write_file" => "write_file",
-            "edit_file" => "write_file", // edit shares write_file permissions
-            "list_files" => "read_file",
-            "search" => "read_file",
-            _ => tool_name,
-        };
+        let config_name = config_name(tool_name);
 
-        if let Some(perm) = self.tool_permissions.get(config_name) {
-            if !perm.enabled {
-                return Err(SecurityDenied::ToolDisabled {
+        let Some(perm) = self.tool_permissions.get(config_name) else {
+            if !self.permissive && GRANT_REQUIRED.contains(&config_name) {
+                return Err(SecurityDenied::NotGranted {
                     tool: tool_name.to_string(),
+                    grant: config_name.to_string(),
                 });
             }
+            return Ok(());
+        };
+
+        if !perm.enabled {
+            return Err(SecurityDenied::ToolDisabled {
+                tool: tool_name.to_string(),
+            });
+        }
 
-            // Check shell deny patterns for bash tool
-            if tool_name == "bash" {
-                if let Some(command) = args.get("command").and_then(|v| v.as_str()) {
-                    for pattern in &self.shell_deny_patterns {
-                        if command.contains(pattern) {
-                            return Err(SecurityDenied::CommandBlocked {
-                                pattern: pattern.clone(),
-                            });
-                        }
+        // Check shell deny patterns for bash tool
+        if tool_name == "bash" {
+            if let Some(command) = args.get("command").and_then(|v| v.as_str()) {
+                for pattern in &self.shell_deny_patterns {
+                    if command.contains(pattern) {
+                        return Err(SecurityDenied::CommandBlocked {
+                            pattern: pattern.clone(),
+                        });
                     }
                 }
             }
+        }
 
-            // Check path allowlists for file tools
-            if matches!(
-                tool_name,
-                "read_file" | "write_file" | "edit_file" | "list_files" | "search"
-            ) && !perm.allowed_paths.is_empty()
-            {
-                let file_path = args
-                    .get("file_path")
-                    .or_else(|| args.get("path"))
-                    .and_then(|v| v.as_str());
-                if let Some(path) = file_path {
-                    let path_expanded = crate::config::expand_tilde(path);
-                    let allowed = perm.allowed_paths.iter().any(|allowed| {
-                        let allowed_expanded = crate::config::expand_tilde(allowed);
-                        path_expanded.starts_with(&allowed_expanded)
+        // Check path allowlists for file tools
+        if matches!(
+            tool_name,
+            "read_file" | "write_file" | "edit_file" | "list_files" | "search"
+        ) && !perm.allowed_paths.is_empty()
+        {
+            let file_path = args
+                .get("file_path")
+                .or_else(|| args.get("path"))
+                .and_then(|v| v.as_str());
+            if let Some(path) = file_path {
+                let path_expanded = crate::config::expand_tilde(path);
+                let allowed = perm.allowed_paths.iter().any(|allowed| {
+                    let allowed_expanded = crate::config::expand_tilde(allowed);
+                    path_expanded.starts_with(&allowed_expanded)
+                }) || (!matches!(tool_name, "write_file" | "edit_file")
+                    && self.is_readable(&path_expanded));
+                if !allowed {
+                    return Err(SecurityDenied::PathNotAllowed {
+                        tool: tool_name.to_string(),
+                        path: path.to_string(),
+                        allowed: perm.allowed_paths.clone(),
                     });
-                    if !allowed {
-                        return Err(SecurityDenied::PathNotAllowed {
-                            tool: tool_name.to_string(),
-                            path: path.to_string(),
-                        });
-                    }
                 }
             }
+        }
 
-            // Check host allowlists for http tool
-            if tool_name == "http" && !perm.allowed_hosts.is_empty() {
-                if let Some(url) = args.get("url").and_then(|v| v.as_str()) {
-                    let allowed = perm.allowed_hosts.iter().any(|host| url.contains(host));
-                    if !allowed {
-                        return Err(SecurityDenied::HostNotAllowed {
-                            tool: tool_name.to_string(),
-                            host: url.to_string(),
-                        });
-                    }
+        // Check host allowlists for http tool
+        if tool_name == "http" && !perm.allowed_hosts.is_empty() {
+            if let Some(url) = args.get("url").and_then(|v| v.as_str()) {
+                let allowed = perm.allowed_hosts.iter().any(|host| url.contains(host));
+                if !allowed {
+                    return Err(SecurityDenied::HostNotAllowed {
+                        tool: tool_name.to_string(),
+                        host: url.to_string(),
+                        allowed: perm.allowed_hosts.clone(),
+                    });
                 }
             }
         }
 
         Ok(())
     }
+
+    /// Whether `path` is inside one of `readable_dirs`, without `..` to climb
+    /// out of it.
+    fn is_readable(&self, path: &Path) -> bool {
+        !path
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+            && self.readable_dirs.iter().any(|dir| path.starts_with(dir))
+    }
+
+    /// Whether calls of a tool wait for the user's approval.
+    pub fn requires_approval(&self, tool_name: &str) -> bool {
+        self.tool_permissions
+            .get(config_name(tool_name))
+            .is_some_and(|perm| perm.requires_approval)
+    }
+
+    /// Config name and daily call limit of a tool, if it has one. Tools
+    /// sharing a config name share the quota.
+    pub fn quota(&self, tool_name: &str) -> Option<(&str, u64)> {
+        let (config_name, perm) = self
+            .tool_permissions
+            .get_key_value(config_name(tool_name))?;
+        Some((config_name.as_str(), perm.max_calls_per_day?))
+    }
 }
 
 /// Wraps an AgentTool with security policy checks.
@@ -144,6 +316,8 @@ pub struct SecureToolWrapper {
     pub policy: Arc<std::sync::RwLock<SecurityPolicy>>,
     pub db: Db,
     pub session_id: Arc<std::sync::RwLock<String>>,
+    pub hints: DenialHints,
+    pub approvals: approval::Approvals,
 }
 
 #[async_trait::async_trait]
@@ -170,9 +344,61 @@ impl yoagent::AgentTool for SecureToolWrapper {
         ctx: yoagent::types::ToolContext,
     ) -> Result<yoagent::ToolResult, yoagent::ToolError> {
         // Check security policy (scoped to drop read guard before await)
-        let denied = {
+        let (denied, quota, requires_approval) = {
             let policy = self.policy.read().unwrap();
-            policy.check_tool_call(self.inner.name(), &params).err()
+            let quota = policy
+                .quota(self.inner.name())
+                .map(|(name, limit)| (name.to_string(), limit));
+            (
+                policy.check_tool_call(self.inner.name(), &params).err(),
+                quota,
+                policy.requires_approval(self.inner.name()),
+            )
+        };
+        // Ask before counting against the quota, so denied calls don't use it up
+        if denied.is_none() && requires_approval {
+            let session = self.session_id.read().unwrap().clone();
+            let outcome = self
+                .approvals
+                .request(&session, self.inner.name(), &params, &ctx)
+                .await;
+            let event = if outcome == approval::Outcome::Approved {
+                "approval_granted"
+            } else {
+                "approval_denied"
+            };
+            let _ = self
+                .db
+                .audit_log(
+                    Some(&session),
+                    event,
+                    Some(self.inner.name()),
+                    Some(outcome.describe()),
+                    0,
+                )
+                .await;
+            if outcome != approval::Outcome::Approved {
+                return Err(yoagent::ToolError::Failed(format!(
+                    "Tool call {}. Do not retry it unless the user asks; tell them it was not run.",
+                    outcome.describe()
+                )));
+            }
+        }
+        // Allowed calls count against the daily quota; a database error
+        // doesn't block the call
+        let denied = match (denied, quota) {
+            (None, Some((name, limit))) => match self.db.tool_quota_take(&name, limit).await {
+                Ok(true) => None,
+                Ok(false) => Some(SecurityDenied::QuotaExceeded {
+                    tool: self.inner.name().to_string(),
+                    limit,
+                }),
+                Err(e) => {
+                    tracing::warn!("Failed to count call against quota of '{}': {}", name, e);
+                    None
+                }
+            },
+            (denied, _) => denied,
         };
         if let Some(denied) = denied {
             let session = self.session_id.read().unwrap().clone();
@@ -186,9 +412,10 @@ impl yoagent::AgentTool for SecureToolWrapper {
                     0,
                 )
                 .await;
-            return Err(yoagent::ToolError::Failed(format!(
-                "Security policy: {}",
-                denied
+            return Err(yoagent::ToolError::Failed(denial_message(
+                &denied,
+                self.inner.name(),
+                &self.hints,
             )));
         }
 
@@ -217,6 +444,8 @@ pub fn wrap_tools(
     policy: Arc<std::sync::RwLock<SecurityPolicy>>,
     db: Db,
     session_id: Arc<std::sync::RwLock<String>>,
+    hints: DenialHints,
+    approvals: approval::Approvals,
 ) -> Vec<Box<dyn yoagent::AgentTool>> {
     tools
         .into_iter()
@@ -226,6 +455,8 @@ pub fn wrap_tools(
                 policy: policy.clone(),
                 db: db.clone(),
                 session_id: session_id.clone(),
+                hints: hints.clone(),
+                approvals: approvals.clone(),
             }) as Box<dyn yoagent::AgentTool>
         })
         .collect()
@@ -235,6 +466,7 @@ pub fn wrap_tools(
 mod tests {
     use super::*;
     use serde_json::json;
+    use yoagent::AgentTool;
 
     fn test_policy() -> SecurityPolicy {
         SecurityPolicy {
@@ -247,6 +479,7 @@ mod tests {
                         allowed_paths: vec![],
                         allowed_hosts: vec![],
                         requires_approval: false,
+                        max_calls_per_day: None,
                     },
                 ),
                 (
@@ -256,6 +489,7 @@ mod tests {
                         allowed_paths: vec!["/tmp/".to_string()],
                         allowed_hosts: vec![],
                         requires_approval: false,
+                        max_calls_per_day: None,
                     },
                 ),
                 (
@@ -265,9 +499,12 @@ mod tests {
                         allowed_paths: vec![],
                         allowed_hosts: vec![],
                         requires_approval: false,
+                        max_calls_per_day: None,
                     },
                 ),
             ]),
+            permissive: false,
+            readable_dirs: vec![],
         }
     }
 
@@ -313,6 +550,90 @@ mod tests {
         assert!(matches!(result, Err(SecurityDenied::PathNotAllowed { .. })));
     }
 
+    #[test]
+    fn test_readable_dir() {
+        let policy = test_policy().with_readable_dir(PathBuf::from("/var/yoclaw/uploads"));
+        let read = |path: &str| policy.check_tool_call("read_file", &json!({"file_path": path}));
+        assert!(read("/var/yoclaw/uploads/tg-1-1700000000000.txt").is_ok());
+        assert!(read("/var/yoclaw/uploads/../yoclaw.db").is_err());
+        assert!(read("/var/yoclaw/yoclaw.db").is_err());
+        assert!(policy
+            .check_tool_call("search", &json!({"path": "/var/yoclaw/uploads"}))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_structured_denial_and_one_time_hint() {
+        let policy = test_policy();
+        let denied = policy
+            .check_tool_call("read_file", &json!({"file_path": "/etc/passwd"}))
+            .unwrap_err();
+        let value = denied.to_json("read_file");
+        assert_eq!(value["reason"], "path_not_allowed");
+        assert_eq!(value["allowed_paths"], json!(["/tmp/"]));
+        assert_eq!(value["retryable"], false);
+
+        let hints = DenialHints::default();
+        let first = denial_message(&denied, "read_file", &hints);
+        assert!(first.starts_with("Security policy: Path '/etc/passwd'"));
+        assert!(first.ends_with(DENIAL_HINT));
+        let again = denial_message(&denied, "read_file", &hints);
+        assert!(again.ends_with(REPEATED_DENIAL_HINT));
+
+        // A different reason is explained once as well
+        let blocked = policy
+            .check_tool_call("bash", &json!({"command": "sudo ls"}))
+            .unwrap_err();
+        assert!(denial_message(&blocked, "bash", &hints).ends_with(DENIAL_HINT));
+    }
+
+    #[tokio::test]
+    async fn test_daily_quota() {
+        let mut policy = test_policy();
+        policy
+            .tool_permissions
+            .get_mut("read_file")
+            .unwrap()
+            .max_calls_per_day = Some(1);
+        assert_eq!(policy.quota("list_files"), Some(("read_file", 1)));
+        assert_eq!(policy.quota("bash"), None);
+        policy.tool_permissions.insert(
+            "memory_search".to_string(),
+            ToolPerm {
+                enabled: true,
+                allowed_paths: vec![],
+                allowed_hosts: vec![],
+                requires_approval: false,
+                max_calls_per_day: Some(1),
+            },
+        );
+
+        let db = Db::open_memory().unwrap();
+        let wrapper = SecureToolWrapper {
+            inner: Box::new(crate::conductor::tools::MemorySearchTool::new(db.clone())),
+            policy: Arc::new(std::sync::RwLock::new(policy)),
+            db: db.clone(),
+            session_id: Arc::default(),
+            hints: DenialHints::default(),
+            approvals: Default::default(),
+        };
+        let ctx = || yoagent::types::ToolContext {
+            tool_call_id: "1".into(),
+            tool_name: "memory_search".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        };
+
+        let params = json!({"query": "coffee"});
+        assert!(wrapper.execute(params.clone(), ctx()).await.is_ok());
+        let Err(yoagent::ToolError::Failed(message)) = wrapper.execute(params, ctx()).await else {
+            panic!("expected the quota to deny the call");
+        };
+        assert!(message.contains("\"reason\":\"quota_exceeded\""));
+        assert_eq!(db.tool_calls_today("memory_search").await.unwrap(), 1);
+    }
+
     #[test]
     fn test_unknown_tool_allowed() {
         let policy = test_policy();
@@ -320,4 +641,36 @@ mod tests {
         let result = policy.check_tool_call("memory_search", &json!({"query": "test"}));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_powerful_tools_need_a_grant() {
+        let policy = SecurityPolicy::from_config(&SecurityConfig::default());
+        let result = policy.check_tool_call("http", &json!({"url": "https://example.com"}));
+        assert!(matches!(result, Err(SecurityDenied::NotGranted { .. })));
+        let denied = policy
+            .check_tool_call("edit_file", &json!({"file_path": "/tmp/a"}))
+            .unwrap_err();
+        assert_eq!(denied.reason(), "tool_not_granted");
+        assert!(denied.to_string().contains("[security.tools.write_file]"));
+        assert!(!policy.tool_enabled("shell"));
+        assert!(policy.tool_enabled("read_file"));
+        assert_eq!(
+            SecurityPolicy::ungranted(&SecurityConfig::default()),
+            vec!["shell", "write_file", "http"]
+        );
+
+        // Granted in the test policy
+        assert!(test_policy()
+            .check_tool_call("bash", &json!({"command": "ls"}))
+            .is_ok());
+
+        let permissive = SecurityConfig {
+            permissive: true,
+            ..Default::default()
+        };
+        assert!(SecurityPolicy::from_config(&permissive)
+            .check_tool_call("bash", &json!({"command": "ls"}))
+            .is_ok());
+        assert!(SecurityPolicy::ungranted(&permissive).is_empty());
+    }
 }
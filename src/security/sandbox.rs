@@ -0,0 +1,407 @@
+//! Containerized execution profiles.
+//!
+//! `[profiles.<name>]` describes a container: image, network access, mounts
+//! and resource limits. Shell commands that resolve to a profile run in a
+//! fresh container per call (`docker run --rm ...`) instead of on the host.
+//! A command resolves to a profile when:
+//!
+//! - it comes from a skill whose manifest declares `profile:`, i.e. the
+//!   conductor's [`ActiveSkill`] is that skill (the agent read its file),
+//! - `[security.tools.shell] profile` is set, or
+//! - it comes from a worker with `profile` set, which gets a `bash` tool that
+//!   only runs in that profile.
+//!
+//! `ProfiledShellTool` keeps the name `bash`, so it is wrapped by
+//! `SecureToolWrapper` like the host shell and deny patterns still apply. A
+//! profile that isn't defined fails the call instead of falling back to the
+//! host.
+
+use crate::conductor::tools::ActiveSkill;
+use crate::config::ProfileConfig;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use yoagent::types::*;
+
+/// Profile definitions by name, shared by all profiled tools.
+pub type Profiles = Arc<HashMap<String, ProfileConfig>>;
+
+/// Arguments for `<runtime> run` that execute `command` under `profile`.
+pub fn run_args(profile: &ProfileConfig, container: &str, command: &str) -> Vec<String> {
+    let mut args: Vec<String> = ["run", "--rm", "--name", container]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if !profile.network {
+        args.extend(["--network".into(), "none".into()]);
+    }
+    args.extend([
+        "--cap-drop".into(),
+        "ALL".into(),
+        "--security-opt".into(),
+        "no-new-privileges".into(),
+        "--pids-limit".into(),
+        profile.pids_limit.to_string(),
+    ]);
+    if let Some(ref memory) = profile.memory {
+        args.extend(["--memory".into(), memory.clone()]);
+    }
+    if let Some(cpus) = profile.cpus {
+        args.extend(["--cpus".into(), cpus.to_string()]);
+    }
+    if profile.read_only {
+        args.push("--read-only".into());
+    }
+    for mount in &profile.mounts {
+        // Expand ~ on the host side only
+        let mount = match mount.split_once(':') {
+            Some((host, rest)) => format!(
+                "{}:{}",
+                crate::config::expand_tilde(host).to_string_lossy(),
+                rest
+            ),
+            None => mount.clone(),
+        };
+        args.extend(["-v".into(), mount]);
+    }
+    if let Some(ref workdir) = profile.workdir {
+        args.extend(["-w".into(), workdir.clone()]);
+    }
+    let mut env: Vec<_> = profile.env.iter().collect();
+    env.sort();
+    for (name, value) in env {
+        args.extend(["-e".into(), format!("{}={}", name, value)]);
+    }
+    args.extend([
+        profile.image.clone(),
+        "sh".into(),
+        "-c".into(),
+        command.to_string(),
+    ]);
+    args
+}
+
+/// Run `command` in a new container for `profile`, killing it after the
+/// profile's timeout.
+pub async fn run(
+    name: &str,
+    profile: &ProfileConfig,
+    command: &str,
+) -> Result<ToolResult, ToolError> {
+    let container = format!("yoclaw-{}-{}", name, uuid::Uuid::new_v4().simple());
+    let child = tokio::process::Command::new(&profile.runtime)
+        .args(run_args(profile, &container, command))
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+
+    let output = match tokio::time::timeout(Duration::from_secs(profile.timeout_secs), child).await
+    {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            return Err(ToolError::Failed(format!(
+                "Failed to start '{}' for profile '{}': {}",
+                profile.runtime, name, e
+            )))
+        }
+        Err(_) => {
+            // Killing the client doesn't stop the container
+            let _ = tokio::process::Command::new(&profile.runtime)
+                .args(["rm", "-f", &container])
+                .output()
+                .await;
+            return Err(ToolError::Failed(format!(
+                "Command timed out after {}s in profile '{}'",
+                profile.timeout_secs, name
+            )));
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut text = stdout.trim_end().to_string();
+    if !stderr.trim().is_empty() {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format!("[stderr]\n{}", stderr.trim_end()));
+    }
+    let code = output.status.code();
+    if code != Some(0) {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        match code {
+            Some(code) => text.push_str(&format!("Exit code: {}", code)),
+            None => text.push_str("Killed by signal"),
+        }
+    }
+
+    Ok(ToolResult {
+        content: vec![Content::Text { text }],
+        details: serde_json::json!({ "profile": name, "exit_code": code }),
+    })
+}
+
+/// `bash` tool that runs commands in an execution profile when one applies,
+/// and on the host (if it has a host shell) otherwise.
+pub struct ProfiledShellTool {
+    host: Option<Box<dyn AgentTool>>,
+    profiles: Profiles,
+    /// Profile for commands without a skill profile.
+    default: Option<String>,
+    /// Skill name → profile name.
+    skills: HashMap<String, String>,
+    /// Skill being followed, whose profile applies.
+    active: ActiveSkill,
+    description: String,
+}
+
+impl ProfiledShellTool {
+    /// Route commands from the host shell `host` through profiles.
+    pub fn new(host: Box<dyn AgentTool>, profiles: Profiles) -> Self {
+        let description = host.description().to_string();
+        Self {
+            host: Some(host),
+            profiles,
+            default: None,
+            skills: HashMap::new(),
+            active: ActiveSkill::default(),
+            description,
+        }
+    }
+
+    /// A shell that only runs in `profile`, for workers.
+    pub fn confined(profile: &str, profiles: Profiles) -> Self {
+        let image = profiles
+            .get(profile)
+            .map(|p| p.image.as_str())
+            .unwrap_or("undefined");
+        let description = format!(
+            "Run a shell command in a fresh '{}' container (profile '{}'). Nothing persists \
+             between calls except mounted directories.",
+            image, profile
+        );
+        Self {
+            host: None,
+            profiles,
+            default: Some(profile.to_string()),
+            skills: HashMap::new(),
+            active: ActiveSkill::default(),
+            description,
+        }
+    }
+
+    /// Run every command without a skill profile in `profile`.
+    pub fn with_default(mut self, profile: Option<String>) -> Self {
+        self.default = profile;
+        self
+    }
+
+    pub fn with_skill_profiles(mut self, skills: HashMap<String, String>) -> Self {
+        self.skills = skills;
+        self
+    }
+
+    /// Run commands in the profile of the skill being followed.
+    pub fn with_active_skill(mut self, active: ActiveSkill) -> Self {
+        self.active = active;
+        self
+    }
+
+    /// Profile a call resolves to, if any: that of the skill being followed,
+    /// else the default. The model can't pick one.
+    fn resolve(&self) -> Option<String> {
+        self.active
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|skill| self.skills.get(skill))
+            .or(self.default.as_ref())
+            .cloned()
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentTool for ProfiledShellTool {
+    fn name(&self) -> &str {
+        "bash"
+    }
+
+    fn label(&self) -> &str {
+        "Shell"
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        match &self.host {
+            Some(host) => host.parameters_schema(),
+            None => serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "Shell command to run"
+                    }
+                },
+                "required": ["command"]
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        params: serde_json::Value,
+        ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let Some(name) = self.resolve() else {
+            let host = self
+                .host
+                .as_ref()
+                .ok_or_else(|| ToolError::Failed("No shell available".into()))?;
+            return host.execute(params, ctx).await;
+        };
+        let profile = self.profiles.get(&name).ok_or_else(|| {
+            ToolError::Failed(format!("Execution profile '{}' is not defined", name))
+        })?;
+        let command = params["command"]
+            .as_str()
+            .ok_or_else(|| ToolError::InvalidArgs("Missing 'command' parameter".into()))?;
+        run(&name, profile, command).await
+    }
+}
+
+/// Replace the host `bash` tool in `tools` with a `ProfiledShellTool` if a
+/// shell profile or any skill profile is configured. Skill profiles apply
+/// while `active` is their skill.
+pub fn apply_profiles(
+    tools: Vec<Box<dyn AgentTool>>,
+    profiles: &Profiles,
+    default: Option<String>,
+    skills: HashMap<String, String>,
+    active: ActiveSkill,
+) -> Vec<Box<dyn AgentTool>> {
+    if default.is_none() && skills.is_empty() {
+        return tools;
+    }
+    for name in default.iter().chain(skills.values()) {
+        if !profiles.contains_key(name) {
+            tracing::warn!(
+                "Execution profile '{}' is not defined; shell commands using it will fail",
+                name
+            );
+        }
+    }
+    tools
+        .into_iter()
+        .map(|tool| {
+            if tool.name() == "bash" {
+                Box::new(
+                    ProfiledShellTool::new(tool, profiles.clone())
+                        .with_default(default.clone())
+                        .with_skill_profiles(skills.clone())
+                        .with_active_skill(active.clone()),
+                ) as Box<dyn AgentTool>
+            } else {
+                tool
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(runtime: &str) -> ProfileConfig {
+        ProfileConfig {
+            image: "python:3.12-slim".into(),
+            runtime: runtime.into(),
+            network: false,
+            mounts: vec!["/srv/data:/data:ro".into()],
+            workdir: Some("/data".into()),
+            memory: Some("512m".into()),
+            cpus: Some(1.0),
+            pids_limit: 64,
+            read_only: true,
+            timeout_secs: 5,
+            env: HashMap::from([("PYTHONUNBUFFERED".to_string(), "1".to_string())]),
+        }
+    }
+
+    fn profiles(runtime: &str) -> Profiles {
+        Arc::new(HashMap::from([("py".to_string(), profile(runtime))]))
+    }
+
+    fn test_ctx() -> ToolContext {
+        ToolContext {
+            tool_call_id: "test".into(),
+            tool_name: "bash".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        }
+    }
+
+    fn text(result: &ToolResult) -> &str {
+        match &result.content[0] {
+            Content::Text { text } => text,
+            _ => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn test_run_args_enforce_limits() {
+        let args = run_args(&profile("docker"), "yoclaw-py-1", "python -V").join(" ");
+        assert!(args.starts_with("run --rm --name yoclaw-py-1 --network none"));
+        assert!(args.contains("--cap-drop ALL"));
+        assert!(args.contains("--pids-limit 64"));
+        assert!(args.contains("--memory 512m"));
+        assert!(args.contains("--cpus 1"));
+        assert!(args.contains("--read-only"));
+        assert!(args.contains("-v /srv/data:/data:ro -w /data -e PYTHONUNBUFFERED=1"));
+        assert!(args.ends_with("python:3.12-slim sh -c python -V"));
+
+        let mut open = profile("docker");
+        open.network = true;
+        assert!(!run_args(&open, "c", "ls").contains(&"none".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_confined_shell_runs_in_profile() {
+        // `echo` as the runtime prints the container arguments instead
+        let tool = ProfiledShellTool::confined("py", profiles("echo"));
+        let result = tool
+            .execute(serde_json::json!({"command": "ls"}), test_ctx())
+            .await
+            .unwrap();
+        assert!(text(&result).starts_with("run --rm --name yoclaw-py-"));
+        assert!(text(&result).ends_with("python:3.12-slim sh -c ls"));
+        assert_eq!(result.details["profile"], "py");
+
+        let tool = ProfiledShellTool::confined("missing", profiles("echo"));
+        let err = tool
+            .execute(serde_json::json!({"command": "ls"}), test_ctx())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("'missing' is not defined"));
+    }
+
+    #[test]
+    fn test_skill_profile_resolution() {
+        let active = ActiveSkill::default();
+        let tool = ProfiledShellTool::confined("py", profiles("docker"))
+            .with_default(None)
+            .with_skill_profiles(HashMap::from([("data".to_string(), "py".to_string())]))
+            .with_active_skill(active.clone());
+        assert_eq!(tool.resolve(), None);
+        *active.write().unwrap() = Some("data".into());
+        assert_eq!(tool.resolve().as_deref(), Some("py"));
+        *active.write().unwrap() = Some("other".into());
+        assert_eq!(tool.resolve(), None);
+        assert!(tool.parameters_schema()["properties"]["skill"].is_null());
+    }
+}
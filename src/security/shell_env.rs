@@ -0,0 +1,193 @@
+//! Environment of host shell commands (`[security.tools.shell.env]`).
+//!
+//! yoagent's `bash` tool runs `bash -c <command>` in the daemon's
+//! environment, which holds bot tokens and API keys. `ScrubbedShellTool`
+//! wraps it and runs each command as `exec env -i <vars> bash -c <command>`
+//! instead, so the command only sees the daemon variables in `inherit` and
+//! those the config sets. Commands in execution profiles never see the
+//! daemon's environment; their variables come from `[profiles.<name>] env`.
+
+use crate::config::ShellEnvConfig;
+use yoagent::types::*;
+
+/// Variables a scrubbed shell command runs with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellEnv {
+    vars: Vec<(String, String)>,
+}
+
+impl ShellEnv {
+    /// The variables of `config`, inherited ones read from the daemon's
+    /// environment now.
+    pub fn from_config(config: &ShellEnvConfig) -> Self {
+        Self::resolve(config, |name| std::env::var(name).ok())
+    }
+
+    fn resolve(config: &ShellEnvConfig, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut vars: Vec<(String, String)> = Vec::new();
+        let inherited = config
+            .inherit
+            .iter()
+            .filter_map(|name| lookup(name).map(|value| (name.clone(), value)));
+        let mut set: Vec<_> = config.vars.clone().into_iter().collect();
+        set.sort();
+        for (name, value) in inherited.chain(set) {
+            if !is_valid_name(&name) {
+                tracing::warn!(
+                    "Ignoring invalid shell environment variable name '{}'",
+                    name
+                );
+                continue;
+            }
+            vars.retain(|(n, _)| *n != name);
+            vars.push((name, value));
+        }
+        Self { vars }
+    }
+
+    /// `command`, run with only these variables.
+    pub fn wrap(&self, command: &str) -> String {
+        let mut wrapped = String::from("exec env -i");
+        for (name, value) in &self.vars {
+            wrapped.push(' ');
+            wrapped.push_str(name);
+            wrapped.push('=');
+            wrapped.push_str(&quote(value));
+        }
+        wrapped.push_str(" bash -c ");
+        wrapped.push_str(&quote(command));
+        wrapped
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `s` as a single-quoted shell word.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Host `bash` tool whose commands run in a `ShellEnv`.
+pub struct ScrubbedShellTool {
+    inner: Box<dyn AgentTool>,
+    env: ShellEnv,
+}
+
+impl ScrubbedShellTool {
+    pub fn new(inner: Box<dyn AgentTool>, env: ShellEnv) -> Self {
+        Self { inner, env }
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentTool for ScrubbedShellTool {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn label(&self) -> &str {
+        self.inner.label()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.inner.parameters_schema()
+    }
+
+    async fn execute(
+        &self,
+        mut params: serde_json::Value,
+        ctx: ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        if let Some(command) = params["command"].as_str() {
+            params["command"] = serde_json::Value::String(self.env.wrap(command));
+        }
+        self.inner.execute(params, ctx).await
+    }
+}
+
+/// Run the host `bash` tool in `tools` with the environment of `config`
+/// (the default one if not configured).
+pub fn apply_env(
+    tools: Vec<Box<dyn AgentTool>>,
+    config: Option<&ShellEnvConfig>,
+) -> Vec<Box<dyn AgentTool>> {
+    let env = ShellEnv::from_config(&config.cloned().unwrap_or_default());
+    tools
+        .into_iter()
+        .map(|tool| {
+            if tool.name() == "bash" {
+                Box::new(ScrubbedShellTool::new(tool, env.clone())) as Box<dyn AgentTool>
+            } else {
+                tool
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_resolve_and_wrap() {
+        let config = ShellEnvConfig {
+            inherit: vec!["PATH".into(), "HOME".into(), "MISSING".into()],
+            vars: HashMap::from([
+                ("HOME".to_string(), "/srv/agent".to_string()),
+                ("GIT_AUTHOR_NAME".to_string(), "O'Brien".to_string()),
+                ("BAD-NAME".to_string(), "x".to_string()),
+            ]),
+        };
+        let env = ShellEnv::resolve(&config, |name| match name {
+            "PATH" => Some("/usr/bin".to_string()),
+            "HOME" => Some("/root".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            env.wrap("echo 'hi'"),
+            r#"exec env -i PATH='/usr/bin' GIT_AUTHOR_NAME='O'\''Brien' HOME='/srv/agent' bash -c 'echo '\''hi'\'''"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scrubbed_shell_hides_daemon_env() {
+        std::env::set_var("YOCLAW_TEST_SHELL_SECRET", "leaked");
+        let config = ShellEnvConfig {
+            vars: HashMap::from([("GREETING".to_string(), "it's set".to_string())]),
+            ..Default::default()
+        };
+        let tools = apply_env(yoagent::tools::default_tools(), Some(&config));
+        let bash = tools.iter().find(|t| t.name() == "bash").unwrap();
+        let ctx = ToolContext {
+            tool_call_id: "test".into(),
+            tool_name: "bash".into(),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            on_update: None,
+            on_progress: None,
+        };
+        let result = bash
+            .execute(
+                serde_json::json!({"command": "echo \"[$GREETING] [$YOCLAW_TEST_SHELL_SECRET]\""}),
+                ctx,
+            )
+            .await
+            .unwrap();
+        let text = match &result.content[0] {
+            Content::Text { text } => text.clone(),
+            _ => String::new(),
+        };
+        assert!(text.contains("[it's set] []"), "{}", text);
+        std::env::remove_var("YOCLAW_TEST_SHELL_SECRET");
+    }
+}
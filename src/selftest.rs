@@ -0,0 +1,465 @@
+//! Startup self-test.
+//!
+//! A bad setup otherwise surfaces at the first message, as a provider's 401,
+//! a channel that never connects or a skill quietly left unfiltered. Before
+//! the daemon starts its channels, [`run`] checks what can be checked
+//! without the network:
+//!
+//! ```text
+//! database   migrations applied, and not from a newer yoclaw
+//! provider   API keys present, without stray whitespace or placeholders
+//! channels   bot and app tokens present and shaped like the platform's
+//! skills     every SKILL.md has a frontmatter manifest
+//! clock      system time plausible, TZ and the stored timezone valid
+//! ```
+//!
+//! Failed checks are reported together and keep the daemon from starting;
+//! warnings are logged.
+
+use crate::config::{AgentConfig, ChannelsConfig, Config};
+use crate::db::{now_ms, Db, DbError};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// 2024-01-01T00:00:00Z. A clock before this is certainly wrong, and breaks
+/// TLS, cron schedules and daily budgets.
+const EARLIEST_PLAUSIBLE_MS: u64 = 1_704_067_200_000;
+
+/// Outcome of one check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Likely a mistake, but the daemon can run.
+    Warn,
+    /// The daemon would fail at runtime; it doesn't start.
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// All checks, in the order they ran.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn failed(&self) -> bool {
+        self.checks.iter().any(|c| c.status == Status::Fail)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|c| c.status == Status::Warn)
+    }
+}
+
+/// The failed checks, one per line, for the startup error.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed: Vec<&Check> = self
+            .checks
+            .iter()
+            .filter(|c| c.status == Status::Fail)
+            .collect();
+        write!(f, "Startup self-test failed ({} problem", failed.len())?;
+        if failed.len() != 1 {
+            f.write_str("s")?;
+        }
+        f.write_str("):")?;
+        for check in failed {
+            write!(f, "\n  - {}: {}", check.name, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every check against `config` and the opened database.
+pub async fn run(config: &Config, db: &Db) -> Result<Report, DbError> {
+    let mut checks = vec![check_schema(
+        db.schema_version().await?,
+        Db::latest_schema_version(),
+    )];
+    checks.extend(check_provider_keys(&config.agent));
+    checks.extend(check_channels(&config.channels));
+    checks.push(check_skills(&config.skills_dirs()));
+    checks.push(check_clock(now_ms()));
+    // The stored answer is only read without [agent.preamble] timezone
+    let stored = match config.agent.preamble.timezone {
+        Some(_) => None,
+        None => db
+            .memory_get(crate::conductor::preamble::TIMEZONE_MEMORY)
+            .await?
+            .map(|m| m.content),
+    };
+    checks.push(check_timezone(
+        std::env::var("TZ").ok().as_deref(),
+        stored.as_deref(),
+    ));
+    Ok(Report { checks })
+}
+
+fn check_schema(version: i64, latest: i64) -> Check {
+    const NAME: &str = "database";
+    if version > latest {
+        Check::fail(
+            NAME,
+            format!(
+                "schema version {} was written by a newer yoclaw (this one knows {}); upgrade \
+                 yoclaw or restore a backup",
+                version, latest
+            ),
+        )
+    } else if version < latest {
+        Check::fail(
+            NAME,
+            format!(
+                "schema version {} of {}: migrations not applied",
+                version, latest
+            ),
+        )
+    } else {
+        Check::ok(NAME, format!("schema version {}", version))
+    }
+}
+
+fn check_provider_keys(agent: &AgentConfig) -> Vec<Check> {
+    const NAME: &str = "provider";
+    let keys = std::iter::once(("agent.api_key".to_string(), &agent.api_key)).chain(
+        agent
+            .api_keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (format!("agent.api_keys[{}]", i), k)),
+    );
+    // Local servers behind `base_url` often take no key
+    let keyless = agent.api_key.is_empty() && agent.base_url.is_some();
+    let mut checks = Vec::new();
+    for (field, key) in keys.skip(usize::from(keyless)) {
+        if key.is_empty() {
+            checks.push(Check::fail(NAME, format!("{} is empty", field)));
+        } else if key.chars().any(char::is_whitespace) {
+            checks.push(Check::fail(
+                NAME,
+                format!("{} contains whitespace (pasted with a newline?)", field),
+            ));
+        } else if is_placeholder(key) {
+            checks.push(Check::fail(
+                NAME,
+                format!("{} is a placeholder, not a key", field),
+            ));
+        } else if agent.provider == "anthropic" && !key.starts_with("sk-ant-") {
+            checks.push(Check::warn(
+                NAME,
+                format!("{} doesn't look like an Anthropic key (sk-ant-...)", field),
+            ));
+        }
+    }
+    if checks.is_empty() {
+        let count = agent.api_keys.len() + usize::from(!keyless);
+        let detail = match (&agent.base_url, count) {
+            (Some(base_url), 0) => format!("no key, using {}", base_url),
+            _ => format!("{} key(s) set", count),
+        };
+        checks.push(Check::ok(NAME, detail));
+    }
+    checks
+}
+
+/// Text left in from an example config.
+fn is_placeholder(key: &str) -> bool {
+    let lower = key.to_lowercase();
+    key.starts_with('<')
+        || key.contains("...")
+        || lower.contains("your_")
+        || lower.contains("your-")
+        || lower == "changeme"
+}
+
+/// What a token should look like, and a test for it.
+type Shape = (&'static str, fn(&str) -> bool);
+
+fn check_channels(channels: &ChannelsConfig) -> Vec<Check> {
+    const NAME: &str = "channels";
+    let mut checks = Vec::new();
+    let mut token = |field: &str, value: &str, shape: Option<Shape>| {
+        if value.trim().is_empty() {
+            checks.push(Check::fail(NAME, format!("{} is empty", field)));
+        } else if value.trim() != value {
+            checks.push(Check::fail(
+                NAME,
+                format!("{} has leading or trailing whitespace", field),
+            ));
+        } else if let Some((expected, ok)) = shape {
+            if !ok(value) {
+                checks.push(Check::fail(
+                    NAME,
+                    format!("{} doesn't look like {}", field, expected),
+                ));
+            }
+        }
+    };
+    if let Some(ref tg) = channels.telegram {
+        token(
+            "channels.telegram.bot_token",
+            &tg.bot_token,
+            Some(("a BotFather token (<bot id>:<secret>)", |t| {
+                t.split_once(':')
+                    .is_some_and(|(id, secret)| id.parse::<u64>().is_ok() && !secret.is_empty())
+            })),
+        );
+    }
+    if let Some(ref dc) = channels.discord {
+        token("channels.discord.bot_token", &dc.bot_token, None);
+    }
+    if let Some(ref slack) = channels.slack {
+        token(
+            "channels.slack.bot_token",
+            &slack.bot_token,
+            Some(("a bot token (xoxb-...)", |t| t.starts_with("xoxb-"))),
+        );
+        token(
+            "channels.slack.app_token",
+            &slack.app_token,
+            Some(("an app-level token (xapp-...)", |t| t.starts_with("xapp-"))),
+        );
+    }
+    if let Some(ref signal) = channels.signal {
+        token("channels.signal.endpoint", &signal.endpoint, None);
+    }
+    if checks.is_empty() {
+        checks.push(Check::ok(NAME, "tokens present"));
+    }
+    checks
+}
+
+/// Skills whose SKILL.md has no manifest are loaded without their tool
+/// requirements, so a skill needing a disabled tool would slip through.
+fn check_skills(dirs: &[PathBuf]) -> Check {
+    const NAME: &str = "skills";
+    let mut broken = Vec::new();
+    let mut count = 0;
+    for dir in dirs {
+        for file in skill_files(dir) {
+            count += 1;
+            let parsed = std::fs::read_to_string(&file)
+                .ok()
+                .and_then(|c| crate::skills::manifest::parse_manifest(&c));
+            if parsed.is_none() {
+                broken.push(file.display().to_string());
+            }
+        }
+    }
+    if broken.is_empty() {
+        Check::ok(NAME, format!("{} skill(s) parsed", count))
+    } else {
+        Check::fail(
+            NAME,
+            format!(
+                "no readable frontmatter (---, name:, description:, ---) in {}",
+                broken.join(", ")
+            ),
+        )
+    }
+}
+
+/// `<dir>/<skill>/SKILL.md` files; none for a missing directory.
+fn skill_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path().join("SKILL.md"))
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+fn check_clock(now: u64) -> Check {
+    const NAME: &str = "clock";
+    let time = chrono::DateTime::from_timestamp_millis(now as i64)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+    if now < EARLIEST_PLAUSIBLE_MS {
+        Check::fail(
+            NAME,
+            format!("system time is {}; set the clock (or enable NTP)", time),
+        )
+    } else {
+        Check::ok(NAME, time)
+    }
+}
+
+/// `tz` is the `TZ` environment variable, `stored` the onboarding answer
+/// the date preamble reads the user's timezone from.
+fn check_timezone(tz: Option<&str>, stored: Option<&str>) -> Check {
+    const NAME: &str = "timezone";
+    if let Some(tz) = tz.filter(|t| !t.is_empty()) {
+        // `:Europe/Berlin` is the POSIX form of a zoneinfo name
+        let name = tz.trim_start_matches(':');
+        if name.parse::<chrono_tz::Tz>().is_err() && !Path::new(name).is_absolute() {
+            return Check::warn(
+                NAME,
+                format!(
+                    "TZ={} is not a known timezone; local time falls back to UTC",
+                    tz
+                ),
+            );
+        }
+    }
+    if let Some(text) = stored {
+        if crate::conductor::preamble::parse_timezone(text).is_none() {
+            return Check::warn(
+                NAME,
+                format!(
+                    "the stored timezone \"{}\" is not an IANA name; set [agent.preamble] \
+                     timezone or edit the profile:timezone memory",
+                    text
+                ),
+            );
+        }
+    }
+    Check::ok(NAME, "valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[tokio::test]
+    async fn test_run_reports_failures() {
+        let config = parse_config(
+            r#"
+[agent]
+model = "claude"
+api_key = "sk-ant-abc"
+api_keys = ["sk-ant-def\n"]
+skills_dirs = ["/nonexistent/skills"]
+
+[channels.telegram]
+bot_token = "123456:ABC-DEF"
+
+[channels.slack]
+bot_token = "xoxb-1"
+app_token = "xoxb-2"
+"#,
+        )
+        .unwrap();
+        let db = Db::open_memory().unwrap();
+        let report = run(&config, &db).await.unwrap();
+        assert!(report.failed());
+        assert_eq!(
+            report.to_string(),
+            "Startup self-test failed (2 problems):\n  \
+             - provider: agent.api_keys[0] contains whitespace (pasted with a newline?)\n  \
+             - channels: channels.slack.app_token doesn't look like an app-level token (xapp-...)"
+        );
+    }
+
+    #[test]
+    fn test_checks() {
+        assert_eq!(check_schema(24, 24).status, Status::Ok);
+        assert_eq!(check_schema(25, 24).status, Status::Fail);
+        assert_eq!(check_clock(0).status, Status::Fail);
+        assert_eq!(check_clock(EARLIEST_PLAUSIBLE_MS).status, Status::Ok);
+        assert_eq!(
+            check_timezone(Some("Europe/Berlin"), None).status,
+            Status::Ok
+        );
+        assert_eq!(check_timezone(Some(":UTC"), None).status, Status::Ok);
+        assert_eq!(
+            check_timezone(Some("Mars/Olympus"), None).status,
+            Status::Warn
+        );
+        assert_eq!(
+            check_timezone(None, Some("The user's timezone: somewhere")).status,
+            Status::Warn
+        );
+        assert!(is_placeholder("<your key>"));
+        assert!(is_placeholder("YOUR_API_KEY"));
+        assert!(!is_placeholder("sk-ant-api03-xyz"));
+    }
+
+    #[test]
+    fn test_check_provider_keys_without_key() {
+        let config = parse_config(
+            r#"
+[agent]
+provider = "openai"
+model = "llama3"
+api_key = ""
+"#,
+        )
+        .unwrap();
+        let checks = check_provider_keys(&config.agent);
+        assert_eq!(checks[0].status, Status::Fail);
+        assert_eq!(checks[0].detail, "agent.api_key is empty");
+
+        let config = parse_config(
+            r#"
+[agent]
+provider = "openai"
+model = "llama3"
+api_key = ""
+base_url = "http://localhost:11434/v1"
+"#,
+        )
+        .unwrap();
+        let checks = check_provider_keys(&config.agent);
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].status, Status::Ok);
+        assert_eq!(checks[0].detail, "no key, using http://localhost:11434/v1");
+    }
+
+    #[test]
+    fn test_check_skills() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("good")).unwrap();
+        std::fs::write(
+            dir.path().join("good/SKILL.md"),
+            "---\nname: good\ndescription: Works\n---\nBody",
+        )
+        .unwrap();
+        let dirs = vec![dir.path().to_path_buf()];
+        assert_eq!(check_skills(&dirs).status, Status::Ok);
+
+        std::fs::create_dir(dir.path().join("bad")).unwrap();
+        std::fs::write(dir.path().join("bad/SKILL.md"), "# No frontmatter").unwrap();
+        let check = check_skills(&dirs);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.detail.contains("bad/SKILL.md"));
+    }
+}
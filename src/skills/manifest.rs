@@ -1,18 +1,27 @@
 //! Parse extended YAML frontmatter from SKILL.md files.
 //!
 //! yoagent's built-in parser only extracts `name` and `description`.
-//! We additionally parse the `tools` field for capability-based filtering.
+//! We additionally parse the `tools` field for capability-based filtering and
+//! `memory_namespace` for skill-scoped memory and `profile` for the execution
+//! profile its shell commands run in.
+
+use serde::Serialize;
 
 /// Parsed skill manifest from SKILL.md frontmatter.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SkillManifest {
     pub name: String,
     pub description: String,
     /// Tools this skill requires (e.g. ["http", "shell"]).
     pub tools: Vec<String>,
+    /// Memory namespace isolating this skill's memories from general memory.
+    pub memory_namespace: Option<String>,
+    /// Execution profile (`[profiles.<name>]`) for this skill's shell commands.
+    pub profile: Option<String>,
 }
 
-/// Parse a SKILL.md file's YAML frontmatter, extracting name, description, and tools.
+/// Parse a SKILL.md file's YAML frontmatter, extracting name, description, tools,
+/// memory namespace and execution profile.
 pub fn parse_manifest(content: &str) -> Option<SkillManifest> {
     let trimmed = content.trim_start();
     if !trimmed.starts_with("---") {
@@ -26,6 +35,8 @@ pub fn parse_manifest(content: &str) -> Option<SkillManifest> {
     let mut name = None;
     let mut description = None;
     let mut tools = Vec::new();
+    let mut memory_namespace = None;
+    let mut profile = None;
 
     for line in yaml_block.lines() {
         let line = line.trim();
@@ -35,6 +46,16 @@ pub fn parse_manifest(content: &str) -> Option<SkillManifest> {
             description = Some(unquote(rest.trim()));
         } else if let Some(rest) = line.strip_prefix("tools:") {
             tools = parse_tools_value(rest.trim());
+        } else if let Some(rest) = line.strip_prefix("memory_namespace:") {
+            let ns = unquote(rest.trim());
+            if !ns.is_empty() {
+                memory_namespace = Some(ns);
+            }
+        } else if let Some(rest) = line.strip_prefix("profile:") {
+            let name = unquote(rest.trim());
+            if !name.is_empty() {
+                profile = Some(name);
+            }
         }
     }
 
@@ -42,6 +63,8 @@ pub fn parse_manifest(content: &str) -> Option<SkillManifest> {
         name: name?,
         description: description?,
         tools,
+        memory_namespace,
+        profile,
     })
 }
 
@@ -127,6 +150,25 @@ Instructions here.
         assert!(parse_manifest(content).is_none());
     }
 
+    #[test]
+    fn test_parse_manifest_memory_namespace() {
+        let content =
+            "---\nname: inbox-triage\ndescription: Triage email\nmemory_namespace: inbox\n---\n";
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(manifest.memory_namespace.as_deref(), Some("inbox"));
+
+        let content = "---\nname: plain\ndescription: Plain\n---\n";
+        assert!(parse_manifest(content).unwrap().memory_namespace.is_none());
+    }
+
+    #[test]
+    fn test_parse_manifest_profile() {
+        let content =
+            "---\nname: data\ndescription: Analyze CSVs\ntools: [shell]\nprofile: docker-python\n---\n";
+        let manifest = parse_manifest(content).unwrap();
+        assert_eq!(manifest.profile.as_deref(), Some("docker-python"));
+    }
+
     #[test]
     fn test_parse_single_tool_no_brackets() {
         let content = "---\nname: simple\ndescription: Simple skill\ntools: http\n---\n";
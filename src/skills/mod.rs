@@ -1,11 +1,14 @@
 pub mod manifest;
 
+use crate::config::Config;
 use crate::security::SecurityPolicy;
 use manifest::{parse_manifest, SkillManifest};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::path::Path;
 
 /// A loaded skill with its manifest (including required tools) and file path.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LoadedSkill {
     pub manifest: SkillManifest,
     pub dir_name: String,
@@ -38,6 +41,8 @@ pub fn load_filtered_skills(dirs: &[&Path], policy: &SecurityPolicy) -> (String,
                         name: skill.name.clone(),
                         description: skill.description.clone(),
                         tools: Vec::new(),
+                        memory_namespace: None,
+                        profile: None,
                     },
                     dir_name: skill.name.clone(),
                     file_path: skill.file_path.clone(),
@@ -47,12 +52,7 @@ pub fn load_filtered_skills(dirs: &[&Path], policy: &SecurityPolicy) -> (String,
         };
 
         // Check if all required tools are enabled
-        let all_tools_available = manifest.tools.iter().all(|tool| {
-            match policy.tool_permissions.get(tool.as_str()) {
-                Some(perm) => perm.enabled,
-                None => true, // Unknown tools are allowed by default
-            }
-        });
+        let all_tools_available = manifest.tools.iter().all(|tool| policy.tool_enabled(tool));
 
         if all_tools_available {
             kept_skills.push(LoadedSkill {
@@ -78,6 +78,71 @@ pub fn load_filtered_skills(dirs: &[&Path], policy: &SecurityPolicy) -> (String,
     (prompt, kept_skills)
 }
 
+/// Skills in `config`'s skill directories whose tools are enabled.
+pub fn load_configured(config: &Config) -> Vec<LoadedSkill> {
+    let skills_dirs = config.skills_dirs();
+    let skills_refs: Vec<&Path> = skills_dirs.iter().map(|p| p.as_path()).collect();
+    let policy = SecurityPolicy::from_config(&config.security);
+    load_filtered_skills(&skills_refs, &policy).1
+}
+
+/// Prompt fragment for `skills`, leaving out the `disabled` ones.
+pub fn prompt(skills: &[LoadedSkill], disabled: &[String]) -> String {
+    format_skills_for_prompt(&enabled(skills, disabled))
+}
+
+/// The `skills` not listed in `disabled`.
+pub fn enabled(skills: &[LoadedSkill], disabled: &[String]) -> Vec<LoadedSkill> {
+    skills
+        .iter()
+        .filter(|s| !disabled.contains(&s.manifest.name))
+        .cloned()
+        .collect()
+}
+
+/// Where a skill's enabled state comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillSource {
+    /// The skill file and `agent.disabled_skills` in config.toml.
+    File,
+    /// `/admin skills enable|disable` or the web API.
+    Override,
+}
+
+/// A skill with its effective state.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillStatus {
+    #[serde(flatten)]
+    pub skill: LoadedSkill,
+    pub enabled: bool,
+    pub source: SkillSource,
+}
+
+/// Effective state of `skills`: an override if one is set, else enabled
+/// unless listed in `disabled`.
+pub fn statuses(
+    skills: Vec<LoadedSkill>,
+    disabled: &[String],
+    overrides: &HashMap<String, bool>,
+) -> Vec<SkillStatus> {
+    skills
+        .into_iter()
+        .map(|skill| match overrides.get(&skill.manifest.name) {
+            Some(&enabled) => SkillStatus {
+                skill,
+                enabled,
+                source: SkillSource::Override,
+            },
+            None => SkillStatus {
+                enabled: !disabled.contains(&skill.manifest.name),
+                skill,
+                source: SkillSource::File,
+            },
+        })
+        .collect()
+}
+
 /// Format kept skills as XML for the system prompt.
 /// Matches yoagent's `SkillSet::format_for_prompt()` format.
 fn format_skills_for_prompt(skills: &[LoadedSkill]) -> String {
@@ -114,8 +179,48 @@ fn xml_escape(s: &str) -> String {
         .replace('\'', "&apos;")
 }
 
-/// Format loaded skills for display (inspect command).
-pub fn format_skills_info(skills: &[LoadedSkill]) -> String {
+/// Map skill names to their declared memory namespaces.
+pub fn memory_namespaces(skills: &[LoadedSkill]) -> std::collections::HashMap<String, String> {
+    skills
+        .iter()
+        .filter_map(|s| {
+            s.manifest
+                .memory_namespace
+                .as_ref()
+                .map(|ns| (s.manifest.name.clone(), ns.clone()))
+        })
+        .collect()
+}
+
+/// Map skill files, canonicalized where possible, to skill names.
+pub fn skill_files(
+    skills: &[LoadedSkill],
+) -> std::collections::HashMap<std::path::PathBuf, String> {
+    skills
+        .iter()
+        .map(|s| {
+            let path = std::fs::canonicalize(&s.file_path).unwrap_or_else(|_| s.file_path.clone());
+            (path, s.manifest.name.clone())
+        })
+        .collect()
+}
+
+/// Map skill names to the execution profiles they request.
+pub fn skill_profiles(skills: &[LoadedSkill]) -> std::collections::HashMap<String, String> {
+    skills
+        .iter()
+        .filter_map(|s| {
+            s.manifest
+                .profile
+                .as_ref()
+                .map(|p| (s.manifest.name.clone(), p.clone()))
+        })
+        .collect()
+}
+
+/// Format skills and their state for display (inspect command, `/admin
+/// skills list`).
+pub fn format_skills_info(skills: &[SkillStatus]) -> String {
     if skills.is_empty() {
         return "No skills loaded.".to_string();
     }
@@ -123,14 +228,20 @@ pub fn format_skills_info(skills: &[LoadedSkill]) -> String {
     skills
         .iter()
         .map(|s| {
-            let tools = if s.manifest.tools.is_empty() {
+            let manifest = &s.skill.manifest;
+            let tools = if manifest.tools.is_empty() {
                 "none".to_string()
             } else {
-                s.manifest.tools.join(", ")
+                manifest.tools.join(", ")
+            };
+            let state = if s.enabled { "enabled" } else { "disabled" };
+            let source = match s.source {
+                SkillSource::File => "file",
+                SkillSource::Override => "override",
             };
             format!(
-                "  {} — {} (tools: {})",
-                s.manifest.name, s.manifest.description, tools
+                "  {} — {} (tools: {}) [{}, {}]",
+                manifest.name, manifest.description, tools, state, source
             )
         })
         .collect::<Vec<_>>()
@@ -173,6 +284,8 @@ mod tests {
         SecurityPolicy {
             shell_deny_patterns: vec![],
             tool_permissions: HashMap::new(),
+            permissive: true,
+            readable_dirs: vec![],
         }
     }
 
@@ -187,6 +300,7 @@ mod tests {
                         allowed_paths: vec![],
                         allowed_hosts: vec![],
                         requires_approval: false,
+                        max_calls_per_day: None,
                     },
                 ),
                 (
@@ -196,9 +310,12 @@ mod tests {
                         allowed_paths: vec![],
                         allowed_hosts: vec![],
                         requires_approval: false,
+                        max_calls_per_day: None,
                     },
                 ),
             ]),
+            permissive: false,
+            readable_dirs: vec![],
         }
     }
 
@@ -247,6 +364,44 @@ mod tests {
         assert!(prompt.is_empty());
     }
 
+    #[test]
+    fn test_prompt_leaves_out_disabled() {
+        let tmp = TempDir::new().unwrap();
+        create_skill(tmp.path(), "weather", "Get weather", &["http"]);
+        create_skill(tmp.path(), "coding", "Write code", &["shell"]);
+
+        let (_, loaded) = load_filtered_skills(&[tmp.path()], &permissive_policy());
+        let text = prompt(&loaded, &["coding".to_string()]);
+        assert!(text.contains("weather"));
+        assert!(!text.contains("coding"));
+        assert!(prompt(&loaded, &["weather".into(), "coding".into()]).is_empty());
+    }
+
+    #[test]
+    fn test_statuses() {
+        let tmp = TempDir::new().unwrap();
+        create_skill(tmp.path(), "weather", "Get weather", &[]);
+        create_skill(tmp.path(), "coding", "Write code", &[]);
+        create_skill(tmp.path(), "notes", "Take notes", &[]);
+        let (_, mut loaded) = load_filtered_skills(&[tmp.path()], &permissive_policy());
+        loaded.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+
+        let disabled = vec!["coding".to_string(), "notes".to_string()];
+        let overrides = HashMap::from([("notes".to_string(), true)]);
+        let states: Vec<(String, bool, SkillSource)> = statuses(loaded, &disabled, &overrides)
+            .into_iter()
+            .map(|s| (s.skill.manifest.name, s.enabled, s.source))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("coding".into(), false, SkillSource::File),
+                ("notes".into(), true, SkillSource::Override),
+                ("weather".into(), true, SkillSource::File),
+            ]
+        );
+    }
+
     #[test]
     fn test_format_skills_info() {
         let skills = vec![
@@ -255,6 +410,8 @@ mod tests {
                     name: "weather".into(),
                     description: "Get weather".into(),
                     tools: vec!["http".into()],
+                    memory_namespace: None,
+                    profile: None,
                 },
                 dir_name: "weather".into(),
                 file_path: "/tmp/weather/SKILL.md".into(),
@@ -264,12 +421,17 @@ mod tests {
                     name: "coding".into(),
                     description: "Write code".into(),
                     tools: vec!["shell".into(), "write_file".into()],
+                    memory_namespace: None,
+                    profile: None,
                 },
                 dir_name: "coding".into(),
                 file_path: "/tmp/coding/SKILL.md".into(),
             },
         ];
-        let info = format_skills_info(&skills);
+        let overrides = HashMap::from([("coding".to_string(), false)]);
+        let info = format_skills_info(&statuses(skills, &[], &overrides));
+        assert!(info.contains("[enabled, file]"));
+        assert!(info.contains("[disabled, override]"));
         assert!(info.contains("weather"));
         assert!(info.contains("http"));
         assert!(info.contains("coding"));
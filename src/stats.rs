@@ -0,0 +1,237 @@
+//! Per-session usage report for `/stats` and `yoclaw inspect --stats`.
+//!
+//! Everything is derived from audit events (`llm_usage`, `response`,
+//! `tool_call`, `compaction`) plus the session tape.
+
+use crate::config::PricingConfig;
+use crate::db::audit::AuditEntry;
+use crate::db::{Db, DbError};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionStats {
+    pub session_id: String,
+    /// LLM calls (one per agent turn, including tool-use turns).
+    pub turns: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Messages answered by the agent, with end-to-end latency in ms.
+    pub latencies_ms: Vec<u64>,
+    pub tool_calls: BTreeMap<String, u64>,
+    pub compactions: u64,
+    /// Messages currently in the tape.
+    pub tape_messages: usize,
+}
+
+impl SessionStats {
+    /// Build stats from a session's audit events (any order) and tape length.
+    pub fn from_events(session_id: &str, events: &[AuditEntry], tape_messages: usize) -> Self {
+        let mut stats = SessionStats {
+            session_id: session_id.to_string(),
+            tape_messages,
+            ..Default::default()
+        };
+        for event in events {
+            let detail = event.detail.as_deref().unwrap_or("");
+            match event.event_type.as_str() {
+                "llm_usage" => {
+                    stats.turns += 1;
+                    match (detail_value(detail, "in"), detail_value(detail, "out")) {
+                        (Some(input), Some(output)) => {
+                            stats.input_tokens += input;
+                            stats.output_tokens += output;
+                        }
+                        // Older rows only carry the total
+                        _ => stats.input_tokens += event.tokens_used,
+                    }
+                }
+                "response" => {
+                    if let Some(ms) = detail_value(detail, "latency_ms") {
+                        stats.latencies_ms.push(ms);
+                    }
+                }
+                "tool_call" => {
+                    let tool = event.tool_name.clone().unwrap_or_else(|| "unknown".into());
+                    *stats.tool_calls.entry(tool).or_default() += 1;
+                }
+                "compaction" => stats.compactions += 1,
+                _ => {}
+            }
+        }
+        stats.latencies_ms.sort_unstable();
+        stats
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// Estimated cost in dollars.
+    pub fn estimated_cost(&self, pricing: &PricingConfig) -> f64 {
+        pricing.cost(self.input_tokens, self.output_tokens)
+    }
+
+    pub fn avg_latency_ms(&self) -> Option<u64> {
+        if self.latencies_ms.is_empty() {
+            return None;
+        }
+        Some(self.latencies_ms.iter().sum::<u64>() / self.latencies_ms.len() as u64)
+    }
+
+    /// Nearest-rank percentile (`p` in 0..=100) of response latency.
+    pub fn latency_percentile(&self, p: u8) -> Option<u64> {
+        if self.latencies_ms.is_empty() {
+            return None;
+        }
+        let rank = (p.min(100) as usize * self.latencies_ms.len()).div_ceil(100);
+        Some(self.latencies_ms[rank.saturating_sub(1)])
+    }
+
+    /// Plain-text report, suitable for chat and the terminal.
+    pub fn format(&self, pricing: Option<&PricingConfig>) -> String {
+        let mut lines = vec![
+            format!("Session {}", self.session_id),
+            format!(
+                "Responses: {} ({} LLM turns)",
+                self.latencies_ms.len(),
+                self.turns
+            ),
+            format!(
+                "Tokens: {} in / {} out ({} total)",
+                self.input_tokens,
+                self.output_tokens,
+                self.total_tokens()
+            ),
+        ];
+        match pricing {
+            Some(p) => lines.push(format!("Estimated cost: ${:.4}", self.estimated_cost(p))),
+            None => lines.push("Estimated cost: n/a (set [agent.pricing])".to_string()),
+        }
+        if let (Some(avg), Some(p50), Some(p95)) = (
+            self.avg_latency_ms(),
+            self.latency_percentile(50),
+            self.latency_percentile(95),
+        ) {
+            lines.push(format!(
+                "Latency: avg {} / p50 {} / p95 {}",
+                format_ms(avg),
+                format_ms(p50),
+                format_ms(p95)
+            ));
+        }
+        if self.tool_calls.is_empty() {
+            lines.push("Tool calls: none".to_string());
+        } else {
+            let mut tools: Vec<(&String, &u64)> = self.tool_calls.iter().collect();
+            tools.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let list: Vec<String> = tools.iter().map(|(t, n)| format!("{} {}", t, n)).collect();
+            lines.push(format!("Tool calls: {}", list.join(", ")));
+        }
+        lines.push(format!("Compactions: {}", self.compactions));
+        lines.push(format!("Messages in tape: {}", self.tape_messages));
+        lines.join("\n")
+    }
+}
+
+/// Compute stats for one session.
+pub async fn session_stats(db: &Db, session_id: &str) -> Result<SessionStats, DbError> {
+    let events = db.audit_session_events(session_id).await?;
+    let tape = db.tape_load_messages(session_id).await?;
+    Ok(SessionStats::from_events(session_id, &events, tape.len()))
+}
+
+/// Read `key=<number>` from a space-separated audit detail.
+fn detail_value(detail: &str, key: &str) -> Option<u64> {
+    detail.split_whitespace().find_map(|part| {
+        let (k, v) = part.split_once('=')?;
+        if k == key {
+            v.parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+fn format_ms(ms: u64) -> String {
+    if ms >= 1000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}ms", ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        event_type: &str,
+        tool: Option<&str>,
+        detail: Option<&str>,
+        tokens: u64,
+    ) -> AuditEntry {
+        AuditEntry {
+            id: None,
+            session_id: Some("tg-1".into()),
+            event_type: event_type.into(),
+            tool_name: tool.map(Into::into),
+            detail: detail.map(Into::into),
+            tokens_used: tokens,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_stats_from_events() {
+        let mut events = vec![
+            event("llm_usage", None, Some("in=1000 out=200"), 1200),
+            event("llm_usage", None, None, 300),
+            event("tool_call", Some("shell"), Some("ls"), 0),
+            event("tool_call", Some("shell"), Some("pwd"), 0),
+            event("tool_call", Some("memory_search"), None, 0),
+            event("compaction", None, Some("dropped=12"), 0),
+            event("route", None, Some("x"), 0),
+        ];
+        for ms in [900, 100, 500, 300, 2000] {
+            events.push(event(
+                "response",
+                None,
+                Some(&format!("latency_ms={}", ms)),
+                0,
+            ));
+        }
+
+        let stats = SessionStats::from_events("tg-1", &events, 8);
+        assert_eq!(stats.turns, 2);
+        assert_eq!(stats.input_tokens, 1300);
+        assert_eq!(stats.output_tokens, 200);
+        assert_eq!(stats.tool_calls["shell"], 2);
+        assert_eq!(stats.tool_calls["memory_search"], 1);
+        assert_eq!(stats.compactions, 1);
+        assert_eq!(stats.avg_latency_ms(), Some(760));
+        assert_eq!(stats.latency_percentile(50), Some(500));
+        assert_eq!(stats.latency_percentile(95), Some(2000));
+
+        let pricing = PricingConfig {
+            input_per_mtok: 3.0,
+            output_per_mtok: 15.0,
+        };
+        assert!((stats.estimated_cost(&pricing) - 0.0069).abs() < 1e-9);
+
+        let report = stats.format(Some(&pricing));
+        assert!(report.contains("Responses: 5 (2 LLM turns)"));
+        assert!(report.contains("Estimated cost: $0.0069"));
+        assert!(report.contains("p95 2.0s"));
+        assert!(report.contains("Tool calls: shell 2, memory_search 1"));
+    }
+
+    #[test]
+    fn test_empty_session() {
+        let stats = SessionStats::from_events("tg-2", &[], 0);
+        assert_eq!(stats.latency_percentile(50), None);
+        let report = stats.format(None);
+        assert!(report.contains("n/a"));
+        assert!(!report.contains("Latency"));
+    }
+}
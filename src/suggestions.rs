@@ -0,0 +1,162 @@
+//! Follow-up suggestions under replies.
+//!
+//! On channels with `suggestions = true`, after a reply in a direct message
+//! the main loop asks the `[suggestions]` model for a few short things the
+//! user might say next and offers them as buttons: a reply keyboard on
+//! Telegram, components on Discord. Tapping one sends its text as the next
+//! user message. The suggestions are noted on the session's tape, so the
+//! agent knows what was offered. Anything going wrong just means no
+//! buttons.
+
+use crate::config::Config;
+use crate::scheduler::AgentRunConfig;
+use std::time::Duration;
+
+/// Text of the message carrying the buttons, where they can't be put on
+/// the reply itself.
+pub const HEADER: &str = "Suggested follow-ups:";
+/// Longest suggestion kept, in characters. Discord button labels stop at 80.
+pub const MAX_CHARS: usize = 60;
+/// The user has their reply already; don't hold the session up for long.
+const TIMEOUT: Duration = Duration::from_secs(20);
+/// Characters of the exchange sent to the model.
+const INPUT_CHARS: usize = 4_000;
+
+const SYSTEM_PROMPT: &str =
+    "You suggest what a user might say next in a chat with an assistant. You write \
+     in the user's voice and language.";
+
+/// Model settings for suggestions, from `[suggestions]`.
+pub fn agent_config(config: &Config) -> AgentRunConfig {
+    AgentRunConfig {
+        provider: config.agent.provider.clone(),
+        model: config
+            .suggestions
+            .model
+            .clone()
+            .unwrap_or_else(|| config.scheduler.cortex.model.clone()),
+        api_key: config.agent.api_key.clone(),
+        endpoint: crate::conductor::endpoint::Endpoint::agent(&config.agent),
+        context: Default::default(),
+        max_tokens: Some(200),
+        stream_provider: None,
+        fallback_providers: Vec::new(),
+    }
+}
+
+/// Up to `count` follow-ups to the exchange, or none if the model fails or
+/// finds nothing worth suggesting.
+pub async fn generate(
+    agent_config: &AgentRunConfig,
+    message: &str,
+    reply: &str,
+    count: usize,
+) -> Vec<String> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let prompt = format!(
+        "The user wrote:\n{}\n\nThe assistant replied:\n{}\n\nWrite up to {} short \
+         follow-up messages the user is likely to send next, one per line, each under \
+         {} characters. No numbering, quotes or commentary. Write NONE if nothing \
+         natural follows.",
+        clip(message, INPUT_CHARS / 4),
+        clip(reply, INPUT_CHARS),
+        count,
+        MAX_CHARS
+    );
+    let result = tokio::time::timeout(
+        TIMEOUT,
+        crate::scheduler::run_ephemeral_prompt(agent_config, SYSTEM_PROMPT, &prompt),
+    )
+    .await;
+    match result {
+        Ok(Ok(text)) => parse(&text, count),
+        Ok(Err(e)) => {
+            tracing::warn!("Follow-up suggestions failed: {}", e);
+            Vec::new()
+        }
+        Err(_) => {
+            tracing::warn!("Follow-up suggestions timed out");
+            Vec::new()
+        }
+    }
+}
+
+/// Suggestions in the model's answer: one per line, without list markers
+/// or quotes. Empty, overlong and repeated lines are dropped.
+pub fn parse(text: &str, count: usize) -> Vec<String> {
+    let mut suggestions: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line
+            .trim()
+            .trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '-' | '*' | '•'))
+            .trim_start_matches(['.', ')'])
+            .trim()
+            .trim_matches(['"', '“', '”'])
+            .trim();
+        if line.is_empty()
+            || line.eq_ignore_ascii_case("none")
+            || line.chars().count() > MAX_CHARS
+            || suggestions.iter().any(|s| s.eq_ignore_ascii_case(line))
+        {
+            continue;
+        }
+        suggestions.push(line.to_string());
+        if suggestions.len() == count {
+            break;
+        }
+    }
+    suggestions
+}
+
+/// Note recorded on the tape after the reply.
+pub fn tape_note(suggestions: &[String]) -> String {
+    format!(
+        "[Follow-up suggestions offered to the user as buttons: {}]",
+        suggestions
+            .iter()
+            .map(|s| format!("\"{}\"", s))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// First `max` characters of `text`.
+fn clip(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "1. What about tomorrow?\n- \"And the weekend?\"\n\n* what about tomorrow?\n\
+                    Will it rain in Paris too?";
+        assert_eq!(
+            parse(text, 3),
+            vec![
+                "What about tomorrow?",
+                "And the weekend?",
+                "Will it rain in Paris too?"
+            ]
+        );
+        assert_eq!(parse(text, 1), vec!["What about tomorrow?"]);
+        assert!(parse("NONE", 3).is_empty());
+        assert!(parse(&"x".repeat(MAX_CHARS + 1), 3).is_empty());
+    }
+
+    #[test]
+    fn test_tape_note() {
+        let note = tape_note(&["Thanks!".into(), "More detail".into()]);
+        assert_eq!(
+            note,
+            "[Follow-up suggestions offered to the user as buttons: \"Thanks!\", \"More detail\"]"
+        );
+    }
+}
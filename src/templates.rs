@@ -0,0 +1,173 @@
+//! Conversation templates.
+//!
+//! `/start <name> [text]` sends the prompt of `[templates.<name>]` to the
+//! agent in place of the command, so recurring conversations (a standup, a
+//! weekly review) start from the same setup every time. Text after the name
+//! fills `{input}`, or is appended when the prompt has no `{input}`. With
+//! `new_session`, the chat's conversation is set aside first and the
+//! template starts from an empty one.
+//!
+//! A bare `/start` is left alone, since Telegram sends it when a chat is
+//! opened, and so is every `/start` when no templates are configured.
+
+use crate::channels::IncomingMessage;
+use crate::config::TemplateConfig;
+use std::collections::HashMap;
+
+/// A `/start <name> [text]` command.
+#[derive(Debug, PartialEq)]
+pub struct StartCommand<'a> {
+    pub name: &'a str,
+    pub input: &'a str,
+}
+
+/// The `/start` command in `text`, if templates are configured.
+pub fn parse<'a>(
+    templates: &HashMap<String, TemplateConfig>,
+    text: &'a str,
+) -> Option<StartCommand<'a>> {
+    if templates.is_empty() {
+        return None;
+    }
+    let rest = text.trim().strip_prefix("/start")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let (name, input) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some(StartCommand {
+        name,
+        input: input.trim(),
+    })
+}
+
+/// The message the agent receives for `template`.
+pub fn render(template: &TemplateConfig, input: &str, msg: &IncomingMessage) -> String {
+    let now = chrono::Local::now();
+    let prompt = template
+        .prompt
+        .replace(
+            "{sender}",
+            msg.sender_name.as_deref().unwrap_or(&msg.sender_id),
+        )
+        .replace("{channel}", &msg.channel)
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{weekday}", &now.format("%A").to_string());
+    if template.prompt.contains("{input}") {
+        prompt.replace("{input}", input)
+    } else if input.is_empty() {
+        prompt
+    } else {
+        format!("{}\n\n{}", prompt.trim_end(), input)
+    }
+}
+
+/// Reply to a `/start` naming no configured template.
+pub fn unknown(templates: &HashMap<String, TemplateConfig>, name: &str) -> String {
+    let mut names: Vec<&String> = templates.keys().collect();
+    names.sort();
+    let lines: Vec<String> = names
+        .into_iter()
+        .map(|n| match templates[n].description {
+            Some(ref d) => format!("- {}: {}", n, d),
+            None => format!("- {}", n),
+        })
+        .collect();
+    format!(
+        "Unknown template '{}'. Available:\n{}",
+        name,
+        lines.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templates() -> HashMap<String, TemplateConfig> {
+        HashMap::from([
+            (
+                "standup".to_string(),
+                TemplateConfig {
+                    prompt: "Run my standup for {weekday}. Ask what I did yesterday.".into(),
+                    description: Some("Daily standup".into()),
+                    new_session: true,
+                },
+            ),
+            (
+                "review".to_string(),
+                TemplateConfig {
+                    prompt: "Weekly review for {sender}. Focus: {input}".into(),
+                    description: None,
+                    new_session: false,
+                },
+            ),
+        ])
+    }
+
+    fn msg() -> IncomingMessage {
+        IncomingMessage {
+            channel: "telegram".to_string(),
+            sender_id: "42".to_string(),
+            sender_name: Some("alice".to_string()),
+            session_id: "tg-42".to_string(),
+            chat_id: Some("42".to_string()),
+            thread_id: None,
+            content: "/start review".to_string(),
+            reply_to: None,
+            quote: None,
+            timestamp: 0,
+            worker_hint: None,
+            is_group: false,
+            message_id: None,
+            kind: Default::default(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let t = templates();
+        assert_eq!(
+            parse(&t, " /start review  hiring and budget "),
+            Some(StartCommand {
+                name: "review",
+                input: "hiring and budget"
+            })
+        );
+        assert_eq!(
+            parse(&t, "/start standup"),
+            Some(StartCommand {
+                name: "standup",
+                input: ""
+            })
+        );
+        assert_eq!(parse(&t, "/start"), None);
+        assert_eq!(parse(&t, "/started"), None);
+        assert_eq!(parse(&t, "please /start standup"), None);
+        assert_eq!(parse(&HashMap::new(), "/start standup"), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let t = templates();
+        assert_eq!(
+            render(&t["review"], "hiring", &msg()),
+            "Weekly review for alice. Focus: hiring"
+        );
+        let weekday = chrono::Local::now().format("%A").to_string();
+        assert_eq!(
+            render(&t["standup"], "", &msg()),
+            format!("Run my standup for {}. Ask what I did yesterday.", weekday)
+        );
+        assert!(render(&t["standup"], "Short one today", &msg()).ends_with("\n\nShort one today"));
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert_eq!(
+            unknown(&templates(), "retro"),
+            "Unknown template 'retro'. Available:\n- review\n- standup: Daily standup"
+        );
+    }
+}
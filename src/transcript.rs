@@ -0,0 +1,653 @@
+//! Render session tapes as shareable transcripts.
+//!
+//! HTML transcripts are self-contained (inline CSS, no scripts) with markdown
+//! rendered and tool calls collapsed into `<details>` blocks. PDF transcripts
+//! are plain text laid out with a built-in font, so no font files are needed.
+//! Markdown transcripts keep the messages as written, with tool calls and
+//! results in code blocks; JSON is the tape itself.
+
+use yoagent::types::*;
+use yoagent::AgentMessage;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TranscriptError {
+    #[error("Unknown transcript format '{0}' (expected html, pdf, markdown or json)")]
+    UnknownFormat(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Html,
+    Pdf,
+    Markdown,
+    Json,
+}
+
+impl std::str::FromStr for TranscriptFormat {
+    type Err = TranscriptError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "html" => Ok(Self::Html),
+            "pdf" => Ok(Self::Pdf),
+            "markdown" | "md" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            other => Err(TranscriptError::UnknownFormat(other.to_string())),
+        }
+    }
+}
+
+impl TranscriptFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Pdf => "pdf",
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Html => "text/html; charset=utf-8",
+            Self::Pdf => "application/pdf",
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Json => "application/json",
+        }
+    }
+}
+
+/// Render a session tape in the given format.
+pub fn render(format: TranscriptFormat, session_id: &str, messages: &[AgentMessage]) -> Vec<u8> {
+    match format {
+        TranscriptFormat::Html => render_html(session_id, messages).into_bytes(),
+        TranscriptFormat::Pdf => render_pdf(session_id, messages),
+        TranscriptFormat::Markdown => render_markdown(session_id, messages).into_bytes(),
+        TranscriptFormat::Json => {
+            let tape = serde_json::json!({ "session_id": session_id, "messages": messages });
+            serde_json::to_vec_pretty(&tape).unwrap_or_default()
+        }
+    }
+}
+
+/// Token totals shown in the transcript footer.
+#[derive(Debug, Default, PartialEq)]
+struct Totals {
+    turns: usize,
+    tool_calls: usize,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+impl Totals {
+    fn from_messages(messages: &[AgentMessage]) -> Self {
+        let mut totals = Self::default();
+        for msg in messages {
+            if let AgentMessage::Llm(Message::Assistant { content, usage, .. }) = msg {
+                totals.turns += 1;
+                totals.input_tokens += usage.input;
+                totals.output_tokens += usage.output;
+                totals.tool_calls += content
+                    .iter()
+                    .filter(|c| matches!(c, Content::ToolCall { .. }))
+                    .count();
+            }
+        }
+        totals
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} assistant turns · {} tool calls · {} input tokens · {} output tokens",
+            self.turns, self.tool_calls, self.input_tokens, self.output_tokens
+        )
+    }
+}
+
+fn exported_at() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M").to_string()
+}
+
+// ---------------------------------------------------------------------------
+// HTML
+// ---------------------------------------------------------------------------
+
+const HTML_STYLE: &str = "\
+body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',Helvetica,Arial,sans-serif;\
+max-width:820px;margin:32px auto;padding:0 16px;color:#1f2328;line-height:1.5}\
+header{border-bottom:1px solid #d0d7de;margin-bottom:24px}\
+header h1{font-size:20px;margin:0 0 4px}header p{color:#656d76;margin:0 0 12px;font-size:13px}\
+.msg{margin:0 0 16px;padding:12px 16px;border-radius:8px;border:1px solid #d0d7de}\
+.user{background:#ddf4ff;border-color:#b6e3ff}.assistant{background:#fff}\
+.role{font-size:12px;font-weight:600;color:#656d76;text-transform:uppercase;margin-bottom:4px}\
+.body>:first-child{margin-top:0}.body>:last-child{margin-bottom:0}\
+pre{background:#f6f8fa;padding:8px 12px;border-radius:6px;overflow-x:auto;font-size:12px}\
+code{font-family:ui-monospace,SFMono-Regular,Menlo,monospace}\
+details{margin:8px 0;font-size:13px}summary{cursor:pointer;color:#656d76}\
+details.error summary{color:#cf222e}\
+footer{border-top:1px solid #d0d7de;margin-top:24px;padding-top:8px;color:#656d76;font-size:12px}";
+
+/// Render a self-contained HTML transcript.
+pub fn render_html(session_id: &str, messages: &[AgentMessage]) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!(
+        "<title>Transcript {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape_html(session_id),
+        HTML_STYLE
+    ));
+    out.push_str(&format!(
+        "<header><h1>{}</h1><p>{} messages · exported {}</p></header>\n",
+        escape_html(session_id),
+        messages.len(),
+        exported_at()
+    ));
+
+    for msg in messages {
+        let AgentMessage::Llm(msg) = msg else {
+            continue;
+        };
+        match msg {
+            Message::User { content, .. } => {
+                out.push_str(
+                    "<div class=\"msg user\"><div class=\"role\">User</div><div class=\"body\">",
+                );
+                for c in content {
+                    if let Content::Text { text } = c {
+                        out.push_str(&markdown_to_html(text));
+                    }
+                }
+                out.push_str("</div></div>\n");
+            }
+            Message::Assistant { content, model, .. } => {
+                out.push_str(&format!(
+                    "<div class=\"msg assistant\"><div class=\"role\">Assistant · {}</div><div class=\"body\">",
+                    escape_html(model)
+                ));
+                for c in content {
+                    match c {
+                        Content::Text { text } => out.push_str(&markdown_to_html(text)),
+                        Content::ToolCall {
+                            name, arguments, ..
+                        } => {
+                            let args = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                            out.push_str(&format!(
+                                "<details><summary>Tool call: {}</summary><pre><code>{}</code></pre></details>",
+                                escape_html(name),
+                                escape_html(&args)
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+                out.push_str("</div></div>\n");
+            }
+            Message::ToolResult {
+                tool_name,
+                content,
+                is_error,
+                ..
+            } => {
+                let text = content_text(content);
+                out.push_str(&format!(
+                    "<details{}><summary>{} {}</summary><pre><code>{}</code></pre></details>\n",
+                    if *is_error { " class=\"error\"" } else { "" },
+                    if *is_error {
+                        "Tool error:"
+                    } else {
+                        "Tool result:"
+                    },
+                    escape_html(tool_name),
+                    escape_html(&text)
+                ));
+            }
+        }
+    }
+
+    out.push_str(&format!(
+        "<footer>{}</footer>\n</body>\n</html>\n",
+        Totals::from_messages(messages).summary()
+    ));
+    out
+}
+
+/// Render markdown to HTML. Raw HTML in messages is shown as text, not
+/// interpreted, so a shared transcript can't carry markup from the chat.
+fn markdown_to_html(text: &str) -> String {
+    use pulldown_cmark::{Event, Options, Parser};
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let parser = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        other => other,
+    });
+    let mut out = String::new();
+    pulldown_cmark::html::push_html(&mut out, parser);
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The text blocks of a message, one per line; images and other blocks are
+/// left out.
+pub(crate) fn content_text(content: &[Content]) -> String {
+    content
+        .iter()
+        .filter_map(|c| match c {
+            Content::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ---------------------------------------------------------------------------
+// Markdown
+// ---------------------------------------------------------------------------
+
+/// Render a Markdown transcript.
+pub fn render_markdown(session_id: &str, messages: &[AgentMessage]) -> String {
+    let mut out = format!(
+        "# Transcript {}\n\n{} messages · exported {}\n\n",
+        session_id,
+        messages.len(),
+        exported_at()
+    );
+
+    for msg in messages {
+        let AgentMessage::Llm(msg) = msg else {
+            continue;
+        };
+        match msg {
+            Message::User { content, .. } => {
+                out.push_str(&format!("## User\n\n{}\n\n", content_text(content)));
+            }
+            Message::Assistant { content, model, .. } => {
+                out.push_str(&format!("## Assistant ({})\n\n", model));
+                for c in content {
+                    match c {
+                        Content::Text { text } => out.push_str(&format!("{}\n\n", text)),
+                        Content::ToolCall {
+                            name, arguments, ..
+                        } => {
+                            let args = serde_json::to_string_pretty(arguments).unwrap_or_default();
+                            out.push_str(&format!(
+                                "Tool call: `{}`\n\n```json\n{}\n```\n\n",
+                                name, args
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Message::ToolResult {
+                tool_name,
+                content,
+                is_error,
+                ..
+            } => {
+                let label = if *is_error {
+                    "Tool error"
+                } else {
+                    "Tool result"
+                };
+                out.push_str(&format!(
+                    "{}: `{}`\n\n```\n{}\n```\n\n",
+                    label,
+                    tool_name,
+                    content_text(content)
+                ));
+            }
+        }
+    }
+
+    out.push_str(&format!(
+        "---\n\n{}\n",
+        Totals::from_messages(messages).summary()
+    ));
+    out
+}
+
+// ---------------------------------------------------------------------------
+// PDF
+// ---------------------------------------------------------------------------
+
+/// US Letter page in points.
+const PAGE_WIDTH: u32 = 612;
+const PAGE_HEIGHT: u32 = 792;
+const MARGIN: u32 = 50;
+const FONT_SIZE: u32 = 9;
+const LINE_HEIGHT: u32 = 12;
+/// Courier glyphs are 0.6em wide, so this many fit between the margins.
+const CHARS_PER_LINE: usize = ((PAGE_WIDTH - 2 * MARGIN) * 10 / (FONT_SIZE * 6)) as usize;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT) as usize;
+/// Tool results are truncated in PDFs, which have no collapsible sections.
+const PDF_TOOL_RESULT_LINES: usize = 10;
+
+/// Render a plain-text PDF transcript.
+pub fn render_pdf(session_id: &str, messages: &[AgentMessage]) -> Vec<u8> {
+    let mut lines = vec![
+        format!("Transcript {}", session_id),
+        format!("{} messages, exported {}", messages.len(), exported_at()),
+        String::new(),
+    ];
+
+    for msg in messages {
+        let AgentMessage::Llm(msg) = msg else {
+            continue;
+        };
+        match msg {
+            Message::User { content, .. } => {
+                lines.push("USER".to_string());
+                push_wrapped(&mut lines, &content_text(content), "  ");
+            }
+            Message::Assistant { content, model, .. } => {
+                lines.push(format!("ASSISTANT ({})", model));
+                for c in content {
+                    match c {
+                        Content::Text { text } => push_wrapped(&mut lines, text, "  "),
+                        Content::ToolCall {
+                            name, arguments, ..
+                        } => push_wrapped(
+                            &mut lines,
+                            &format!("[tool call] {} {}", name, arguments),
+                            "  ",
+                        ),
+                        _ => {}
+                    }
+                }
+            }
+            Message::ToolResult {
+                tool_name,
+                content,
+                is_error,
+                ..
+            } => {
+                let label = if *is_error {
+                    "tool error"
+                } else {
+                    "tool result"
+                };
+                lines.push(format!("  [{}] {}", label, tool_name));
+                let text = content_text(content);
+                let total = text.lines().count();
+                let shown: Vec<&str> = text.lines().take(PDF_TOOL_RESULT_LINES).collect();
+                push_wrapped(&mut lines, &shown.join("\n"), "    ");
+                if total > PDF_TOOL_RESULT_LINES {
+                    lines.push(format!(
+                        "    ... {} more lines",
+                        total - PDF_TOOL_RESULT_LINES
+                    ));
+                }
+            }
+        }
+        lines.push(String::new());
+    }
+
+    lines.push("-".repeat(CHARS_PER_LINE));
+    push_wrapped(&mut lines, &Totals::from_messages(messages).summary(), "");
+
+    write_pdf(&lines)
+}
+
+/// Wrap `text` to the page width, prefixing every line with `indent`.
+fn push_wrapped(lines: &mut Vec<String>, text: &str, indent: &str) {
+    let width = CHARS_PER_LINE.saturating_sub(indent.len()).max(1);
+    for raw in text.lines() {
+        let chars: Vec<char> = raw.chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+            continue;
+        }
+        for chunk in chars.chunks(width) {
+            lines.push(format!("{}{}", indent, chunk.iter().collect::<String>()));
+        }
+    }
+}
+
+/// Encode a line for a PDF string literal in the standard Courier font.
+/// Characters outside Latin-1 are replaced with '?'.
+fn pdf_string(line: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(line.len() + 2);
+    out.push(b'(');
+    for c in line.chars() {
+        let byte = match c {
+            '·' => b'-',
+            c if (c as u32) < 0x20 => b' ',
+            c if (c as u32) < 0x100 => c as u32 as u8,
+            _ => b'?',
+        };
+        if matches!(byte, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(byte);
+    }
+    out.push(b')');
+    out
+}
+
+/// Lay out lines on pages and serialize a minimal PDF 1.4 document.
+fn write_pdf(lines: &[String]) -> Vec<u8> {
+    let pages: Vec<&[String]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(LINES_PER_PAGE).collect()
+    };
+
+    // Objects: 1 catalog, 2 page tree, 3 font, then (page, content) pairs
+    let kids = (0..pages.len())
+        .map(|i| format!("{} 0 R", 4 + i * 2))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()).into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+
+    for (i, page) in pages.iter().enumerate() {
+        let mut stream = format!(
+            "BT /F1 {} Tf {} TL {} {} Td\n",
+            FONT_SIZE,
+            LINE_HEIGHT,
+            MARGIN,
+            PAGE_HEIGHT - MARGIN
+        )
+        .into_bytes();
+        for line in page.iter() {
+            stream.extend(pdf_string(line));
+            stream.extend_from_slice(b" Tj T*\n");
+        }
+        stream.extend_from_slice(b"ET");
+
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                5 + i * 2
+            )
+            .into_bytes(),
+        );
+        let mut content = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+        content.extend(stream);
+        content.extend_from_slice(b"\nendstream");
+        objects.push(content);
+    }
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, obj) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        out.extend_from_slice(obj);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+    let xref_offset = out.len();
+    out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        out.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    out.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        )
+        .into_bytes(),
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_tape() -> Vec<AgentMessage> {
+        vec![
+            AgentMessage::Llm(Message::user("What's in **notes.txt**? <script>x</script>")),
+            AgentMessage::Llm(Message::Assistant {
+                content: vec![Content::ToolCall {
+                    id: "tc-1".to_string(),
+                    name: "read_file".to_string(),
+                    arguments: serde_json::json!({"path": "notes.txt"}),
+                }],
+                stop_reason: StopReason::ToolUse,
+                model: "mock".to_string(),
+                provider: "mock".to_string(),
+                usage: Usage {
+                    input: 100,
+                    output: 20,
+                    ..Usage::default()
+                },
+                timestamp: 0,
+                error_message: None,
+            }),
+            AgentMessage::Llm(Message::ToolResult {
+                tool_call_id: "tc-1".to_string(),
+                tool_name: "read_file".to_string(),
+                content: vec![Content::Text {
+                    text: "buy milk".to_string(),
+                }],
+                is_error: false,
+                timestamp: 0,
+            }),
+            AgentMessage::Llm(Message::Assistant {
+                content: vec![Content::Text {
+                    text: "It says: `buy milk`".to_string(),
+                }],
+                stop_reason: StopReason::Stop,
+                model: "mock".to_string(),
+                provider: "mock".to_string(),
+                usage: Usage {
+                    input: 150,
+                    output: 10,
+                    ..Usage::default()
+                },
+                timestamp: 0,
+                error_message: None,
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(
+            "html".parse::<TranscriptFormat>().unwrap(),
+            TranscriptFormat::Html
+        );
+        assert_eq!(
+            "PDF".parse::<TranscriptFormat>().unwrap(),
+            TranscriptFormat::Pdf
+        );
+        assert_eq!(
+            "md".parse::<TranscriptFormat>().unwrap(),
+            TranscriptFormat::Markdown
+        );
+        assert!("docx".parse::<TranscriptFormat>().is_err());
+    }
+
+    #[test]
+    fn test_totals() {
+        let totals = Totals::from_messages(&sample_tape());
+        assert_eq!(
+            totals,
+            Totals {
+                turns: 2,
+                tool_calls: 1,
+                input_tokens: 250,
+                output_tokens: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html("tg-1", &sample_tape());
+        assert!(html.contains("<strong>notes.txt</strong>"));
+        assert!(html.contains("<code>buy milk</code>"));
+        assert!(html.contains("<summary>Tool call: read_file</summary>"));
+        assert!(html.contains("<summary>Tool result: read_file</summary>"));
+        assert!(html.contains("250 input tokens"));
+        // Raw HTML from the chat is escaped, not rendered
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_render_markdown_and_json() {
+        let md = render_markdown("tg-1", &sample_tape());
+        assert!(md.starts_with("# Transcript tg-1\n"));
+        assert!(md.contains("## Assistant (mock)\n\nIt says: `buy milk`"));
+        assert!(md.contains("Tool call: `read_file`\n\n```json"));
+        assert!(md.contains("Tool result: `read_file`"));
+        assert!(md.trim_end().ends_with("30 output tokens"));
+
+        let json = render(TranscriptFormat::Json, "tg-1", &sample_tape());
+        let tape: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(tape["session_id"], "tg-1");
+        let messages: Vec<AgentMessage> = serde_json::from_value(tape["messages"].clone()).unwrap();
+        assert_eq!(messages.len(), sample_tape().len());
+    }
+
+    #[test]
+    fn test_render_pdf_structure() {
+        let pdf = render_pdf("tg-1", &sample_tape());
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+        assert!(text.contains("(USER) Tj"));
+        assert!(text.contains("[tool call] read_file"));
+        assert!(text.contains("/Count 1"));
+
+        // startxref points at the xref table
+        let start: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(text[start..].starts_with("xref"));
+    }
+
+    #[test]
+    fn test_pdf_paginates_and_escapes() {
+        let long = (0..LINES_PER_PAGE * 2)
+            .map(|i| format!("line (paren) {}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let tape = vec![AgentMessage::Llm(Message::user(&long))];
+        let text = String::from_utf8_lossy(&render_pdf("s", &tape)).to_string();
+        assert!(text.contains("/Count 3"));
+        assert!(text.contains("line \\(paren\\) 0"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Voice messages turned into text.
+//!
+//! With `[channels.transcription]`, voice messages from Telegram and Discord
+//! are transcribed when their message is answered, by the Whisper API (or a
+//! server offering the same endpoint) or a local whisper binary. The text
+//! goes to the agent marked as spoken; a voice message that can't be
+//! transcribed is listed with the other files instead.
+
+use crate::attachments::SavedAttachment;
+use crate::config::{Config, TranscriptionConfig};
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TranscriptionError {
+    #[error("Unknown transcription provider '{0}' (expected openai or local)")]
+    UnknownProvider(String),
+    #[error("No API key for transcription: set [channels.transcription] api_key")]
+    NoApiKey,
+    #[error("Transcription timed out after {0}s")]
+    Timeout(u64),
+    #[error("Transcription failed: {0}")]
+    Failed(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+/// Text of the voice messages among `attachments`, and the attachments left
+/// for the agent: the others, and voice messages that failed.
+pub async fn transcribe_all(
+    config: &Config,
+    attachments: Vec<SavedAttachment>,
+) -> (Vec<String>, Vec<SavedAttachment>) {
+    let Some(ref transcription) = config.channels.transcription else {
+        return (Vec::new(), attachments);
+    };
+    let mut texts = Vec::new();
+    let mut rest = Vec::new();
+    for attachment in attachments {
+        if !attachment.voice {
+            rest.push(attachment);
+            continue;
+        }
+        match transcribe(transcription, &config.agent, &attachment).await {
+            Ok(text) if !text.is_empty() => texts.push(text),
+            Ok(_) => tracing::info!("Voice message {} has no speech", attachment.name),
+            Err(e) => {
+                tracing::warn!("Failed to transcribe {}: {}", attachment.name, e);
+                rest.push(attachment);
+            }
+        }
+    }
+    (texts, rest)
+}
+
+/// Transcript of one audio file.
+pub async fn transcribe(
+    config: &TranscriptionConfig,
+    agent: &crate::config::AgentConfig,
+    attachment: &SavedAttachment,
+) -> Result<String, TranscriptionError> {
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let text = match config.provider.as_str() {
+        "openai" => {
+            let api_key = match config.api_key {
+                Some(ref key) => key.as_str(),
+                None if agent.provider == "openai" => agent.api_key.as_str(),
+                None => return Err(TranscriptionError::NoApiKey),
+            };
+            tokio::time::timeout(timeout, transcribe_api(config, api_key, attachment))
+                .await
+                .map_err(|_| TranscriptionError::Timeout(config.timeout_secs))??
+        }
+        "local" => tokio::time::timeout(timeout, transcribe_local(config, attachment))
+            .await
+            .map_err(|_| TranscriptionError::Timeout(config.timeout_secs))??,
+        other => return Err(TranscriptionError::UnknownProvider(other.to_string())),
+    };
+    Ok(text.trim().to_string())
+}
+
+/// `POST {base_url}/audio/transcriptions`, the Whisper API.
+async fn transcribe_api(
+    config: &TranscriptionConfig,
+    api_key: &str,
+    attachment: &SavedAttachment,
+) -> Result<String, TranscriptionError> {
+    let data = tokio::fs::read(&attachment.path).await?;
+    let file = reqwest::multipart::Part::bytes(data)
+        .file_name(attachment.name.clone())
+        .mime_str(&attachment.mime_type)?;
+    let mut form = reqwest::multipart::Form::new()
+        .part("file", file)
+        .text(
+            "model",
+            config.model.clone().unwrap_or_else(|| "whisper-1".into()),
+        )
+        .text("response_format", "text");
+    if let Some(ref language) = config.language {
+        form = form.text("language", language.clone());
+    }
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/audio/transcriptions",
+            config.base_url.trim_end_matches('/')
+        ))
+        .bearer_auth(api_key)
+        .multipart(form)
+        .send()
+        .await?;
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(TranscriptionError::Failed(format!("{}: {}", status, body)));
+    }
+    Ok(body)
+}
+
+/// Run the local binary and read the transcript from its output.
+async fn transcribe_local(
+    config: &TranscriptionConfig,
+    attachment: &SavedAttachment,
+) -> Result<String, TranscriptionError> {
+    let output = tokio::process::Command::new(&config.command)
+        .args(local_args(config, attachment))
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(TranscriptionError::Failed(format!(
+            "{} exited with {}: {}",
+            config.command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `args` with `{file}`, `{model}` and `{language}` filled in. Without a
+/// language hint, `{language}` is "auto".
+fn local_args(config: &TranscriptionConfig, attachment: &SavedAttachment) -> Vec<String> {
+    let file = attachment.path.to_string_lossy();
+    config
+        .args
+        .iter()
+        .map(|arg| {
+            arg.replace("{file}", &file)
+                .replace("{model}", config.model.as_deref().unwrap_or(""))
+                .replace("{language}", config.language.as_deref().unwrap_or("auto"))
+        })
+        .collect()
+}
+
+/// Message text with the transcripts of its voice messages in front.
+pub fn with_transcripts(content: &str, texts: &[String]) -> String {
+    let spoken = texts
+        .iter()
+        .map(|text| format!("[Voice message, transcribed]\n{}", text))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if content.is_empty() {
+        spoken
+    } else {
+        format!("{}\n\n{}", spoken, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn with_transcription(transcription: &str) -> Config {
+        parse_config(&format!(
+            "[agent]\nmodel = \"m\"\napi_key = \"k\"\n\n[channels.transcription]\n{}",
+            transcription
+        ))
+        .unwrap()
+    }
+
+    fn voice(path: &str) -> SavedAttachment {
+        SavedAttachment {
+            name: "voice.ogg".into(),
+            mime_type: "audio/ogg".into(),
+            path: path.into(),
+            size: 4,
+            voice: true,
+        }
+    }
+
+    #[test]
+    fn test_local_args() {
+        let config = with_transcription("provider = \"local\"\nmodel = \"base.bin\"");
+        let transcription = config.channels.transcription.unwrap();
+        assert_eq!(
+            local_args(&transcription, &voice("/tmp/v.ogg")),
+            vec![
+                "-m",
+                "base.bin",
+                "-f",
+                "/tmp/v.ogg",
+                "-l",
+                "auto",
+                "-nt",
+                "-np"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_all() {
+        let config = with_transcription(
+            "provider = \"local\"\ncommand = \"echo\"\nargs = [\"hello\", \"{language}\"]\nlanguage = \"de\"",
+        );
+        let file = SavedAttachment {
+            voice: false,
+            ..voice("/tmp/report.pdf")
+        };
+        let (texts, rest) = transcribe_all(&config, vec![voice("/tmp/v.ogg"), file.clone()]).await;
+        assert_eq!(texts, vec!["hello de"]);
+        assert_eq!(rest, vec![file]);
+
+        // A voice message that can't be transcribed is kept as a file
+        let config = with_transcription("provider = \"local\"\ncommand = \"false\"");
+        let (texts, rest) = transcribe_all(&config, vec![voice("/tmp/v.ogg")]).await;
+        assert!(texts.is_empty());
+        assert_eq!(rest.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_openai_needs_a_key() {
+        let config = with_transcription("provider = \"openai\"");
+        let result = transcribe(
+            config.channels.transcription.as_ref().unwrap(),
+            &config.agent,
+            &voice("/tmp/v.ogg"),
+        )
+        .await;
+        assert!(matches!(result, Err(TranscriptionError::NoApiKey)));
+    }
+
+    #[test]
+    fn test_with_transcripts() {
+        let texts = vec!["Remind me at five".to_string()];
+        assert_eq!(
+            with_transcripts("", &texts),
+            "[Voice message, transcribed]\nRemind me at five"
+        );
+        assert_eq!(
+            with_transcripts("see above", &texts),
+            "[Voice message, transcribed]\nRemind me at five\n\nsee above"
+        );
+    }
+}
@@ -1,10 +1,12 @@
 use crate::channels::coalesce::SharedDebounce;
 use crate::conductor::Conductor;
 use crate::config::{self, Config};
+use crate::routing::Router;
 use crate::security::SecurityPolicy;
 use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Watches the config file for changes and applies hot-reloadable settings.
 pub struct ConfigWatcher {
@@ -83,6 +85,9 @@ pub struct ConfigDiff {
     pub budget_changed: bool,
     pub security_changed: bool,
     pub debounce_changed: bool,
+    pub routing_changed: bool,
+    /// Channels whose adapter is added, removed or rebuilt.
+    pub channels_changed: Vec<&'static str>,
     pub restart_required: Vec<&'static str>,
 }
 
@@ -91,11 +96,13 @@ pub fn diff_configs(old: &Config, new: &Config) -> ConfigDiff {
     let mut restart_required = Vec::new();
 
     // Check restart-required fields
-    if old.agent.provider != new.agent.provider
-        || old.agent.model != new.agent.model
-        || old.agent.api_key != new.agent.api_key
+    if old.agent.provider != new.agent.provider || old.agent.model != new.agent.model {
+        restart_required.push("agent provider/model");
+    }
+    if old.agent.base_url != new.agent.base_url
+        || old.agent.provider_options != new.agent.provider_options
     {
-        restart_required.push("agent provider/model/api_key");
+        restart_required.push("agent.base_url/provider_options");
     }
     if old.agent.max_tokens != new.agent.max_tokens {
         restart_required.push("agent.max_tokens");
@@ -103,22 +110,52 @@ pub fn diff_configs(old: &Config, new: &Config) -> ConfigDiff {
     if old.agent.thinking != new.agent.thinking {
         restart_required.push("agent.thinking");
     }
+    if old.agent.thinking_rules != new.agent.thinking_rules {
+        restart_required.push("agent.thinking_rules");
+    }
+    if old.agent.native_tools != new.agent.native_tools {
+        restart_required.push("agent.native_tools");
+    }
+    if old.agent.retry != new.agent.retry {
+        restart_required.push("agent.retry");
+    }
+    if old.agent.fallback_providers != new.agent.fallback_providers {
+        restart_required.push("agent.fallback_providers");
+    }
+    if old.agent.tool_progress != new.agent.tool_progress {
+        restart_required.push("agent.tool_progress");
+    }
+    if old.agent.ask_user != new.agent.ask_user {
+        restart_required.push("agent.ask_user");
+    }
+    if old.agent.parallel_sessions != new.agent.parallel_sessions {
+        restart_required.push("agent.parallel_sessions");
+    }
     if old.persistence != new.persistence {
-        restart_required.push("persistence.db_path");
+        restart_required.push("persistence.*");
     }
     if old.web != new.web {
         restart_required.push("web.*");
     }
-    // Channel tokens require reconnection
-    if old.channels.telegram.as_ref().map(|t| &t.bot_token)
-        != new.channels.telegram.as_ref().map(|t| &t.bot_token)
-    {
-        restart_required.push("channels.telegram.bot_token");
+    if old.webhooks != new.webhooks {
+        restart_required.push("webhooks.*");
     }
-    if old.channels.discord.as_ref().map(|d| &d.bot_token)
-        != new.channels.discord.as_ref().map(|d| &d.bot_token)
-    {
-        restart_required.push("channels.discord.bot_token");
+    if old.onboarding != new.onboarding {
+        restart_required.push("onboarding.enabled");
+    }
+    if old.background != new.background {
+        restart_required.push("background.*");
+    }
+    // These inboxes are handed to the web server when it starts
+    if old.channels.webhook != new.channels.webhook {
+        restart_required.push("channels.webhook");
+    }
+    if old.channels.web != new.channels.web {
+        restart_required.push("channels.web");
+    }
+    // The mail sender is built with the scheduler's delivery loop
+    if old.channels.email != new.channels.email {
+        restart_required.push("channels.email");
     }
     // Injection detector is baked into Agent at startup — cannot hot-reload
     if old.security.injection != new.security.injection {
@@ -129,71 +166,146 @@ pub fn diff_configs(old: &Config, new: &Config) -> ConfigDiff {
         budget_changed: old.agent.budget != new.agent.budget,
         security_changed: old.security != new.security,
         debounce_changed: debounce_changed(old, new),
+        routing_changed: old.routing != new.routing,
+        channels_changed: channels_changed(old, new),
         restart_required,
     }
 }
 
+/// Channels whose adapter was built from settings that changed. Settings
+/// read for each message (greeting, debounce, citations, ...) don't count,
+/// so editing them doesn't reconnect the bot.
+fn channels_changed(old: &Config, new: &Config) -> Vec<&'static str> {
+    let (o, n) = (&old.channels, &new.channels);
+    let limit_changed = old.attachments.download_limit() != new.attachments.download_limit();
+    let mut changed = Vec::new();
+    if o.telegram
+        .as_ref()
+        .map(|c| (&c.bot_token, &c.allowed_senders, &c.filters))
+        != n.telegram
+            .as_ref()
+            .map(|c| (&c.bot_token, &c.allowed_senders, &c.filters))
+        || (limit_changed && n.telegram.is_some())
+    {
+        changed.push("telegram");
+    }
+    if o.discord.as_ref().map(|c| {
+        (
+            &c.bot_token,
+            &c.allowed_guilds,
+            &c.allowed_users,
+            &c.routing,
+            &c.filters,
+        )
+    }) != n.discord.as_ref().map(|c| {
+        (
+            &c.bot_token,
+            &c.allowed_guilds,
+            &c.allowed_users,
+            &c.routing,
+            &c.filters,
+        )
+    }) || (limit_changed && n.discord.is_some())
+    {
+        changed.push("discord");
+    }
+    if o.slack.as_ref().map(|c| {
+        (
+            &c.bot_token,
+            &c.app_token,
+            &c.allowed_channels,
+            &c.allowed_users,
+            &c.filters,
+        )
+    }) != n.slack.as_ref().map(|c| {
+        (
+            &c.bot_token,
+            &c.app_token,
+            &c.allowed_channels,
+            &c.allowed_users,
+            &c.filters,
+        )
+    }) || (limit_changed && n.slack.is_some())
+    {
+        changed.push("slack");
+    }
+    if o.signal
+        .as_ref()
+        .map(|c| (&c.endpoint, &c.account, &c.allowed_numbers, &c.filters))
+        != n.signal
+            .as_ref()
+            .map(|c| (&c.endpoint, &c.account, &c.allowed_numbers, &c.filters))
+    {
+        changed.push("signal");
+    }
+    changed
+}
+
 fn debounce_changed(old: &Config, new: &Config) -> bool {
-    old.channels.telegram.as_ref().map(|t| t.debounce_ms)
-        != new.channels.telegram.as_ref().map(|t| t.debounce_ms)
-        || old.channels.discord.as_ref().map(|d| d.debounce_ms)
-            != new.channels.discord.as_ref().map(|d| d.debounce_ms)
-        || old.channels.slack.as_ref().map(|s| s.debounce_ms)
-            != new.channels.slack.as_ref().map(|s| s.debounce_ms)
+    old.channels.debounce() != new.channels.debounce()
 }
 
-/// Apply hot-reloadable config changes to the running system.
+/// Apply hot-reloadable config changes to the running system, except for
+/// the conductors: each gets them through [`reload_conductor`].
 pub fn apply_hot_reload(
     diff: &ConfigDiff,
     new_config: &Config,
-    conductor: &mut Conductor,
+    router: &mut Arc<Router>,
     shared_debounce: &SharedDebounce,
 ) {
+    if diff.debounce_changed {
+        shared_debounce.write().unwrap().per_channel = new_config.channels.debounce();
+        tracing::info!("Debounce timings reloaded");
+    }
+
+    // API keys go to every provider through the shared key ring
+    crate::conductor::keys::configure(&new_config.agent);
+
+    if diff.routing_changed {
+        // Keep the old rules if the new ones don't compile
+        match Router::from_config(&new_config.routing) {
+            Ok(new_router) => {
+                *router = Arc::new(new_router);
+                tracing::info!("Routing rules reloaded");
+            }
+            Err(e) => tracing::error!("Routing rules not reloaded: {}", e),
+        }
+    }
+
+    for field in &diff.restart_required {
+        tracing::warn!("Config change requires restart: {}", field);
+    }
+}
+
+/// Apply hot-reloadable config changes to one conductor.
+pub fn reload_conductor(diff: &ConfigDiff, new_config: &Config, conductor: &mut Conductor) {
     if diff.budget_changed {
         conductor.update_budget(
             new_config.agent.budget.max_tokens_per_day,
             new_config.agent.budget.max_turns_per_session,
+            new_config.agent.budget.max_cost_per_day,
         );
     }
 
     if diff.security_changed {
-        let new_policy = SecurityPolicy::from_config(&new_config.security);
+        let new_policy = SecurityPolicy::from_config(&new_config.security)
+            .with_readable_dir(new_config.uploads_dir());
         conductor.update_security(new_policy);
     }
 
-    if diff.debounce_changed {
-        let mut debounce = shared_debounce.write().unwrap();
-        debounce.per_channel.clear();
-        if let Some(ref tg) = new_config.channels.telegram {
-            debounce
-                .per_channel
-                .insert("telegram".into(), Duration::from_millis(tg.debounce_ms));
-        }
-        if let Some(ref dc) = new_config.channels.discord {
-            debounce
-                .per_channel
-                .insert("discord".into(), Duration::from_millis(dc.debounce_ms));
-        }
-        if let Some(ref sl) = new_config.channels.slack {
-            debounce
-                .per_channel
-                .insert("slack".into(), Duration::from_millis(sl.debounce_ms));
-        }
-        tracing::info!("Debounce timings reloaded");
-    }
-
-    // Always update group catchup (cheap no-op if unchanged)
+    // Always update group catchup, persona fragments, skills and temperature
+    // (cheap no-op if unchanged)
     conductor.update_max_group_catchup(new_config.agent.context.max_group_catchup_messages);
-
-    for field in &diff.restart_required {
-        tracing::warn!("Config change requires restart: {}", field);
-    }
+    conductor.update_persona_fragments(&new_config.agent.persona_fragments);
+    conductor.update_skills(&new_config.agent.disabled_skills);
+    conductor.update_default_temperature(new_config.agent.temperature);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
+    use std::time::Duration;
 
     #[test]
     fn test_watcher_detects_change() {
@@ -376,9 +488,57 @@ max_tokens_per_day = 100000
         assert!(!diff.budget_changed);
         assert!(!diff.security_changed);
         assert!(!diff.debounce_changed);
+        assert!(!diff.routing_changed);
+        assert!(diff.channels_changed.is_empty());
         assert!(diff.restart_required.is_empty());
     }
 
+    #[test]
+    fn test_diff_channels_changed() {
+        let base = r#"
+[agent]
+model = "test"
+api_key = "key"
+[channels.telegram]
+bot_token = "tg-1"
+[channels.slack]
+bot_token = "xoxb"
+app_token = "xapp"
+"#;
+        let old = config::parse_config(base).unwrap();
+
+        // A new token, a removed channel and an added one
+        let new = config::parse_config(
+            r#"
+[agent]
+model = "test"
+api_key = "key"
+[channels.telegram]
+bot_token = "tg-2"
+[channels.signal]
+account = "+15550100"
+"#,
+        )
+        .unwrap();
+        let diff = diff_configs(&old, &new);
+        assert_eq!(diff.channels_changed, vec!["telegram", "slack", "signal"]);
+        assert!(diff.restart_required.is_empty());
+
+        // Settings read per message don't restart the adapter
+        let new = config::parse_config(&format!(
+            "{}{}",
+            base.replace(
+                "bot_token = \"tg-1\"",
+                "bot_token = \"tg-1\"\ngreeting = \"Hi\""
+            ),
+            "[channels.web]\ntoken = \"t\"\n"
+        ))
+        .unwrap();
+        let diff = diff_configs(&old, &new);
+        assert!(diff.channels_changed.is_empty());
+        assert_eq!(diff.restart_required, vec!["channels.web"]);
+    }
+
     #[test]
     fn test_diff_injection_requires_restart() {
         let old = config::parse_config(
@@ -0,0 +1,113 @@
+//! Aggregate usage counts for status pages.
+//!
+//! With `[web.analytics]` configured, `GET /api/analytics` reports messages
+//! and tokens per day and channel, and nothing else: no content, no session
+//! or sender IDs. A day on a channel with fewer than `min_senders` distinct
+//! senders is left out (it would describe one or two people), and only the
+//! number of buckets left out is reported. Token counts are rounded down to
+//! `token_step`, so a single reply can't be read off the difference between
+//! two requests. The endpoint needs no token; everything else under `/api`
+//! still exposes full detail and should stay on a private address.
+
+use crate::config::AnalyticsConfig;
+use crate::db::analytics::DailyActivity;
+use serde::Serialize;
+
+/// One reported bucket.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DayCount {
+    pub date: String,
+    pub channel: String,
+    pub messages: u64,
+    pub tokens: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Analytics {
+    pub days: Vec<DayCount>,
+    /// Buckets left out for having too few senders.
+    pub suppressed: usize,
+}
+
+/// Start (ms) of the UTC day `days - 1` days ago, so `days` covers today
+/// and the days before it.
+pub fn since_ms(days: u32) -> u64 {
+    let today = chrono::Utc::now().date_naive();
+    let first = today - chrono::Duration::days(days.saturating_sub(1) as i64);
+    first
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+        .timestamp_millis() as u64
+}
+
+/// The publishable part of `activity`.
+pub fn summarize(activity: Vec<DailyActivity>, config: &AnalyticsConfig) -> Analytics {
+    let step = config.token_step.max(1);
+    let mut days = Vec::new();
+    let mut suppressed = 0;
+    for day in activity {
+        // Token-only buckets (cron jobs, webhooks) have no senders to protect
+        if day.messages > 0 && day.senders < config.min_senders {
+            suppressed += 1;
+            continue;
+        }
+        days.push(DayCount {
+            date: day.date,
+            channel: day.channel,
+            messages: day.messages,
+            tokens: day.tokens / step * step,
+        });
+    }
+    Analytics { days, suppressed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(channel: &str, messages: u64, senders: u64, tokens: u64) -> DailyActivity {
+        DailyActivity {
+            date: "2026-10-16".into(),
+            channel: channel.into(),
+            messages,
+            senders,
+            tokens,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let config = AnalyticsConfig {
+            days: 30,
+            min_senders: 3,
+            token_step: 1000,
+        };
+        let analytics = summarize(
+            vec![
+                day("discord", 40, 5, 12_345),
+                day("telegram", 9, 1, 4_000),
+                day("webhook", 0, 0, 2_500),
+            ],
+            &config,
+        );
+        assert_eq!(analytics.suppressed, 1);
+        assert_eq!(
+            analytics.days,
+            vec![
+                DayCount {
+                    date: "2026-10-16".into(),
+                    channel: "discord".into(),
+                    messages: 40,
+                    tokens: 12_000,
+                },
+                DayCount {
+                    date: "2026-10-16".into(),
+                    channel: "webhook".into(),
+                    messages: 0,
+                    tokens: 2_000,
+                },
+            ]
+        );
+    }
+}
@@ -1,6 +1,12 @@
+use super::push::Topic;
 use super::AppState;
+use crate::db::annotations::Annotation;
+use crate::db::deliveries::DeliveryEntry;
+use crate::db::drafts::DraftDecision;
+use crate::db::queue::FailedEntry;
+use crate::db::worker_runs::WorkerRun;
 use axum::extract::{Path, Query, State};
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Json, Router};
 use serde::{Deserialize, Serialize};
 
@@ -8,9 +14,39 @@ pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/sessions", get(list_sessions))
         .route("/sessions/{id}/messages", get(get_session_messages))
+        .route("/sessions/{id}/transcript", get(get_session_transcript))
+        .route(
+            "/sessions/{id}/messages/{idx}/annotate",
+            post(annotate_message),
+        )
+        .route("/sessions/{id}/annotations", get(session_annotations))
+        .route("/annotations", get(export_annotations))
         .route("/queue", get(queue_status))
+        .route("/queue/failures", get(queue_failures))
+        .route("/queue/retry", post(queue_retry))
         .route("/budget", get(budget_status))
+        .route("/memory/cache", get(memory_cache_status))
         .route("/audit", get(audit_log))
+        .route("/audit/daily", get(audit_daily))
+        .route("/deliveries", get(deliveries))
+        .route("/workers/runs", get(worker_runs))
+        .route("/push/key", get(push_key))
+        .route("/push/subscribe", post(push_subscribe))
+        .route("/push/unsubscribe", post(push_unsubscribe))
+        .route("/admin/pause", get(list_pauses).post(pause))
+        .route("/admin/resume", post(resume))
+        .route("/drafts", get(list_drafts))
+        .route("/drafts/{id}/approve", post(approve_draft))
+        .route("/drafts/{id}/edit", post(edit_draft))
+        .route("/drafts/{id}/reject", post(reject_draft))
+        .route("/query", post(query))
+        .route("/analytics", get(analytics))
+        .route("/channels/webhook", post(webhook_message))
+        .route("/chat", post(chat_message))
+        .route("/cron", get(cron_jobs))
+        .route("/skills", get(list_skills))
+        .route("/skills/{name}/enable", post(enable_skill))
+        .route("/skills/{name}/disable", post(disable_skill))
 }
 
 #[derive(Serialize)]
@@ -44,14 +80,230 @@ async fn get_session_messages(
     Ok(Json(json))
 }
 
+#[derive(Deserialize)]
+struct TranscriptQuery {
+    format: Option<String>,
+}
+
+/// Rendered transcript as a file download (`?format=html|pdf|markdown|json`, default html).
+async fn get_session_transcript(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(q): Query<TranscriptQuery>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    let format: crate::transcript::TranscriptFormat =
+        match q.format.as_deref().unwrap_or("html").parse() {
+            Ok(f) => f,
+            Err(e) => return Ok((StatusCode::BAD_REQUEST, e.to_string()).into_response()),
+        };
+    let messages = state.db.tape_load_messages(&id).await?;
+    if messages.is_empty() {
+        return Ok((StatusCode::NOT_FOUND, "Session not found").into_response());
+    }
+
+    let body = crate::transcript::render(format, &id, &messages);
+    // Session IDs are channel-generated, but keep the filename header-safe
+    let filename: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let disposition = format!(
+        "attachment; filename=\"transcript-{}.{}\"",
+        filename,
+        format.extension()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct AnnotateRequest {
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Label one tape message with a note and/or tags. The message is copied
+/// into the annotation, so it survives compaction.
+async fn annotate_message(
+    State(state): State<AppState>,
+    Path((id, idx)): Path<(String, usize)>,
+    Json(req): Json<AnnotateRequest>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    let note = req.note.trim();
+    let mut tags: Vec<String> = Vec::new();
+    for tag in req.tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    if note.is_empty() && tags.is_empty() {
+        return Ok((StatusCode::BAD_REQUEST, "A note or a tag is required").into_response());
+    }
+    let messages = state.db.tape_load_messages(&id).await?;
+    let Some(message) = messages.get(idx) else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let annotation_id = state
+        .db
+        .annotation_add(&id, idx, note, &tags, message)
+        .await?;
+    tracing::info!("Annotated message {} of {} from the web API", idx, id);
+    Ok(Json(Annotation {
+        id: annotation_id,
+        session_id: id,
+        message_idx: idx,
+        note: note.to_string(),
+        tags,
+        message: serde_json::to_value(message)?,
+        created_at: crate::db::now_ms(),
+    })
+    .into_response())
+}
+
+async fn session_annotations(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<Annotation>>, AppError> {
+    Ok(Json(state.db.annotation_list(Some(&id), None).await?))
+}
+
+#[derive(Deserialize)]
+struct AnnotationQuery {
+    session: Option<String>,
+    tag: Option<String>,
+    format: Option<String>,
+}
+
+/// All annotations, optionally of one session or with one tag. With
+/// `?format=jsonl`, a download with one annotation per line.
+async fn export_annotations(
+    State(state): State<AppState>,
+    Query(q): Query<AnnotationQuery>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    let annotations = state
+        .db
+        .annotation_list(q.session.as_deref(), q.tag.as_deref())
+        .await?;
+    match q.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(annotations).into_response()),
+        "jsonl" => {
+            let mut body = String::new();
+            for annotation in &annotations {
+                body.push_str(&serde_json::to_string(annotation)?);
+                body.push('\n');
+            }
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "application/x-ndjson"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"annotations.jsonl\"",
+                    ),
+                ],
+                body,
+            )
+                .into_response())
+        }
+        other => Ok((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown format '{}' (expected json or jsonl)", other),
+        )
+            .into_response()),
+    }
+}
+
 #[derive(Serialize)]
 struct QueueStatus {
     pending: usize,
+    /// Messages that failed processing in the last 24 hours.
+    failed_24h: u64,
 }
 
 async fn queue_status(State(state): State<AppState>) -> Result<Json<QueueStatus>, AppError> {
     let pending = state.db.queue_pending_count().await?;
-    Ok(Json(QueueStatus { pending }))
+    let since = crate::db::now_ms().saturating_sub(24 * 60 * 60 * 1000);
+    let failed_24h = state.db.queue_failed_count(since).await?;
+    Ok(Json(QueueStatus {
+        pending,
+        failed_24h,
+    }))
+}
+
+#[derive(Deserialize)]
+struct FailureQuery {
+    limit: Option<usize>,
+}
+
+/// Recent failed messages with their failure context.
+async fn queue_failures(
+    State(state): State<AppState>,
+    Query(q): Query<FailureQuery>,
+) -> Result<Json<Vec<FailedEntry>>, AppError> {
+    let limit = q.limit.unwrap_or(50);
+    Ok(Json(state.db.queue_failures(limit).await?))
+}
+
+#[derive(Deserialize)]
+struct RetryRequest {
+    /// How far back to look, e.g. "2h".
+    since: String,
+    /// Time between two retries, e.g. "10s".
+    pace: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RetryResponse {
+    requeued: usize,
+}
+
+/// Queue the messages that failed in the last `since` for another attempt,
+/// `pace` apart (`yoclaw queue retry-failed`).
+async fn queue_retry(
+    State(state): State<AppState>,
+    Json(req): Json<RetryRequest>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    let parse = |s: &str| crate::retry::parse_duration(s);
+    let since = match parse(&req.since) {
+        Ok(d) => d,
+        Err(e) => return Ok((StatusCode::BAD_REQUEST, e.to_string()).into_response()),
+    };
+    let pace = match req.pace.as_deref().map(parse).transpose() {
+        Ok(d) => d.unwrap_or(crate::retry::DEFAULT_PACE),
+        Err(e) => return Ok((StatusCode::BAD_REQUEST, e.to_string()).into_response()),
+    };
+    let requeued = crate::retry::retry_failed(&state.db, since, pace).await?;
+    tracing::info!(
+        "Queued {} failed message(s) again from the web API",
+        requeued
+    );
+    Ok(Json(RetryResponse { requeued }).into_response())
 }
 
 #[derive(Serialize)]
@@ -59,19 +311,47 @@ struct BudgetStatus {
     tokens_used_today: u64,
     daily_limit: Option<u64>,
     remaining: Option<u64>,
+    cost_today_usd: f64,
+    cost_week_usd: f64,
+    max_cost_per_day: Option<f64>,
+    cost_remaining_usd: Option<f64>,
 }
 
 async fn budget_status(State(state): State<AppState>) -> Result<Json<BudgetStatus>, AppError> {
     let used = state.db.audit_token_usage_today().await?;
     let limit = state.config.agent.budget.max_tokens_per_day;
     let remaining = limit.map(|l| l.saturating_sub(used));
+    let cost_today = state.db.audit_cost_today().await?;
+    let max_cost = state.config.agent.budget.max_cost_per_day;
     Ok(Json(BudgetStatus {
         tokens_used_today: used,
         daily_limit: limit,
         remaining,
+        cost_today_usd: cost_today,
+        cost_week_usd: state.db.audit_cost_days(7).await?,
+        max_cost_per_day: max_cost,
+        cost_remaining_usd: max_cost.map(|max| (max - cost_today).max(0.0)),
     }))
 }
 
+#[derive(Serialize)]
+struct MemoryCacheStatus {
+    hits: u64,
+    misses: u64,
+    hit_rate: f64,
+    entries: usize,
+}
+
+async fn memory_cache_status(State(state): State<AppState>) -> Json<MemoryCacheStatus> {
+    let stats = state.db.memory_cache_stats();
+    Json(MemoryCacheStatus {
+        hits: stats.hits,
+        misses: stats.misses,
+        hit_rate: stats.hit_rate(),
+        entries: stats.entries,
+    })
+}
+
 #[derive(Deserialize)]
 struct AuditQuery {
     session: Option<String>,
@@ -110,6 +390,486 @@ async fn audit_log(
     Ok(Json(result))
 }
 
+#[derive(Deserialize)]
+struct AuditDailyQuery {
+    days: Option<u32>,
+}
+
+/// Daily counts of audit entries past `[audit] keep_days`.
+async fn audit_daily(
+    State(state): State<AppState>,
+    Query(q): Query<AuditDailyQuery>,
+) -> Result<Json<Vec<crate::db::audit::AuditDay>>, AppError> {
+    let days = q.days.unwrap_or(365) as i64;
+    let since = chrono::Utc::now().date_naive() - chrono::Duration::days(days);
+    let rows = state
+        .db
+        .audit_daily(&since.format("%Y-%m-%d").to_string())
+        .await?;
+    Ok(Json(rows))
+}
+
+#[derive(Deserialize)]
+struct DeliveryQuery {
+    #[serde(default)]
+    failed: bool,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct DeliveryLog {
+    /// Failed or rate-limited deliveries in the last 24 hours.
+    failed_24h: u64,
+    entries: Vec<DeliveryEntry>,
+}
+
+async fn deliveries(
+    State(state): State<AppState>,
+    Query(q): Query<DeliveryQuery>,
+) -> Result<Json<DeliveryLog>, AppError> {
+    let limit = q.limit.unwrap_or(50);
+    let since = crate::db::now_ms().saturating_sub(24 * 60 * 60 * 1000);
+    Ok(Json(DeliveryLog {
+        failed_24h: state.db.delivery_failed_count(since).await?,
+        entries: state.db.delivery_query(q.failed, limit).await?,
+    }))
+}
+
+#[derive(Deserialize)]
+struct WorkerRunQuery {
+    session: Option<String>,
+    #[serde(default)]
+    failed: bool,
+    limit: Option<usize>,
+}
+
+/// Recent worker runs with their transcripts, newest first.
+async fn worker_runs(
+    State(state): State<AppState>,
+    Query(q): Query<WorkerRunQuery>,
+) -> Result<Json<Vec<WorkerRun>>, AppError> {
+    let limit = q.limit.unwrap_or(20);
+    Ok(Json(
+        state
+            .db
+            .worker_run_list(q.session.as_deref(), q.failed, limit)
+            .await?,
+    ))
+}
+
+#[derive(Serialize)]
+struct PushKey {
+    public_key: String,
+    topics: Vec<&'static str>,
+}
+
+/// VAPID public key for `pushManager.subscribe`; 404 when push isn't configured.
+async fn push_key(State(state): State<AppState>) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    match state.config.web.push {
+        Some(ref push) => Json(PushKey {
+            public_key: push.vapid_public_key.clone(),
+            topics: Topic::ALL.iter().map(|t| t.as_str()).collect(),
+        })
+        .into_response(),
+        None => (
+            axum::http::StatusCode::NOT_FOUND,
+            "Push notifications are not configured",
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SubscriptionKeys {
+    p256dh: String,
+    auth: String,
+}
+
+/// A browser `PushSubscription` as JSON, plus the topics to receive.
+#[derive(Deserialize)]
+struct SubscribeRequest {
+    endpoint: String,
+    keys: SubscriptionKeys,
+    #[serde(default)]
+    topics: Option<Vec<String>>,
+}
+
+/// Whether the request carries the `[web] admin_token`. Routes that change
+/// state answer 401 otherwise, and always when no token is set.
+fn is_admin(state: &AppState, headers: &axum::http::HeaderMap) -> bool {
+    let auth = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    state
+        .config
+        .web
+        .admin_token
+        .as_deref()
+        .is_some_and(|token| super::console::authorized(token, auth))
+}
+
+async fn push_subscribe(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<SubscribeRequest>,
+) -> Result<axum::http::StatusCode, AppError> {
+    use axum::http::StatusCode;
+
+    if state.config.web.push.is_none() {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+    if !is_admin(&state, &headers) {
+        return Ok(StatusCode::UNAUTHORIZED);
+    }
+    // The notifier posts to the endpoint, so it must be a real push service
+    if !super::push::known_endpoint(&req.endpoint) {
+        return Ok(StatusCode::BAD_REQUEST);
+    }
+    // All topics unless the browser picked some
+    let topics: Vec<String> = match req.topics {
+        Some(topics) => {
+            if topics.iter().any(|t| Topic::parse(t).is_none()) {
+                return Ok(StatusCode::BAD_REQUEST);
+            }
+            topics
+        }
+        None => Topic::ALL.iter().map(|t| t.as_str().to_string()).collect(),
+    };
+    state
+        .db
+        .push_subscribe(&req.endpoint, &req.keys.p256dh, &req.keys.auth, &topics)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeRequest {
+    endpoint: String,
+}
+
+async fn push_unsubscribe(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<UnsubscribeRequest>,
+) -> Result<axum::http::StatusCode, AppError> {
+    if !is_admin(&state, &headers) {
+        return Ok(axum::http::StatusCode::UNAUTHORIZED);
+    }
+    state.db.push_unsubscribe(&req.endpoint).await?;
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+async fn list_pauses(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<crate::db::pause::Pause>>, AppError> {
+    Ok(Json(state.db.pause_list().await?))
+}
+
+#[derive(Deserialize, Default)]
+struct PauseRequest {
+    /// One session instead of the whole daemon.
+    session: Option<String>,
+    reason: Option<String>,
+}
+
+/// Read-only mode: messages are queued but no agent runs until resumed.
+async fn pause(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    body: Option<Json<PauseRequest>>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::response::IntoResponse;
+    if !is_admin(&state, &headers) {
+        return Ok(axum::http::StatusCode::UNAUTHORIZED.into_response());
+    }
+    let req = body.map(|Json(r)| r).unwrap_or_default();
+    let scope = req
+        .session
+        .as_deref()
+        .unwrap_or(crate::db::pause::PAUSE_ALL);
+    let pause = state
+        .db
+        .pause_set(scope, "web", req.reason.as_deref())
+        .await?;
+    tracing::warn!("Paused {} from the web API", scope);
+    Ok(Json(pause).into_response())
+}
+
+async fn resume(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    body: Option<Json<PauseRequest>>,
+) -> Result<axum::http::StatusCode, AppError> {
+    if !is_admin(&state, &headers) {
+        return Ok(axum::http::StatusCode::UNAUTHORIZED);
+    }
+    let req = body.map(|Json(r)| r).unwrap_or_default();
+    let scope = req
+        .session
+        .as_deref()
+        .unwrap_or(crate::db::pause::PAUSE_ALL);
+    if state.db.pause_clear(scope).await? {
+        tracing::info!("Resumed {} from the web API", scope);
+        Ok(axum::http::StatusCode::NO_CONTENT)
+    } else {
+        Ok(axum::http::StatusCode::NOT_FOUND)
+    }
+}
+
+/// Replies in reviewed chats waiting for a decision.
+async fn list_drafts(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<crate::db::drafts::Draft>>, AppError> {
+    Ok(Json(state.db.draft_pending().await?))
+}
+
+#[derive(Deserialize)]
+struct EditDraftRequest {
+    content: String,
+}
+
+async fn approve_draft(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<axum::response::Response, AppError> {
+    decide_draft(&state, &headers, id, DraftDecision::Approve).await
+}
+
+async fn edit_draft(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+    Json(req): Json<EditDraftRequest>,
+) -> Result<axum::response::Response, AppError> {
+    decide_draft(&state, &headers, id, DraftDecision::Edit(req.content)).await
+}
+
+async fn reject_draft(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<axum::response::Response, AppError> {
+    decide_draft(&state, &headers, id, DraftDecision::Reject).await
+}
+
+/// Record a decision; the main loop posts the draft on its next tick.
+async fn decide_draft(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    id: i64,
+    decision: DraftDecision,
+) -> Result<axum::response::Response, AppError> {
+    use axum::response::IntoResponse;
+    if !is_admin(state, headers) {
+        return Ok(axum::http::StatusCode::UNAUTHORIZED.into_response());
+    }
+    match state.db.draft_decide(id, &decision, "web").await? {
+        Some(draft) => {
+            tracing::info!("Draft #{} {} from the web API", id, draft.status);
+            Ok(Json(draft).into_response())
+        }
+        None => Ok(axum::http::StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+/// Skills with whether each is enabled, and by the file or an override.
+async fn list_skills(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<crate::skills::SkillStatus>>, AppError> {
+    Ok(Json(
+        crate::admin::skill_statuses(&state.db, &state.config).await?,
+    ))
+}
+
+async fn enable_skill(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path(name): Path<String>,
+) -> Result<axum::http::StatusCode, AppError> {
+    set_skill(&state, &headers, &name, true).await
+}
+
+async fn disable_skill(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path(name): Path<String>,
+) -> Result<axum::http::StatusCode, AppError> {
+    set_skill(&state, &headers, &name, false).await
+}
+
+/// Store the override; the main loop reloads the config on its next tick.
+async fn set_skill(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    name: &str,
+    enabled: bool,
+) -> Result<axum::http::StatusCode, AppError> {
+    use crate::admin::AdminError;
+    if !is_admin(state, headers) {
+        return Ok(axum::http::StatusCode::UNAUTHORIZED);
+    }
+    match crate::admin::set_skill(&state.db, &state.config, name, enabled).await {
+        Ok((key, value)) => {
+            tracing::info!("Admin override from the web API: {} = {}", key, value);
+            Ok(axum::http::StatusCode::NO_CONTENT)
+        }
+        Err(AdminError::UnknownSkill(_)) => Ok(axum::http::StatusCode::NOT_FOUND),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Cron jobs with their schedules in words and next run times.
+async fn cron_jobs(State(state): State<AppState>) -> Result<Json<serde_json::Value>, AppError> {
+    let jobs = crate::scheduler::cron::list_jobs(&state.db).await?;
+    let listings: Vec<_> = jobs.iter().map(|job| job.listing()).collect();
+    Ok(Json(serde_json::to_value(&listings)?))
+}
+
+#[derive(Deserialize)]
+struct QueryRequest {
+    sql: String,
+}
+
+/// Read-only SQL console; 404 unless `[web.console]` is configured.
+async fn query(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<QueryRequest>,
+) -> Result<axum::response::Response, AppError> {
+    use super::console::{self, ConsoleError};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    let Some(ref config) = state.config.web.console else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let auth = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !console::authorized(&config.token, auth) {
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    }
+
+    let _ = state
+        .db
+        .audit_log(None, "web_query", None, Some(&req.sql), 0)
+        .await;
+    match console::run(&state.db, &req.sql, config.max_rows).await {
+        Ok(result) => Ok(Json(result).into_response()),
+        // Rejected statements and SQL errors are the caller's to fix
+        Err(e @ (ConsoleError::NotReadOnly | ConsoleError::Db(crate::db::DbError::Sqlite(_)))) => {
+            Ok((StatusCode::BAD_REQUEST, e.to_string()).into_response())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+struct AnalyticsQuery {
+    days: Option<u32>,
+}
+
+/// Aggregate counts per day and channel, safe to publish.
+async fn analytics(
+    State(state): State<AppState>,
+    Query(q): Query<AnalyticsQuery>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::response::IntoResponse;
+
+    let Some(ref config) = state.config.web.analytics else {
+        return Ok(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    let days = q.days.unwrap_or(config.days).clamp(1, config.days.max(1));
+    let activity = state
+        .db
+        .daily_activity(super::analytics::since_ms(days))
+        .await?;
+    Ok(Json(super::analytics::summarize(activity, config)).into_response())
+}
+
+/// A message for the agent from another system (`[channels.webhook]`).
+async fn webhook_message(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(msg): Json<crate::channels::webhook::InboundMessage>,
+) -> axum::response::Response {
+    use crate::channels::webhook::WebhookChannelError;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    let Some(ref inbox) = state.webhook else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let auth = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !inbox.authorized(auth) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let submitted = match inbox.submit(msg) {
+        Ok(submitted) => submitted,
+        Err(e @ WebhookChannelError::NotStarted) => {
+            return (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
+        }
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let session_id = submitted.session_id;
+    let Some(reply) = submitted.reply else {
+        return (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({ "session_id": session_id })),
+        )
+            .into_response();
+    };
+    match tokio::time::timeout(inbox.reply_timeout(), reply).await {
+        Ok(Ok(reply)) => {
+            Json(serde_json::json!({ "session_id": session_id, "reply": reply })).into_response()
+        }
+        // Still being worked on; the reply will only be on the tape
+        _ => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({ "session_id": session_id })),
+        )
+            .into_response(),
+    }
+}
+
+/// Queue a message typed into the web UI. The reply streams over `/api/events`.
+async fn chat_message(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<crate::channels::web::ChatRequest>,
+) -> axum::response::Response {
+    use crate::channels::web::WebChatError;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    let Some(ref inbox) = state.web_chat else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let auth = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !inbox.authorized(auth) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    match inbox.submit(req) {
+        Ok(session_id) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({ "session_id": session_id })),
+        )
+            .into_response(),
+        Err(e @ WebChatError::NotStarted) => {
+            (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
 /// Unified error type for API handlers.
 struct AppError(anyhow::Error);
 
@@ -0,0 +1,157 @@
+//! Read-only SQL console for the dashboard.
+//!
+//! With `[web.console]` configured, `POST /api/query` runs one `SELECT` (or
+//! `WITH ... SELECT`) against the live database and returns at most
+//! `max_rows` rows. Requests must carry `Authorization: Bearer <token>`.
+//! Queries run on the read-only reader pool, and SQLite must also report the
+//! prepared statement as read-only, so the keyword check is not the only
+//! guard. Every query is recorded in the audit log.
+
+use crate::db::{Db, DbError};
+use rusqlite::types::ValueRef;
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConsoleError {
+    #[error("Only a single read-only SELECT statement is allowed")]
+    NotReadOnly,
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+/// Result of a console query.
+#[derive(Debug, Serialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// More rows matched than `max_rows`.
+    pub truncated: bool,
+}
+
+/// Whether an `Authorization` header carries the console token.
+pub fn authorized(token: &str, header: Option<&str>) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // An empty token would let any "Bearer " header through
+    !token.is_empty() && crate::webhook::constant_time_eq(token.as_bytes(), given.trim().as_bytes())
+}
+
+/// `sql` without trailing semicolons, if it starts with SELECT or WITH and
+/// is a single statement. A semicolon inside a string literal is refused too.
+fn check(sql: &str) -> Result<&str, ConsoleError> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    if sql.contains(';') {
+        return Err(ConsoleError::NotReadOnly);
+    }
+    let keyword = sql
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    if keyword.eq_ignore_ascii_case("select") || keyword.eq_ignore_ascii_case("with") {
+        Ok(sql)
+    } else {
+        Err(ConsoleError::NotReadOnly)
+    }
+}
+
+/// Run a read-only query, keeping the first `max_rows` rows.
+pub async fn run(db: &Db, sql: &str, max_rows: usize) -> Result<QueryResult, ConsoleError> {
+    let sql = check(sql)?.to_string();
+    db.exec_read(move |conn| {
+        let mut stmt = conn.prepare(&sql)?;
+        if !stmt.readonly() {
+            return Ok(None);
+        }
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        let mut cursor = stmt.query([])?;
+        while let Some(row) = cursor.next()? {
+            if rows.len() >= max_rows {
+                truncated = true;
+                break;
+            }
+            let values = (0..columns.len())
+                .map(|i| row.get_ref(i).map(to_json))
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.push(values);
+        }
+        Ok(Some(QueryResult {
+            columns,
+            rows,
+            truncated,
+        }))
+    })
+    .await?
+    .ok_or(ConsoleError::NotReadOnly)
+}
+
+fn to_json(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+        ValueRef::Blob(b) => format!("<{} bytes>", b.len()).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check("  select 1;  ").unwrap(), "select 1");
+        assert!(check("WITH t AS (SELECT 1) SELECT * FROM t").is_ok());
+        assert!(check("DELETE FROM queue").is_err());
+        assert!(check("PRAGMA writable_schema = 1").is_err());
+        assert!(check("").is_err());
+    }
+
+    #[test]
+    fn test_authorized() {
+        assert!(authorized("s3cret", Some("Bearer s3cret")));
+        assert!(!authorized("s3cret", Some("Bearer wrong")));
+        assert!(!authorized("s3cret", Some("s3cret")));
+        assert!(!authorized("s3cret", None));
+        assert!(!authorized("", Some("Bearer ")));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let db = Db::open_memory().unwrap();
+        for i in 0..3 {
+            db.memory_store(None, &format!("fact {}", i), None, None)
+                .await
+                .unwrap();
+        }
+        let result = run(
+            &db,
+            "SELECT content, 1.5 AS x, NULL AS n FROM memory ORDER BY id",
+            2,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.columns, vec!["content", "x", "n"]);
+        assert_eq!(
+            result.rows[0],
+            vec![
+                serde_json::json!("fact 0"),
+                serde_json::json!(1.5),
+                serde_json::Value::Null
+            ]
+        );
+        assert_eq!(result.rows.len(), 2);
+        assert!(result.truncated);
+
+        // A write disguised behind a CTE is refused by SQLite's own check
+        assert!(matches!(
+            run(&db, "WITH t AS (SELECT 1) DELETE FROM memory", 10).await,
+            Err(ConsoleError::NotReadOnly)
+        ));
+        assert!(run(&db, "SELECT 1; DELETE FROM memory", 10).await.is_err());
+        assert_eq!(db.memory_search("fact", 10).await.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,188 @@
+use super::{api, sse, static_handler, AppState};
+use crate::fleet::{AgentStatus, Fleet, FleetError};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use std::sync::Arc;
+
+/// Combined dashboard for `yoclaw fleet`: the usual dashboard and API for each
+/// agent under `/agents/{name}/`, plus `/api/fleet` for lifecycle controls.
+pub fn build_router(fleet: Arc<Fleet>) -> Router {
+    let first = fleet
+        .agents()
+        .first()
+        .map(|a| format!("/agents/{}/", a.name))
+        .unwrap_or_else(|| "/api/fleet/agents".to_string());
+
+    let mut router = Router::new()
+        .route(
+            "/",
+            get(move || {
+                let first = first.clone();
+                async move { Redirect::temporary(&first) }
+            }),
+        )
+        .nest("/api/fleet", routes().with_state(fleet.clone()));
+    for agent in fleet.agents() {
+        let state = AppState {
+            db: agent.db.clone(),
+            config: agent.config.clone(),
+            event_tx: agent.events.clone(),
+            // The webhook and web chat channels need the agent's own web server
+            webhook: None,
+            web_chat: None,
+        };
+        router = router.nest(
+            &format!("/agents/{}/api", agent.name),
+            api::routes()
+                .route("/events", get(sse::events_handler))
+                .with_state(state),
+        );
+    }
+    router.fallback(static_handler)
+}
+
+fn routes() -> Router<Arc<Fleet>> {
+    Router::new()
+        .route("/agents", get(list_agents))
+        .route("/agents/{name}/start", post(start_agent))
+        .route("/agents/{name}/stop", post(stop_agent))
+        .route("/agents/{name}/restart", post(restart_agent))
+}
+
+async fn list_agents(State(fleet): State<Arc<Fleet>>) -> Json<Vec<AgentStatus>> {
+    Json(fleet.status())
+}
+
+async fn start_agent(State(fleet): State<Arc<Fleet>>, Path(name): Path<String>) -> Response {
+    lifecycle_response(fleet.start(&name))
+}
+
+async fn stop_agent(State(fleet): State<Arc<Fleet>>, Path(name): Path<String>) -> Response {
+    lifecycle_response(fleet.stop(&name).await)
+}
+
+async fn restart_agent(State(fleet): State<Arc<Fleet>>, Path(name): Path<String>) -> Response {
+    lifecycle_response(fleet.restart(&name).await)
+}
+
+fn lifecycle_response(result: Result<(), FleetError>) -> Response {
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e @ FleetError::UnknownAgent(_)) => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(e @ (FleetError::AlreadyRunning(_) | FleetError::NotRunning(_))) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Serve the combined dashboard for `fleet` until the process exits.
+pub async fn start_server(fleet: Arc<Fleet>, bind: &str, port: u16) -> Result<(), anyhow::Error> {
+    let addr = format!("{}:{}", bind, port);
+    let app = build_router(fleet).layer(super::cors());
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    tracing::info!("Fleet dashboard available at http://{}", addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use std::future::Future;
+    use std::pin::Pin;
+    use tower::ServiceExt;
+
+    fn idle(
+        _: std::path::PathBuf,
+        _: tokio::sync::broadcast::Sender<crate::web::SseEvent>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), anyhow::Error>>>> {
+        Box::pin(std::future::pending())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fleet_routes() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["sales", "support"] {
+            std::fs::write(
+                dir.path().join(format!("{}.toml", name)),
+                format!(
+                    "[agent]\nmodel = \"m\"\napi_key = \"k\"\n\n[persistence]\ndb_path = \"{}\"\n",
+                    dir.path().join(format!("{}.db", name)).display()
+                ),
+            )
+            .unwrap();
+        }
+        let fleet = Arc::new(Fleet::load(dir.path(), idle).unwrap());
+        fleet
+            .get("support")
+            .unwrap()
+            .db
+            .queue_push(&crate::db::queue::QueueEntry::new(
+                "telegram", "1", "tg-1", "hi",
+            ))
+            .await
+            .unwrap();
+
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let post = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = build_router(fleet.clone()).oneshot(get("/")).await.unwrap();
+        assert_eq!(response.headers()["location"], "/agents/sales/");
+
+        // Each agent's API reads its own database
+        let response = build_router(fleet.clone())
+            .oneshot(get("/agents/support/api/queue"))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["pending"], 1);
+
+        let response = build_router(fleet.clone())
+            .oneshot(post("/api/fleet/agents/sales/start"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = build_router(fleet.clone())
+            .oneshot(post("/api/fleet/agents/sales/start"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = build_router(fleet.clone())
+            .oneshot(post("/api/fleet/agents/nobody/stop"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = build_router(fleet.clone())
+            .oneshot(get("/api/fleet/agents"))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["name"], "sales");
+        assert_eq!(json[0]["state"], "running");
+        assert_eq!(json[1]["state"], "stopped");
+
+        fleet.stop_all().await;
+    }
+}
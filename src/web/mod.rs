@@ -1,4 +1,8 @@
+pub mod analytics;
 pub mod api;
+pub mod console;
+pub mod fleet;
+pub mod push;
 pub mod sse;
 
 use crate::config::Config;
@@ -23,6 +27,16 @@ pub enum SseEvent {
     },
     #[serde(rename = "stream_end")]
     StreamEnd { session_id: String, channel: String },
+    /// A web chat message sent outside a streamed reply.
+    #[serde(rename = "chat_message")]
+    ChatMessage { session_id: String, text: String },
+    #[serde(rename = "delivery_failed")]
+    DeliveryFailed {
+        session_id: String,
+        channel: String,
+        status: String,
+        error: String,
+    },
 }
 
 /// Shared application state for all web handlers.
@@ -31,6 +45,10 @@ pub struct AppState {
     pub db: Db,
     pub config: Arc<Config>,
     pub event_tx: broadcast::Sender<SseEvent>,
+    /// Messages posted to `/api/channels/webhook`, with `[channels.webhook]`
+    pub webhook: Option<Arc<crate::channels::webhook::WebhookInbox>>,
+    /// Messages posted to `/api/chat`, with `[channels.web]`
+    pub web_chat: Option<Arc<crate::channels::web::WebChatInbox>>,
 }
 
 /// Build the axum router with all API routes and static file serving.
@@ -76,11 +94,24 @@ use axum::response::IntoResponse;
 #[folder = "web/dist/"]
 struct StaticAssets;
 
+/// Pages on any origin may read the API, but not change anything: a
+/// cross-origin POST with a JSON body or a token needs a preflight, which
+/// only allows GET. The dashboard itself is same-origin and needs no CORS.
+pub(crate) fn cors() -> tower_http::cors::CorsLayer {
+    use axum::http::Method;
+    tower_http::cors::CorsLayer::new()
+        .allow_origin(tower_http::cors::Any)
+        .allow_methods([Method::GET, Method::HEAD])
+        .allow_headers(tower_http::cors::Any)
+}
+
 /// Start the web server if enabled in config.
 pub async fn start_server(
     db: Db,
     config: Arc<Config>,
     event_tx: broadcast::Sender<SseEvent>,
+    webhook: Option<Arc<crate::channels::webhook::WebhookInbox>>,
+    web_chat: Option<Arc<crate::channels::web::WebChatInbox>>,
 ) -> Result<(), anyhow::Error> {
     let bind = &config.web.bind;
     let port = config.web.port;
@@ -90,14 +121,11 @@ pub async fn start_server(
         db,
         config: config.clone(),
         event_tx,
+        webhook,
+        web_chat,
     };
 
-    let app = build_router(state).layer(
-        tower_http::cors::CorsLayer::new()
-            .allow_origin(tower_http::cors::Any)
-            .allow_methods(tower_http::cors::Any)
-            .allow_headers(tower_http::cors::Any),
-    );
+    let app = build_router(state).layer(cors());
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     tracing::info!("Web UI available at http://{}", addr);
@@ -113,6 +141,9 @@ mod tests {
     use axum::http::{Request, StatusCode};
     use tower::ServiceExt;
 
+    /// `Authorization` header with `test_state()`'s admin token.
+    const ADMIN: &str = "Bearer admin";
+
     fn test_state() -> AppState {
         let db = Db::open_memory().unwrap();
         let config = crate::config::parse_config(
@@ -120,6 +151,9 @@ mod tests {
 [agent]
 model = "test"
 api_key = "test"
+
+[web]
+admin_token = "admin"
 "#,
         )
         .unwrap();
@@ -128,6 +162,8 @@ api_key = "test"
             db,
             config: Arc::new(config),
             event_tx,
+            webhook: None,
+            web_chat: None,
         }
     }
 
@@ -149,6 +185,125 @@ api_key = "test"
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_api_session_transcript() {
+        let state = test_state();
+        let msgs = vec![yoagent::AgentMessage::Llm(yoagent::types::Message::user(
+            "hello",
+        ))];
+        state.db.tape_save_messages("tg-1", &msgs).await.unwrap();
+
+        let response = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/sessions/tg-1/transcript?format=pdf")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/pdf");
+        assert!(response.headers()["content-disposition"]
+            .to_str()
+            .unwrap()
+            .contains("transcript-tg-1.pdf"));
+
+        let response = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/sessions/tg-1/transcript?format=docx")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/sessions/missing/transcript")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_api_annotations() {
+        let state = test_state();
+        let msgs = vec![
+            yoagent::AgentMessage::Llm(yoagent::types::Message::user("capital of Australia?")),
+            yoagent::AgentMessage::Llm(yoagent::types::Message::user("Sydney.")),
+        ];
+        state.db.tape_save_messages("tg-1", &msgs).await.unwrap();
+
+        let annotate = |idx: usize, body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sessions/tg-1/messages/{}/annotate", idx))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let response = build_router(state.clone())
+            .oneshot(annotate(
+                1,
+                serde_json::json!({"note": "It's Canberra", "tags": ["hallucination", " "]}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = build_router(state.clone())
+            .oneshot(annotate(2, serde_json::json!({"tags": ["great answer"]})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = build_router(state.clone())
+            .oneshot(annotate(0, serde_json::json!({"note": "  "})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/sessions/tg-1/annotations")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["message_idx"], 1);
+        assert_eq!(json[0]["tags"], serde_json::json!(["hallucination"]));
+
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/annotations?tag=hallucination&format=jsonl")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&body).unwrap().lines().collect();
+        assert_eq!(lines.len(), 1);
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["note"], "It's Canberra");
+        assert_eq!(line["session_id"], "tg-1");
+    }
+
     #[tokio::test]
     async fn test_api_queue() {
         let state = test_state();
@@ -167,6 +322,87 @@ api_key = "test"
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_api_queue_failures() {
+        let state = test_state();
+        let id = state
+            .db
+            .queue_push(&crate::db::queue::QueueEntry::new("tg", "u1", "tg-1", "hi"))
+            .await
+            .unwrap();
+        let failure = crate::db::queue::QueueFailure {
+            class: "worker".into(),
+            message: "Worker 'coding' failed: timeout".into(),
+            tool: Some("coding".into()),
+            turns: 1,
+            retryable: true,
+            ..Default::default()
+        };
+        state.db.queue_mark_failed(id, &failure).await.unwrap();
+        let app = build_router(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/queue/failures")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["session_id"], "tg-1");
+        assert_eq!(json[0]["failure"]["class"], "worker");
+        assert_eq!(json[0]["failure"]["tool"], "coding");
+        assert_eq!(json[0]["failure"]["retryable"], true);
+    }
+
+    #[tokio::test]
+    async fn test_api_queue_retry() {
+        let state = test_state();
+        let id = state
+            .db
+            .queue_push(&crate::db::queue::QueueEntry::new("tg", "u1", "tg-1", "hi"))
+            .await
+            .unwrap();
+        state
+            .db
+            .queue_mark_failed(id, &crate::db::queue::QueueFailure::unknown("HTTP 529"))
+            .await
+            .unwrap();
+        let post = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/queue/retry")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = build_router(state.clone())
+            .oneshot(post(serde_json::json!({"since": "soon"})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = build_router(state.clone())
+            .oneshot(post(serde_json::json!({"since": "2h", "pace": "5s"})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["requeued"], 1);
+        assert_eq!(state.db.queue_retry_count().await.unwrap(), 1);
+    }
+
     #[tokio::test]
     async fn test_api_budget() {
         let state = test_state();
@@ -185,6 +421,729 @@ api_key = "test"
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_api_budget_cost() {
+        let state = test_state();
+        let app = build_router(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/budget")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["cost_today_usd"], 0.0);
+        assert!(json["max_cost_per_day"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_api_memory_cache() {
+        let state = test_state();
+        let app = build_router(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/memory/cache")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_api_deliveries() {
+        let state = test_state();
+        state
+            .db
+            .delivery_log(
+                "telegram",
+                "tg-1",
+                "send",
+                crate::db::deliveries::DeliveryStatus::Failed,
+                3,
+                Some("timed out"),
+                "hello",
+            )
+            .await
+            .unwrap();
+        let app = build_router(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/deliveries?failed=true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["failed_24h"], 1);
+        assert_eq!(json["entries"][0]["error"], "timed out");
+    }
+
+    #[tokio::test]
+    async fn test_api_worker_runs() {
+        let state = test_state();
+        let id = state
+            .db
+            .worker_run_start("tg-1", "call-1", None, "research", "find the docs")
+            .await
+            .unwrap();
+        state
+            .db
+            .worker_run_finish(
+                id,
+                crate::db::worker_runs::RunStatus::Failed,
+                None,
+                Some("overloaded"),
+                &[],
+                0,
+            )
+            .await
+            .unwrap();
+        let app = build_router(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/workers/runs?session=tg-1&failed=true")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["worker"], "research");
+        assert_eq!(json[0]["error"], "overloaded");
+    }
+
+    #[tokio::test]
+    async fn test_api_push_subscribe() {
+        let mut state = test_state();
+        let subscription = serde_json::json!({
+            "endpoint": "https://fcm.googleapis.com/fcm/send/abc",
+            "keys": {"p256dh": "BKey", "auth": "secret"},
+            "topics": ["jobs", "budget"]
+        });
+        let post = |uri: &str, body: &serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header("authorization", ADMIN)
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // Not configured
+        let response = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/push/key")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut config = (*state.config).clone();
+        config.web.push = Some(crate::config::PushConfig {
+            vapid_public_key: "BPublic".into(),
+            vapid_private_key: "private".into(),
+            subject: "mailto:ops@example.com".into(),
+            mention_keywords: vec![],
+            budget_alert_percent: 80,
+            poll_secs: 30,
+        });
+        state.config = Arc::new(config);
+
+        let response = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/push/key")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["public_key"], "BPublic");
+
+        // Subscribing takes the admin token and a push service endpoint
+        let mut anonymous = post("/api/push/subscribe", &subscription);
+        anonymous.headers_mut().remove("authorization");
+        let response = build_router(state.clone())
+            .oneshot(anonymous)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let mut elsewhere = subscription.clone();
+        elsewhere["endpoint"] = serde_json::json!("https://internal.example/hook");
+        let response = build_router(state.clone())
+            .oneshot(post("/api/push/subscribe", &elsewhere))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(state
+            .db
+            .push_subscriptions("jobs")
+            .await
+            .unwrap()
+            .is_empty());
+
+        let response = build_router(state.clone())
+            .oneshot(post("/api/push/subscribe", &subscription))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let subs = state.db.push_subscriptions("jobs").await.unwrap();
+        assert_eq!(subs[0].auth, "secret");
+        assert!(state
+            .db
+            .push_subscriptions("mentions")
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut unknown = subscription.clone();
+        unknown["topics"] = serde_json::json!(["everything"]);
+        let response = build_router(state.clone())
+            .oneshot(post("/api/push/subscribe", &unknown))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = build_router(state.clone())
+            .oneshot(post(
+                "/api/push/unsubscribe",
+                &serde_json::json!({"endpoint": "https://fcm.googleapis.com/fcm/send/abc"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(state
+            .db
+            .push_subscriptions("jobs")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_pause_and_resume() {
+        let state = test_state();
+        let post = |uri: &str, body: Option<serde_json::Value>| {
+            let builder = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("authorization", ADMIN);
+            match body {
+                Some(body) => builder
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+                None => builder.body(Body::empty()).unwrap(),
+            }
+        };
+
+        // Not without the admin token
+        for uri in ["/api/admin/pause", "/api/admin/resume"] {
+            let mut anonymous = post(uri, None);
+            anonymous.headers_mut().remove("authorization");
+            let response = build_router(state.clone())
+                .oneshot(anonymous)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert!(state.db.pause_list().await.unwrap().is_empty());
+
+        let response = build_router(state.clone())
+            .oneshot(post("/api/admin/pause", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = build_router(state.clone())
+            .oneshot(post(
+                "/api/admin/pause",
+                Some(serde_json::json!({"session": "tg-9", "reason": "spam"})),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/admin/pause")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["scope"], "all");
+        assert_eq!(json[0]["by"], "web");
+        assert_eq!(json[1]["scope"], "tg-9");
+        assert_eq!(json[1]["reason"], "spam");
+
+        let response = build_router(state.clone())
+            .oneshot(post("/api/admin/resume", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = build_router(state.clone())
+            .oneshot(post("/api/admin/resume", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        // The session pause is still in effect
+        assert!(state.db.pause_check(Some("tg-9")).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_api_drafts() {
+        let state = test_state();
+        let id = state
+            .db
+            .draft_create("slack", "slack-C1", "Any discounts?", "10% off this week.")
+            .await
+            .unwrap();
+
+        let response = build_router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/api/drafts")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["id"], id);
+        assert_eq!(json[0]["status"], "pending");
+
+        let edit = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/drafts/{}/edit", id))
+                .header("content-type", "application/json")
+                .header("authorization", ADMIN)
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        // Not without the admin token
+        for action in ["approve", "reject"] {
+            let response = build_router(state.clone())
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/api/drafts/{}/{}", id, action))
+                        .header("authorization", "Bearer wrong")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let mut anonymous = edit(serde_json::json!({"content": "Free stuff!"}));
+        anonymous.headers_mut().remove("authorization");
+        let response = build_router(state.clone())
+            .oneshot(anonymous)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = build_router(state.clone())
+            .oneshot(edit(serde_json::json!({"content": "5% off this week."})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // Already decided
+        let response = build_router(state.clone())
+            .oneshot(edit(serde_json::json!({"content": "Never mind."})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let draft = state.db.draft_get(id).await.unwrap().unwrap();
+        assert_eq!(draft.sent_content.as_deref(), Some("5% off this week."));
+        assert_eq!(draft.decided_by.as_deref(), Some("web"));
+    }
+
+    #[tokio::test]
+    async fn test_cors_allows_only_reads() {
+        let preflight = |method: &str| {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/api/drafts/1/approve")
+                .header("origin", "https://elsewhere.example")
+                .header("access-control-request-method", method)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let app = build_router(test_state()).layer(cors());
+        let response = app.clone().oneshot(preflight("POST")).await.unwrap();
+        let methods = response.headers()["access-control-allow-methods"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(methods.contains("GET"));
+        assert!(!methods.contains("POST"));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/sessions")
+                    .header("origin", "https://elsewhere.example")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    }
+
+    #[tokio::test]
+    async fn test_api_skills() {
+        let state = test_state();
+        let toggle = |uri: &str, auth: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("authorization", auth)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for uri in ["/api/skills/web/enable", "/api/skills/web/disable"] {
+            let response = build_router(state.clone())
+                .oneshot(toggle(uri, "Bearer wrong"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert!(state.db.override_list().await.unwrap().is_empty());
+
+        let response = build_router(state.clone())
+            .oneshot(toggle("/api/skills/nonexistent/disable", ADMIN))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_api_cron() {
+        let state = test_state();
+        crate::scheduler::cron::create_job(
+            &state.db,
+            &Default::default(),
+            "standup",
+            "every weekday at 9am",
+            "Standup notes",
+            None,
+            "isolated",
+        )
+        .await
+        .unwrap();
+
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/cron")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["name"], "standup");
+        assert_eq!(json[0]["schedule"], "0 9 * * Mon-Fri");
+        assert_eq!(json[0]["schedule_text"], "every weekday at 9am");
+        assert!(json[0]["next_run"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_api_query() {
+        let mut state = test_state();
+        let query = |token: &str, sql: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/query")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::from(serde_json::json!({ "sql": sql }).to_string()))
+                .unwrap()
+        };
+
+        // Disabled without [web.console]
+        let response = build_router(state.clone())
+            .oneshot(query("s3cret", "SELECT 1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut config = (*state.config).clone();
+        config.web.console = Some(crate::config::ConsoleConfig {
+            token: "s3cret".into(),
+            max_rows: 10,
+        });
+        state.config = Arc::new(config);
+
+        let response = build_router(state.clone())
+            .oneshot(query("wrong", "SELECT 1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = build_router(state.clone())
+            .oneshot(query("s3cret", "SELECT 1 AS one"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["columns"][0], "one");
+        assert_eq!(json["rows"][0][0], 1);
+        assert_eq!(json["truncated"], false);
+
+        for sql in ["DELETE FROM queue", "SELECT * FROM no_such_table"] {
+            let response = build_router(state.clone())
+                .oneshot(query("s3cret", sql))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_analytics() {
+        let mut state = test_state();
+        let get = || {
+            Request::builder()
+                .uri("/api/analytics?days=7")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // Disabled without [web.analytics]
+        let response = build_router(state.clone()).oneshot(get()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut config = (*state.config).clone();
+        config.web.analytics = Some(crate::config::AnalyticsConfig {
+            days: 30,
+            min_senders: 2,
+            token_step: 100,
+        });
+        state.config = Arc::new(config);
+        for (channel, sender) in [("discord", "a"), ("discord", "b"), ("telegram", "c")] {
+            state
+                .db
+                .queue_push(&crate::db::queue::QueueEntry::new(
+                    channel,
+                    sender,
+                    "s1",
+                    "private words",
+                ))
+                .await
+                .unwrap();
+        }
+
+        let response = build_router(state).oneshot(get()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("private"));
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["days"].as_array().unwrap().len(), 1);
+        assert_eq!(json["days"][0]["channel"], "discord");
+        assert_eq!(json["days"][0]["messages"], 2);
+        assert_eq!(json["suppressed"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_api_webhook_channel() {
+        use crate::channels::webhook::WebhookAdapter;
+        use crate::channels::{ChannelAdapter, OutgoingMessage};
+
+        let mut state = test_state();
+        let post = |token: &str, body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/channels/webhook")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let hello = serde_json::json!({ "text": "hello", "sender_id": "ci" });
+
+        // Disabled without [channels.webhook]
+        let response = build_router(state.clone())
+            .oneshot(post("s3cret", hello.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let adapter = Arc::new(WebhookAdapter::new(
+            crate::config::WebhookChannelConfig {
+                token: "s3cret".into(),
+                callbacks: Vec::new(),
+                reply_timeout_secs: 5,
+                debounce_ms: 0,
+                citations: false,
+            },
+            Default::default(),
+        ));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        adapter.start(tx).await.unwrap();
+        state.webhook = Some(adapter.inbox());
+
+        let response = build_router(state.clone())
+            .oneshot(post("wrong", hello.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Stands in for the main loop
+        let agent = adapter.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                let reply = format!("You said {}", msg.content);
+                agent
+                    .send(OutgoingMessage {
+                        channel: msg.channel,
+                        session_id: msg.session_id,
+                        content: reply,
+                        reply_to: None,
+                    })
+                    .await
+                    .unwrap();
+            }
+        });
+        let response = build_router(state)
+            .oneshot(post("s3cret", hello))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["session_id"], "wh-ci");
+        assert_eq!(json["reply"], "You said hello");
+    }
+
+    #[tokio::test]
+    async fn test_api_chat() {
+        use crate::channels::web::WebChatAdapter;
+        use crate::channels::ChannelAdapter;
+
+        let mut state = test_state();
+        let post = |token: &str, body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/chat")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let hello = serde_json::json!({ "text": "hello" });
+
+        // Disabled without [channels.web]
+        let response = build_router(state.clone())
+            .oneshot(post("s3cret", hello.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let adapter = WebChatAdapter::new(
+            crate::config::WebChatConfig {
+                token: "s3cret".into(),
+                debounce_ms: 0,
+                citations: false,
+                latency: None,
+            },
+            state.event_tx.clone(),
+        );
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        adapter.start(tx).await.unwrap();
+        state.web_chat = Some(adapter.inbox());
+
+        let response = build_router(state.clone())
+            .oneshot(post("wrong", hello.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = build_router(state.clone())
+            .oneshot(post("s3cret", hello))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let session_id = json["session_id"].as_str().unwrap();
+        assert!(session_id.starts_with("web-"));
+        let incoming = rx.recv().await.unwrap();
+        assert_eq!(incoming.session_id, session_id);
+        assert_eq!(incoming.content, "hello");
+
+        let response = build_router(state)
+            .oneshot(post(
+                "s3cret",
+                serde_json::json!({ "text": "hi", "session_id": "tg-1" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_api_audit() {
         let state = test_state();
@@ -202,4 +1161,36 @@ api_key = "test"
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn test_api_audit_daily() {
+        let state = test_state();
+        state
+            .db
+            .audit_log(None, "tool_call", Some("shell"), None, 0)
+            .await
+            .unwrap();
+        state
+            .db
+            .audit_rollup(crate::db::now_ms() + 1)
+            .await
+            .unwrap();
+
+        let response = build_router(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/audit/daily?days=7")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["tool_name"], "shell");
+        assert_eq!(json[0]["events"], 1);
+    }
 }
@@ -0,0 +1,397 @@
+//! Web Push notifications for the dashboard.
+//!
+//! Browsers subscribe from the dashboard (`/api/push/subscribe`, with the
+//! `[web] admin_token`) with the topics they want. Only https endpoints of
+//! the browsers' push services are accepted, so the notifier never posts to
+//! an arbitrary URL. [`PushNotifier::run`] polls the database every
+//! `poll_secs` for new events and sends each one, encrypted and signed with
+//! the `[web.push]` VAPID keys, to the subscriptions for its topic. Push
+//! services answer 404 or 410 for subscriptions that are gone; those are
+//! removed.
+
+use crate::config::PushConfig;
+use crate::db::{now_ms, Db, DbError};
+use serde::Serialize;
+use std::time::Duration;
+use web_push::{
+    ContentEncoding, SubscriptionInfo, VapidSignatureBuilder, WebPushMessageBuilder,
+    URL_SAFE_NO_PAD,
+};
+
+/// How long push services keep an undelivered notification.
+const TTL_SECS: u32 = 24 * 60 * 60;
+
+/// Longest message excerpt in a notification body.
+const EXCERPT_CHARS: usize = 120;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PushError {
+    #[error("Database error: {0}")]
+    Db(#[from] DbError),
+    #[error("Web Push error: {0}")]
+    WebPush(#[from] web_push::WebPushError),
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Push service returned {0}")]
+    Status(u16),
+    #[error("Subscription expired")]
+    Gone,
+}
+
+/// Hosts of the push services browsers subscribe with, and their subdomains:
+/// Chrome/Edge (FCM), Firefox, Safari and legacy Edge (WNS).
+const PUSH_SERVICES: &[&str] = &[
+    "fcm.googleapis.com",
+    "android.googleapis.com",
+    "push.services.mozilla.com",
+    "push.apple.com",
+    "notify.windows.com",
+];
+
+/// Whether `endpoint` is an https URL on a known push service.
+pub fn known_endpoint(endpoint: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(endpoint) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    url.scheme() == "https"
+        && PUSH_SERVICES.iter().any(|service| {
+            host == *service
+                || host
+                    .strip_suffix(service)
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+}
+
+/// What a subscription can ask to be notified about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topic {
+    /// Incoming messages containing a `mention_keywords` entry.
+    Mentions,
+    /// Daily token usage crossing `budget_alert_percent` or the limit.
+    Budget,
+    /// Tool calls waiting for an operator's approval.
+    Approvals,
+    /// Failed cron runs and background tasks.
+    Jobs,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 4] = [
+        Topic::Mentions,
+        Topic::Budget,
+        Topic::Approvals,
+        Topic::Jobs,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Topic::Mentions => "mentions",
+            Topic::Budget => "budget",
+            Topic::Approvals => "approvals",
+            Topic::Jobs => "jobs",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Topic> {
+        Topic::ALL.into_iter().find(|t| t.as_str() == s)
+    }
+}
+
+/// Payload shown by the dashboard's service worker.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Notification {
+    pub topic: Topic,
+    pub title: String,
+    pub body: String,
+    /// Dashboard path opened when the notification is clicked.
+    pub url: String,
+}
+
+/// Sends notifications to subscribed browsers.
+#[derive(Clone)]
+pub struct PushNotifier {
+    db: Db,
+    config: PushConfig,
+    /// `max_tokens_per_day`, for budget alerts.
+    budget_limit: Option<u64>,
+    client: reqwest::Client,
+}
+
+impl PushNotifier {
+    pub fn new(db: Db, config: PushConfig, budget_limit: Option<u64>) -> Self {
+        Self {
+            db,
+            config,
+            budget_limit,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Poll for events and send them until the process exits. Events from
+    /// before the notifier started are not sent.
+    pub async fn run(self) {
+        let mut since = now_ms();
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.poll_secs.max(1)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let until = now_ms();
+            match self.poll(since).await {
+                Ok(notifications) => {
+                    for n in &notifications {
+                        self.notify(n).await;
+                    }
+                    since = until;
+                }
+                Err(e) => tracing::error!("Push notifier poll failed: {}", e),
+            }
+        }
+    }
+
+    /// Send `notification` to every subscription for its topic.
+    /// Returns how many accepted it.
+    pub async fn notify(&self, notification: &Notification) -> usize {
+        let subscriptions = match self
+            .db
+            .push_subscriptions(notification.topic.as_str())
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!("Failed to load push subscriptions: {}", e);
+                return 0;
+            }
+        };
+        let payload = serde_json::to_vec(notification).unwrap_or_default();
+        let mut sent = 0;
+        for sub in subscriptions {
+            let info = SubscriptionInfo::new(&sub.endpoint, &sub.p256dh, &sub.auth);
+            match self.send(&info, &payload).await {
+                Ok(()) => sent += 1,
+                Err(PushError::Gone) => {
+                    tracing::info!("Removing expired push subscription {}", sub.endpoint);
+                    let _ = self.db.push_unsubscribe(&sub.endpoint).await;
+                }
+                Err(e) => tracing::warn!("Push to {} failed: {}", sub.endpoint, e),
+            }
+        }
+        sent
+    }
+
+    async fn send(&self, info: &SubscriptionInfo, payload: &[u8]) -> Result<(), PushError> {
+        let mut signature = VapidSignatureBuilder::from_base64(
+            &self.config.vapid_private_key,
+            URL_SAFE_NO_PAD,
+            info,
+        )?;
+        signature.add_claim("sub", self.config.subject.as_str());
+
+        let mut builder = WebPushMessageBuilder::new(info);
+        builder.set_payload(ContentEncoding::Aes128Gcm, payload);
+        builder.set_vapid_signature(signature.build()?);
+        builder.set_ttl(TTL_SECS);
+        let message = builder.build()?;
+
+        let mut request = self
+            .client
+            .post(message.endpoint.to_string())
+            .header("TTL", message.ttl.to_string());
+        if let Some(payload) = message.payload {
+            request = request
+                .header("Content-Encoding", payload.content_encoding.to_str())
+                .header("Content-Type", "application/octet-stream");
+            for (name, value) in payload.crypto_headers {
+                request = request.header(name, value);
+            }
+            request = request.body(payload.content);
+        }
+
+        let status = request.send().await?.status().as_u16();
+        match status {
+            200..=299 => Ok(()),
+            404 | 410 => Err(PushError::Gone),
+            _ => Err(PushError::Status(status)),
+        }
+    }
+
+    /// Notifications for events after `since_ms`.
+    pub async fn poll(&self, since_ms: u64) -> Result<Vec<Notification>, DbError> {
+        let mut notifications = Vec::new();
+
+        if !self.config.mention_keywords.is_empty() {
+            let keywords: Vec<String> = self
+                .config
+                .mention_keywords
+                .iter()
+                .map(|k| k.to_lowercase())
+                .collect();
+            for msg in self.db.push_recent_messages(since_ms).await? {
+                let content = msg.content.to_lowercase();
+                if keywords.iter().any(|k| content.contains(k)) {
+                    notifications.push(Notification {
+                        topic: Topic::Mentions,
+                        title: format!("{} mentioned you", msg.sender),
+                        body: format!("{}: {}", msg.session_id, excerpt(&msg.content)),
+                        url: "/".to_string(),
+                    });
+                }
+            }
+        }
+
+        for job in self.db.push_failed_jobs(since_ms).await? {
+            notifications.push(Notification {
+                topic: Topic::Jobs,
+                title: format!("Job failed: {}", job.name),
+                body: excerpt(&job.error),
+                url: "/".to_string(),
+            });
+        }
+
+        if let Some(n) = self.budget_alert().await? {
+            notifications.push(n);
+        }
+
+        Ok(notifications)
+    }
+
+    /// A budget alert, the first time today's usage crosses a threshold.
+    async fn budget_alert(&self) -> Result<Option<Notification>, DbError> {
+        let Some(limit) = self.budget_limit.filter(|l| *l > 0) else {
+            return Ok(None);
+        };
+        let used = self.db.audit_token_usage_today().await?;
+        let percent = used.saturating_mul(100) / limit;
+        let level = if percent >= 100 {
+            100
+        } else if percent >= self.config.budget_alert_percent {
+            self.config.budget_alert_percent
+        } else {
+            return Ok(None);
+        };
+
+        let day = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        if self.db.push_budget_alerted(&day).await?.unwrap_or(0) >= level {
+            return Ok(None);
+        }
+        self.db.push_set_budget_alerted(&day, level).await?;
+
+        let title = if level >= 100 {
+            "Daily token budget used up".to_string()
+        } else {
+            format!("{}% of the daily token budget used", percent)
+        };
+        Ok(Some(Notification {
+            topic: Topic::Budget,
+            title,
+            body: format!("{} of {} tokens used today", used, limit),
+            url: "/".to_string(),
+        }))
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(EXCERPT_CHARS) {
+        Some((i, _)) => format!("{}...", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::queue::QueueEntry;
+
+    fn notifier(db: &Db, budget_limit: Option<u64>) -> PushNotifier {
+        let config = PushConfig {
+            vapid_public_key: "pub".into(),
+            vapid_private_key: "priv".into(),
+            subject: "mailto:ops@example.com".into(),
+            mention_keywords: vec!["@Yoclaw".into()],
+            budget_alert_percent: 80,
+            poll_secs: 30,
+        };
+        PushNotifier::new(db.clone(), config, budget_limit)
+    }
+
+    #[tokio::test]
+    async fn test_poll_collects_events() {
+        let db = Db::open_memory().unwrap();
+        let notifier = notifier(&db, Some(1_000));
+        let since = now_ms() - 1;
+
+        db.queue_push(&QueueEntry::new(
+            "telegram",
+            "7",
+            "tg-7",
+            "hey @yoclaw, the deploy broke",
+        ))
+        .await
+        .unwrap();
+        db.queue_push(&QueueEntry::new("telegram", "7", "tg-7", "never mind"))
+            .await
+            .unwrap();
+        let task = db
+            .background_enqueue("tg-7", "summarize logs")
+            .await
+            .unwrap();
+        db.background_finish(task, false, "Timed out after 600s")
+            .await
+            .unwrap();
+        db.audit_log(Some("tg-7"), "tokens", None, None, 850)
+            .await
+            .unwrap();
+
+        let notifications = notifier.poll(since).await.unwrap();
+        let topics: Vec<Topic> = notifications.iter().map(|n| n.topic).collect();
+        assert_eq!(topics, vec![Topic::Mentions, Topic::Jobs, Topic::Budget]);
+        assert!(notifications[0].body.contains("the deploy broke"));
+        assert_eq!(
+            notifications[1].title,
+            format!("Job failed: background #{}", task)
+        );
+        assert_eq!(notifications[2].title, "85% of the daily token budget used");
+
+        // Each budget threshold is announced once a day
+        assert!(notifier.poll(now_ms()).await.unwrap().is_empty());
+        db.audit_log(Some("tg-7"), "tokens", None, None, 200)
+            .await
+            .unwrap();
+        let notifications = notifier.poll(now_ms()).await.unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].title, "Daily token budget used up");
+    }
+
+    #[test]
+    fn test_topic_names() {
+        for topic in Topic::ALL {
+            assert_eq!(Topic::parse(topic.as_str()), Some(topic));
+        }
+        assert_eq!(Topic::parse("everything"), None);
+        assert_eq!(
+            serde_json::to_value(Topic::Approvals).unwrap(),
+            serde_json::json!("approvals")
+        );
+    }
+
+    #[test]
+    fn test_known_endpoint() {
+        assert!(known_endpoint("https://fcm.googleapis.com/fcm/send/abc"));
+        assert!(known_endpoint(
+            "https://updates.push.services.mozilla.com/wpush/v2/abc"
+        ));
+        assert!(known_endpoint("https://web.push.apple.com/abc"));
+        assert!(!known_endpoint("http://fcm.googleapis.com/fcm/send/abc"));
+        assert!(!known_endpoint("https://evilpush.apple.com/abc"));
+        assert!(!known_endpoint("https://fcm.googleapis.com.example/abc"));
+        assert!(!known_endpoint("https://127.0.0.1/abc"));
+        assert!(!known_endpoint("not a url"));
+    }
+}
@@ -0,0 +1,258 @@
+//! Outbound webhooks.
+//!
+//! Cron jobs can deliver results to a `[webhooks.<name>]` target by setting
+//! `target = "webhook:<name>"`. The result is POSTed as JSON. When the target
+//! has a secret, the request is signed so the receiver can check that it came
+//! from yoclaw and is recent:
+//!
+//! - `X-Yoclaw-Timestamp`: unix seconds at signing time
+//! - `X-Yoclaw-Signature`: `v1=` + hex HMAC-SHA256 of `"{timestamp}.{body}"`
+//! - `X-Yoclaw-Delivery`: unique ID per request, for deduplication
+//!
+//! Receivers should reject timestamps outside a replay window (5 minutes by
+//! default). [`verify`] does both checks.
+//!
+//! Targets with `events` also receive lifecycle events, signed the same way;
+//! see [`crate::events`].
+
+use crate::channels::OutgoingMessage;
+use crate::config::WebhookTargetConfig;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const TIMESTAMP_HEADER: &str = "X-Yoclaw-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Yoclaw-Signature";
+pub const DELIVERY_HEADER: &str = "X-Yoclaw-Delivery";
+
+/// Session ID prefix that addresses a webhook target, e.g. "webhook:ci".
+pub const TARGET_PREFIX: &str = "webhook:";
+
+/// Default replay window for [`verify`], in seconds.
+pub const DEFAULT_REPLAY_WINDOW_SECS: u64 = 300;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Unknown webhook target '{0}'")]
+    UnknownTarget(String),
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Webhook returned {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("Malformed signature headers")]
+    Malformed,
+    #[error("Signature mismatch")]
+    BadSignature,
+    #[error("Timestamp outside the replay window")]
+    Expired,
+}
+
+/// Signature header value for `body` signed at `timestamp`.
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("v1={}", hex)
+}
+
+/// Check a received request's timestamp and signature headers against its body.
+/// The timestamp must be within `window_secs` of `now_secs` in either direction.
+pub fn verify(
+    secret: &str,
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+    now_secs: u64,
+    window_secs: u64,
+) -> Result<(), WebhookError> {
+    let ts: u64 = timestamp
+        .trim()
+        .parse()
+        .map_err(|_| WebhookError::Malformed)?;
+    let signature = signature.trim();
+    if !signature.starts_with("v1=") {
+        return Err(WebhookError::Malformed);
+    }
+    if !constant_time_eq(sign(secret, ts, body).as_bytes(), signature.as_bytes()) {
+        return Err(WebhookError::BadSignature);
+    }
+    if now_secs.abs_diff(ts) > window_secs {
+        return Err(WebhookError::Expired);
+    }
+    Ok(())
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Delivers messages to the configured webhook targets.
+pub struct WebhookSender {
+    targets: HashMap<String, WebhookTargetConfig>,
+    http: reqwest::Client,
+}
+
+impl WebhookSender {
+    pub fn new(targets: HashMap<String, WebhookTargetConfig>) -> Self {
+        Self {
+            targets,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// POST `msg` to the target named by its session ID ("webhook:<name>").
+    pub async fn send(&self, msg: &OutgoingMessage) -> Result<(), WebhookError> {
+        let name = msg
+            .session_id
+            .strip_prefix(TARGET_PREFIX)
+            .unwrap_or(&msg.session_id);
+        self.post(name, None, &msg.content).await
+    }
+
+    /// POST a reply in `session_id` to target `name`. Used for the callbacks
+    /// of the webhook channel; the body also carries `session_id`.
+    pub async fn send_reply(
+        &self,
+        name: &str,
+        session_id: &str,
+        content: &str,
+    ) -> Result<(), WebhookError> {
+        self.post(name, Some(session_id), content).await
+    }
+
+    /// POST lifecycle `event` with its `data` to target `name`.
+    pub async fn send_event(
+        &self,
+        name: &str,
+        event: &str,
+        data: &serde_json::Value,
+    ) -> Result<(), WebhookError> {
+        self.post_json(name, serde_json::json!({ "event": event, "data": data }))
+            .await
+    }
+
+    async fn post(
+        &self,
+        name: &str,
+        session_id: Option<&str>,
+        content: &str,
+    ) -> Result<(), WebhookError> {
+        let mut body = serde_json::json!({ "content": content });
+        if let Some(session_id) = session_id {
+            body["session_id"] = session_id.into();
+        }
+        self.post_json(name, body).await
+    }
+
+    /// POST `body` with the delivery ID, target and timestamp added, signed.
+    async fn post_json(&self, name: &str, mut body: serde_json::Value) -> Result<(), WebhookError> {
+        let target = self
+            .targets
+            .get(name)
+            .ok_or_else(|| WebhookError::UnknownTarget(name.to_string()))?;
+
+        let timestamp = chrono::Utc::now().timestamp() as u64;
+        let delivery_id = uuid::Uuid::new_v4().to_string();
+        body["id"] = delivery_id.clone().into();
+        body["target"] = name.into();
+        body["timestamp"] = timestamp.into();
+        let body = body.to_string();
+
+        let mut request = self
+            .http
+            .post(&target.url)
+            .timeout(Duration::from_secs(target.timeout_secs))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(DELIVERY_HEADER, &delivery_id);
+        if let Some(ref secret) = target.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, timestamp, body.as_bytes()));
+        }
+
+        let response = request.body(body).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(WebhookError::Status {
+                status: status.as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        tracing::debug!("Webhook '{}' delivered ({})", name, delivery_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = br#"{"content":"hi"}"#;
+
+    #[test]
+    fn test_sign_known_vector() {
+        assert_eq!(
+            sign("secret", 1_700_000_000, BODY),
+            "v1=786cd668a72d88eb84d182ac85e334a27cf498b74f770467b2e0583cb80c4bcf"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let ts = 1_700_000_000;
+        let sig = sign("secret", ts, BODY);
+        let window = DEFAULT_REPLAY_WINDOW_SECS;
+
+        assert!(verify("secret", "1700000000", &sig, BODY, ts + 10, window).is_ok());
+        assert!(matches!(
+            verify("other", "1700000000", &sig, BODY, ts, window),
+            Err(WebhookError::BadSignature)
+        ));
+        assert!(matches!(
+            verify("secret", "1700000000", &sig, b"{}", ts, window),
+            Err(WebhookError::BadSignature)
+        ));
+        // Replaying the same signature under a new timestamp doesn't verify
+        assert!(matches!(
+            verify("secret", "1700000900", &sig, BODY, ts + 900, window),
+            Err(WebhookError::BadSignature)
+        ));
+        assert!(matches!(
+            verify("secret", "1700000000", &sig, BODY, ts + window + 1, window),
+            Err(WebhookError::Expired)
+        ));
+        assert!(matches!(
+            verify("secret", "yesterday", &sig, BODY, ts, window),
+            Err(WebhookError::Malformed)
+        ));
+        assert!(matches!(
+            verify("secret", "1700000000", "abc", BODY, ts, window),
+            Err(WebhookError::Malformed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_unknown_target() {
+        let sender = WebhookSender::new(HashMap::new());
+        let msg = OutgoingMessage {
+            channel: "webhook".into(),
+            session_id: "webhook:missing".into(),
+            content: "hi".into(),
+            reply_to: None,
+        };
+        assert!(matches!(
+            sender.send(&msg).await,
+            Err(WebhookError::UnknownTarget(name)) if name == "missing"
+        ));
+    }
+}
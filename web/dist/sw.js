@@ -0,0 +1,24 @@
+// Shows Web Push notifications from yoclaw while the dashboard is closed.
+'use strict';
+
+self.addEventListener('push', (event) => {
+  let data = {};
+  try { data = event.data ? event.data.json() : {}; } catch (e) { data = { body: event.data.text() }; }
+  event.waitUntil(self.registration.showNotification(data.title || 'yoclaw', {
+    body: data.body || '',
+    tag: data.topic,
+    data: { url: data.url || '/' },
+  }));
+});
+
+self.addEventListener('notificationclick', (event) => {
+  event.notification.close();
+  const url = event.notification.data && event.notification.data.url || '/';
+  event.waitUntil((async () => {
+    const windows = await self.clients.matchAll({ type: 'window', includeUncontrolled: true });
+    for (const w of windows) {
+      if ('focus' in w) return w.focus();
+    }
+    return self.clients.openWindow(url);
+  })());
+});